@@ -1,12 +1,20 @@
-use std::{str::FromStr, sync::Arc};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
 
 use anyhow::bail;
+use dashmap::DashMap;
 use itertools::Itertools;
-use solana_account_decoder::{UiAccount, UiDataSliceConfig};
+use solana_account_decoder::{
+    parse_account_data::AccountAdditionalData, UiAccount, UiAccountEncoding, UiDataSliceConfig,
+};
+use solana_lite_rpc_cluster_endpoints::rpc_pool::RpcPool;
 use solana_lite_rpc_core::{
     commitment_utils::Commitment,
     structures::{
-        account_data::{AccountData, AccountNotificationMessage, AccountStream},
+        account_data::{Account, AccountData, AccountNotificationMessage, AccountStream},
         account_filter::AccountFilters,
     },
     types::BlockStream,
@@ -15,17 +23,27 @@ use solana_lite_rpc_core::{
 use solana_rpc_client::nonblocking::rpc_client::RpcClient;
 use solana_rpc_client_api::{
     config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
     response::RpcKeyedAccount,
 };
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, slot_history::Slot};
 use tokio::sync::broadcast::Sender;
 
-use crate::account_store_interface::AccountStorageInterface;
+use crate::account_store_interface::{AccountStorageInterface, AccountStoreStats};
+use crate::token_accounts::{
+    filters_cover_token_accounts, largest_token_accounts, mint_decimals, token_account_mint,
+};
 
 #[derive(Clone)]
 pub struct AccountService {
     account_store: Arc<dyn AccountStorageInterface>,
     pub account_notification_sender: Sender<AccountNotificationMessage>,
+    // filters of the accounts streamed into the store
+    filters: Arc<RwLock<AccountFilters>>,
+    // fetches the decimals of the mints which are not in the store
+    rpc_pool: Option<RpcPool>,
+    // the decimals of a mint never change
+    fetched_mint_decimals: Arc<DashMap<Pubkey, u8>>,
 }
 
 impl AccountService {
@@ -34,9 +52,37 @@ impl AccountService {
         Self {
             account_store,
             account_notification_sender,
+            filters: Arc::new(RwLock::new(vec![])),
+            rpc_pool: None,
+            fetched_mint_decimals: Arc::new(DashMap::new()),
         }
     }
 
+    /// the decimals of mints which are not in the store are fetched from the rpc nodes
+    pub fn with_rpc_pool(mut self, rpc_pool: RpcPool) -> Self {
+        self.rpc_pool = Some(rpc_pool);
+        self
+    }
+
+    /// the filters of the accounts streamed into the store, to tell which queries it answers completely
+    pub fn set_filters(&self, filters: AccountFilters) {
+        *self.filters.write().unwrap() = filters;
+    }
+
+    pub async fn stats(&self) -> AccountStoreStats {
+        self.account_store.stats().await
+    }
+
+    /// removes the accounts which are not matched by any of the filters anymore,
+    /// returns the number of removed accounts
+    pub async fn retain_filtered_accounts(&self, filters: &AccountFilters) -> usize {
+        self.account_store
+            .retain_accounts(&|account_data| {
+                filters.iter().any(|filter| filter.allows(account_data))
+            })
+            .await
+    }
+
     pub async fn populate_from_rpc(
         &self,
         rpc_client: Arc<RpcClient>,
@@ -113,12 +159,12 @@ impl AccountService {
                         }
                     }
                 }
-                for (index, account) in fetch_accounts.iter().enumerate() {
+                for (index, account) in fetch_accounts.into_iter().enumerate() {
                     if let Some(account) = account {
                         self.account_store
                             .initilize_account(AccountData {
                                 pubkey: accounts[index],
-                                account: account.clone(),
+                                account: account.into(),
                                 updated_slot,
                             })
                             .await;
@@ -198,9 +244,12 @@ impl AccountService {
         vec![processed_task, block_processing_task]
     }
 
+    /// `additional_data` carries the decimals of the mint for jsonParsed token accounts, see
+    /// [`Self::additional_data`]
     pub fn convert_account_data_to_ui_account(
         account_data: &AccountData,
         config: Option<RpcAccountInfoConfig>,
+        additional_data: Option<AccountAdditionalData>,
     ) -> UiAccount {
         let encoding = config
             .as_ref()
@@ -212,11 +261,65 @@ impl AccountService {
             &account_data.pubkey,
             &account_data.account,
             encoding,
-            None,
+            additional_data,
             data_slice,
         )
     }
 
+    /// the data jsonParsed needs next to the account itself: the decimals of the mint of a token
+    /// account, looked up in the account store at the commitment of the config or fetched from the
+    /// rpc nodes; none for other encodings and accounts, which are parsed without or fall back to base64
+    pub async fn additional_data(
+        &self,
+        account_data: &AccountData,
+        config: Option<&RpcAccountInfoConfig>,
+    ) -> Option<AccountAdditionalData> {
+        let config = config?;
+        if config.encoding != Some(UiAccountEncoding::JsonParsed) {
+            return None;
+        }
+        let mint = token_account_mint(&account_data.account)?;
+        let commitment = Commitment::from(config.commitment.unwrap_or_default());
+        let decimals = self.mint_decimals(mint, commitment).await?;
+        Some(AccountAdditionalData {
+            spl_token_decimals: Some(decimals),
+        })
+    }
+
+    async fn mint_decimals(&self, mint: Pubkey, commitment: Commitment) -> Option<u8> {
+        if let Some(mint_account) = self
+            .account_store
+            .get_account(mint, commitment)
+            .await
+            .filter(|mint_account| !mint_account.is_closed())
+        {
+            return mint_decimals(&mint_account.account);
+        }
+        if let Some(decimals) = self.fetched_mint_decimals.get(&mint) {
+            return Some(*decimals);
+        }
+
+        let rpc_pool = self.rpc_pool.as_ref()?;
+        let commitment_config = commitment.into_commiment_config();
+        let mint_account = match rpc_pool
+            .call(|rpc_client| async move {
+                rpc_client
+                    .get_account_with_commitment(&mint, commitment_config)
+                    .await
+            })
+            .await
+        {
+            Ok(response) => response.value?,
+            Err(err) => {
+                log::debug!("Fetching the mint {mint} failed: {err:#}");
+                return None;
+            }
+        };
+        let decimals = mint_decimals(&Account::from(mint_account))?;
+        self.fetched_mint_decimals.insert(mint, decimals);
+        Some(decimals)
+    }
+
     pub async fn get_account(
         &self,
         account: Pubkey,
@@ -230,8 +333,15 @@ impl AccountService {
         let commitment = Commitment::from(commitment);
 
         if let Some(account_data) = self.account_store.get_account(account, commitment).await {
-            let ui_account =
-                Self::convert_account_data_to_ui_account(&account_data, config.clone());
+            if account_data.is_closed() {
+                return Ok((account_data.updated_slot, None));
+            }
+            let additional_data = self.additional_data(&account_data, config.as_ref()).await;
+            let ui_account = Self::convert_account_data_to_ui_account(
+                &account_data,
+                config.clone(),
+                additional_data,
+            );
 
             // if minimum context slot is not satisfied return Null
             let minimum_context_slot = config
@@ -289,22 +399,40 @@ impl AccountService {
                 .max()
                 .unwrap_or_default();
             let acc_config = config.map(|c| c.account_config);
-            let rpc_keyed_accounts = program_accounts
+            let json_parsed = acc_config
+                .as_ref()
+                .is_some_and(|c| c.encoding == Some(UiAccountEncoding::JsonParsed));
+            // token accounts of the same mint share the decimals
+            let mut decimals_by_mint: HashMap<Pubkey, Option<u8>> = HashMap::new();
+            let mut rpc_keyed_accounts = vec![];
+            for account_data in program_accounts
                 .iter()
-                .filter_map(|account_data| {
-                    if account_data.updated_slot >= min_context_slot {
-                        Some(RpcKeyedAccount {
-                            pubkey: account_data.pubkey.to_string(),
-                            account: Self::convert_account_data_to_ui_account(
-                                account_data,
-                                acc_config.clone(),
-                            ),
-                        })
-                    } else {
-                        None
-                    }
-                })
-                .collect_vec();
+                .filter(|account_data| account_data.updated_slot >= min_context_slot)
+            {
+                let mint = token_account_mint(&account_data.account).filter(|_| json_parsed);
+                let decimals = match mint {
+                    Some(mint) => match decimals_by_mint.get(&mint) {
+                        Some(decimals) => *decimals,
+                        None => {
+                            let decimals = self.mint_decimals(mint, commitment).await;
+                            decimals_by_mint.insert(mint, decimals);
+                            decimals
+                        }
+                    },
+                    None => None,
+                };
+                let additional_data = decimals.map(|decimals| AccountAdditionalData {
+                    spl_token_decimals: Some(decimals),
+                });
+                rpc_keyed_accounts.push(RpcKeyedAccount {
+                    pubkey: account_data.pubkey.to_string(),
+                    account: Self::convert_account_data_to_ui_account(
+                        account_data,
+                        acc_config.clone(),
+                        additional_data,
+                    ),
+                });
+            }
             Ok((slot, rpc_keyed_accounts))
         } else {
             bail!(
@@ -313,4 +441,201 @@ impl AccountService {
             )
         }
     }
+
+    /// the decimals of the mint and its token accounts with the largest balances, none if the mint
+    /// is not in the account store or the filters do not cover all of its token accounts
+    pub async fn get_token_largest_accounts(
+        &self,
+        mint: Pubkey,
+        commitment: CommitmentConfig,
+        limit: usize,
+    ) -> Option<(Slot, u8, Vec<(Pubkey, u64)>)> {
+        let commitment = Commitment::from(commitment);
+        let mint_account = self
+            .account_store
+            .get_account(mint, commitment)
+            .await
+            .filter(|mint_account| !mint_account.is_closed())?;
+        let decimals = mint_decimals(&mint_account.account)?;
+        if !filters_cover_token_accounts(
+            &self.filters.read().unwrap(),
+            &mint_account.account.owner,
+            &mint,
+        ) {
+            return None;
+        }
+
+        let mint_filter = RpcFilterType::Memcmp(Memcmp::new(
+            0,
+            MemcmpEncodedBytes::Bytes(mint.to_bytes().to_vec()),
+        ));
+        let token_accounts = self
+            .account_store
+            .get_program_accounts(
+                mint_account.account.owner,
+                Some(vec![mint_filter]),
+                commitment,
+            )
+            .await?;
+        let slot = token_accounts
+            .iter()
+            .map(|account_data| account_data.updated_slot)
+            .chain([mint_account.updated_slot])
+            .max()
+            .unwrap_or_default();
+        Some((
+            slot,
+            decimals,
+            largest_token_accounts(&token_accounts, &mint, limit),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_account_decoder::{parse_token::spl_token_ids, UiAccountData};
+    use solana_lite_rpc_core::structures::account_filter::AccountFilter;
+
+    use super::*;
+    use crate::inmemory_account_store::InmemoryAccountStore;
+
+    const MINT_DECIMALS: u8 = 6;
+
+    fn account_data(pubkey: Pubkey, data: Vec<u8>) -> AccountData {
+        AccountData {
+            pubkey,
+            account: Account {
+                lamports: 2_039_280,
+                data: data.into(),
+                owner: spl_token_ids()[0],
+                executable: false,
+                rent_epoch: 0,
+            },
+            updated_slot: 10,
+        }
+    }
+
+    // an initialized mint without authorities
+    fn mint(pubkey: Pubkey) -> AccountData {
+        let mut data = vec![0; 82];
+        data[36..44].copy_from_slice(&1_000_000u64.to_le_bytes());
+        data[44] = MINT_DECIMALS;
+        data[45] = 1;
+        account_data(pubkey, data)
+    }
+
+    // an initialized token account
+    fn token_account(mint: &Pubkey, amount: u64) -> AccountData {
+        let mut data = vec![0; 165];
+        data[0..32].copy_from_slice(mint.as_ref());
+        data[32..64].copy_from_slice(Pubkey::new_unique().as_ref());
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data[108] = 1;
+        account_data(Pubkey::new_unique(), data)
+    }
+
+    fn json_parsed() -> RpcAccountInfoConfig {
+        RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::JsonParsed),
+            ..Default::default()
+        }
+    }
+
+    fn token_amount(ui_account: &UiAccount) -> Option<serde_json::Value> {
+        match &ui_account.data {
+            UiAccountData::Json(parsed) => parsed.parsed["info"].get("tokenAmount").cloned(),
+            _ => None,
+        }
+    }
+
+    async fn account_service(accounts: &[AccountData]) -> AccountService {
+        let store = Arc::new(InmemoryAccountStore::new());
+        for account in accounts {
+            store.initilize_account(account.clone()).await;
+        }
+        let account_service = AccountService::new(store);
+        account_service.set_filters(vec![AccountFilter {
+            accounts: vec![],
+            program_id: Some(spl_token_ids()[0].to_string()),
+            filters: None,
+        }]);
+        account_service
+    }
+
+    #[tokio::test]
+    async fn test_json_parsed_token_accounts_carry_the_decimals_of_the_mint() {
+        let mint_pubkey = Pubkey::new_unique();
+        let token_account = token_account(&mint_pubkey, 1_500_000);
+        let account_service = account_service(&[mint(mint_pubkey), token_account.clone()]).await;
+
+        let (_, ui_account) = account_service
+            .get_account(token_account.pubkey, Some(json_parsed()))
+            .await
+            .unwrap();
+        let token_amount = token_amount(&ui_account.unwrap()).unwrap();
+        assert_eq!(token_amount["decimals"], MINT_DECIMALS);
+        assert_eq!(token_amount["amount"], "1500000");
+        assert_eq!(token_amount["uiAmountString"], "1.5");
+
+        let (_, program_accounts) = account_service
+            .get_program_accounts(
+                spl_token_ids()[0],
+                Some(RpcProgramAccountsConfig {
+                    account_config: json_parsed(),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap();
+        let parsed: Vec<_> = program_accounts
+            .iter()
+            .filter_map(|keyed_account| token_amount(&keyed_account.account))
+            .collect();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0]["decimals"], MINT_DECIMALS);
+    }
+
+    #[tokio::test]
+    async fn test_token_accounts_of_an_unknown_mint_without_rpc_fall_back_to_base64() {
+        let token_account = token_account(&Pubkey::new_unique(), 1);
+        let account_service = account_service(&[token_account.clone()]).await;
+
+        let (_, ui_account) = account_service
+            .get_account(token_account.pubkey, Some(json_parsed()))
+            .await
+            .unwrap();
+        assert!(token_amount(&ui_account.unwrap()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_largest_accounts_of_a_covered_mint() {
+        let mint_pubkey = Pubkey::new_unique();
+        let accounts = [
+            mint(mint_pubkey),
+            token_account(&mint_pubkey, 5),
+            token_account(&mint_pubkey, 50),
+        ];
+        let account_service = account_service(&accounts).await;
+
+        let (slot, decimals, largest) = account_service
+            .get_token_largest_accounts(mint_pubkey, CommitmentConfig::processed(), 20)
+            .await
+            .unwrap();
+        assert_eq!((slot, decimals), (10, MINT_DECIMALS));
+        assert_eq!(
+            largest,
+            vec![(accounts[2].pubkey, 50), (accounts[1].pubkey, 5)]
+        );
+
+        // only some token accounts of the mint are subscribed
+        account_service.set_filters(vec![AccountFilter {
+            accounts: vec![accounts[1].pubkey.to_string()],
+            program_id: None,
+            filters: None,
+        }]);
+        assert!(account_service
+            .get_token_largest_accounts(mint_pubkey, CommitmentConfig::processed(), 20)
+            .await
+            .is_none());
+    }
 }
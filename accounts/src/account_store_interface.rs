@@ -5,6 +5,14 @@ use solana_rpc_client_api::filter::RpcFilterType;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::slot_history::Slot;
 
+/// size of an account store, for diagnostics
+#[derive(Clone, Debug, Default)]
+pub struct AccountStoreStats {
+    pub accounts: usize,
+    pub owners: usize,
+    pub confirmed_slots: usize,
+}
+
 #[async_trait]
 pub trait AccountStorageInterface: Send + Sync {
     async fn update_account(&self, account_data: AccountData, commitment: Commitment);
@@ -21,4 +29,9 @@ pub trait AccountStorageInterface: Send + Sync {
     ) -> Option<Vec<AccountData>>;
 
     async fn process_slot_data(&self, slot: Slot, commitment: Commitment) -> Vec<AccountData>;
+
+    /// removes the accounts of which no version is kept, returns the number of removed accounts
+    async fn retain_accounts(&self, keep: &(dyn Fn(&AccountData) -> bool + Send + Sync)) -> usize;
+
+    async fn stats(&self) -> AccountStoreStats;
 }
@@ -1,18 +1,28 @@
 use std::{
     collections::{BTreeSet, HashSet},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use async_trait::async_trait;
 use dashmap::DashMap;
 use itertools::Itertools;
-use solana_lite_rpc_core::{commitment_utils::Commitment, structures::account_data::AccountData};
+use solana_lite_rpc_core::{
+    commitment_utils::Commitment,
+    memory_budget::{EvictionPriority, MemoryConsumer},
+    structures::account_data::AccountData,
+};
 use solana_rpc_client_api::filter::RpcFilterType;
 use solana_sdk::{pubkey::Pubkey, slot_history::Slot};
 use std::collections::BTreeMap;
 use tokio::sync::RwLock;
 
-use crate::account_store_interface::AccountStorageInterface;
+use crate::account_store_interface::{AccountStorageInterface, AccountStoreStats};
+
+// finalized closed accounts are answered as closed for about an hour, after that they are forgotten
+const TOMBSTONE_RETENTION_SLOTS: Slot = 9_000;
 
 #[derive(Clone, Default)]
 pub struct AccountDataByCommitment {
@@ -49,6 +59,14 @@ impl AccountDataByCommitment {
         }
     }
 
+    /// the processed, confirmed and finalized versions of the account
+    pub fn versions(&self) -> impl Iterator<Item = &AccountData> {
+        self.processed_accounts
+            .values()
+            .chain(self.confirmed_account.iter())
+            .chain(self.finalized_account.iter())
+    }
+
     pub fn initialize(data: AccountData) -> Self {
         let mut processed_accounts = BTreeMap::new();
         processed_accounts.insert(data.updated_slot, data.clone());
@@ -185,6 +203,25 @@ pub struct InmemoryAccountStore {
     account_store: Arc<DashMap<Pubkey, AccountDataByCommitment>>,
     confirmed_slots_map: RwLock<BTreeSet<Slot>>,
     owner_map_accounts: Arc<DashMap<Pubkey, HashSet<Pubkey>>>,
+    // accounts by the slot they were closed in, evicted once the slot is older than the retention
+    tombstones: Mutex<BTreeMap<Slot, Vec<Pubkey>>>,
+    // estimated bytes of the accounts, tracked on every change so accounting does not scan the store
+    accounts_bytes: AtomicUsize,
+    // number of accounts listed by the owners
+    owned_accounts: AtomicUsize,
+}
+
+// estimated heap memory of an account, the confirmed and finalized versions share their data with
+// a processed version
+fn account_size(account: &AccountDataByCommitment) -> usize {
+    let versions = account.processed_accounts.len() + 2;
+    std::mem::size_of::<(Pubkey, AccountDataByCommitment)>()
+        + versions * std::mem::size_of::<AccountData>()
+        + account
+            .processed_accounts
+            .values()
+            .map(|account_data| account_data.account.data.len())
+            .sum::<usize>()
 }
 
 impl InmemoryAccountStore {
@@ -193,18 +230,44 @@ impl InmemoryAccountStore {
             account_store: Arc::new(DashMap::new()),
             confirmed_slots_map: RwLock::new(BTreeSet::new()),
             owner_map_accounts: Arc::new(DashMap::new()),
+            tombstones: Mutex::new(BTreeMap::new()),
+            accounts_bytes: AtomicUsize::new(0),
+            owned_accounts: AtomicUsize::new(0),
+        }
+    }
+
+    // the new size is added before the previous one is subtracted so the counter never underflows
+    fn resize_account(&self, previous_size: usize, size: usize) {
+        self.accounts_bytes.fetch_add(size, Ordering::Relaxed);
+        self.accounts_bytes
+            .fetch_sub(previous_size, Ordering::Relaxed);
+    }
+
+    fn remove_account_owner(&self, account: &Pubkey, owner: Pubkey, remove_empty: bool) {
+        if let dashmap::mapref::entry::Entry::Occupied(mut occ) =
+            self.owner_map_accounts.entry(owner)
+        {
+            if occ.get_mut().remove(account) {
+                self.owned_accounts.fetch_sub(1, Ordering::Relaxed);
+            }
+            if remove_empty && occ.get().is_empty() {
+                occ.remove();
+            }
         }
     }
 
     fn add_account_owner(&self, account: Pubkey, owner: Pubkey) {
         match self.owner_map_accounts.entry(owner) {
             dashmap::mapref::entry::Entry::Occupied(mut occ) => {
-                occ.get_mut().insert(account);
+                if occ.get_mut().insert(account) {
+                    self.owned_accounts.fetch_add(1, Ordering::Relaxed);
+                }
             }
             dashmap::mapref::entry::Entry::Vacant(vc) => {
                 let mut set = HashSet::new();
                 set.insert(account);
                 vc.insert(set);
+                self.owned_accounts.fetch_add(1, Ordering::Relaxed);
             }
         }
     }
@@ -221,21 +284,61 @@ impl InmemoryAccountStore {
             && prev_account_data.account.owner != new_account_data.account.owner
         {
             if commitment == Commitment::Finalized {
-                match self
-                    .owner_map_accounts
-                    .entry(prev_account_data.account.owner)
-                {
-                    dashmap::mapref::entry::Entry::Occupied(mut occ) => {
-                        occ.get_mut().remove(&prev_account_data.pubkey);
-                    }
-                    dashmap::mapref::entry::Entry::Vacant(_) => {
-                        // do nothing
-                    }
-                }
+                self.remove_account_owner(
+                    &prev_account_data.pubkey,
+                    prev_account_data.account.owner,
+                    false,
+                );
             }
             self.add_account_owner(new_account_data.pubkey, new_account_data.account.owner);
         }
     }
+
+    // a finalized closed account is only kept as a tombstone, so it is known to be closed, and is
+    // not listed by the owners anymore unless it has been recreated in a later slot
+    fn remove_closed_account_owners(&self, closed_account_data: &AccountData, owners: &[Pubkey]) {
+        let recreated = self
+            .account_store
+            .get(&closed_account_data.pubkey)
+            .map(|account| {
+                account
+                    .processed_accounts
+                    .range(closed_account_data.updated_slot + 1..)
+                    .any(|(_, account_data)| !account_data.is_closed())
+            })
+            .unwrap_or_default();
+        if recreated {
+            return;
+        }
+        self.tombstones
+            .lock()
+            .unwrap()
+            .entry(closed_account_data.updated_slot)
+            .or_default()
+            .push(closed_account_data.pubkey);
+        for owner in owners {
+            self.remove_account_owner(&closed_account_data.pubkey, *owner, true);
+        }
+    }
+
+    // forgets the accounts closed before the retention, unless they were recreated since
+    fn evict_tombstones(&self, finalized_slot: Slot) {
+        let expired = {
+            let mut tombstones = self.tombstones.lock().unwrap();
+            let retained =
+                tombstones.split_off(&finalized_slot.saturating_sub(TOMBSTONE_RETENTION_SLOTS));
+            std::mem::replace(&mut *tombstones, retained)
+        };
+        for pubkey in expired.into_values().flatten() {
+            if let Some((_, account)) = self.account_store.remove_if(&pubkey, |_, account| {
+                account
+                    .versions()
+                    .all(|account_data| account_data.is_closed())
+            }) {
+                self.resize_account(account_size(&account), 0);
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -254,22 +357,42 @@ impl AccountStorageInterface for InmemoryAccountStore {
             commitment
         };
 
+        let closed_account_data = (commitment == Commitment::Finalized && account_data.is_closed())
+            .then(|| account_data.clone());
+        let mut prev_owner = None;
         match self.account_store.entry(account_data.pubkey) {
             dashmap::mapref::entry::Entry::Occupied(mut occ) => {
                 let prev_account = occ.get().get_account_data(commitment);
-                if let Some(prev_account) = prev_account {
-                    self.update_owner(&prev_account, &account_data, commitment);
+                match prev_account {
+                    // the owners forgot the account when it was closed
+                    Some(prev_account) if prev_account.is_closed() && !account_data.is_closed() => {
+                        self.add_account_owner(account_data.pubkey, account_data.account.owner);
+                    }
+                    Some(prev_account) => {
+                        self.update_owner(&prev_account, &account_data, commitment);
+                        prev_owner = Some(prev_account.account.owner);
+                    }
+                    None => {}
                 }
+                let previous_size = account_size(occ.get());
                 occ.get_mut().update(account_data, commitment);
+                self.resize_account(previous_size, account_size(occ.get()));
             }
             dashmap::mapref::entry::Entry::Vacant(vac) => {
                 self.add_account_owner(account_data.pubkey, account_data.account.owner);
-                vac.insert(AccountDataByCommitment::new(
-                    account_data.clone(),
-                    commitment,
-                ));
+                let account = AccountDataByCommitment::new(account_data.clone(), commitment);
+                self.resize_account(0, account_size(&account));
+                vac.insert(account);
             }
         }
+
+        if let Some(closed_account_data) = closed_account_data {
+            let owners = prev_owner
+                .into_iter()
+                .chain([closed_account_data.account.owner])
+                .collect_vec();
+            self.remove_closed_account_owners(&closed_account_data, &owners);
+        }
     }
 
     async fn initilize_account(&self, account_data: AccountData) {
@@ -281,10 +404,12 @@ impl AccountStorageInterface for InmemoryAccountStore {
             }
             false => {
                 self.add_account_owner(account_data.pubkey, account_data.account.owner);
-                self.account_store.insert(
-                    account_data.pubkey,
-                    AccountDataByCommitment::initialize(account_data),
-                );
+                let pubkey = account_data.pubkey;
+                let account = AccountDataByCommitment::initialize(account_data);
+                self.resize_account(0, account_size(&account));
+                if let Some(previous) = self.account_store.insert(pubkey, account) {
+                    self.resize_account(account_size(&previous), 0);
+                }
             }
         }
     }
@@ -307,7 +432,7 @@ impl AccountStorageInterface for InmemoryAccountStore {
             let mut return_vec = vec![];
             for program_account in program_accounts.iter() {
                 let account_data = self.get_account(*program_account, commitment).await;
-                if let Some(account_data) = account_data {
+                if let Some(account_data) = account_data.filter(|x| !x.is_closed()) {
                     // recheck program owner and filters
                     if account_data.account.owner.eq(&program_pubkey) {
                         match &account_filters {
@@ -360,7 +485,12 @@ impl AccountStorageInterface for InmemoryAccountStore {
         let updated_accounts = self
             .account_store
             .iter_mut()
-            .filter_map(|mut acc| acc.promote_slot_commitment(slot, commitment))
+            .filter_map(|mut acc| {
+                let previous_size = account_size(&acc);
+                let promoted = acc.promote_slot_commitment(slot, commitment);
+                self.resize_account(previous_size, account_size(&acc));
+                promoted
+            })
             .collect_vec();
 
         // update owners
@@ -368,12 +498,27 @@ impl AccountStorageInterface for InmemoryAccountStore {
             .iter()
             .for_each(|(account_data, prev_account_data)| {
                 if let Some(prev_account_data) = prev_account_data {
-                    if prev_account_data.account.owner != account_data.account.owner {
+                    if prev_account_data.is_closed() && !account_data.is_closed() {
+                        // the owners forgot the account when it was closed
+                        self.add_account_owner(account_data.pubkey, account_data.account.owner);
+                    } else if prev_account_data.account.owner != account_data.account.owner {
                         self.update_owner(prev_account_data, account_data, commitment);
                     }
                 }
+                if commitment == Commitment::Finalized && account_data.is_closed() {
+                    let owners = prev_account_data
+                        .iter()
+                        .map(|prev_account_data| prev_account_data.account.owner)
+                        .chain([account_data.account.owner])
+                        .collect_vec();
+                    self.remove_closed_account_owners(account_data, &owners);
+                }
             });
 
+        if commitment == Commitment::Finalized {
+            self.evict_tombstones(slot);
+        }
+
         updated_accounts
             .iter()
             .filter_map(|(account_data, prev_account_data)| {
@@ -390,6 +535,63 @@ impl AccountStorageInterface for InmemoryAccountStore {
             .cloned()
             .collect_vec()
     }
+
+    async fn retain_accounts(&self, keep: &(dyn Fn(&AccountData) -> bool + Send + Sync)) -> usize {
+        let candidates = self
+            .account_store
+            .iter()
+            .filter(|account| !account.versions().any(keep))
+            .map(|account| *account.key())
+            .collect_vec();
+        let mut removed = 0;
+        for pubkey in candidates {
+            // the account could have been updated since
+            let Some((_, account)) = self
+                .account_store
+                .remove_if(&pubkey, |_, account| !account.versions().any(keep))
+            else {
+                continue;
+            };
+            self.resize_account(account_size(&account), 0);
+            for owner in account
+                .versions()
+                .map(|account_data| account_data.account.owner)
+            {
+                self.remove_account_owner(&pubkey, owner, true);
+            }
+            removed += 1;
+        }
+        removed
+    }
+
+    async fn stats(&self) -> AccountStoreStats {
+        AccountStoreStats {
+            accounts: self.account_store.len(),
+            owners: self.owner_map_accounts.len(),
+            confirmed_slots: self.confirmed_slots_map.read().await.len(),
+        }
+    }
+}
+
+#[async_trait]
+impl MemoryConsumer for InmemoryAccountStore {
+    fn name(&self) -> &'static str {
+        "account_store"
+    }
+
+    // accounts are only streamed, an evicted account could not be served until it changes again
+    fn eviction_priority(&self) -> EvictionPriority {
+        EvictionPriority::Never
+    }
+
+    async fn memory_usage(&self) -> usize {
+        let owners = self.owner_map_accounts.len() + self.owned_accounts.load(Ordering::Relaxed);
+        self.accounts_bytes.load(Ordering::Relaxed) + owners * std::mem::size_of::<Pubkey>()
+    }
+
+    async fn evict(&self, _bytes: usize) -> usize {
+        0
+    }
 }
 
 impl Default for InmemoryAccountStore {
@@ -403,13 +605,15 @@ mod tests {
     use itertools::Itertools;
     use rand::{rngs::ThreadRng, Rng};
     use solana_lite_rpc_core::{
-        commitment_utils::Commitment, structures::account_data::AccountData,
+        commitment_utils::Commitment,
+        memory_budget::MemoryConsumer,
+        structures::account_data::{Account, AccountData},
     };
-    use solana_sdk::{account::Account, pubkey::Pubkey, slot_history::Slot};
+    use solana_sdk::{pubkey::Pubkey, slot_history::Slot, system_program};
 
     use crate::{
         account_store_interface::AccountStorageInterface,
-        inmemory_account_store::InmemoryAccountStore,
+        inmemory_account_store::{account_size, InmemoryAccountStore, TOMBSTONE_RETENTION_SLOTS},
     };
 
     fn create_random_account(
@@ -423,7 +627,7 @@ mod tests {
             pubkey,
             account: Account {
                 lamports: rng.gen(),
-                data: (0..length).map(|_| rng.gen::<u8>()).collect_vec(),
+                data: (0..length).map(|_| rng.gen::<u8>()).collect_vec().into(),
                 owner: program,
                 executable: false,
                 rent_epoch: 0,
@@ -432,6 +636,20 @@ mod tests {
         }
     }
 
+    fn create_closed_account(updated_slot: Slot, pubkey: Pubkey) -> AccountData {
+        AccountData {
+            pubkey,
+            account: Account {
+                lamports: 0,
+                data: Default::default(),
+                owner: system_program::id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+            updated_slot,
+        }
+    }
+
     #[tokio::test]
     pub async fn test_account_store() {
         let store = InmemoryAccountStore::default();
@@ -839,4 +1057,343 @@ mod tests {
         assert_eq!(p_3, Some(vec![]));
         assert_eq!(p_4, Some(vec![account_processed.clone()]));
     }
+
+    #[tokio::test]
+    pub async fn test_account_data_is_shared_not_copied() {
+        let store = InmemoryAccountStore::default();
+        let mut rng = rand::thread_rng();
+        let program = Pubkey::new_unique();
+
+        // a busy program: many accounts of the same owner updated in every slot
+        let mut updates = vec![];
+        for slot in 1..4 {
+            for _ in 0..100 {
+                let account = create_random_account(&mut rng, slot, Pubkey::new_unique(), program);
+                store.initilize_account(account.clone()).await;
+                updates.push(account);
+            }
+        }
+
+        let program_accounts = store
+            .get_program_accounts(program, None, Commitment::Finalized)
+            .await
+            .unwrap();
+        assert_eq!(program_accounts.len(), updates.len());
+        for account in program_accounts {
+            let update = updates
+                .iter()
+                .find(|update| update.pubkey == account.pubkey)
+                .unwrap();
+            // same buffer as the update, only the reference count changed
+            assert_eq!(account.account.data.as_ptr(), update.account.data.as_ptr());
+        }
+    }
+
+    #[tokio::test]
+    pub async fn test_close_then_recreate_account() {
+        let store = InmemoryAccountStore::default();
+        let mut rng = rand::thread_rng();
+        let program = Pubkey::new_unique();
+        let pk1 = Pubkey::new_unique();
+
+        let account_data_0 = create_random_account(&mut rng, 0, pk1, program);
+        store.initilize_account(account_data_0.clone()).await;
+
+        let closed_account = create_closed_account(1, pk1);
+        store
+            .update_account(closed_account.clone(), Commitment::Processed)
+            .await;
+
+        // the closure is served as is, but the account is not listed by its program anymore
+        assert_eq!(
+            store.get_account(pk1, Commitment::Processed).await,
+            Some(closed_account.clone())
+        );
+        assert_eq!(
+            store
+                .get_program_accounts(program, None, Commitment::Processed)
+                .await,
+            Some(vec![])
+        );
+        assert_eq!(
+            store
+                .get_program_accounts(program, None, Commitment::Finalized)
+                .await,
+            Some(vec![account_data_0.clone()])
+        );
+
+        store.process_slot_data(1, Commitment::Confirmed).await;
+        let finalized = store.process_slot_data(1, Commitment::Finalized).await;
+        assert_eq!(finalized, vec![closed_account.clone()]);
+
+        // only a tombstone is left
+        assert_eq!(
+            store.get_account(pk1, Commitment::Finalized).await,
+            Some(closed_account.clone())
+        );
+        assert!(!store.owner_map_accounts.contains_key(&program));
+        assert!(!store.owner_map_accounts.contains_key(&system_program::id()));
+        assert_eq!(
+            store
+                .get_program_accounts(program, None, Commitment::Finalized)
+                .await,
+            None
+        );
+
+        let recreated_account = create_random_account(&mut rng, 2, pk1, program);
+        store
+            .update_account(recreated_account.clone(), Commitment::Processed)
+            .await;
+        assert_eq!(
+            store
+                .get_program_accounts(program, None, Commitment::Processed)
+                .await,
+            Some(vec![recreated_account.clone()])
+        );
+        assert_eq!(
+            store
+                .get_program_accounts(program, None, Commitment::Finalized)
+                .await,
+            Some(vec![])
+        );
+
+        store.process_slot_data(2, Commitment::Confirmed).await;
+        store.process_slot_data(2, Commitment::Finalized).await;
+        assert_eq!(
+            store.get_account(pk1, Commitment::Finalized).await,
+            Some(recreated_account.clone())
+        );
+        assert_eq!(
+            store
+                .get_program_accounts(program, None, Commitment::Finalized)
+                .await,
+            Some(vec![recreated_account.clone()])
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_recreate_account_before_closure_is_finalized() {
+        let store = InmemoryAccountStore::default();
+        let mut rng = rand::thread_rng();
+        let program = Pubkey::new_unique();
+        let other_program = Pubkey::new_unique();
+        let pk1 = Pubkey::new_unique();
+
+        store
+            .initilize_account(create_random_account(&mut rng, 0, pk1, program))
+            .await;
+
+        let closed_account = create_closed_account(1, pk1);
+        store
+            .update_account(closed_account.clone(), Commitment::Processed)
+            .await;
+        // recreated for another program in the next slot
+        let recreated_account = create_random_account(&mut rng, 2, pk1, other_program);
+        store
+            .update_account(recreated_account.clone(), Commitment::Processed)
+            .await;
+
+        store.process_slot_data(1, Commitment::Confirmed).await;
+        store.process_slot_data(2, Commitment::Confirmed).await;
+        store.process_slot_data(1, Commitment::Finalized).await;
+
+        // finalizing the closure does not forget the recreated account
+        assert_eq!(
+            store.get_account(pk1, Commitment::Finalized).await,
+            Some(closed_account.clone())
+        );
+        assert_eq!(
+            store
+                .get_program_accounts(other_program, None, Commitment::Confirmed)
+                .await,
+            Some(vec![recreated_account.clone()])
+        );
+        assert_eq!(
+            store
+                .get_program_accounts(other_program, None, Commitment::Finalized)
+                .await,
+            Some(vec![])
+        );
+
+        store.process_slot_data(2, Commitment::Finalized).await;
+        assert_eq!(
+            store
+                .get_program_accounts(other_program, None, Commitment::Finalized)
+                .await,
+            Some(vec![recreated_account.clone()])
+        );
+        assert_eq!(
+            store
+                .get_program_accounts(program, None, Commitment::Finalized)
+                .await,
+            Some(vec![])
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_retain_accounts() {
+        let store = InmemoryAccountStore::default();
+        let mut rng = rand::thread_rng();
+        let program = Pubkey::new_unique();
+        let other_program = Pubkey::new_unique();
+        let pk1 = Pubkey::new_unique();
+        let pk2 = Pubkey::new_unique();
+        let pk3 = Pubkey::new_unique();
+
+        let account_data_1 = create_random_account(&mut rng, 0, pk1, program);
+        store.initilize_account(account_data_1.clone()).await;
+        store
+            .initilize_account(create_random_account(&mut rng, 0, pk2, other_program))
+            .await;
+        // moved to the kept program, but not finalized yet
+        store
+            .initilize_account(create_random_account(&mut rng, 0, pk3, other_program))
+            .await;
+        let account_data_3 = create_random_account(&mut rng, 1, pk3, program);
+        store
+            .update_account(account_data_3.clone(), Commitment::Processed)
+            .await;
+
+        let removed = store
+            .retain_accounts(&|account_data| account_data.account.owner == program)
+            .await;
+
+        assert_eq!(removed, 1);
+        assert_eq!(store.get_account(pk2, Commitment::Finalized).await, None);
+        assert_eq!(
+            store.get_account(pk1, Commitment::Finalized).await,
+            Some(account_data_1)
+        );
+        assert_eq!(
+            store.get_account(pk3, Commitment::Processed).await,
+            Some(account_data_3)
+        );
+        assert_eq!(
+            store
+                .get_program_accounts(other_program, None, Commitment::Finalized)
+                .await
+                .map(|accounts| accounts.len()),
+            Some(1)
+        );
+        assert_eq!(store.stats().await.accounts, 2);
+    }
+
+    #[tokio::test]
+    pub async fn test_tombstones_are_evicted_after_the_retention() {
+        let store = InmemoryAccountStore::default();
+        let mut rng = rand::thread_rng();
+        let program = Pubkey::new_unique();
+        let closed = Pubkey::new_unique();
+        let recreated = Pubkey::new_unique();
+
+        for pubkey in [closed, recreated] {
+            store
+                .initilize_account(create_random_account(&mut rng, 0, pubkey, program))
+                .await;
+            store
+                .update_account(create_closed_account(1, pubkey), Commitment::Processed)
+                .await;
+        }
+        store.process_slot_data(1, Commitment::Confirmed).await;
+        store.process_slot_data(1, Commitment::Finalized).await;
+        let recreated_account = create_random_account(&mut rng, 2, recreated, program);
+        store
+            .update_account(recreated_account.clone(), Commitment::Processed)
+            .await;
+        store.process_slot_data(2, Commitment::Confirmed).await;
+        store.process_slot_data(2, Commitment::Finalized).await;
+
+        // still within the retention
+        store
+            .process_slot_data(1 + TOMBSTONE_RETENTION_SLOTS, Commitment::Finalized)
+            .await;
+        assert!(store
+            .get_account(closed, Commitment::Finalized)
+            .await
+            .is_some());
+
+        store
+            .process_slot_data(2 + TOMBSTONE_RETENTION_SLOTS, Commitment::Finalized)
+            .await;
+        assert_eq!(store.get_account(closed, Commitment::Finalized).await, None);
+        assert!(!store.account_store.contains_key(&closed));
+        // the account was recreated after its closure
+        assert_eq!(
+            store.get_account(recreated, Commitment::Finalized).await,
+            Some(recreated_account)
+        );
+        assert!(store.tombstones.lock().unwrap().is_empty());
+    }
+
+    // the usage tracked on every change matches a scan of the store
+    async fn assert_tracked_memory_usage(store: &InmemoryAccountStore) {
+        let accounts: usize = store
+            .account_store
+            .iter()
+            .map(|entry| account_size(&entry))
+            .sum();
+        let owners: usize = store
+            .owner_map_accounts
+            .iter()
+            .map(|entry| (entry.len() + 1) * std::mem::size_of::<Pubkey>())
+            .sum();
+        assert_eq!(store.memory_usage().await, accounts + owners);
+    }
+
+    #[tokio::test]
+    pub async fn test_memory_usage_is_tracked() {
+        let store = InmemoryAccountStore::default();
+        let mut rng = rand::thread_rng();
+        let program = Pubkey::new_unique();
+        let other_program = Pubkey::new_unique();
+        let pubkeys = (0..4).map(|_| Pubkey::new_unique()).collect_vec();
+        assert_tracked_memory_usage(&store).await;
+
+        for pubkey in &pubkeys {
+            store
+                .initilize_account(create_random_account(&mut rng, 0, *pubkey, program))
+                .await;
+        }
+        assert_tracked_memory_usage(&store).await;
+
+        for slot in 1..4 {
+            store
+                .update_account(
+                    create_random_account(&mut rng, slot, pubkeys[0], program),
+                    Commitment::Processed,
+                )
+                .await;
+            assert_tracked_memory_usage(&store).await;
+        }
+        store
+            .update_account(
+                create_random_account(&mut rng, 1, pubkeys[1], other_program),
+                Commitment::Processed,
+            )
+            .await;
+        store
+            .update_account(create_closed_account(1, pubkeys[2]), Commitment::Processed)
+            .await;
+        assert_tracked_memory_usage(&store).await;
+
+        for slot in 1..4 {
+            store.process_slot_data(slot, Commitment::Confirmed).await;
+            assert_tracked_memory_usage(&store).await;
+            store.process_slot_data(slot, Commitment::Finalized).await;
+            assert_tracked_memory_usage(&store).await;
+        }
+
+        // the tombstone of the closed account expires
+        store
+            .process_slot_data(2 + TOMBSTONE_RETENTION_SLOTS, Commitment::Finalized)
+            .await;
+        assert!(!store.account_store.contains_key(&pubkeys[2]));
+        assert_tracked_memory_usage(&store).await;
+
+        let removed = store
+            .retain_accounts(&|account_data| account_data.pubkey != pubkeys[3])
+            .await;
+        assert_eq!(removed, 1);
+        assert_tracked_memory_usage(&store).await;
+    }
 }
@@ -1,3 +1,4 @@
 pub mod account_service;
 pub mod account_store_interface;
 pub mod inmemory_account_store;
+pub mod token_accounts;
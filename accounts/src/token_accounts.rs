@@ -0,0 +1,262 @@
+use solana_account_decoder::parse_token::{is_known_spl_token_id, spl_token_ids};
+use solana_lite_rpc_core::structures::{
+    account_data::{Account, AccountData},
+    account_filter::{AccountFilterType, AccountFilters},
+};
+use solana_sdk::pubkey::{Pubkey, PUBKEY_BYTES};
+
+// the base layouts of mints and token accounts are the same for spl token and token-2022
+const MINT_LEN: usize = 82;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+// token-2022 accounts with extensions have their type after the base token account layout
+const ACCOUNT_TYPE_OFFSET: usize = TOKEN_ACCOUNT_LEN;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+// the spl token program, token-2022 is the second known program
+fn spl_token_id() -> Pubkey {
+    spl_token_ids()[0]
+}
+
+fn has_account_type(account: &Account, account_type: u8) -> bool {
+    account.data.get(ACCOUNT_TYPE_OFFSET) == Some(&account_type)
+}
+
+/// decimals of a mint of the spl token or token-2022 program
+pub fn mint_decimals(account: &Account) -> Option<u8> {
+    if !is_known_spl_token_id(&account.owner) {
+        return None;
+    }
+    if account.data.len() == MINT_LEN || has_account_type(account, ACCOUNT_TYPE_MINT) {
+        account.data.get(MINT_DECIMALS_OFFSET).copied()
+    } else {
+        None
+    }
+}
+
+fn is_token_account(account: &Account) -> bool {
+    is_known_spl_token_id(&account.owner)
+        && (account.data.len() == TOKEN_ACCOUNT_LEN
+            || has_account_type(account, ACCOUNT_TYPE_ACCOUNT))
+}
+
+/// mint of a token account of the spl token or token-2022 program
+pub fn token_account_mint(account: &Account) -> Option<Pubkey> {
+    if !is_token_account(account) {
+        return None;
+    }
+    let mint = &account.data[TOKEN_ACCOUNT_MINT_OFFSET..TOKEN_ACCOUNT_MINT_OFFSET + PUBKEY_BYTES];
+    Pubkey::try_from(mint).ok()
+}
+
+/// balance of a token account of the mint
+pub fn token_account_amount(account: &Account, mint: &Pubkey) -> Option<u64> {
+    if !is_token_account(account) {
+        return None;
+    }
+    let account_mint =
+        &account.data[TOKEN_ACCOUNT_MINT_OFFSET..TOKEN_ACCOUNT_MINT_OFFSET + PUBKEY_BYTES];
+    if account_mint != mint.as_ref() {
+        return None;
+    }
+    let amount = account.data[TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8]
+        .try_into()
+        .ok()?;
+    Some(u64::from_le_bytes(amount))
+}
+
+/// the token accounts of the mint with the largest balances, largest first
+pub fn largest_token_accounts<'a>(
+    accounts: impl IntoIterator<Item = &'a AccountData>,
+    mint: &Pubkey,
+    limit: usize,
+) -> Vec<(Pubkey, u64)> {
+    let mut balances = accounts
+        .into_iter()
+        .filter_map(|account_data| {
+            token_account_amount(&account_data.account, mint)
+                .map(|amount| (account_data.pubkey, amount))
+        })
+        .collect::<Vec<_>>();
+    balances.sort_by(|(_, a), (_, b)| b.cmp(a));
+    balances.truncate(limit);
+    balances
+}
+
+/// the filters subscribe every token account of the mint: a filter on the token program without
+/// further conditions than the mint at offset 0 and, for spl token, the size of a token account
+pub fn filters_cover_token_accounts(
+    filters: &AccountFilters,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+) -> bool {
+    let program_id = token_program.to_string();
+    filters
+        .iter()
+        .filter(|filter| filter.program_id.as_ref() == Some(&program_id))
+        .any(|filter| {
+            filter.filters.iter().flatten().all(|filter| match filter {
+                AccountFilterType::Memcmp(memcmp) => {
+                    memcmp.offset as usize == TOKEN_ACCOUNT_MINT_OFFSET
+                        && memcmp.data.bytes().as_deref() == Some(mint.as_ref())
+                }
+                // token-2022 accounts with extensions are larger
+                AccountFilterType::Datasize(size) => {
+                    *size as usize == TOKEN_ACCOUNT_LEN && *token_program == spl_token_id()
+                }
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_account(mint: &Pubkey, amount: u64) -> AccountData {
+        let mut data = vec![0; TOKEN_ACCOUNT_LEN];
+        data[TOKEN_ACCOUNT_MINT_OFFSET..TOKEN_ACCOUNT_MINT_OFFSET + PUBKEY_BYTES]
+            .copy_from_slice(mint.as_ref());
+        data[TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8]
+            .copy_from_slice(&amount.to_le_bytes());
+        AccountData {
+            pubkey: Pubkey::new_unique(),
+            account: Account {
+                lamports: 2_039_280,
+                data: data.into(),
+                owner: spl_token_id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+            updated_slot: 1,
+        }
+    }
+
+    #[test]
+    fn test_mint_decimals() {
+        let mut data = vec![0; MINT_LEN];
+        data[MINT_DECIMALS_OFFSET] = 6;
+        let mut mint = Account {
+            lamports: 1_461_600,
+            data: data.into(),
+            owner: spl_token_id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        assert_eq!(mint_decimals(&mint), Some(6));
+
+        // a token account is no mint
+        let mut data = mint.data.to_vec();
+        data.resize(TOKEN_ACCOUNT_LEN, 0);
+        mint.data = data.clone().into();
+        assert_eq!(mint_decimals(&mint), None);
+
+        // token-2022 mint with extensions
+        data.resize(TOKEN_ACCOUNT_LEN + 10, 0);
+        data[ACCOUNT_TYPE_OFFSET] = ACCOUNT_TYPE_MINT;
+        mint.data = data.into();
+        assert_eq!(mint_decimals(&mint), Some(6));
+
+        mint.owner = Pubkey::new_unique();
+        assert_eq!(mint_decimals(&mint), None);
+    }
+
+    #[test]
+    fn test_largest_token_accounts() {
+        let mint = Pubkey::new_unique();
+        let accounts = vec![
+            token_account(&mint, 5),
+            token_account(&mint, 50),
+            token_account(&Pubkey::new_unique(), 1_000),
+            token_account(&mint, 20),
+        ];
+
+        let largest = largest_token_accounts(&accounts, &mint, 2);
+        assert_eq!(
+            largest,
+            vec![(accounts[1].pubkey, 50), (accounts[3].pubkey, 20)]
+        );
+    }
+
+    #[test]
+    fn test_token_account_mint() {
+        let mint = Pubkey::new_unique();
+        let mut account = token_account(&mint, 5).account;
+        assert_eq!(token_account_mint(&account), Some(mint));
+
+        // a mint has no mint
+        account.data = vec![0; MINT_LEN].into();
+        assert_eq!(token_account_mint(&account), None);
+    }
+
+    #[test]
+    fn test_filters_cover_token_accounts() {
+        use solana_lite_rpc_core::structures::account_filter::{
+            AccountFilter, MemcmpFilter, MemcmpFilterData,
+        };
+
+        let mint = Pubkey::new_unique();
+        let token_program = spl_token_id();
+        let filter = |program_id: &Pubkey, filters: Option<Vec<AccountFilterType>>| {
+            vec![AccountFilter {
+                accounts: vec![],
+                program_id: Some(program_id.to_string()),
+                filters,
+            }]
+        };
+        let memcmp = |offset: u64, bytes: &[u8]| {
+            AccountFilterType::Memcmp(MemcmpFilter {
+                offset,
+                data: MemcmpFilterData::Base58(bs58::encode(bytes).into_string()),
+            })
+        };
+
+        assert!(filters_cover_token_accounts(
+            &filter(&token_program, None),
+            &token_program,
+            &mint
+        ));
+        assert!(filters_cover_token_accounts(
+            &filter(
+                &token_program,
+                Some(vec![
+                    AccountFilterType::Datasize(TOKEN_ACCOUNT_LEN as u64),
+                    memcmp(0, mint.as_ref())
+                ])
+            ),
+            &token_program,
+            &mint
+        ));
+        // another mint, the owner of the token accounts or another program
+        assert!(!filters_cover_token_accounts(
+            &filter(
+                &token_program,
+                Some(vec![memcmp(0, Pubkey::new_unique().as_ref())])
+            ),
+            &token_program,
+            &mint
+        ));
+        assert!(!filters_cover_token_accounts(
+            &filter(&token_program, Some(vec![memcmp(32, mint.as_ref())])),
+            &token_program,
+            &mint
+        ));
+        assert!(!filters_cover_token_accounts(
+            &filter(&Pubkey::new_unique(), None),
+            &token_program,
+            &mint
+        ));
+        // token-2022 accounts with extensions are not of the base size
+        let token_2022 = spl_token_ids()[1];
+        assert!(!filters_cover_token_accounts(
+            &filter(
+                &token_2022,
+                Some(vec![AccountFilterType::Datasize(TOKEN_ACCOUNT_LEN as u64)])
+            ),
+            &token_2022,
+            &mint
+        ));
+    }
+}
@@ -1,17 +1,23 @@
+use anyhow::bail;
 use async_trait::async_trait;
 use dashmap::DashMap;
 use itertools::Itertools;
-use prometheus::{opts, register_int_gauge, IntGauge};
+use prometheus::{opts, register_int_counter, register_int_gauge, IntCounter, IntGauge};
 use serde::{Deserialize, Serialize};
 use solana_address_lookup_table_program::state::AddressLookupTable;
+use solana_lite_rpc_core::structures::account_data::{AccountData, AccountStream};
 use solana_lite_rpc_core::traits::address_lookup_table_interface::AddressLookupTableInterface;
+use solana_lite_rpc_core::AnyhowJoinHandle;
 use solana_rpc_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
 use std::{sync::Arc, time::Duration};
+use tokio::sync::broadcast::error::RecvError;
 
 lazy_static::lazy_static! {
     static ref LRPC_ALTS_IN_STORE: IntGauge =
        register_int_gauge!(opts!("literpc_alts_stored", "Alts stored in literpc")).unwrap();
+    static ref LRPC_ALT_UPDATES: IntCounter =
+       register_int_counter!(opts!("literpc_alt_updates", "Alts updated from the account stream")).unwrap();
 }
 
 #[derive(Clone)]
@@ -75,7 +81,13 @@ impl AddressLookupTableStore {
     }
 
     pub fn save_account(&self, address: &Pubkey, data: &[u8]) {
-        let lookup_table = AddressLookupTable::deserialize(data).unwrap();
+        let lookup_table = match AddressLookupTable::deserialize(data) {
+            Ok(lookup_table) => lookup_table,
+            Err(e) => {
+                log::error!("Cannot deserialize address lookup table {address}: {e}");
+                return;
+            }
+        };
         if self
             .map
             .insert(*address, lookup_table.addresses.to_vec())
@@ -86,6 +98,40 @@ impl AddressLookupTableStore {
         drop(lookup_table);
     }
 
+    /// Applies the lookup tables streamed by the account subscription, so extended tables are resolved
+    /// without a reload and closed tables are forgotten. Only accounts matching the account filters are
+    /// streamed, a filter on the address lookup table program follows all of them.
+    pub fn start_account_updates(&self, mut account_stream: AccountStream) -> AnyhowJoinHandle {
+        let this = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let notification = match account_stream.recv().await {
+                    Ok(notification) => notification,
+                    Err(RecvError::Lagged(lagged)) => {
+                        log::warn!("ALT updates lagged by {lagged} accounts");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => bail!("account stream closed"),
+                };
+                let AccountData {
+                    pubkey, account, ..
+                } = notification.data;
+                if account.owner != solana_address_lookup_table_program::id() {
+                    continue;
+                }
+
+                LRPC_ALT_UPDATES.inc();
+                if account.lamports == 0 || account.data.is_empty() {
+                    if this.map.remove(&pubkey).is_some() {
+                        LRPC_ALTS_IN_STORE.dec();
+                    }
+                } else {
+                    this.save_account(&pubkey, &account.data);
+                }
+            }
+        })
+    }
+
     pub async fn reload_if_necessary(
         &self,
         alt_messages: &[&solana_sdk::message::v0::MessageAddressTableLookup],
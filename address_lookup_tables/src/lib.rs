@@ -1 +1,2 @@
 pub mod address_lookup_table_store;
+pub mod resolved_block_stream;
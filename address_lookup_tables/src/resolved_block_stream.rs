@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use itertools::Itertools;
+use log::warn;
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_core::{
+    traits::address_lookup_table_interface::AddressLookupTableInterface, types::BlockStream,
+    AnyhowJoinHandle,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+lazy_static::lazy_static! {
+    static ref LRPC_ALT_RESOLVED_TRANSACTIONS: IntCounter =
+       register_int_counter!(opts!("literpc_alt_resolved_transactions", "Transactions whose accounts were resolved through address lookup tables")).unwrap();
+}
+
+/// The block stream with the accounts loaded from address lookup tables added to the writable and
+/// readable accounts of every transaction, so all consumers of the blocks see the complete account
+/// lists instead of each resolving them on its own.
+pub fn create_resolved_block_stream(
+    mut block_stream: BlockStream,
+    address_lookup_tables: Arc<dyn AddressLookupTableInterface>,
+) -> (BlockStream, AnyhowJoinHandle) {
+    let (resolved_sender, resolved_stream) = tokio::sync::broadcast::channel(64);
+    let jh = tokio::spawn(async move {
+        loop {
+            let mut block = match block_stream.recv().await {
+                Ok(block) => block,
+                Err(RecvError::Lagged(lagged)) => {
+                    warn!("resolved block stream lagged by {lagged} blocks - continue");
+                    continue;
+                }
+                Err(RecvError::Closed) => anyhow::bail!("block stream closed"),
+            };
+
+            let lookups = block
+                .transactions
+                .iter()
+                .flat_map(|transaction| &transaction.address_lookup_tables)
+                .collect_vec();
+            if !lookups.is_empty() {
+                address_lookup_tables.reload_if_necessary(&lookups).await;
+                for transaction in &mut block.transactions {
+                    if transaction.address_lookup_tables.is_empty() {
+                        continue;
+                    }
+                    let (writable_accounts, readable_accounts) = address_lookup_tables
+                        .resolve_transaction_accounts(transaction)
+                        .await;
+                    transaction.writable_accounts = writable_accounts;
+                    transaction.readable_accounts = readable_accounts;
+                    LRPC_ALT_RESOLVED_TRANSACTIONS.inc();
+                }
+            }
+            // nobody listens yet
+            let _ = resolved_sender.send(block);
+        }
+    });
+    (resolved_stream, jh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use solana_lite_rpc_core::structures::produced_block::{
+        ProducedBlock, TransactionInfo, TransactionMessage,
+    };
+    use solana_sdk::{
+        commitment_config::CommitmentConfig, hash::Hash, message::v0::MessageAddressTableLookup,
+        pubkey::Pubkey, signature::Signature,
+    };
+
+    // every lookup table loads one writable and one readable address
+    struct FixedLookupTables {
+        writable: Pubkey,
+        readable: Pubkey,
+    }
+
+    #[async_trait]
+    impl AddressLookupTableInterface for FixedLookupTables {
+        async fn resolve_addresses_from_lookup_table(
+            &self,
+            _message_address_table_lookup: &MessageAddressTableLookup,
+        ) -> (Vec<Pubkey>, Vec<Pubkey>) {
+            (vec![self.writable], vec![self.readable])
+        }
+
+        async fn reload_if_necessary(
+            &self,
+            _message_address_table_lookups: &[&MessageAddressTableLookup],
+        ) {
+        }
+    }
+
+    fn transaction(
+        writable_accounts: Vec<Pubkey>,
+        address_lookup_tables: Vec<MessageAddressTableLookup>,
+    ) -> TransactionInfo {
+        TransactionInfo {
+            signature: Signature::new_unique(),
+            is_vote: false,
+            err: None,
+            cu_requested: None,
+            prioritization_fees: None,
+            cu_consumed: None,
+            recent_blockhash: Hash::new_unique(),
+            message: TransactionMessage::default(),
+            writable_accounts,
+            readable_accounts: vec![],
+            address_lookup_tables,
+            program_ids: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocks_carry_the_accounts_of_their_lookup_tables() {
+        let lookup_tables = FixedLookupTables {
+            writable: Pubkey::new_unique(),
+            readable: Pubkey::new_unique(),
+        };
+        let (writable, readable) = (lookup_tables.writable, lookup_tables.readable);
+        let (block_sender, block_stream) = tokio::sync::broadcast::channel(4);
+        let (mut resolved_stream, _jh) =
+            create_resolved_block_stream(block_stream, Arc::new(lookup_tables));
+
+        let payer = Pubkey::new_unique();
+        let lookup = MessageAddressTableLookup {
+            account_key: Pubkey::new_unique(),
+            writable_indexes: vec![0],
+            readonly_indexes: vec![1],
+        };
+        block_sender
+            .send(ProducedBlock {
+                transactions: vec![
+                    transaction(vec![payer], vec![lookup.clone()]),
+                    // the source already reported the loaded address
+                    transaction(vec![payer, writable], vec![lookup]),
+                    transaction(vec![payer], vec![]),
+                ],
+                block_height: 42,
+                ..ProducedBlock::new_for_tests(100, CommitmentConfig::processed())
+            })
+            .unwrap();
+
+        let block = resolved_stream.recv().await.unwrap();
+        assert_eq!(
+            block.transactions[0].writable_accounts,
+            vec![payer, writable]
+        );
+        assert_eq!(block.transactions[0].readable_accounts, vec![readable]);
+        assert_eq!(
+            block.transactions[1].writable_accounts,
+            vec![payer, writable]
+        );
+        assert_eq!(block.transactions[2].writable_accounts, vec![payer]);
+        assert!(block.transactions[2].readable_accounts.is_empty());
+    }
+}
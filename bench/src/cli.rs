@@ -1,5 +1,7 @@
 use clap::{command, Parser};
 
+use crate::{endpoints::EndpointMode, tx_mix::TxMix};
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
@@ -23,4 +25,76 @@ pub struct Args {
     // choose between small (179 bytes) and large (1186 bytes) transactions
     #[arg(short = 'L', long, default_value_t = false)]
     pub large_transactions: bool,
+    /// Weighted mix of memo, transfer and spl-transfer transactions, e.g. memo=2,transfer=1,spl-transfer=1
+    #[arg(short = 'x', long, default_value = "memo")]
+    pub tx_mix: TxMix,
+    /// Compute unit price in micro lamports added to every transaction
+    #[arg(long)]
+    pub compute_unit_price: Option<u64>,
+    /// Compute unit limit added to every transaction
+    #[arg(long)]
+    pub compute_unit_limit: Option<u32>,
+    /// Latency histogram output file, json or csv by extension, empty to skip
+    #[arg(long, default_value_t = String::from("latency.json"))]
+    pub latency_file: String,
+    /// Reference rpc, every other transaction is sent through it to compare it with lite-rpc
+    #[arg(short = 'c', long)]
+    pub compare_rpc_addr: Option<String>,
+    /// Comparison output file
+    #[arg(long, default_value_t = String::from("comparison.json"))]
+    pub comparison_file: String,
+    /// Sends an open loop load at this rate through lite-rpc instead of the fixed count runs
+    #[arg(long)]
+    pub target_tps: Option<u64>,
+    /// Duration of the load (s)
+    #[arg(long, default_value_t = 300)]
+    pub duration_secs: u64,
+    /// Time to ramp the load up linearly to the target tps (s)
+    #[arg(long, default_value_t = 0)]
+    pub ramp_up_secs: u64,
+    /// Interval of the progress output of the load (s)
+    #[arg(long, default_value_t = 10)]
+    pub progress_interval_secs: u64,
+    /// Aborts the load when the share of failed sends and lost transactions of a progress interval is higher
+    #[arg(long, default_value_t = 0.5)]
+    pub max_error_rate: f64,
+    /// Compute unit prices to run the bench at one after the other, e.g. 0,1000,10000,100000
+    #[arg(long, value_delimiter = ',')]
+    pub fee_sweep: Vec<u64>,
+    /// Fee sweep output file, json or csv by extension
+    #[arg(long, default_value_t = String::from("fee_sweep.json"))]
+    pub fee_sweep_file: String,
+    /// Rpc endpoints to send through in one run, statuses are polled from lite-rpc
+    #[arg(short = 'e', long, value_delimiter = ',')]
+    pub endpoints: Vec<String>,
+    /// Whether every transaction goes to one of the endpoints or to all of them
+    #[arg(long, value_enum, default_value_t = EndpointMode::Distribute)]
+    pub endpoint_mode: EndpointMode,
+    /// Endpoints output file
+    #[arg(long, default_value_t = String::from("endpoints.json"))]
+    pub endpoints_file: String,
+    /// Uploads the results of the runs and their transactions to this postgres (libpq style config)
+    #[arg(long, env = "BENCH_PG_CONFIG")]
+    pub pg_config: Option<String>,
+    /// Label of the uploaded results, e.g. the cluster conditions
+    #[arg(long)]
+    pub pg_label: Option<String>,
+    /// Measures the notification latency of this websocket or gRPC source against --reference-source instead of sending
+    #[arg(long)]
+    pub subscription_source: Option<String>,
+    /// Websocket or gRPC source the notifications are compared to
+    #[arg(long)]
+    pub reference_source: Option<String>,
+    /// x-token of gRPC subscription sources
+    #[arg(long, env = "GRPC_X_TOKEN")]
+    pub grpc_x_token: Option<String>,
+    /// Accounts to subscribe to besides the slots
+    #[arg(long, value_delimiter = ',')]
+    pub subscribe_accounts: Vec<String>,
+    /// Duration of the subscription measurement (s)
+    #[arg(long, default_value_t = 60)]
+    pub subscription_duration_secs: u64,
+    /// Subscription latency output file
+    #[arg(long, default_value_t = String::from("subscriptions.json"))]
+    pub subscriptions_file: String,
 }
@@ -0,0 +1,185 @@
+use std::{fs::File, path::Path, time::Duration};
+
+use anyhow::Context;
+use log::info;
+
+use crate::latency::LatencyHistogram;
+
+/// the endpoint a transaction was sent through
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SendTarget {
+    #[default]
+    LiteRpc,
+    Reference,
+}
+
+impl SendTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SendTarget::LiteRpc => "lite-rpc",
+            SendTarget::Reference => "reference",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EndpointStats {
+    sent: u64,
+    confirmed: u64,
+    latencies: LatencyHistogram,
+}
+
+impl EndpointStats {
+    fn merge(&mut self, other: &EndpointStats) {
+        self.sent += other.sent;
+        self.confirmed += other.confirmed;
+        self.latencies.merge(&other.latencies);
+    }
+
+    fn report(&self) -> EndpointReport {
+        let summary = self.latencies.summary("");
+        EndpointReport {
+            sent: self.sent,
+            confirmed: self.confirmed,
+            landing_rate: if self.sent > 0 {
+                self.confirmed as f64 / self.sent as f64
+            } else {
+                0.0
+            },
+            mean_ms: summary.mean_ms,
+            p50_ms: summary.p50_ms,
+            p90_ms: summary.p90_ms,
+            p99_ms: summary.p99_ms,
+        }
+    }
+}
+
+/// transactions of a run sent alternately through lite-rpc and a reference rpc
+#[derive(Clone, Debug, Default)]
+pub struct Comparison {
+    lite_rpc: EndpointStats,
+    reference: EndpointStats,
+}
+
+impl Comparison {
+    fn stats_mut(&mut self, target: SendTarget) -> &mut EndpointStats {
+        match target {
+            SendTarget::LiteRpc => &mut self.lite_rpc,
+            SendTarget::Reference => &mut self.reference,
+        }
+    }
+
+    pub fn add_confirmed(&mut self, target: SendTarget, time_to_confirm: Duration) {
+        let stats = self.stats_mut(target);
+        stats.sent += 1;
+        stats.confirmed += 1;
+        stats.latencies.add(time_to_confirm);
+    }
+
+    pub fn add_unconfirmed(&mut self, target: SendTarget) {
+        self.stats_mut(target).sent += 1;
+    }
+
+    pub fn merge(&mut self, other: &Comparison) {
+        self.lite_rpc.merge(&other.lite_rpc);
+        self.reference.merge(&other.reference);
+    }
+
+    pub fn report(&self, reference_rpc_addr: &str) -> ComparisonReport {
+        let lite_rpc = self.lite_rpc.report();
+        let reference = self.reference.report();
+        ComparisonReport {
+            reference_rpc_addr: reference_rpc_addr.to_string(),
+            delta: EndpointDelta {
+                landing_rate: lite_rpc.landing_rate - reference.landing_rate,
+                mean_ms: lite_rpc.mean_ms - reference.mean_ms,
+                p50_ms: lite_rpc.p50_ms - reference.p50_ms,
+                p90_ms: lite_rpc.p90_ms - reference.p90_ms,
+                p99_ms: lite_rpc.p99_ms - reference.p99_ms,
+            },
+            lite_rpc,
+            reference,
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct EndpointReport {
+    pub sent: u64,
+    pub confirmed: u64,
+    pub landing_rate: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+}
+
+/// lite-rpc minus reference, a negative latency delta means lite-rpc confirmed faster
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct EndpointDelta {
+    pub landing_rate: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ComparisonReport {
+    pub reference_rpc_addr: String,
+    pub lite_rpc: EndpointReport,
+    pub reference: EndpointReport,
+    pub delta: EndpointDelta,
+}
+
+impl ComparisonReport {
+    pub fn log(&self) {
+        for (name, report) in [("lite-rpc", &self.lite_rpc), ("reference", &self.reference)] {
+            info!(
+                "{name}: {} of {} tx(s) confirmed ({:.1}%), p50 {}ms, p90 {}ms, p99 {}ms",
+                report.confirmed,
+                report.sent,
+                report.landing_rate * 100.0,
+                report.p50_ms,
+                report.p90_ms,
+                report.p99_ms
+            );
+        }
+        info!(
+            "lite-rpc vs reference: landing rate {:+.1}%, p50 {:+}ms, p90 {:+}ms, p99 {:+}ms",
+            self.delta.landing_rate * 100.0,
+            self.delta.p50_ms,
+            self.delta.p90_ms,
+            self.delta.p99_ms
+        );
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("creating comparison report {}", path.display()))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn comparison_deltas() {
+    let mut comparison = Comparison::default();
+    for ms in [100, 200, 300] {
+        comparison.add_confirmed(SendTarget::LiteRpc, Duration::from_millis(ms));
+    }
+    comparison.add_unconfirmed(SendTarget::LiteRpc);
+    for ms in [400, 500] {
+        comparison.add_confirmed(SendTarget::Reference, Duration::from_millis(ms));
+    }
+    comparison.add_unconfirmed(SendTarget::Reference);
+    comparison.add_unconfirmed(SendTarget::Reference);
+
+    let report = comparison.report("http://reference");
+    assert_eq!(report.lite_rpc.landing_rate, 0.75);
+    assert_eq!(report.reference.landing_rate, 0.5);
+    assert_eq!(report.delta.landing_rate, 0.25);
+    assert_eq!(report.delta.p50_ms, 200.0 - 400.0);
+}
@@ -0,0 +1,259 @@
+use std::{fs::File, path::Path, sync::Arc};
+
+use anyhow::Context;
+use dashmap::DashMap;
+use futures::future::join_all;
+use log::{info, warn};
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::config::RpcSendTransactionConfig;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, signature::Keypair, signature::Signature,
+    transaction::Transaction,
+};
+use tokio::{
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    helpers::BenchHelper,
+    latency::LatencyHistogram,
+    tx_mix::{TransactionSize, TxGenerator},
+};
+
+// transactions not confirmed after this count as lost
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+// getSignatureStatuses takes up to 256 signatures
+const MAX_SIGNATURES_PER_REQUEST: usize = 256;
+
+/// how the transactions are spread over the endpoints
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EndpointMode {
+    /// every transaction goes to one endpoint, round robin
+    Distribute,
+    /// every endpoint gets its own transaction at the same time
+    Duplicate,
+}
+
+pub struct Endpoint {
+    pub url: String,
+    rpc_client: RpcClient,
+}
+
+impl Endpoint {
+    pub fn new(url: String) -> Self {
+        Self {
+            rpc_client: RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed()),
+            url,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct EndpointStats {
+    sent: u64,
+    send_errors: u64,
+    total_send_time: Duration,
+}
+
+struct SentTx {
+    sent_instant: Instant,
+    // endpoint which accepted the transaction
+    endpoint: usize,
+}
+
+/// per endpoint results of a run, in duplicate mode every endpoint is sent a distinct transaction so
+/// the landing rate and latency of an endpoint only count its own transactions
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct EndpointsReport {
+    pub mode: EndpointMode,
+    pub endpoints: Vec<EndpointResult>,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct EndpointResult {
+    pub url: String,
+    pub sent: u64,
+    pub send_errors: u64,
+    pub mean_send_ms: f64,
+    pub confirmed: u64,
+    pub landing_rate: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl EndpointsReport {
+    pub fn log(&self) {
+        for endpoint in &self.endpoints {
+            info!(
+                "{}: {} of {} tx(s) confirmed ({:.1}%), {} send errors, send {:.1}ms, p50 {}ms, p90 {}ms, p99 {}ms",
+                endpoint.url,
+                endpoint.confirmed,
+                endpoint.sent,
+                endpoint.landing_rate * 100.0,
+                endpoint.send_errors,
+                endpoint.mean_send_ms,
+                endpoint.p50_ms,
+                endpoint.p90_ms,
+                endpoint.p99_ms
+            );
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("creating endpoints report {}", path.display()))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+/// sends the transactions through the endpoints and polls their statuses from the status rpc
+/// meanwhile
+#[allow(clippy::too_many_arguments)]
+pub async fn bench_endpoints(
+    endpoints: Vec<Endpoint>,
+    mode: EndpointMode,
+    status_rpc_client: Arc<RpcClient>,
+    tx_count: usize,
+    funded_payer: Keypair,
+    block_hash: Arc<RwLock<Hash>>,
+    mut tx_generator: TxGenerator,
+    transaction_size: TransactionSize,
+) -> EndpointsReport {
+    let endpoints = Arc::new(endpoints);
+    let sent_txs: Arc<DashMap<Signature, SentTx>> = Arc::new(DashMap::new());
+
+    // transaction sender task
+    let sender = {
+        let endpoints = endpoints.clone();
+        let sent_txs = sent_txs.clone();
+        tokio::spawn(async move {
+            let mut stats = vec![EndpointStats::default(); endpoints.len()];
+            let config = RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..RpcSendTransactionConfig::default()
+            };
+            let txs_per_round = match mode {
+                EndpointMode::Distribute => 1,
+                EndpointMode::Duplicate => endpoints.len(),
+            };
+            let rand_strings = BenchHelper::generate_random_strings(
+                tx_count * txs_per_round,
+                Some(0),
+                transaction_size.memo_chars(),
+            );
+            for (i, round_strings) in rand_strings.chunks(txs_per_round).enumerate() {
+                let blockhash = { *block_hash.read().await };
+                let targets: Vec<(usize, Transaction)> = round_strings
+                    .iter()
+                    .enumerate()
+                    .map(|(j, rand_string)| {
+                        let target = match mode {
+                            EndpointMode::Distribute => i % endpoints.len(),
+                            EndpointMode::Duplicate => j,
+                        };
+                        let (_, tx) = tx_generator.create(rand_string, &funded_payer, blockhash);
+                        (target, tx)
+                    })
+                    .collect();
+                let results = join_all(targets.into_iter().map(|(target, tx)| {
+                    let endpoint = &endpoints[target];
+                    async move {
+                        let start_time = Instant::now();
+                        let result = endpoint
+                            .rpc_client
+                            .send_transaction_with_config(&tx, config)
+                            .await;
+                        // every send is timed on its own, not after the slowest endpoint
+                        let sent_instant = Instant::now();
+                        (target, tx, sent_instant - start_time, sent_instant, result)
+                    }
+                }))
+                .await;
+
+                for (target, tx, send_time, sent_instant, result) in results {
+                    let endpoint_stats = &mut stats[target];
+                    endpoint_stats.sent += 1;
+                    endpoint_stats.total_send_time += send_time;
+                    match result {
+                        Ok(_) => {
+                            sent_txs.insert(
+                                tx.signatures[0],
+                                SentTx {
+                                    sent_instant,
+                                    endpoint: target,
+                                },
+                            );
+                        }
+                        Err(e) => {
+                            endpoint_stats.send_errors += 1;
+                            warn!(
+                                "tx send to {} failed with error {}",
+                                endpoints[target].url, e
+                            );
+                        }
+                    }
+                }
+            }
+            stats
+        })
+    };
+
+    let mut confirmations = vec![LatencyHistogram::default(); endpoints.len()];
+    while !(sender.is_finished() && sent_txs.is_empty()) {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let signatures = sent_txs.iter().map(|x| *x.key()).collect::<Vec<_>>();
+        for chunk in signatures.chunks(MAX_SIGNATURES_PER_REQUEST) {
+            let Ok(statuses) = status_rpc_client.get_signature_statuses(chunk).await else {
+                continue;
+            };
+            for (signature, status) in chunk.iter().zip(statuses.value) {
+                let Some((_, tx)) = sent_txs.remove_if(signature, |_, tx| {
+                    status.is_some() || tx.sent_instant.elapsed() > CONFIRMATION_TIMEOUT
+                }) else {
+                    continue;
+                };
+                if status.is_some() {
+                    confirmations[tx.endpoint].add(tx.sent_instant.elapsed());
+                }
+            }
+        }
+    }
+    let stats = sender.await.expect("sender task must succeed");
+
+    EndpointsReport {
+        mode,
+        endpoints: endpoints
+            .iter()
+            .zip(stats)
+            .zip(confirmations)
+            .map(|((endpoint, stats), latencies)| {
+                let summary = latencies.summary(endpoint.url.clone());
+                let confirmed = latencies.len() as u64;
+                EndpointResult {
+                    url: endpoint.url.clone(),
+                    sent: stats.sent,
+                    send_errors: stats.send_errors,
+                    mean_send_ms: if stats.sent > 0 {
+                        stats.total_send_time.as_secs_f64() * 1_000.0 / stats.sent as f64
+                    } else {
+                        0.0
+                    },
+                    confirmed,
+                    landing_rate: if stats.sent > 0 {
+                        confirmed as f64 / stats.sent as f64
+                    } else {
+                        0.0
+                    },
+                    p50_ms: summary.p50_ms,
+                    p90_ms: summary.p90_ms,
+                    p99_ms: summary.p99_ms,
+                }
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,92 @@
+use std::{collections::HashMap, fs::File, path::Path};
+
+use anyhow::Context;
+use log::info;
+
+use crate::{latency::LatencyHistogram, metrics::Metric, tx_mix::TxKind};
+
+/// landing rate and confirmation latency of the run at one compute unit price
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct FeeLevelReport {
+    /// micro lamports per compute unit
+    pub compute_unit_price: u64,
+    pub sent: u64,
+    pub confirmed: u64,
+    pub landing_rate: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl FeeLevelReport {
+    pub fn new(
+        compute_unit_price: u64,
+        metric: &Metric,
+        latencies: &HashMap<TxKind, LatencyHistogram>,
+    ) -> Self {
+        let mut histogram = LatencyHistogram::default();
+        for kind_histogram in latencies.values() {
+            histogram.merge(kind_histogram);
+        }
+        let summary = histogram.summary(compute_unit_price.to_string());
+        Self {
+            compute_unit_price,
+            sent: metric.txs_sent,
+            confirmed: metric.txs_confirmed,
+            landing_rate: if metric.txs_sent > 0 {
+                metric.txs_confirmed as f64 / metric.txs_sent as f64
+            } else {
+                0.0
+            },
+            mean_ms: summary.mean_ms,
+            p50_ms: summary.p50_ms,
+            p90_ms: summary.p90_ms,
+            p99_ms: summary.p99_ms,
+        }
+    }
+}
+
+/// runs at increasing compute unit prices, one after the other
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct FeeSweepReport {
+    pub compute_unit_limit: Option<u32>,
+    pub levels: Vec<FeeLevelReport>,
+}
+
+impl FeeSweepReport {
+    pub fn log(&self) {
+        for level in &self.levels {
+            info!(
+                "compute unit price {}: {} of {} tx(s) confirmed ({:.1}%), p50 {}ms, p90 {}ms, p99 {}ms",
+                level.compute_unit_price,
+                level.confirmed,
+                level.sent,
+                level.landing_rate * 100.0,
+                level.p50_ms,
+                level.p90_ms,
+                level.p99_ms
+            );
+        }
+    }
+
+    /// writes json, or csv with a row per level if the file name ends with `.csv`
+    pub fn write(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "csv")
+        {
+            let mut writer = csv::Writer::from_path(path)?;
+            for level in &self.levels {
+                writer.serialize(level)?;
+            }
+            writer.flush()?;
+        } else {
+            let file = File::create(path)
+                .with_context(|| format!("creating fee sweep report {}", path.display()))?;
+            serde_json::to_writer_pretty(file, self)?;
+        }
+        Ok(())
+    }
+}
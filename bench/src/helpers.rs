@@ -107,10 +107,22 @@ impl BenchHelper {
             .collect()
     }
 
-    pub fn create_memo_tx_small(msg: &[u8], payer: &Keypair, blockhash: Hash) -> Transaction {
+    /// memo instruction signed by the signers besides the payer
+    pub fn memo_instruction(msg: &[u8], signers: &[Keypair]) -> Instruction {
         let memo = Pubkey::from_str(MEMO_PROGRAM_ID).unwrap();
 
-        let instruction = Instruction::new_with_bytes(memo, msg, vec![]);
+        Instruction::new_with_bytes(
+            memo,
+            msg,
+            signers
+                .iter()
+                .map(|keypair| AccountMeta::new_readonly(keypair.pubkey(), true))
+                .collect_vec(),
+        )
+    }
+
+    pub fn create_memo_tx_small(msg: &[u8], payer: &Keypair, blockhash: Hash) -> Transaction {
+        let instruction = Self::memo_instruction(msg, &[]);
         let message = Message::new(&[instruction], Some(&payer.pubkey()));
         Transaction::new(&[payer], message, blockhash)
     }
@@ -118,16 +130,7 @@ impl BenchHelper {
     pub fn create_memo_tx_large(msg: &[u8], payer: &Keypair, blockhash: Hash) -> Transaction {
         let accounts = (0..8).map(|_| Keypair::new()).collect_vec();
 
-        let memo = Pubkey::from_str(MEMO_PROGRAM_ID).unwrap();
-
-        let instruction = Instruction::new_with_bytes(
-            memo,
-            msg,
-            accounts
-                .iter()
-                .map(|keypair| AccountMeta::new_readonly(keypair.pubkey(), true))
-                .collect_vec(),
-        );
+        let instruction = Self::memo_instruction(msg, &accounts);
         let message = Message::new(&[instruction], Some(&payer.pubkey()));
 
         let mut signers = vec![payer];
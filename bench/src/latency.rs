@@ -0,0 +1,257 @@
+use std::{collections::HashMap, fs::File, path::Path, time::Duration};
+
+use anyhow::Context;
+
+use crate::tx_mix::TxKind;
+
+// upper bounds of the histogram buckets in ms, fixed so histograms of different runs and versions
+// line up, the last bucket holds everything slower
+const BUCKET_BOUNDS_MS: [u64; 16] = [
+    50, 100, 200, 300, 400, 500, 750, 1_000, 1_500, 2_000, 3_000, 5_000, 10_000, 20_000, 30_000,
+    60_000,
+];
+
+/// send to confirmed latencies of a bench run, every sample is kept for exact percentiles
+#[derive(Clone, Debug, Default)]
+pub struct LatencyHistogram {
+    samples_us: Vec<u64>,
+}
+
+impl LatencyHistogram {
+    pub fn add(&mut self, latency: Duration) {
+        self.samples_us.push(latency.as_micros() as u64);
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        self.samples_us.extend_from_slice(&other.samples_us);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples_us.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples_us.is_empty()
+    }
+
+    pub fn summary(&self, label: impl Into<String>) -> LatencySummary {
+        let mut samples = self.samples_us.clone();
+        samples.sort_unstable();
+        // nearest rank percentile
+        let percentile = |p: f64| -> f64 {
+            if samples.is_empty() {
+                return 0.0;
+            }
+            let rank = ((p / 100.0) * samples.len() as f64).ceil() as usize;
+            samples[rank.clamp(1, samples.len()) - 1] as f64 / 1_000.0
+        };
+
+        let mut buckets: Vec<LatencyBucket> = BUCKET_BOUNDS_MS
+            .iter()
+            .map(|upper_bound_ms| LatencyBucket {
+                upper_bound_ms: Some(*upper_bound_ms),
+                count: 0,
+            })
+            .collect();
+        buckets.push(LatencyBucket {
+            upper_bound_ms: None,
+            count: 0,
+        });
+        for sample in &samples {
+            let bucket = BUCKET_BOUNDS_MS
+                .iter()
+                .position(|upper_bound_ms| *sample <= upper_bound_ms * 1_000)
+                .unwrap_or(BUCKET_BOUNDS_MS.len());
+            buckets[bucket].count += 1;
+        }
+
+        let mean_ms = if samples.is_empty() {
+            0.0
+        } else {
+            samples.iter().sum::<u64>() as f64 / samples.len() as f64 / 1_000.0
+        };
+        LatencySummary {
+            label: label.into(),
+            count: samples.len() as u64,
+            min_ms: percentile(0.0),
+            mean_ms,
+            p50_ms: percentile(50.0),
+            p75_ms: percentile(75.0),
+            p90_ms: percentile(90.0),
+            p95_ms: percentile(95.0),
+            p99_ms: percentile(99.0),
+            max_ms: percentile(100.0),
+            buckets,
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct LatencyBucket {
+    /// inclusive, none for the bucket of everything slower than the last bound
+    pub upper_bound_ms: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct LatencySummary {
+    pub label: String,
+    pub count: u64,
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p75_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    pub buckets: Vec<LatencyBucket>,
+}
+
+/// latencies of every run, of every transaction kind and of the whole bench
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct LatencyReport {
+    /// version of the rpc the bench ran against
+    pub rpc_version: Option<String>,
+    pub total: LatencySummary,
+    pub runs: Vec<LatencySummary>,
+    pub tx_kinds: Vec<LatencySummary>,
+}
+
+impl LatencyReport {
+    pub fn new(rpc_version: Option<String>, runs: &[HashMap<TxKind, LatencyHistogram>]) -> Self {
+        let mut total = LatencyHistogram::default();
+        let mut by_kind: HashMap<TxKind, LatencyHistogram> = HashMap::new();
+        let mut run_summaries = vec![];
+        for (run, histograms) in runs.iter().enumerate() {
+            let mut run_histogram = LatencyHistogram::default();
+            for (kind, histogram) in histograms {
+                run_histogram.merge(histogram);
+                by_kind.entry(*kind).or_default().merge(histogram);
+            }
+            total.merge(&run_histogram);
+            run_summaries.push(run_histogram.summary(format!("run {}", run + 1)));
+        }
+        let mut tx_kinds: Vec<LatencySummary> = by_kind
+            .into_iter()
+            .map(|(kind, histogram)| histogram.summary(format!("{kind:?}")))
+            .collect();
+        tx_kinds.sort_by(|a, b| a.label.cmp(&b.label));
+
+        Self {
+            rpc_version,
+            total: total.summary("total"),
+            runs: run_summaries,
+            tx_kinds,
+        }
+    }
+
+    /// writes json, or csv if the file name ends with `.csv`: the percentiles to the file and the
+    /// buckets to a `-buckets.csv` file next to it
+    pub fn write(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "csv")
+        {
+            self.write_csv(path)
+        } else {
+            let file = File::create(path)
+                .with_context(|| format!("creating latency report {}", path.display()))?;
+            serde_json::to_writer_pretty(file, self)?;
+            Ok(())
+        }
+    }
+
+    fn summaries(&self) -> impl Iterator<Item = &LatencySummary> {
+        std::iter::once(&self.total)
+            .chain(self.runs.iter())
+            .chain(self.tx_kinds.iter())
+    }
+
+    fn write_csv(&self, path: &Path) -> anyhow::Result<()> {
+        #[derive(serde::Serialize)]
+        struct PercentileRow<'a> {
+            rpc_version: &'a str,
+            label: &'a str,
+            count: u64,
+            min_ms: f64,
+            mean_ms: f64,
+            p50_ms: f64,
+            p75_ms: f64,
+            p90_ms: f64,
+            p95_ms: f64,
+            p99_ms: f64,
+            max_ms: f64,
+        }
+        #[derive(serde::Serialize)]
+        struct BucketRow<'a> {
+            rpc_version: &'a str,
+            label: &'a str,
+            upper_bound_ms: Option<u64>,
+            count: u64,
+        }
+
+        let rpc_version = self.rpc_version.as_deref().unwrap_or_default();
+        let mut percentiles = csv::Writer::from_path(path)?;
+        for summary in self.summaries() {
+            percentiles.serialize(PercentileRow {
+                rpc_version,
+                label: &summary.label,
+                count: summary.count,
+                min_ms: summary.min_ms,
+                mean_ms: summary.mean_ms,
+                p50_ms: summary.p50_ms,
+                p75_ms: summary.p75_ms,
+                p90_ms: summary.p90_ms,
+                p95_ms: summary.p95_ms,
+                p99_ms: summary.p99_ms,
+                max_ms: summary.max_ms,
+            })?;
+        }
+        percentiles.flush()?;
+
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut buckets =
+            csv::Writer::from_path(path.with_file_name(format!("{stem}-buckets.csv")))?;
+        for summary in self.summaries() {
+            for bucket in &summary.buckets {
+                buckets.serialize(BucketRow {
+                    rpc_version,
+                    label: &summary.label,
+                    upper_bound_ms: bucket.upper_bound_ms,
+                    count: bucket.count,
+                })?;
+            }
+        }
+        buckets.flush()?;
+        Ok(())
+    }
+}
+
+#[test]
+fn latency_percentiles_and_buckets() {
+    let mut histogram = LatencyHistogram::default();
+    for ms in 1..=100 {
+        histogram.add(Duration::from_millis(ms * 10));
+    }
+    histogram.add(Duration::from_secs(90));
+
+    let summary = histogram.summary("test");
+    assert_eq!(summary.count, 101);
+    assert_eq!(summary.min_ms, 10.0);
+    assert_eq!(summary.p50_ms, 510.0);
+    assert_eq!(summary.p99_ms, 1_000.0);
+    assert_eq!(summary.max_ms, 90_000.0);
+    // 10..=50ms
+    assert_eq!(summary.buckets[0].count, 5);
+    assert_eq!(summary.buckets.last().unwrap().count, 1);
+    assert_eq!(
+        summary
+            .buckets
+            .iter()
+            .map(|bucket| bucket.count)
+            .sum::<u64>(),
+        101
+    );
+}
@@ -1,3 +1,11 @@
 pub mod cli;
+pub mod compare;
+pub mod endpoints;
+pub mod fee_sweep;
 pub mod helpers;
+pub mod latency;
+pub mod load;
 pub mod metrics;
+pub mod postgres;
+pub mod subscriptions;
+pub mod tx_mix;
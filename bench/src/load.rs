@@ -0,0 +1,302 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use dashmap::DashMap;
+use log::{info, warn};
+use rand::{distributions::Alphanumeric, prelude::Distribution, SeedableRng};
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{hash::Hash, signature::Keypair, signature::Signature};
+use tokio::{
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    latency::LatencyHistogram,
+    metrics::Metric,
+    tx_mix::{TransactionSize, TxGenerator, TxKind},
+};
+
+// transactions not confirmed after this count as lost
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+// getSignatureStatuses takes up to 256 signatures
+const MAX_SIGNATURES_PER_REQUEST: usize = 256;
+// error rates of windows with fewer transactions are too noisy to abort on
+const MIN_TXS_FOR_ERROR_RATE: u64 = 100;
+
+/// open loop load: transactions are sent at the target rate whether or not earlier ones were
+/// answered, ramping up linearly from zero
+#[derive(Clone, Debug)]
+pub struct LoadConfig {
+    pub target_tps: u64,
+    pub duration: Duration,
+    pub ramp_up: Duration,
+    pub progress_interval: Duration,
+    /// aborts once the share of failed sends and lost transactions of a progress interval is higher
+    pub max_error_rate: f64,
+}
+
+impl LoadConfig {
+    /// transactions which should have been sent after the elapsed time
+    fn scheduled(&self, elapsed: Duration) -> u64 {
+        let target_tps = self.target_tps as f64;
+        let elapsed = elapsed.min(self.duration).as_secs_f64();
+        let ramp_up = self.ramp_up.min(self.duration).as_secs_f64();
+        if elapsed < ramp_up {
+            // area under the ramp
+            (target_tps * elapsed * elapsed / (2.0 * ramp_up)) as u64
+        } else {
+            (target_tps * (ramp_up / 2.0 + elapsed - ramp_up)) as u64
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct PendingTx {
+    sent_duration: Duration,
+    sent_instant: Instant,
+    transaction_bytes: u64,
+    kind: TxKind,
+}
+
+#[derive(Default)]
+struct Counters {
+    sent: AtomicU64,
+    send_errors: AtomicU64,
+    confirmed: AtomicU64,
+    lost: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+struct CounterSnapshot {
+    sent: u64,
+    send_errors: u64,
+    confirmed: u64,
+    lost: u64,
+}
+
+impl Counters {
+    fn snapshot(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            sent: self.sent.load(Ordering::Relaxed),
+            send_errors: self.send_errors.load(Ordering::Relaxed),
+            confirmed: self.confirmed.load(Ordering::Relaxed),
+            lost: self.lost.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub struct LoadResult {
+    pub metric: Metric,
+    pub latencies: HashMap<TxKind, LatencyHistogram>,
+    /// the load stopped early because of the error rate
+    pub aborted: bool,
+}
+
+#[derive(Default)]
+struct Confirmations {
+    metric: Metric,
+    latencies: HashMap<TxKind, LatencyHistogram>,
+}
+
+/// sends the load through lite-rpc and tracks the confirmations until every transaction is
+/// confirmed or lost
+pub async fn run_load(
+    config: LoadConfig,
+    rpc_client: Arc<RpcClient>,
+    funded_payer: Keypair,
+    block_hash: Arc<RwLock<Hash>>,
+    mut tx_generator: TxGenerator,
+    transaction_size: TransactionSize,
+) -> LoadResult {
+    info!(
+        "Sending {} tps for {:?} with a ramp up of {:?}",
+        config.target_tps, config.duration, config.ramp_up
+    );
+    let pending: Arc<DashMap<Signature, PendingTx>> = Arc::new(DashMap::new());
+    let counters = Arc::new(Counters::default());
+    let confirmations = Arc::new(std::sync::Mutex::new(Confirmations::default()));
+
+    let confirmation_task = tokio::spawn(track_confirmations(
+        rpc_client.clone(),
+        pending.clone(),
+        counters.clone(),
+        confirmations.clone(),
+    ));
+
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+    let started = Instant::now();
+    let mut scheduled_sent = 0;
+    let mut tick = tokio::time::interval(Duration::from_millis(10));
+    let mut last_progress = started;
+    let mut last_snapshot = CounterSnapshot::default();
+    let mut aborted = false;
+    while started.elapsed() < config.duration {
+        tick.tick().await;
+        let scheduled = config.scheduled(started.elapsed());
+        while scheduled_sent < scheduled {
+            scheduled_sent += 1;
+            let memo: Vec<u8> = Alphanumeric
+                .sample_iter(&mut rng)
+                .take(transaction_size.memo_chars())
+                .collect();
+            let blockhash = { *block_hash.read().await };
+            let (kind, tx) = tx_generator.create(&memo, &funded_payer, blockhash);
+            let rpc_client = rpc_client.clone();
+            let pending = pending.clone();
+            let counters = counters.clone();
+            // open loop, a slow answer does not hold back the next transaction
+            tokio::spawn(async move {
+                let start_time = Instant::now();
+                match rpc_client.send_transaction(&tx).await {
+                    Ok(signature) => {
+                        counters.sent.fetch_add(1, Ordering::Relaxed);
+                        pending.insert(
+                            signature,
+                            PendingTx {
+                                sent_duration: start_time.elapsed(),
+                                sent_instant: Instant::now(),
+                                transaction_bytes: bincode::serialized_size(&tx).unwrap(),
+                                kind,
+                            },
+                        );
+                    }
+                    Err(e) => {
+                        counters.send_errors.fetch_add(1, Ordering::Relaxed);
+                        warn!("tx send failed with error {}", e);
+                    }
+                }
+            });
+        }
+
+        if last_progress.elapsed() >= config.progress_interval {
+            let snapshot = counters.snapshot();
+            let window_secs = last_progress.elapsed().as_secs_f64();
+            let sent = snapshot.sent - last_snapshot.sent;
+            let send_errors = snapshot.send_errors - last_snapshot.send_errors;
+            let confirmed = snapshot.confirmed - last_snapshot.confirmed;
+            let lost = snapshot.lost - last_snapshot.lost;
+            info!(
+                "{:?}: sent {:.0} tps, confirmed {:.0} tps, {} send errors, {} lost, {} pending",
+                started.elapsed(),
+                sent as f64 / window_secs,
+                confirmed as f64 / window_secs,
+                send_errors,
+                lost,
+                pending.len()
+            );
+
+            let attempts = sent + send_errors;
+            if attempts >= MIN_TXS_FOR_ERROR_RATE {
+                let error_rate = (send_errors + lost) as f64 / attempts as f64;
+                if error_rate > config.max_error_rate {
+                    warn!(
+                        "Aborting the load, error rate {:.1}% is above {:.1}%",
+                        error_rate * 100.0,
+                        config.max_error_rate * 100.0
+                    );
+                    aborted = true;
+                    break;
+                }
+            }
+            last_progress = Instant::now();
+            last_snapshot = snapshot;
+        }
+    }
+    let gross_send_time = started.elapsed();
+
+    // sends still in flight insert into pending a little later
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    while !pending.is_empty() {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    confirmation_task.abort();
+
+    let snapshot = counters.snapshot();
+    info!(
+        "Load done after {:?}: {} sent, {} send errors, {} confirmed, {} lost",
+        gross_send_time, snapshot.sent, snapshot.send_errors, snapshot.confirmed, snapshot.lost
+    );
+    let Confirmations {
+        mut metric,
+        latencies,
+    } = std::mem::take(&mut *confirmations.lock().unwrap());
+    metric.set_total_gross_send_time(gross_send_time.as_micros() as f64 / 1_000.0);
+    metric.finalize();
+    LoadResult {
+        metric,
+        latencies,
+        aborted,
+    }
+}
+
+async fn track_confirmations(
+    rpc_client: Arc<RpcClient>,
+    pending: Arc<DashMap<Signature, PendingTx>>,
+    counters: Arc<Counters>,
+    confirmations: Arc<std::sync::Mutex<Confirmations>>,
+) {
+    loop {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let signatures = pending.iter().map(|x| *x.key()).collect::<Vec<_>>();
+        for chunk in signatures.chunks(MAX_SIGNATURES_PER_REQUEST) {
+            let Ok(statuses) = rpc_client.get_signature_statuses(chunk).await else {
+                continue;
+            };
+            for (signature, status) in chunk.iter().zip(statuses.value) {
+                let Some((_, tx)) = pending.remove_if(signature, |_, tx| {
+                    status.is_some() || tx.sent_instant.elapsed() > CONFIRMATION_TIMEOUT
+                }) else {
+                    continue;
+                };
+                let mut confirmations = confirmations.lock().unwrap();
+                if status.is_some() {
+                    let time_to_confirm = tx.sent_instant.elapsed();
+                    counters.confirmed.fetch_add(1, Ordering::Relaxed);
+                    confirmations.metric.add_successful_transaction(
+                        tx.sent_duration,
+                        time_to_confirm,
+                        tx.transaction_bytes,
+                    );
+                    confirmations
+                        .latencies
+                        .entry(tx.kind)
+                        .or_default()
+                        .add(time_to_confirm);
+                } else {
+                    counters.lost.fetch_add(1, Ordering::Relaxed);
+                    confirmations
+                        .metric
+                        .add_unsuccessful_transaction(tx.sent_duration, tx.transaction_bytes);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn load_ramps_up_linearly() {
+    let config = LoadConfig {
+        target_tps: 100,
+        duration: Duration::from_secs(20),
+        ramp_up: Duration::from_secs(10),
+        progress_interval: Duration::from_secs(10),
+        max_error_rate: 0.5,
+    };
+    assert_eq!(config.scheduled(Duration::ZERO), 0);
+    assert_eq!(config.scheduled(Duration::from_secs(5)), 125);
+    assert_eq!(config.scheduled(Duration::from_secs(10)), 500);
+    assert_eq!(config.scheduled(Duration::from_secs(20)), 1500);
+    assert_eq!(config.scheduled(Duration::from_secs(30)), 1500);
+
+    let no_ramp_up = LoadConfig {
+        ramp_up: Duration::ZERO,
+        ..config
+    };
+    assert_eq!(no_ramp_up.scheduled(Duration::from_secs(3)), 300);
+}
@@ -1,21 +1,36 @@
 use bench::{
     cli::Args,
+    compare::{Comparison, SendTarget},
+    endpoints::{bench_endpoints, Endpoint},
+    fee_sweep::{FeeLevelReport, FeeSweepReport},
     helpers::BenchHelper,
+    latency::{LatencyHistogram, LatencyReport},
+    load::{run_load, LoadConfig, LoadResult},
     metrics::{AvgMetric, Metric, TxMetricData},
+    postgres::{BenchResultsUploader, BenchRunRecord},
+    subscriptions::{bench_subscriptions, SubscriptionSource},
+    tx_mix::{ComputeBudget, TransactionSize, TransferAccounts, TxGenerator, TxKind},
 };
 use clap::Parser;
 use dashmap::DashMap;
 use futures::future::join_all;
 use log::{error, info, warn};
+use rand::SeedableRng;
 use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::config::RpcSendTransactionConfig;
+use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signature;
 use solana_sdk::{
     commitment_config::CommitmentConfig, hash::Hash, signature::Keypair, signer::Signer,
     slot_history::Slot,
 };
-use std::sync::{
-    atomic::{AtomicU64, Ordering},
-    Arc,
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 use tokio::{
     sync::{mpsc::UnboundedSender, RwLock},
@@ -34,8 +49,54 @@ async fn main() {
         lite_rpc_addr,
         transaction_save_file,
         large_transactions,
+        tx_mix,
+        compute_unit_price,
+        compute_unit_limit,
+        latency_file,
+        compare_rpc_addr,
+        comparison_file,
+        target_tps,
+        duration_secs,
+        ramp_up_secs,
+        progress_interval_secs,
+        max_error_rate,
+        fee_sweep,
+        fee_sweep_file,
+        endpoints,
+        endpoint_mode,
+        endpoints_file,
+        pg_config,
+        pg_label,
+        subscription_source,
+        reference_source,
+        grpc_x_token,
+        subscribe_accounts,
+        subscription_duration_secs,
+        subscriptions_file,
     } = Args::parse();
 
+    // measures notifications, nothing is sent
+    if let Some(subscription_source) = subscription_source {
+        let reference_source = reference_source.expect("--reference-source is required");
+        let measured: SubscriptionSource = subscription_source.parse().unwrap();
+        let reference: SubscriptionSource = reference_source.parse().unwrap();
+        let accounts = subscribe_accounts
+            .iter()
+            .map(|account| Pubkey::from_str(account).unwrap())
+            .collect();
+        let report = bench_subscriptions(
+            measured.with_x_token(grpc_x_token.clone()),
+            reference.with_x_token(grpc_x_token),
+            accounts,
+            Duration::from_secs(subscription_duration_secs),
+        )
+        .await
+        .unwrap();
+        report.log();
+        report.write(&subscriptions_file).unwrap();
+        return;
+    }
+
     let mut run_interval_ms = tokio::time::interval(Duration::from_millis(run_interval_ms));
 
     let transaction_size = if large_transactions {
@@ -57,8 +118,30 @@ async fn main() {
         lite_rpc_addr.clone(),
         CommitmentConfig::confirmed(),
     ));
+    let compare_rpc_client = compare_rpc_addr.as_ref().map(|compare_rpc_addr| {
+        info!("Comparing with {compare_rpc_addr}");
+        Arc::new(RpcClient::new_with_commitment(
+            compare_rpc_addr.clone(),
+            CommitmentConfig::confirmed(),
+        ))
+    });
+
+    let transfer_accounts = TransferAccounts::create(&rpc_client, &funded_payer, &tx_mix)
+        .await
+        .unwrap();
+    let compute_budget = ComputeBudget {
+        unit_price: compute_unit_price,
+        unit_limit: compute_unit_limit,
+    };
+
     let bh = rpc_client.get_latest_blockhash().await.unwrap();
     let slot = rpc_client.get_slot().await.unwrap();
+    let started_at = chrono::Utc::now();
+    // connect before the runs to not lose their results to a wrong config
+    let mut uploader = match &pg_config {
+        Some(pg_config) => Some(BenchResultsUploader::connect(pg_config).await.unwrap()),
+        None => None,
+    };
     let block_hash: Arc<RwLock<Hash>> = Arc::new(RwLock::new(bh));
     let current_slot = Arc::new(AtomicU64::new(slot));
     {
@@ -91,20 +174,147 @@ async fn main() {
 
     // transaction logger
     let (tx_log_sx, mut tx_log_rx) = tokio::sync::mpsc::unbounded_channel::<TxMetricData>();
-    let log_transactions = !transaction_save_file.is_empty();
-    if log_transactions {
-        tokio::spawn(async move {
-            let mut tx_writer = csv::Writer::from_path(transaction_save_file).unwrap();
-            while let Some(x) = tx_log_rx.recv().await {
-                tx_writer.serialize(x).unwrap();
+    let log_transactions = !transaction_save_file.is_empty() || uploader.is_some();
+    let collect_transactions = uploader.is_some();
+    let tx_logger = tokio::spawn(async move {
+        let mut tx_writer = (!transaction_save_file.is_empty())
+            .then(|| csv::Writer::from_path(transaction_save_file).unwrap());
+        // kept for the upload
+        let mut txs = vec![];
+        while let Some(x) = tx_log_rx.recv().await {
+            if let Some(tx_writer) = &mut tx_writer {
+                tx_writer.serialize(&x).unwrap();
             }
-        });
+            if collect_transactions {
+                txs.push(x);
+            }
+        }
+        txs
+    });
+
+    if let Some(target_tps) = target_tps {
+        let config = LoadConfig {
+            target_tps,
+            duration: Duration::from_secs(duration_secs),
+            ramp_up: Duration::from_secs(ramp_up_secs),
+            progress_interval: Duration::from_secs(progress_interval_secs),
+            max_error_rate,
+        };
+        let tx_generator = TxGenerator::new(
+            tx_mix,
+            compute_budget,
+            transaction_size,
+            transfer_accounts,
+            rand_chacha::ChaCha8Rng::seed_from_u64(0),
+        );
+        let LoadResult {
+            metric,
+            latencies,
+            aborted,
+        } = run_load(
+            config,
+            rpc_client.clone(),
+            funded_payer,
+            block_hash,
+            tx_generator,
+            transaction_size,
+        )
+        .await;
+        info!("Load: {metric:?}");
+
+        let mut csv_writer = csv::Writer::from_path(metrics_file_name).unwrap();
+        csv_writer.serialize(metric).unwrap();
+        csv_writer.flush().unwrap();
+        if !latency_file.is_empty() {
+            write_latency_report(&rpc_client, &[latencies], &latency_file).await;
+        }
+        if aborted {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if !endpoints.is_empty() {
+        info!("Sending {tx_count} tx(s) through {endpoints:?} ({endpoint_mode:?})");
+        let tx_generator = TxGenerator::new(
+            tx_mix,
+            compute_budget,
+            transaction_size,
+            transfer_accounts,
+            rand_chacha::ChaCha8Rng::seed_from_u64(0),
+        );
+        let report = bench_endpoints(
+            endpoints.into_iter().map(Endpoint::new).collect(),
+            endpoint_mode,
+            rpc_client.clone(),
+            tx_count,
+            funded_payer,
+            block_hash,
+            tx_generator,
+            transaction_size,
+        )
+        .await;
+        report.log();
+        report.write(&endpoints_file).unwrap();
+        return;
+    }
+
+    if !fee_sweep.is_empty() {
+        let mut report = FeeSweepReport {
+            compute_unit_limit,
+            levels: vec![],
+        };
+        for (seed, compute_unit_price) in fee_sweep.into_iter().enumerate() {
+            info!("Sending {tx_count} tx(s) at a compute unit price of {compute_unit_price}");
+            let funded_payer = Keypair::from_bytes(funded_payer.to_bytes().as_slice()).unwrap();
+            let tx_generator = TxGenerator::new(
+                tx_mix.clone(),
+                ComputeBudget {
+                    unit_price: Some(compute_unit_price),
+                    ..compute_budget
+                },
+                transaction_size,
+                transfer_accounts.clone(),
+                rand_chacha::ChaCha8Rng::seed_from_u64(seed as u64),
+            );
+            let RunResult {
+                metric, latencies, ..
+            } = bench(
+                rpc_client.clone(),
+                None,
+                tx_count,
+                funded_payer,
+                seed as u64,
+                block_hash.clone(),
+                current_slot.clone(),
+                tx_log_sx.clone(),
+                log_transactions,
+                transaction_size,
+                tx_generator,
+            )
+            .await;
+            report
+                .levels
+                .push(FeeLevelReport::new(compute_unit_price, &metric, &latencies));
+            run_interval_ms.tick().await;
+        }
+        report.log();
+        report.write(&fee_sweep_file).unwrap();
+        return;
     }
 
     for seed in 0..runs {
         let funded_payer = Keypair::from_bytes(funded_payer.to_bytes().as_slice()).unwrap();
+        let tx_generator = TxGenerator::new(
+            tx_mix.clone(),
+            compute_budget,
+            transaction_size,
+            transfer_accounts.clone(),
+            rand_chacha::ChaCha8Rng::seed_from_u64(seed as u64),
+        );
         tasks.push(tokio::spawn(bench(
             rpc_client.clone(),
+            compare_rpc_client.clone(),
             tx_count,
             funded_payer,
             seed as u64,
@@ -113,6 +323,7 @@ async fn main() {
             tx_log_sx.clone(),
             log_transactions,
             transaction_size,
+            tx_generator,
         )));
         // wait for an interval
         run_interval_ms.tick().await;
@@ -122,13 +333,23 @@ async fn main() {
 
     let mut run_num = 1;
 
+    let mut latencies = vec![];
+    let mut comparison = Comparison::default();
+    let mut total_metric = Metric::default();
     let mut csv_writer = csv::Writer::from_path(metrics_file_name).unwrap();
     for res in join_res {
         match res {
-            Ok(metric) => {
+            Ok(RunResult {
+                metric,
+                latencies: run_latencies,
+                comparison: run_comparison,
+            }) => {
+                latencies.push(run_latencies);
+                comparison.merge(&run_comparison);
                 info!("Run {run_num}: Sent and Confirmed {tx_count} tx(s) in {metric:?} with",);
                 // update avg metric
                 avg_metric += &metric;
+                total_metric += &metric;
                 csv_writer.serialize(metric).unwrap();
             }
             Err(_) => {
@@ -144,6 +365,67 @@ async fn main() {
     csv_writer.serialize(avg_metric).unwrap();
 
     csv_writer.flush().unwrap();
+
+    if !latency_file.is_empty() {
+        write_latency_report(&rpc_client, &latencies, &latency_file).await;
+    }
+
+    if let Some(compare_rpc_addr) = compare_rpc_addr {
+        let report = comparison.report(&compare_rpc_addr);
+        report.log();
+        if !comparison_file.is_empty() {
+            report.write(&comparison_file).unwrap();
+        }
+    }
+
+    // the runs are done, the logger gets the last transactions and stops
+    drop(tx_log_sx);
+    let txs = tx_logger.await.unwrap();
+    if let Some(uploader) = &mut uploader {
+        let rpc_version = rpc_client
+            .get_version()
+            .await
+            .map(|version| version.solana_core)
+            .ok();
+        let record = BenchRunRecord {
+            started_at,
+            label: pg_label,
+            rpc_addr: lite_rpc_addr,
+            rpc_version,
+            start_slot: slot,
+            runs: runs as u64,
+            tx_count: tx_count as u64,
+            tx_mix: tx_mix.to_string(),
+            compute_unit_price,
+            compute_unit_limit,
+            txs_sent: total_metric.txs_sent,
+            txs_confirmed: total_metric.txs_confirmed,
+            latency: LatencyReport::new(None, &latencies).total,
+        };
+        let run_id = uploader.upload(&record, &txs).await.unwrap();
+        info!(
+            "Uploaded the results as run {run_id} with {} tx(s)",
+            txs.len()
+        );
+    }
+}
+
+async fn write_latency_report(
+    rpc_client: &RpcClient,
+    latencies: &[HashMap<TxKind, LatencyHistogram>],
+    latency_file: &str,
+) {
+    let rpc_version = rpc_client
+        .get_version()
+        .await
+        .map(|version| version.solana_core)
+        .ok();
+    let report = LatencyReport::new(rpc_version, latencies);
+    info!(
+        "Confirmation latency p50 {}ms, p90 {}ms, p99 {}ms",
+        report.total.p50_ms, report.total.p90_ms, report.total.p99_ms
+    );
+    report.write(latency_file).unwrap();
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -152,15 +434,26 @@ struct TxSendData {
     sent_instant: Instant,
     sent_slot: Slot,
     transaction_bytes: u64,
+    kind: TxKind,
+    target: SendTarget,
 }
 
 struct ApiCallerResult {
     gross_send_time: Duration,
 }
 
+struct RunResult {
+    /// of the transactions sent through lite-rpc
+    metric: Metric,
+    /// of the transactions sent through lite-rpc
+    latencies: HashMap<TxKind, LatencyHistogram>,
+    comparison: Comparison,
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn bench(
     rpc_client: Arc<RpcClient>,
+    compare_rpc_client: Option<Arc<RpcClient>>,
     tx_count: usize,
     funded_payer: Keypair,
     seed: u64,
@@ -169,7 +462,8 @@ async fn bench(
     tx_metric_sx: UnboundedSender<TxMetricData>,
     log_txs: bool,
     transaction_size: TransactionSize,
-) -> Metric {
+    mut tx_generator: TxGenerator,
+) -> RunResult {
     let map_of_txs: Arc<DashMap<Signature, TxSendData>> = Arc::new(DashMap::new());
     // transaction sender task
     let api_caller_result = {
@@ -178,26 +472,39 @@ async fn bench(
         let current_slot = current_slot.clone();
         tokio::spawn(async move {
             let map_of_txs = map_of_txs.clone();
-            let n_chars = match transaction_size {
-                TransactionSize::Small => 10,
-                TransactionSize::Large => 240, // 565 is max but we need to lower that to not burn the CUs
-            };
-            let rand_strings = BenchHelper::generate_random_strings(tx_count, Some(seed), n_chars);
+            let rand_strings = BenchHelper::generate_random_strings(
+                tx_count,
+                Some(seed),
+                transaction_size.memo_chars(),
+            );
 
             let bench_start_time = Instant::now();
 
-            for rand_string in &rand_strings {
+            for (i, rand_string) in rand_strings.iter().enumerate() {
                 let blockhash = { *block_hash.read().await };
-                let tx = match transaction_size {
-                    TransactionSize::Small => {
-                        BenchHelper::create_memo_tx_small(rand_string, &funded_payer, blockhash)
-                    }
-                    TransactionSize::Large => {
-                        BenchHelper::create_memo_tx_large(rand_string, &funded_payer, blockhash)
-                    }
+                let (kind, tx) = tx_generator.create(rand_string, &funded_payer, blockhash);
+                // interleave lite-rpc and the reference rpc when comparing
+                let target = match &compare_rpc_client {
+                    Some(_) if i % 2 == 1 => SendTarget::Reference,
+                    _ => SendTarget::LiteRpc,
                 };
                 let start_time = Instant::now();
-                match rpc_client.send_transaction(&tx).await {
+                let sent = match (target, &compare_rpc_client) {
+                    (SendTarget::Reference, Some(compare_rpc_client)) => {
+                        // lite-rpc does not simulate either
+                        compare_rpc_client
+                            .send_transaction_with_config(
+                                &tx,
+                                RpcSendTransactionConfig {
+                                    skip_preflight: true,
+                                    ..RpcSendTransactionConfig::default()
+                                },
+                            )
+                            .await
+                    }
+                    _ => rpc_client.send_transaction(&tx).await,
+                };
+                match sent {
                     Ok(signature) => {
                         map_of_txs.insert(
                             signature,
@@ -206,6 +513,8 @@ async fn bench(
                                 sent_instant: Instant::now(),
                                 sent_slot: current_slot.load(std::sync::atomic::Ordering::Relaxed),
                                 transaction_bytes: bincode::serialized_size(&tx).unwrap(),
+                                kind,
+                                target,
                             },
                         );
                     }
@@ -221,6 +530,10 @@ async fn bench(
     };
 
     let mut metric = Metric::default();
+    // confirmed and sent transactions per kind
+    let mut landed_by_kind: HashMap<TxKind, (u64, u64)> = HashMap::new();
+    let mut latencies: HashMap<TxKind, LatencyHistogram> = HashMap::new();
+    let mut comparison = Comparison::default();
     let confirmation_time = Instant::now();
     let mut confirmed_count = 0;
     while confirmation_time.elapsed() < Duration::from_secs(60)
@@ -239,19 +552,31 @@ async fn bench(
                     let tx_data = map_of_txs.get(signature).unwrap();
                     let time_to_confirm = tx_data.sent_instant.elapsed();
                     let transaction_bytes = tx_data.transaction_bytes;
-                    metric.add_successful_transaction(
-                        tx_data.sent_duration,
-                        time_to_confirm,
-                        transaction_bytes,
-                    );
+                    comparison.add_confirmed(tx_data.target, time_to_confirm);
+                    if tx_data.target == SendTarget::LiteRpc {
+                        let landed = landed_by_kind.entry(tx_data.kind).or_default();
+                        landed.0 += 1;
+                        landed.1 += 1;
+                        latencies
+                            .entry(tx_data.kind)
+                            .or_default()
+                            .add(time_to_confirm);
+                        metric.add_successful_transaction(
+                            tx_data.sent_duration,
+                            time_to_confirm,
+                            transaction_bytes,
+                        );
+                    }
 
                     if log_txs {
                         let _ = tx_metric_sx.send(TxMetricData {
                             signature: signature.to_string(),
+                            tx_kind: tx_data.kind,
+                            send_target: tx_data.target,
                             sent_slot: tx_data.sent_slot,
-                            confirmed_slot: current_slot.load(Ordering::Relaxed),
+                            confirmed_slot: Some(current_slot.load(Ordering::Relaxed)),
                             time_to_send_in_millis: tx_data.sent_duration.as_millis() as u64,
-                            time_to_confirm_in_millis: time_to_confirm.as_millis() as u64,
+                            time_to_confirm_in_millis: Some(time_to_confirm.as_millis() as u64),
                         });
                     }
                     drop(tx_data);
@@ -263,7 +588,25 @@ async fn bench(
     }
 
     for tx in map_of_txs.iter() {
-        metric.add_unsuccessful_transaction(tx.sent_duration, tx.transaction_bytes);
+        if log_txs {
+            let _ = tx_metric_sx.send(TxMetricData {
+                signature: tx.key().to_string(),
+                tx_kind: tx.kind,
+                send_target: tx.target,
+                sent_slot: tx.sent_slot,
+                confirmed_slot: None,
+                time_to_send_in_millis: tx.sent_duration.as_millis() as u64,
+                time_to_confirm_in_millis: None,
+            });
+        }
+        comparison.add_unconfirmed(tx.target);
+        if tx.target == SendTarget::LiteRpc {
+            landed_by_kind.entry(tx.kind).or_default().1 += 1;
+            metric.add_unsuccessful_transaction(tx.sent_duration, tx.transaction_bytes);
+        }
+    }
+    for (kind, (confirmed, sent)) in landed_by_kind {
+        info!("Run {seed}: {confirmed} of {sent} {kind:?} tx(s) confirmed");
     }
 
     let api_caller_result = api_caller_result
@@ -274,16 +617,9 @@ async fn bench(
         .set_total_gross_send_time(api_caller_result.gross_send_time.as_micros() as f64 / 1_000.0);
 
     metric.finalize();
-    metric
-}
-
-// see https://spl.solana.com/memo for sizing of transactions
-// As of v1.5.1, an unsigned instruction can support single-byte UTF-8 of up to 566 bytes.
-// An instruction with a simple memo of 32 bytes can support up to 12 signers.
-#[derive(Debug, Clone, Copy)]
-enum TransactionSize {
-    // 179 bytes, 5237 CUs
-    Small,
-    // 1186 bytes, 193175 CUs
-    Large,
+    RunResult {
+        metric,
+        latencies,
+        comparison,
+    }
 }
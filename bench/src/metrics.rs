@@ -5,6 +5,8 @@ use std::{
 
 use solana_sdk::slot_history::Slot;
 
+use crate::{compare::SendTarget, tx_mix::TxKind};
+
 #[derive(Clone, Copy, Debug, Default, serde::Serialize)]
 pub struct Metric {
     pub txs_sent: u64,
@@ -138,8 +140,11 @@ impl From<AvgMetric> for Metric {
 #[derive(Clone, Debug, Default, serde::Serialize)]
 pub struct TxMetricData {
     pub signature: String,
+    pub tx_kind: TxKind,
+    pub send_target: SendTarget,
     pub sent_slot: Slot,
-    pub confirmed_slot: Slot,
+    /// none if the transaction was not confirmed
+    pub confirmed_slot: Option<Slot>,
     pub time_to_send_in_millis: u64,
-    pub time_to_confirm_in_millis: u64,
+    pub time_to_confirm_in_millis: Option<u64>,
 }
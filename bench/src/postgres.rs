@@ -0,0 +1,183 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use native_tls::TlsConnector;
+use postgres_native_tls::MakeTlsConnector;
+use tokio_postgres::{config::SslMode, tls::MakeTlsConnect, types::ToSql, Client, NoTls, Socket};
+
+use crate::{latency::LatencySummary, metrics::TxMetricData};
+
+const SCHEMA: &str = include_str!("../../migrations/bench.sql");
+// rows per insert statement, postgres takes at most 65535 parameters
+const TX_ROWS_PER_INSERT: usize = 1_000;
+
+/// metadata and totals of a bench invocation
+pub struct BenchRunRecord {
+    pub started_at: DateTime<Utc>,
+    pub label: Option<String>,
+    pub rpc_addr: String,
+    pub rpc_version: Option<String>,
+    pub start_slot: u64,
+    pub runs: u64,
+    pub tx_count: u64,
+    pub tx_mix: String,
+    pub compute_unit_price: Option<u64>,
+    pub compute_unit_limit: Option<u32>,
+    pub txs_sent: u64,
+    pub txs_confirmed: u64,
+    pub latency: LatencySummary,
+}
+
+/// uploads bench results into the `bench` schema of migrations/bench.sql, creating it if needed
+pub struct BenchResultsUploader {
+    client: Client,
+}
+
+impl BenchResultsUploader {
+    /// connects with a libpq style config, verifying the server certificate unless sslmode=disable
+    pub async fn connect(pg_config: &str) -> anyhow::Result<Self> {
+        let pg_config = pg_config.parse::<tokio_postgres::Config>()?;
+        let client = if let SslMode::Disable = pg_config.get_ssl_mode() {
+            Self::spawn_connection(pg_config, NoTls).await?
+        } else {
+            let connector = MakeTlsConnector::new(TlsConnector::new()?);
+            Self::spawn_connection(pg_config, connector).await?
+        };
+        client
+            .batch_execute(SCHEMA)
+            .await
+            .context("creating the bench schema")?;
+        Ok(Self { client })
+    }
+
+    async fn spawn_connection<T>(
+        pg_config: tokio_postgres::Config,
+        connector: T,
+    ) -> anyhow::Result<Client>
+    where
+        T: MakeTlsConnect<Socket> + Send + 'static,
+        <T as MakeTlsConnect<Socket>>::Stream: Send,
+    {
+        let (client, connection) = pg_config
+            .connect(connector)
+            .await
+            .context("Connecting to Postgres failed")?;
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                log::error!("Connection to Postgres broke {err:?}");
+            }
+        });
+        Ok(client)
+    }
+
+    /// inserts the run and its transactions in one transaction, returns the id of the run
+    pub async fn upload(
+        &mut self,
+        run: &BenchRunRecord,
+        txs: &[TxMetricData],
+    ) -> anyhow::Result<i64> {
+        let transaction = self.client.transaction().await?;
+        let landing_rate = if run.txs_sent > 0 {
+            run.txs_confirmed as f64 / run.txs_sent as f64
+        } else {
+            0.0
+        };
+        let row = transaction
+            .query_one(
+                "INSERT INTO bench.Runs (started_at, label, rpc_addr, rpc_version, bench_version, \
+                 start_slot, runs, tx_count, tx_mix, compute_unit_price, compute_unit_limit, \
+                 txs_sent, txs_confirmed, landing_rate, mean_confirmation_ms, p50_confirmation_ms, \
+                 p90_confirmation_ms, p99_confirmation_ms) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18) \
+                 RETURNING id",
+                &[
+                    &run.started_at,
+                    &run.label,
+                    &run.rpc_addr,
+                    &run.rpc_version,
+                    &env!("CARGO_PKG_VERSION"),
+                    &(run.start_slot as i64),
+                    &(run.runs as i64),
+                    &(run.tx_count as i64),
+                    &run.tx_mix,
+                    &run.compute_unit_price.map(|price| price as i64),
+                    &run.compute_unit_limit.map(i64::from),
+                    &(run.txs_sent as i64),
+                    &(run.txs_confirmed as i64),
+                    &landing_rate,
+                    &run.latency.mean_ms,
+                    &run.latency.p50_ms,
+                    &run.latency.p90_ms,
+                    &run.latency.p99_ms,
+                ],
+            )
+            .await
+            .context("inserting the bench run")?;
+        let run_id: i64 = row.get(0);
+
+        for chunk in txs.chunks(TX_ROWS_PER_INSERT) {
+            let mut query = String::from(
+                "INSERT INTO bench.Txs (run_id, signature, tx_kind, send_target, sent_slot, \
+                 confirmed_slot, time_to_send_ms, time_to_confirm_ms) VALUES ",
+            );
+            let rows: Vec<TxRow> = chunk.iter().map(TxRow::from).collect();
+            let mut params: Vec<&(dyn ToSql + Sync)> = Vec::with_capacity(rows.len() * 8);
+            for (i, row) in rows.iter().enumerate() {
+                if i > 0 {
+                    query.push(',');
+                }
+                let arg = i * 8;
+                query.push_str(&format!(
+                    "(${}, ${}, ${}, ${}, ${}, ${}, ${}, ${})",
+                    arg + 1,
+                    arg + 2,
+                    arg + 3,
+                    arg + 4,
+                    arg + 5,
+                    arg + 6,
+                    arg + 7,
+                    arg + 8
+                ));
+                params.extend_from_slice(&[
+                    &run_id,
+                    &row.signature,
+                    &row.tx_kind,
+                    &row.send_target,
+                    &row.sent_slot,
+                    &row.confirmed_slot,
+                    &row.time_to_send_ms,
+                    &row.time_to_confirm_ms,
+                ]);
+            }
+            transaction
+                .execute(&query, &params)
+                .await
+                .context("inserting the bench transactions")?;
+        }
+        transaction.commit().await?;
+        Ok(run_id)
+    }
+}
+
+struct TxRow {
+    signature: String,
+    tx_kind: &'static str,
+    send_target: &'static str,
+    sent_slot: i64,
+    confirmed_slot: Option<i64>,
+    time_to_send_ms: i64,
+    time_to_confirm_ms: Option<i64>,
+}
+
+impl From<&TxMetricData> for TxRow {
+    fn from(tx: &TxMetricData) -> Self {
+        Self {
+            signature: tx.signature.clone(),
+            tx_kind: tx.tx_kind.as_str(),
+            send_target: tx.send_target.as_str(),
+            sent_slot: tx.sent_slot as i64,
+            confirmed_slot: tx.confirmed_slot.map(|slot| slot as i64),
+            time_to_send_ms: tx.time_to_send_in_millis as i64,
+            time_to_confirm_ms: tx.time_to_confirm_in_millis.map(|ms| ms as i64),
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use std::{collections::HashMap, fs::File, path::Path, str::FromStr};
+
+use anyhow::{bail, Context};
+use futures::StreamExt;
+use log::{info, warn};
+use solana_account_decoder::UiAccountEncoding;
+use solana_pubsub_client::nonblocking::pubsub_client::PubsubClient;
+use solana_rpc_client_api::config::RpcAccountInfoConfig;
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::{Duration, Instant},
+};
+use tonic::transport::ClientTlsConfig;
+use yellowstone_grpc_client::GeyserGrpcClient;
+use yellowstone_grpc_proto::geyser::{
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterSlots,
+};
+
+/// a websocket (`ws://`, `wss://`) or a yellowstone gRPC (`http://`, `https://`) source of
+/// notifications
+#[derive(Clone, Debug)]
+pub enum SubscriptionSource {
+    Ws(String),
+    Grpc {
+        addr: String,
+        x_token: Option<String>,
+    },
+}
+
+impl FromStr for SubscriptionSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("ws://") || s.starts_with("wss://") {
+            Ok(SubscriptionSource::Ws(s.to_string()))
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(SubscriptionSource::Grpc {
+                addr: s.to_string(),
+                x_token: None,
+            })
+        } else {
+            bail!("subscription source {s} is neither a websocket nor a gRPC url")
+        }
+    }
+}
+
+impl SubscriptionSource {
+    pub fn with_x_token(self, x_token: Option<String>) -> Self {
+        match self {
+            SubscriptionSource::Grpc { addr, .. } => SubscriptionSource::Grpc { addr, x_token },
+            ws => ws,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            SubscriptionSource::Ws(addr) => addr,
+            SubscriptionSource::Grpc { addr, .. } => addr,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Notification {
+    Slot(Slot),
+    Account(Pubkey, Slot),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Side {
+    Measured,
+    Reference,
+}
+
+struct Arrival {
+    side: Side,
+    notification: Notification,
+    at: Instant,
+}
+
+#[derive(Default)]
+struct Arrivals {
+    measured: Option<Instant>,
+    reference: Option<Instant>,
+}
+
+/// how much later the measured source notified than the reference, negative if it was faster
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct NotificationLatency {
+    pub matched: u64,
+    /// seen by only one of the sources
+    pub only_measured: u64,
+    pub only_reference: u64,
+    pub mean_ms: f64,
+    pub p10_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl NotificationLatency {
+    fn new(mut deltas_ms: Vec<f64>, only_measured: u64, only_reference: u64) -> Self {
+        deltas_ms.sort_by(|a, b| a.total_cmp(b));
+        // nearest rank percentile
+        let percentile = |p: f64| -> f64 {
+            if deltas_ms.is_empty() {
+                return 0.0;
+            }
+            let rank = ((p / 100.0) * deltas_ms.len() as f64).ceil() as usize;
+            deltas_ms[rank.clamp(1, deltas_ms.len()) - 1]
+        };
+        Self {
+            matched: deltas_ms.len() as u64,
+            only_measured,
+            only_reference,
+            mean_ms: if deltas_ms.is_empty() {
+                0.0
+            } else {
+                deltas_ms.iter().sum::<f64>() / deltas_ms.len() as f64
+            },
+            p10_ms: percentile(10.0),
+            p50_ms: percentile(50.0),
+            p90_ms: percentile(90.0),
+            p99_ms: percentile(99.0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct SubscriptionReport {
+    pub measured: String,
+    pub reference: String,
+    pub slots: NotificationLatency,
+    pub accounts: NotificationLatency,
+}
+
+impl SubscriptionReport {
+    pub fn log(&self) {
+        for (name, latency) in [("slot", &self.slots), ("account", &self.accounts)] {
+            info!(
+                "{name} notifications of {} vs {}: {} matched, {} only measured, {} only reference, delta p10 {:.1}ms, p50 {:.1}ms, p90 {:.1}ms, p99 {:.1}ms",
+                self.measured,
+                self.reference,
+                latency.matched,
+                latency.only_measured,
+                latency.only_reference,
+                latency.p10_ms,
+                latency.p50_ms,
+                latency.p90_ms,
+                latency.p99_ms
+            );
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("creating subscription report {}", path.display()))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+/// subscribes to slots and the accounts on both sources for the duration and compares when every
+/// notification arrived
+pub async fn bench_subscriptions(
+    measured: SubscriptionSource,
+    reference: SubscriptionSource,
+    accounts: Vec<Pubkey>,
+    duration: Duration,
+) -> anyhow::Result<SubscriptionReport> {
+    let (arrival_sx, mut arrival_rx) = unbounded_channel();
+    let mut tasks = vec![];
+    for (side, source) in [(Side::Measured, &measured), (Side::Reference, &reference)] {
+        tasks.push(tokio::spawn(subscribe(
+            side,
+            source.clone(),
+            accounts.clone(),
+            arrival_sx.clone(),
+        )));
+    }
+    drop(arrival_sx);
+
+    let mut arrivals: HashMap<Notification, Arrivals> = HashMap::new();
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            arrival = arrival_rx.recv() => {
+                let Some(Arrival { side, notification, at }) = arrival else {
+                    break;
+                };
+                let arrivals = arrivals.entry(notification).or_default();
+                // the first notification counts, e.g. for several updates of an account in a slot
+                match side {
+                    Side::Measured => arrivals.measured.get_or_insert(at),
+                    Side::Reference => arrivals.reference.get_or_insert(at),
+                };
+            }
+            _ = &mut deadline => break,
+        }
+    }
+    for task in tasks {
+        if task.is_finished() {
+            if let Ok(Err(err)) = task.await {
+                bail!("subscription failed: {err:#}");
+            }
+        } else {
+            task.abort();
+        }
+    }
+
+    let mut slot_deltas = vec![];
+    let mut account_deltas = vec![];
+    let mut unmatched: HashMap<(bool, Side), u64> = HashMap::new();
+    for (notification, arrivals) in arrivals {
+        let is_slot = matches!(notification, Notification::Slot(_));
+        match (arrivals.measured, arrivals.reference) {
+            (Some(measured), Some(reference)) => {
+                let delta_ms = if measured >= reference {
+                    (measured - reference).as_secs_f64() * 1_000.0
+                } else {
+                    -(reference - measured).as_secs_f64() * 1_000.0
+                };
+                if is_slot {
+                    slot_deltas.push(delta_ms);
+                } else {
+                    account_deltas.push(delta_ms);
+                }
+            }
+            (Some(_), None) => *unmatched.entry((is_slot, Side::Measured)).or_default() += 1,
+            (None, Some(_)) => *unmatched.entry((is_slot, Side::Reference)).or_default() += 1,
+            (None, None) => {}
+        }
+    }
+    let unmatched = |is_slot: bool, side: Side| unmatched.get(&(is_slot, side)).copied();
+    Ok(SubscriptionReport {
+        measured: measured.name().to_string(),
+        reference: reference.name().to_string(),
+        slots: NotificationLatency::new(
+            slot_deltas,
+            unmatched(true, Side::Measured).unwrap_or_default(),
+            unmatched(true, Side::Reference).unwrap_or_default(),
+        ),
+        accounts: NotificationLatency::new(
+            account_deltas,
+            unmatched(false, Side::Measured).unwrap_or_default(),
+            unmatched(false, Side::Reference).unwrap_or_default(),
+        ),
+    })
+}
+
+async fn subscribe(
+    side: Side,
+    source: SubscriptionSource,
+    accounts: Vec<Pubkey>,
+    arrival_sx: UnboundedSender<Arrival>,
+) -> anyhow::Result<()> {
+    let send = |notification: Notification| {
+        let _ = arrival_sx.send(Arrival {
+            side,
+            notification,
+            at: Instant::now(),
+        });
+    };
+    match source {
+        SubscriptionSource::Ws(addr) => {
+            let pubsub_client = PubsubClient::new(&addr)
+                .await
+                .with_context(|| format!("connecting to {addr}"))?;
+            let (mut slots, _unsubscribe) = pubsub_client.slot_subscribe().await?;
+            let mut account_streams = vec![];
+            for account in &accounts {
+                let config = RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(CommitmentConfig::processed()),
+                    ..RpcAccountInfoConfig::default()
+                };
+                let (stream, _unsubscribe) = pubsub_client
+                    .account_subscribe(account, Some(config))
+                    .await?;
+                account_streams.push(stream.map(move |update| (*account, update.context.slot)));
+            }
+            let mut account_updates = futures::stream::select_all(account_streams);
+            info!("Subscribed to {addr}");
+            loop {
+                tokio::select! {
+                    slot = slots.next() => match slot {
+                        Some(slot) => send(Notification::Slot(slot.slot)),
+                        None => bail!("slot subscription of {addr} closed"),
+                    },
+                    Some((account, slot)) = account_updates.next() => {
+                        send(Notification::Account(account, slot));
+                    }
+                }
+            }
+        }
+        SubscriptionSource::Grpc { addr, x_token } => {
+            let tls_config = addr.starts_with("https").then(ClientTlsConfig::new);
+            let mut client = GeyserGrpcClient::connect(addr.clone(), x_token, tls_config)?;
+            let mut slots_filter = HashMap::new();
+            slots_filter.insert(
+                "bench_slots".to_string(),
+                SubscribeRequestFilterSlots {
+                    filter_by_commitment: None,
+                },
+            );
+            let mut accounts_filter = HashMap::new();
+            if !accounts.is_empty() {
+                accounts_filter.insert(
+                    "bench_accounts".to_string(),
+                    SubscribeRequestFilterAccounts {
+                        account: accounts.iter().map(|account| account.to_string()).collect(),
+                        owner: vec![],
+                        filters: vec![],
+                    },
+                );
+            }
+            let mut stream = client
+                .subscribe_once(
+                    slots_filter,
+                    accounts_filter,
+                    Default::default(),
+                    Default::default(),
+                    Default::default(),
+                    Default::default(),
+                    Some(CommitmentLevel::Processed),
+                    vec![],
+                    None,
+                )
+                .await?;
+            info!("Subscribed to {addr}");
+            while let Some(update) = stream.next().await {
+                match update?.update_oneof {
+                    Some(UpdateOneof::Slot(slot)) => send(Notification::Slot(slot.slot)),
+                    Some(UpdateOneof::Account(account)) => {
+                        let Some(info) = account.account else {
+                            continue;
+                        };
+                        match Pubkey::try_from(info.pubkey.as_slice()) {
+                            Ok(pubkey) => send(Notification::Account(pubkey, account.slot)),
+                            Err(_) => warn!("invalid account pubkey from {addr}"),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            bail!("gRPC subscription of {addr} closed")
+        }
+    }
+}
+
+#[test]
+fn notification_latency_percentiles() {
+    let latency = NotificationLatency::new(vec![30.0, -10.0, 20.0, 0.0, 10.0], 2, 1);
+    assert_eq!(latency.matched, 5);
+    assert_eq!(latency.only_measured, 2);
+    assert_eq!(latency.only_reference, 1);
+    assert_eq!(latency.mean_ms, 10.0);
+    assert_eq!(latency.p10_ms, -10.0);
+    assert_eq!(latency.p50_ms, 10.0);
+    assert_eq!(latency.p99_ms, 30.0);
+}
@@ -0,0 +1,406 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context};
+use log::info;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, hash::Hash, instruction::Instruction,
+    message::Message, program_pack::Pack, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    system_instruction, transaction::Transaction,
+};
+
+use crate::helpers::BenchHelper;
+
+// see https://spl.solana.com/memo for sizing of transactions
+// As of v1.5.1, an unsigned instruction can support single-byte UTF-8 of up to 566 bytes.
+// An instruction with a simple memo of 32 bytes can support up to 12 signers.
+#[derive(Debug, Clone, Copy)]
+pub enum TransactionSize {
+    // 179 bytes, 5237 CUs
+    Small,
+    // 1186 bytes, 193175 CUs
+    Large,
+}
+
+impl TransactionSize {
+    /// length of the random memo
+    pub fn memo_chars(&self) -> usize {
+        match self {
+            TransactionSize::Small => 10,
+            TransactionSize::Large => 240, // 565 is max but we need to lower that to not burn the CUs
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TxKind {
+    #[default]
+    Memo,
+    Transfer,
+    SplTransfer,
+}
+
+impl TxKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TxKind::Memo => "memo",
+            TxKind::Transfer => "transfer",
+            TxKind::SplTransfer => "spl-transfer",
+        }
+    }
+}
+
+impl FromStr for TxKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "memo" => Ok(TxKind::Memo),
+            "transfer" => Ok(TxKind::Transfer),
+            "spl-transfer" => Ok(TxKind::SplTransfer),
+            _ => bail!("unknown transaction kind {s}, expected memo, transfer or spl-transfer"),
+        }
+    }
+}
+
+/// weighted mix of transaction kinds, parsed from `memo=2,transfer=1,spl-transfer=1`, a kind
+/// without a weight counts once
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxMix {
+    weights: Vec<(TxKind, u32)>,
+}
+
+impl Default for TxMix {
+    fn default() -> Self {
+        Self {
+            weights: vec![(TxKind::Memo, 1)],
+        }
+    }
+}
+
+impl FromStr for TxMix {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = vec![];
+        for entry in s
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (kind, weight) = match entry.split_once('=') {
+                Some((kind, weight)) => (
+                    kind.trim(),
+                    weight
+                        .trim()
+                        .parse()
+                        .with_context(|| format!("invalid weight in {entry}"))?,
+                ),
+                None => (entry, 1),
+            };
+            weights.push((kind.parse()?, weight));
+        }
+        if weights.iter().all(|(_, weight)| *weight == 0) {
+            bail!("transaction mix {s} has no transactions");
+        }
+        Ok(Self { weights })
+    }
+}
+
+impl Display for TxMix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let weights: Vec<String> = self
+            .weights
+            .iter()
+            .map(|(kind, weight)| format!("{}={weight}", kind.as_str()))
+            .collect();
+        f.write_str(&weights.join(","))
+    }
+}
+
+impl TxMix {
+    pub fn contains(&self, kind: TxKind) -> bool {
+        self.weights
+            .iter()
+            .any(|(mix_kind, weight)| *mix_kind == kind && *weight > 0)
+    }
+
+    pub fn pick(&self, rng: &mut impl Rng) -> TxKind {
+        let total: u32 = self.weights.iter().map(|(_, weight)| weight).sum();
+        let mut choice = rng.gen_range(0..total);
+        for (kind, weight) in &self.weights {
+            if choice < *weight {
+                return *kind;
+            }
+            choice -= weight;
+        }
+        unreachable!("choice is below the total weight")
+    }
+}
+
+/// compute budget instructions prepended to every transaction
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ComputeBudget {
+    /// micro lamports per compute unit
+    pub unit_price: Option<u64>,
+    pub unit_limit: Option<u32>,
+}
+
+impl ComputeBudget {
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let mut instructions = vec![];
+        if let Some(unit_limit) = self.unit_limit {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(unit_limit));
+        }
+        if let Some(unit_price) = self.unit_price {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(unit_price));
+        }
+        instructions
+    }
+}
+
+/// accounts the transfers of the mix go to, created once before the bench
+#[derive(Clone, Debug)]
+pub struct TransferAccounts {
+    pub recipient: Pubkey,
+    pub token: Option<TokenAccounts>,
+}
+
+/// a mint and two token accounts of the payer, the source holding all the tokens
+#[derive(Clone, Debug)]
+pub struct TokenAccounts {
+    pub mint: Pubkey,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+}
+
+impl TransferAccounts {
+    /// funds the recipient of sol transfers and creates the token accounts if the mix has spl transfers
+    pub async fn create(
+        rpc_client: &RpcClient,
+        payer: &Keypair,
+        tx_mix: &TxMix,
+    ) -> anyhow::Result<Self> {
+        let recipient = Keypair::new().pubkey();
+        if tx_mix.contains(TxKind::Transfer) {
+            let rent = rpc_client.get_minimum_balance_for_rent_exemption(0).await?;
+            let instruction = system_instruction::transfer(&payer.pubkey(), &recipient, rent);
+            send_and_confirm(rpc_client, payer, &[], &[instruction]).await?;
+            info!("Sol transfers go to {recipient}");
+        }
+
+        let token = if tx_mix.contains(TxKind::SplTransfer) {
+            Some(TokenAccounts::create(rpc_client, payer).await?)
+        } else {
+            None
+        };
+        Ok(Self { recipient, token })
+    }
+}
+
+impl TokenAccounts {
+    pub async fn create(rpc_client: &RpcClient, payer: &Keypair) -> anyhow::Result<Self> {
+        let mint = Keypair::new();
+        let source = Keypair::new();
+        let destination = Keypair::new();
+        let mint_rent = rpc_client
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)
+            .await?;
+        let account_rent = rpc_client
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)
+            .await?;
+
+        let mut instructions = vec![
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                mint_rent,
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint2(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &payer.pubkey(),
+                None,
+                0,
+            )?,
+        ];
+        for account in [&source, &destination] {
+            instructions.push(system_instruction::create_account(
+                &payer.pubkey(),
+                &account.pubkey(),
+                account_rent,
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ));
+            instructions.push(spl_token::instruction::initialize_account3(
+                &spl_token::id(),
+                &account.pubkey(),
+                &mint.pubkey(),
+                &payer.pubkey(),
+            )?);
+        }
+        instructions.push(spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &source.pubkey(),
+            &payer.pubkey(),
+            &[],
+            u64::MAX,
+        )?);
+        send_and_confirm(
+            rpc_client,
+            payer,
+            &[&mint, &source, &destination],
+            &instructions,
+        )
+        .await?;
+        info!(
+            "Spl transfers of mint {} go from {} to {}",
+            mint.pubkey(),
+            source.pubkey(),
+            destination.pubkey()
+        );
+
+        Ok(Self {
+            mint: mint.pubkey(),
+            source: source.pubkey(),
+            destination: destination.pubkey(),
+        })
+    }
+}
+
+async fn send_and_confirm(
+    rpc_client: &RpcClient,
+    payer: &Keypair,
+    signers: &[&Keypair],
+    instructions: &[Instruction],
+) -> anyhow::Result<()> {
+    let blockhash = rpc_client.get_latest_blockhash().await?;
+    let mut all_signers = vec![payer];
+    all_signers.extend(signers);
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    rpc_client
+        .send_and_confirm_transaction(&tx)
+        .await
+        .context("setting up the bench accounts")?;
+    Ok(())
+}
+
+/// creates the transactions of a bench run, the kinds and amounts are picked from the seeded rng
+pub struct TxGenerator {
+    tx_mix: TxMix,
+    compute_budget: ComputeBudget,
+    transaction_size: TransactionSize,
+    accounts: TransferAccounts,
+    rng: ChaCha8Rng,
+}
+
+impl TxGenerator {
+    pub fn new(
+        tx_mix: TxMix,
+        compute_budget: ComputeBudget,
+        transaction_size: TransactionSize,
+        accounts: TransferAccounts,
+        rng: ChaCha8Rng,
+    ) -> Self {
+        Self {
+            tx_mix,
+            compute_budget,
+            transaction_size,
+            accounts,
+            rng,
+        }
+    }
+
+    /// the memo is the content of memo transactions, ignored by the other kinds
+    pub fn create(
+        &mut self,
+        memo: &[u8],
+        payer: &Keypair,
+        blockhash: Hash,
+    ) -> (TxKind, Transaction) {
+        let kind = self.tx_mix.pick(&mut self.rng);
+        // random amounts keep the signatures of transfers within a blockhash apart
+        let amount = self.rng.gen_range(1..=1_000_000);
+        let mut instructions = self.compute_budget.instructions();
+        let mut memo_signers = vec![];
+        match kind {
+            TxKind::Memo => {
+                if let TransactionSize::Large = self.transaction_size {
+                    memo_signers = (0..8).map(|_| Keypair::new()).collect();
+                }
+                instructions.push(BenchHelper::memo_instruction(memo, &memo_signers));
+            }
+            TxKind::Transfer => {
+                instructions.push(system_instruction::transfer(
+                    &payer.pubkey(),
+                    &self.accounts.recipient,
+                    amount,
+                ));
+            }
+            TxKind::SplTransfer => {
+                let token = self
+                    .accounts
+                    .token
+                    .as_ref()
+                    .expect("token accounts are created for spl transfers");
+                instructions.push(
+                    spl_token::instruction::transfer(
+                        &spl_token::id(),
+                        &token.source,
+                        &token.destination,
+                        &payer.pubkey(),
+                        &[],
+                        amount,
+                    )
+                    .expect("valid spl token transfer"),
+                );
+            }
+        }
+
+        let message = Message::new(&instructions, Some(&payer.pubkey()));
+        let mut signers = vec![payer];
+        signers.extend(memo_signers.iter());
+        (kind, Transaction::new(&signers, message, blockhash))
+    }
+}
+
+#[test]
+fn tx_mix_parses_weights() {
+    let tx_mix: TxMix = "memo=2, transfer,spl-transfer=0".parse().unwrap();
+    assert_eq!(
+        tx_mix.weights,
+        vec![
+            (TxKind::Memo, 2),
+            (TxKind::Transfer, 1),
+            (TxKind::SplTransfer, 0)
+        ]
+    );
+    assert!(!tx_mix.contains(TxKind::SplTransfer));
+    assert_eq!(tx_mix.to_string(), "memo=2,transfer=1,spl-transfer=0");
+
+    assert!("memo=0".parse::<TxMix>().is_err());
+    assert!("vote".parse::<TxMix>().is_err());
+}
+
+#[test]
+fn tx_mix_picks_by_weight() {
+    use rand::SeedableRng;
+
+    let tx_mix: TxMix = "memo=3,transfer=1".parse().unwrap();
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+    let memos = (0..4000)
+        .filter(|_| tx_mix.pick(&mut rng) == TxKind::Memo)
+        .count();
+    assert!((2800..3200).contains(&memos), "{memos} memos");
+}
@@ -0,0 +1,386 @@
+use std::future::Future;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use log::{info, warn};
+use solana_lite_rpc_cluster_endpoints::rpc_polling;
+use solana_lite_rpc_core::structures::produced_block::{ProducedBlock, TransactionInfo};
+use solana_lite_rpc_core::traits::block_storage::BlockStorage;
+use solana_lite_rpc_core::AnyhowJoinHandle;
+use solana_sdk::clock::UnixTimestamp;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::message::v0::LoadedAddresses;
+use solana_sdk::signature::Signature;
+use solana_sdk::slot_history::Slot;
+use solana_sdk::transaction::VersionedTransaction;
+use solana_storage_bigtable::{CredentialType, LedgerStorage, LedgerStorageConfig};
+use solana_transaction_status::{
+    BlockEncodingOptions, EncodedConfirmedTransactionWithStatusMeta, TransactionDetails,
+    TransactionStatusMeta, UiTransactionEncoding, VersionedConfirmedBlock,
+    VersionedTransactionWithStatusMeta,
+};
+
+// newest slot the binary search for the last block starts from, beyond any slot of a cluster
+const MAX_SLOT: Slot = 1 << 40;
+const NEW_BLOCKS_BATCH: usize = 1000;
+// the slot range served to the requests is read from the table at this interval
+const SLOT_RANGE_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+// first slot while the table has no block
+const NO_SLOT: Slot = Slot::MAX;
+
+/// BlockStorage on the BigTable schema of solana-validator (tables blocks, tx and tx-by-addr),
+/// e.g. the ledger uploaded by a validator or warehouse node.
+/// Written blocks only carry what lite-rpc knows of a transaction: the signatures after the first one are
+/// default signatures, the fee, balances and logs are empty.
+#[derive(Clone)]
+pub struct BigTableBlockStorage {
+    ledger_storage: LedgerStorage,
+    read_only: bool,
+    // slot range of the table, refreshed in the background and extended by the written blocks
+    first_slot: Arc<AtomicU64>,
+    last_slot: Arc<AtomicU64>,
+}
+
+impl BigTableBlockStorage {
+    /// credentials are read from GOOGLE_APPLICATION_CREDENTIALS
+    pub async fn new(
+        instance_name: String,
+        app_profile_id: Option<String>,
+        read_only: bool,
+    ) -> anyhow::Result<Self> {
+        let default_config = LedgerStorageConfig::default();
+        let config = LedgerStorageConfig {
+            read_only,
+            timeout: Some(Duration::from_secs(30)),
+            credential_type: CredentialType::Filepath(None),
+            instance_name,
+            app_profile_id: app_profile_id.unwrap_or(default_config.app_profile_id),
+            ..default_config
+        };
+        let ledger_storage = LedgerStorage::new_with_config(config)
+            .await
+            .context("connect to bigtable")?;
+        let block_storage = Self {
+            ledger_storage,
+            read_only,
+            first_slot: Arc::new(AtomicU64::new(NO_SLOT)),
+            last_slot: Arc::new(AtomicU64::new(0)),
+        };
+        block_storage.refresh_slot_range().await?;
+        let slot_range = block_storage.cached_slot_range();
+        if read_only && slot_range.is_empty() {
+            bail!("no blocks in bigtable");
+        }
+        info!("Block storage in bigtable with slots {:?}", slot_range);
+
+        Ok(block_storage)
+    }
+
+    /// keeps the slot range up to date with the blocks other writers add to the table
+    pub fn start_slot_range_refresh(&self) -> AnyhowJoinHandle {
+        let block_storage = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SLOT_RANGE_REFRESH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(err) = block_storage.refresh_slot_range().await {
+                    warn!("Failed to read the slot range from bigtable: {err:?}");
+                }
+            }
+        })
+    }
+
+    pub async fn get_transaction(
+        &self,
+        signature: &Signature,
+    ) -> anyhow::Result<Option<EncodedConfirmedTransactionWithStatusMeta>> {
+        let Some(transaction) = self
+            .ledger_storage
+            .get_confirmed_transaction(signature)
+            .await?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(
+            transaction.encode(UiTransactionEncoding::Base64, Some(0))?,
+        ))
+    }
+
+    fn cached_slot_range(&self) -> RangeInclusive<Slot> {
+        self.first_slot.load(Ordering::Relaxed)..=self.last_slot.load(Ordering::Relaxed)
+    }
+
+    // the newest slot is searched once and then followed with the blocks added after it
+    async fn refresh_slot_range(&self) -> anyhow::Result<()> {
+        let Some(first_slot) = self.ledger_storage.get_first_available_block().await? else {
+            return Ok(());
+        };
+        let mut last_slot = self.last_slot.load(Ordering::Relaxed);
+        if last_slot < first_slot {
+            last_slot = find_last_slot(first_slot, |slot| async move {
+                Ok(!self
+                    .ledger_storage
+                    .get_confirmed_blocks(slot, 1)
+                    .await?
+                    .is_empty())
+            })
+            .await?;
+        }
+        loop {
+            let new_slots = self
+                .ledger_storage
+                .get_confirmed_blocks(last_slot + 1, NEW_BLOCKS_BATCH)
+                .await?;
+            if let Some(new_last_slot) = new_slots.last() {
+                last_slot = *new_last_slot;
+            }
+            if new_slots.len() < NEW_BLOCKS_BATCH {
+                break;
+            }
+        }
+        // the oldest blocks might have been deleted from the table
+        self.first_slot.store(first_slot, Ordering::Relaxed);
+        self.last_slot.fetch_max(last_slot, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+// block in the solana-validator format, see the comment of BigTableBlockStorage for what is missing
+fn versioned_confirmed_block(block: &ProducedBlock) -> anyhow::Result<VersionedConfirmedBlock> {
+    let transactions = block
+        .transactions
+        .iter()
+        .map(transaction_with_status_meta)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(VersionedConfirmedBlock {
+        previous_blockhash: block.previous_blockhash.to_string(),
+        blockhash: block.blockhash.to_string(),
+        parent_slot: block.parent_slot,
+        transactions,
+        rewards: block.rewards.clone().unwrap_or_default(),
+        block_time: Some(block.block_time as UnixTimestamp),
+        block_height: Some(block.block_height),
+    })
+}
+
+fn transaction_with_status_meta(
+    transaction: &TransactionInfo,
+) -> anyhow::Result<VersionedTransactionWithStatusMeta> {
+    let message = transaction.message.to_versioned_message()?;
+    let mut signatures =
+        vec![Signature::default(); usize::from(message.header().num_required_signatures).max(1)];
+    signatures[0] = transaction.signature;
+    // the accounts which are not in the message were loaded from the address lookup tables
+    let static_account_keys = message.static_account_keys();
+    let loaded_addresses = LoadedAddresses {
+        writable: transaction
+            .writable_accounts
+            .iter()
+            .filter(|account| !static_account_keys.contains(account))
+            .copied()
+            .collect(),
+        readonly: transaction
+            .readable_accounts
+            .iter()
+            .filter(|account| !static_account_keys.contains(account))
+            .copied()
+            .collect(),
+    };
+    let status = match &transaction.err {
+        Some(err) => Err(err.clone()),
+        None => Ok(()),
+    };
+    Ok(VersionedTransactionWithStatusMeta {
+        transaction: VersionedTransaction {
+            signatures,
+            message,
+        },
+        meta: TransactionStatusMeta {
+            status,
+            fee: 0,
+            pre_balances: vec![],
+            post_balances: vec![],
+            inner_instructions: None,
+            log_messages: None,
+            pre_token_balances: None,
+            post_token_balances: None,
+            rewards: None,
+            loaded_addresses,
+            return_data: None,
+            compute_units_consumed: transaction.cu_consumed,
+        },
+    })
+}
+
+// binary search of the newest slot with a block, the table only answers which blocks exist at or after a slot;
+// a block exists at first_slot
+async fn find_last_slot<F, Fut>(first_slot: Slot, has_block_at_or_after: F) -> anyhow::Result<Slot>
+where
+    F: Fn(Slot) -> Fut,
+    Fut: Future<Output = anyhow::Result<bool>>,
+{
+    let (mut low, mut high) = (first_slot, MAX_SLOT.max(first_slot));
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if has_block_at_or_after(mid).await? {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+#[async_trait]
+impl BlockStorage for BigTableBlockStorage {
+    async fn save_block(&self, block: &ProducedBlock) -> anyhow::Result<()> {
+        if self.read_only {
+            bail!(
+                "bigtable block storage is read-only, block {} is not stored",
+                block.slot
+            );
+        }
+        // uploading a slot again overwrites its rows
+        self.ledger_storage
+            .upload_confirmed_block(block.slot, versioned_confirmed_block(block)?)
+            .await
+            .with_context(|| format!("upload block {} to bigtable", block.slot))?;
+        self.first_slot.fetch_min(block.slot, Ordering::Relaxed);
+        self.last_slot.fetch_max(block.slot, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    async fn query_block(&self, slot: Slot) -> anyhow::Result<ProducedBlock> {
+        let confirmed_block = self
+            .ledger_storage
+            .get_confirmed_block(slot)
+            .await
+            .with_context(|| format!("Block {} not found in bigtable", slot))?;
+        let ui_block = confirmed_block.encode_with_options(
+            UiTransactionEncoding::Base64,
+            BlockEncodingOptions {
+                transaction_details: TransactionDetails::Full,
+                show_rewards: true,
+                max_supported_transaction_version: Some(0),
+            },
+        )?;
+        rpc_polling::poll_blocks::from_ui_block(ui_block, slot, CommitmentConfig::finalized())
+    }
+
+    // the block is read but not converted, bigtable has no row with the block time only
+    async fn query_block_time(&self, slot: Slot) -> anyhow::Result<Option<UnixTimestamp>> {
+        match self.ledger_storage.get_confirmed_block(slot).await {
+            Ok(confirmed_block) => Ok(confirmed_block.block_time),
+            Err(solana_storage_bigtable::Error::BlockNotFound(_)) => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("read block {} from bigtable", slot)),
+        }
+    }
+
+    async fn query_first_slot(&self, slots: RangeInclusive<Slot>) -> anyhow::Result<Option<Slot>> {
+        if slots.is_empty() {
+            return Ok(None);
+        }
+        let first_slot = self
+            .ledger_storage
+            .get_confirmed_blocks(*slots.start(), 1)
+            .await?
+            .first()
+            .copied();
+        Ok(first_slot.filter(|slot| slots.contains(slot)))
+    }
+
+    async fn get_slot_range(&self) -> RangeInclusive<Slot> {
+        self.cached_slot_range()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_lite_rpc_core::structures::produced_block::TransactionMessage;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+    use solana_sdk::message::{v0, VersionedMessage};
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+
+    async fn find_last_stored_slot(first_slot: Slot, stored_slots: &[Slot]) -> Slot {
+        find_last_slot(first_slot, |slot| {
+            let found = stored_slots.iter().any(|stored| *stored >= slot);
+            async move { Ok(found) }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_find_last_slot() {
+        assert_eq!(find_last_stored_slot(100, &[100]).await, 100);
+        assert_eq!(
+            find_last_stored_slot(100, &[100, 101, 150, 255744000]).await,
+            255744000
+        );
+        // skipped slots
+        assert_eq!(
+            find_last_stored_slot(255744000, &[255744000, 255744002, 255744007]).await,
+            255744007
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_last_slot_at_the_limit() {
+        assert_eq!(find_last_stored_slot(0, &[0, MAX_SLOT]).await, MAX_SLOT);
+        assert_eq!(
+            find_last_stored_slot(MAX_SLOT + 5, &[MAX_SLOT + 5]).await,
+            MAX_SLOT + 5
+        );
+    }
+
+    #[test]
+    fn test_written_transaction_keeps_the_first_signature_and_the_loaded_addresses() {
+        let payer = Pubkey::new_unique();
+        let cosigner = Pubkey::new_unique();
+        let loaded = Pubkey::new_unique();
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![AccountMeta::new_readonly(cosigner, true)],
+        );
+        let message = VersionedMessage::V0(
+            v0::Message::try_compile(&payer, &[instruction], &[], Hash::new_unique()).unwrap(),
+        );
+        let signature = Signature::new_unique();
+        let transaction = TransactionInfo {
+            signature,
+            is_vote: false,
+            err: None,
+            cu_requested: None,
+            prioritization_fees: None,
+            cu_consumed: Some(1_500),
+            recent_blockhash: *message.recent_blockhash(),
+            message: TransactionMessage::new(bincode::serialize(&message).unwrap()),
+            writable_accounts: vec![payer, loaded],
+            readable_accounts: vec![cosigner],
+            address_lookup_tables: vec![],
+            program_ids: vec![],
+        };
+
+        let written = transaction_with_status_meta(&transaction).unwrap();
+        assert_eq!(
+            written.transaction.signatures,
+            vec![signature, Signature::default()]
+        );
+        assert_eq!(written.meta.loaded_addresses.writable, vec![loaded]);
+        assert!(written.meta.loaded_addresses.readonly.is_empty());
+        assert_eq!(written.meta.compute_units_consumed, Some(1_500));
+        assert!(written.meta.status.is_ok());
+    }
+}
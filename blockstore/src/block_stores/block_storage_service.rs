@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use log::{debug, info, warn};
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
+use solana_lite_rpc_core::traits::block_storage::BlockStorage;
+use solana_lite_rpc_core::types::BlockStream;
+use solana_lite_rpc_core::AnyhowJoinHandle;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio_util::sync::CancellationToken;
+
+lazy_static::lazy_static! {
+    static ref BLOCK_STORAGE_SAVED_BLOCKS: IntCounter =
+        register_int_counter!(opts!("literpc_block_storage_saved_blocks", "Number of finalized blocks saved to the block storage")).unwrap();
+    static ref BLOCK_STORAGE_SAVE_ERRORS: IntCounter =
+        register_int_counter!(opts!("literpc_block_storage_save_errors", "Number of blocks which could not be saved to the block storage")).unwrap();
+}
+
+/// Persists the finalized blocks of the block stream to a BlockStorage
+pub struct BlockStorageService {}
+
+impl BlockStorageService {
+    /// on shutdown the blocks already received are saved before the task ends
+    pub fn start(
+        block_storage: Arc<dyn BlockStorage>,
+        mut block_stream: BlockStream,
+        shutdown: CancellationToken,
+    ) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            loop {
+                let block = tokio::select! {
+                    block = block_stream.recv() => match block {
+                        Ok(block) => block,
+                        Err(RecvError::Lagged(lagged)) => {
+                            warn!("block storage lagged by {lagged} blocks - continue");
+                            continue;
+                        }
+                        Err(RecvError::Closed) => anyhow::bail!("block stream closed"),
+                    },
+                    _ = shutdown.cancelled() => {
+                        let mut saved = 0;
+                        loop {
+                            match block_stream.try_recv() {
+                                Ok(block) => {
+                                    if save_finalized_block(block_storage.as_ref(), &block).await {
+                                        saved += 1;
+                                    }
+                                }
+                                Err(TryRecvError::Lagged(_)) => continue,
+                                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+                            }
+                        }
+                        info!("block storage saved {saved} pending blocks on shutdown");
+                        return Ok(());
+                    }
+                };
+                save_finalized_block(block_storage.as_ref(), &block).await;
+            }
+        })
+    }
+}
+
+/// returns true if the block was finalized and saved
+async fn save_finalized_block(block_storage: &dyn BlockStorage, block: &ProducedBlock) -> bool {
+    if !block.commitment_config.is_finalized() {
+        return false;
+    }
+
+    match block_storage.save_block(block).await {
+        Ok(()) => {
+            BLOCK_STORAGE_SAVED_BLOCKS.inc();
+            debug!("saved block {} to block storage", block.slot);
+            true
+        }
+        Err(err) => {
+            BLOCK_STORAGE_SAVE_ERRORS.inc();
+            warn!(
+                "failed to save block {} to block storage: {err:?}",
+                block.slot
+            );
+            false
+        }
+    }
+}
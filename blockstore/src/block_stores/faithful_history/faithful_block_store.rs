@@ -1,12 +1,17 @@
-use anyhow::bail;
+use anyhow::{bail, Context};
 use log::warn;
 use solana_lite_rpc_cluster_endpoints::rpc_polling;
 use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
 use solana_rpc_client::nonblocking::rpc_client::RpcClient;
-use solana_rpc_client_api::config::RpcBlockConfig;
-use solana_sdk::clock::Slot;
+use solana_rpc_client_api::config::{RpcBlockConfig, RpcTransactionConfig};
+use solana_rpc_client_api::request::RpcRequest;
+use solana_sdk::clock::{Slot, UnixTimestamp};
 use solana_sdk::commitment_config::CommitmentConfig;
-use solana_transaction_status::{TransactionDetails, UiTransactionEncoding};
+use solana_sdk::signature::Signature;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionDetails, UiConfirmedBlock,
+    UiTransactionEncoding,
+};
 use std::ops::RangeInclusive;
 use std::sync::Arc;
 
@@ -42,11 +47,30 @@ impl FaithfulBlockStore {
             .get_block_with_config(slot, faithful_config)
             .await
         {
-            Ok(block) => Ok(rpc_polling::poll_blocks::from_ui_block(
-                block,
-                slot,
-                CommitmentConfig::finalized(),
-            )),
+            Ok(block) => {
+                rpc_polling::poll_blocks::from_ui_block(block, slot, CommitmentConfig::finalized())
+            }
+            Err(err) => {
+                bail!(format!(
+                    "Block {} not found in faithful_history: {}",
+                    slot, err
+                ));
+            }
+        }
+    }
+
+    // block as returned by the faithful rpc api, encoded according to the config
+    pub async fn get_ui_block(
+        &self,
+        slot: Slot,
+        config: RpcBlockConfig,
+    ) -> anyhow::Result<UiConfirmedBlock> {
+        match self
+            .faithful_rpc_client
+            .get_block_with_config(slot, config)
+            .await
+        {
+            Ok(block) => Ok(block),
             Err(err) => {
                 bail!(format!(
                     "Block {} not found in faithful_history: {}",
@@ -55,4 +79,27 @@ impl FaithfulBlockStore {
             }
         }
     }
+
+    pub async fn get_block_time(&self, slot: Slot) -> anyhow::Result<UnixTimestamp> {
+        self.faithful_rpc_client
+            .get_block_time(slot)
+            .await
+            .with_context(|| format!("Block time of {} not found in faithful_history", slot))
+    }
+
+    pub async fn get_transaction(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> anyhow::Result<Option<EncodedConfirmedTransactionWithStatusMeta>> {
+        // unknown signatures are returned as null
+        let transaction = self
+            .faithful_rpc_client
+            .send(
+                RpcRequest::GetTransaction,
+                serde_json::json!([signature.to_string(), config]),
+            )
+            .await?;
+        Ok(transaction)
+    }
 }
@@ -1,3 +1,8 @@
+pub mod bigtable_block_storage;
+pub mod block_storage_service;
 pub mod faithful_history;
 pub mod multiple_strategy_block_store;
+pub mod object_store_block_storage;
+#[cfg(feature = "postgres")]
 pub mod postgres;
+pub mod warm_start;
@@ -1,8 +1,8 @@
 use crate::block_stores::faithful_history::faithful_block_store::FaithfulBlockStore;
-use crate::block_stores::postgres::postgres_block_store_query::PostgresQueryBlockStore;
 use anyhow::{bail, Context, Result};
 use log::{debug, trace};
 use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
+use solana_lite_rpc_core::traits::block_storage::BlockStorage;
 use solana_rpc_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::slot_history::Slot;
 use std::ops::{Deref, RangeInclusive};
@@ -10,7 +10,7 @@ use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub enum BlockSource {
-    // serve two epochs from postgres
+    // serve recent epochs from the persistent block storage (postgres or object store)
     RecentEpochDatabase,
     // serve epochs older than two from faithful_history service
     FaithfulArchive,
@@ -33,7 +33,7 @@ impl Deref for BlockStorageData {
 
 // you might need to add a read-cache instead
 pub struct MultipleStrategyBlockStorage {
-    block_storage_query: PostgresQueryBlockStore,
+    persistent_block_storage: Arc<dyn BlockStorage>,
     // note supported ATM
     faithful_block_storage: Option<FaithfulBlockStore>, // to fetch legacy blocks from faithful_history
                                                         // last_confirmed_slot: Arc<AtomicU64>,
@@ -41,11 +41,11 @@ pub struct MultipleStrategyBlockStorage {
 
 impl MultipleStrategyBlockStorage {
     pub fn new(
-        block_storage_query: PostgresQueryBlockStore,
+        persistent_block_storage: Arc<dyn BlockStorage>,
         _faithful_rpc_client: Option<Arc<RpcClient>>,
     ) -> Self {
         Self {
-            block_storage_query,
+            persistent_block_storage,
             // faithful_history not used ATM
             faithful_block_storage: None,
             // faithful_block_storage: faithful_rpc_client.map(|rpc| FaithfulBlockStore::new(rpc)),
@@ -55,7 +55,7 @@ impl MultipleStrategyBlockStorage {
     // we need to build the slots from right to left
     pub async fn get_slot_range(&self) -> RangeInclusive<Slot> {
         // merge them
-        let persistent_storage_range = self.block_storage_query.get_slot_range().await;
+        let persistent_storage_range = self.persistent_block_storage.get_slot_range().await;
         trace!("Persistent storage range: {:?}", persistent_storage_range);
 
         let mut lower = *persistent_storage_range.start();
@@ -98,14 +98,14 @@ impl MultipleStrategyBlockStorage {
         // 2.1. if yes; fetch from Postgres
         // 2.2. if not: try to fetch from faithful_history
 
-        match self.block_storage_query.is_block_in_range(slot).await {
+        match self.persistent_block_storage.is_block_in_range(slot).await {
             true => {
                 debug!(
                     "Assume block {} to be available in persistent block-storage",
                     slot,
                 );
                 let lookup = self
-                    .block_storage_query
+                    .persistent_block_storage
                     .query_block(slot)
                     .await
                     .context(format!("block {} not found although it was in range", slot));
@@ -0,0 +1,224 @@
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use log::{debug, info};
+use object_store::path::Path;
+use object_store::ObjectStore;
+use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
+use solana_lite_rpc_core::traits::block_storage::BlockStorage;
+use solana_sdk::slot_history::Slot;
+use tokio::sync::Mutex;
+
+const BLOCKS_DIR: &str = "blocks";
+const SLOT_RANGE_FILE: &str = "slot_range.bin";
+const ZSTD_LEVEL: i32 = 3;
+
+/// BlockStorage for deployments without postgres: every block is one zstd compressed bincode object
+/// `<prefix>/blocks/<slot>.bin.zst` in a local directory or an object store (s3, gcs).
+pub struct ObjectStoreBlockStorage {
+    store: Box<dyn ObjectStore>,
+    prefix: Path,
+    // min and max stored slot, None if empty
+    // held while the slot range object is written so it never goes backwards
+    slot_range: Arc<Mutex<Option<(Slot, Slot)>>>,
+}
+
+impl ObjectStoreBlockStorage {
+    /// url is either `file:///some/dir`, `s3://bucket/prefix` or `gs://bucket/prefix`;
+    /// credentials are taken from the usual environment variables (e.g. AWS_ACCESS_KEY_ID, AWS_REGION).
+    /// The slot range is restored from `<prefix>/slot_range.bin`, which is updated with every saved block.
+    pub async fn new(url: &str) -> anyhow::Result<Self> {
+        let parsed_url = url::Url::parse(url).context("invalid block storage url")?;
+        // config keys of object_store are lowercase
+        let options = std::env::vars().map(|(key, value)| (key.to_ascii_lowercase(), value));
+        let (store, prefix) = object_store::parse_url_opts(&parsed_url, options)
+            .context("unsupported block storage url")?;
+
+        let slot_range = match read_slot_range(store.as_ref(), &prefix).await? {
+            Some(slot_range) => slot_range,
+            None => {
+                // storage written before the slot range was persisted, list the blocks once
+                let slot_range = list_slot_range(store.as_ref(), &prefix).await?;
+                if let Some(slot_range) = slot_range {
+                    write_slot_range(store.as_ref(), &prefix, slot_range).await?;
+                }
+                slot_range
+            }
+        };
+        info!(
+            "Storing blocks in {} - found stored slots {:?}",
+            url, slot_range
+        );
+
+        Ok(Self {
+            store,
+            prefix,
+            slot_range: Arc::new(Mutex::new(slot_range)),
+        })
+    }
+
+    fn block_path(&self, slot: Slot) -> Path {
+        self.prefix
+            .child(BLOCKS_DIR)
+            .child(format!("{}.bin.zst", slot))
+    }
+}
+
+#[async_trait]
+impl BlockStorage for ObjectStoreBlockStorage {
+    async fn save_block(&self, block: &ProducedBlock) -> anyhow::Result<()> {
+        let serialized = bincode::serialize(block).context("serialize block")?;
+        let compressed = zstd::encode_all(serialized.as_slice(), ZSTD_LEVEL)?;
+        let path = self.block_path(block.slot);
+        self.store
+            .put(&path, compressed.into())
+            .await
+            .with_context(|| format!("failed to write {}", path))?;
+        debug!("Saved block {} to {}", block.slot, path);
+
+        let mut slot_range = self.slot_range.lock().await;
+        let updated = match *slot_range {
+            Some((min, max)) => (min.min(block.slot), max.max(block.slot)),
+            None => (block.slot, block.slot),
+        };
+        if *slot_range != Some(updated) {
+            write_slot_range(self.store.as_ref(), &self.prefix, updated).await?;
+            *slot_range = Some(updated);
+        }
+        Ok(())
+    }
+
+    async fn query_block(&self, slot: Slot) -> anyhow::Result<ProducedBlock> {
+        let path = self.block_path(slot);
+        let compressed = self
+            .store
+            .get(&path)
+            .await
+            .with_context(|| format!("Block {} not found in object store", slot))?
+            .bytes()
+            .await?;
+        let serialized = zstd::decode_all(compressed.as_ref())?;
+        Ok(bincode::deserialize(&serialized)?)
+    }
+
+    // the objects are named by slot without padding, so a range cannot be listed; the few slots of the
+    // range are checked one after the other within the stored slot range
+    async fn query_first_slot(&self, slots: RangeInclusive<Slot>) -> anyhow::Result<Option<Slot>> {
+        let stored = self.get_slot_range().await;
+        let first_slot = (*slots.start()).max(*stored.start());
+        let last_slot = (*slots.end()).min(*stored.end());
+        for slot in first_slot..=last_slot {
+            match self.store.head(&self.block_path(slot)).await {
+                Ok(_) => return Ok(Some(slot)),
+                Err(object_store::Error::NotFound { .. }) => continue,
+                Err(err) => {
+                    return Err(err).with_context(|| format!("failed to look up block {}", slot))
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    async fn get_slot_range(&self) -> RangeInclusive<Slot> {
+        match *self.slot_range.lock().await {
+            Some((min, max)) => min..=max,
+            None => RangeInclusive::new(1, 0), // empty
+        }
+    }
+}
+
+async fn read_slot_range(
+    store: &dyn ObjectStore,
+    prefix: &Path,
+) -> anyhow::Result<Option<(Slot, Slot)>> {
+    match store.get(&prefix.child(SLOT_RANGE_FILE)).await {
+        Ok(result) => {
+            let serialized = result.bytes().await.context("read stored slot range")?;
+            Ok(Some(
+                bincode::deserialize(&serialized).context("invalid stored slot range")?,
+            ))
+        }
+        Err(object_store::Error::NotFound { .. }) => Ok(None),
+        Err(err) => Err(err).context("read stored slot range"),
+    }
+}
+
+async fn write_slot_range(
+    store: &dyn ObjectStore,
+    prefix: &Path,
+    slot_range: (Slot, Slot),
+) -> anyhow::Result<()> {
+    let serialized = bincode::serialize(&slot_range).context("serialize slot range")?;
+    store
+        .put(&prefix.child(SLOT_RANGE_FILE), serialized.into())
+        .await
+        .context("write stored slot range")?;
+    Ok(())
+}
+
+async fn list_slot_range(
+    store: &dyn ObjectStore,
+    prefix: &Path,
+) -> anyhow::Result<Option<(Slot, Slot)>> {
+    let blocks_dir = prefix.child(BLOCKS_DIR);
+    store
+        .list(Some(&blocks_dir))
+        .try_fold(None, |range: Option<(Slot, Slot)>, meta| async move {
+            let Some(slot) = meta
+                .location
+                .filename()
+                .and_then(|filename| filename.strip_suffix(".bin.zst"))
+                .and_then(|slot| slot.parse::<Slot>().ok())
+            else {
+                return Ok(range);
+            };
+            Ok(Some(match range {
+                Some((min, max)) => (min.min(slot), max.max(slot)),
+                None => (slot, slot),
+            }))
+        })
+        .await
+        .context("list stored blocks")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::commitment_config::CommitmentConfig;
+
+    fn block(slot: Slot) -> ProducedBlock {
+        ProducedBlock {
+            block_time: 1699260872,
+            ..ProducedBlock::new_for_tests(slot, CommitmentConfig::finalized())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slot_range_is_restored_without_listing() {
+        let dir = std::env::temp_dir().join(format!("literpc-blocks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let url = format!("file://{}", dir.display());
+
+        let storage = ObjectStoreBlockStorage::new(&url).await.unwrap();
+        assert!(storage.get_slot_range().await.is_empty());
+        storage.save_block(&block(12)).await.unwrap();
+        storage.save_block(&block(10)).await.unwrap();
+        storage.save_block(&block(11)).await.unwrap();
+        assert_eq!(storage.get_slot_range().await, 10..=12);
+
+        let restored = ObjectStoreBlockStorage::new(&url).await.unwrap();
+        assert_eq!(restored.get_slot_range().await, 10..=12);
+        assert_eq!(restored.query_block(11).await.unwrap().slot, 11);
+
+        // a storage without the slot range object is listed once
+        std::fs::remove_file(dir.join(SLOT_RANGE_FILE)).unwrap();
+        let listed = ObjectStoreBlockStorage::new(&url).await.unwrap();
+        assert_eq!(listed.get_slot_range().await, 10..=12);
+        assert!(dir.join(SLOT_RANGE_FILE).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,13 +1,21 @@
+pub mod postgres_block_archiver;
+pub mod postgres_block_storage;
+pub mod postgres_block_store_pruner;
 pub mod postgres_block_store_query;
 pub mod postgres_block_store_writer;
+pub mod postgres_epoch_manager;
+pub mod postgres_migrations;
+pub use postgres_compression::PayloadCompression;
 pub use postgres_config::PostgresSessionConfig;
 pub use postgres_session::PostgresSession;
 pub use postgres_session::PostgresWriteSession;
 
 mod postgres_block;
+mod postgres_compression;
 mod postgres_config;
 mod postgres_epoch;
 mod postgres_session;
+mod postgres_signature_index;
 mod postgres_transaction;
 
 // role for block store componente owner with full write access
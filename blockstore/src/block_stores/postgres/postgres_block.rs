@@ -1,3 +1,4 @@
+use super::postgres_compression::{read_payload, PayloadCompression};
 use super::postgres_epoch::PostgresEpoch;
 use super::postgres_session::PostgresSession;
 use log::{debug, warn};
@@ -6,7 +7,10 @@ use solana_lite_rpc_core::structures::produced_block::TransactionInfo;
 use solana_lite_rpc_core::{encoding::BASE64, structures::produced_block::ProducedBlock};
 use solana_sdk::clock::Slot;
 use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
 use solana_transaction_status::Reward;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 use std::time::Instant;
 use tokio_postgres::types::ToSql;
 
@@ -18,7 +22,9 @@ pub struct PostgresBlock {
     pub parent_slot: i64,
     pub block_time: i64,
     pub previous_blockhash: String,
+    // base64 encoded, None if stored compressed in rewards_zstd
     pub rewards: Option<String>,
+    pub rewards_zstd: Option<Vec<u8>>,
     pub leader_id: Option<String>,
 }
 
@@ -31,47 +37,72 @@ impl From<&ProducedBlock> for PostgresBlock {
             .unwrap_or(None);
 
         Self {
-            blockhash: value.blockhash.clone(),
+            blockhash: value.blockhash.to_string(),
             block_height: value.block_height as i64,
             slot: value.slot as i64,
             parent_slot: value.parent_slot as i64,
             block_time: value.block_time as i64,
-            previous_blockhash: value.previous_blockhash.clone(),
+            previous_blockhash: value.previous_blockhash.to_string(),
             // TODO add leader_id, etc.
             rewards,
-            leader_id: value.leader_id.clone(),
+            rewards_zstd: None,
+            leader_id: value.leader_id.map(|leader_id| leader_id.to_string()),
         }
     }
 }
 
 impl PostgresBlock {
+    pub fn compress(&mut self, payload_compression: &PayloadCompression) {
+        if let Some(compressed) = self
+            .rewards
+            .as_deref()
+            .and_then(|rewards| payload_compression.compress(rewards))
+        {
+            self.rewards = None;
+            self.rewards_zstd = Some(compressed);
+        }
+    }
+
+    // base64 encoded rewards
+    pub fn rewards_base64(&self) -> anyhow::Result<Option<String>> {
+        read_payload(&self.rewards, &self.rewards_zstd)
+    }
+
     pub fn to_produced_block(
         &self,
         transaction_infos: Vec<TransactionInfo>,
         commitment_config: CommitmentConfig,
     ) -> ProducedBlock {
         let rewards_vec: Option<Vec<Reward>> = self
-            .rewards
-            .as_ref()
-            .map(|x| BASE64.deserialize::<Vec<Reward>>(x).ok())
-            .unwrap_or(None);
+            .rewards_base64()
+            .ok()
+            .flatten()
+            .and_then(|x| BASE64.deserialize::<Vec<Reward>>(&x).ok());
 
         ProducedBlock {
             // TODO implement
             transactions: transaction_infos,
             leader_id: None,
-            blockhash: self.blockhash.clone(),
+            blockhash: parse_hash(&self.blockhash, self.slot),
             block_height: self.block_height as u64,
             slot: self.slot as Slot,
             parent_slot: self.parent_slot as Slot,
             block_time: self.block_time as u64,
             commitment_config,
-            previous_blockhash: self.previous_blockhash.clone(),
+            previous_blockhash: parse_hash(&self.previous_blockhash, self.slot),
             rewards: rewards_vec,
         }
     }
 }
 
+// blocks are only written by lite-rpc, a hash which cannot be parsed is reported and defaulted
+fn parse_hash(hash: &str, slot: i64) -> Hash {
+    Hash::from_str(hash).unwrap_or_else(|err| {
+        warn!("Failed to parse blockhash {hash} of block {slot}: {err:?}");
+        Hash::default()
+    })
+}
+
 impl PostgresBlock {
     pub fn build_create_table_statement(epoch: EpochRef) -> String {
         let schema = PostgresEpoch::build_schema_name(epoch);
@@ -86,6 +117,7 @@ impl PostgresBlock {
                 block_time BIGINT NOT NULL,
                 previous_blockhash TEXT NOT NULL,
                 rewards TEXT,
+                rewards_zstd BYTEA,
                 CONSTRAINT pk_block_slot PRIMARY KEY(slot)
             ) WITH (FILLFACTOR=90);
             CLUSTER {schema}.blocks USING pk_block_slot;
@@ -94,11 +126,27 @@ impl PostgresBlock {
         )
     }
 
+    pub fn build_query_first_slot_statement(
+        epoch: EpochRef,
+        slots: &RangeInclusive<Slot>,
+    ) -> String {
+        format!(
+            r#"
+                SELECT slot FROM {schema}.blocks
+                WHERE slot >= {first_slot} AND slot <= {last_slot}
+                ORDER BY slot LIMIT 1
+            "#,
+            schema = PostgresEpoch::build_schema_name(epoch),
+            first_slot = slots.start(),
+            last_slot = slots.end()
+        )
+    }
+
     pub fn build_query_statement(epoch: EpochRef, slot: Slot) -> String {
         format!(
             r#"
                 SELECT
-                    slot, blockhash, block_height, parent_slot, block_time, previous_blockhash, rewards, leader_id,
+                    slot, blockhash, block_height, parent_slot, block_time, previous_blockhash, rewards, rewards_zstd, leader_id,
                     {epoch}::bigint as _epoch, '{schema}'::text as _epoch_schema FROM {schema}.blocks
                 WHERE slot = {slot}
             "#,
@@ -108,13 +156,39 @@ impl PostgresBlock {
         )
     }
 
+    pub fn build_query_block_time_statement(epoch: EpochRef, slot: Slot) -> String {
+        format!(
+            r#"
+                SELECT block_time FROM {schema}.blocks
+                WHERE slot = {slot}
+            "#,
+            schema = PostgresEpoch::build_schema_name(epoch),
+            slot = slot
+        )
+    }
+
+    pub fn build_query_range_statement(epoch: EpochRef, slots: &RangeInclusive<Slot>) -> String {
+        format!(
+            r#"
+                SELECT
+                    slot, blockhash, block_height, parent_slot, block_time, previous_blockhash, rewards, rewards_zstd, leader_id
+                FROM {schema}.blocks
+                WHERE slot >= {first_slot} AND slot <= {last_slot}
+                ORDER BY slot
+            "#,
+            schema = PostgresEpoch::build_schema_name(epoch),
+            first_slot = slots.start(),
+            last_slot = slots.end()
+        )
+    }
+
     // true is actually inserted; false if operation was noop
     pub async fn save(
         &self,
         postgres_session: &PostgresSession,
         epoch: EpochRef,
     ) -> anyhow::Result<bool> {
-        const NB_ARGUMENTS: usize = 8;
+        const NB_ARGUMENTS: usize = 9;
 
         let started = Instant::now();
         let schema = PostgresEpoch::build_schema_name(epoch);
@@ -122,7 +196,7 @@ impl PostgresBlock {
 
         let statement = format!(
             r#"
-                INSERT INTO {schema}.blocks (slot, blockhash, block_height, parent_slot, block_time, previous_blockhash, rewards, leader_id)
+                INSERT INTO {schema}.blocks (slot, blockhash, block_height, parent_slot, block_time, previous_blockhash, rewards, rewards_zstd, leader_id)
                 VALUES {}
                 -- prevent updates
                 ON CONFLICT DO NOTHING
@@ -145,6 +219,7 @@ impl PostgresBlock {
         args.push(&self.block_time);
         args.push(&self.previous_blockhash);
         args.push(&self.rewards);
+        args.push(&self.rewards_zstd);
         args.push(&self.leader_id);
 
         let returning = postgres_session
@@ -189,18 +264,24 @@ impl PostgresBlock {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use solana_lite_rpc_core::structures::produced_block::TransactionMessage;
     use solana_sdk::commitment_config::CommitmentConfig;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::reward_type::RewardType;
+    use solana_sdk::signature::Signature;
 
     #[test]
     fn map_postgresblock_to_produced_block() {
+        let blockhash = Hash::new_unique();
         let block = PostgresBlock {
             slot: 5050505,
-            blockhash: "blockhash".to_string(),
+            blockhash: blockhash.to_string(),
             block_height: 4040404,
             parent_slot: 5050500,
             block_time: 12121212,
-            previous_blockhash: "previous_blockhash".to_string(),
+            previous_blockhash: Hash::new_unique().to_string(),
             rewards: None,
+            rewards_zstd: None,
             leader_id: None,
         };
 
@@ -211,21 +292,54 @@ mod tests {
 
         assert_eq!(produced_block.slot, 5050505);
         assert_eq!(produced_block.transactions.len(), 2);
+        assert_eq!(produced_block.blockhash, blockhash);
+    }
+
+    #[test]
+    fn compressed_rewards_are_restored() {
+        let rewards = vec![Reward {
+            pubkey: Pubkey::new_unique().to_string(),
+            lamports: 5000,
+            post_balance: 1000000,
+            reward_type: Some(RewardType::Voting),
+            commission: None,
+        }];
+        let mut block = PostgresBlock {
+            slot: 5050505,
+            blockhash: Hash::new_unique().to_string(),
+            block_height: 4040404,
+            parent_slot: 5050500,
+            block_time: 12121212,
+            previous_blockhash: Hash::new_unique().to_string(),
+            rewards: BASE64.serialize(&rewards).ok(),
+            rewards_zstd: None,
+            leader_id: None,
+        };
+
+        block.compress(&PayloadCompression {
+            zstd_level: Some(3),
+        });
+        assert!(block.rewards.is_none());
+        assert!(block.rewards_zstd.is_some());
+
+        let produced_block = block.to_produced_block(vec![], CommitmentConfig::confirmed());
+        assert_eq!(produced_block.rewards, Some(rewards));
     }
 
     fn create_tx_info() -> TransactionInfo {
         TransactionInfo {
-            signature: "signature".to_string(),
+            signature: Signature::new_unique(),
             is_vote: false,
             err: None,
             cu_requested: None,
             prioritization_fees: None,
             cu_consumed: None,
-            recent_blockhash: "recent_blockhash".to_string(),
-            message: "message".to_string(),
+            recent_blockhash: Hash::new_unique(),
+            message: TransactionMessage::new(b"message".to_vec()),
             writable_accounts: vec![],
             readable_accounts: vec![],
             address_lookup_tables: vec![],
+            program_ids: vec![],
         }
     }
 }
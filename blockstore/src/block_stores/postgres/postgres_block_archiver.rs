@@ -0,0 +1,293 @@
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Context;
+use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use log::{debug, info};
+use object_store::path::Path;
+use object_store::ObjectStore;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_core::structures::epoch::EpochRef;
+use solana_sdk::clock::Slot;
+
+use super::postgres_block::PostgresBlock;
+use super::postgres_block_store_query::PostgresQueryBlockStore;
+use super::postgres_transaction::PostgresTransaction;
+
+lazy_static::lazy_static! {
+    static ref BLOCKSTORE_ARCHIVED_EPOCHS: IntCounter =
+        register_int_counter!(opts!("literpc_blockstore_archived_epochs", "Number of epochs archived as parquet files before pruning")).unwrap();
+    static ref BLOCKSTORE_ARCHIVED_FILES: IntCounter =
+        register_int_counter!(opts!("literpc_blockstore_archived_files", "Number of parquet files written by the block store archiver")).unwrap();
+}
+
+// keeps the transactions of one file in memory - ~100 slots are a few hundred MB
+const SLOTS_PER_FILE: Slot = 100;
+
+/// Exports the blocks and transactions of an epoch as parquet files to a local directory or an object store.
+/// Files are partitioned hive-style by epoch so they can be queried with DuckDB or Spark, e.g.
+/// `<prefix>/epoch=592/transactions/255744000-255744099.parquet`.
+/// Columns have the same names and encodings (base64 for message, err and rewards) as the postgres tables,
+/// compressed payloads are exported decompressed.
+pub struct ParquetBlockArchiver {
+    store: Box<dyn ObjectStore>,
+    prefix: Path,
+}
+
+impl ParquetBlockArchiver {
+    /// url is either `file:///some/dir`, `s3://bucket/prefix` or `gs://bucket/prefix`;
+    /// credentials are taken from the usual environment variables (e.g. AWS_ACCESS_KEY_ID, AWS_REGION)
+    pub fn new(url: &str) -> anyhow::Result<Self> {
+        let parsed_url = url::Url::parse(url).context("invalid block store archive url")?;
+        // config keys of object_store are lowercase
+        let options = std::env::vars().map(|(key, value)| (key.to_ascii_lowercase(), value));
+        let (store, prefix) = object_store::parse_url_opts(&parsed_url, options)
+            .context("unsupported block store archive url")?;
+        info!("Archiving pruned blocks to {}", url);
+        Ok(Self { store, prefix })
+    }
+
+    pub async fn archive_epoch(
+        &self,
+        block_store_query: &PostgresQueryBlockStore,
+        epoch: EpochRef,
+        slots: &RangeInclusive<Slot>,
+    ) -> anyhow::Result<()> {
+        let started_at = Instant::now();
+        let mut first_slot = *slots.start();
+        while first_slot <= *slots.end() {
+            let file_slots = first_slot..=(first_slot + SLOTS_PER_FILE - 1).min(*slots.end());
+
+            let blocks = block_store_query
+                .query_postgres_blocks(epoch, &file_slots)
+                .await?;
+            if !blocks.is_empty() {
+                self.write_file(epoch, "blocks", &file_slots, blocks_to_batch(&blocks)?)
+                    .await?;
+            }
+
+            let transactions = block_store_query
+                .query_postgres_transactions(epoch, &file_slots)
+                .await?;
+            if !transactions.is_empty() {
+                self.write_file(
+                    epoch,
+                    "transactions",
+                    &file_slots,
+                    transactions_to_batch(&transactions)?,
+                )
+                .await?;
+            }
+
+            first_slot = *file_slots.end() + 1;
+        }
+
+        BLOCKSTORE_ARCHIVED_EPOCHS.inc();
+        info!(
+            "Archived epoch {} with slots {:?} in {:.2}s",
+            epoch,
+            slots,
+            started_at.elapsed().as_secs_f64()
+        );
+        Ok(())
+    }
+
+    async fn write_file(
+        &self,
+        epoch: EpochRef,
+        table: &str,
+        slots: &RangeInclusive<Slot>,
+        batch: RecordBatch,
+    ) -> anyhow::Result<()> {
+        let mut buffer = Vec::new();
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), Some(properties))?;
+        writer.write(&batch)?;
+        writer.close()?;
+
+        let path = self
+            .prefix
+            .child(format!("epoch={}", epoch))
+            .child(table)
+            .child(format!("{}-{}.parquet", slots.start(), slots.end()));
+        debug!("Writing {} rows to {}", batch.num_rows(), path);
+        self.store
+            .put(&path, buffer.into())
+            .await
+            .with_context(|| format!("failed to write {}", path))?;
+        BLOCKSTORE_ARCHIVED_FILES.inc();
+        Ok(())
+    }
+}
+
+fn blocks_to_batch(blocks: &[PostgresBlock]) -> anyhow::Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("slot", DataType::Int64, false),
+        Field::new("blockhash", DataType::Utf8, false),
+        Field::new("block_height", DataType::Int64, false),
+        Field::new("parent_slot", DataType::Int64, false),
+        Field::new("block_time", DataType::Int64, false),
+        Field::new("previous_blockhash", DataType::Utf8, false),
+        Field::new("rewards", DataType::Utf8, true),
+        Field::new("leader_id", DataType::Utf8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from_iter_values(blocks.iter().map(|b| b.slot))),
+        Arc::new(StringArray::from_iter_values(
+            blocks.iter().map(|b| &b.blockhash),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            blocks.iter().map(|b| b.block_height),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            blocks.iter().map(|b| b.parent_slot),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            blocks.iter().map(|b| b.block_time),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            blocks.iter().map(|b| &b.previous_blockhash),
+        )),
+        Arc::new(StringArray::from_iter(
+            blocks
+                .iter()
+                .map(|b| b.rewards_base64())
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        )),
+        Arc::new(StringArray::from_iter(
+            blocks.iter().map(|b| b.leader_id.as_deref()),
+        )),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+fn transactions_to_batch(transactions: &[PostgresTransaction]) -> anyhow::Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("signature", DataType::Utf8, false),
+        Field::new("slot", DataType::Int64, false),
+        Field::new("err", DataType::Utf8, true),
+        Field::new("cu_requested", DataType::Int64, true),
+        Field::new("prioritization_fees", DataType::Int64, true),
+        Field::new("cu_consumed", DataType::Int64, true),
+        Field::new("recent_blockhash", DataType::Utf8, false),
+        Field::new("message", DataType::Utf8, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            transactions.iter().map(|tx| &tx.signature),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            transactions.iter().map(|tx| tx.slot),
+        )),
+        Arc::new(StringArray::from_iter(
+            transactions.iter().map(|tx| tx.err.as_deref()),
+        )),
+        Arc::new(Int64Array::from_iter(
+            transactions.iter().map(|tx| tx.cu_requested),
+        )),
+        Arc::new(Int64Array::from_iter(
+            transactions.iter().map(|tx| tx.prioritization_fees),
+        )),
+        Arc::new(Int64Array::from_iter(
+            transactions.iter().map(|tx| tx.cu_consumed),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            transactions.iter().map(|tx| &tx.recent_blockhash),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            transactions
+                .iter()
+                .map(|tx| tx.message_base64())
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        )),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[tokio::test]
+    async fn test_blocks_round_trip_through_parquet() {
+        let dir = std::env::temp_dir().join(format!("literpc-archive-{}", std::process::id()));
+        let archiver = ParquetBlockArchiver::new(&format!("file://{}", dir.display())).unwrap();
+        let blocks = vec![
+            PostgresBlock {
+                slot: 255744000,
+                blockhash: "blockhash1".to_string(),
+                block_height: 1000,
+                parent_slot: 255743999,
+                block_time: 1710000000,
+                previous_blockhash: "blockhash0".to_string(),
+                rewards: Some("cmV3YXJkcw==".to_string()),
+                rewards_zstd: None,
+                leader_id: None,
+            },
+            PostgresBlock {
+                slot: 255744001,
+                blockhash: "blockhash2".to_string(),
+                block_height: 1001,
+                parent_slot: 255744000,
+                block_time: 1710000001,
+                previous_blockhash: "blockhash1".to_string(),
+                rewards: None,
+                rewards_zstd: None,
+                leader_id: Some("leader".to_string()),
+            },
+        ];
+
+        archiver
+            .write_file(
+                EpochRef::new(592),
+                "blocks",
+                &(255744000..=255744001),
+                blocks_to_batch(&blocks).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let path = archiver
+            .prefix
+            .child("epoch=592")
+            .child("blocks")
+            .child("255744000-255744001.parquet");
+        let file = archiver
+            .store
+            .get(&path)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let batches = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.columns(), blocks_to_batch(&blocks).unwrap().columns());
+        let leader_ids = batch
+            .column_by_name("leader_id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert!(leader_ids.is_null(0));
+        assert_eq!(leader_ids.value(1), "leader");
+    }
+}
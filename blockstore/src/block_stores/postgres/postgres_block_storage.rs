@@ -0,0 +1,82 @@
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use solana_lite_rpc_core::structures::epoch::EpochCache;
+use solana_lite_rpc_core::structures::produced_block::{ProducedBlock, TransactionInfo};
+use solana_lite_rpc_core::traits::block_storage::BlockStorage;
+use solana_sdk::clock::UnixTimestamp;
+use solana_sdk::signature::Signature;
+use solana_sdk::slot_history::Slot;
+
+use super::postgres_block_store_query::PostgresQueryBlockStore;
+use super::postgres_block_store_writer::PostgresBlockStore;
+
+/// BlockStorage backed by the epoch schemas in postgres
+#[derive(Clone)]
+pub struct PostgresBlockStorage {
+    block_store_writer: PostgresBlockStore,
+    block_store_query: PostgresQueryBlockStore,
+    epoch_schedule: EpochCache,
+    // epoch schemas are created once per epoch, u64::MAX if none was prepared yet
+    prepared_epoch: Arc<AtomicU64>,
+}
+
+impl PostgresBlockStorage {
+    pub fn new(
+        block_store_writer: PostgresBlockStore,
+        block_store_query: PostgresQueryBlockStore,
+        epoch_schedule: EpochCache,
+    ) -> Self {
+        Self {
+            block_store_writer,
+            block_store_query,
+            epoch_schedule,
+            prepared_epoch: Arc::new(AtomicU64::new(u64::MAX)),
+        }
+    }
+}
+
+#[async_trait]
+impl BlockStorage for PostgresBlockStorage {
+    async fn save_block(&self, block: &ProducedBlock) -> anyhow::Result<()> {
+        let epoch = self.epoch_schedule.get_epoch_at_slot(block.slot).epoch;
+        if self.prepared_epoch.load(Ordering::Relaxed) != epoch {
+            self.block_store_writer
+                .prepare_epoch_schema(block.slot)
+                .await?;
+            self.prepared_epoch.store(epoch, Ordering::Relaxed);
+        }
+        self.block_store_writer.save_block(block).await
+    }
+
+    async fn query_block(&self, slot: Slot) -> anyhow::Result<ProducedBlock> {
+        self.block_store_query.query_block(slot).await
+    }
+
+    async fn get_slot_range(&self) -> RangeInclusive<Slot> {
+        self.block_store_query.get_slot_range().await
+    }
+
+    async fn query_first_slot(&self, slots: RangeInclusive<Slot>) -> anyhow::Result<Option<Slot>> {
+        self.block_store_query.query_first_slot(slots).await
+    }
+
+    async fn query_block_time(&self, slot: Slot) -> anyhow::Result<Option<UnixTimestamp>> {
+        self.block_store_query.query_block_time(slot).await
+    }
+
+    async fn is_block_in_range(&self, slot: Slot) -> bool {
+        self.block_store_query.is_block_in_range(slot).await
+    }
+
+    async fn query_transaction(
+        &self,
+        signature: &Signature,
+    ) -> anyhow::Result<Option<(Slot, TransactionInfo)>> {
+        self.block_store_query
+            .query_transaction(&signature.to_string())
+            .await
+    }
+}
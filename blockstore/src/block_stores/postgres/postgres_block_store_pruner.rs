@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::env;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use log::{debug, info, warn};
+use prometheus::{opts, register_int_counter, register_int_gauge, IntCounter, IntGauge};
+use solana_lite_rpc_core::structures::epoch::EpochRef;
+use solana_lite_rpc_core::AnyhowJoinHandle;
+use solana_sdk::clock::{Slot, DEFAULT_MS_PER_SLOT};
+
+use super::postgres_block_archiver::ParquetBlockArchiver;
+use super::postgres_block_store_query::PostgresQueryBlockStore;
+use super::postgres_block_store_writer::PostgresBlockStore;
+
+lazy_static::lazy_static! {
+    static ref BLOCKSTORE_MIN_SLOT: IntGauge =
+        register_int_gauge!(opts!("literpc_blockstore_min_slot", "Oldest slot stored in the postgres block store")).unwrap();
+    static ref BLOCKSTORE_MAX_SLOT: IntGauge =
+        register_int_gauge!(opts!("literpc_blockstore_max_slot", "Newest slot stored in the postgres block store")).unwrap();
+    static ref BLOCKSTORE_EPOCHS: IntGauge =
+        register_int_gauge!(opts!("literpc_blockstore_epochs", "Number of epochs with blocks in the postgres block store")).unwrap();
+    static ref BLOCKSTORE_PRUNED_EPOCHS: IntCounter =
+        register_int_counter!(opts!("literpc_blockstore_pruned_epochs", "Number of epoch schemas dropped by the retention policy")).unwrap();
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockStoreRetentionConfig {
+    // number of epochs kept, including the current one; must be at least 1
+    pub retain_epochs: u64,
+    // epochs whose newest block is older than this (measured in slots) are dropped as well
+    pub max_age: Option<Duration>,
+    pub prune_interval: Duration,
+    // epochs are exported as parquet files to this url before they are dropped, see ParquetBlockArchiver
+    pub archive_url: Option<String>,
+}
+
+impl Default for BlockStoreRetentionConfig {
+    fn default() -> Self {
+        Self {
+            retain_epochs: 2,
+            max_age: None,
+            prune_interval: Duration::from_secs(600),
+            archive_url: None,
+        }
+    }
+}
+
+impl BlockStoreRetentionConfig {
+    // BLOCKSTORE_RETAIN_EPOCHS, BLOCKSTORE_MAX_AGE_SECS, BLOCKSTORE_PRUNE_INTERVAL_SECS, BLOCKSTORE_ARCHIVE_URL
+    pub fn new_from_env() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        if let Ok(retain_epochs) = env::var("BLOCKSTORE_RETAIN_EPOCHS") {
+            config.retain_epochs = retain_epochs.parse().context("BLOCKSTORE_RETAIN_EPOCHS")?;
+        }
+        if let Ok(max_age_secs) = env::var("BLOCKSTORE_MAX_AGE_SECS") {
+            config.max_age = Some(Duration::from_secs(
+                max_age_secs.parse().context("BLOCKSTORE_MAX_AGE_SECS")?,
+            ));
+        }
+        if let Ok(prune_interval_secs) = env::var("BLOCKSTORE_PRUNE_INTERVAL_SECS") {
+            config.prune_interval = Duration::from_secs(
+                prune_interval_secs
+                    .parse()
+                    .context("BLOCKSTORE_PRUNE_INTERVAL_SECS")?,
+            );
+        }
+        config.archive_url = env::var("BLOCKSTORE_ARCHIVE_URL").ok();
+        anyhow::ensure!(
+            config.retain_epochs > 0,
+            "BLOCKSTORE_RETAIN_EPOCHS must be at least 1"
+        );
+        Ok(config)
+    }
+
+    fn max_age_in_slots(&self) -> Option<Slot> {
+        self.max_age
+            .map(|max_age| max_age.as_millis() as Slot / DEFAULT_MS_PER_SLOT)
+    }
+
+    /// epochs falling out of the retention window, oldest first
+    pub fn epochs_to_prune(
+        &self,
+        ranges: &HashMap<EpochRef, RangeInclusive<Slot>>,
+    ) -> Vec<EpochRef> {
+        let (Some(current_epoch), Some(newest_slot)) = (
+            ranges.keys().max().copied(),
+            ranges.values().map(|range| *range.end()).max(),
+        ) else {
+            return vec![];
+        };
+
+        let max_age_in_slots = self.max_age_in_slots();
+        let mut epochs = ranges
+            .iter()
+            .filter(|(epoch, range)| {
+                let too_many_epochs =
+                    epoch.get_epoch() + self.retain_epochs <= current_epoch.get_epoch();
+                let too_old = max_age_in_slots
+                    .map(|max_age| range.end() + max_age < newest_slot)
+                    .unwrap_or(false);
+                too_many_epochs || too_old
+            })
+            .map(|(epoch, _)| *epoch)
+            .collect::<Vec<_>>();
+        epochs.sort();
+        epochs
+    }
+}
+
+/// Drops the epoch schemas which fall out of the retention window and reports the stored slot range.
+/// With an archive url configured an epoch is only dropped after it was archived successfully.
+#[derive(Clone)]
+pub struct PostgresBlockStorePruner {
+    block_store_query: PostgresQueryBlockStore,
+    block_store_writer: PostgresBlockStore,
+    archiver: Option<Arc<ParquetBlockArchiver>>,
+    config: BlockStoreRetentionConfig,
+}
+
+impl PostgresBlockStorePruner {
+    pub fn new(
+        block_store_query: PostgresQueryBlockStore,
+        block_store_writer: PostgresBlockStore,
+        config: BlockStoreRetentionConfig,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(config.retain_epochs > 0, "must retain the current epoch");
+        let archiver = config
+            .archive_url
+            .as_deref()
+            .map(ParquetBlockArchiver::new)
+            .transpose()?
+            .map(Arc::new);
+        Ok(Self {
+            block_store_query,
+            block_store_writer,
+            archiver,
+            config,
+        })
+    }
+
+    pub fn start(self) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            info!("Start block store pruner with {:?}", self.config);
+            let mut interval = tokio::time::interval(self.config.prune_interval);
+            loop {
+                interval.tick().await;
+                if let Err(err) = self.prune().await {
+                    warn!("Error pruning block store: {err:?}");
+                }
+            }
+        })
+    }
+
+    pub async fn prune(&self) -> anyhow::Result<()> {
+        let ranges = self.block_store_query.get_slot_range_by_epoch().await;
+        let Some(newest_slot) = ranges.values().map(|range| *range.end()).max() else {
+            debug!("Block store is empty - nothing to prune");
+            return Ok(());
+        };
+
+        let mut retained = ranges.clone();
+        for epoch in self.config.epochs_to_prune(&ranges) {
+            let range = &ranges[&epoch];
+            info!(
+                "Pruning epoch {} with slots {:?} from block store",
+                epoch, range
+            );
+            if let Some(archiver) = &self.archiver {
+                archiver
+                    .archive_epoch(&self.block_store_query, epoch, range)
+                    .await?;
+            }
+            self.block_store_writer.drop_epoch_schema(epoch).await?;
+            BLOCKSTORE_PRUNED_EPOCHS.inc();
+            retained.remove(&epoch);
+        }
+
+        BLOCKSTORE_EPOCHS.set(retained.len() as i64);
+        if let Some(min_slot) = retained.values().map(|range| *range.start()).min() {
+            BLOCKSTORE_MIN_SLOT.set(min_slot as i64);
+        }
+        BLOCKSTORE_MAX_SLOT.set(newest_slot as i64);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_stores::postgres::PostgresSessionConfig;
+    use solana_lite_rpc_core::structures::epoch::EpochCache;
+
+    fn ranges(epochs: &[(u64, RangeInclusive<Slot>)]) -> HashMap<EpochRef, RangeInclusive<Slot>> {
+        epochs
+            .iter()
+            .map(|(epoch, range)| (EpochRef::new(*epoch), range.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_keeps_the_retained_epochs() {
+        let config = BlockStoreRetentionConfig {
+            retain_epochs: 2,
+            ..Default::default()
+        };
+        let ranges = ranges(&[(10, 100..=199), (11, 200..=299), (12, 300..=350)]);
+
+        assert_eq!(config.epochs_to_prune(&ranges), vec![EpochRef::new(10)]);
+    }
+
+    #[test]
+    fn test_prunes_epochs_older_than_max_age() {
+        let config = BlockStoreRetentionConfig {
+            retain_epochs: 3,
+            // 100 slots
+            max_age: Some(Duration::from_millis(100 * DEFAULT_MS_PER_SLOT)),
+            ..Default::default()
+        };
+        let ranges = ranges(&[(10, 100..=199), (11, 200..=299), (12, 300..=350)]);
+
+        assert_eq!(config.epochs_to_prune(&ranges), vec![EpochRef::new(10)]);
+    }
+
+    #[test]
+    fn test_keeps_everything_within_retention() {
+        let config = BlockStoreRetentionConfig {
+            retain_epochs: 1,
+            ..Default::default()
+        };
+
+        assert!(config.epochs_to_prune(&HashMap::new()).is_empty());
+        assert!(config
+            .epochs_to_prune(&ranges(&[(12, 300..=350)]))
+            .is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "need postgres database"]
+    async fn test_new_rejects_zero_retained_epochs() {
+        let epoch_cache = EpochCache::new_for_tests();
+        let pg_session_config = PostgresSessionConfig::new_for_tests();
+        let block_store_writer =
+            PostgresBlockStore::new(epoch_cache.clone(), pg_session_config.clone())
+                .await
+                .unwrap();
+        let block_store_query = PostgresQueryBlockStore::new(epoch_cache, pg_session_config).await;
+
+        let pruner = PostgresBlockStorePruner::new(
+            block_store_query,
+            block_store_writer,
+            BlockStoreRetentionConfig {
+                retain_epochs: 0,
+                ..Default::default()
+            },
+        );
+        assert!(pruner.is_err());
+    }
+}
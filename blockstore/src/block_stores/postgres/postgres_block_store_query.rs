@@ -7,7 +7,11 @@ use anyhow::{bail, Result};
 use itertools::Itertools;
 use log::{debug, info, warn};
 use solana_lite_rpc_core::structures::epoch::EpochRef;
-use solana_lite_rpc_core::structures::{epoch::EpochCache, produced_block::ProducedBlock};
+use solana_lite_rpc_core::structures::{
+    epoch::EpochCache,
+    produced_block::{ProducedBlock, TransactionInfo},
+};
+use solana_sdk::clock::UnixTimestamp;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::slot_history::Slot;
 
@@ -15,6 +19,7 @@ use super::postgres_block::*;
 use super::postgres_config::*;
 use super::postgres_epoch::*;
 use super::postgres_session::*;
+use super::postgres_signature_index::*;
 use super::postgres_transaction::*;
 
 #[derive(Clone)]
@@ -95,6 +100,7 @@ impl PostgresQueryBlockStore {
                     cu_consumed: tx_row.get("cu_consumed"),
                     recent_blockhash: tx_row.get("recent_blockhash"),
                     message: tx_row.get("message"),
+                    message_zstd: tx_row.get("message_zstd"),
                 };
 
                 postgres_transaction.to_transaction_info()
@@ -113,6 +119,7 @@ impl PostgresQueryBlockStore {
         let block_time: i64 = row.get("block_time");
         let previous_blockhash: String = row.get("previous_blockhash");
         let rewards: Option<String> = row.get("rewards");
+        let rewards_zstd: Option<Vec<u8>> = row.get("rewards_zstd");
         let leader_id: Option<String> = row.get("leader_id");
 
         let postgres_block = PostgresBlock {
@@ -123,6 +130,7 @@ impl PostgresQueryBlockStore {
             block_time,
             previous_blockhash,
             rewards,
+            rewards_zstd,
             leader_id,
         };
 
@@ -144,6 +152,156 @@ impl PostgresQueryBlockStore {
         Ok(produced_block)
     }
 
+    /// block time from the blocks table, without the transactions of the block
+    pub async fn query_block_time(&self, slot: Slot) -> Result<Option<UnixTimestamp>> {
+        let epoch: EpochRef = self.epoch_schedule.get_epoch_at_slot(slot).into();
+        let statement = PostgresBlock::build_query_block_time_statement(epoch, slot);
+        let row = self.get_session().await.query_opt(&statement, &[]).await?;
+        Ok(row.map(|row| row.get::<_, i64>("block_time") as UnixTimestamp))
+    }
+
+    /// first stored slot of the range, one query per stored epoch the range touches
+    pub async fn query_first_slot(&self, slots: RangeInclusive<Slot>) -> Result<Option<Slot>> {
+        if slots.is_empty() {
+            return Ok(None);
+        }
+        let stored_epochs = self.get_stored_epochs().await?;
+        let first_epoch = self.epoch_schedule.get_epoch_at_slot(*slots.start()).epoch;
+        let last_epoch = self.epoch_schedule.get_epoch_at_slot(*slots.end()).epoch;
+        for epoch in (first_epoch..=last_epoch).map(EpochRef::new) {
+            if !stored_epochs.contains(&epoch) {
+                continue;
+            }
+            let statement = PostgresBlock::build_query_first_slot_statement(epoch, &slots);
+            if let Some(row) = self.get_session().await.query_opt(&statement, &[]).await? {
+                let slot: i64 = row.get("slot");
+                return Ok(Some(slot as Slot));
+            }
+        }
+        Ok(None)
+    }
+
+    // epochs with a schema in the database, whether or not they have blocks yet
+    async fn get_stored_epochs(&self) -> Result<Vec<EpochRef>> {
+        let statement = format!(
+            r#"
+                SELECT schema_name FROM information_schema.schemata
+                WHERE schema_name ~ '^{schema_prefix}[0-9]+$'
+            "#,
+            schema_prefix = EPOCH_SCHEMA_PREFIX
+        );
+        let rows = self.get_session().await.query_list(&statement, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(|row| PostgresEpoch::parse_epoch_from_schema_name(row.get("schema_name")))
+            .sorted()
+            .collect_vec())
+    }
+
+    // slot and position in the block of the transaction, looked up in the signature index of every stored epoch
+    pub async fn query_signature_location(&self, signature: &str) -> Result<Option<(Slot, u32)>> {
+        let epochs = self.get_stored_epochs().await?;
+        let Some(statement) = PostgresSignatureIndex::build_query_statement(&epochs) else {
+            return Ok(None);
+        };
+        let row = self
+            .get_session()
+            .await
+            .query_opt(&statement, &[&signature])
+            .await?;
+        Ok(row.map(|row| {
+            let slot: i64 = row.get("slot");
+            let idx_in_block: i32 = row.get("idx_in_block");
+            (slot as Slot, idx_in_block as u32)
+        }))
+    }
+
+    pub async fn query_transaction(
+        &self,
+        signature: &str,
+    ) -> Result<Option<(Slot, TransactionInfo)>> {
+        let Some((slot, _)) = self.query_signature_location(signature).await? else {
+            return Ok(None);
+        };
+        let epoch: EpochRef = self.epoch_schedule.get_epoch_at_slot(slot).into();
+
+        let statement = PostgresTransaction::build_query_by_signature_statement(epoch);
+        let slot_param = slot as i64;
+        let Some(tx_row) = self
+            .get_session()
+            .await
+            .query_opt(&statement, &[&signature, &slot_param])
+            .await?
+        else {
+            bail!(
+                "Transaction {signature} is in the signature index but not stored in slot {slot}"
+            );
+        };
+
+        let postgres_transaction = PostgresTransaction {
+            slot: slot_param,
+            signature: signature.to_string(),
+            err: tx_row.get("err"),
+            cu_requested: tx_row.get("cu_requested"),
+            prioritization_fees: tx_row.get("prioritization_fees"),
+            cu_consumed: tx_row.get("cu_consumed"),
+            recent_blockhash: tx_row.get("recent_blockhash"),
+            message: tx_row.get("message"),
+            message_zstd: tx_row.get("message_zstd"),
+        };
+        Ok(Some((slot, postgres_transaction.to_transaction_info())))
+    }
+
+    // raw block rows of the given slots, used for archiving
+    pub(crate) async fn query_postgres_blocks(
+        &self,
+        epoch: EpochRef,
+        slots: &RangeInclusive<Slot>,
+    ) -> Result<Vec<PostgresBlock>> {
+        let statement = PostgresBlock::build_query_range_statement(epoch, slots);
+        let rows = self.get_session().await.query_list(&statement, &[]).await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| PostgresBlock {
+                slot: row.get("slot"),
+                blockhash: row.get("blockhash"),
+                block_height: row.get("block_height"),
+                parent_slot: row.get("parent_slot"),
+                block_time: row.get("block_time"),
+                previous_blockhash: row.get("previous_blockhash"),
+                rewards: row.get("rewards"),
+                rewards_zstd: row.get("rewards_zstd"),
+                leader_id: row.get("leader_id"),
+            })
+            .collect_vec())
+    }
+
+    // raw transaction rows of the given slots, used for archiving
+    pub(crate) async fn query_postgres_transactions(
+        &self,
+        epoch: EpochRef,
+        slots: &RangeInclusive<Slot>,
+    ) -> Result<Vec<PostgresTransaction>> {
+        let statement = PostgresTransaction::build_query_range_statement(epoch, slots);
+        let rows = self.get_session().await.query_list(&statement, &[]).await?;
+
+        Ok(rows
+            .iter()
+            .map(|tx_row| PostgresTransaction {
+                slot: tx_row.get("slot"),
+                signature: tx_row.get("signature"),
+                err: tx_row.get("err"),
+                cu_requested: tx_row.get("cu_requested"),
+                prioritization_fees: tx_row.get("prioritization_fees"),
+                cu_consumed: tx_row.get("cu_consumed"),
+                recent_blockhash: tx_row.get("recent_blockhash"),
+                message: tx_row.get("message"),
+                message_zstd: tx_row.get("message_zstd"),
+            })
+            .collect_vec())
+    }
+
     async fn check_query_role(session_cache: &PostgresSessionCache) {
         let role = LITERPC_QUERY_ROLE;
         let statement = format!("SELECT 1 FROM pg_roles WHERE rolname='{role}'");
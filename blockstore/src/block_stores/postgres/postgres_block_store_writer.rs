@@ -11,9 +11,12 @@ use solana_sdk::slot_history::Slot;
 use tokio_postgres::error::SqlState;
 
 use super::postgres_block::*;
+use super::postgres_compression::*;
 use super::postgres_config::*;
 use super::postgres_epoch::*;
+use super::postgres_migrations::*;
 use super::postgres_session::*;
+use super::postgres_signature_index::*;
 use super::postgres_transaction::*;
 
 const PARALLEL_WRITE_SESSIONS: usize = 4;
@@ -25,10 +28,16 @@ pub struct PostgresBlockStore {
     // use this session only for the write path!
     write_sessions: Vec<PostgresWriteSession>,
     epoch_schedule: EpochCache,
+    payload_compression: PayloadCompression,
+    notify_channel: Option<String>,
 }
 
 impl PostgresBlockStore {
-    pub async fn new(epoch_schedule: EpochCache, pg_session_config: PostgresSessionConfig) -> Self {
+    /// applies the pending block store migrations, see `BLOCK_STORE_MIGRATIONS`
+    pub async fn new(
+        epoch_schedule: EpochCache,
+        pg_session_config: PostgresSessionConfig,
+    ) -> Result<Self> {
         let session_cache = PostgresSessionCache::new(pg_session_config.clone())
             .await
             .unwrap();
@@ -46,12 +55,34 @@ impl PostgresBlockStore {
         );
 
         Self::check_write_role(&session_cache).await;
-
-        Self {
+        let session = session_cache.get_session().await?;
+        run_migrations(
+            &session.client,
+            BLOCK_STORE_MIGRATIONS_SCHEMA,
+            BLOCK_STORE_MIGRATIONS,
+        )
+        .await
+        .context("migrate the block store")?;
+
+        Ok(Self {
             session_cache,
             write_sessions,
             epoch_schedule,
-        }
+            payload_compression: PayloadCompression::default(),
+            notify_channel: None,
+        })
+    }
+
+    // compress transaction messages and block rewards of newly written blocks
+    pub fn with_payload_compression(mut self, payload_compression: PayloadCompression) -> Self {
+        self.payload_compression = payload_compression;
+        self
+    }
+
+    // NOTIFY listeners on this channel after a block and its transactions were written
+    pub fn with_notify_channel(mut self, channel: String) -> Self {
+        self.notify_channel = Some(channel);
+        self
     }
 
     async fn check_write_role(session_cache: &PostgresSessionCache) {
@@ -132,6 +163,12 @@ impl PostgresBlockStore {
             .await
             .context("create foreign key constraint between transactions and blocks")?;
 
+        let statement = PostgresSignatureIndex::build_create_table_statement(epoch);
+        session
+            .execute_multiple(&statement)
+            .await
+            .context("create signature index table for new epoch")?;
+
         info!("Start new epoch in postgres schema {}", schema_name);
         Ok(true)
     }
@@ -171,9 +208,14 @@ impl PostgresBlockStore {
         let transactions = block
             .transactions
             .iter()
-            .map(|x| PostgresTransaction::new(x, slot))
+            .map(|x| {
+                let mut transaction = PostgresTransaction::new(x, slot);
+                transaction.compress(&self.payload_compression);
+                transaction
+            })
             .collect_vec();
-        let postgres_block = PostgresBlock::from(block);
+        let mut postgres_block = PostgresBlock::from(block);
+        postgres_block.compress(&self.payload_compression);
 
         let epoch = self.epoch_schedule.get_epoch_at_slot(slot);
 
@@ -211,6 +253,11 @@ impl PostgresBlockStore {
             result.expect("Save query must succeed");
         }
 
+        PostgresSignatureIndex::from_block(block)
+            .save(&write_session_single, epoch.into())
+            .await
+            .context("save signature index")?;
+
         let elapsed_txs_insert = started_txs.elapsed();
 
         info!(
@@ -223,6 +270,18 @@ impl PostgresBlockStore {
             chunk_size,
         );
 
+        if let Some(channel) = &self.notify_channel {
+            // listeners re-read the block from the tables, a failed notification does not fail the write
+            if let Err(err) =
+                notify_block_saved(&write_session_single, channel, block, epoch.into()).await
+            {
+                warn!(
+                    "Failed to notify {} about block {}: {:?}",
+                    channel, slot, err
+                );
+            }
+        }
+
         Ok(())
     }
 
@@ -273,7 +332,7 @@ impl PostgresBlockStore {
         Ok(created_current || created_next)
     }
 
-    // used for testing only ATM
+    // used by the retention pruner and for testing
     pub async fn drop_epoch_schema(&self, epoch: EpochRef) -> anyhow::Result<()> {
         // create schema for new epoch
         let schema_name = PostgresEpoch::build_schema_name(epoch);
@@ -309,6 +368,27 @@ fn build_assign_permissions_statements(epoch: EpochRef) -> String {
     )
 }
 
+async fn notify_block_saved(
+    session: &PostgresSession,
+    channel: &str,
+    block: &ProducedBlock,
+    epoch: EpochRef,
+) -> Result<()> {
+    let payload = serde_json::json!({
+        "slot": block.slot,
+        "epoch": epoch.get_epoch(),
+        "blockHeight": block.block_height,
+        "blockhash": block.blockhash.to_string(),
+        "commitment": block.commitment_config.commitment.to_string(),
+        "transactionCount": block.transactions.len(),
+    })
+    .to_string();
+    session
+        .execute("SELECT pg_notify($1, $2)", &[&channel, &payload])
+        .await?;
+    Ok(())
+}
+
 fn div_ceil(a: usize, b: usize) -> usize {
     (a.saturating_add(b).saturating_sub(1)).saturating_div(b)
 }
@@ -316,8 +396,9 @@ fn div_ceil(a: usize, b: usize) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use solana_lite_rpc_core::structures::produced_block::TransactionInfo;
+    use solana_lite_rpc_core::structures::produced_block::{TransactionInfo, TransactionMessage};
     use solana_sdk::commitment_config::CommitmentConfig;
+    use solana_sdk::hash::Hash;
     use solana_sdk::signature::Signature;
     use std::str::FromStr;
 
@@ -352,7 +433,9 @@ mod tests {
         let epoch_cache = EpochCache::new_for_tests();
 
         let postgres_block_store =
-            PostgresBlockStore::new(epoch_cache.clone(), pg_session_config.clone()).await;
+            PostgresBlockStore::new(epoch_cache.clone(), pg_session_config.clone())
+                .await
+                .unwrap();
 
         postgres_block_store
             .save_block(&create_test_block())
@@ -366,32 +449,28 @@ mod tests {
 
         ProducedBlock {
             block_height: 42,
-            blockhash: "blockhash".to_string(),
-            previous_blockhash: "previous_blockhash".to_string(),
             parent_slot: 666,
-            slot: 223555999,
             transactions: vec![create_test_tx(sig1), create_test_tx(sig2)],
             // TODO double if this is unix millis or seconds
             block_time: 1699260872000,
-            commitment_config: CommitmentConfig::finalized(),
-            leader_id: None,
-            rewards: None,
+            ..ProducedBlock::new_for_tests(223555999, CommitmentConfig::finalized())
         }
     }
 
     fn create_test_tx(signature: Signature) -> TransactionInfo {
         TransactionInfo {
-            signature: signature.to_string(),
+            signature,
             is_vote: false,
             err: None,
             cu_requested: Some(40000),
             prioritization_fees: Some(5000),
             cu_consumed: Some(32000),
-            recent_blockhash: "recent_blockhash".to_string(),
-            message: "some message".to_string(),
+            recent_blockhash: Hash::new_unique(),
+            message: TransactionMessage::new(b"some message".to_vec()),
             writable_accounts: vec![],
             readable_accounts: vec![],
             address_lookup_tables: vec![],
+            program_ids: vec![],
         }
     }
 }
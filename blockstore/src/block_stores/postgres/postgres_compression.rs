@@ -0,0 +1,88 @@
+use std::env;
+
+use anyhow::Context;
+use base64::Engine;
+
+/// zstd compression of the large payloads (transaction message, block rewards) written to postgres;
+/// compressed payloads are stored as bytea in message_zstd and rewards_zstd instead of the base64 text columns
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PayloadCompression {
+    // None disables compression
+    pub zstd_level: Option<i32>,
+}
+
+impl PayloadCompression {
+    // BLOCKSTORE_ZSTD_LEVEL
+    pub fn new_from_env() -> anyhow::Result<Self> {
+        let zstd_level = match env::var("BLOCKSTORE_ZSTD_LEVEL") {
+            Ok(level) => Some(level.parse::<i32>().context("BLOCKSTORE_ZSTD_LEVEL")?),
+            Err(_) => None,
+        };
+        if let Some(level) = zstd_level {
+            anyhow::ensure!(
+                zstd::compression_level_range().contains(&level),
+                "BLOCKSTORE_ZSTD_LEVEL must be within {:?}",
+                zstd::compression_level_range()
+            );
+        }
+        Ok(Self { zstd_level })
+    }
+
+    // payload is base64 encoded; None if compression is disabled or failed, the payload is stored as text then
+    pub fn compress(&self, payload: &str) -> Option<Vec<u8>> {
+        let level = self.zstd_level?;
+        let raw = base64::engine::general_purpose::STANDARD
+            .decode(payload)
+            .ok()?;
+        zstd::encode_all(raw.as_slice(), level).ok()
+    }
+}
+
+/// restores the base64 payload from the zstd frame
+pub fn decompress_payload(compressed: &[u8]) -> anyhow::Result<String> {
+    let raw = zstd::decode_all(compressed).context("decompress payload")?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(raw))
+}
+
+/// base64 payload of a row which stores it either as text or compressed as bytea
+pub fn read_payload(
+    text: &Option<String>,
+    compressed: &Option<Vec<u8>>,
+) -> anyhow::Result<Option<String>> {
+    match compressed {
+        Some(compressed) => decompress_payload(compressed).map(Some),
+        None => Ok(text.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let payload = base64::engine::general_purpose::STANDARD.encode([7u8; 1000]);
+        let compression = PayloadCompression {
+            zstd_level: Some(3),
+        };
+
+        let compressed = compression.compress(&payload).unwrap();
+        assert!(compressed.len() < 1000);
+        assert_eq!(decompress_payload(&compressed).unwrap(), payload);
+        assert_eq!(
+            read_payload(&None, &Some(compressed)).unwrap(),
+            Some(payload)
+        );
+    }
+
+    #[test]
+    fn test_uncompressed_passthrough() {
+        let payload = base64::engine::general_purpose::STANDARD.encode([1u8, 2, 3]);
+        assert_eq!(PayloadCompression::default().compress(&payload), None);
+        assert_eq!(
+            read_payload(&Some(payload.clone()), &None).unwrap(),
+            Some(payload)
+        );
+        assert_eq!(read_payload(&None, &None).unwrap(), None);
+    }
+}
@@ -28,8 +28,13 @@ impl PostgresSessionConfig {
         }
 
         let env_pg_config = env::var("PG_CONFIG").context("PG_CONFIG not found")?;
+        Self::new(env_pg_config).map(Some)
+    }
 
-        let ssl_config = if env_pg_config
+    /// libpq config or url; unless ssl is disabled the certificates are taken from
+    /// CA_PEM_B64, CLIENT_PKS_B64 and CLIENT_PKS_PASS
+    pub fn new(pg_config: String) -> anyhow::Result<Self> {
+        let ssl_config = if pg_config
             .parse::<tokio_postgres::Config>()?
             .get_ssl_mode()
             .eq(&SslMode::Disable)
@@ -49,10 +54,10 @@ impl PostgresSessionConfig {
             })
         };
 
-        Ok(Some(Self {
-            pg_config: env_pg_config,
+        Ok(Self {
+            pg_config,
             ssl: ssl_config,
-        }))
+        })
     }
 }
 
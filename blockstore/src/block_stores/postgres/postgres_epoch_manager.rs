@@ -0,0 +1,147 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use log::info;
+use prometheus::{opts, register_int_gauge, IntGauge};
+use solana_lite_rpc_core::epoch_notifier::EpochAwareCache;
+use solana_lite_rpc_core::structures::epoch::{Epoch, EpochRef};
+
+use super::postgres_block_store_pruner::PostgresBlockStorePruner;
+use super::postgres_block_store_writer::PostgresBlockStore;
+
+lazy_static::lazy_static! {
+    static ref BLOCKSTORE_CURRENT_EPOCH: IntGauge =
+        register_int_gauge!(opts!("literpc_blockstore_current_epoch", "Epoch of the newest processed slot seen by the block store epoch manager")).unwrap();
+}
+
+/// Creates the schemas of the current and the next epoch whenever the processed slot crosses an epoch boundary
+/// and drops the schemas falling out of the retention window right away (if a pruner is given),
+/// so no external job needs to prepare or clean up epoch schemas. Register it with the epoch notifier.
+pub struct PostgresEpochManager {
+    block_store_writer: PostgresBlockStore,
+    pruner: Option<PostgresBlockStorePruner>,
+}
+
+impl PostgresEpochManager {
+    pub fn new(
+        block_store_writer: PostgresBlockStore,
+        pruner: Option<PostgresBlockStorePruner>,
+    ) -> Self {
+        Self {
+            block_store_writer,
+            pruner,
+        }
+    }
+}
+
+#[async_trait]
+impl EpochAwareCache for PostgresEpochManager {
+    fn name(&self) -> &'static str {
+        "postgres_epoch_schemas"
+    }
+
+    async fn on_new_epoch(&self, epoch: &Epoch) -> anyhow::Result<()> {
+        let slot = epoch.absolute_slot;
+        let epoch: EpochRef = (*epoch).into();
+        info!("Block store entering epoch {} at slot {}", epoch, slot);
+        BLOCKSTORE_CURRENT_EPOCH.set(epoch.get_epoch() as i64);
+        self.block_store_writer
+            .prepare_epoch_schema(slot)
+            .await
+            .with_context(|| format!("failed to prepare schemas for epoch {epoch}"))?;
+        if let Some(pruner) = &self.pruner {
+            pruner
+                .prune()
+                .await
+                .with_context(|| format!("failed to prune block store on epoch {epoch}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_stores::postgres::postgres_block_store_pruner::BlockStoreRetentionConfig;
+    use crate::block_stores::postgres::postgres_block_store_query::PostgresQueryBlockStore;
+    use crate::block_stores::postgres::PostgresSessionConfig;
+    use solana_lite_rpc_core::structures::epoch::EpochCache;
+    use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
+    use solana_sdk::commitment_config::CommitmentConfig;
+
+    // note: the pruner drops every other epoch schema of the test database
+    #[tokio::test]
+    #[ignore = "need postgres database"]
+    async fn test_rollover_prepares_the_next_epoch_and_prunes_the_previous_one() {
+        // 1000 slots per epoch
+        let epoch_cache = EpochCache::new_for_tests();
+        let pg_session_config = PostgresSessionConfig::new_for_tests();
+        let block_store_writer =
+            PostgresBlockStore::new(epoch_cache.clone(), pg_session_config.clone())
+                .await
+                .unwrap();
+        let block_store_query =
+            PostgresQueryBlockStore::new(epoch_cache.clone(), pg_session_config).await;
+        for epoch in 900_000..=900_002 {
+            let _ = block_store_writer
+                .drop_epoch_schema(EpochRef::new(epoch))
+                .await;
+        }
+        let pruner = PostgresBlockStorePruner::new(
+            block_store_query.clone(),
+            block_store_writer.clone(),
+            BlockStoreRetentionConfig {
+                retain_epochs: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let epoch_manager = PostgresEpochManager::new(block_store_writer.clone(), Some(pruner));
+
+        let first_slot = 900_000 * 1000;
+        epoch_manager
+            .on_new_epoch(&epoch_cache.get_epoch_at_slot(first_slot))
+            .await
+            .unwrap();
+        block_store_writer
+            .save_block(&ProducedBlock::new_for_tests(
+                first_slot,
+                CommitmentConfig::finalized(),
+            ))
+            .await
+            .unwrap();
+        // the schema of the next epoch was prepared with the current one
+        block_store_writer
+            .save_block(&ProducedBlock::new_for_tests(
+                first_slot + 1000,
+                CommitmentConfig::finalized(),
+            ))
+            .await
+            .unwrap();
+
+        epoch_manager
+            .on_new_epoch(&epoch_cache.get_epoch_at_slot(first_slot + 1000))
+            .await
+            .unwrap();
+
+        let ranges = block_store_query.get_slot_range_by_epoch().await;
+        assert!(!ranges.contains_key(&EpochRef::new(900_000)));
+        assert_eq!(
+            ranges.get(&EpochRef::new(900_001)),
+            Some(&((first_slot + 1000)..=(first_slot + 1000)))
+        );
+        // both the current and the next epoch exist already
+        assert!(!block_store_writer
+            .prepare_epoch_schema(first_slot + 1000)
+            .await
+            .unwrap());
+
+        block_store_writer
+            .drop_epoch_schema(EpochRef::new(900_001))
+            .await
+            .unwrap();
+        block_store_writer
+            .drop_epoch_schema(EpochRef::new(900_002))
+            .await
+            .unwrap();
+    }
+}
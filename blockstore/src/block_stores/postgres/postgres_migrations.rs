@@ -0,0 +1,115 @@
+use anyhow::Context;
+use log::info;
+use tokio_postgres::Client;
+
+/// a schema change applied once per database, in order of its version
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+// append new migrations here, never change applied ones; they bring the epoch schemas created by
+// older versions to the tables new epoch schemas are created with
+pub const BLOCK_STORE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "epoch_signature_index",
+        sql: include_str!(
+            "../../../../migrations/versions/blockstore/V001__epoch_signature_index.sql"
+        ),
+    },
+    Migration {
+        version: 2,
+        name: "epoch_compressed_payloads",
+        sql: include_str!(
+            "../../../../migrations/versions/blockstore/V002__epoch_compressed_payloads.sql"
+        ),
+    },
+];
+
+// schema of the table recording the applied block store migrations
+pub const BLOCK_STORE_MIGRATIONS_SCHEMA: &str = "rpc2a_meta";
+
+// serializes concurrent lite-rpc instances migrating the same database
+const MIGRATION_LOCK_ID: i64 = 0x6c69_7465_7270_63;
+
+/// Applies all migrations which are not yet recorded in `<schema>.SchemaMigrations`,
+/// each one in its own transaction. Returns the number of applied migrations.
+pub async fn run_migrations(
+    client: &Client,
+    schema: &str,
+    migrations: &[Migration],
+) -> anyhow::Result<usize> {
+    client
+        .batch_execute(&format!(
+            r#"
+                CREATE SCHEMA IF NOT EXISTS {schema};
+                CREATE TABLE IF NOT EXISTS {schema}.SchemaMigrations (
+                    version INT NOT NULL PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    applied_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+                );
+            "#
+        ))
+        .await
+        .context("create migrations table")?;
+
+    let mut applied = 0;
+    for migration in migrations {
+        client.batch_execute("BEGIN").await?;
+        match apply_migration(client, schema, migration).await {
+            Ok(true) => {
+                client.batch_execute("COMMIT").await?;
+                info!(
+                    "Applied postgres migration {}.{} ({})",
+                    schema, migration.version, migration.name
+                );
+                applied += 1;
+            }
+            Ok(false) => {
+                client.batch_execute("COMMIT").await?;
+            }
+            Err(err) => {
+                client.batch_execute("ROLLBACK").await?;
+                return Err(err).with_context(|| {
+                    format!(
+                        "postgres migration {}.{} ({}) failed",
+                        schema, migration.version, migration.name
+                    )
+                });
+            }
+        }
+    }
+    Ok(applied)
+}
+
+// runs inside a transaction, returns false if the migration was applied before
+async fn apply_migration(
+    client: &Client,
+    schema: &str,
+    migration: &Migration,
+) -> anyhow::Result<bool> {
+    client
+        .execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_ID])
+        .await?;
+    let already_applied = client
+        .query_opt(
+            &format!("SELECT version FROM {schema}.SchemaMigrations WHERE version = $1"),
+            &[&migration.version],
+        )
+        .await?
+        .is_some();
+    if already_applied {
+        return Ok(false);
+    }
+
+    client.batch_execute(migration.sql).await?;
+    client
+        .execute(
+            &format!("INSERT INTO {schema}.SchemaMigrations(version, name) VALUES ($1, $2)"),
+            &[&migration.version, &migration.name],
+        )
+        .await?;
+    Ok(true)
+}
@@ -0,0 +1,121 @@
+use itertools::Itertools;
+use log::debug;
+use solana_lite_rpc_core::structures::epoch::EpochRef;
+use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
+use tokio::time::Instant;
+
+use super::postgres_epoch::*;
+use super::postgres_session::*;
+
+/// Maps a signature to its slot and position in the block, one narrow table per epoch schema
+/// so lookups by signature hit the primary key instead of scanning transaction_blockdata.
+/// Epoch schemas of older versions get it with a backfill by the block store migrations.
+#[derive(Debug)]
+pub struct PostgresSignatureIndex {
+    pub signatures: Vec<String>,
+    pub slots: Vec<i64>,
+    pub indexes_in_block: Vec<i32>,
+}
+
+impl PostgresSignatureIndex {
+    pub fn from_block(block: &ProducedBlock) -> Self {
+        let nb_transactions = block.transactions.len();
+        let mut index = Self {
+            signatures: Vec::with_capacity(nb_transactions),
+            slots: Vec::with_capacity(nb_transactions),
+            indexes_in_block: Vec::with_capacity(nb_transactions),
+        };
+        for (index_in_block, tx) in block.transactions.iter().enumerate() {
+            index.signatures.push(tx.signature.to_string());
+            index.slots.push(block.slot as i64);
+            index.indexes_in_block.push(index_in_block as i32);
+        }
+        index
+    }
+
+    pub fn build_create_table_statement(epoch: EpochRef) -> String {
+        let schema = PostgresEpoch::build_schema_name(epoch);
+        format!(
+            r#"
+                CREATE TABLE IF NOT EXISTS {schema}.signature_index(
+                    -- never put sig on TOAST
+                    signature text STORAGE PLAIN PRIMARY KEY WITH (FILLFACTOR=90),
+                    slot bigint NOT NULL,
+                    idx_in_block int NOT NULL
+                ) WITH (FILLFACTOR=90);
+            "#,
+            schema = schema
+        )
+    }
+
+    // param: $1 signature; looks up the given epochs in one statement, newest slot first
+    pub fn build_query_statement(epochs: &[EpochRef]) -> Option<String> {
+        if epochs.is_empty() {
+            return None;
+        }
+        let lookups = epochs
+            .iter()
+            .map(|epoch| {
+                format!(
+                    "SELECT slot, idx_in_block FROM {schema}.signature_index WHERE signature = $1",
+                    schema = PostgresEpoch::build_schema_name(*epoch),
+                )
+            })
+            .join(" UNION ALL ");
+        Some(format!(
+            r#"
+                SELECT slot, idx_in_block FROM ({lookups}) AS locations
+                ORDER BY slot DESC
+                LIMIT 1
+            "#
+        ))
+    }
+
+    pub async fn save(
+        &self,
+        postgres_session: &PostgresSession,
+        epoch: EpochRef,
+    ) -> anyhow::Result<()> {
+        let started_at = Instant::now();
+        let statement = format!(
+            r#"
+                INSERT INTO {schema}.signature_index(signature, slot, idx_in_block)
+                SELECT * FROM unnest($1::text[], $2::bigint[], $3::int[])
+                ON CONFLICT DO NOTHING
+            "#,
+            schema = PostgresEpoch::build_schema_name(epoch),
+        );
+        let num_rows = postgres_session
+            .execute(
+                &statement,
+                &[&self.signatures, &self.slots, &self.indexes_in_block],
+            )
+            .await?;
+        debug!(
+            "inserted {} rows into signature index in {}ms",
+            num_rows,
+            started_at.elapsed().as_millis()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_statement_covers_the_given_epochs() {
+        assert_eq!(PostgresSignatureIndex::build_query_statement(&[]), None);
+
+        let statement = PostgresSignatureIndex::build_query_statement(&[
+            EpochRef::new(591),
+            EpochRef::new(592),
+        ])
+        .unwrap();
+        assert!(statement.contains("FROM rpc2a_epoch_591.signature_index"));
+        assert!(statement.contains("FROM rpc2a_epoch_592.signature_index"));
+        assert!(!statement.contains("rpc2a_epoch_590"));
+        assert_eq!(statement.matches("UNION ALL").count(), 1);
+    }
+}
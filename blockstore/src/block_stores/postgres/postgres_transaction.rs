@@ -1,14 +1,22 @@
 use futures_util::pin_mut;
-use log::debug;
+use log::{debug, warn};
 use solana_lite_rpc_core::structures::epoch::EpochRef;
-use solana_lite_rpc_core::{encoding::BASE64, structures::produced_block::TransactionInfo};
+use solana_lite_rpc_core::{
+    encoding::BASE64,
+    structures::produced_block::{TransactionInfo, TransactionMessage},
+};
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::Signature;
 use solana_sdk::slot_history::Slot;
 use solana_sdk::transaction::TransactionError;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 use tokio::time::Instant;
 use tokio_postgres::binary_copy::BinaryCopyInWriter;
 use tokio_postgres::types::Type;
 use tokio_postgres::CopyInSink;
 
+use super::postgres_compression::{read_payload, PayloadCompression};
 use super::postgres_epoch::*;
 use super::postgres_session::*;
 
@@ -22,13 +30,15 @@ pub struct PostgresTransaction {
     pub prioritization_fees: Option<i64>,
     pub cu_consumed: Option<i64>,
     pub recent_blockhash: String,
-    pub message: String,
+    // base64 encoded, None if stored compressed in message_zstd
+    pub message: Option<String>,
+    pub message_zstd: Option<Vec<u8>>,
 }
 
 impl PostgresTransaction {
     pub fn new(value: &TransactionInfo, slot: Slot) -> Self {
         Self {
-            signature: value.signature.clone(),
+            signature: value.signature.to_string(),
             err: value
                 .err
                 .clone()
@@ -37,15 +47,46 @@ impl PostgresTransaction {
             cu_requested: value.cu_requested.map(|x| x as i64),
             prioritization_fees: value.prioritization_fees.map(|x| x as i64),
             cu_consumed: value.cu_consumed.map(|x| x as i64),
-            recent_blockhash: value.recent_blockhash.clone(),
-            message: value.message.clone(),
+            recent_blockhash: value.recent_blockhash.to_string(),
+            message: Some(value.message.to_base64()),
+            message_zstd: None,
             slot: slot as i64,
         }
     }
 
+    pub fn compress(&mut self, payload_compression: &PayloadCompression) {
+        if let Some(compressed) = self
+            .message
+            .as_deref()
+            .and_then(|message| payload_compression.compress(message))
+        {
+            self.message = None;
+            self.message_zstd = Some(compressed);
+        }
+    }
+
+    // base64 encoded message
+    pub fn message_base64(&self) -> anyhow::Result<String> {
+        read_payload(&self.message, &self.message_zstd)?
+            .ok_or_else(|| anyhow::anyhow!("transaction {} has no message", self.signature))
+    }
+
     pub fn to_transaction_info(&self) -> TransactionInfo {
+        let message = self.message_base64().unwrap_or_else(|err| {
+            warn!(
+                "Failed to read message of transaction {}: {err:?}",
+                self.signature
+            );
+            String::new()
+        });
         TransactionInfo {
-            signature: self.signature.clone(),
+            signature: Signature::from_str(&self.signature).unwrap_or_else(|err| {
+                warn!(
+                    "Failed to parse signature of transaction {}: {err:?}",
+                    self.signature
+                );
+                Signature::default()
+            }),
             err: self
                 .err
                 .as_ref()
@@ -53,13 +94,26 @@ impl PostgresTransaction {
             cu_requested: self.cu_requested.map(|x| x as u32),
             prioritization_fees: self.prioritization_fees.map(|x| x as u64),
             cu_consumed: self.cu_consumed.map(|x| x as u64),
-            recent_blockhash: self.recent_blockhash.clone(),
-            message: self.message.clone(),
+            recent_blockhash: Hash::from_str(&self.recent_blockhash).unwrap_or_else(|err| {
+                warn!(
+                    "Failed to parse recent blockhash of transaction {}: {err:?}",
+                    self.signature
+                );
+                Hash::default()
+            }),
+            message: TransactionMessage::from_base64(&message).unwrap_or_else(|err| {
+                warn!(
+                    "Failed to decode message of transaction {}: {err:?}",
+                    self.signature
+                );
+                TransactionMessage::default()
+            }),
             // TODO readable_accounts etc.
             readable_accounts: vec![],
             writable_accounts: vec![],
             is_vote: false,
             address_lookup_tables: vec![],
+            program_ids: vec![],
         }
     }
 
@@ -86,7 +140,9 @@ impl PostgresTransaction {
                     cu_consumed bigint,
                     recent_blockhash text NOT NULL,
                     err text,
-                    message text NOT NULL
+                    -- base64 or zstd compressed
+                    message text,
+                    message_zstd bytea
                     -- model_transaction_blockdata
                 ) WITH (FILLFACTOR=90,TOAST_TUPLE_TARGET=128);
                 CREATE INDEX idx_slot ON {schema}.transaction_blockdata USING btree (slot) WITH (FILLFACTOR=90);
@@ -123,7 +179,8 @@ impl PostgresTransaction {
                 cu_consumed bigint,
                 recent_blockhash text STORAGE PLAIN,
                 err text STORAGE PLAIN,
-                message text STORAGE PLAIN
+                message text STORAGE PLAIN,
+                message_zstd bytea STORAGE PLAIN
                 -- model_transaction_blockdata
             );
             TRUNCATE transaction_raw_blockdata;
@@ -139,7 +196,8 @@ impl PostgresTransaction {
                 cu_consumed,
                 recent_blockhash,
                 err,
-                message
+                message,
+                message_zstd
                 -- model_transaction_blockdata
             ) FROM STDIN BINARY
         "#;
@@ -155,7 +213,8 @@ impl PostgresTransaction {
                 Type::INT8,
                 Type::TEXT,
                 Type::TEXT,
-                Type::TEXT, // model_transaction_blockdata
+                Type::TEXT,
+                Type::BYTEA, // model_transaction_blockdata
             ],
         );
         pin_mut!(writer);
@@ -170,6 +229,7 @@ impl PostgresTransaction {
                 err,
                 recent_blockhash,
                 message,
+                message_zstd,
                 // model_transaction_blockdata
             } = tx;
 
@@ -184,6 +244,7 @@ impl PostgresTransaction {
                     &err,
                     &recent_blockhash,
                     &message,
+                    &message_zstd,
                     // model_transaction_blockdata
                 ])
                 .await?;
@@ -222,7 +283,8 @@ impl PostgresTransaction {
                     cu_consumed,
                     err,
                     recent_blockhash,
-                    message
+                    message,
+                    message_zstd
                     -- model_transaction_blockdata
                 FROM transaction_raw_blockdata
         "#,
@@ -249,7 +311,8 @@ impl PostgresTransaction {
                     cu_consumed,
                     err,
                     recent_blockhash,
-                    message
+                    message,
+                    message_zstd
                     -- model_transaction_blockdata
                 FROM {schema}.transaction_blockdata
                 WHERE slot = {}
@@ -258,4 +321,48 @@ impl PostgresTransaction {
             schema = PostgresEpoch::build_schema_name(epoch),
         )
     }
+
+    pub fn build_query_range_statement(epoch: EpochRef, slots: &RangeInclusive<Slot>) -> String {
+        format!(
+            r#"
+                SELECT
+                    (SELECT signature FROM {schema}.transaction_ids tx_ids WHERE tx_ids.transaction_id = transaction_blockdata.transaction_id),
+                    slot,
+                    cu_requested,
+                    prioritization_fees,
+                    cu_consumed,
+                    err,
+                    recent_blockhash,
+                    message,
+                    message_zstd
+                    -- model_transaction_blockdata
+                FROM {schema}.transaction_blockdata
+                WHERE slot >= {first_slot} AND slot <= {last_slot}
+                ORDER BY slot
+            "#,
+            schema = PostgresEpoch::build_schema_name(epoch),
+            first_slot = slots.start(),
+            last_slot = slots.end()
+        )
+    }
+
+    // params: $1 signature, $2 slot (from the signature index)
+    pub fn build_query_by_signature_statement(epoch: EpochRef) -> String {
+        format!(
+            r#"
+                SELECT
+                    cu_requested,
+                    prioritization_fees,
+                    cu_consumed,
+                    err,
+                    recent_blockhash,
+                    message,
+                    message_zstd
+                    -- model_transaction_blockdata
+                FROM {schema}.transaction_blockdata
+                WHERE slot = $2 AND transaction_id = (SELECT transaction_id FROM {schema}.transaction_ids WHERE signature = $1)
+            "#,
+            schema = PostgresEpoch::build_schema_name(epoch),
+        )
+    }
 }
@@ -0,0 +1,50 @@
+use std::time::Instant;
+
+use futures::{stream, StreamExt};
+use log::{debug, info};
+use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
+use solana_lite_rpc_core::traits::block_storage::BlockStorage;
+use solana_sdk::slot_history::Slot;
+
+// parallel reads against the block storage while loading
+const PARALLEL_QUERIES: usize = 16;
+
+/// Loads the blocks of the newest `nb_slots` slots from the block storage, ordered by slot.
+/// Slots which are not stored (skipped or not saved) are left out.
+pub async fn load_recent_blocks(
+    block_storage: &dyn BlockStorage,
+    nb_slots: u64,
+) -> Vec<ProducedBlock> {
+    let slot_range = block_storage.get_slot_range().await;
+    if nb_slots == 0 || slot_range.is_empty() {
+        return vec![];
+    }
+    let started_at = Instant::now();
+    let last_slot = *slot_range.end();
+    let first_slot = last_slot
+        .saturating_sub(nb_slots - 1)
+        .max(*slot_range.start());
+
+    let blocks: Vec<ProducedBlock> = stream::iter(first_slot..=last_slot)
+        .map(|slot: Slot| async move {
+            match block_storage.query_block(slot).await {
+                Ok(block) => Some(block),
+                Err(err) => {
+                    debug!("Slot {} not loaded for warm start: {:?}", slot, err);
+                    None
+                }
+            }
+        })
+        .buffered(PARALLEL_QUERIES)
+        .filter_map(|block| async move { block })
+        .collect()
+        .await;
+    info!(
+        "Loaded {} blocks of slots {}..={} for warm start in {:.2}s",
+        blocks.len(),
+        first_slot,
+        last_slot,
+        started_at.elapsed().as_secs_f64()
+    );
+    blocks
+}
@@ -1,15 +1,479 @@
+use std::sync::Arc;
+
+use log::debug;
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
+use solana_lite_rpc_core::traits::block_storage::BlockStorage;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use solana_rpc_client_api::config::{RpcBlockConfig, RpcTransactionConfig};
+use solana_rpc_client_api::custom_error::{
+    JSON_RPC_SERVER_ERROR_BLOCK_CLEANED_UP, JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE,
+    JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED, JSON_RPC_SERVER_ERROR_SLOT_SKIPPED,
+};
+use solana_rpc_client_api::request::{RpcError, RpcRequest};
+use solana_sdk::clock::UnixTimestamp;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_sdk::slot_history::Slot;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, Reward, TransactionConfirmationStatus,
+    TransactionDetails, TransactionStatus, UiConfirmedBlock,
+};
+
+use crate::block_stores::faithful_history::faithful_block_store::FaithfulBlockStore;
+
+// how far after the first slot of an epoch the stored blocks are searched for the first block,
+// the leaders of the first slots might have skipped them
+const MAX_SKIPPED_SLOTS: u64 = 64;
+
+lazy_static::lazy_static! {
+    static ref HISTORY_STORED_BLOCK_TIMES: IntCounter =
+        register_int_counter!(opts!("literpc_history_stored_block_times", "Number of block times served from the block storage")).unwrap();
+    static ref HISTORY_FAITHFUL_BLOCK_TIMES: IntCounter =
+        register_int_counter!(opts!("literpc_history_faithful_block_times", "Number of block times served from faithful_history")).unwrap();
+    static ref HISTORY_STORED_BLOCKS: IntCounter =
+        register_int_counter!(opts!("literpc_history_stored_blocks", "Number of blocks served from the block storage")).unwrap();
+    static ref HISTORY_FAITHFUL_BLOCKS: IntCounter =
+        register_int_counter!(opts!("literpc_history_faithful_blocks", "Number of blocks served from faithful_history")).unwrap();
+    static ref HISTORY_FAITHFUL_TRANSACTIONS: IntCounter =
+        register_int_counter!(opts!("literpc_history_faithful_transactions", "Number of transactions served from faithful_history")).unwrap();
+    static ref HISTORY_STORED_SIGNATURE_STATUSES: IntCounter =
+        register_int_counter!(opts!("literpc_history_stored_signature_statuses", "Number of signature statuses served from the block storage")).unwrap();
+}
+
+/// Serves blocks and transactions from the block storage (if configured) and the rpc node and falls back to
+/// faithful_history (if configured) for the ones which are older than the ledger retention of the rpc node.
 pub struct History {
-    // Placeholder
+    rpc_client: Arc<RpcClient>,
+    faithful_block_store: Option<FaithfulBlockStore>,
+    block_storage: Option<Arc<dyn BlockStorage>>,
 }
 
 impl History {
-    pub fn new() -> Self {
-        History {}
+    pub fn new(rpc_client: Arc<RpcClient>, faithful_rpc_client: Option<Arc<RpcClient>>) -> Self {
+        History {
+            rpc_client,
+            faithful_block_store: faithful_rpc_client.map(FaithfulBlockStore::new),
+            block_storage: None,
+        }
+    }
+
+    /// persisted blocks, block times and signature statuses are read from the block storage before asking the rpc node
+    pub fn with_block_storage(mut self, block_storage: Arc<dyn BlockStorage>) -> Self {
+        self.block_storage = Some(block_storage);
+        self
+    }
+
+    /// block time of slots which are no longer in memory: block storage, then the rpc node, then faithful_history;
+    /// none if no source has a block at the slot, failures of the sources are returned
+    pub async fn get_block_time(&self, slot: Slot) -> anyhow::Result<Option<UnixTimestamp>> {
+        if let Some(block_storage) = &self.block_storage {
+            if block_storage.is_block_in_range(slot).await {
+                if let Some(block_time) = block_storage.query_block_time(slot).await? {
+                    HISTORY_STORED_BLOCK_TIMES.inc();
+                    return Ok(Some(block_time));
+                }
+            }
+        }
+
+        let local_err = match self.rpc_client.get_block_time(slot).await {
+            Ok(block_time) => return Ok(Some(block_time)),
+            Err(err) => err,
+        };
+        let Some(faithful_block_store) = &self.faithful_block_store else {
+            return if is_block_not_available(&local_err) {
+                Ok(None)
+            } else {
+                Err(local_err.into())
+            };
+        };
+
+        match faithful_block_store.get_block_time(slot).await {
+            Ok(block_time) => {
+                HISTORY_FAITHFUL_BLOCK_TIMES.inc();
+                Ok(Some(block_time))
+            }
+            Err(err)
+                if err
+                    .downcast_ref::<ClientError>()
+                    .is_some_and(is_block_not_available) =>
+            {
+                debug!(
+                    "Block time of {} not found in any history source: {}",
+                    slot, err
+                );
+                if is_block_not_available(&local_err) {
+                    Ok(None)
+                } else {
+                    Err(local_err.into())
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn get_block(
+        &self,
+        slot: Slot,
+        config: RpcBlockConfig,
+    ) -> anyhow::Result<Option<UiConfirmedBlock>> {
+        if let Some(block_storage) = &self.block_storage {
+            // the stored blocks carry no transaction metas, full transactions come from the rpc node
+            if stored_block_details(&config) && block_storage.is_block_in_range(slot).await {
+                match block_storage.query_block(slot).await {
+                    Ok(block) => {
+                        HISTORY_STORED_BLOCKS.inc();
+                        return Ok(Some(to_ui_block(block, &config)));
+                    }
+                    Err(err) => debug!("Block {} not found in block storage: {}", slot, err),
+                }
+            }
+        }
+
+        // the rpc node answers with an error for slots which were cleaned up from its ledger
+        let local = self
+            .rpc_client
+            .send::<Option<UiConfirmedBlock>>(
+                RpcRequest::GetBlock,
+                serde_json::json!([slot, config]),
+            )
+            .await;
+
+        let faithful_block_store = match (&local, &self.faithful_block_store) {
+            (Ok(Some(_)), _) | (_, None) => return Ok(local?),
+            (_, Some(faithful_block_store)) => faithful_block_store,
+        };
+
+        match faithful_block_store.get_ui_block(slot, config).await {
+            Ok(block) => {
+                HISTORY_FAITHFUL_BLOCKS.inc();
+                Ok(Some(block))
+            }
+            Err(err) => {
+                debug!("Block {} not found in any history source: {}", slot, err);
+                Ok(local?)
+            }
+        }
+    }
+
+    /// slot and rewards of the first block at or after the slot, the epoch rewards are paid out in
+    /// the first block of an epoch
+    pub async fn get_first_block_rewards(
+        &self,
+        slot: Slot,
+    ) -> anyhow::Result<Option<(Slot, Vec<Reward>)>> {
+        if let Some(block_storage) = &self.block_storage {
+            if block_storage.get_slot_range().await.contains(&slot) {
+                if let Some(first_slot) = block_storage
+                    .query_first_slot(slot..=slot + MAX_SKIPPED_SLOTS)
+                    .await?
+                {
+                    let block = block_storage.query_block(first_slot).await?;
+                    return Ok(Some((first_slot, block.rewards.unwrap_or_default())));
+                }
+            }
+        }
+
+        // the rpc node does not know slots beyond its ledger, faithful_history still serves them
+        let first_slot = match self.rpc_client.get_blocks_with_limit(slot, 1).await {
+            Ok(slots) => match slots.first() {
+                Some(first_slot) => *first_slot,
+                None => return Ok(None),
+            },
+            Err(err) => {
+                debug!(
+                    "First block after {} not found on the rpc node: {}",
+                    slot, err
+                );
+                slot
+            }
+        };
+        let config = RpcBlockConfig {
+            transaction_details: Some(TransactionDetails::None),
+            rewards: Some(true),
+            commitment: Some(CommitmentConfig::finalized()),
+            max_supported_transaction_version: Some(0),
+            ..RpcBlockConfig::default()
+        };
+        let block = self.get_block(first_slot, config).await?;
+        Ok(block.map(|block| (first_slot, block.rewards.unwrap_or_default())))
+    }
+
+    /// status of a transaction which is no longer in memory, looked up by signature in the block storage
+    pub async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> anyhow::Result<Option<TransactionStatus>> {
+        let Some(block_storage) = &self.block_storage else {
+            return Ok(None);
+        };
+        let Some((slot, transaction)) = block_storage.query_transaction(signature).await? else {
+            return Ok(None);
+        };
+        HISTORY_STORED_SIGNATURE_STATUSES.inc();
+        // only finalized blocks are stored
+        Ok(Some(TransactionStatus {
+            slot,
+            confirmations: None,
+            status: transaction.err.clone().map_or(Ok(()), Err),
+            err: transaction.err,
+            confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+        }))
+    }
+
+    /// the stored transactions carry no status metas, transactions are served by the rpc node and faithful_history
+    pub async fn get_transaction(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> anyhow::Result<Option<EncodedConfirmedTransactionWithStatusMeta>> {
+        let local = self
+            .rpc_client
+            .send::<Option<EncodedConfirmedTransactionWithStatusMeta>>(
+                RpcRequest::GetTransaction,
+                serde_json::json!([signature.to_string(), config]),
+            )
+            .await;
+
+        let faithful_block_store = match (&local, &self.faithful_block_store) {
+            (Ok(Some(_)), _) | (_, None) => return Ok(local?),
+            (_, Some(faithful_block_store)) => faithful_block_store,
+        };
+
+        match faithful_block_store
+            .get_transaction(signature, config)
+            .await
+        {
+            Ok(Some(transaction)) => {
+                HISTORY_FAITHFUL_TRANSACTIONS.inc();
+                Ok(Some(transaction))
+            }
+            Ok(None) => Ok(local?),
+            Err(err) => {
+                debug!(
+                    "Transaction {} not found in any history source: {}",
+                    signature, err
+                );
+                Ok(local?)
+            }
+        }
+    }
+}
+
+/// the block storage can answer block requests without transaction details or with signatures only
+fn stored_block_details(config: &RpcBlockConfig) -> bool {
+    matches!(
+        config.transaction_details,
+        Some(TransactionDetails::None) | Some(TransactionDetails::Signatures)
+    )
+}
+
+fn to_ui_block(block: ProducedBlock, config: &RpcBlockConfig) -> UiConfirmedBlock {
+    let signatures = match config.transaction_details {
+        Some(TransactionDetails::Signatures) => Some(
+            block
+                .transactions
+                .iter()
+                .map(|transaction| transaction.signature.to_string())
+                .collect(),
+        ),
+        _ => None,
+    };
+    let rewards = config
+        .rewards
+        .unwrap_or(true)
+        .then(|| block.rewards.unwrap_or_default());
+    UiConfirmedBlock {
+        previous_blockhash: block.previous_blockhash.to_string(),
+        blockhash: block.blockhash.to_string(),
+        parent_slot: block.parent_slot,
+        transactions: None,
+        signatures,
+        rewards,
+        block_time: Some(block.block_time as UnixTimestamp),
+        block_height: Some(block.block_height),
     }
 }
 
-impl Default for History {
-    fn default() -> Self {
-        Self::new()
+// solana-rpc answers slots without a block (skipped, cleaned up or not produced yet) with these errors
+fn is_block_not_available(err: &ClientError) -> bool {
+    matches!(
+        err.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. })
+            if [
+                JSON_RPC_SERVER_ERROR_BLOCK_CLEANED_UP,
+                JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE,
+                JSON_RPC_SERVER_ERROR_SLOT_SKIPPED,
+                JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED,
+            ]
+            .contains(code)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::RangeInclusive;
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    // blocks of the stored slots, every query fails if broken
+    struct TestBlockStorage {
+        slots: Vec<Slot>,
+        broken: bool,
+    }
+
+    #[async_trait]
+    impl BlockStorage for TestBlockStorage {
+        async fn save_block(&self, _block: &ProducedBlock) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn query_block(&self, slot: Slot) -> anyhow::Result<ProducedBlock> {
+            anyhow::ensure!(
+                !self.broken && self.slots.contains(&slot),
+                "block {slot} not found"
+            );
+            Ok(ProducedBlock {
+                slot,
+                rewards: Some(vec![]),
+                ..stored_block()
+            })
+        }
+
+        async fn query_first_slot(
+            &self,
+            slots: RangeInclusive<Slot>,
+        ) -> anyhow::Result<Option<Slot>> {
+            anyhow::ensure!(!self.broken, "storage unavailable");
+            Ok(self.slots.iter().copied().find(|slot| slots.contains(slot)))
+        }
+
+        async fn get_slot_range(&self) -> RangeInclusive<Slot> {
+            self.slots[0]..=*self.slots.last().unwrap()
+        }
+    }
+
+    fn history(slots: Vec<Slot>, broken: bool) -> History {
+        // nothing listens on the port, the rpc node is never asked in these tests
+        History::new(
+            Arc::new(RpcClient::new("http://127.0.0.1:1".to_string())),
+            None,
+        )
+        .with_block_storage(Arc::new(TestBlockStorage { slots, broken }))
+    }
+
+    #[tokio::test]
+    async fn test_first_block_rewards_skip_the_missing_slots() {
+        let history = history(vec![90, 103, 150], false);
+        let (slot, rewards) = history.get_first_block_rewards(100).await.unwrap().unwrap();
+        assert_eq!(slot, 103);
+        assert!(rewards.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_time_is_read_from_the_block_storage() {
+        let history = history(vec![90, 103, 150], false);
+        assert_eq!(history.get_block_time(103).await.unwrap(), Some(1699260872));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_transaction_is_none() {
+        let mocks = [(RpcRequest::GetTransaction, serde_json::Value::Null)].into();
+        let history = History::new(
+            Arc::new(RpcClient::new_mock_with_mocks(
+                "succeeds".to_string(),
+                mocks,
+            )),
+            None,
+        );
+        let transaction = history
+            .get_transaction(&Signature::new_unique(), RpcTransactionConfig::default())
+            .await
+            .unwrap();
+        assert!(transaction.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_block_time_returns_storage_errors() {
+        let history = history(vec![90, 103, 150], true);
+        assert!(history.get_block_time(103).await.is_err());
+    }
+
+    #[test]
+    fn test_only_missing_blocks_are_not_available() {
+        let not_available =
+            ClientError::from(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: JSON_RPC_SERVER_ERROR_SLOT_SKIPPED,
+                message: "Slot 103 was skipped".to_string(),
+                data: solana_rpc_client_api::request::RpcResponseErrorData::Empty,
+            }));
+        assert!(is_block_not_available(&not_available));
+        let unreachable =
+            ClientError::from(ClientErrorKind::Custom("connection refused".to_string()));
+        assert!(!is_block_not_available(&unreachable));
+    }
+
+    #[tokio::test]
+    async fn test_first_block_rewards_return_storage_errors() {
+        let history = history(vec![90, 103, 150], true);
+        assert!(history.get_first_block_rewards(100).await.is_err());
+    }
+
+    fn stored_block() -> ProducedBlock {
+        ProducedBlock {
+            block_height: 42,
+            block_time: 1699260872,
+            ..ProducedBlock::new_for_tests(667, CommitmentConfig::finalized())
+        }
+    }
+
+    #[test]
+    fn test_full_transactions_are_not_served_from_the_block_storage() {
+        assert!(!stored_block_details(&RpcBlockConfig::default()));
+        assert!(!stored_block_details(&RpcBlockConfig {
+            transaction_details: Some(TransactionDetails::Full),
+            ..RpcBlockConfig::default()
+        }));
+        assert!(stored_block_details(&RpcBlockConfig {
+            transaction_details: Some(TransactionDetails::Signatures),
+            ..RpcBlockConfig::default()
+        }));
+    }
+
+    #[test]
+    fn test_stored_block_is_encoded_according_to_the_config() {
+        let block = stored_block();
+        let ui_block = to_ui_block(
+            block.clone(),
+            &RpcBlockConfig {
+                transaction_details: Some(TransactionDetails::Signatures),
+                rewards: Some(false),
+                ..RpcBlockConfig::default()
+            },
+        );
+        assert_eq!(ui_block.blockhash, block.blockhash.to_string());
+        assert_eq!(
+            ui_block.previous_blockhash,
+            block.previous_blockhash.to_string()
+        );
+        assert_eq!(ui_block.parent_slot, 666);
+        assert_eq!(ui_block.block_time, Some(1699260872));
+        assert_eq!(ui_block.block_height, Some(42));
+        assert_eq!(ui_block.signatures, Some(vec![]));
+        assert!(ui_block.rewards.is_none());
+        assert!(ui_block.transactions.is_none());
+
+        let ui_block = to_ui_block(
+            block,
+            &RpcBlockConfig {
+                transaction_details: Some(TransactionDetails::None),
+                ..RpcBlockConfig::default()
+            },
+        );
+        assert!(ui_block.signatures.is_none());
+        assert_eq!(ui_block.rewards, Some(vec![]));
     }
 }
@@ -0,0 +1,392 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use prometheus::{opts, register_int_counter, register_int_gauge, IntCounter, IntGauge};
+use solana_lite_rpc_cluster_endpoints::block_info_stream::create_block_info_stream;
+use solana_lite_rpc_cluster_endpoints::endpoint_stremers::EndpointStreaming;
+use solana_lite_rpc_cluster_endpoints::rpc_polling::vote_accounts_and_cluster_info_polling::{
+    poll_cluster_info, poll_vote_accounts,
+};
+use solana_lite_rpc_cluster_endpoints::rpc_pool::RpcPool;
+use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
+use solana_lite_rpc_core::structures::slot_notification::SlotNotification;
+use solana_lite_rpc_core::traits::block_storage::BlockStorage;
+use solana_lite_rpc_core::AnyhowJoinHandle;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::slot_history::Slot;
+use tokio::sync::broadcast::Sender;
+use tokio::time::Instant;
+
+// nominal slot time of the cluster, the pace of a replay at speed 1
+const SLOT_DURATION: Duration = Duration::from_millis(400);
+// as fast as possible still waits for the consumers once the channel is this full, they would lag otherwise
+const MAX_BUFFERED_BLOCKS: usize = 32;
+// the broadcast channel does not notify once the consumers caught up, the checks back off up to this
+const MAX_BACKPRESSURE_WAIT: Duration = Duration::from_millis(50);
+const BLOCK_CHANNEL_SIZE: usize = 64;
+// the range of the block storage is checked again after the end of the stored blocks was reached
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+lazy_static::lazy_static! {
+    static ref REPLAY_BLOCKS: IntCounter =
+        register_int_counter!(opts!("literpc_replay_blocks", "Number of blocks replayed from the block storage")).unwrap();
+    static ref REPLAY_SLOT: IntGauge =
+        register_int_gauge!(opts!("literpc_replay_slot", "Slot of the last block replayed from the block storage")).unwrap();
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplayConfig {
+    pub from_slot: Slot,
+    /// replays up to the newest stored block and follows the blocks stored later if not set
+    pub to_slot: Option<Slot>,
+    /// multiple of the real-time pace, 0 replays as fast as the consumers keep up
+    pub speed: f64,
+}
+
+/// Streams the stored blocks of a slot range instead of the blocks of the cluster, so that the
+/// notifiers and everything subscribed to them (websocket, geyser re-export, sinks) serve the
+/// history unchanged. Blocks are sent with the processed, confirmed and finalized commitment like
+/// a live source would. Cluster nodes and vote accounts are still polled from the rpc nodes.
+/// Transactions must not be sent while replaying, the caller starts the node draining.
+pub fn create_replay_subscription(
+    block_storage: Arc<dyn BlockStorage>,
+    rpc_pool: RpcPool,
+    config: ReplayConfig,
+) -> anyhow::Result<(EndpointStreaming, Vec<AnyhowJoinHandle>)> {
+    anyhow::ensure!(
+        config.speed >= 0.0 && config.speed.is_finite(),
+        "replay speed must be a positive number or 0"
+    );
+    if let Some(to_slot) = config.to_slot {
+        anyhow::ensure!(
+            to_slot >= config.from_slot,
+            "replay must end at or after slot {}",
+            config.from_slot
+        );
+    }
+
+    let (slot_sx, slot_notifier) = tokio::sync::broadcast::channel(16);
+    let (block_sx, blocks_notifier) = tokio::sync::broadcast::channel(BLOCK_CHANNEL_SIZE);
+    let (cluster_info_sx, cluster_info_notifier) = tokio::sync::broadcast::channel(16);
+    let (va_sx, vote_account_notifier) = tokio::sync::broadcast::channel(16);
+
+    let mut endpoint_tasks = vec![replay_blocks(block_storage, config, block_sx, slot_sx)];
+
+    let (block_info_notifier, block_info_task) =
+        create_block_info_stream(blocks_notifier.resubscribe());
+    endpoint_tasks.push(block_info_task);
+    endpoint_tasks.push(poll_cluster_info(rpc_pool.clone(), cluster_info_sx));
+    endpoint_tasks.push(poll_vote_accounts(rpc_pool, va_sx));
+
+    let streamers = EndpointStreaming {
+        blocks_notifier,
+        block_info_notifier,
+        slot_notifier,
+        cluster_info_notifier,
+        vote_account_notifier,
+        processed_account_stream: None,
+        // the stored blocks are ordered
+        block_ordering_notifier: None,
+        // the clock of the replayed slots is not stored
+        clock_notifier: None,
+    };
+    Ok((streamers, endpoint_tasks))
+}
+
+fn replay_blocks(
+    block_storage: Arc<dyn BlockStorage>,
+    config: ReplayConfig,
+    block_sx: Sender<ProducedBlock>,
+    slot_sx: Sender<SlotNotification>,
+) -> AnyhowJoinHandle {
+    tokio::spawn(async move {
+        let ReplayConfig {
+            from_slot,
+            to_slot,
+            speed,
+        } = config;
+        let stored = block_storage.get_slot_range().await;
+        if stored.is_empty() || *stored.end() < from_slot {
+            warn!("no blocks stored after slot {from_slot}, waiting for blocks to replay");
+        }
+        info!(
+            "Replaying the stored blocks from slot {from_slot} to {} at {}",
+            to_slot.map_or("the newest stored slot".to_string(), |slot| slot
+                .to_string()),
+            if speed == 0.0 {
+                "full speed".to_string()
+            } else {
+                format!("{speed}x speed")
+            }
+        );
+
+        let started_at = Instant::now();
+        let mut nb_blocks = 0;
+        let mut slot = from_slot;
+        // the pace is relative to the first replayed block
+        let mut first_block: Option<(Slot, Instant)> = None;
+        loop {
+            if to_slot.is_some_and(|to_slot| slot > to_slot) {
+                info!(
+                    "Replayed {nb_blocks} blocks of slots {from_slot}..={} in {:.2}s",
+                    slot - 1,
+                    started_at.elapsed().as_secs_f64()
+                );
+                // the consumers would shut down on a closed stream, the last state stays served
+                std::future::pending::<()>().await;
+            }
+            let stored = block_storage.get_slot_range().await;
+            if stored.is_empty() || *stored.end() < slot {
+                tokio::time::sleep(FOLLOW_INTERVAL).await;
+                continue;
+            }
+            // blocks stored before the replay started are not there anymore
+            slot = slot.max(*stored.start());
+
+            let block = match block_storage.query_block(slot).await {
+                Ok(block) => block,
+                Err(err) => {
+                    // skipped or not stored
+                    debug!("Slot {slot} not replayed: {err:?}");
+                    slot += 1;
+                    continue;
+                }
+            };
+
+            if speed == 0.0 {
+                let mut wait = Duration::from_millis(1);
+                while block_sx.len() > MAX_BUFFERED_BLOCKS {
+                    tokio::time::sleep(wait).await;
+                    wait = (wait * 2).min(MAX_BACKPRESSURE_WAIT);
+                }
+            } else if let Some((first_slot, first_sent_at)) = first_block {
+                // the pace of the slots, independent of the time it took to load the block
+                let elapsed_slots = block.slot - first_slot;
+                let due_at = first_sent_at + SLOT_DURATION.mul_f64(elapsed_slots as f64 / speed);
+                tokio::time::sleep_until(due_at).await;
+            } else {
+                first_block = Some((block.slot, Instant::now()));
+            }
+
+            let _ = slot_sx.send(SlotNotification {
+                processed_slot: block.slot,
+                estimated_processed_slot: block.slot,
+            });
+            let confirmed = block.to_confirmed_block();
+            let finalized = block.to_finalized_block();
+            for block in [
+                ProducedBlock {
+                    commitment_config: CommitmentConfig::processed(),
+                    ..block
+                },
+                confirmed,
+                finalized,
+            ] {
+                if block_sx.send(block).is_err() {
+                    anyhow::bail!("block stream closed");
+                }
+            }
+            REPLAY_BLOCKS.inc();
+            REPLAY_SLOT.set(slot as i64);
+            nb_blocks += 1;
+            slot += 1;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::ops::RangeInclusive;
+    use std::sync::RwLock;
+
+    use async_trait::async_trait;
+    use tokio::sync::broadcast::{self, Receiver};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct InMemoryBlockStorage(RwLock<BTreeMap<Slot, ProducedBlock>>);
+
+    impl InMemoryBlockStorage {
+        fn with_slots(slots: impl IntoIterator<Item = Slot>) -> Arc<Self> {
+            let storage = Self::default();
+            for slot in slots {
+                storage.0.write().unwrap().insert(
+                    slot,
+                    ProducedBlock::new_for_tests(slot, CommitmentConfig::finalized()),
+                );
+            }
+            Arc::new(storage)
+        }
+    }
+
+    #[async_trait]
+    impl BlockStorage for InMemoryBlockStorage {
+        async fn save_block(&self, block: &ProducedBlock) -> anyhow::Result<()> {
+            self.0.write().unwrap().insert(block.slot, block.clone());
+            Ok(())
+        }
+
+        async fn query_block(&self, slot: Slot) -> anyhow::Result<ProducedBlock> {
+            self.0
+                .read()
+                .unwrap()
+                .get(&slot)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("slot {slot} not stored"))
+        }
+
+        async fn query_first_slot(
+            &self,
+            slots: RangeInclusive<Slot>,
+        ) -> anyhow::Result<Option<Slot>> {
+            Ok(self
+                .0
+                .read()
+                .unwrap()
+                .range(slots)
+                .next()
+                .map(|(slot, _)| *slot))
+        }
+
+        async fn get_slot_range(&self) -> RangeInclusive<Slot> {
+            let blocks = self.0.read().unwrap();
+            match (blocks.keys().next(), blocks.keys().next_back()) {
+                (Some(first), Some(last)) => *first..=*last,
+                _ => 1..=0,
+            }
+        }
+    }
+
+    fn start_replay(
+        block_storage: Arc<InMemoryBlockStorage>,
+        config: ReplayConfig,
+    ) -> (AnyhowJoinHandle, Receiver<ProducedBlock>) {
+        let (block_sx, blocks) = broadcast::channel(BLOCK_CHANNEL_SIZE);
+        let (slot_sx, _slots) = broadcast::channel(BLOCK_CHANNEL_SIZE);
+        (
+            replay_blocks(block_storage, config, block_sx, slot_sx),
+            blocks,
+        )
+    }
+
+    // the slots of the processed blocks received until the replay has nothing more to send
+    async fn replayed_slots(blocks: &mut Receiver<ProducedBlock>) -> Vec<Slot> {
+        let mut slots = vec![];
+        while let Ok(Ok(block)) = tokio::time::timeout(Duration::from_secs(10), blocks.recv()).await
+        {
+            if block.commitment_config == CommitmentConfig::processed() {
+                slots.push(block.slot);
+            }
+        }
+        slots
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_skipped_slots_are_left_out_up_to_the_end_slot() {
+        let block_storage = InMemoryBlockStorage::with_slots([10, 11, 13, 14, 15]);
+        let (_replay, mut blocks) = start_replay(
+            block_storage,
+            ReplayConfig {
+                from_slot: 11,
+                to_slot: Some(14),
+                speed: 0.0,
+            },
+        );
+
+        assert_eq!(replayed_slots(&mut blocks).await, vec![11, 13, 14]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_every_block_is_sent_with_all_commitments() {
+        let block_storage = InMemoryBlockStorage::with_slots([10]);
+        let (_replay, mut blocks) = start_replay(
+            block_storage,
+            ReplayConfig {
+                from_slot: 10,
+                to_slot: Some(10),
+                speed: 0.0,
+            },
+        );
+
+        for commitment_config in [
+            CommitmentConfig::processed(),
+            CommitmentConfig::confirmed(),
+            CommitmentConfig::finalized(),
+        ] {
+            let block = blocks.recv().await.unwrap();
+            assert_eq!(block.slot, 10);
+            assert_eq!(block.commitment_config, commitment_config);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_starts_at_the_oldest_stored_slot_and_follows_new_blocks() {
+        let block_storage = InMemoryBlockStorage::with_slots([10, 11]);
+        let (_replay, mut blocks) = start_replay(
+            block_storage.clone(),
+            ReplayConfig {
+                from_slot: 5,
+                to_slot: None,
+                speed: 0.0,
+            },
+        );
+        assert_eq!(replayed_slots(&mut blocks).await, vec![10, 11]);
+
+        block_storage
+            .save_block(&ProducedBlock::new_for_tests(
+                12,
+                CommitmentConfig::finalized(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(replayed_slots(&mut blocks).await, vec![12]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_blocks_are_paced_by_their_slots() {
+        let block_storage = InMemoryBlockStorage::with_slots([10, 11, 14]);
+        let (_replay, mut blocks) = start_replay(
+            block_storage,
+            ReplayConfig {
+                from_slot: 10,
+                to_slot: Some(14),
+                speed: 2.0,
+            },
+        );
+
+        let mut sent_at = vec![];
+        while sent_at.len() < 3 {
+            let block = blocks.recv().await.unwrap();
+            if block.commitment_config == CommitmentConfig::processed() {
+                sent_at.push(Instant::now());
+            }
+        }
+        // half a slot duration per slot at twice the speed, the skipped slots included
+        assert_eq!(sent_at[1] - sent_at[0], SLOT_DURATION / 2);
+        assert_eq!(sent_at[2] - sent_at[0], SLOT_DURATION * 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_full_speed_waits_for_the_consumers() {
+        let block_storage = InMemoryBlockStorage::with_slots(0..100);
+        let (_replay, mut blocks) = start_replay(
+            block_storage,
+            ReplayConfig {
+                from_slot: 0,
+                to_slot: Some(99),
+                speed: 0.0,
+            },
+        );
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        // three commitments per block on top of the limit
+        assert!(blocks.len() <= MAX_BUFFERED_BLOCKS + 3);
+        assert_eq!(
+            replayed_slots(&mut blocks).await,
+            (0..100).collect::<Vec<_>>()
+        );
+    }
+}
@@ -79,7 +79,11 @@ async fn storage_test() {
         PostgresQueryBlockStore::new(epoch_cache.clone(), pg_session_config.clone()).await,
     );
 
-    let block_storage = Arc::new(PostgresBlockStore::new(epoch_cache, pg_session_config).await);
+    let block_storage = Arc::new(
+        PostgresBlockStore::new(epoch_cache, pg_session_config)
+            .await
+            .unwrap(),
+    );
     let current_epoch = rpc_client.get_epoch_info().await.unwrap().epoch;
     block_storage
         .drop_epoch_schema(EpochRef::new(current_epoch))
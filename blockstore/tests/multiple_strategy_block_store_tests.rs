@@ -1,26 +1,19 @@
 use solana_lite_rpc_blockstore::block_stores::multiple_strategy_block_store::BlockStorageData;
 use solana_lite_rpc_blockstore::block_stores::multiple_strategy_block_store::MultipleStrategyBlockStorage;
+use solana_lite_rpc_blockstore::block_stores::postgres::postgres_block_storage::PostgresBlockStorage;
 use solana_lite_rpc_blockstore::block_stores::postgres::postgres_block_store_query::PostgresQueryBlockStore;
 use solana_lite_rpc_blockstore::block_stores::postgres::postgres_block_store_writer::PostgresBlockStore;
 use solana_lite_rpc_blockstore::block_stores::postgres::PostgresSessionConfig;
 use solana_lite_rpc_core::structures::epoch::EpochCache;
 use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
+use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::reward_type::RewardType;
-use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash};
 use solana_transaction_status::Reward;
+use std::sync::Arc;
 
 pub fn create_test_block(slot: u64, commitment_config: CommitmentConfig) -> ProducedBlock {
     ProducedBlock {
-        block_height: slot,
-        blockhash: Hash::new_unique().to_string(),
-        previous_blockhash: Hash::new_unique().to_string(),
-        parent_slot: slot - 1,
-        transactions: vec![],
-        block_time: 0,
-        commitment_config,
-        leader_id: None,
-        slot,
         rewards: Some(vec![Reward {
             pubkey: Pubkey::new_unique().to_string(),
             lamports: 5000,
@@ -28,6 +21,7 @@ pub fn create_test_block(slot: u64, commitment_config: CommitmentConfig) -> Prod
             reward_type: Some(RewardType::Voting),
             commission: None,
         }]),
+        ..ProducedBlock::new_for_tests(slot, commitment_config)
     }
 }
 
@@ -38,11 +32,17 @@ async fn test_in_multiple_stategy_block_store() {
 
     let pg_session_config = PostgresSessionConfig::new_from_env().unwrap().unwrap();
     let epoch_cache = EpochCache::new_for_tests();
-    let persistent_store =
-        PostgresBlockStore::new(epoch_cache.clone(), pg_session_config.clone()).await;
-    let block_storage_query = PostgresQueryBlockStore::new(epoch_cache, pg_session_config).await;
+    let persistent_store = PostgresBlockStore::new(epoch_cache.clone(), pg_session_config.clone())
+        .await
+        .unwrap();
+    let block_storage_query =
+        PostgresQueryBlockStore::new(epoch_cache.clone(), pg_session_config).await;
     let multi_store = MultipleStrategyBlockStorage::new(
-        block_storage_query.clone(),
+        Arc::new(PostgresBlockStorage::new(
+            persistent_store.clone(),
+            block_storage_query,
+            epoch_cache,
+        )),
         None, // not supported
     );
 
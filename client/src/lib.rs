@@ -0,0 +1,40 @@
+//! typed client for the methods and subscriptions lite-rpc adds to the solana rpc
+
+use solana_sdk::signature::Signature;
+
+pub mod pubsub_client;
+pub mod rpc_client;
+pub mod types;
+
+pub use pubsub_client::LitePubsubClient;
+pub use rpc_client::LiteRpcClient;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ClientError {
+    #[error("rpc error {0}")]
+    Rpc(#[from] jsonrpsee::core::Error),
+    #[error("failed to serialize the transaction {0}")]
+    Serialization(#[from] bincode::Error),
+    #[error("invalid signature in the response {0}")]
+    InvalidSignature(#[from] solana_sdk::signature::ParseSignatureError),
+    #[error("transaction is not signed")]
+    Unsigned,
+    #[error("transaction {0} is unknown to the node")]
+    UnknownTransaction(Signature),
+    #[error("transaction {0} will not land: {1}")]
+    NotLanded(Signature, String),
+    #[error("transaction {0} did not land in time")]
+    Timeout(Signature),
+    #[error("subscription closed")]
+    SubscriptionClosed,
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+/// where a transaction landed at the awaited commitment
+#[derive(Clone, Debug)]
+pub struct LandedTransaction {
+    pub signature: Signature,
+    pub slot: solana_sdk::clock::Slot,
+    pub err: Option<solana_sdk::transaction::TransactionError>,
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use jsonrpsee::core::client::{Subscription, SubscriptionClientT};
+use jsonrpsee::rpc_params;
+use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
+use serde_json::json;
+use solana_rpc_client_api::response::{ProcessedSignatureResult, Response as RpcResponse};
+use solana_sdk::commitment_config::CommitmentLevel;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+
+use crate::rpc_client::{first_signature, LiteRpcClient};
+use crate::types::{BlockCommitmentNotification, PrioFeesSummary, SendTransactionConfig};
+use crate::{ClientError, ClientResult, LandedTransaction};
+
+/// client of the websocket endpoint of lite-rpc
+pub struct LitePubsubClient {
+    client: WsClient,
+}
+
+impl LitePubsubClient {
+    pub async fn connect(url: &str) -> ClientResult<Self> {
+        Ok(Self {
+            client: WsClientBuilder::default().build(url).await?,
+        })
+    }
+
+    pub fn from_client(client: WsClient) -> Self {
+        Self { client }
+    }
+
+    /// a single notification once the transaction landed at the commitment
+    pub async fn signature_subscribe(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentLevel,
+    ) -> ClientResult<Subscription<RpcResponse<ProcessedSignatureResult>>> {
+        Ok(self
+            .client
+            .subscribe(
+                "signatureSubscribe",
+                rpc_params![signature.to_string(), json!({ "commitment": commitment })],
+                "signatureUnsubscribe",
+            )
+            .await?)
+    }
+
+    /// the fee percentiles and the compute units of every processed block
+    pub async fn block_priofees_summary_subscribe(
+        &self,
+    ) -> ClientResult<Subscription<PrioFeesSummary>> {
+        Ok(self
+            .client
+            .subscribe(
+                "blockPrioritizationFeesSummarySubscribe",
+                rpc_params![],
+                "blockPrioritizationFeesSummaryUnsubscribe",
+            )
+            .await?)
+    }
+
+    /// every commitment a block reaches, conflicting blocks of a slot are flagged
+    pub async fn block_commitment_subscribe(
+        &self,
+    ) -> ClientResult<Subscription<BlockCommitmentNotification>> {
+        Ok(self
+            .client
+            .subscribe(
+                "blockCommitmentSubscribe",
+                rpc_params![],
+                "blockCommitmentUnsubscribe",
+            )
+            .await?)
+    }
+
+    /// subscribes to the signature before sending the transaction through the rpc client, so the
+    /// landing can not be missed
+    pub async fn send_and_confirm_transaction(
+        &self,
+        rpc_client: &LiteRpcClient,
+        transaction: &VersionedTransaction,
+        config: &SendTransactionConfig,
+        commitment: CommitmentLevel,
+        timeout: Duration,
+    ) -> ClientResult<LandedTransaction> {
+        let signature = first_signature(transaction)?;
+        let mut subscription = self.signature_subscribe(&signature, commitment).await?;
+        rpc_client.send_transaction(transaction, config).await?;
+
+        let notification = match tokio::time::timeout(timeout, subscription.next()).await {
+            Ok(Some(notification)) => notification?,
+            Ok(None) => return Err(ClientError::SubscriptionClosed),
+            Err(_elapsed) => return Err(ClientError::Timeout(signature)),
+        };
+        Ok(LandedTransaction {
+            signature,
+            slot: notification.context.slot,
+            err: notification.value.err,
+        })
+    }
+}
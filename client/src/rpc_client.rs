@@ -0,0 +1,265 @@
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::rpc_params;
+use serde_json::json;
+use solana_rpc_client_api::response::Response as RpcResponse;
+use solana_sdk::commitment_config::CommitmentLevel;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use solana_transaction_status::TransactionStatus;
+
+use crate::types::{
+    AccountContention, AccountPrioFeesStats, BlockUtilizationStats, ClockUpdate,
+    MessageFeeRecommendation, PrioFeeForMessageConfig, PrioFeesMethod, PrioFeesPercentiles,
+    PrioFeesStats, ProgramPrioFeesStats, SendTransactionConfig, TxLifecycle, UpcomingLeader,
+};
+use crate::{ClientError, ClientResult, LandedTransaction};
+
+// interval of polling the lifecycle while waiting for a transaction to land
+const LANDING_POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+/// client of the http endpoint of lite-rpc
+#[derive(Clone)]
+pub struct LiteRpcClient {
+    client: HttpClient,
+}
+
+impl LiteRpcClient {
+    pub fn new(url: &str) -> ClientResult<Self> {
+        Ok(Self {
+            client: HttpClientBuilder::default().build(url)?,
+        })
+    }
+
+    pub fn from_client(client: HttpClient) -> Self {
+        Self { client }
+    }
+
+    /// sends the base64 encoded transaction and returns its signature
+    pub async fn send_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+        config: &SendTransactionConfig,
+    ) -> ClientResult<Signature> {
+        let encoded = encode_transaction(transaction)?;
+        let mut config = serde_json::to_value(config).unwrap_or_else(|_| json!({}));
+        config["encoding"] = json!("base64");
+
+        let signature: String = self
+            .client
+            .request("sendTransaction", rpc_params![encoded, config])
+            .await?;
+        Ok(signature.parse()?)
+    }
+
+    /// sends the transaction and polls its lifecycle until it landed at the commitment, fails as
+    /// soon as lite-rpc dropped it or its blockhash expired
+    pub async fn send_and_confirm_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+        config: &SendTransactionConfig,
+        commitment: CommitmentLevel,
+        timeout: Duration,
+    ) -> ClientResult<LandedTransaction> {
+        let signature = self.send_transaction(transaction, config).await?;
+        self.confirm_transaction(&signature, commitment, timeout)
+            .await
+    }
+
+    /// waits for a transaction sent through this node to land at the commitment
+    pub async fn confirm_transaction(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentLevel,
+        timeout: Duration,
+    ) -> ClientResult<LandedTransaction> {
+        let started_at = Instant::now();
+        loop {
+            let Some(lifecycle) = self.get_transaction_lifecycle(signature).await? else {
+                return Err(ClientError::UnknownTransaction(*signature));
+            };
+
+            if let Some((slot, _failed)) = lifecycle.landed(commitment) {
+                let err = self
+                    .get_signature_status(signature)
+                    .await?
+                    .and_then(|status| status.err);
+                return Ok(LandedTransaction {
+                    signature: *signature,
+                    slot,
+                    err,
+                });
+            }
+            if let Some(reason) = lifecycle.abandoned() {
+                return Err(ClientError::NotLanded(*signature, reason));
+            }
+            if started_at.elapsed() >= timeout {
+                return Err(ClientError::Timeout(*signature));
+            }
+            tokio::time::sleep(LANDING_POLL_INTERVAL).await;
+        }
+    }
+
+    pub async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> ClientResult<Option<TransactionStatus>> {
+        let statuses: RpcResponse<Vec<Option<TransactionStatus>>> = self
+            .client
+            .request(
+                "getSignatureStatuses",
+                rpc_params![vec![signature.to_string()]],
+            )
+            .await?;
+        Ok(statuses.value.into_iter().next().flatten())
+    }
+
+    /// the states the transaction passed on this node, none for transactions it did not receive
+    pub async fn get_transaction_lifecycle(
+        &self,
+        signature: &Signature,
+    ) -> ClientResult<Option<TxLifecycle>> {
+        Ok(self
+            .client
+            .request(
+                "getTransactionLifecycle",
+                rpc_params![signature.to_string()],
+            )
+            .await?)
+    }
+
+    /// leaders of the next slots with their tpu address and stake, 150 slots if not set
+    pub async fn get_upcoming_leaders(
+        &self,
+        slots: Option<u64>,
+    ) -> ClientResult<Vec<UpcomingLeader>> {
+        Ok(self
+            .client
+            .request("getUpcomingLeaders", rpc_params![slots])
+            .await?)
+    }
+
+    pub async fn get_clock(&self) -> ClientResult<ClockUpdate> {
+        Ok(self.client.request("getClock", rpc_params![]).await?)
+    }
+
+    pub async fn get_latest_block_priofees(
+        &self,
+        method: PrioFeesMethod,
+    ) -> ClientResult<RpcResponse<PrioFeesStats>> {
+        Ok(self
+            .client
+            .request("getLatestBlockPrioFees", rpc_params![method])
+            .await?)
+    }
+
+    pub async fn get_latest_account_priofees(
+        &self,
+        account: &Pubkey,
+        method: PrioFeesMethod,
+    ) -> ClientResult<RpcResponse<AccountPrioFeesStats>> {
+        Ok(self
+            .client
+            .request(
+                "getLatestAccountPrioFees",
+                rpc_params![account.to_string(), method],
+            )
+            .await?)
+    }
+
+    pub async fn get_program_priofees(
+        &self,
+        program_id: &Pubkey,
+        method: PrioFeesMethod,
+    ) -> ClientResult<RpcResponse<ProgramPrioFeesStats>> {
+        Ok(self
+            .client
+            .request(
+                "getProgramPrioFees",
+                rpc_params![program_id.to_string(), method],
+            )
+            .await?)
+    }
+
+    pub async fn get_priofees_percentiles(
+        &self,
+        lookback_blocks: Option<usize>,
+    ) -> ClientResult<RpcResponse<PrioFeesPercentiles>> {
+        Ok(self
+            .client
+            .request("getPrioFeesPercentiles", rpc_params![lookback_blocks])
+            .await?)
+    }
+
+    pub async fn get_write_lock_priofees(
+        &self,
+        account: &Pubkey,
+        lookback_blocks: Option<usize>,
+    ) -> ClientResult<RpcResponse<PrioFeesPercentiles>> {
+        Ok(self
+            .client
+            .request(
+                "getWriteLockPrioFees",
+                rpc_params![account.to_string(), lookback_blocks],
+            )
+            .await?)
+    }
+
+    pub async fn get_block_utilization(
+        &self,
+        lookback_blocks: Option<usize>,
+    ) -> ClientResult<RpcResponse<BlockUtilizationStats>> {
+        Ok(self
+            .client
+            .request("getBlockUtilization", rpc_params![lookback_blocks])
+            .await?)
+    }
+
+    pub async fn get_hottest_accounts(
+        &self,
+        lookback_slots: Option<u64>,
+        limit: Option<usize>,
+    ) -> ClientResult<RpcResponse<Vec<AccountContention>>> {
+        Ok(self
+            .client
+            .request("getHottestAccounts", rpc_params![lookback_slots, limit])
+            .await?)
+    }
+
+    /// compute unit price to set for the message to land given the contention on its writable accounts
+    pub async fn get_recommended_priofee_for_message(
+        &self,
+        message: &VersionedMessage,
+        config: &PrioFeeForMessageConfig,
+    ) -> ClientResult<RpcResponse<MessageFeeRecommendation>> {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(message.serialize());
+        Ok(self
+            .client
+            .request(
+                "getRecommendedPrioFeeForMessage",
+                rpc_params![encoded, config],
+            )
+            .await?)
+    }
+}
+
+fn encode_transaction(transaction: &VersionedTransaction) -> ClientResult<String> {
+    if transaction.signatures.is_empty() {
+        return Err(ClientError::Unsigned);
+    }
+    let bytes = bincode::serialize(transaction)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+pub(crate) fn first_signature(transaction: &VersionedTransaction) -> ClientResult<Signature> {
+    transaction
+        .signatures
+        .first()
+        .copied()
+        .ok_or(ClientError::Unsigned)
+}
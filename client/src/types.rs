@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize, Serializer};
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentLevel;
+use solana_transaction_status::TransactionConfirmationStatus;
+
+/// the config of `sendTransaction`, the transaction is always sent base64 encoded
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendTransactionConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u16>,
+    /// url which gets a POST when the transaction is confirmed, finalized or expired
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
+}
+
+/// the blocks the prioritization fee statistics are calculated over
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PrioFeesMethod {
+    #[default]
+    Latest,
+    LastNBlocks(usize),
+    LastNSlots(u64),
+}
+
+impl Serialize for PrioFeesMethod {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            PrioFeesMethod::Latest => serializer.serialize_str("latest"),
+            PrioFeesMethod::LastNBlocks(nb) => {
+                serializer.serialize_str(&format!("last_n_blocks({nb})"))
+            }
+            PrioFeesMethod::LastNSlots(nb) => {
+                serializer.serialize_str(&format!("last_n_slots({nb})"))
+            }
+        }
+    }
+}
+
+/// the config of `getRecommendedPrioFeeForMessage`
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrioFeeForMessageConfig {
+    /// percentile of the fees paid on the writable accounts, must be a multiple of 5
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percentile: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookback_slots: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TxAggregateStats {
+    pub total: u64,
+    pub nonvote: u64,
+}
+
+/// fee distribution of a block or of the blocks of the calculation method
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PrioFeesStats {
+    // the arrays are same size and ordered monotonically
+    pub by_tx: Vec<u64>,
+    pub by_tx_percentiles: Vec<f32>,
+    pub by_cu: Vec<u64>,
+    pub by_cu_percentiles: Vec<f32>,
+    pub tx_count: TxAggregateStats,
+    pub cu_consumed: TxAggregateStats,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccountPrioFeesStats {
+    pub write_stats: PrioFeesStats,
+    pub all_stats: PrioFeesStats,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProgramPrioFeesStats {
+    pub program_stats: PrioFeesStats,
+    pub market_stats: PrioFeesStats,
+}
+
+/// compute unit prices (in micro lamports) at fixed percentiles
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct FeePercentiles {
+    pub p25: u64,
+    pub p50: u64,
+    pub p75: u64,
+    pub p90: u64,
+    pub p95: u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct LandedPrioFeesPercentiles {
+    pub tx_count: u64,
+    pub cu_consumed: u64,
+    pub by_tx: FeePercentiles,
+    pub by_cu: FeePercentiles,
+}
+
+/// answer of `getPrioFeesPercentiles` and `getWriteLockPrioFees`
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PrioFeesPercentiles {
+    pub nb_blocks: usize,
+    pub successful: LandedPrioFeesPercentiles,
+    pub failed: LandedPrioFeesPercentiles,
+}
+
+/// notification of `blockPrioritizationFeesSummarySubscribe`
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PrioFeesSummary {
+    pub slot: Slot,
+    pub tx_count: TxAggregateStats,
+    pub cu_consumed: TxAggregateStats,
+    pub by_tx: FeePercentiles,
+    pub by_cu: FeePercentiles,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct BlockUtilization {
+    pub slot: Slot,
+    pub cu_limit: u64,
+    pub cu_consumed: TxAggregateStats,
+    pub fill_ratio: f64,
+    pub nonvote_fill_ratio: f64,
+}
+
+/// answer of `getBlockUtilization`, the blocks are ordered newest first
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct BlockUtilizationStats {
+    pub nb_blocks: usize,
+    pub avg_fill_ratio: f64,
+    pub max_fill_ratio: f64,
+    pub avg_nonvote_fill_ratio: f64,
+    pub blocks: Vec<BlockUtilization>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccountWriteLockFee {
+    pub account: String,
+    pub tx_count: u64,
+    pub cu_price: u64,
+}
+
+/// answer of `getHottestAccounts`
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccountContention {
+    pub account: String,
+    pub nb_blocks: usize,
+    pub tx_count: u64,
+    pub cu_consumed: u64,
+    pub by_tx: FeePercentiles,
+}
+
+/// answer of `getRecommendedPrioFeeForMessage`
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct MessageFeeRecommendation {
+    pub recommended_cu_price: u64,
+    pub percentile: u32,
+    pub lookback_slots: u64,
+    pub contended_accounts: Vec<AccountWriteLockFee>,
+}
+
+/// state of a transaction sent through lite-rpc
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "state")]
+pub enum TxLifecycleState {
+    Received,
+    Sanitized,
+    Queued,
+    #[serde(rename_all = "camelCase")]
+    Forwarded {
+        leaders: Vec<String>,
+        attempt: usize,
+    },
+    #[serde(rename_all = "camelCase")]
+    ForwardFailed {
+        attempt: usize,
+        error: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Landed {
+        slot: Slot,
+        confirmation_status: TransactionConfirmationStatus,
+        failed: bool,
+    },
+    Expired,
+    Dropped {
+        reason: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    DryRun {
+        leaders: Vec<String>,
+        fee_lamports: u64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxLifecycleEvent {
+    #[serde(flatten)]
+    pub state: TxLifecycleState,
+    /// milliseconds since the unix epoch
+    pub timestamp: u64,
+}
+
+/// answer of `getTransactionLifecycle`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxLifecycle {
+    pub last_valid_block_height: u64,
+    pub events: Vec<TxLifecycleEvent>,
+}
+
+impl TxLifecycle {
+    pub fn state(&self) -> Option<&TxLifecycleState> {
+        self.events.last().map(|event| &event.state)
+    }
+
+    /// the slot and the failure of the landing at the commitment, none if it did not land yet
+    pub fn landed(&self, commitment: CommitmentLevel) -> Option<(Slot, bool)> {
+        self.events.iter().find_map(|event| match &event.state {
+            TxLifecycleState::Landed {
+                slot,
+                confirmation_status,
+                failed,
+            } if reaches_commitment(confirmation_status, commitment) => Some((*slot, *failed)),
+            _ => None,
+        })
+    }
+
+    /// the reason the transaction will not land anymore, none while it can still land
+    pub fn abandoned(&self) -> Option<String> {
+        self.events.iter().find_map(|event| match &event.state {
+            TxLifecycleState::Expired => Some("expired".to_string()),
+            TxLifecycleState::Dropped { reason } => Some(reason.clone()),
+            TxLifecycleState::DryRun { .. } => Some("dry_run".to_string()),
+            _ => None,
+        })
+    }
+}
+
+/// answer of `getUpcomingLeaders`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingLeader {
+    pub identity: String,
+    pub slots: Vec<Slot>,
+    pub tpu_quic: Option<String>,
+    pub stake: Option<u64>,
+}
+
+/// answer of `getClock`, the Clock sysvar of the latest processed slot
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockUpdate {
+    pub slot: Slot,
+    pub epoch: u64,
+    pub leader_schedule_epoch: u64,
+    pub unix_timestamp: i64,
+    pub epoch_start_timestamp: i64,
+}
+
+/// notification of `blockCommitmentSubscribe`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockCommitmentNotification {
+    pub slot: Slot,
+    pub blockhash: String,
+    pub commitment: CommitmentLevel,
+    pub previous_commitment: Option<CommitmentLevel>,
+    pub previous_blockhash: Option<String>,
+    pub conflict: bool,
+}
+
+pub fn reaches_commitment(
+    status: &TransactionConfirmationStatus,
+    commitment: CommitmentLevel,
+) -> bool {
+    match commitment {
+        CommitmentLevel::Finalized => *status == TransactionConfirmationStatus::Finalized,
+        CommitmentLevel::Confirmed => *status != TransactionConfirmationStatus::Processed,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_priofees_method_as_string() {
+        assert_eq!(
+            serde_json::to_string(&PrioFeesMethod::LastNBlocks(15)).unwrap(),
+            "\"last_n_blocks(15)\""
+        );
+        assert_eq!(
+            serde_json::to_string(&PrioFeesMethod::Latest).unwrap(),
+            "\"latest\""
+        );
+    }
+
+    #[test]
+    fn parses_lifecycle() {
+        let lifecycle: TxLifecycle = serde_json::from_str(
+            r#"{"lastValidBlockHeight":120,"events":[
+                {"state":"received","timestamp":1},
+                {"state":"forwarded","leaders":["abc"],"attempt":1,"timestamp":2},
+                {"state":"landed","slot":42,"confirmationStatus":"confirmed","failed":false,"timestamp":3}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            lifecycle.landed(CommitmentLevel::Confirmed),
+            Some((42, false))
+        );
+        assert_eq!(lifecycle.landed(CommitmentLevel::Finalized), None);
+        assert_eq!(lifecycle.abandoned(), None);
+    }
+}
@@ -0,0 +1,85 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use solana_lite_rpc_cluster_endpoints::grpc_subscription::from_grpc_block_update;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use yellowstone_grpc_proto::prelude::{
+    BlockHeight, CompiledInstruction, Message, MessageHeader, SubscribeUpdateBlock,
+    SubscribeUpdateTransactionInfo, Transaction, TransactionStatusMeta, UnixTimestamp,
+};
+
+// from an almost empty block to a congested mainnet block
+const TRANSACTIONS_PER_BLOCK: [usize; 3] = [100, 1_000, 5_000];
+
+fn bench_from_grpc_block_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_grpc_block_update");
+    for transactions in TRANSACTIONS_PER_BLOCK {
+        let block = block(transactions);
+        group.throughput(Throughput::Elements(transactions as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(transactions),
+            &block,
+            |b, block| {
+                b.iter_batched(
+                    || block.clone(),
+                    |block| from_grpc_block_update(block, CommitmentConfig::confirmed()).unwrap(),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+fn block(transactions: usize) -> SubscribeUpdateBlock {
+    SubscribeUpdateBlock {
+        slot: 42,
+        blockhash: Hash::new_unique().to_string(),
+        parent_slot: 41,
+        parent_blockhash: Hash::new_unique().to_string(),
+        block_time: Some(UnixTimestamp { timestamp: 0 }),
+        block_height: Some(BlockHeight { block_height: 42 }),
+        executed_transaction_count: transactions as u64,
+        transactions: (0..transactions).map(transaction).collect(),
+        ..Default::default()
+    }
+}
+
+// a transfer like transaction with a signer, a writable account and a program
+fn transaction(index: usize) -> SubscribeUpdateTransactionInfo {
+    let signature = Signature::new_unique();
+    SubscribeUpdateTransactionInfo {
+        signature: signature.as_ref().to_vec(),
+        is_vote: false,
+        index: index as u64,
+        transaction: Some(Transaction {
+            signatures: vec![signature.as_ref().to_vec()],
+            message: Some(Message {
+                header: Some(MessageHeader {
+                    num_required_signatures: 1,
+                    num_readonly_signed_accounts: 0,
+                    num_readonly_unsigned_accounts: 1,
+                }),
+                account_keys: (0..3)
+                    .map(|_| Pubkey::new_unique().to_bytes().to_vec())
+                    .collect(),
+                recent_blockhash: Hash::new_unique().to_bytes().to_vec(),
+                instructions: vec![CompiledInstruction {
+                    program_id_index: 2,
+                    accounts: vec![0, 1],
+                    data: vec![2, 0, 0, 0, 64, 66, 15, 0, 0, 0, 0, 0],
+                }],
+                versioned: true,
+                address_table_lookups: vec![],
+            }),
+        }),
+        meta: Some(TransactionStatusMeta {
+            compute_units_consumed: Some(450),
+            ..Default::default()
+        }),
+    }
+}
+
+criterion_group!(benches, bench_from_grpc_block_update);
+criterion_main!(benches);
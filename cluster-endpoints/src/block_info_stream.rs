@@ -0,0 +1,30 @@
+use log::warn;
+use solana_lite_rpc_core::{
+    structures::block_info::BlockInfo,
+    types::{BlockInfoStream, BlockStream},
+    AnyhowJoinHandle,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+/// the metadata of the blocks of the block stream; the block stream is subscribed once here instead
+/// of by every consumer which only needs the metadata
+pub fn create_block_info_stream(
+    mut block_stream: BlockStream,
+) -> (BlockInfoStream, AnyhowJoinHandle) {
+    let (block_info_sender, block_info_stream) = tokio::sync::broadcast::channel(64);
+    let jh = tokio::spawn(async move {
+        loop {
+            match block_stream.recv().await {
+                Ok(block) => {
+                    // nobody listens yet
+                    let _ = block_info_sender.send(BlockInfo::from(&block));
+                }
+                Err(RecvError::Lagged(lagged)) => {
+                    warn!("block info stream lagged by {lagged} blocks - continue");
+                }
+                Err(RecvError::Closed) => anyhow::bail!("block stream closed"),
+            }
+        }
+    });
+    (block_info_stream, jh)
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use log::{debug, warn};
+use solana_lite_rpc_core::{
+    structures::{
+        account_data::AccountStream, account_filter::AccountFilter, clock_update::ClockUpdate,
+    },
+    types::{ClockStream, LatestClock},
+    AnyhowJoinHandle,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, sysvar};
+use tokio::sync::{broadcast::error::RecvError, watch};
+
+use crate::rpc_pool::RpcPool;
+
+// the clock changes once per slot
+const CLOCK_POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+/// subscribes the Clock sysvar, independent of the account filters of the user
+pub fn clock_account_filter() -> AccountFilter {
+    AccountFilter {
+        accounts: vec![sysvar::clock::id().to_string()],
+        program_id: None,
+        filters: None,
+    }
+}
+
+/// the clock updates of the sysvar account stream
+pub fn create_clock_stream(mut account_stream: AccountStream) -> (ClockStream, AnyhowJoinHandle) {
+    let (clock_sender, clock_stream) = tokio::sync::broadcast::channel(16);
+    let jh = tokio::spawn(async move {
+        let mut last_slot = 0;
+        loop {
+            match account_stream.recv().await {
+                Ok(notification) => {
+                    let Some(clock) = ClockUpdate::from_account_data(&notification.data) else {
+                        continue;
+                    };
+                    // every source sends the clock of the slot
+                    if clock.slot <= last_slot {
+                        continue;
+                    }
+                    last_slot = clock.slot;
+                    // nobody listens yet
+                    let _ = clock_sender.send(clock);
+                }
+                Err(RecvError::Lagged(lagged)) => {
+                    warn!("clock stream lagged by {lagged} account updates - continue");
+                }
+                Err(RecvError::Closed) => anyhow::bail!("sysvar account stream closed"),
+            }
+        }
+    });
+    (clock_stream, jh)
+}
+
+/// the Clock sysvar polled from the rpc nodes, for sources without an account stream
+pub fn poll_clock(rpc_pool: RpcPool) -> (ClockStream, AnyhowJoinHandle) {
+    let (clock_sender, clock_stream) = tokio::sync::broadcast::channel(16);
+    // task MUST not terminate but might be aborted from outside
+    let jh = tokio::spawn(async move {
+        let mut last_slot = 0;
+        loop {
+            match rpc_pool
+                .call(|rpc_client| async move {
+                    rpc_client
+                        .get_account_with_commitment(
+                            &sysvar::clock::id(),
+                            CommitmentConfig::processed(),
+                        )
+                        .await
+                })
+                .await
+            {
+                Ok(response) => match response.value.as_ref().and_then(ClockUpdate::from_account) {
+                    Some(clock) if clock.slot > last_slot => {
+                        last_slot = clock.slot;
+                        let _ = clock_sender.send(clock);
+                    }
+                    Some(_) => {}
+                    None => warn!("clock sysvar could not be decoded"),
+                },
+                Err(error) => debug!("polling the clock sysvar failed <{error:?}> - retrying"),
+            }
+            tokio::time::sleep(CLOCK_POLL_INTERVAL).await;
+        }
+    });
+    (clock_stream, jh)
+}
+
+/// the latest clock of the stream, for consumers which only need the current time
+pub fn create_latest_clock(mut clock_stream: ClockStream) -> (LatestClock, AnyhowJoinHandle) {
+    let (latest_sender, latest_clock) = watch::channel(None);
+    let jh = tokio::spawn(async move {
+        loop {
+            match clock_stream.recv().await {
+                Ok(clock) => {
+                    latest_sender.send_replace(Some(clock));
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => anyhow::bail!("clock stream closed"),
+            }
+        }
+    });
+    (latest_clock, jh)
+}
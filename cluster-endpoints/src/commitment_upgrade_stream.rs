@@ -0,0 +1,33 @@
+use log::warn;
+use solana_lite_rpc_core::{
+    structures::commitment_upgrade::CommitmentUpgradeTracker,
+    types::{BlockInfoStream, CommitmentUpgradeStream},
+    AnyhowJoinHandle,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+/// the confirmations and finalizations of the slots of the block info stream, for consumers keeping
+/// views which have to be rolled back when a processed block is not the one confirmed
+pub fn create_commitment_upgrade_stream(
+    mut block_info_stream: BlockInfoStream,
+) -> (CommitmentUpgradeStream, AnyhowJoinHandle) {
+    let (upgrade_sender, upgrade_stream) = tokio::sync::broadcast::channel(64);
+    let jh = tokio::spawn(async move {
+        let mut tracker = CommitmentUpgradeTracker::new();
+        loop {
+            match block_info_stream.recv().await {
+                Ok(block_info) => {
+                    if let Some(upgrade) = tracker.update(&block_info) {
+                        // nobody listens yet
+                        let _ = upgrade_sender.send(upgrade);
+                    }
+                }
+                Err(RecvError::Lagged(lagged)) => {
+                    warn!("commitment upgrade stream lagged by {lagged} blocks - continue");
+                }
+                Err(RecvError::Closed) => anyhow::bail!("block info stream closed"),
+            }
+        }
+    });
+    (upgrade_stream, jh)
+}
@@ -1,13 +1,22 @@
 use solana_lite_rpc_core::{
     structures::account_data::AccountStream,
-    types::{BlockStream, ClusterInfoStream, SlotStream, VoteAccountStream},
+    types::{
+        BlockInfoStream, BlockOrderingEventStream, BlockStream, ClockStream, ClusterInfoStream,
+        SlotStream, VoteAccountStream,
+    },
 };
 
 /// subscribers to broadcast channels should assume that channels are not getting closed unless the system is shutting down
 pub struct EndpointStreaming {
     pub blocks_notifier: BlockStream,
+    /// metadata of the blocks of `blocks_notifier`
+    pub block_info_notifier: BlockInfoStream,
     pub slot_notifier: SlotStream,
     pub vote_account_notifier: VoteAccountStream,
     pub cluster_info_notifier: ClusterInfoStream,
     pub processed_account_stream: Option<AccountStream>,
+    /// blocks dropped by the gRPC multiplexer for being out of order or conflicting, none for other sources
+    pub block_ordering_notifier: Option<BlockOrderingEventStream>,
+    /// the Clock sysvar of every slot, none if the source cannot provide it
+    pub clock_notifier: Option<ClockStream>,
 }
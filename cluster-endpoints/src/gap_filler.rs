@@ -0,0 +1,293 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use log::warn;
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
+use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash, slot_history::Slot};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+use crate::{rpc_polling::poll_blocks::process_block, rpc_pool::RpcPool};
+
+lazy_static::lazy_static! {
+    static ref GAP_FILLED_BLOCKS: IntCounter =
+        register_int_counter!(opts!("literpc_gap_filled_blocks", "Finalized blocks missing in the geyser streams fetched from the rpc node")).unwrap();
+    static ref GAP_FILL_FAILURES: IntCounter =
+        register_int_counter!(opts!("literpc_gap_fill_failures", "Missing finalized blocks the rpc node did not serve")).unwrap();
+}
+
+// at most this many missing blocks are fetched for one gap, older ones stay missing
+const MAX_GAP_FILL_BLOCKS: usize = 32;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// fetches the finalized blocks missing in the block stream, e.g. after a source hiccup, from the rpc node
+#[derive(Clone)]
+pub struct GapFiller {
+    rpc_pool: RpcPool,
+}
+
+impl GapFiller {
+    pub fn new(rpc_pool: RpcPool) -> Self {
+        Self { rpc_pool }
+    }
+
+    /// the finalized block of the slot
+    pub async fn fetch_block(&self, slot: Slot) -> Option<ProducedBlock> {
+        let rpc_client = self.rpc_pool.primary();
+        let fetched = timeout(
+            FETCH_TIMEOUT,
+            process_block(&rpc_client, slot, CommitmentConfig::finalized()),
+        )
+        .await;
+        match fetched {
+            Ok(Some(block)) => {
+                GAP_FILLED_BLOCKS.inc();
+                Some(block)
+            }
+            _ => {
+                GAP_FILL_FAILURES.inc();
+                warn!("cannot fetch the missing finalized block {slot} from the rpc node");
+                None
+            }
+        }
+    }
+
+    /// the finalized ancestors of the block after `last_finalized_slot`, oldest first; a finalized block
+    /// is the child of the previous one, so any block in between was missed
+    pub async fn fetch_missing_ancestors(
+        &self,
+        block: &ProducedBlock,
+        last_finalized_slot: Slot,
+    ) -> Vec<ProducedBlock> {
+        let mut missing = vec![];
+        let mut parent_slot = block.parent_slot;
+        while parent_slot > last_finalized_slot {
+            if missing.len() == MAX_GAP_FILL_BLOCKS {
+                warn!("gap of the block stream before slot {parent_slot} is too large, not filled");
+                break;
+            }
+            let Some(parent) = self.fetch_block(parent_slot).await else {
+                break;
+            };
+            parent_slot = parent.parent_slot;
+            missing.push(parent);
+        }
+        missing.reverse();
+        missing
+    }
+}
+
+/// a finalized block meta of the sources, with its block if the processed block was seen
+pub(crate) struct PendingFinalized {
+    pub slot: Slot,
+    pub blockhash: Hash,
+    pub block: Option<ProducedBlock>,
+    /// blocks missed before `block`, oldest first
+    pub missing_ancestors: Vec<ProducedBlock>,
+    gap_filled: bool,
+}
+
+impl PendingFinalized {
+    pub fn new(slot: Slot, blockhash: Hash, block: Option<ProducedBlock>) -> Self {
+        Self {
+            slot,
+            blockhash,
+            block,
+            missing_ancestors: vec![],
+            gap_filled: false,
+        }
+    }
+}
+
+pub(crate) struct GapFill {
+    block: Option<ProducedBlock>,
+    missing_ancestors: Vec<ProducedBlock>,
+}
+
+/// Finalized blocks in arrival order. The blocks missed before the oldest one are fetched by a
+/// spawned task so the multiplexer keeps serving the other commitments meanwhile; the finalized
+/// blocks arriving in the meantime wait here to stay in slot order.
+pub(crate) struct FinalizedBacklog {
+    gap_filler: Option<GapFiller>,
+    pending: VecDeque<PendingFinalized>,
+    fill_in_flight: bool,
+    fill_sender: mpsc::Sender<GapFill>,
+}
+
+impl FinalizedBacklog {
+    /// the results of the gap fills arrive on the receiver and go to [`FinalizedBacklog::complete`]
+    pub fn new(gap_filler: Option<GapFiller>) -> (Self, mpsc::Receiver<GapFill>) {
+        let (fill_sender, fill_receiver) = mpsc::channel(1);
+        let backlog = Self {
+            gap_filler,
+            pending: VecDeque::new(),
+            fill_in_flight: false,
+            fill_sender,
+        };
+        (backlog, fill_receiver)
+    }
+
+    pub fn push(&mut self, finalized: PendingFinalized) {
+        self.pending.push_back(finalized);
+    }
+
+    pub fn complete(&mut self, fill: GapFill) {
+        self.fill_in_flight = false;
+        if let Some(finalized) = self.pending.front_mut() {
+            finalized.block = fill.block;
+            finalized.missing_ancestors = fill.missing_ancestors;
+            finalized.gap_filled = true;
+        }
+    }
+
+    /// the finalized blocks which can be emitted after `last_emitted_finalized_slot`, in order; stops
+    /// at the first one with a gap before it and fetches the missing blocks
+    pub fn take_ready(&mut self, mut last_emitted_finalized_slot: Slot) -> Vec<PendingFinalized> {
+        let mut ready = vec![];
+        while !self.fill_in_flight {
+            let Some(finalized) = self.pending.front_mut() else {
+                break;
+            };
+            let has_gap = !finalized.gap_filled
+                && last_emitted_finalized_slot > 0
+                && finalized.block.as_ref().map_or(true, |block| {
+                    block.parent_slot > last_emitted_finalized_slot
+                });
+            if has_gap {
+                if let Some(gap_filler) = &self.gap_filler {
+                    self.fill_in_flight = true;
+                    tokio::spawn(fill_gap(
+                        gap_filler.clone(),
+                        finalized.slot,
+                        finalized.blockhash,
+                        finalized.block.take(),
+                        last_emitted_finalized_slot,
+                        self.fill_sender.clone(),
+                    ));
+                    break;
+                }
+            }
+            let finalized = self.pending.pop_front().expect("front exists");
+            if let Some(block) = &finalized.block {
+                last_emitted_finalized_slot = block.slot;
+            }
+            ready.push(finalized);
+        }
+        ready
+    }
+}
+
+async fn fill_gap(
+    gap_filler: GapFiller,
+    slot: Slot,
+    blockhash: Hash,
+    block: Option<ProducedBlock>,
+    last_finalized_slot: Slot,
+    fill_sender: mpsc::Sender<GapFill>,
+) {
+    let block = match block {
+        Some(block) => Some(block),
+        // the processed block was missed by the sources
+        None => gap_filler
+            .fetch_block(slot)
+            .await
+            .filter(|block| block.blockhash == blockhash),
+    };
+    let missing_ancestors = match &block {
+        Some(block) if block.parent_slot > last_finalized_slot => {
+            gap_filler
+                .fetch_missing_ancestors(block, last_finalized_slot)
+                .await
+        }
+        _ => vec![],
+    };
+    // the multiplexer is gone
+    let _ = fill_sender
+        .send(GapFill {
+            block,
+            missing_ancestors,
+        })
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(slot: Slot, parent_slot: Slot) -> ProducedBlock {
+        ProducedBlock {
+            parent_slot,
+            ..ProducedBlock::new_for_tests(slot, CommitmentConfig::finalized())
+        }
+    }
+
+    fn finalized(block: ProducedBlock) -> PendingFinalized {
+        PendingFinalized::new(block.slot, block.blockhash, Some(block))
+    }
+
+    fn slots(ready: &[PendingFinalized]) -> Vec<Slot> {
+        ready.iter().map(|finalized| finalized.slot).collect()
+    }
+
+    #[tokio::test]
+    async fn test_blocks_without_gap_are_ready_in_order() {
+        let (mut backlog, _fills) = FinalizedBacklog::new(None);
+        backlog.push(finalized(block(11, 10)));
+        backlog.push(finalized(block(12, 11)));
+        backlog.push(finalized(block(14, 12)));
+
+        let ready = backlog.take_ready(10);
+        assert_eq!(slots(&ready), vec![11, 12, 14]);
+        assert!(backlog.take_ready(14).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_blocks_after_a_gap_wait_for_the_fill() {
+        // nothing listens on the port, the fill fails fast and keeps the block
+        let gap_filler =
+            GapFiller::new(RpcPool::from_urls("http://127.0.0.1:1".to_string(), vec![]));
+        let (mut backlog, mut fills) = FinalizedBacklog::new(Some(gap_filler));
+        backlog.push(finalized(block(11, 10)));
+        // slots 12 and 13 were missed
+        backlog.push(finalized(block(14, 13)));
+        backlog.push(finalized(block(15, 14)));
+
+        assert_eq!(slots(&backlog.take_ready(10)), vec![11]);
+        // the blocks after the gap are held back while the fill is in flight
+        assert!(backlog.take_ready(11).is_empty());
+        assert!(backlog.take_ready(11).is_empty());
+
+        backlog.complete(fills.recv().await.unwrap());
+        let ready = backlog.take_ready(11);
+        assert_eq!(slots(&ready), vec![14, 15]);
+        assert_eq!(ready[0].block.as_ref().unwrap().slot, 14);
+        assert!(ready[0].missing_ancestors.is_empty());
+        // only one fill was started
+        assert!(fills.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fill_completes_the_oldest_pending_block() {
+        let (mut backlog, _fills) = FinalizedBacklog::new(None);
+        let missed = block(14, 13);
+        backlog.push(PendingFinalized::new(missed.slot, missed.blockhash, None));
+        backlog.push(finalized(block(15, 14)));
+
+        backlog.complete(GapFill {
+            block: Some(missed.clone()),
+            missing_ancestors: vec![block(12, 11), block(13, 12)],
+        });
+        let ready = backlog.take_ready(11);
+        assert_eq!(slots(&ready), vec![14, 15]);
+        assert_eq!(ready[0].block.as_ref().unwrap().blockhash, missed.blockhash);
+        assert_eq!(
+            ready[0]
+                .missing_ancestors
+                .iter()
+                .map(|block| block.slot)
+                .collect::<Vec<_>>(),
+            vec![12, 13]
+        );
+    }
+}
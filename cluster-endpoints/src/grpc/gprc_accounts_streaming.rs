@@ -1,19 +1,20 @@
 use geyser_grpc_connector::grpc_subscription_autoreconnect_tasks::create_geyser_autoconnection_task;
 use std::{collections::HashMap, time::Duration};
 
+use crate::grpc_multiplex::wait_for_reload;
 use geyser_grpc_connector::GrpcSourceConfig;
 use geyser_grpc_connector::Message::GeyserSubscribeUpdate;
 use itertools::Itertools;
 use solana_lite_rpc_core::{
     commitment_utils::Commitment,
     structures::{
-        account_data::{AccountData, AccountNotificationMessage, AccountStream},
+        account_data::{Account, AccountData, AccountNotificationMessage, AccountStream},
         account_filter::{AccountFilterType, AccountFilters, MemcmpFilterData},
     },
     AnyhowJoinHandle,
 };
-use solana_sdk::{account::Account, pubkey::Pubkey};
-use tokio::sync::broadcast;
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::{broadcast, watch};
 use yellowstone_grpc_proto::geyser::{
     subscribe_request_filter_accounts_filter::Filter,
     subscribe_request_filter_accounts_filter_memcmp::Data, subscribe_update::UpdateOneof,
@@ -87,7 +88,7 @@ pub fn start_account_streaming_tasks(
                         vec![]
                     };
                     subscribe_accounts.insert(
-                        format!("accounts_{}", program_id),
+                        format!("accounts_{index}_{program_id}"),
                         SubscribeRequestFilterAccounts {
                             account: vec![],
                             owner: vec![program_id.clone()],
@@ -132,7 +133,8 @@ pub fn start_account_streaming_tasks(
                                     pubkey: Pubkey::new_from_array(account_pk_bytes),
                                     account: Account {
                                         lamports: account_data.lamports,
-                                        data: account_data.data,
+                                        // takes over the decoded buffer without a copy
+                                        data: account_data.data.into(),
                                         owner: Pubkey::new_from_array(owner),
                                         executable: account_data.executable,
                                         rent_epoch: account_data.rent_epoch,
@@ -167,13 +169,26 @@ pub fn start_account_streaming_tasks(
 pub fn create_grpc_account_streaming(
     grpc_sources: Vec<GrpcSourceConfig>,
     accounts_filters: AccountFilters,
+) -> (AnyhowJoinHandle, AccountStream) {
+    create_reloadable_grpc_account_streaming(
+        watch::channel(grpc_sources).1,
+        watch::channel(accounts_filters).1,
+    )
+}
+
+/// same as `create_grpc_account_streaming` but resubscribes whenever the sources or the filters change
+pub fn create_reloadable_grpc_account_streaming(
+    mut grpc_sources_rx: watch::Receiver<Vec<GrpcSourceConfig>>,
+    mut accounts_filters_rx: watch::Receiver<AccountFilters>,
 ) -> (AnyhowJoinHandle, AccountStream) {
     let (account_sender, accounts_stream) = broadcast::channel::<AccountNotificationMessage>(128);
 
     let jh: AnyhowJoinHandle = tokio::spawn(async move {
         loop {
+            let grpc_sources = grpc_sources_rx.borrow_and_update().clone();
+            let accounts_filters = accounts_filters_rx.borrow_and_update().clone();
             let (accounts_sx, mut accounts_rx) = tokio::sync::mpsc::unbounded_channel();
-            grpc_sources
+            let streaming_tasks = grpc_sources
                 .iter()
                 .map(|grpc_config| {
                     start_account_streaming_tasks(
@@ -186,7 +201,18 @@ pub fn create_grpc_account_streaming(
             drop(accounts_sx);
 
             loop {
-                match tokio::time::timeout(Duration::from_secs(60), accounts_rx.recv()).await {
+                let next = tokio::select! {
+                    next = tokio::time::timeout(Duration::from_secs(60), accounts_rx.recv()) => next,
+                    _ = wait_for_reload(&mut grpc_sources_rx) => {
+                        log::info!("grpc sources changed; restarting account subscription");
+                        break;
+                    }
+                    _ = wait_for_reload(&mut accounts_filters_rx) => {
+                        log::info!("account filters changed; restarting account subscription");
+                        break;
+                    }
+                };
+                match next {
                     Ok(Some(data)) => {
                         let _ = account_sender.send(data);
                     }
@@ -200,6 +226,7 @@ pub fn create_grpc_account_streaming(
                     }
                 }
             }
+            streaming_tasks.iter().for_each(|task| task.abort());
         }
     });
 
@@ -3,6 +3,7 @@ use log::{debug, info, warn};
 use solana_lite_rpc_core::types::BlockStream;
 use solana_sdk::clock::Slot;
 use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
 use std::collections::HashMap;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
@@ -20,14 +21,14 @@ pub fn debugtask_blockstream_confirmation_sequence(
         // throttle cleanup
         let mut slots_since_last_cleanup = 0;
         // use blockhash as key instead of slot as for processed the slot is ambiguous
-        let mut saw_processed_at: HashMap<String, (Slot, SystemTime)> = HashMap::new();
-        let mut saw_confirmed_at: HashMap<String, (Slot, SystemTime)> = HashMap::new();
-        let mut saw_finalized_at: HashMap<String, (Slot, SystemTime)> = HashMap::new();
+        let mut saw_processed_at: HashMap<Hash, (Slot, SystemTime)> = HashMap::new();
+        let mut saw_confirmed_at: HashMap<Hash, (Slot, SystemTime)> = HashMap::new();
+        let mut saw_finalized_at: HashMap<Hash, (Slot, SystemTime)> = HashMap::new();
         'recv_loop: loop {
             match block_notifier.recv().await {
                 Ok(block) => {
                     let slot = block.slot;
-                    let blockhash = block.blockhash.to_string();
+                    let blockhash = block.blockhash;
                     if slot < cleanup_before_slot {
                         continue 'recv_loop;
                     }
@@ -41,7 +42,7 @@ pub fn debugtask_blockstream_confirmation_sequence(
 
                     if block.commitment_config.is_processed() {
                         let prev_value =
-                            saw_processed_at.insert(blockhash.clone(), (slot, SystemTime::now()));
+                            saw_processed_at.insert(blockhash, (slot, SystemTime::now()));
                         match prev_value {
                             None => {
                                 // okey
@@ -59,7 +60,7 @@ pub fn debugtask_blockstream_confirmation_sequence(
                     }
                     if block.commitment_config.is_confirmed() {
                         let prev_value =
-                            saw_confirmed_at.insert(blockhash.clone(), (slot, SystemTime::now()));
+                            saw_confirmed_at.insert(blockhash, (slot, SystemTime::now()));
                         match prev_value {
                             None => {
                                 // okey
@@ -76,7 +77,7 @@ pub fn debugtask_blockstream_confirmation_sequence(
                     }
                     if block.commitment_config.is_finalized() {
                         let prev_value =
-                            saw_finalized_at.insert(blockhash.clone(), (slot, SystemTime::now()));
+                            saw_finalized_at.insert(blockhash, (slot, SystemTime::now()));
                         match prev_value {
                             None => {
                                 // okey
@@ -236,7 +237,7 @@ pub fn debugtask_blockstream_slot_progression(
     let last_slot_seen = latest_slot_seen_shared.clone();
     tokio::spawn(async move {
         let mut last_highest_slot_number = 0;
-        let mut last_blockhash: Option<String> = None;
+        let mut last_blockhash: Option<Hash> = None;
 
         'recv_loop: loop {
             match block_notifier.recv().await {
@@ -290,7 +291,7 @@ pub fn debugtask_blockstream_slot_progression(
                                 block.slot, block.blockhash, block.previous_blockhash, last_blockhash);
                         }
                     }
-                    last_blockhash = Some(block.blockhash.to_string());
+                    last_blockhash = Some(block.blockhash);
                 } // -- Ok
                 Err(RecvError::Lagged(missed_blocks)) => {
                     // very unlikely to happen
@@ -1,24 +1,43 @@
+use crate::gap_filler::{FinalizedBacklog, GapFiller, PendingFinalized};
+use crate::grpc_source_metrics::create_instrumented_autoconnection_task;
 use crate::grpc_subscription::from_grpc_block_update;
 use anyhow::{bail, Context};
-use geyser_grpc_connector::grpc_subscription_autoreconnect_tasks::create_geyser_autoconnection_task_with_mpsc;
 use geyser_grpc_connector::grpcmultiplex_fastestwins::FromYellowstoneExtractor;
 use geyser_grpc_connector::{GeyserFilter, GrpcSourceConfig, Message};
 use log::{debug, info, trace, warn};
+use prometheus::{opts, register_int_counter_vec, register_int_gauge, IntCounterVec, IntGauge};
+use solana_lite_rpc_core::structures::block_ordering::{BlockOrderingBuffer, BlockOrderingEvent};
 use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
 use solana_lite_rpc_core::structures::slot_notification::SlotNotification;
 use solana_lite_rpc_core::AnyhowJoinHandle;
 use solana_sdk::clock::Slot;
 use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
 
 use std::collections::{BTreeSet, HashMap, HashSet};
+use std::str::FromStr;
 use std::time::Duration;
-use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::{Receiver, Sender};
+use tokio::sync::watch;
 use tokio::task::AbortHandle;
 use tokio::time::{sleep, Instant};
 use tracing::debug_span;
 use yellowstone_grpc_proto::geyser::subscribe_update::UpdateOneof;
 use yellowstone_grpc_proto::geyser::SubscribeUpdate;
 
+lazy_static::lazy_static! {
+    static ref BLOCK_ORDERING_EVENTS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_block_ordering_events", "Blocks of the multiplexed sources which were out of order or conflicted with an emitted block"), &["kind", "commitment"]).unwrap();
+    static ref BLOCK_ORDERING_PENDING: IntGauge =
+        register_int_gauge!(opts!("literpc_block_ordering_pending", "Blocks held back by the multiplexer until the blocks of their parent slots were emitted")).unwrap();
+}
+
+// blocks wait at most this long for the block of their parent slot from another source
+const MAX_REORDER_DELAY: Duration = Duration::from_millis(200);
+// blocks per commitment held back at most
+const MAX_REORDER_PENDING: usize = 32;
+const REORDER_TICK: Duration = Duration::from_millis(20);
+
 /// connect to all sources provided using transparent autoconnection task
 /// shutdown handling:
 /// - task will shutdown of the receiver side of block_sender gets closed
@@ -31,10 +50,11 @@ fn create_grpc_multiplex_processed_block_task(
 
     let (autoconnect_tx, mut blocks_rx) = tokio::sync::mpsc::channel(10);
     for grpc_source in grpc_sources {
-        create_geyser_autoconnection_task_with_mpsc(
+        create_instrumented_autoconnection_task(
             grpc_source.clone(),
             GeyserFilter(COMMITMENT_CONFIG).blocks_and_txs(),
             autoconnect_tx.clone(),
+            "blocks",
         );
     }
 
@@ -114,10 +134,11 @@ fn create_grpc_multiplex_block_meta_task(
 ) -> Vec<AbortHandle> {
     let (autoconnect_tx, mut blocks_rx) = tokio::sync::mpsc::channel(10);
     for grpc_source in grpc_sources {
-        create_geyser_autoconnection_task_with_mpsc(
+        create_instrumented_autoconnection_task(
             grpc_source.clone(),
             GeyserFilter(commitment_config).blocks_meta(),
             autoconnect_tx.clone(),
+            block_meta_stream_label(commitment_config),
         );
     }
 
@@ -131,10 +152,18 @@ fn create_grpc_multiplex_block_meta_task(
                             UpdateOneof::BlockMeta(block_meta) => {
                                 let proposed_slot = block_meta.slot;
                                 if proposed_slot > tip {
+                                    let Ok(blockhash) = Hash::from_str(&block_meta.blockhash)
+                                    else {
+                                        warn!(
+                                            "skipping block meta {} with invalid blockhash {}",
+                                            proposed_slot, block_meta.blockhash
+                                        );
+                                        continue;
+                                    };
                                     tip = proposed_slot;
                                     let block_meta = BlockMeta {
                                         slot: proposed_slot,
-                                        blockhash: block_meta.blockhash,
+                                        blockhash,
                                     };
 
                                     let send_started_at = Instant::now();
@@ -180,12 +209,33 @@ fn create_grpc_multiplex_block_meta_task(
     vec![jh_merging_streams.abort_handle()]
 }
 
+/// resolves when a new value was sent, never if the sender is gone (the value is fixed then)
+pub(crate) async fn wait_for_reload<T>(receiver: &mut watch::Receiver<T>) {
+    if receiver.changed().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
 /// connect to multiple grpc sources to consume processed blocks and block status update
 /// emits full blocks for commitment levels processed, confirmed, finalized in that order
+/// and in nondecreasing slot order per commitment
 /// the channel must never be closed
 pub fn create_grpc_multiplex_blocks_subscription(
     grpc_sources: Vec<GrpcSourceConfig>,
 ) -> (Receiver<ProducedBlock>, AnyhowJoinHandle) {
+    create_reloadable_grpc_multiplex_blocks_subscription(watch::channel(grpc_sources).1, None, None)
+}
+
+/// same as `create_grpc_multiplex_blocks_subscription` but the multiplexer reconnects to the new
+/// source list whenever it changes, the output channel stays open; with a gap filler the finalized
+/// blocks missed by the sources are fetched from the rpc node and emitted in order; the blocks
+/// dropped for being out of order or conflicting are reported to the ordering event sender
+pub fn create_reloadable_grpc_multiplex_blocks_subscription(
+    mut grpc_sources_rx: watch::Receiver<Vec<GrpcSourceConfig>>,
+    gap_filler: Option<GapFiller>,
+    block_ordering_sender: Option<Sender<BlockOrderingEvent>>,
+) -> (Receiver<ProducedBlock>, AnyhowJoinHandle) {
+    let grpc_sources = grpc_sources_rx.borrow_and_update().clone();
     info!("Setup grpc multiplexed blocks connection...");
     if grpc_sources.is_empty() {
         info!("- no grpc connection configured");
@@ -203,6 +253,14 @@ pub fn create_grpc_multiplex_blocks_subscription(
 
     // task MUST not terminate but might be aborted from outside
     let jh_block_emitter_task = tokio::task::spawn(async move {
+        let mut grpc_sources = grpc_sources;
+        // survives the reconnects, blocks finalized while reconnecting are a gap
+        let mut last_emitted_finalized_slot: Slot = 0;
+        // the sources are raced, the blocks of a slower source might arrive after the ones of its successors
+        let mut block_ordering = BlockOrderingBuffer::new(MAX_REORDER_DELAY, MAX_REORDER_PENDING);
+        let mut reorder_tick = tokio::time::interval(REORDER_TICK);
+        // survives the reconnects like the last emitted slot, a running gap fill stays valid
+        let (mut finalized_backlog, mut gap_fill_receiver) = FinalizedBacklog::new(gap_filler);
         loop {
             // channels must NEVER GET CLOSED (unless full restart of multiplexer)
             let (processed_block_sender, mut processed_block_reciever) =
@@ -246,7 +304,7 @@ pub fn create_grpc_multiplex_blocks_subscription(
 
             // by blockhash
             // this map consumes sigificant amount of memory constrainted by CLEANUP_SLOTS_BEHIND_FINALIZED
-            let mut recent_processed_blocks = HashMap::<String, ProducedBlock>::new();
+            let mut recent_processed_blocks = HashMap::<Hash, ProducedBlock>::new();
 
             let mut cleanup_tick = tokio::time::interval(Duration::from_secs(5));
             let mut last_finalized_slot: Slot = 0;
@@ -254,7 +312,7 @@ pub fn create_grpc_multiplex_blocks_subscription(
             let mut cleanup_without_recv_full_blocks: u8 = 0;
             let mut cleanup_without_confirmed_recv_blocks_meta: u8 = 0;
             let mut cleanup_without_finalized_recv_blocks_meta: u8 = 0;
-            let mut confirmed_block_not_yet_processed = HashSet::<String>::new();
+            let mut confirmed_block_not_yet_processed = HashSet::<Hash>::new();
 
             //  start logging errors when we recieve first finalized block
             let mut startup_completed = false;
@@ -270,17 +328,14 @@ pub fn create_grpc_multiplex_blocks_subscription(
                             cleanup_without_recv_full_blocks = 0;
 
                             let processed_block = processed_block.expect("processed block from stream");
+                            let _span = debug_span!("broadcast_processed_block", slot = processed_block.slot).entered();
                             trace!("got processed block {} with blockhash {}",
-                                processed_block.slot, processed_block.blockhash.clone());
-                            if let Err(e) = producedblock_sender.send(processed_block.clone()) {
-                                warn!("produced block channel has no receivers {e:?}");
-                            }
+                                processed_block.slot, processed_block.blockhash);
+                            emit_ordered(&mut block_ordering, processed_block.clone(), &producedblock_sender, block_ordering_sender.as_ref());
                             if confirmed_block_not_yet_processed.remove(&processed_block.blockhash) {
-                                if let Err(e) = producedblock_sender.send(processed_block.to_confirmed_block()) {
-                                    warn!("produced block channel has no receivers while trying to send confirmed block {e:?}");
-                                }
+                                emit_ordered(&mut block_ordering, processed_block.to_confirmed_block(), &producedblock_sender, block_ordering_sender.as_ref());
                             }
-                            recent_processed_blocks.insert(processed_block.blockhash.clone(), processed_block);
+                            recent_processed_blocks.insert(processed_block.blockhash, processed_block);
                         },
                         meta_confirmed = block_meta_reciever_confirmed.recv() => {
                             cleanup_without_confirmed_recv_blocks_meta = 0;
@@ -289,12 +344,10 @@ pub fn create_grpc_multiplex_blocks_subscription(
                             if let Some(cached_processed_block) = recent_processed_blocks.get(&blockhash) {
                                 let confirmed_block = cached_processed_block.to_confirmed_block();
                                 debug!("got confirmed blockmeta {} with blockhash {}",
-                                    confirmed_block.slot, confirmed_block.blockhash.clone());
-                                if let Err(e) = producedblock_sender.send(confirmed_block) {
-                                    warn!("confirmed block channel has no receivers {e:?}");
-                                }
+                                    confirmed_block.slot, confirmed_block.blockhash);
+                                emit_ordered(&mut block_ordering, confirmed_block, &producedblock_sender, block_ordering_sender.as_ref());
                             } else {
-                                confirmed_block_not_yet_processed.insert(blockhash.clone());
+                                confirmed_block_not_yet_processed.insert(blockhash);
                                 log::debug!("backlog of not yet confirmed blocks: {}; recent blocks map size: {}",
                                 confirmed_block_not_yet_processed.len(), recent_processed_blocks.len());
                             }
@@ -303,21 +356,22 @@ pub fn create_grpc_multiplex_blocks_subscription(
                             cleanup_without_finalized_recv_blocks_meta = 0;
                             let meta_finalized = meta_finalized.expect("finalized block meta from stream");
                             // let _span = debug_span!("sequence_block_meta_finalized", ?meta_finalized.slot).entered();
-                            let blockhash = meta_finalized.blockhash;
-                            if let Some(cached_processed_block) = recent_processed_blocks.remove(&blockhash) {
-                                let finalized_block = cached_processed_block.to_finalized_block();
-                                last_finalized_slot = finalized_block.slot;
-                                startup_completed = true;
-                                debug!("got finalized blockmeta {} with blockhash {}",
-                                    finalized_block.slot, finalized_block.blockhash.clone());
-                                if let Err(e) = producedblock_sender.send(finalized_block) {
-                                    warn!("Finalized block channel has no receivers {e:?}");
-                                }
-                            } else if startup_completed {
-                                // this warning is ok for first few blocks when we start lrpc
-                                log::warn!("finalized block meta received for blockhash {} which was never seen or already emitted", blockhash);
-                            }
+                            let finalized_block = recent_processed_blocks
+                                .remove(&meta_finalized.blockhash)
+                                .map(|cached_processed_block| cached_processed_block.to_finalized_block());
+                            finalized_backlog.push(PendingFinalized::new(meta_finalized.slot, meta_finalized.blockhash, finalized_block));
                         },
+                    Some(gap_fill) = gap_fill_receiver.recv() => {
+                        finalized_backlog.complete(gap_fill);
+                    }
+                    _ = reorder_tick.tick(), if block_ordering.pending() > 0 => {
+                        send_ready_blocks(&mut block_ordering, &producedblock_sender);
+                    }
+                    _ = wait_for_reload(&mut grpc_sources_rx) => {
+                        grpc_sources = grpc_sources_rx.borrow_and_update().clone();
+                        info!("grpc sources changed - restarting blocks multiplexer with {} sources", grpc_sources.len());
+                        break 'recv_loop;
+                    }
                     _ = cleanup_tick.tick() => {
                          // timebased restart
                         if cleanup_without_recv_full_blocks > MAX_ALLOWED_CLEANUP_WITHOUT_RECV ||
@@ -342,6 +396,36 @@ pub fn create_grpc_multiplex_blocks_subscription(
                         }
                     }
                 }
+
+                for finalized in finalized_backlog.take_ready(last_emitted_finalized_slot) {
+                    for missing_block in finalized.missing_ancestors {
+                        debug!("filled gap with finalized block {}", missing_block.slot);
+                        emit_ordered(
+                            &mut block_ordering,
+                            missing_block,
+                            &producedblock_sender,
+                            block_ordering_sender.as_ref(),
+                        );
+                    }
+                    if let Some(finalized_block) = finalized.block {
+                        last_finalized_slot = finalized_block.slot;
+                        last_emitted_finalized_slot = finalized_block.slot;
+                        startup_completed = true;
+                        debug!(
+                            "got finalized blockmeta {} with blockhash {}",
+                            finalized_block.slot, finalized_block.blockhash
+                        );
+                        emit_ordered(
+                            &mut block_ordering,
+                            finalized_block,
+                            &producedblock_sender,
+                            block_ordering_sender.as_ref(),
+                        );
+                    } else if startup_completed {
+                        // this warning is ok for first few blocks when we start lrpc
+                        log::warn!("finalized block meta received for blockhash {} which was never seen or already emitted", finalized.blockhash);
+                    }
+                }
             } // -- END receiver loop
             task_list.iter().for_each(|task| task.abort());
         } // -- END reconnect loop
@@ -350,9 +434,72 @@ pub fn create_grpc_multiplex_blocks_subscription(
     (blocks_output_stream, jh_block_emitter_task)
 }
 
+/// passes the block through the ordering buffer and sends the blocks which are due
+fn emit_ordered(
+    block_ordering: &mut BlockOrderingBuffer,
+    block: ProducedBlock,
+    producedblock_sender: &Sender<ProducedBlock>,
+    block_ordering_sender: Option<&Sender<BlockOrderingEvent>>,
+) {
+    if let Some(event) = block_ordering.insert(block, std::time::Instant::now()) {
+        let commitment = event.commitment_config().commitment;
+        BLOCK_ORDERING_EVENTS
+            .with_label_values(&[event.kind(), &commitment.to_string()])
+            .inc();
+        match &event {
+            BlockOrderingEvent::OutOfOrder {
+                slot,
+                last_emitted_slot,
+                ..
+            } => debug!(
+                "dropped {commitment} block {slot} arriving after block {last_emitted_slot}"
+            ),
+            // processed blocks of competing forks are expected
+            BlockOrderingEvent::Conflict {
+                slot,
+                blockhash,
+                previous_blockhash,
+                commitment_config,
+            } => log::log!(
+                if commitment_config.is_processed() {
+                    log::Level::Debug
+                } else {
+                    log::Level::Warn
+                },
+                "{commitment} block {slot} with blockhash {blockhash} conflicts with the emitted block {previous_blockhash}"
+            ),
+        }
+        if let Some(block_ordering_sender) = block_ordering_sender {
+            // nobody listens yet
+            let _ = block_ordering_sender.send(event);
+        }
+    }
+    send_ready_blocks(block_ordering, producedblock_sender);
+}
+
+fn send_ready_blocks(
+    block_ordering: &mut BlockOrderingBuffer,
+    producedblock_sender: &Sender<ProducedBlock>,
+) {
+    for block in block_ordering.pop_ready(std::time::Instant::now()) {
+        if let Err(e) = producedblock_sender.send(block) {
+            warn!("produced block channel has no receivers {e:?}");
+        }
+    }
+    BLOCK_ORDERING_PENDING.set(block_ordering.pending() as i64);
+}
+
 pub fn create_grpc_multiplex_processed_slots_subscription(
     grpc_sources: Vec<GrpcSourceConfig>,
 ) -> (Receiver<SlotNotification>, AnyhowJoinHandle) {
+    create_reloadable_grpc_multiplex_processed_slots_subscription(watch::channel(grpc_sources).1)
+}
+
+/// same as `create_grpc_multiplex_processed_slots_subscription` but reconnects to the new source list whenever it changes
+pub fn create_reloadable_grpc_multiplex_processed_slots_subscription(
+    mut grpc_sources_rx: watch::Receiver<Vec<GrpcSourceConfig>>,
+) -> (Receiver<SlotNotification>, AnyhowJoinHandle) {
+    let grpc_sources = grpc_sources_rx.borrow_and_update().clone();
     const COMMITMENT_CONFIG: CommitmentConfig = CommitmentConfig::processed();
     info!("Setup grpc multiplexed slots connection...");
     if grpc_sources.is_empty() {
@@ -368,18 +515,27 @@ pub fn create_grpc_multiplex_processed_slots_subscription(
 
     // task MUST not terminate but might be aborted from outside
     let jh_multiplex_task = tokio::spawn(async move {
+        let mut grpc_sources = grpc_sources;
         loop {
             let (autoconnect_tx, mut slots_rx) = tokio::sync::mpsc::channel(10);
             for grpc_source in &grpc_sources {
-                create_geyser_autoconnection_task_with_mpsc(
+                create_instrumented_autoconnection_task(
                     grpc_source.clone(),
                     GeyserFilter(COMMITMENT_CONFIG).slots(),
                     autoconnect_tx.clone(),
+                    "slots",
                 );
             }
 
             'recv_loop: loop {
-                let next = tokio::time::timeout(Duration::from_secs(30), slots_rx.recv()).await;
+                let next = tokio::select! {
+                    next = tokio::time::timeout(Duration::from_secs(30), slots_rx.recv()) => next,
+                    _ = wait_for_reload(&mut grpc_sources_rx) => {
+                        grpc_sources = grpc_sources_rx.borrow_and_update().clone();
+                        info!("grpc sources changed - restarting slots multiplexer with {} sources", grpc_sources.len());
+                        break 'recv_loop;
+                    }
+                };
                 match next {
                     Ok(Some(Message::GeyserSubscribeUpdate(slot_update))) => {
                         let mapfilter = map_slot_from_yellowstone_update(*slot_update);
@@ -432,10 +588,18 @@ pub fn create_grpc_multiplex_processed_slots_subscription(
     (multiplexed_messages_rx, jh_multiplex_task)
 }
 
+fn block_meta_stream_label(commitment_config: CommitmentConfig) -> &'static str {
+    if commitment_config.is_finalized() {
+        "block_meta_finalized"
+    } else {
+        "block_meta_confirmed"
+    }
+}
+
 #[allow(dead_code)]
 struct BlockMeta {
     pub slot: Slot,
-    pub blockhash: String,
+    pub blockhash: Hash,
 }
 
 struct BlockMetaExtractor(CommitmentConfig);
@@ -444,13 +608,22 @@ impl FromYellowstoneExtractor for BlockMetaExtractor {
     type Target = BlockMeta;
     fn map_yellowstone_update(&self, update: SubscribeUpdate) -> Option<(u64, BlockMeta)> {
         match update.update_oneof {
-            Some(UpdateOneof::BlockMeta(block_meta)) => Some((
-                block_meta.slot,
-                BlockMeta {
-                    slot: block_meta.slot,
-                    blockhash: block_meta.blockhash,
-                },
-            )),
+            Some(UpdateOneof::BlockMeta(block_meta)) => {
+                let Ok(blockhash) = Hash::from_str(&block_meta.blockhash) else {
+                    warn!(
+                        "skipping block meta {} with invalid blockhash {}",
+                        block_meta.slot, block_meta.blockhash
+                    );
+                    return None;
+                };
+                Some((
+                    block_meta.slot,
+                    BlockMeta {
+                        slot: block_meta.slot,
+                        blockhash,
+                    },
+                ))
+            }
             _ => None,
         }
     }
@@ -470,8 +643,14 @@ fn map_block_from_yellowstone_update(
     let _span = debug_span!("map_block_from_yellowstone_update").entered();
     match update.update_oneof {
         Some(UpdateOneof::Block(update_block_message)) => {
-            let block = from_grpc_block_update(update_block_message, commitment_config);
-            Some((block.slot, block))
+            let slot = update_block_message.slot;
+            match from_grpc_block_update(update_block_message, commitment_config) {
+                Ok(block) => Some((block.slot, block)),
+                Err(err) => {
+                    warn!("skipping block {slot}: {err:?}");
+                    None
+                }
+            }
         }
         _ => None,
     }
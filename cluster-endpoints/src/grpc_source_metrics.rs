@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Context;
+use futures::{Stream, StreamExt};
+use geyser_grpc_connector::{GrpcConnectionTimeouts, GrpcSourceConfig, Message};
+use log::{debug, warn};
+use prometheus::{
+    opts, register_int_counter_vec, register_int_gauge_vec, IntCounterVec, IntGaugeVec,
+};
+use solana_lite_rpc_core::time_utils::unix_timestamp_ms;
+use solana_sdk::clock::Slot;
+use tokio::time::timeout;
+use yellowstone_grpc_client::GeyserGrpcClient;
+use yellowstone_grpc_proto::geyser::subscribe_update::UpdateOneof;
+use yellowstone_grpc_proto::geyser::{SubscribeRequest, SubscribeUpdate};
+use yellowstone_grpc_proto::prost::Message as _;
+use yellowstone_grpc_proto::tonic::transport::ClientTlsConfig;
+use yellowstone_grpc_proto::tonic::{Code, Status};
+
+lazy_static::lazy_static! {
+    static ref GRPC_SOURCE_CONNECTED: IntGaugeVec =
+        register_int_gauge_vec!(opts!("literpc_grpc_source_connected", "1 if the grpc source stream delivers data, 0 while connecting"), &["source", "stream"]).unwrap();
+    static ref GRPC_SOURCE_RECONNECTS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_grpc_source_reconnects", "Number of reconnect attempts to the grpc source"), &["source", "stream"]).unwrap();
+    static ref GRPC_SOURCE_MESSAGES: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_grpc_source_messages", "Number of messages received from the grpc source"), &["source", "stream"]).unwrap();
+    static ref GRPC_SOURCE_BYTES: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_grpc_source_bytes", "Encoded size of the messages received from the grpc source"), &["source", "stream"]).unwrap();
+    static ref GRPC_SOURCE_LAST_RECEIVED: IntGaugeVec =
+        register_int_gauge_vec!(opts!("literpc_grpc_source_last_received_timestamp", "Unix timestamp (ms) of the last message received from the grpc source"), &["source", "stream"]).unwrap();
+    static ref GRPC_SOURCE_SLOT: IntGaugeVec =
+        register_int_gauge_vec!(opts!("literpc_grpc_source_slot", "Highest slot received from the grpc source"), &["source", "stream"]).unwrap();
+    static ref GRPC_SOURCE_OVERSIZED_MESSAGES: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_grpc_source_oversized_messages", "Messages from the grpc source rejected for exceeding the max message size"), &["source", "stream"]).unwrap();
+    static ref GRPC_SOURCE_SLOT_LAG: IntGaugeVec =
+        register_int_gauge_vec!(opts!("literpc_grpc_source_slot_lag", "Slots behind the best grpc source of the same stream"), &["source", "stream"]).unwrap();
+
+    // state per (stream, source), also used to calculate the lag against the best source
+    static ref SOURCE_STATES: Mutex<HashMap<(String, String), GrpcSourceState>> = Mutex::new(HashMap::new());
+}
+
+/// default limit of a single update received from a grpc source
+pub const DEFAULT_GRPC_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+// for sources configured without timeouts
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+static MAX_MESSAGE_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_GRPC_MAX_MESSAGE_SIZE);
+
+/// decoding limit of the grpc clients, applies to the sources connected afterwards
+pub fn set_max_message_size(bytes: usize) {
+    MAX_MESSAGE_SIZE.store(bytes, Ordering::Relaxed);
+}
+
+pub fn max_message_size() -> usize {
+    MAX_MESSAGE_SIZE.load(Ordering::Relaxed)
+}
+
+/// snapshot of a grpc source as seen by the instrumented autoconnection task
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct GrpcSourceState {
+    pub source: String,
+    pub stream: String,
+    pub connected: bool,
+    pub reconnects: u64,
+    /// unix timestamp in ms, 0 if nothing was received yet
+    pub last_received_ms: u64,
+    /// highest slot received
+    pub slot: Slot,
+}
+
+/// current state of all grpc sources, sorted by stream and source
+pub fn grpc_source_states() -> Vec<GrpcSourceState> {
+    let mut states: Vec<GrpcSourceState> =
+        SOURCE_STATES.lock().unwrap().values().cloned().collect();
+    states.sort_by(|a, b| (&a.stream, &a.source).cmp(&(&b.stream, &b.source)));
+    states
+}
+
+/// Same as `create_geyser_autoconnection_task_with_mpsc` but every source gets its own task so the messages
+/// can be accounted per source (labels `source` = grpc address and `stream`) before they are merged into `merged_tx`.
+/// Updates larger than [`max_message_size`] are rejected by the grpc client while decoding, the source reconnects then.
+/// The source task stops once `merged_tx` is closed.
+pub fn create_instrumented_autoconnection_task(
+    grpc_source: GrpcSourceConfig,
+    subscribe_filter: SubscribeRequest,
+    merged_tx: tokio::sync::mpsc::Sender<Message>,
+    stream: &'static str,
+) {
+    let source = grpc_source.grpc_addr.clone();
+    let receive_timeout = timeouts(&grpc_source).receive_timeout;
+
+    tokio::spawn(async move {
+        let labels = [source.as_str(), stream];
+        let mut attempt: u32 = 0;
+        let mut failed_attempts: u32 = 0;
+        loop {
+            attempt += 1;
+            update_source_state(&source, stream, |state| {
+                state.connected = false;
+                if attempt > 1 {
+                    state.reconnects += 1;
+                }
+            });
+            GRPC_SOURCE_CONNECTED.with_label_values(&labels).set(0);
+            if attempt > 1 {
+                GRPC_SOURCE_RECONNECTS.with_label_values(&labels).inc();
+                tokio::time::sleep(reconnect_backoff(failed_attempts)).await;
+            }
+            if merged_tx.send(Message::Connecting(attempt)).await.is_err() {
+                debug!(
+                    "merged channel of {} stream closed - stop source {}",
+                    stream, source
+                );
+                return;
+            }
+
+            let mut updates = match subscribe(&grpc_source, subscribe_filter.clone()).await {
+                Ok(updates) => updates,
+                Err(err) => {
+                    failed_attempts += 1;
+                    warn!("failed to subscribe to {source} ({stream}) - retrying: {err:#}");
+                    continue;
+                }
+            };
+
+            loop {
+                let update = match timeout(receive_timeout, updates.next()).await {
+                    Ok(Some(Ok(update))) => update,
+                    Ok(Some(Err(status))) if status.code() == Code::OutOfRange => {
+                        GRPC_SOURCE_OVERSIZED_MESSAGES
+                            .with_label_values(&labels)
+                            .inc();
+                        warn!(
+                            "message from {source} ({stream}) exceeds the max message size of {} bytes - reconnecting: {}",
+                            max_message_size(),
+                            status.message()
+                        );
+                        break;
+                    }
+                    Ok(Some(Err(status))) => {
+                        warn!("grpc error on {source} ({stream}) - reconnecting: {status}");
+                        break;
+                    }
+                    Ok(None) => {
+                        warn!("stream of {source} ({stream}) closed - reconnecting");
+                        break;
+                    }
+                    Err(_elapsed) => {
+                        warn!(
+                            "no message from {source} ({stream}) within {receive_timeout:?} - reconnecting"
+                        );
+                        break;
+                    }
+                };
+                failed_attempts = 0;
+
+                let now = unix_timestamp_ms();
+                update_source_state(&source, stream, |state| {
+                    state.connected = true;
+                    state.last_received_ms = now;
+                });
+                GRPC_SOURCE_CONNECTED.with_label_values(&labels).set(1);
+                GRPC_SOURCE_MESSAGES.with_label_values(&labels).inc();
+                GRPC_SOURCE_BYTES
+                    .with_label_values(&labels)
+                    .inc_by(update.encoded_len() as u64);
+                GRPC_SOURCE_LAST_RECEIVED
+                    .with_label_values(&labels)
+                    .set(now as i64);
+                if let Some(slot) = slot_of_update(update.update_oneof.as_ref()) {
+                    update_source_slot(&source, stream, slot);
+                }
+
+                if merged_tx
+                    .send(Message::GeyserSubscribeUpdate(Box::new(update)))
+                    .await
+                    .is_err()
+                {
+                    debug!(
+                        "merged channel of {} stream closed - stop source {}",
+                        stream, source
+                    );
+                    return;
+                }
+            }
+        }
+    });
+}
+
+fn timeouts(grpc_source: &GrpcSourceConfig) -> GrpcConnectionTimeouts {
+    grpc_source
+        .timeouts
+        .clone()
+        .unwrap_or(GrpcConnectionTimeouts {
+            connect_timeout: DEFAULT_TIMEOUT,
+            request_timeout: DEFAULT_TIMEOUT,
+            subscribe_timeout: DEFAULT_TIMEOUT,
+            receive_timeout: DEFAULT_TIMEOUT,
+        })
+}
+
+/// connects with the tls config and timeouts of the source and the decoding limit of the client
+/// set to [`max_message_size`]
+async fn subscribe(
+    grpc_source: &GrpcSourceConfig,
+    subscribe_filter: SubscribeRequest,
+) -> anyhow::Result<impl Stream<Item = Result<SubscribeUpdate, Status>>> {
+    let timeouts = timeouts(grpc_source);
+    let tls_config = grpc_source.tls_config.clone().or_else(|| {
+        grpc_source
+            .grpc_addr
+            .starts_with("https")
+            .then(ClientTlsConfig::new)
+    });
+    let mut client = GeyserGrpcClient::connect_with_timeout(
+        grpc_source.grpc_addr.clone(),
+        grpc_source.grpc_x_token.clone(),
+        tls_config,
+        Some(timeouts.connect_timeout),
+        Some(timeouts.request_timeout),
+        false,
+    )
+    .await?;
+    client.geyser = client.geyser.max_decoding_message_size(max_message_size());
+
+    let updates = timeout(
+        timeouts.subscribe_timeout,
+        client.subscribe_once2(subscribe_filter),
+    )
+    .await
+    .context("subscribe timed out")??;
+    Ok(updates)
+}
+
+fn reconnect_backoff(failed_attempts: u32) -> Duration {
+    Duration::from_millis(500 * u64::from(failed_attempts.min(10)))
+}
+
+fn slot_of_update(update: Option<&UpdateOneof>) -> Option<Slot> {
+    match update? {
+        UpdateOneof::Slot(slot) => Some(slot.slot),
+        UpdateOneof::Block(block) => Some(block.slot),
+        UpdateOneof::BlockMeta(block_meta) => Some(block_meta.slot),
+        UpdateOneof::Account(account) => Some(account.slot),
+        UpdateOneof::Transaction(transaction) => Some(transaction.slot),
+        _ => None,
+    }
+}
+
+fn update_source_state(source: &str, stream: &str, update: impl FnOnce(&mut GrpcSourceState)) {
+    let mut source_states = SOURCE_STATES.lock().unwrap();
+    let state = source_states
+        .entry((stream.to_string(), source.to_string()))
+        .or_insert_with(|| GrpcSourceState {
+            source: source.to_string(),
+            stream: stream.to_string(),
+            ..Default::default()
+        });
+    update(state);
+}
+
+fn update_source_slot(source: &str, stream: &str, slot: Slot) {
+    let mut source_states = SOURCE_STATES.lock().unwrap();
+    let Some(state) = source_states.get_mut(&(stream.to_string(), source.to_string())) else {
+        return;
+    };
+    state.slot = state.slot.max(slot);
+    GRPC_SOURCE_SLOT
+        .with_label_values(&[source, stream])
+        .set(state.slot as i64);
+
+    // a new best slot changes the lag of all other sources of the stream
+    for (other_source, lag) in slot_lags(source_states.values(), stream) {
+        GRPC_SOURCE_SLOT_LAG
+            .with_label_values(&[other_source.as_str(), stream])
+            .set(lag as i64);
+    }
+}
+
+/// slots behind the best source per source of the stream
+fn slot_lags<'a>(
+    states: impl Iterator<Item = &'a GrpcSourceState> + Clone,
+    stream: &str,
+) -> Vec<(&'a String, Slot)> {
+    let states = states.filter(|state| state.stream == stream);
+    let best_slot = states
+        .clone()
+        .map(|state| state.slot)
+        .max()
+        .unwrap_or_default();
+    states
+        .map(|state| (&state.source, best_slot.saturating_sub(state.slot)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(source: &str, stream: &str, slot: Slot) -> GrpcSourceState {
+        GrpcSourceState {
+            source: source.to_string(),
+            stream: stream.to_string(),
+            slot,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lag_of_every_source_follows_the_best_source() {
+        let states = [
+            state("a", "blocks", 110),
+            state("b", "blocks", 100),
+            state("c", "blocks", 108),
+            state("a", "slots", 50),
+        ];
+
+        let lags = slot_lags(states.iter(), "blocks");
+
+        let lags: Vec<(&str, Slot)> = lags
+            .into_iter()
+            .map(|(source, lag)| (source.as_str(), lag))
+            .collect();
+        assert_eq!(lags, vec![("a", 0), ("b", 10), ("c", 2)]);
+    }
+
+    #[test]
+    fn configured_timeouts_are_used() {
+        let configured = GrpcConnectionTimeouts {
+            connect_timeout: Duration::from_secs(1),
+            request_timeout: Duration::from_secs(2),
+            subscribe_timeout: Duration::from_secs(3),
+            receive_timeout: Duration::from_secs(4),
+        };
+        let source =
+            GrpcSourceConfig::new("http://localhost:10000".to_string(), None, None, configured);
+        assert_eq!(timeouts(&source).connect_timeout, Duration::from_secs(1));
+        assert_eq!(timeouts(&source).receive_timeout, Duration::from_secs(4));
+    }
+}
@@ -1,15 +1,19 @@
+use crate::block_info_stream::create_block_info_stream;
+use crate::clock_stream::{clock_account_filter, create_clock_stream};
 use crate::endpoint_stremers::EndpointStreaming;
-use crate::grpc::gprc_accounts_streaming::create_grpc_account_streaming;
+use crate::gap_filler::GapFiller;
+use crate::grpc::gprc_accounts_streaming::create_reloadable_grpc_account_streaming;
 use crate::grpc_multiplex::{
-    create_grpc_multiplex_blocks_subscription, create_grpc_multiplex_processed_slots_subscription,
+    create_reloadable_grpc_multiplex_blocks_subscription,
+    create_reloadable_grpc_multiplex_processed_slots_subscription,
 };
+use crate::rpc_pool::RpcPool;
+use anyhow::Context;
 use geyser_grpc_connector::GrpcSourceConfig;
 use itertools::Itertools;
-use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_lite_rpc_core::structures::account_filter::AccountFilters;
 use solana_lite_rpc_core::{
-    encoding::BASE64,
-    structures::produced_block::{ProducedBlock, TransactionInfo},
+    structures::produced_block::{ProducedBlock, TransactionInfo, TransactionMessage},
     AnyhowJoinHandle,
 };
 use solana_sdk::program_utils::limited_deserialize;
@@ -29,7 +33,8 @@ use solana_sdk::{
     transaction::TransactionError,
 };
 use solana_transaction_status::{Reward, RewardType};
-use std::sync::Arc;
+use std::str::FromStr;
+use tokio::sync::watch;
 use tracing::debug_span;
 
 use crate::rpc_polling::vote_accounts_and_cluster_info_polling::{
@@ -41,7 +46,7 @@ use yellowstone_grpc_proto::prelude::SubscribeUpdateBlock;
 pub fn from_grpc_block_update(
     block: SubscribeUpdateBlock,
     commitment_config: CommitmentConfig,
-) -> ProducedBlock {
+) -> anyhow::Result<ProducedBlock> {
     let _span = debug_span!("from_grpc_block_update", ?block.slot).entered();
     let txs: Vec<TransactionInfo> = block
         .transactions
@@ -189,36 +194,55 @@ pub fn from_grpc_block_update(
                         .unwrap_or(false)
             });
 
-            let readable_accounts = account_keys
+            let mut readable_accounts: Vec<Pubkey> = account_keys
                 .iter()
                 .enumerate()
                 .filter(|(index, _)| !message.is_maybe_writable(*index))
                 .map(|(_, pk)| *pk)
                 .collect();
-            let writable_accounts = account_keys
+            let mut writable_accounts: Vec<Pubkey> = account_keys
                 .iter()
                 .enumerate()
                 .filter(|(index, _)| message.is_maybe_writable(*index))
                 .map(|(_, pk)| *pk)
                 .collect();
+            // the addresses v0 transactions loaded from their lookup tables
+            writable_accounts.extend(
+                meta.loaded_writable_addresses
+                    .iter()
+                    .filter_map(|key| Pubkey::try_from(key.as_slice()).ok()),
+            );
+            readable_accounts.extend(
+                meta.loaded_readonly_addresses
+                    .iter()
+                    .filter_map(|key| Pubkey::try_from(key.as_slice()).ok()),
+            );
 
             let address_lookup_tables = message
                 .address_table_lookups()
                 .map(|x| x.to_vec())
                 .unwrap_or_default();
 
+            let program_ids = message
+                .instructions()
+                .iter()
+                .map(|i| *i.program_id(message.static_account_keys()))
+                .unique()
+                .collect();
+
             Some(TransactionInfo {
-                signature: signature.to_string(),
+                signature,
                 is_vote: is_vote_transaction,
                 err,
                 cu_requested,
                 prioritization_fees,
                 cu_consumed: compute_units_consumed,
-                recent_blockhash: message.recent_blockhash().to_string(),
-                message: BASE64.encode(message.serialize()),
+                recent_blockhash: *message.recent_blockhash(),
+                message: TransactionMessage::new(message.serialize()),
                 readable_accounts,
                 writable_accounts,
                 address_lookup_tables,
+                program_ids,
             })
         })
         .collect();
@@ -240,7 +264,7 @@ pub fn from_grpc_block_update(
                     }
                     yellowstone_grpc_proto::prelude::RewardType::Voting => Some(RewardType::Voting),
                 },
-                commission: None,
+                commission: reward.commission.parse().ok(),
             })
             .collect_vec()
     });
@@ -249,81 +273,144 @@ pub fn from_grpc_block_update(
         rewards
             .iter()
             .find(|reward| Some(RewardType::Fee) == reward.reward_type)
-            .map(|leader_reward| leader_reward.pubkey.clone())
+            .and_then(|leader_reward| Pubkey::from_str(&leader_reward.pubkey).ok())
     } else {
         None
     };
 
-    ProducedBlock {
+    Ok(ProducedBlock {
         transactions: txs,
         block_height: block
             .block_height
             .map(|block_height| block_height.block_height)
             .unwrap(),
         block_time: block.block_time.map(|time| time.timestamp).unwrap() as u64,
-        blockhash: block.blockhash,
-        previous_blockhash: block.parent_blockhash,
+        blockhash: Hash::from_str(&block.blockhash)
+            .with_context(|| format!("invalid blockhash {}", block.blockhash))?,
+        previous_blockhash: Hash::from_str(&block.parent_blockhash)
+            .with_context(|| format!("invalid parent blockhash {}", block.parent_blockhash))?,
         commitment_config,
         leader_id,
         parent_slot: block.parent_slot,
         slot: block.slot,
         rewards,
-    }
+    })
 }
 
 pub fn create_grpc_subscription(
-    rpc_client: Arc<RpcClient>,
+    rpc_pool: RpcPool,
     grpc_sources: Vec<GrpcSourceConfig>,
     accounts_filter: AccountFilters,
+) -> anyhow::Result<(EndpointStreaming, Vec<AnyhowJoinHandle>)> {
+    create_reloadable_grpc_subscription(
+        rpc_pool,
+        watch::channel(grpc_sources).1,
+        watch::channel(accounts_filter).1,
+    )
+}
+
+/// grpc subscription following changes of the source list and account filters without closing the streams;
+/// the account stream is only created if the initial account filters are not empty
+pub fn create_reloadable_grpc_subscription(
+    rpc_pool: RpcPool,
+    grpc_sources: watch::Receiver<Vec<GrpcSourceConfig>>,
+    accounts_filter: watch::Receiver<AccountFilters>,
 ) -> anyhow::Result<(EndpointStreaming, Vec<AnyhowJoinHandle>)> {
     let (cluster_info_sx, cluster_info_notifier) = tokio::sync::broadcast::channel(10);
     let (va_sx, vote_account_notifier) = tokio::sync::broadcast::channel(10);
 
     // processed slot is required to keep up with leader schedule
     let (slot_multiplex_channel, jh_multiplex_slotstream) =
-        create_grpc_multiplex_processed_slots_subscription(grpc_sources.clone());
+        create_reloadable_grpc_multiplex_processed_slots_subscription(grpc_sources.clone());
 
+    let (block_ordering_sx, block_ordering_notifier) = tokio::sync::broadcast::channel(64);
     let (block_multiplex_channel, jh_multiplex_blockstream) =
-        create_grpc_multiplex_blocks_subscription(grpc_sources.clone());
+        create_reloadable_grpc_multiplex_blocks_subscription(
+            grpc_sources.clone(),
+            Some(GapFiller::new(rpc_pool.clone())),
+            Some(block_ordering_sx),
+        );
+    let (block_info_notifier, jh_block_info_stream) =
+        create_block_info_stream(block_multiplex_channel.resubscribe());
 
-    let cluster_info_polling = poll_cluster_info(rpc_client.clone(), cluster_info_sx);
-    let vote_accounts_polling = poll_vote_accounts(rpc_client.clone(), va_sx);
+    let cluster_info_polling = poll_cluster_info(rpc_pool.clone(), cluster_info_sx);
+    let vote_accounts_polling = poll_vote_accounts(rpc_pool, va_sx);
+
+    // the sysvars are a subscription of their own, the account filters of the user are replaced on reload
+    let (sysvar_account_jh, sysvar_account_stream) = create_reloadable_grpc_account_streaming(
+        grpc_sources.clone(),
+        watch::channel(vec![clock_account_filter()]).1,
+    );
+    let (clock_notifier, jh_clock_stream) = create_clock_stream(sysvar_account_stream);
 
     // accounts
-    if !accounts_filter.is_empty() {
+    if !accounts_filter.borrow().is_empty() {
         let (account_jh, processed_account_stream) =
-            create_grpc_account_streaming(grpc_sources, accounts_filter);
+            create_reloadable_grpc_account_streaming(grpc_sources, accounts_filter);
         let streamers = EndpointStreaming {
             blocks_notifier: block_multiplex_channel,
+            block_info_notifier,
             slot_notifier: slot_multiplex_channel,
             cluster_info_notifier,
             vote_account_notifier,
             processed_account_stream: Some(processed_account_stream),
+            block_ordering_notifier: Some(block_ordering_notifier),
+            clock_notifier: Some(clock_notifier),
         };
 
         let endpoint_tasks = vec![
             jh_multiplex_slotstream,
             jh_multiplex_blockstream,
+            jh_block_info_stream,
             cluster_info_polling,
             vote_accounts_polling,
+            sysvar_account_jh,
+            jh_clock_stream,
             account_jh,
         ];
         Ok((streamers, endpoint_tasks))
     } else {
         let streamers = EndpointStreaming {
             blocks_notifier: block_multiplex_channel,
+            block_info_notifier,
             slot_notifier: slot_multiplex_channel,
             cluster_info_notifier,
             vote_account_notifier,
             processed_account_stream: None,
+            block_ordering_notifier: Some(block_ordering_notifier),
+            clock_notifier: Some(clock_notifier),
         };
 
         let endpoint_tasks = vec![
             jh_multiplex_slotstream,
             jh_multiplex_blockstream,
+            jh_block_info_stream,
             cluster_info_polling,
             vote_accounts_polling,
+            sysvar_account_jh,
+            jh_clock_stream,
         ];
         Ok((streamers, endpoint_tasks))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yellowstone_grpc_proto::prelude::{BlockHeight, UnixTimestamp};
+
+    #[test]
+    fn test_an_invalid_blockhash_is_an_error() {
+        let block = SubscribeUpdateBlock {
+            slot: 42,
+            blockhash: "not-a-blockhash".to_string(),
+            parent_slot: 41,
+            parent_blockhash: Hash::new_unique().to_string(),
+            block_height: Some(BlockHeight { block_height: 42 }),
+            block_time: Some(UnixTimestamp { timestamp: 0 }),
+            ..Default::default()
+        };
+
+        assert!(from_grpc_block_update(block, CommitmentConfig::confirmed()).is_err());
+    }
+}
@@ -2,18 +2,21 @@ use crate::rpc_polling::vote_accounts_and_cluster_info_polling::{
     poll_cluster_info, poll_vote_accounts,
 };
 use crate::{
+    block_info_stream::create_block_info_stream,
+    clock_stream::poll_clock,
     endpoint_stremers::EndpointStreaming,
     rpc_polling::{poll_blocks::poll_block, poll_slots::poll_slots},
+    rpc_pool::RpcPool,
 };
-use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_lite_rpc_core::AnyhowJoinHandle;
 use solana_sdk::commitment_config::CommitmentConfig;
-use std::sync::Arc;
 
 pub fn create_json_rpc_polling_subscription(
-    rpc_client: Arc<RpcClient>,
+    rpc_pool: RpcPool,
     num_parallel_tasks: usize,
 ) -> anyhow::Result<(EndpointStreaming, Vec<AnyhowJoinHandle>)> {
+    // slots and blocks are polled from the primary rpc node, the pool covers the cluster polling
+    let rpc_client = rpc_pool.primary();
     let (slot_sx, slot_notifier) = tokio::sync::broadcast::channel(16);
     let (block_sx, blocks_notifier) = tokio::sync::broadcast::channel(16);
     let (cluster_info_sx, cluster_info_notifier) = tokio::sync::broadcast::channel(16);
@@ -31,19 +34,29 @@ pub fn create_json_rpc_polling_subscription(
     );
     endpoint_tasks.append(&mut block_polling_tasks);
 
-    let cluster_info_polling = poll_cluster_info(rpc_client.clone(), cluster_info_sx);
+    let (block_info_notifier, block_info_task) =
+        create_block_info_stream(blocks_notifier.resubscribe());
+    endpoint_tasks.push(block_info_task);
+
+    let cluster_info_polling = poll_cluster_info(rpc_pool.clone(), cluster_info_sx);
     endpoint_tasks.push(cluster_info_polling);
 
-    let vote_accounts_polling = poll_vote_accounts(rpc_client.clone(), va_sx);
+    let (clock_notifier, clock_polling) = poll_clock(rpc_pool.clone());
+    endpoint_tasks.push(clock_polling);
+
+    let vote_accounts_polling = poll_vote_accounts(rpc_pool, va_sx);
     endpoint_tasks.push(vote_accounts_polling);
 
     let streamers = EndpointStreaming {
         blocks_notifier,
+        block_info_notifier,
         slot_notifier,
         cluster_info_notifier,
         vote_account_notifier,
         // does not support accounts support with rpc polling
         processed_account_stream: None,
+        block_ordering_notifier: None,
+        clock_notifier: Some(clock_notifier),
     };
     Ok((streamers, endpoint_tasks))
 }
@@ -1,13 +1,20 @@
+pub mod block_info_stream;
+pub mod clock_stream;
+pub mod commitment_upgrade_stream;
 pub mod endpoint_stremers;
+pub mod gap_filler;
 pub mod grpc;
 pub mod grpc_inspect;
 pub mod grpc_leaders_getter;
 pub mod grpc_multiplex;
+pub mod grpc_source_metrics;
 pub mod grpc_stream_utils;
 pub mod grpc_subscription;
 pub mod json_rpc_leaders_getter;
 pub mod json_rpc_subscription;
 pub mod rpc_polling;
+pub mod rpc_pool;
+pub mod stake_leader_schedule;
 
 pub use geyser_grpc_connector;
 pub use yellowstone_grpc_proto::geyser::CommitmentLevel;
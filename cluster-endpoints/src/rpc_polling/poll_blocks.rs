@@ -1,7 +1,6 @@
 use anyhow::{bail, Context};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_lite_rpc_core::encoding::BinaryEncoding;
-use solana_lite_rpc_core::structures::produced_block::TransactionInfo;
+use solana_lite_rpc_core::structures::produced_block::{TransactionInfo, TransactionMessage};
 use solana_lite_rpc_core::{
     structures::{
         produced_block::ProducedBlock,
@@ -18,13 +17,15 @@ use solana_sdk::vote::instruction::VoteInstruction;
 use solana_sdk::{
     commitment_config::{CommitmentConfig, CommitmentLevel},
     compute_budget,
+    hash::Hash,
+    pubkey::Pubkey,
     slot_history::Slot,
 };
 use solana_transaction_status::option_serializer::OptionSerializer;
 use solana_transaction_status::{
     TransactionDetails, UiConfirmedBlock, UiTransactionEncoding, UiTransactionStatusMeta,
 };
-use std::{sync::Arc, time::Duration};
+use std::{str::FromStr, sync::Arc, time::Duration};
 use tokio::sync::broadcast::{Receiver, Sender};
 
 pub const NUM_PARALLEL_TASKS_DEFAULT: usize = 16;
@@ -46,9 +47,14 @@ pub async fn process_block(
             },
         )
         .await;
-    block
-        .ok()
-        .map(|block| from_ui_block(block, slot, commitment_config))
+    let block = block.ok()?;
+    match from_ui_block(block, slot, commitment_config) {
+        Ok(block) => Some(block),
+        Err(err) => {
+            log::warn!("skipping block {slot}: {err:?}");
+            None
+        }
+    }
 }
 
 pub fn poll_block(
@@ -180,12 +186,14 @@ pub fn from_ui_block(
     block: UiConfirmedBlock,
     slot: Slot,
     commitment_config: CommitmentConfig,
-) -> ProducedBlock {
+) -> anyhow::Result<ProducedBlock> {
     let block_height = block.block_height.unwrap_or_default();
     let txs = block.transactions.unwrap_or_default();
 
-    let blockhash = block.blockhash;
-    let previous_blockhash = block.previous_blockhash;
+    let blockhash = Hash::from_str(&block.blockhash)
+        .with_context(|| format!("invalid blockhash {}", block.blockhash))?;
+    let previous_blockhash = Hash::from_str(&block.previous_blockhash)
+        .with_context(|| format!("invalid previous blockhash {}", block.previous_blockhash))?;
     let parent_slot = block.parent_slot;
     let rewards = block.rewards.clone();
 
@@ -195,6 +203,7 @@ pub fn from_ui_block(
             let Some(UiTransactionStatusMeta {
                 err,
                 compute_units_consumed,
+                loaded_addresses,
                 ..
             }) = tx.meta
             else {
@@ -209,7 +218,7 @@ pub fn from_ui_block(
                 return None;
             };
 
-            let signature = tx.signatures[0].to_string();
+            let signature = tx.signatures[0];
             let cu_consumed = match compute_units_consumed {
                 OptionSerializer::Some(cu_consumed) => Some(cu_consumed),
                 _ => None,
@@ -264,8 +273,8 @@ pub fn from_ui_block(
                 }
             };
 
-            let blockhash = tx.message.recent_blockhash().to_string();
-            let message = BinaryEncoding::Base64.encode(tx.message.serialize());
+            let blockhash = *tx.message.recent_blockhash();
+            let message = TransactionMessage::new(tx.message.serialize());
 
             let is_vote_transaction = tx.message.instructions().iter().any(|i| {
                 i.program_id(tx.message.static_account_keys())
@@ -285,6 +294,21 @@ pub fn from_ui_block(
                     readable_accounts.push(*account);
                 }
             }
+            // the addresses v0 transactions loaded from their lookup tables
+            if let OptionSerializer::Some(loaded_addresses) = loaded_addresses {
+                writable_accounts.extend(
+                    loaded_addresses
+                        .writable
+                        .iter()
+                        .filter_map(|key| Pubkey::from_str(key).ok()),
+                );
+                readable_accounts.extend(
+                    loaded_addresses
+                        .readonly
+                        .iter()
+                        .filter_map(|key| Pubkey::from_str(key).ok()),
+                );
+            }
 
             let address_lookup_tables = tx
                 .message
@@ -292,6 +316,14 @@ pub fn from_ui_block(
                 .map(|x| x.to_vec())
                 .unwrap_or_default();
 
+            let mut program_ids = vec![];
+            for instruction in tx.message.instructions() {
+                let program_id = *instruction.program_id(accounts);
+                if !program_ids.contains(&program_id) {
+                    program_ids.push(program_id);
+                }
+            }
+
             Some(TransactionInfo {
                 signature,
                 is_vote: is_vote_transaction,
@@ -304,6 +336,7 @@ pub fn from_ui_block(
                 readable_accounts,
                 writable_accounts,
                 address_lookup_tables,
+                program_ids,
             })
         })
         .collect();
@@ -312,14 +345,14 @@ pub fn from_ui_block(
         rewards
             .iter()
             .find(|reward| Some(RewardType::Fee) == reward.reward_type)
-            .map(|leader_reward| leader_reward.pubkey.clone())
+            .and_then(|leader_reward| Pubkey::from_str(&leader_reward.pubkey).ok())
     } else {
         None
     };
 
     let block_time = block.block_time.unwrap_or(0) as u64;
 
-    ProducedBlock {
+    Ok(ProducedBlock {
         transactions: txs,
         block_height,
         leader_id,
@@ -330,7 +363,7 @@ pub fn from_ui_block(
         slot,
         commitment_config,
         rewards,
-    }
+    })
 }
 
 #[inline]
@@ -1,18 +1,21 @@
+use crate::rpc_pool::RpcPool;
 use log::{debug, warn};
-use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_lite_rpc_core::AnyhowJoinHandle;
 use solana_rpc_client_api::response::{RpcContactInfo, RpcVoteAccountStatus};
-use std::{sync::Arc, time::Duration};
+use std::time::Duration;
 use tokio::sync::broadcast::Sender;
 
 pub fn poll_cluster_info(
-    rpc_client: Arc<RpcClient>,
+    rpc_pool: RpcPool,
     contact_info_sender: Sender<Vec<RpcContactInfo>>,
 ) -> AnyhowJoinHandle {
     // task MUST not terminate but might be aborted from outside
     tokio::spawn(async move {
         loop {
-            match rpc_client.get_cluster_nodes().await {
+            match rpc_pool
+                .call(|rpc_client| async move { rpc_client.get_cluster_nodes().await })
+                .await
+            {
                 Ok(cluster_nodes) => {
                     debug!("get cluster_nodes from rpc: {:?}", cluster_nodes.len());
                     if let Err(e) = contact_info_sender.send(cluster_nodes) {
@@ -31,13 +34,16 @@ pub fn poll_cluster_info(
 }
 
 pub fn poll_vote_accounts(
-    rpc_client: Arc<RpcClient>,
+    rpc_pool: RpcPool,
     vote_account_sender: Sender<RpcVoteAccountStatus>,
 ) -> AnyhowJoinHandle {
     // task MUST not terminate but might be aborted from outside
     tokio::spawn(async move {
         loop {
-            match rpc_client.get_vote_accounts().await {
+            match rpc_pool
+                .call(|rpc_client| async move { rpc_client.get_vote_accounts().await })
+                .await
+            {
                 Ok(vote_accounts) => {
                     debug!(
                         "get vote_accounts from rpc: {:?}",
@@ -0,0 +1,304 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use log::{info, warn};
+use prometheus::{opts, register_int_counter, register_int_gauge_vec, IntCounter, IntGaugeVec};
+use solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient};
+use solana_lite_rpc_core::AnyhowJoinHandle;
+
+lazy_static::lazy_static! {
+    static ref RPC_ENDPOINT_AVAILABLE: IntGaugeVec =
+    register_int_gauge_vec!(opts!("literpc_rpc_endpoint_available", "1 if the circuit breaker of the rpc endpoint is closed, by endpoint index"), &["endpoint"]).unwrap();
+    static ref RPC_FAILOVERS: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_failovers", "Rpc calls which failed on an endpoint and were retried on the next one")).unwrap();
+}
+
+// consecutive failures opening the circuit breaker of an endpoint
+const FAILURE_THRESHOLD: usize = 3;
+// an open endpoint is tried again after this delay
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct CircuitBreaker {
+    consecutive_failures: usize,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    // closed or half-open, a half-open endpoint gets one call which closes or re-opens it
+    fn is_available(&self) -> bool {
+        self.opened_at
+            .map_or(true, |opened_at| opened_at.elapsed() >= OPEN_DURATION)
+    }
+}
+
+struct RpcEndpoint {
+    client: Arc<RpcClient>,
+    breaker: Mutex<CircuitBreaker>,
+}
+
+/// The rpc node and its fallbacks. Calls go to the first endpoint whose circuit breaker is closed
+/// and fail over to the next ones, an endpoint is skipped after consecutive failures until the
+/// health check or a later call finds it working again.
+#[derive(Clone)]
+pub struct RpcPool {
+    endpoints: Arc<Vec<RpcEndpoint>>,
+}
+
+impl RpcPool {
+    pub fn new(primary: Arc<RpcClient>, fallbacks: Vec<Arc<RpcClient>>) -> Self {
+        let endpoints: Vec<RpcEndpoint> = std::iter::once(primary)
+            .chain(fallbacks)
+            .map(|client| RpcEndpoint {
+                client,
+                breaker: Mutex::new(CircuitBreaker::default()),
+            })
+            .collect();
+        for index in 0..endpoints.len() {
+            RPC_ENDPOINT_AVAILABLE
+                .with_label_values(&[&index.to_string()])
+                .set(1);
+        }
+        Self {
+            endpoints: Arc::new(endpoints),
+        }
+    }
+
+    pub fn from_urls(primary: String, fallbacks: Vec<String>) -> Self {
+        Self::new(
+            Arc::new(RpcClient::new(primary)),
+            fallbacks
+                .into_iter()
+                .map(|url| Arc::new(RpcClient::new(url)))
+                .collect(),
+        )
+    }
+
+    pub fn primary(&self) -> Arc<RpcClient> {
+        self.endpoints[0].client.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// urls of the endpoints in the order to try them, the available ones first
+    pub fn urls(&self) -> Vec<String> {
+        self.ordered_endpoints()
+            .into_iter()
+            .map(|index| self.endpoints[index].client.url())
+            .collect()
+    }
+
+    /// records the outcome of a request sent to an endpoint without going through [`RpcPool::call`]
+    pub fn report(&self, url: &str, success: bool) {
+        if let Some(index) = self
+            .endpoints
+            .iter()
+            .position(|endpoint| endpoint.client.url() == url)
+        {
+            self.record(index, success);
+        }
+    }
+
+    /// calls the available endpoints in order until one succeeds, the error of the last one is returned
+    pub async fn call<T, F, Fut>(&self, f: F) -> anyhow::Result<T>
+    where
+        F: Fn(Arc<RpcClient>) -> Fut,
+        Fut: Future<Output = Result<T, ClientError>>,
+    {
+        let mut last_error = None;
+        for index in self.ordered_endpoints() {
+            if last_error.is_some() {
+                RPC_FAILOVERS.inc();
+            }
+            match f(self.endpoints[index].client.clone()).await {
+                Ok(result) => {
+                    self.record(index, true);
+                    return Ok(result);
+                }
+                Err(e) => {
+                    self.record(index, false);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.map_or_else(
+            || anyhow!("no rpc endpoint configured"),
+            anyhow::Error::from,
+        ))
+    }
+
+    /// checks the health of the endpoints periodically, so unavailable endpoints are closed again
+    /// without waiting for calls to them
+    pub fn start_health_checks(self, interval: Duration) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                for (index, endpoint) in self.endpoints.iter().enumerate() {
+                    let result = endpoint.client.get_health().await;
+                    if let Err(e) = &result {
+                        warn!("rpc endpoint {index} is unhealthy: {e}");
+                    }
+                    self.record(index, result.is_ok());
+                }
+            }
+        })
+    }
+
+    // available endpoints in configured order first, the others are tried as a last resort
+    fn ordered_endpoints(&self) -> Vec<usize> {
+        let (available, unavailable): (Vec<usize>, Vec<usize>) = (0..self.endpoints.len())
+            .partition(|index| {
+                self.endpoints[*index]
+                    .breaker
+                    .lock()
+                    .unwrap()
+                    .is_available()
+            });
+        available.into_iter().chain(unavailable).collect()
+    }
+
+    fn record(&self, index: usize, success: bool) {
+        let mut breaker = self.endpoints[index].breaker.lock().unwrap();
+        if success {
+            if breaker.opened_at.take().is_some() {
+                info!("rpc endpoint {index} is available again");
+            }
+            breaker.consecutive_failures = 0;
+        } else {
+            breaker.consecutive_failures += 1;
+            if breaker.consecutive_failures >= FAILURE_THRESHOLD
+                && (breaker.opened_at.is_none() || breaker.is_available())
+            {
+                warn!(
+                    "rpc endpoint {index} failed {} times in a row, skipping it for {OPEN_DURATION:?}",
+                    breaker.consecutive_failures
+                );
+                breaker.opened_at = Some(Instant::now());
+            }
+        }
+        RPC_ENDPOINT_AVAILABLE
+            .with_label_values(&[&index.to_string()])
+            .set(breaker.opened_at.is_none() as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use solana_client::client_error::ClientErrorKind;
+
+    use super::*;
+
+    const PRIMARY: &str = "http://127.0.0.1:1";
+    const FALLBACK: &str = "http://127.0.0.1:2";
+
+    fn rpc_pool() -> RpcPool {
+        RpcPool::from_urls(PRIMARY.to_string(), vec![FALLBACK.to_string()])
+    }
+
+    fn fail(rpc_pool: &RpcPool, url: &str, times: usize) {
+        for _ in 0..times {
+            rpc_pool.report(url, false);
+        }
+    }
+
+    #[test]
+    fn test_endpoint_is_skipped_after_consecutive_failures() {
+        let rpc_pool = rpc_pool();
+
+        fail(&rpc_pool, PRIMARY, FAILURE_THRESHOLD - 1);
+        assert_eq!(rpc_pool.urls(), vec![PRIMARY, FALLBACK]);
+
+        fail(&rpc_pool, PRIMARY, 1);
+        assert_eq!(rpc_pool.urls(), vec![FALLBACK, PRIMARY]);
+    }
+
+    #[test]
+    fn test_success_resets_the_consecutive_failures() {
+        let rpc_pool = rpc_pool();
+
+        fail(&rpc_pool, PRIMARY, FAILURE_THRESHOLD - 1);
+        rpc_pool.report(PRIMARY, true);
+        fail(&rpc_pool, PRIMARY, FAILURE_THRESHOLD - 1);
+        assert_eq!(rpc_pool.urls(), vec![PRIMARY, FALLBACK]);
+
+        // a success of an open endpoint, e.g. from the health check, closes it again
+        fail(&rpc_pool, PRIMARY, 1);
+        assert_eq!(rpc_pool.urls(), vec![FALLBACK, PRIMARY]);
+        rpc_pool.report(PRIMARY, true);
+        assert_eq!(rpc_pool.urls(), vec![PRIMARY, FALLBACK]);
+    }
+
+    #[test]
+    fn test_half_open_endpoint_is_reopened_by_a_failure() {
+        let rpc_pool = rpc_pool();
+        fail(&rpc_pool, PRIMARY, FAILURE_THRESHOLD);
+
+        // the open duration passed, the endpoint gets one call
+        let opened_at = Instant::now() - OPEN_DURATION;
+        rpc_pool.endpoints[0].breaker.lock().unwrap().opened_at = Some(opened_at);
+        assert_eq!(rpc_pool.urls(), vec![PRIMARY, FALLBACK]);
+
+        fail(&rpc_pool, PRIMARY, 1);
+        assert_eq!(rpc_pool.urls(), vec![FALLBACK, PRIMARY]);
+        assert!(rpc_pool.endpoints[0].breaker.lock().unwrap().opened_at > Some(opened_at));
+    }
+
+    #[tokio::test]
+    async fn test_calls_fail_over_and_skip_the_open_endpoint() {
+        let rpc_pool = rpc_pool();
+        let calls: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+        let call = || {
+            rpc_pool.call(|client| {
+                let url = client.url();
+                *calls.lock().unwrap().entry(url.clone()).or_default() += 1;
+                async move {
+                    if url == PRIMARY {
+                        Err(ClientError::from(ClientErrorKind::Custom(
+                            "primary is down".to_string(),
+                        )))
+                    } else {
+                        Ok(url)
+                    }
+                }
+            })
+        };
+
+        for _ in 0..FAILURE_THRESHOLD {
+            assert_eq!(call().await.unwrap(), FALLBACK);
+        }
+        assert_eq!(calls.lock().unwrap()[PRIMARY], FAILURE_THRESHOLD);
+
+        // the primary is open, calls go to the fallback first
+        assert_eq!(call().await.unwrap(), FALLBACK);
+        assert_eq!(calls.lock().unwrap()[PRIMARY], FAILURE_THRESHOLD);
+        assert_eq!(calls.lock().unwrap()[FALLBACK], FAILURE_THRESHOLD + 1);
+    }
+
+    #[tokio::test]
+    async fn test_error_of_the_last_endpoint_is_returned() {
+        let rpc_pool = rpc_pool();
+
+        let err = rpc_pool
+            .call(|client| async move {
+                Err::<(), _>(ClientError::from(ClientErrorKind::Custom(format!(
+                    "{} is down",
+                    client.url()
+                ))))
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(&format!("{FALLBACK} is down")));
+    }
+}
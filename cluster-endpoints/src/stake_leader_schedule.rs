@@ -0,0 +1,237 @@
+use anyhow::{ensure, Context};
+use async_trait::async_trait;
+use itertools::Itertools;
+use prometheus::{opts, register_int_counter_vec, register_int_gauge, IntCounterVec, IntGauge};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_ledger::leader_schedule::LeaderSchedule;
+use solana_lite_rpc_core::{
+    epoch_notifier::EpochAwareCache,
+    structures::{
+        epoch::{Epoch, EpochCache},
+        leaderschedule::{CalculatedSchedule, LeaderScheduleData},
+    },
+};
+use solana_rpc_client_api::config::{RpcGetVoteAccountsConfig, RpcLeaderScheduleConfig};
+use solana_sdk::{
+    clock::NUM_CONSECUTIVE_LEADER_SLOTS, commitment_config::CommitmentConfig, pubkey::Pubkey,
+    slot_history::Slot,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    sync::Arc,
+};
+use tokio::sync::RwLock;
+
+use crate::grpc_leaders_getter::GrpcLeaderGetter;
+
+lazy_static::lazy_static! {
+    static ref LEADER_SCHEDULE_UPDATES: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_leader_schedule_updates", "Leader schedules of an epoch fetched from the rpc node or calculated from the epoch stakes"), &["source", "result"]).unwrap();
+    static ref LEADER_SCHEDULE_EPOCH: IntGauge =
+        register_int_gauge!(opts!("literpc_leader_schedule_epoch", "Latest epoch with a leader schedule")).unwrap();
+}
+
+/// Keeps the leader schedule of the current and the next epoch in a [`CalculatedSchedule`].
+/// The schedule of the current epoch is fetched once with `getLeaderSchedule` on startup, the one of
+/// the next epoch is calculated from the vote account stakes, which the rpc node returns once per
+/// epoch. Both are known before the epoch starts, so a flaky rpc node does not interrupt the
+/// forwarding of transactions at an epoch boundary. The schedules are updated by the epoch notifier.
+pub struct StakeLeaderSchedule {
+    rpc_client: Arc<RpcClient>,
+    epoch_data: EpochCache,
+    leader_schedule: Arc<RwLock<CalculatedSchedule>>,
+}
+
+impl StakeLeaderSchedule {
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        epoch_data: EpochCache,
+        leader_schedule: Arc<RwLock<CalculatedSchedule>>,
+    ) -> Self {
+        Self {
+            rpc_client,
+            epoch_data,
+            leader_schedule,
+        }
+    }
+
+    /// leader getter for the tpu service which reads the schedules kept up to date by this service
+    pub fn leader_getter(&self) -> GrpcLeaderGetter {
+        GrpcLeaderGetter::new(self.leader_schedule.clone(), self.epoch_data.clone())
+    }
+
+    /// loads the schedules of the epoch of the slot and the next one
+    pub async fn bootstrap(&self, slot: Slot) -> anyhow::Result<()> {
+        let epoch = self.epoch_data.get_epoch_at_slot(slot).epoch;
+        self.update_schedule(slot, epoch).await
+    }
+
+    async fn update_schedule(&self, slot: Slot, epoch: u64) -> anyhow::Result<()> {
+        let (current_epoch, next_epoch) = {
+            let schedule = self.leader_schedule.read().await;
+            (
+                schedule.current.as_ref().map(|data| data.epoch),
+                schedule.next.as_ref().map(|data| data.epoch),
+            )
+        };
+
+        if current_epoch != Some(epoch) {
+            if next_epoch == Some(epoch) {
+                let mut schedule = self.leader_schedule.write().await;
+                schedule.current = schedule.next.take();
+            } else {
+                // the stakes of the current epoch are no longer known by the rpc node
+                let current = self.fetch_leader_schedule(slot, epoch).await;
+                LEADER_SCHEDULE_UPDATES
+                    .with_label_values(&["rpc", result_label(&current)])
+                    .inc();
+                self.leader_schedule.write().await.current = Some(current?);
+            }
+            LEADER_SCHEDULE_EPOCH.set(epoch as i64);
+            log::info!("Leader schedule of epoch {epoch} is current");
+        }
+
+        if next_epoch != Some(epoch + 1) {
+            let next = self.calculate_next_leader_schedule(epoch).await;
+            LEADER_SCHEDULE_UPDATES
+                .with_label_values(&["stakes", result_label(&next)])
+                .inc();
+            self.leader_schedule.write().await.next = Some(next?);
+            LEADER_SCHEDULE_EPOCH.set(epoch as i64 + 1);
+            log::info!("Calculated the leader schedule of epoch {}", epoch + 1);
+        }
+        Ok(())
+    }
+
+    async fn fetch_leader_schedule(
+        &self,
+        slot: Slot,
+        epoch: u64,
+    ) -> anyhow::Result<LeaderScheduleData> {
+        let schedule_by_node = self
+            .rpc_client
+            .get_leader_schedule_with_config(
+                Some(slot),
+                RpcLeaderScheduleConfig {
+                    identity: None,
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await
+            .context("failed to get leader schedule")?
+            .with_context(|| format!("no leader schedule for epoch {epoch}"))?;
+        let schedule_by_slot = slot_leaders_from_schedule(&schedule_by_node)?;
+        Ok(LeaderScheduleData {
+            schedule_by_node,
+            schedule_by_slot,
+            epoch,
+        })
+    }
+
+    async fn calculate_next_leader_schedule(
+        &self,
+        epoch: u64,
+    ) -> anyhow::Result<LeaderScheduleData> {
+        // the stakes of the vote accounts during an epoch are the stakes of the leader schedule of the next one
+        let commitment = CommitmentConfig::confirmed();
+        let epoch_info = self
+            .rpc_client
+            .get_epoch_info_with_commitment(commitment)
+            .await
+            .context("failed to get epoch info")?;
+        ensure!(
+            epoch_info.epoch == epoch,
+            "rpc node is at epoch {}, expected {epoch}",
+            epoch_info.epoch
+        );
+        let vote_accounts = self
+            .rpc_client
+            .get_vote_accounts_with_config(RpcGetVoteAccountsConfig {
+                commitment: Some(commitment),
+                ..Default::default()
+            })
+            .await
+            .context("failed to get vote accounts")?;
+
+        let mut stakes: HashMap<Pubkey, u64> = HashMap::new();
+        for vote_account in vote_accounts
+            .current
+            .iter()
+            .chain(vote_accounts.delinquent.iter())
+            .filter(|vote_account| vote_account.activated_stake > 0)
+        {
+            let node_pubkey = Pubkey::from_str(&vote_account.node_pubkey)
+                .context("invalid node pubkey in vote accounts")?;
+            *stakes.entry(node_pubkey).or_default() += vote_account.activated_stake;
+        }
+        ensure!(!stakes.is_empty(), "no staked vote accounts");
+
+        let next_epoch = epoch + 1;
+        let slots_in_epoch = self.epoch_data.get_slots_in_epoch(next_epoch);
+        tokio::task::spawn_blocking(move || {
+            calculate_leader_schedule(stakes, next_epoch, slots_in_epoch)
+        })
+        .await
+        .context("leader schedule calculation panicked")
+    }
+}
+
+#[async_trait]
+impl EpochAwareCache for StakeLeaderSchedule {
+    fn name(&self) -> &'static str {
+        "leader_schedule"
+    }
+
+    async fn on_new_epoch(&self, epoch: &Epoch) -> anyhow::Result<()> {
+        self.update_schedule(epoch.absolute_slot, epoch.epoch).await
+    }
+}
+
+/// same schedule as the validators derive from the stakes of the epoch
+pub fn calculate_leader_schedule(
+    stakes: HashMap<Pubkey, u64>,
+    epoch: u64,
+    slots_in_epoch: u64,
+) -> LeaderScheduleData {
+    let mut stakes = stakes.into_iter().collect_vec();
+    // sorted by stake then pubkey like in leader_schedule_utils, the order changes the schedule
+    stakes.sort_unstable_by(|(l_pubkey, l_stake), (r_pubkey, r_stake)| {
+        r_stake.cmp(l_stake).then_with(|| r_pubkey.cmp(l_pubkey))
+    });
+    let mut seed = [0u8; 32];
+    seed[0..8].copy_from_slice(&epoch.to_le_bytes());
+    let schedule = LeaderSchedule::new(&stakes, seed, slots_in_epoch, NUM_CONSECUTIVE_LEADER_SLOTS);
+
+    let schedule_by_slot = schedule.get_slot_leaders().to_vec();
+    let schedule_by_node = schedule_by_slot
+        .iter()
+        .enumerate()
+        .map(|(index, pubkey)| (pubkey.to_string(), index))
+        .into_group_map();
+    LeaderScheduleData {
+        schedule_by_node,
+        schedule_by_slot,
+        epoch,
+    }
+}
+
+fn slot_leaders_from_schedule(
+    schedule_by_node: &HashMap<String, Vec<usize>>,
+) -> anyhow::Result<Vec<Pubkey>> {
+    let mut slot_leaders = BTreeMap::new();
+    for (node, slot_indexes) in schedule_by_node {
+        let pubkey = Pubkey::from_str(node).context("invalid pubkey in leader schedule")?;
+        for slot_index in slot_indexes {
+            slot_leaders.insert(*slot_index, pubkey);
+        }
+    }
+    Ok(slot_leaders.into_values().collect())
+}
+
+fn result_label<T>(result: &anyhow::Result<T>) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(_) => "error",
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use solana_lite_rpc_cluster_endpoints::geyser_grpc_connector::{
+    GrpcConnectionTimeouts, GrpcSourceConfig,
+};
+use solana_lite_rpc_cluster_endpoints::grpc_multiplex::{
+    create_grpc_multiplex_blocks_subscription, create_grpc_multiplex_processed_slots_subscription,
+};
+use solana_lite_rpc_test_utils::mock_geyser::{Fault, MockGeyser, MockGeyserHandle};
+use solana_lite_rpc_test_utils::updates::{self, blockhash};
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentLevel;
+use tokio::time::timeout;
+use yellowstone_grpc_proto::geyser::CommitmentLevel as GeyserCommitmentLevel;
+
+const FIRST_SLOT: Slot = 100;
+const SLOTS: u64 = 20;
+const LAST_SLOT: Slot = FIRST_SLOT + SLOTS - 1;
+
+fn source(mock: &MockGeyserHandle) -> GrpcSourceConfig {
+    let timeouts = GrpcConnectionTimeouts {
+        connect_timeout: Duration::from_secs(5),
+        request_timeout: Duration::from_secs(5),
+        subscribe_timeout: Duration::from_secs(5),
+        receive_timeout: Duration::from_secs(5),
+    };
+    GrpcSourceConfig::new(mock.grpc_addr(), None, None, timeouts)
+}
+
+fn chain(
+    commitment: GeyserCommitmentLevel,
+) -> Vec<yellowstone_grpc_proto::geyser::SubscribeUpdate> {
+    updates::chain(FIRST_SLOT, SLOTS, commitment)
+}
+
+#[tokio::test]
+async fn multiplexed_blocks_are_deduplicated_and_skip_gaps() {
+    // a slot and a block per slot, index 8 is the slot update of the 5th slot
+    let gap_slot = FIRST_SLOT + 4;
+    let mock = MockGeyser::new()
+        .with_updates(
+            GeyserCommitmentLevel::Processed,
+            chain(GeyserCommitmentLevel::Processed),
+        )
+        .with_updates(
+            GeyserCommitmentLevel::Confirmed,
+            chain(GeyserCommitmentLevel::Confirmed),
+        )
+        .with_updates(
+            GeyserCommitmentLevel::Finalized,
+            chain(GeyserCommitmentLevel::Finalized),
+        )
+        .with_fault(GeyserCommitmentLevel::Processed, 3, Fault::Duplicate(2))
+        .with_fault(GeyserCommitmentLevel::Processed, 8, Fault::Skip(2))
+        // finalized block metas of blocks not processed yet are dropped by the multiplexer
+        .with_fault(
+            GeyserCommitmentLevel::Finalized,
+            0,
+            Fault::Delay(Duration::from_secs(1)),
+        )
+        .start()
+        .await
+        .unwrap();
+
+    let (mut blocks, _jh) = create_grpc_multiplex_blocks_subscription(vec![source(&mock)]);
+    let mut slots_by_commitment: HashMap<CommitmentLevel, Vec<Slot>> = HashMap::new();
+    timeout(Duration::from_secs(30), async {
+        loop {
+            let block = blocks.recv().await.unwrap();
+            assert_eq!(block.blockhash, blockhash(block.slot));
+            let commitment = block.commitment_config.commitment;
+            slots_by_commitment
+                .entry(commitment)
+                .or_default()
+                .push(block.slot);
+            if commitment == CommitmentLevel::Finalized && block.slot == LAST_SLOT {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("all blocks finalized");
+
+    let expected: Vec<Slot> = (FIRST_SLOT..=LAST_SLOT)
+        .filter(|slot| *slot != gap_slot)
+        .collect();
+    for commitment in [
+        CommitmentLevel::Processed,
+        CommitmentLevel::Confirmed,
+        CommitmentLevel::Finalized,
+    ] {
+        assert_eq!(slots_by_commitment[&commitment], expected, "{commitment:?}");
+    }
+}
+
+#[tokio::test]
+async fn multiplexed_slots_resume_after_disconnect() {
+    let mock = MockGeyser::new()
+        .with_updates(
+            GeyserCommitmentLevel::Processed,
+            chain(GeyserCommitmentLevel::Processed),
+        )
+        .with_fault(GeyserCommitmentLevel::Processed, 10, Fault::Disconnect)
+        .start()
+        .await
+        .unwrap();
+
+    let (mut slots, _jh) = create_grpc_multiplex_processed_slots_subscription(vec![source(&mock)]);
+    let mut received = vec![];
+    timeout(Duration::from_secs(30), async {
+        while received.last() != Some(&LAST_SLOT) {
+            received.push(slots.recv().await.unwrap().processed_slot);
+        }
+    })
+    .await
+    .expect("all slots received");
+
+    assert_eq!(received, (FIRST_SLOT..=LAST_SLOT).collect::<Vec<_>>());
+    assert!(mock.subscriptions() >= 2, "the source was reconnected");
+}
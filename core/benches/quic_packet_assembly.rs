@@ -0,0 +1,84 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use solana_lite_rpc_core::structures::proxy_request_format::{TpuForwardingRequest, TxData};
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+
+// transactions per uni stream to the quic proxy, same as the quic proxy connection manager
+const CHUNK_SIZE_PER_STREAM: usize = 20;
+const TPU_FANOUT_NODES: usize = 4;
+const TRANSACTIONS: [usize; 2] = [20, 200];
+
+fn bench_quic_packet_assembly(c: &mut Criterion) {
+    let tpu_nodes = (0..TPU_FANOUT_NODES)
+        .map(|i| {
+            (
+                SocketAddr::from((Ipv4Addr::LOCALHOST, 8000 + i as u16)),
+                Pubkey::new_unique(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("quic_packet_assembly");
+    for nb_transactions in TRANSACTIONS {
+        let transactions = transactions(nb_transactions);
+        group.throughput(Throughput::Elements(nb_transactions as u64));
+        group.bench_with_input(
+            BenchmarkId::new("wire_transactions", nb_transactions),
+            &transactions,
+            |b, transactions| {
+                b.iter(|| {
+                    transactions
+                        .iter()
+                        .map(|tx| bincode::serialize(tx).unwrap())
+                        .collect::<Vec<_>>()
+                })
+            },
+        );
+
+        let txs = transactions
+            .iter()
+            .map(|tx| TxData::new(tx.signatures[0], bincode::serialize(tx).unwrap()))
+            .collect::<Vec<_>>();
+        group.bench_with_input(
+            BenchmarkId::new("proxy_forwarding_requests", nb_transactions),
+            &txs,
+            |b, txs| {
+                b.iter(|| {
+                    txs.chunks(CHUNK_SIZE_PER_STREAM)
+                        .map(|chunk| {
+                            TpuForwardingRequest::new(&tpu_nodes, chunk)
+                                .try_serialize_wire_format()
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn transactions(count: usize) -> Vec<VersionedTransaction> {
+    let payer = Keypair::new();
+    let blockhash = Hash::new_unique();
+    (0..count)
+        .map(|i| {
+            let transfer =
+                system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), i as u64 + 1);
+            VersionedTransaction::from(Transaction::new_signed_with_payer(
+                &[transfer],
+                Some(&payer.pubkey()),
+                &[&payer],
+                blockhash,
+            ))
+        })
+        .collect()
+}
+
+criterion_group!(benches, bench_quic_packet_assembly);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use dashmap::DashMap;
+use solana_lite_rpc_core::stores::tx_store::{TxProps, TxStatusUpdate, TxStore};
+use solana_sdk::signature::Signature;
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+
+const SIGNATURES_PER_BLOCK: [usize; 2] = [1_000, 5_000];
+// share of the transactions of a block which were sent through lite-rpc
+const SENT_BY_LITE_RPC_EVERY: usize = 10;
+
+fn bench_tx_status_ingestion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tx_status_ingestion");
+    for signatures in SIGNATURES_PER_BLOCK {
+        let updates = block_updates(signatures);
+        group.throughput(Throughput::Elements(signatures as u64));
+        group.bench_with_input(
+            BenchmarkId::new("per_transaction", signatures),
+            &updates,
+            |b, updates| {
+                b.iter_batched(
+                    || (tx_store(updates), updates.clone()),
+                    |(tx_store, updates)| {
+                        for update in updates {
+                            tx_store.update_status(
+                                &update.signature,
+                                update.status,
+                                update.last_valid_blockheight,
+                            );
+                        }
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("batched", signatures),
+            &updates,
+            |b, updates| {
+                b.iter_batched(
+                    || (tx_store(updates), updates.clone()),
+                    |(tx_store, updates)| tx_store.update_statuses(updates),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+// the transactions sent through lite-rpc are known before their block arrives
+fn tx_store(updates: &[TxStatusUpdate]) -> TxStore {
+    let tx_store = TxStore {
+        store: Arc::new(DashMap::new()),
+    };
+    for update in updates.iter().step_by(SENT_BY_LITE_RPC_EVERY) {
+        tx_store.insert(
+            update.signature,
+            TxProps {
+                status: None,
+                last_valid_blockheight: update.last_valid_blockheight,
+                sent_by_lite_rpc: true,
+                finalized_at: None,
+            },
+        );
+    }
+    tx_store
+}
+
+fn block_updates(signatures: usize) -> Vec<TxStatusUpdate> {
+    (0..signatures)
+        .map(|_| TxStatusUpdate {
+            signature: Signature::new_unique(),
+            status: TransactionStatus {
+                slot: 42,
+                confirmations: None,
+                status: Ok(()),
+                err: None,
+                confirmation_status: Some(TransactionConfirmationStatus::Confirmed),
+            },
+            last_valid_blockheight: 42 + 150,
+        })
+        .collect()
+}
+
+criterion_group!(benches, bench_tx_status_ingestion);
+criterion_main!(benches);
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+use async_trait::async_trait;
+use log::{info, warn};
+use prometheus::{opts, register_int_counter_vec, register_int_gauge, IntCounterVec, IntGauge};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::structures::epoch::{Epoch, EpochCache};
+use crate::types::{EpochStream, SlotStream};
+use crate::AnyhowJoinHandle;
+
+lazy_static::lazy_static! {
+    static ref CURRENT_EPOCH: IntGauge =
+    register_int_gauge!(opts!("literpc_current_epoch", "Epoch of the newest processed slot seen by the epoch notifier")).unwrap();
+    static ref EPOCH_CACHE_REFRESHES: IntCounterVec =
+    register_int_counter_vec!(opts!("literpc_epoch_cache_refreshes", "Refreshes of the epoch aware caches at an epoch boundary"), &["cache", "result"]).unwrap();
+}
+
+// a failed refresh is retried after this delay until it succeeds or the next epoch starts
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// a cache with values which only change at epoch boundaries, like stakes or the leader schedule
+#[async_trait]
+pub trait EpochAwareCache: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// called once for the epoch of the first slot and then whenever the slots reach a new epoch
+    async fn on_new_epoch(&self, epoch: &Epoch) -> anyhow::Result<()>;
+}
+
+/// Follows the processed slots and refreshes the registered caches when a new epoch starts, so the
+/// caches do not poll on timers of their own. Refreshes which fail are retried during the epoch.
+pub struct EpochNotifier {
+    epoch_cache: EpochCache,
+    caches: Vec<Arc<dyn EpochAwareCache>>,
+    epoch_sender: broadcast::Sender<Epoch>,
+    current_epoch: Option<u64>,
+    // caches which failed to refresh for the current epoch
+    pending: Vec<Arc<dyn EpochAwareCache>>,
+    last_attempt: Option<Instant>,
+}
+
+impl EpochNotifier {
+    pub fn new(epoch_cache: EpochCache) -> Self {
+        let (epoch_sender, _) = broadcast::channel(4);
+        Self {
+            epoch_cache,
+            caches: vec![],
+            epoch_sender,
+            current_epoch: None,
+            pending: vec![],
+            last_attempt: None,
+        }
+    }
+
+    pub fn with_cache(mut self, cache: Arc<dyn EpochAwareCache>) -> Self {
+        self.caches.push(cache);
+        self
+    }
+
+    /// the epoch of the first slot and every new epoch after it
+    pub fn subscribe(&self) -> EpochStream {
+        self.epoch_sender.subscribe()
+    }
+
+    pub async fn on_slot(&mut self, slot: u64) {
+        let epoch = self.epoch_cache.get_epoch_at_slot(slot);
+        if self.current_epoch != Some(epoch.epoch) {
+            info!("Entering epoch {} at slot {slot}", epoch.epoch);
+            CURRENT_EPOCH.set(epoch.epoch as i64);
+            self.current_epoch = Some(epoch.epoch);
+            // no receivers is fine
+            let _ = self.epoch_sender.send(epoch);
+            self.pending = self.caches.clone();
+        } else if self.pending.is_empty()
+            || self
+                .last_attempt
+                .is_some_and(|attempt| attempt.elapsed() < RETRY_INTERVAL)
+        {
+            return;
+        }
+
+        self.last_attempt = Some(Instant::now());
+        let mut failed = vec![];
+        for cache in std::mem::take(&mut self.pending) {
+            let result = cache.on_new_epoch(&epoch).await;
+            EPOCH_CACHE_REFRESHES
+                .with_label_values(&[cache.name(), if result.is_ok() { "ok" } else { "error" }])
+                .inc();
+            if let Err(err) = result {
+                warn!(
+                    "Cannot refresh {} for epoch {}: {err:?}",
+                    cache.name(),
+                    epoch.epoch
+                );
+                failed.push(cache);
+            }
+        }
+        self.pending = failed;
+    }
+
+    pub fn start(mut self, mut slot_notifier: SlotStream) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            loop {
+                let slot = match slot_notifier.recv().await {
+                    Ok(notification) => notification.processed_slot,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => bail!("slot stream closed"),
+                };
+                self.on_slot(slot).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestCache {
+        epochs: Mutex<Vec<u64>>,
+        fail: Mutex<bool>,
+    }
+
+    #[async_trait]
+    impl EpochAwareCache for TestCache {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+
+        async fn on_new_epoch(&self, epoch: &Epoch) -> anyhow::Result<()> {
+            self.epochs.lock().unwrap().push(epoch.epoch);
+            if *self.fail.lock().unwrap() {
+                bail!("refresh failed");
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_refreshes_caches_on_new_epoch() {
+        let cache = Arc::new(TestCache::default());
+        let mut notifier =
+            EpochNotifier::new(EpochCache::new_for_tests()).with_cache(cache.clone());
+        let mut epochs = notifier.subscribe();
+
+        block_on(notifier.on_slot(10));
+        block_on(notifier.on_slot(999));
+        block_on(notifier.on_slot(1000));
+        block_on(notifier.on_slot(1001));
+
+        assert_eq!(*cache.epochs.lock().unwrap(), vec![0, 1]);
+        assert_eq!(epochs.try_recv().unwrap().epoch, 0);
+        assert_eq!(epochs.try_recv().unwrap().epoch, 1);
+    }
+
+    #[test]
+    fn test_failed_refresh_is_retried() {
+        let cache = Arc::new(TestCache::default());
+        *cache.fail.lock().unwrap() = true;
+        let mut notifier =
+            EpochNotifier::new(EpochCache::new_for_tests()).with_cache(cache.clone());
+
+        block_on(notifier.on_slot(10));
+        // not retried before the retry interval
+        block_on(notifier.on_slot(11));
+        assert_eq!(*cache.epochs.lock().unwrap(), vec![0]);
+
+        *cache.fail.lock().unwrap() = false;
+        notifier.last_attempt = None;
+        block_on(notifier.on_slot(12));
+        block_on(notifier.on_slot(13));
+        assert_eq!(*cache.epochs.lock().unwrap(), vec![0, 0]);
+    }
+}
@@ -1,11 +1,15 @@
 pub mod commitment_utils;
 pub mod encoding;
+pub mod epoch_notifier;
 pub mod iterutils;
 pub mod keypair_loader;
+pub mod memory_budget;
 pub mod network_utils;
+pub mod request_id;
 pub mod solana_utils;
 pub mod stores;
 pub mod structures;
+pub mod time_utils;
 pub mod traits;
 pub mod types;
 
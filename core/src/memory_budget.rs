@@ -0,0 +1,246 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use log::{debug, warn};
+use prometheus::{
+    opts, register_int_counter, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+};
+
+use crate::AnyhowJoinHandle;
+
+lazy_static::lazy_static! {
+    static ref MEMORY_BUDGET_BYTES: IntGauge =
+    register_int_gauge!(opts!("literpc_memory_budget_bytes", "Global memory budget of the in-memory caches in bytes")).unwrap();
+    static ref MEMORY_USAGE_BYTES: IntGaugeVec =
+    register_int_gauge_vec!(opts!("literpc_memory_usage_bytes", "Estimated memory used by an in-memory cache in bytes"), &["consumer"]).unwrap();
+    static ref MEMORY_EVICTED_BYTES: IntCounterVec =
+    register_int_counter_vec!(opts!("literpc_memory_evicted_bytes", "Estimated bytes evicted from an in-memory cache to stay within the memory budget"), &["consumer"]).unwrap();
+    static ref MEMORY_BUDGET_EXCEEDED: IntCounter =
+    register_int_counter!(opts!("literpc_memory_budget_exceeded", "Number of checks which could not evict enough to get within the memory budget")).unwrap();
+}
+
+/// order in which consumers give up memory when the budget is exceeded
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EvictionPriority {
+    /// data which can be fetched again, evicted first
+    Low,
+    Medium,
+    /// data which degrades responses when evicted
+    High,
+    /// source of truth, only accounted so the other consumers make room for it
+    Never,
+}
+
+/// an in-memory cache accounted against the global memory budget
+#[async_trait]
+pub trait MemoryConsumer: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn eviction_priority(&self) -> EvictionPriority;
+
+    /// estimated heap memory in bytes
+    async fn memory_usage(&self) -> usize;
+
+    /// evicts the least valuable entries until about `bytes` are freed, returns the estimated bytes freed
+    async fn evict(&self, bytes: usize) -> usize;
+}
+
+/// Accounts the memory of the registered caches and evicts from the lowest priority consumers
+/// when their sum exceeds the budget, so lite-rpc sheds history instead of being OOM-killed.
+#[derive(Clone)]
+pub struct MemoryBudget {
+    // none only accounts the usage, nothing is evicted
+    budget_bytes: Option<usize>,
+    consumers: Vec<Arc<dyn MemoryConsumer>>,
+}
+
+impl MemoryBudget {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            budget_bytes: Some(budget_bytes),
+            consumers: vec![],
+        }
+    }
+
+    /// exports the memory usage of the consumers without evicting
+    pub fn unbounded() -> Self {
+        Self {
+            budget_bytes: None,
+            consumers: vec![],
+        }
+    }
+
+    pub fn budget_bytes(&self) -> Option<usize> {
+        self.budget_bytes
+    }
+
+    /// estimated memory usage per consumer, in registration order
+    pub async fn usages(&self) -> Vec<(&'static str, usize)> {
+        let mut usages = Vec::with_capacity(self.consumers.len());
+        for consumer in &self.consumers {
+            usages.push((consumer.name(), consumer.memory_usage().await));
+        }
+        usages
+    }
+
+    pub fn with_consumer(mut self, consumer: Arc<dyn MemoryConsumer>) -> Self {
+        self.consumers.push(consumer);
+        self
+    }
+
+    /// one accounting round, returns the estimated usage after eviction
+    pub async fn enforce(&self) -> usize {
+        let mut usages = Vec::with_capacity(self.consumers.len());
+        for consumer in &self.consumers {
+            let usage = consumer.memory_usage().await;
+            MEMORY_USAGE_BYTES
+                .with_label_values(&[consumer.name()])
+                .set(usage as i64);
+            usages.push((consumer, usage));
+        }
+        let mut total: usize = usages.iter().map(|(_, usage)| usage).sum();
+        let Some(budget_bytes) = self.budget_bytes else {
+            return total;
+        };
+        if total <= budget_bytes {
+            return total;
+        }
+
+        // stable sort, consumers of the same priority are evicted in registration order
+        usages.sort_by_key(|(consumer, _)| consumer.eviction_priority());
+        for (consumer, usage) in usages {
+            if total <= budget_bytes {
+                break;
+            }
+            if consumer.eviction_priority() == EvictionPriority::Never || usage == 0 {
+                continue;
+            }
+            let freed = consumer.evict(total - budget_bytes).await.min(usage);
+            debug!(
+                "evicted {freed} bytes from {} to stay within the memory budget",
+                consumer.name()
+            );
+            MEMORY_EVICTED_BYTES
+                .with_label_values(&[consumer.name()])
+                .inc_by(freed as u64);
+            MEMORY_USAGE_BYTES
+                .with_label_values(&[consumer.name()])
+                .set((usage - freed) as i64);
+            total -= freed;
+        }
+
+        if total > budget_bytes {
+            MEMORY_BUDGET_EXCEEDED.inc();
+            warn!(
+                "memory usage of {total} bytes exceeds the budget of {budget_bytes} bytes after eviction"
+            );
+        }
+        total
+    }
+
+    pub fn start(self, interval: Duration) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            if let Some(budget_bytes) = self.budget_bytes {
+                MEMORY_BUDGET_BYTES.set(budget_bytes as i64);
+            }
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                self.enforce().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    struct TestConsumer {
+        name: &'static str,
+        priority: EvictionPriority,
+        usage: Mutex<usize>,
+    }
+
+    impl TestConsumer {
+        fn new(name: &'static str, priority: EvictionPriority, usage: usize) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                priority,
+                usage: Mutex::new(usage),
+            })
+        }
+
+        fn usage(&self) -> usize {
+            *self.usage.lock().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl MemoryConsumer for TestConsumer {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn eviction_priority(&self) -> EvictionPriority {
+            self.priority
+        }
+
+        async fn memory_usage(&self) -> usize {
+            self.usage()
+        }
+
+        async fn evict(&self, bytes: usize) -> usize {
+            let mut usage = self.usage.lock().unwrap();
+            let freed = bytes.min(*usage);
+            *usage -= freed;
+            freed
+        }
+    }
+
+    #[test]
+    fn test_evicts_lowest_priority_first() {
+        let high = TestConsumer::new("test_high", EvictionPriority::High, 100);
+        let low = TestConsumer::new("test_low", EvictionPriority::Low, 50);
+        let pinned = TestConsumer::new("test_pinned", EvictionPriority::Never, 100);
+        let budget = MemoryBudget::new(180)
+            .with_consumer(high.clone())
+            .with_consumer(low.clone())
+            .with_consumer(pinned.clone());
+
+        assert_eq!(futures::executor::block_on(budget.enforce()), 180);
+        assert_eq!(low.usage(), 0);
+        assert_eq!(high.usage(), 80);
+        assert_eq!(pinned.usage(), 100);
+    }
+
+    #[test]
+    fn test_pinned_consumers_are_not_evicted() {
+        let low = TestConsumer::new("test_low", EvictionPriority::Low, 10);
+        let pinned = TestConsumer::new("test_pinned", EvictionPriority::Never, 100);
+        let budget = MemoryBudget::new(50)
+            .with_consumer(low.clone())
+            .with_consumer(pinned.clone());
+
+        assert_eq!(futures::executor::block_on(budget.enforce()), 100);
+        assert_eq!(low.usage(), 0);
+        assert_eq!(pinned.usage(), 100);
+    }
+
+    #[test]
+    fn test_unbounded_budget_only_accounts() {
+        let low = TestConsumer::new("test_low", EvictionPriority::Low, 100);
+        let budget = MemoryBudget::unbounded().with_consumer(low.clone());
+
+        assert_eq!(futures::executor::block_on(budget.enforce()), 100);
+        assert_eq!(low.usage(), 100);
+        assert_eq!(
+            futures::executor::block_on(budget.usages()),
+            vec![("test_low", 100)]
+        );
+    }
+}
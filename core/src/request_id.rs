@@ -0,0 +1,27 @@
+use std::future::Future;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+    static API_KEY_ID: String;
+}
+
+/// runs `future` with `request_id` available to everything it calls through `current_request_id`
+pub async fn with_request_id<F: Future>(request_id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(request_id, future).await
+}
+
+/// id of the rpc request being handled by the current task, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|request_id| request_id.clone()).ok()
+}
+
+/// runs `future` with the id of the api key the request was authenticated with available through
+/// `current_api_key_id`
+pub async fn with_api_key_id<F: Future>(api_key_id: String, future: F) -> F::Output {
+    API_KEY_ID.scope(api_key_id, future).await
+}
+
+/// id of the api key of the rpc request being handled by the current task, if any
+pub fn current_api_key_id() -> Option<String> {
+    API_KEY_ID.try_with(|api_key_id| api_key_id.clone()).ok()
+}
@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use prometheus::{opts, register_int_counter, register_int_gauge, IntCounter, IntGauge};
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::slot_history::Slot;
+
+use crate::memory_budget::{EvictionPriority, MemoryConsumer};
+use crate::structures::produced_block::ProducedBlock;
+
+lazy_static::lazy_static! {
+    static ref BLOCK_CACHE_HITS: IntCounter =
+    register_int_counter!(opts!("literpc_block_cache_hits", "Number of block lookups served from the block cache")).unwrap();
+    static ref BLOCK_CACHE_MISSES: IntCounter =
+    register_int_counter!(opts!("literpc_block_cache_misses", "Number of block lookups not found in the block cache")).unwrap();
+    static ref BLOCK_CACHE_EVICTIONS: IntCounter =
+    register_int_counter!(opts!("literpc_block_cache_evictions", "Number of blocks evicted from the block cache")).unwrap();
+    static ref BLOCK_CACHE_SIZE: IntGauge =
+    register_int_gauge!(opts!("literpc_block_cache_size", "Number of blocks in the block cache")).unwrap();
+}
+
+type BlockKey = (Slot, CommitmentLevel);
+
+struct BlockCacheInner {
+    // block and the access tick of its entry in lru
+    blocks: HashMap<BlockKey, (Arc<ProducedBlock>, u64)>,
+    // access tick -> key, the first entry is the least recently used block
+    lru: BTreeMap<u64, BlockKey>,
+    tick: u64,
+    // estimated size of the cached blocks
+    bytes: usize,
+}
+
+impl BlockCacheInner {
+    // removes the least recently used block, returns its estimated size
+    fn evict_lru(&mut self) -> Option<usize> {
+        let (_, evicted) = self.lru.pop_first()?;
+        let (block, _) = self.blocks.remove(&evicted)?;
+        let size = block.estimated_size();
+        self.bytes -= size;
+        BLOCK_CACHE_EVICTIONS.inc();
+        Some(size)
+    }
+}
+
+/// Bounded cache of recent blocks by slot and commitment, evicting the least recently used block.
+/// Blocks are shared as Arc so consumers do not need to keep their own copies.
+#[derive(Clone)]
+pub struct BlockCache {
+    inner: Arc<Mutex<BlockCacheInner>>,
+    // max number of (slot, commitment) entries
+    capacity: usize,
+}
+
+impl BlockCache {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "block cache capacity must be greater than 0");
+        Self {
+            inner: Arc::new(Mutex::new(BlockCacheInner {
+                blocks: HashMap::new(),
+                lru: BTreeMap::new(),
+                tick: 0,
+                bytes: 0,
+            })),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn insert(&self, block: ProducedBlock) -> Arc<ProducedBlock> {
+        let block = Arc::new(block);
+        let key = (block.slot, block.commitment_config.commitment);
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner.bytes += block.estimated_size();
+        if let Some((old_block, old_tick)) = inner.blocks.insert(key, (block.clone(), tick)) {
+            inner.lru.remove(&old_tick);
+            inner.bytes -= old_block.estimated_size();
+        }
+        inner.lru.insert(tick, key);
+
+        while inner.blocks.len() > self.capacity {
+            if inner.evict_lru().is_none() {
+                break;
+            }
+        }
+        BLOCK_CACHE_SIZE.set(inner.blocks.len() as i64);
+        block
+    }
+
+    /// returns the block of the slot with at least the requested commitment, preferring the highest one
+    pub fn get(
+        &self,
+        slot: Slot,
+        commitment_config: CommitmentConfig,
+    ) -> Option<Arc<ProducedBlock>> {
+        let levels: &[CommitmentLevel] = match commitment_config.commitment {
+            CommitmentLevel::Finalized => &[CommitmentLevel::Finalized],
+            CommitmentLevel::Confirmed => &[CommitmentLevel::Finalized, CommitmentLevel::Confirmed],
+            _ => &[
+                CommitmentLevel::Finalized,
+                CommitmentLevel::Confirmed,
+                CommitmentLevel::Processed,
+            ],
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        for level in levels {
+            let key = (slot, *level);
+            if let Some((block, old_tick)) = inner.blocks.get_mut(&key) {
+                let old_tick = std::mem::replace(old_tick, tick);
+                let block = block.clone();
+                inner.lru.remove(&old_tick);
+                inner.lru.insert(tick, key);
+                BLOCK_CACHE_HITS.inc();
+                return Some(block);
+            }
+        }
+        BLOCK_CACHE_MISSES.inc();
+        None
+    }
+}
+
+#[async_trait]
+impl MemoryConsumer for BlockCache {
+    fn name(&self) -> &'static str {
+        "block_cache"
+    }
+
+    // blocks can be fetched again from the block storage or the rpc node
+    fn eviction_priority(&self) -> EvictionPriority {
+        EvictionPriority::Low
+    }
+
+    async fn memory_usage(&self) -> usize {
+        self.inner.lock().unwrap().bytes
+    }
+
+    async fn evict(&self, bytes: usize) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let mut freed = 0;
+        while freed < bytes {
+            match inner.evict_lru() {
+                Some(size) => freed += size,
+                None => break,
+            }
+        }
+        BLOCK_CACHE_SIZE.set(inner.blocks.len() as i64);
+        freed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = BlockCache::new(2);
+        cache.insert(ProducedBlock::new_for_tests(
+            1,
+            CommitmentConfig::confirmed(),
+        ));
+        cache.insert(ProducedBlock::new_for_tests(
+            2,
+            CommitmentConfig::confirmed(),
+        ));
+        assert!(cache.get(1, CommitmentConfig::confirmed()).is_some());
+        cache.insert(ProducedBlock::new_for_tests(
+            3,
+            CommitmentConfig::confirmed(),
+        ));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(1, CommitmentConfig::confirmed()).is_some());
+        assert!(cache.get(2, CommitmentConfig::confirmed()).is_none());
+        assert!(cache.get(3, CommitmentConfig::confirmed()).is_some());
+    }
+
+    #[test]
+    fn test_evict_frees_least_recently_used() {
+        let cache = BlockCache::new(10);
+        for slot in 1..=3 {
+            cache.insert(ProducedBlock::new_for_tests(
+                slot,
+                CommitmentConfig::confirmed(),
+            ));
+        }
+        assert!(cache.get(1, CommitmentConfig::confirmed()).is_some());
+        let block_size =
+            ProducedBlock::new_for_tests(1, CommitmentConfig::confirmed()).estimated_size();
+        assert_eq!(
+            futures::executor::block_on(cache.memory_usage()),
+            3 * block_size
+        );
+
+        let freed = futures::executor::block_on(cache.evict(block_size + 1));
+        assert_eq!(freed, 2 * block_size);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(1, CommitmentConfig::confirmed()).is_some());
+        assert_eq!(
+            futures::executor::block_on(cache.memory_usage()),
+            block_size
+        );
+    }
+
+    #[test]
+    fn test_get_prefers_higher_commitment() {
+        let cache = BlockCache::new(10);
+        cache.insert(ProducedBlock::new_for_tests(
+            1,
+            CommitmentConfig::processed(),
+        ));
+        cache.insert(ProducedBlock::new_for_tests(
+            1,
+            CommitmentConfig::finalized(),
+        ));
+
+        let found = cache.get(1, CommitmentConfig::processed()).unwrap();
+        assert_eq!(found.commitment_config, CommitmentConfig::finalized());
+        assert!(cache.get(2, CommitmentConfig::processed()).is_none());
+
+        cache.insert(ProducedBlock::new_for_tests(
+            2,
+            CommitmentConfig::processed(),
+        ));
+        assert!(cache.get(2, CommitmentConfig::confirmed()).is_none());
+    }
+}
@@ -2,19 +2,21 @@ use dashmap::DashMap;
 use log::info;
 
 use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::hash::Hash;
 use solana_sdk::{clock::MAX_RECENT_BLOCKHASHES, slot_history::Slot};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::watch;
 
 use crate::structures::produced_block::ProducedBlock;
+use crate::types::LatestBlock;
 
 #[derive(Clone, Debug)]
 pub struct BlockInformation {
     pub slot: u64,
     pub block_height: u64,
-    pub last_valid_blockheight: u64,
     pub cleanup_slot: Slot,
-    pub blockhash: String,
+    pub blockhash: Hash,
     pub commitment_config: CommitmentConfig,
     pub block_time: u64,
 }
@@ -24,9 +26,8 @@ impl BlockInformation {
         BlockInformation {
             slot: block.slot,
             block_height: block.block_height,
-            last_valid_blockheight: block.block_height + MAX_RECENT_BLOCKHASHES as u64,
             cleanup_slot: block.block_height + 1000,
-            blockhash: block.blockhash.clone(),
+            blockhash: block.blockhash,
             commitment_config: block.commitment_config,
             block_time: block.block_time,
         }
@@ -37,78 +38,72 @@ impl BlockInformation {
 /// This structure will store block information till where finalized block is still in range of last valid block hash.
 /// So 300 blocks for last valid blockhash and 32 slots between confirmed and finalized.
 /// So it should not store more than 400 blocks information.
+/// The validity of blockhashes is tracked by the blockhash cache only.
 #[derive(Clone)]
 pub struct BlockInformationStore {
-    // maps Block Hash -> Block information
-    blocks: Arc<DashMap<String, BlockInformation>>,
-    latest_confirmed_block: Arc<RwLock<BlockInformation>>,
-    latest_finalized_block: Arc<RwLock<BlockInformation>>,
+    // maps Slot -> Block information
+    blocks: Arc<DashMap<Slot, BlockInformation>>,
+    latest_confirmed_block: Arc<watch::Sender<BlockInformation>>,
+    latest_finalized_block: Arc<watch::Sender<BlockInformation>>,
 }
 
 impl BlockInformationStore {
     pub fn new(latest_finalized_block: BlockInformation) -> Self {
         let blocks = Arc::new(DashMap::new());
 
-        blocks.insert(
-            latest_finalized_block.blockhash.clone(),
-            latest_finalized_block.clone(),
-        );
+        blocks.insert(latest_finalized_block.slot, latest_finalized_block.clone());
 
         Self {
-            latest_confirmed_block: Arc::new(RwLock::new(latest_finalized_block.clone())),
-            latest_finalized_block: Arc::new(RwLock::new(latest_finalized_block)),
+            latest_confirmed_block: Arc::new(watch::channel(latest_finalized_block.clone()).0),
+            latest_finalized_block: Arc::new(watch::channel(latest_finalized_block).0),
             blocks,
         }
     }
 
-    pub fn get_block_info(&self, blockhash: &str) -> Option<BlockInformation> {
-        let info = self.blocks.get(blockhash)?;
-
-        Some(info.value().to_owned())
-    }
-
-    fn get_latest_block_arc(
+    fn get_latest_block_sender(
         &self,
         commitment_config: CommitmentConfig,
-    ) -> Arc<RwLock<BlockInformation>> {
+    ) -> &watch::Sender<BlockInformation> {
         if commitment_config.is_finalized() {
-            self.latest_finalized_block.clone()
+            &self.latest_finalized_block
         } else {
-            self.latest_confirmed_block.clone()
+            &self.latest_confirmed_block
         }
     }
 
-    pub async fn get_latest_blockhash(&self, commitment_config: CommitmentConfig) -> String {
-        self.get_latest_block_arc(commitment_config)
-            .read()
-            .await
+    pub async fn get_latest_blockhash(&self, commitment_config: CommitmentConfig) -> Hash {
+        self.get_latest_block_sender(commitment_config)
+            .borrow()
             .blockhash
-            .clone()
     }
 
     pub async fn get_latest_block_info(
         &self,
         commitment_config: CommitmentConfig,
     ) -> BlockInformation {
-        self.get_latest_block_arc(commitment_config)
-            .read()
-            .await
+        self.get_latest_block_sender(commitment_config)
+            .borrow()
             .clone()
     }
 
     pub async fn get_latest_block(&self, commitment_config: CommitmentConfig) -> BlockInformation {
-        self.get_latest_block_arc(commitment_config)
-            .read()
-            .await
+        self.get_latest_block_sender(commitment_config)
+            .borrow()
             .clone()
     }
 
+    /// latest block (and blockhash) of the commitment, processed is served as confirmed;
+    /// the receiver is notified on every newer block but only keeps the newest one
+    pub fn subscribe_latest_block(&self, commitment_config: CommitmentConfig) -> LatestBlock {
+        self.get_latest_block_sender(commitment_config).subscribe()
+    }
+
     pub async fn add_block(&self, block_info: BlockInformation) -> bool {
         // save slot copy to avoid borrow issues
         let slot = block_info.slot;
         let commitment_config = block_info.commitment_config;
         // check if the block has already been added with higher commitment level
-        match self.blocks.get_mut(&block_info.blockhash) {
+        match self.blocks.get_mut(&slot) {
             Some(mut prev_block_info) => {
                 let should_update = match prev_block_info.commitment_config.commitment {
                     CommitmentLevel::Finalized => false, // should never update blocks of finalized commitment
@@ -126,16 +121,20 @@ impl BlockInformationStore {
                 *prev_block_info = block_info.clone();
             }
             None => {
-                self.blocks
-                    .insert(block_info.blockhash.clone(), block_info.clone());
+                self.blocks.insert(slot, block_info.clone());
             }
         }
 
         // update latest block
-        let latest_block = self.get_latest_block_arc(commitment_config);
-        if slot > latest_block.read().await.slot {
-            *latest_block.write().await = block_info;
-        }
+        self.get_latest_block_sender(commitment_config)
+            .send_if_modified(|latest_block| {
+                if slot > latest_block.slot {
+                    *latest_block = block_info;
+                    true
+                } else {
+                    false
+                }
+            });
         true
     }
 
@@ -144,8 +143,10 @@ impl BlockInformationStore {
             .get_latest_block_info(CommitmentConfig::finalized())
             .await;
         let before_length = self.blocks.len();
-        self.blocks
-            .retain(|_, v| v.last_valid_blockheight >= finalized_block_information.block_height);
+        self.blocks.retain(|_, v| {
+            v.block_height + MAX_RECENT_BLOCKHASHES as u64
+                >= finalized_block_information.block_height
+        });
 
         info!(
             "Cleaned {} block info",
@@ -157,29 +158,21 @@ impl BlockInformationStore {
         self.blocks.len()
     }
 
-    pub async fn is_blockhash_valid(
-        &self,
-        blockhash: &String,
-        commitment_config: CommitmentConfig,
-    ) -> (bool, Slot) {
-        let latest_block = self.get_latest_block(commitment_config).await;
-        match self.blocks.get(blockhash) {
-            Some(block_information) => (
-                latest_block.block_height <= block_information.last_valid_blockheight,
-                latest_block.slot,
-            ),
-            None => (false, latest_block.slot),
+    /// number of blocks, lowest and highest slot per commitment level, for diagnostics
+    pub fn slot_range_by_commitment(&self) -> HashMap<CommitmentLevel, (usize, Slot, Slot)> {
+        let mut ranges: HashMap<CommitmentLevel, (usize, Slot, Slot)> = HashMap::new();
+        for block_info in self.blocks.iter() {
+            let (count, min_slot, max_slot) = ranges
+                .entry(block_info.commitment_config.commitment)
+                .or_insert((0, Slot::MAX, 0));
+            *count += 1;
+            *min_slot = (*min_slot).min(block_info.slot);
+            *max_slot = (*max_slot).max(block_info.slot);
         }
+        ranges
     }
 
     pub fn get_block_info_by_slot(&self, slot: u64) -> Option<BlockInformation> {
-        self.blocks.iter().find_map(|iter| {
-            // should be fine for now, may be could be optimized in future if necessary
-            if iter.slot == slot {
-                Some(iter.value().clone())
-            } else {
-                None
-            }
-        })
+        self.blocks.get(&slot).map(|info| info.value().clone())
     }
 }
@@ -0,0 +1,201 @@
+use dashmap::DashMap;
+use solana_sdk::clock::{MAX_PROCESSING_AGE, MAX_RECENT_BLOCKHASHES};
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::hash::Hash;
+use solana_sdk::slot_history::Slot;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::structures::produced_block::ProducedBlock;
+
+/// transactions with a blockhash are accepted by the leaders until this many blocks were produced on top
+/// of its block, the same as the last valid block height returned by solana-rpc
+pub const BLOCKHASH_VALIDITY: u64 = MAX_PROCESSING_AGE as u64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockhashInfo {
+    pub slot: Slot,
+    pub block_height: u64,
+    pub last_valid_block_height: u64,
+    pub commitment_config: CommitmentConfig,
+}
+
+impl BlockhashInfo {
+    pub fn from_block(block: &ProducedBlock) -> Self {
+        Self {
+            slot: block.slot,
+            block_height: block.block_height,
+            last_valid_block_height: block.block_height + BLOCKHASH_VALIDITY,
+            commitment_config: block.commitment_config,
+        }
+    }
+}
+
+/// Blockhashes of the recent blocks, fed by the block streams. The blockhash rpc methods, the
+/// validation of sent transactions and the expiry of their retries all read it, so they agree on
+/// which blockhashes are valid.
+#[derive(Clone, Default)]
+pub struct BlockhashCache {
+    blockhashes: Arc<DashMap<Hash, BlockhashInfo>>,
+    // processed blocks count as confirmed like in the block information store
+    confirmed_block_height: Arc<AtomicU64>,
+    finalized_block_height: Arc<AtomicU64>,
+}
+
+impl BlockhashCache {
+    /// keeps the highest commitment seen for the blockhash
+    pub fn add_block(&self, block: &ProducedBlock) {
+        let info = BlockhashInfo::from_block(block);
+        self.blockhashes
+            .entry(block.blockhash)
+            .and_modify(|known| {
+                if commitment_rank(info.commitment_config)
+                    > commitment_rank(known.commitment_config)
+                {
+                    *known = info;
+                }
+            })
+            .or_insert(info);
+        self.latest_block_height_of(block.commitment_config)
+            .fetch_max(block.block_height, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, blockhash: &Hash) -> Option<BlockhashInfo> {
+        self.blockhashes.get(blockhash).map(|info| *info)
+    }
+
+    /// last valid block height of the blockhash of a block, also for blocks which were not added yet
+    pub fn last_valid_block_height(&self, blockhash: &Hash, block_height: u64) -> u64 {
+        self.get(blockhash)
+            .map_or(block_height + BLOCKHASH_VALIDITY, |info| {
+                info.last_valid_block_height
+            })
+    }
+
+    pub fn latest_block_height(&self, commitment_config: CommitmentConfig) -> u64 {
+        self.latest_block_height_of(commitment_config)
+            .load(Ordering::Relaxed)
+    }
+
+    /// unknown blockhashes are not valid
+    pub fn is_valid(&self, blockhash: &Hash, commitment_config: CommitmentConfig) -> bool {
+        self.get(blockhash).is_some_and(|info| {
+            self.latest_block_height(commitment_config) <= info.last_valid_block_height
+        })
+    }
+
+    /// a transaction cannot land anymore once the newest block is past its last valid block height
+    pub fn is_expired(&self, last_valid_block_height: u64) -> bool {
+        self.latest_block_height(CommitmentConfig::confirmed()) > last_valid_block_height
+    }
+
+    /// forgets the blockhashes which expired before the finalized block, a few more are kept for
+    /// transactions which are still waiting for their status
+    pub fn clean(&self) {
+        let finalized_block_height = self.latest_block_height(CommitmentConfig::finalized());
+        self.blockhashes.retain(|_, info| {
+            info.block_height + MAX_RECENT_BLOCKHASHES as u64 >= finalized_block_height
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.blockhashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blockhashes.is_empty()
+    }
+
+    fn latest_block_height_of(&self, commitment_config: CommitmentConfig) -> &AtomicU64 {
+        if commitment_config.is_finalized() {
+            &self.finalized_block_height
+        } else {
+            &self.confirmed_block_height
+        }
+    }
+}
+
+fn commitment_rank(commitment_config: CommitmentConfig) -> u8 {
+    match commitment_config.commitment {
+        CommitmentLevel::Finalized => 2,
+        CommitmentLevel::Confirmed => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(block_height: u64, commitment_config: CommitmentConfig) -> ProducedBlock {
+        ProducedBlock {
+            block_height,
+            ..ProducedBlock::new_for_tests(block_height + 10, commitment_config)
+        }
+    }
+
+    #[test]
+    fn blockhash_expires_after_validity() {
+        let cache = BlockhashCache::default();
+        let first = block(100, CommitmentConfig::confirmed());
+        cache.add_block(&first);
+        assert!(cache.is_valid(&first.blockhash, CommitmentConfig::confirmed()));
+        assert!(!cache.is_valid(&Hash::new_unique(), CommitmentConfig::confirmed()));
+        assert!(!cache.is_expired(100 + BLOCKHASH_VALIDITY));
+
+        cache.add_block(&block(
+            101 + BLOCKHASH_VALIDITY,
+            CommitmentConfig::processed(),
+        ));
+        assert!(!cache.is_valid(&first.blockhash, CommitmentConfig::confirmed()));
+        assert!(cache.is_expired(100 + BLOCKHASH_VALIDITY));
+        // the finalized blocks are behind
+        assert!(cache.is_valid(&first.blockhash, CommitmentConfig::finalized()));
+    }
+
+    #[test]
+    fn keeps_highest_commitment() {
+        let cache = BlockhashCache::default();
+        let finalized = block(100, CommitmentConfig::finalized());
+        cache.add_block(&finalized);
+        cache.add_block(&ProducedBlock {
+            commitment_config: CommitmentConfig::confirmed(),
+            ..finalized.clone()
+        });
+        assert_eq!(
+            cache.get(&finalized.blockhash).unwrap().commitment_config,
+            CommitmentConfig::finalized()
+        );
+    }
+
+    #[test]
+    fn clean_keeps_recent_blockhashes() {
+        let cache = BlockhashCache::default();
+        let old = block(100, CommitmentConfig::finalized());
+        let recent = block(200, CommitmentConfig::finalized());
+        cache.add_block(&old);
+        cache.add_block(&recent);
+        cache.add_block(&block(
+            101 + MAX_RECENT_BLOCKHASHES as u64,
+            CommitmentConfig::finalized(),
+        ));
+        cache.clean();
+        assert!(cache.get(&old.blockhash).is_none());
+        assert!(cache.get(&recent.blockhash).is_some());
+    }
+
+    #[test]
+    fn last_valid_block_height_of_unknown_blockhash() {
+        let cache = BlockhashCache::default();
+        let known = block(100, CommitmentConfig::confirmed());
+        cache.add_block(&known);
+        assert_eq!(
+            cache.last_valid_block_height(&known.blockhash, known.block_height),
+            100 + BLOCKHASH_VALIDITY
+        );
+        assert_eq!(
+            cache.last_valid_block_height(&Hash::new_unique(), 120),
+            120 + BLOCKHASH_VALIDITY
+        );
+    }
+}
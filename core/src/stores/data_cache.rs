@@ -4,51 +4,73 @@ use solana_sdk::hash::Hash;
 use solana_sdk::slot_history::Slot;
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
 use std::sync::{atomic::AtomicU64, Arc};
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 
 use crate::{
     stores::{
-        block_information_store::BlockInformationStore, cluster_info_store::ClusterInfo,
-        subscription_store::SubscriptionStore, tx_store::TxStore,
+        block_cache::BlockCache, block_information_store::BlockInformationStore,
+        blockhash_cache::BlockhashCache, cluster_info_store::ClusterInfo,
+        signature_status_cache::SignatureStatusCache, subscription_store::SubscriptionStore,
+        tx_lifecycle_store::TxLifecycleStore, tx_store::TxStore,
     },
     structures::{
         epoch::{Epoch, EpochCache},
+        heartbeat::LoopHeartbeats,
         identity_stakes::IdentityStakes,
         slot_notification::{AtomicSlot, SlotNotification},
         transaction_sent_info::SentTransactionInfo,
     },
+    types::LatestSlot,
 };
 
 use super::block_information_store::BlockInformation;
 pub type TxSubKey = (String, CommitmentConfig);
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct SlotCache {
     current_slot: AtomicSlot,
     estimated_slot: AtomicSlot,
+    reference_slot: AtomicSlot,
+    latest: Arc<watch::Sender<SlotNotification>>,
 }
 
 /// The central data store for all data from the cluster.
 #[derive(Clone)]
 pub struct DataCache {
     pub block_information_store: BlockInformationStore,
+    pub blockhash_cache: BlockhashCache,
+    pub block_cache: BlockCache,
     pub txs: TxStore,
+    /// serialized statuses of the transactions in the tx store
+    pub signature_statuses: SignatureStatusCache,
+    /// lifecycles of the transactions sent through lite-rpc
+    pub tx_lifecycles: TxLifecycleStore,
     pub tx_subs: SubscriptionStore,
     pub slot_cache: SlotCache,
     pub identity_stakes: IdentityStakes,
     pub cluster_info: ClusterInfo,
     pub epoch_data: EpochCache,
     pub leader_schedule: Arc<RwLock<CalculatedSchedule>>,
+    /// progress of the block and transaction loops, behind the liveness endpoint
+    pub heartbeats: LoopHeartbeats,
 }
 
 impl DataCache {
-    pub async fn clean(&self, ttl_duration: std::time::Duration) {
+    pub async fn clean(
+        &self,
+        ttl_duration: std::time::Duration,
+        tx_status_retention: std::time::Duration,
+    ) {
         let block_info = self
             .block_information_store
             .get_latest_block_info(CommitmentConfig::finalized())
             .await;
         self.block_information_store.clean().await;
-        self.txs.clean(block_info.block_height);
+        self.blockhash_cache.clean();
+        self.txs.clean(block_info.block_height, tx_status_retention);
+        self.signature_statuses.clean(&self.txs);
+        self.tx_lifecycles
+            .clean(block_info.block_height, tx_status_retention);
 
         self.tx_subs.clean(ttl_duration);
     }
@@ -75,13 +97,14 @@ impl DataCache {
         Self {
             block_information_store: BlockInformationStore::new(BlockInformation {
                 block_height: 0,
-                blockhash: Hash::new_unique().to_string(),
+                blockhash: Hash::new_unique(),
                 cleanup_slot: 1000,
                 commitment_config: CommitmentConfig::finalized(),
-                last_valid_blockheight: 300,
                 slot: 0,
                 block_time: 0,
             }),
+            blockhash_cache: BlockhashCache::default(),
+            block_cache: BlockCache::new(100),
             cluster_info: ClusterInfo::default(),
             identity_stakes: IdentityStakes::new(Pubkey::new_unique()),
             slot_cache: SlotCache::new(0),
@@ -89,17 +112,32 @@ impl DataCache {
             txs: TxStore {
                 store: Arc::new(DashMap::new()),
             },
+            signature_statuses: SignatureStatusCache::default(),
+            tx_lifecycles: TxLifecycleStore::default(),
             epoch_data: EpochCache::new_for_tests(),
             leader_schedule: Arc::new(RwLock::new(CalculatedSchedule::default())),
+            heartbeats: LoopHeartbeats::default(),
         }
     }
 }
 
+impl Default for SlotCache {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
 impl SlotCache {
     pub fn new(slot: Slot) -> Self {
+        let (latest, _) = watch::channel(SlotNotification {
+            processed_slot: slot,
+            estimated_processed_slot: slot,
+        });
         Self {
             current_slot: Arc::new(AtomicU64::new(slot)),
             estimated_slot: Arc::new(AtomicU64::new(slot)),
+            reference_slot: Arc::new(AtomicU64::new(0)),
+            latest: Arc::new(latest),
         }
     }
     pub fn get_current_slot(&self) -> Slot {
@@ -111,6 +149,17 @@ impl SlotCache {
             .load(std::sync::atomic::Ordering::Relaxed)
     }
 
+    /// newest slot of the reference rpc node, 0 if none is compared against
+    pub fn get_reference_slot(&self) -> Slot {
+        self.reference_slot
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    pub fn update_reference_slot(&self, slot: Slot) {
+        self.reference_slot
+            .fetch_max(slot, std::sync::atomic::Ordering::Relaxed);
+    }
+
     pub fn update(&self, slot_notification: SlotNotification) {
         self.current_slot.store(
             slot_notification.processed_slot,
@@ -120,5 +169,11 @@ impl SlotCache {
             slot_notification.estimated_processed_slot,
             std::sync::atomic::Ordering::Relaxed,
         );
+        self.latest.send_replace(slot_notification);
+    }
+
+    /// latest slot notification, the receiver is notified on every update but only keeps the newest one
+    pub fn subscribe(&self) -> LatestSlot {
+        self.latest.subscribe()
     }
 }
@@ -1,7 +1,11 @@
 // this mod will contain all the different stores that are used by lite-rpc
 
+pub mod block_cache;
 pub mod block_information_store;
+pub mod blockhash_cache;
 pub mod cluster_info_store;
 pub mod data_cache;
+pub mod signature_status_cache;
 pub mod subscription_store;
+pub mod tx_lifecycle_store;
 pub mod tx_store;
@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use dashmap::DashMap;
+use serde_json::value::RawValue;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::TransactionStatus;
+use std::sync::Arc;
+
+use crate::memory_budget::{EvictionPriority, MemoryConsumer};
+use crate::stores::tx_store::TxStore;
+
+// estimated size of an entry without its json: key and value and the hash table overhead
+const STATUS_ENTRY_SIZE: usize =
+    std::mem::size_of::<Signature>() + std::mem::size_of::<CachedStatus>() + 16;
+
+struct CachedStatus {
+    status: TransactionStatus,
+    json: Box<RawValue>,
+}
+
+/// Serialized statuses of getSignatureStatuses, kept as long as their transactions are in the
+/// tx store.
+#[derive(Clone, Default)]
+pub struct SignatureStatusCache {
+    statuses: Arc<DashMap<Signature, CachedStatus>>,
+}
+
+impl SignatureStatusCache {
+    /// the serialized status, serialized again if the status changed since it was cached
+    pub fn get_or_serialize(
+        &self,
+        signature: &Signature,
+        status: TransactionStatus,
+        serialize: impl FnOnce(&TransactionStatus) -> Box<RawValue>,
+    ) -> Box<RawValue> {
+        if let Some(cached) = self.statuses.get(signature) {
+            if cached.status == status {
+                return cached.json.clone();
+            }
+        }
+        let json = serialize(&status);
+        self.statuses.insert(
+            *signature,
+            CachedStatus {
+                status,
+                json: json.clone(),
+            },
+        );
+        json
+    }
+
+    /// removes the statuses of the transactions which left the tx store
+    pub fn clean(&self, txs: &TxStore) {
+        self.statuses
+            .retain(|signature, _| txs.contains_key(signature));
+    }
+
+    pub fn len(&self) -> usize {
+        self.statuses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statuses.is_empty()
+    }
+}
+
+#[async_trait]
+impl MemoryConsumer for SignatureStatusCache {
+    fn name(&self) -> &'static str {
+        "signature_status_cache"
+    }
+
+    // the statuses are serialized again on the next request
+    fn eviction_priority(&self) -> EvictionPriority {
+        EvictionPriority::Low
+    }
+
+    async fn memory_usage(&self) -> usize {
+        self.statuses
+            .iter()
+            .map(|entry| STATUS_ENTRY_SIZE + entry.json.get().len())
+            .sum()
+    }
+
+    async fn evict(&self, bytes: usize) -> usize {
+        let mut freed = 0;
+        self.statuses.retain(|_, cached| {
+            if freed >= bytes {
+                return true;
+            }
+            freed += STATUS_ENTRY_SIZE + cached.json.get().len();
+            false
+        });
+        freed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores::tx_store::TxProps;
+
+    fn status(slot: u64) -> TransactionStatus {
+        TransactionStatus {
+            slot,
+            confirmations: None,
+            status: Ok(()),
+            err: None,
+            confirmation_status: None,
+        }
+    }
+
+    fn serialize(status: &TransactionStatus) -> Box<RawValue> {
+        serde_json::value::to_raw_value(status).unwrap()
+    }
+
+    #[test]
+    fn statuses_are_serialized_once_and_cleaned_with_the_tx_store() {
+        let txs = TxStore {
+            store: Arc::new(DashMap::new()),
+        };
+        let cache = SignatureStatusCache::default();
+        let kept = Signature::new_unique();
+        let removed = Signature::new_unique();
+        txs.insert(
+            kept,
+            TxProps {
+                status: Some(status(1)),
+                last_valid_blockheight: 100,
+                sent_by_lite_rpc: false,
+                finalized_at: None,
+            },
+        );
+
+        let json = cache.get_or_serialize(&kept, status(1), serialize);
+        let cached = cache.get_or_serialize(&kept, status(1), |_| panic!("serialized again"));
+        assert_eq!(json.get(), cached.get());
+        // a new status is serialized again
+        let json = cache.get_or_serialize(&kept, status(2), serialize);
+        assert!(json.get().contains("\"slot\":2"));
+        cache.get_or_serialize(&removed, status(1), serialize);
+        assert_eq!(cache.len(), 2);
+
+        cache.clean(&txs);
+        assert_eq!(cache.len(), 1);
+        assert!(futures::executor::block_on(cache.memory_usage()) > STATUS_ENTRY_SIZE);
+        assert!(futures::executor::block_on(cache.evict(1)) > 0);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,633 @@
+use async_trait::async_trait;
+use dashmap::DashMap;
+use prometheus::{
+    histogram_opts, opts, register_histogram, register_int_counter_vec, register_int_gauge,
+    Histogram, IntCounterVec, IntGauge,
+};
+use serde::Serialize;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, slot_history::Slot};
+use solana_transaction_status::TransactionConfirmationStatus;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::memory_budget::{EvictionPriority, MemoryConsumer};
+use crate::time_utils::{to_unix_timestamp_ms, unix_timestamp_ms};
+
+lazy_static::lazy_static! {
+    static ref TX_LIFECYCLE_TRANSITIONS: IntCounterVec =
+    register_int_counter_vec!(opts!("literpc_tx_lifecycle_transitions", "Number of transactions entering a lifecycle state"), &["state"]).unwrap();
+
+    static ref TX_LIFECYCLE_TRACKED: IntGauge =
+    register_int_gauge!(opts!("literpc_tx_lifecycle_tracked", "Number of transactions with a tracked lifecycle")).unwrap();
+
+    static ref TX_LIFECYCLE_TIME_TO_LAND: Histogram = register_histogram!(histogram_opts!(
+        "literpc_tx_lifecycle_time_to_land",
+        "Seconds from receiving a transaction until it was first seen in a block",
+        vec![0.2, 0.4, 0.8, 1.2, 1.6, 2.4, 3.2, 4.8, 6.4, 9.6, 12.8, 25.6, 51.2, 102.4]
+    ))
+    .unwrap();
+
+    static ref TX_LIFECYCLE_ATTEMPTS_TO_LAND: Histogram = register_histogram!(histogram_opts!(
+        "literpc_tx_lifecycle_attempts_to_land",
+        "Forward attempts of a transaction until it was first seen in a block",
+        vec![1.0, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0, 24.0, 32.0, 48.0, 64.0]
+    ))
+    .unwrap();
+}
+
+// forward attempts and failures kept per transaction, the replays of a transaction which does not
+// land would grow its lifecycle until the blockhash expires otherwise
+const MAX_RETRY_EVENTS: usize = 8;
+
+/// state of a transaction sent through lite-rpc, in the order they are passed
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "state")]
+pub enum TxLifecycleState {
+    /// the transaction was submitted
+    Received,
+    /// the transaction passed the sanitization and the signature verification
+    Sanitized,
+    /// the transaction was queued for the tx sender
+    Queued,
+    /// the transaction was handed to the connections of the leaders, the first attempt is 1
+    #[serde(rename_all = "camelCase")]
+    Forwarded {
+        leaders: Vec<String>,
+        attempt: usize,
+    },
+    /// handing the transaction to the leader connections failed
+    #[serde(rename_all = "camelCase")]
+    ForwardFailed { attempt: usize, error: String },
+    /// the transaction is in a block of the given commitment, recorded once per commitment
+    #[serde(rename_all = "camelCase")]
+    Landed {
+        slot: Slot,
+        confirmation_status: TransactionConfirmationStatus,
+        failed: bool,
+    },
+    /// the blockhash of the transaction expired before it was finalized
+    Expired,
+    /// the transaction was not forwarded
+    Dropped { reason: String },
+    /// the transaction passed all checks but is not forwarded in dry-run mode, with the leaders it
+    /// would have been forwarded to and its estimated fee
+    #[serde(rename_all = "camelCase")]
+    DryRun {
+        leaders: Vec<String>,
+        fee_lamports: u64,
+    },
+}
+
+impl TxLifecycleState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TxLifecycleState::Received => "received",
+            TxLifecycleState::Sanitized => "sanitized",
+            TxLifecycleState::Queued => "queued",
+            TxLifecycleState::Forwarded { .. } => "forwarded",
+            TxLifecycleState::ForwardFailed { .. } => "forward_failed",
+            TxLifecycleState::Landed { .. } => "landed",
+            TxLifecycleState::Expired => "expired",
+            TxLifecycleState::Dropped { .. } => "dropped",
+            TxLifecycleState::DryRun { .. } => "dry_run",
+        }
+    }
+
+    /// a forward attempt or its failure, repeated by the replays of the transaction
+    fn is_retry(&self) -> bool {
+        matches!(
+            self,
+            TxLifecycleState::Forwarded { .. } | TxLifecycleState::ForwardFailed { .. }
+        )
+    }
+
+    // estimated heap memory of the strings of the state
+    fn heap_size(&self) -> usize {
+        match self {
+            TxLifecycleState::Forwarded { leaders, .. }
+            | TxLifecycleState::DryRun { leaders, .. } => {
+                leaders.capacity() * std::mem::size_of::<String>()
+                    + leaders.iter().map(String::capacity).sum::<usize>()
+            }
+            TxLifecycleState::ForwardFailed { error, .. } => error.capacity(),
+            TxLifecycleState::Dropped { reason } => reason.capacity(),
+            _ => 0,
+        }
+    }
+
+    /// no state follows a finalized landing, an expiry, a drop or a dry run
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            TxLifecycleState::Landed {
+                confirmation_status,
+                ..
+            } => *confirmation_status == TransactionConfirmationStatus::Finalized,
+            TxLifecycleState::Expired
+            | TxLifecycleState::Dropped { .. }
+            | TxLifecycleState::DryRun { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxLifecycleEvent {
+    #[serde(flatten)]
+    pub state: TxLifecycleState,
+    /// milliseconds since the unix epoch
+    pub timestamp: u64,
+}
+
+/// the states a transaction passed, answered by `getTransactionLifecycle`; of the forward attempts
+/// and failures the first and the latest are kept
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxLifecycle {
+    /// 0 until the blockhash of the transaction was looked up
+    pub last_valid_block_height: u64,
+    pub events: Vec<TxLifecycleEvent>,
+    #[serde(skip)]
+    received_at: Instant,
+    // when the lifecycle reached a terminal state, kept for the retention from then on
+    #[serde(skip)]
+    terminal_at: Option<Instant>,
+}
+
+impl TxLifecycle {
+    pub fn state(&self) -> Option<&TxLifecycleState> {
+        self.events.last().map(|event| &event.state)
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        self.terminal_at.is_some()
+    }
+
+    fn was_forwarded(&self) -> bool {
+        self.events
+            .iter()
+            .any(|event| matches!(event.state, TxLifecycleState::Forwarded { .. }))
+    }
+
+    fn push(&mut self, state: TxLifecycleState, timestamp: u64) {
+        TX_LIFECYCLE_TRANSITIONS
+            .with_label_values(&[state.as_str()])
+            .inc();
+        if state.is_terminal() {
+            self.terminal_at = Some(Instant::now());
+        }
+        if state.is_retry() {
+            let mut retries = self
+                .events
+                .iter()
+                .enumerate()
+                .filter(|(_, event)| event.state.is_retry())
+                .map(|(index, _)| index);
+            // the second retry makes room, so the first and the latest retries are kept
+            if let Some(second) = retries.nth(1) {
+                if retries.count() + 2 >= MAX_RETRY_EVENTS {
+                    self.events.remove(second);
+                }
+            }
+        }
+        self.events.push(TxLifecycleEvent { state, timestamp });
+    }
+
+    // estimated memory of the entry in the store
+    fn size(&self) -> usize {
+        std::mem::size_of::<(Signature, TxLifecycle)>()
+            + self.events.capacity() * std::mem::size_of::<TxLifecycleEvent>()
+            + self
+                .events
+                .iter()
+                .map(|event| event.state.heap_size())
+                .sum::<usize>()
+    }
+}
+
+/// Lifecycles of the transactions sent through lite-rpc, from the submission until they are
+/// finalized, expired or dropped.
+#[derive(Clone, Debug, Default)]
+pub struct TxLifecycleStore {
+    lifecycles: Arc<DashMap<Signature, TxLifecycle>>,
+    // estimated memory of the lifecycles, updated with every change
+    bytes: Arc<AtomicUsize>,
+}
+
+impl TxLifecycleStore {
+    // the new size is added before the previous one is subtracted so the counter never underflows
+    fn resize(&self, previous_size: usize, size: usize) {
+        self.bytes.fetch_add(size, Ordering::Relaxed);
+        self.bytes.fetch_sub(previous_size, Ordering::Relaxed);
+    }
+
+    /// starts the lifecycle of a sanitized transaction which arrived at `received`;
+    /// the lifecycle of a resubmitted transaction is kept as it is
+    pub fn start(&self, signature: Signature, received: SystemTime) {
+        let mut inserted = false;
+        self.lifecycles.entry(signature).or_insert_with(|| {
+            inserted = true;
+            let mut lifecycle = TxLifecycle {
+                last_valid_block_height: 0,
+                events: Vec::with_capacity(8),
+                received_at: Instant::now(),
+                terminal_at: None,
+            };
+            lifecycle.push(TxLifecycleState::Received, to_unix_timestamp_ms(received));
+            lifecycle.push(TxLifecycleState::Sanitized, unix_timestamp_ms());
+            self.resize(0, lifecycle.size());
+            lifecycle
+        });
+        if inserted {
+            TX_LIFECYCLE_TRACKED.set(self.lifecycles.len() as i64);
+        }
+    }
+
+    /// appends a state to the lifecycle of a tracked transaction which did not reach a terminal
+    /// state, the drops of a resubmission of an already forwarded transaction are ignored
+    pub fn record(&self, signature: &Signature, state: TxLifecycleState) {
+        let Some(mut lifecycle) = self.lifecycles.get_mut(signature) else {
+            return;
+        };
+        if lifecycle.is_terminal()
+            || (matches!(state, TxLifecycleState::Dropped { .. }) && lifecycle.was_forwarded())
+        {
+            return;
+        }
+        let previous_size = lifecycle.size();
+        lifecycle.push(state, unix_timestamp_ms());
+        self.resize(previous_size, lifecycle.size());
+    }
+
+    pub fn record_queued(&self, signature: &Signature, last_valid_block_height: u64) {
+        if let Some(mut lifecycle) = self.lifecycles.get_mut(signature) {
+            lifecycle.last_valid_block_height = last_valid_block_height;
+        }
+        self.record(signature, TxLifecycleState::Queued);
+    }
+
+    pub fn record_forwarded(&self, signature: &Signature, leaders: &[Pubkey], attempt: usize) {
+        self.record(
+            signature,
+            TxLifecycleState::Forwarded {
+                leaders: leaders.iter().map(|leader| leader.to_string()).collect(),
+                attempt,
+            },
+        );
+    }
+
+    /// records the transactions of a block which are tracked, once per commitment
+    pub fn record_landed<'a>(
+        &self,
+        slot: Slot,
+        confirmation_status: &TransactionConfirmationStatus,
+        transactions: impl Iterator<Item = (&'a Signature, bool)>,
+    ) {
+        if self.lifecycles.is_empty() {
+            return;
+        }
+        let timestamp = unix_timestamp_ms();
+        for (signature, failed) in transactions {
+            let Some(mut lifecycle) = self.lifecycles.get_mut(signature) else {
+                continue;
+            };
+            if lifecycle.is_terminal() {
+                continue;
+            }
+            let mut landed_before = false;
+            let mut recorded = false;
+            let mut attempts = 0;
+            for event in &lifecycle.events {
+                match &event.state {
+                    TxLifecycleState::Landed {
+                        confirmation_status: status,
+                        ..
+                    } => {
+                        landed_before = true;
+                        recorded |= status == confirmation_status;
+                    }
+                    TxLifecycleState::Forwarded { attempt, .. } => attempts = *attempt,
+                    _ => {}
+                }
+            }
+            if recorded {
+                continue;
+            }
+            if !landed_before {
+                TX_LIFECYCLE_TIME_TO_LAND.observe(lifecycle.received_at.elapsed().as_secs_f64());
+                TX_LIFECYCLE_ATTEMPTS_TO_LAND.observe(attempts as f64);
+            }
+            let previous_size = lifecycle.size();
+            lifecycle.push(
+                TxLifecycleState::Landed {
+                    slot,
+                    confirmation_status: confirmation_status.clone(),
+                    failed,
+                },
+                timestamp,
+            );
+            self.resize(previous_size, lifecycle.size());
+        }
+    }
+
+    pub fn get(&self, signature: &Signature) -> Option<TxLifecycle> {
+        self.lifecycles
+            .get(signature)
+            .map(|lifecycle| lifecycle.value().clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.lifecycles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lifecycles.is_empty()
+    }
+
+    /// marks the transactions which were not finalized before their blockhash expired as expired
+    /// and removes the lifecycles which reached a terminal state longer than the retention ago
+    pub fn clean(&self, current_finalized_blockheight: u64, retention: Duration) {
+        let timestamp = unix_timestamp_ms();
+        self.lifecycles.retain(|_, lifecycle| {
+            let previous_size = lifecycle.size();
+            if !lifecycle.is_terminal()
+                && lifecycle.last_valid_block_height < current_finalized_blockheight
+            {
+                lifecycle.push(TxLifecycleState::Expired, timestamp);
+            }
+            let retained = match lifecycle.terminal_at {
+                Some(terminal_at) => terminal_at.elapsed() < retention,
+                None => true,
+            };
+            self.resize(previous_size, if retained { lifecycle.size() } else { 0 });
+            retained
+        });
+        TX_LIFECYCLE_TRACKED.set(self.lifecycles.len() as i64);
+    }
+}
+
+#[async_trait]
+impl MemoryConsumer for TxLifecycleStore {
+    fn name(&self) -> &'static str {
+        "tx_lifecycle_store"
+    }
+
+    // the lifecycles only answer support questions
+    fn eviction_priority(&self) -> EvictionPriority {
+        EvictionPriority::Low
+    }
+
+    async fn memory_usage(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// evicts the lifecycles which reached a terminal state first, the oldest first
+    async fn evict(&self, bytes: usize) -> usize {
+        let mut evictable = self
+            .lifecycles
+            .iter()
+            .map(|entry| (entry.terminal_at.is_none(), entry.received_at, *entry.key()))
+            .collect::<Vec<_>>();
+        evictable.sort_unstable_by_key(|(in_flight, received_at, _)| (*in_flight, *received_at));
+
+        let mut freed = 0;
+        for (_, _, signature) in evictable {
+            if freed >= bytes {
+                break;
+            }
+            if let Some((_, lifecycle)) = self.lifecycles.remove(&signature) {
+                let size = lifecycle.size();
+                self.resize(size, 0);
+                freed += size;
+            }
+        }
+        TX_LIFECYCLE_TRACKED.set(self.lifecycles.len() as i64);
+        freed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(store: &TxLifecycleStore, signature: &Signature) -> Vec<&'static str> {
+        store
+            .get(signature)
+            .unwrap()
+            .events
+            .iter()
+            .map(|event| event.state.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_lifecycle_until_finalized() {
+        let store = TxLifecycleStore::default();
+        let signature = Signature::new_unique();
+        let leader = Pubkey::new_unique();
+
+        // not tracked before it was sanitized
+        store.record_queued(&signature, 100);
+        assert!(store.get(&signature).is_none());
+
+        store.start(signature, SystemTime::now());
+        store.record_queued(&signature, 100);
+        store.record_forwarded(&signature, &[leader], 1);
+        store.record_forwarded(&signature, &[leader], 2);
+        for confirmation_status in [
+            TransactionConfirmationStatus::Processed,
+            TransactionConfirmationStatus::Processed,
+            TransactionConfirmationStatus::Confirmed,
+            TransactionConfirmationStatus::Finalized,
+        ] {
+            store.record_landed(42, &confirmation_status, [(&signature, false)].into_iter());
+        }
+        // nothing follows the finalized landing
+        store.record_forwarded(&signature, &[leader], 3);
+
+        assert_eq!(
+            states(&store, &signature),
+            vec![
+                "received",
+                "sanitized",
+                "queued",
+                "forwarded",
+                "forwarded",
+                "landed",
+                "landed",
+                "landed"
+            ]
+        );
+        let lifecycle = store.get(&signature).unwrap();
+        assert!(lifecycle.is_terminal());
+        assert_eq!(lifecycle.last_valid_block_height, 100);
+        assert_eq!(
+            lifecycle.events[3].state,
+            TxLifecycleState::Forwarded {
+                leaders: vec![leader.to_string()],
+                attempt: 1
+            }
+        );
+
+        // kept for the retention
+        store.clean(101, Duration::from_secs(60));
+        assert_eq!(store.len(), 1);
+        store.clean(101, Duration::ZERO);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_lifecycle_expired_and_dropped() {
+        let store = TxLifecycleStore::default();
+        let (expired, dropped, resubmitted) = (
+            Signature::new_unique(),
+            Signature::new_unique(),
+            Signature::new_unique(),
+        );
+        for signature in [expired, dropped, resubmitted] {
+            store.start(signature, SystemTime::now());
+            store.record_queued(&signature, 100);
+        }
+        store.record(
+            &dropped,
+            TxLifecycleState::Dropped {
+                reason: "queue_full".to_string(),
+            },
+        );
+        store.record_forwarded(&resubmitted, &[], 1);
+        // the drop of the resubmission does not end the forwarded transaction
+        store.start(resubmitted, SystemTime::now());
+        store.record(
+            &resubmitted,
+            TxLifecycleState::Dropped {
+                reason: "duplicate".to_string(),
+            },
+        );
+
+        store.clean(100, Duration::from_secs(60));
+        assert_eq!(
+            store.get(&expired).unwrap().state().unwrap().as_str(),
+            "queued"
+        );
+        assert!(store.get(&dropped).unwrap().is_terminal());
+
+        store.clean(101, Duration::from_secs(60));
+        assert_eq!(
+            states(&store, &expired),
+            vec!["received", "sanitized", "queued", "expired"]
+        );
+        assert_eq!(
+            states(&store, &resubmitted),
+            vec!["received", "sanitized", "queued", "forwarded", "expired"]
+        );
+    }
+
+    #[test]
+    fn test_retries_are_capped() {
+        let store = TxLifecycleStore::default();
+        let signature = Signature::new_unique();
+        let leader = Pubkey::new_unique();
+        store.start(signature, SystemTime::now());
+        store.record_queued(&signature, 100);
+        for attempt in 1..=20 {
+            store.record_forwarded(&signature, &[leader], attempt);
+            store.record(
+                &signature,
+                TxLifecycleState::ForwardFailed {
+                    attempt,
+                    error: "connection lost".to_string(),
+                },
+            );
+        }
+        store.record_landed(
+            42,
+            &TransactionConfirmationStatus::Processed,
+            [(&signature, false)].into_iter(),
+        );
+
+        let lifecycle = store.get(&signature).unwrap();
+        assert_eq!(lifecycle.events.len(), 3 + MAX_RETRY_EVENTS + 1);
+        let attempts = lifecycle
+            .events
+            .iter()
+            .filter_map(|event| match event.state {
+                TxLifecycleState::Forwarded { attempt, .. } => Some(attempt),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // the first and the latest attempts
+        assert_eq!(attempts, vec![1, 18, 19, 20]);
+        assert_eq!(lifecycle.state().unwrap().as_str(), "landed");
+    }
+
+    #[test]
+    fn test_memory_usage_is_tracked() {
+        let store = TxLifecycleStore::default();
+        let tracked_size = |store: &TxLifecycleStore| {
+            store
+                .lifecycles
+                .iter()
+                .map(|lifecycle| lifecycle.size())
+                .sum::<usize>()
+        };
+        let signatures = (0..3).map(|_| Signature::new_unique()).collect::<Vec<_>>();
+        for signature in &signatures {
+            store.start(*signature, SystemTime::now());
+            store.record_queued(signature, 100);
+        }
+        for attempt in 1..=20 {
+            store.record_forwarded(&signatures[0], &[Pubkey::new_unique()], attempt);
+        }
+        store.record(
+            &signatures[1],
+            TxLifecycleState::Dropped {
+                reason: "queue_full".to_string(),
+            },
+        );
+        assert_eq!(
+            futures::executor::block_on(store.memory_usage()),
+            tracked_size(&store)
+        );
+
+        // the dropped lifecycle is removed, the others are in flight
+        store.clean(100, Duration::ZERO);
+        assert_eq!(store.len(), 2);
+        assert_eq!(
+            futures::executor::block_on(store.memory_usage()),
+            tracked_size(&store)
+        );
+
+        let freed = futures::executor::block_on(store.evict(1));
+        assert_eq!(store.len(), 1);
+        assert!(freed > 0);
+        assert_eq!(
+            futures::executor::block_on(store.memory_usage()),
+            tracked_size(&store)
+        );
+    }
+
+    #[test]
+    fn test_lifecycle_serialization() {
+        let event = TxLifecycleEvent {
+            state: TxLifecycleState::Landed {
+                slot: 42,
+                confirmation_status: TransactionConfirmationStatus::Confirmed,
+                failed: false,
+            },
+            timestamp: 1,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "state": "landed",
+                "slot": 42,
+                "confirmationStatus": "confirmed",
+                "failed": false,
+                "timestamp": 1
+            })
+        );
+    }
+}
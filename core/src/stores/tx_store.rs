@@ -1,6 +1,24 @@
-use dashmap::DashMap;
-use solana_transaction_status::TransactionStatus;
+use async_trait::async_trait;
+use dashmap::{DashMap, SharedValue};
+use prometheus::{opts, register_int_counter_vec, register_int_gauge, IntCounterVec, IntGauge};
+use solana_sdk::signature::Signature;
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::memory_budget::{EvictionPriority, MemoryConsumer};
+use crate::structures::dropped_transaction::{record_dropped_transactions, DropReason};
+
+lazy_static::lazy_static! {
+    static ref TX_STORE_SIZE: IntGauge =
+    register_int_gauge!(opts!("literpc_tx_store_size", "Number of transaction statuses in the tx store")).unwrap();
+
+    static ref TX_STORE_EVICTIONS: IntCounterVec =
+    register_int_counter_vec!(opts!("literpc_tx_store_evictions", "Number of transaction statuses evicted from the tx store, by reason"), &["reason"]).unwrap();
+}
+
+// estimated size of an entry: key and value and the hash table overhead
+const TX_ENTRY_SIZE: usize = std::mem::size_of::<Signature>() + std::mem::size_of::<TxProps>() + 16;
 
 /// Transaction Properties
 
@@ -9,37 +27,137 @@ pub struct TxProps {
     pub status: Option<TransactionStatus>,
     pub last_valid_blockheight: u64,
     pub sent_by_lite_rpc: bool,
+    /// when the finalized status arrived, the status is kept for the retention from then on
+    pub finalized_at: Option<Instant>,
+}
+
+/// why a transaction status was removed from the tx store
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionReason {
+    /// the transaction was finalized longer than the retention ago
+    Finalized,
+    /// the blockhash expired before the transaction landed
+    BlockhashExpired,
+    /// the blockhash expired and the transaction landed on a fork which was not finalized
+    NotFinalized,
+    /// the memory budget was exceeded
+    MemoryBudget,
+}
+
+impl EvictionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvictionReason::Finalized => "finalized",
+            EvictionReason::BlockhashExpired => "blockhash_expired",
+            EvictionReason::NotFinalized => "not_finalized",
+            EvictionReason::MemoryBudget => "memory_budget",
+        }
+    }
+}
+
+fn record_evictions(reason: EvictionReason, count: u64) {
+    TX_STORE_EVICTIONS
+        .with_label_values(&[reason.as_str()])
+        .inc_by(count);
+}
+
+fn finalized_at(status: &TransactionStatus, now: Instant) -> Option<Instant> {
+    matches!(
+        status.confirmation_status,
+        Some(TransactionConfirmationStatus::Finalized)
+    )
+    .then_some(now)
+}
+
+/// status of a transaction of a block, applied in batches by [`TxStore::update_statuses`]
+#[derive(Debug, Clone)]
+pub struct TxStatusUpdate {
+    pub signature: Signature,
+    pub status: TransactionStatus,
+    pub last_valid_blockheight: u64,
 }
 
 #[derive(Clone, Debug)]
 pub struct TxStore {
-    pub store: Arc<DashMap<String, TxProps>>,
+    pub store: Arc<DashMap<Signature, TxProps>>,
 }
 
 impl TxStore {
     pub fn update_status(
         &self,
-        signature: &String,
+        signature: &Signature,
         transaction_status: TransactionStatus,
         last_valid_blockheight: u64,
     ) -> bool {
+        let finalized_at = finalized_at(&transaction_status, Instant::now());
         if let Some(mut meta) = self.store.get_mut(signature) {
             meta.status = Some(transaction_status);
+            meta.finalized_at = meta.finalized_at.or(finalized_at);
             meta.value().sent_by_lite_rpc
         } else {
             self.store.insert(
-                signature.clone(),
+                *signature,
                 TxProps {
                     status: Some(transaction_status),
                     last_valid_blockheight,
                     sent_by_lite_rpc: false,
+                    finalized_at,
                 },
             );
             false
         }
     }
 
-    pub fn insert(&self, signature: String, props: TxProps) -> Option<TxProps> {
+    /// same as [`TxStore::update_status`] for all transactions of a block, the updates are grouped
+    /// by shard so every shard is locked once per block instead of once per transaction;
+    /// returns the number of updated transactions sent by lite-rpc
+    pub fn update_statuses(&self, updates: Vec<TxStatusUpdate>) -> usize {
+        let shards = self.store.shards();
+        let mut updates_by_shard = (0..shards.len())
+            .map(|_| Vec::with_capacity(updates.len() / shards.len() + 1))
+            .collect::<Vec<_>>();
+        for update in updates {
+            updates_by_shard[self.store.determine_map(&update.signature)].push(update);
+        }
+
+        let now = Instant::now();
+        let mut sent_by_lite_rpc = 0;
+        for (shard, updates) in shards.iter().zip(updates_by_shard) {
+            if updates.is_empty() {
+                continue;
+            }
+            let mut shard = shard.write();
+            shard.reserve(updates.len());
+            for update in updates {
+                let finalized_at = finalized_at(&update.status, now);
+                match shard.get_mut(&update.signature) {
+                    Some(meta) => {
+                        let meta = meta.get_mut();
+                        meta.status = Some(update.status);
+                        meta.finalized_at = meta.finalized_at.or(finalized_at);
+                        if meta.sent_by_lite_rpc {
+                            sent_by_lite_rpc += 1;
+                        }
+                    }
+                    None => {
+                        shard.insert(
+                            update.signature,
+                            SharedValue::new(TxProps {
+                                status: Some(update.status),
+                                last_valid_blockheight: update.last_valid_blockheight,
+                                sent_by_lite_rpc: false,
+                                finalized_at,
+                            }),
+                        );
+                    }
+                }
+            }
+        }
+        TX_STORE_SIZE.set(self.store.len() as i64);
+        sent_by_lite_rpc
+    }
+
+    pub fn insert(&self, signature: Signature, props: TxProps) -> Option<TxProps> {
         self.store.insert(signature, props)
     }
 
@@ -51,28 +169,255 @@ impl TxStore {
         self.store.is_empty()
     }
 
-    pub fn contains_key(&self, signature: &String) -> bool {
+    pub fn contains_key(&self, signature: &Signature) -> bool {
         self.store.contains_key(signature)
     }
 
-    pub fn get(&self, signature: &String) -> Option<TxProps> {
+    pub fn get(&self, signature: &Signature) -> Option<TxProps> {
         self.store.get(signature).map(|x| x.value().clone())
     }
 
-    pub fn clean(&self, current_finalized_blockheight: u64) {
+    /// removes the statuses of transactions finalized longer than the retention ago, and of
+    /// transactions which were not finalized before their blockhash expired
+    pub fn clean(&self, current_finalized_blockheight: u64, finalized_retention: Duration) {
         let length_before = self.store.len();
-        self.store
-            .retain(|_k, v| v.last_valid_blockheight >= current_finalized_blockheight);
+        let (mut finalized, mut expired, mut not_finalized, mut expired_unconfirmed) = (0, 0, 0, 0);
+        self.store.retain(|_k, v| {
+            let reason = match (v.finalized_at, &v.status) {
+                (Some(finalized_at), _) => (finalized_at.elapsed() >= finalized_retention)
+                    .then_some(EvictionReason::Finalized),
+                _ if v.last_valid_blockheight >= current_finalized_blockheight => None,
+                // the finalized blocks passed the last valid block height without its block
+                (None, Some(_)) => Some(EvictionReason::NotFinalized),
+                (None, None) => Some(EvictionReason::BlockhashExpired),
+            };
+            match reason {
+                Some(EvictionReason::Finalized) => finalized += 1,
+                Some(EvictionReason::NotFinalized) => not_finalized += 1,
+                Some(EvictionReason::BlockhashExpired) => {
+                    expired += 1;
+                    if v.sent_by_lite_rpc {
+                        expired_unconfirmed += 1;
+                    }
+                }
+                Some(EvictionReason::MemoryBudget) | None => {}
+            }
+            reason.is_none()
+        });
+        record_evictions(EvictionReason::Finalized, finalized);
+        record_evictions(EvictionReason::BlockhashExpired, expired);
+        record_evictions(EvictionReason::NotFinalized, not_finalized);
+        record_dropped_transactions(DropReason::BlockhashExpired, expired_unconfirmed);
+        TX_STORE_SIZE.set(self.store.len() as i64);
         log::info!(
             "Cleaned {} transactions",
             length_before.saturating_sub(self.store.len())
         );
     }
 
-    pub fn is_transaction_confirmed(&self, signature: &String) -> bool {
+    pub fn is_transaction_confirmed(&self, signature: &Signature) -> bool {
         match self.store.get(signature) {
             Some(props) => props.status.is_some(),
             None => false,
         }
     }
+
+    /// sent transactions which did not land yet and can still land at `current_blockheight`
+    pub fn count_in_flight(&self, current_blockheight: u64) -> usize {
+        self.store
+            .iter()
+            .filter(|tx| {
+                tx.sent_by_lite_rpc
+                    && tx.status.is_none()
+                    && tx.last_valid_blockheight >= current_blockheight
+            })
+            .count()
+    }
+}
+
+#[async_trait]
+impl MemoryConsumer for TxStore {
+    fn name(&self) -> &'static str {
+        "tx_store"
+    }
+
+    // statuses of transactions sent through lite-rpc are never evicted
+    fn eviction_priority(&self) -> EvictionPriority {
+        EvictionPriority::High
+    }
+
+    async fn memory_usage(&self) -> usize {
+        self.store.len() * TX_ENTRY_SIZE
+    }
+
+    /// evicts the statuses of the oldest landed transactions which were not sent through lite-rpc
+    async fn evict(&self, bytes: usize) -> usize {
+        let mut evictable = self
+            .store
+            .iter()
+            .filter(|entry| !entry.sent_by_lite_rpc)
+            .filter_map(|entry| Some((entry.status.as_ref()?.slot, *entry.key())))
+            .collect::<Vec<_>>();
+        evictable.sort_unstable_by_key(|(slot, _)| *slot);
+
+        let mut freed = 0;
+        for (_, signature) in evictable {
+            if freed >= bytes {
+                break;
+            }
+            if self.store.remove(&signature).is_some() {
+                freed += TX_ENTRY_SIZE;
+            }
+        }
+        record_evictions(EvictionReason::MemoryBudget, (freed / TX_ENTRY_SIZE) as u64);
+        TX_STORE_SIZE.set(self.store.len() as i64);
+        freed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_transaction_status::TransactionConfirmationStatus;
+
+    fn tx_store() -> TxStore {
+        TxStore {
+            store: Arc::new(DashMap::new()),
+        }
+    }
+
+    fn signature(i: u64) -> Signature {
+        let mut bytes = [0u8; 64];
+        bytes[..8].copy_from_slice(&i.to_le_bytes());
+        Signature::from(bytes)
+    }
+
+    fn update(signature: Signature) -> TxStatusUpdate {
+        TxStatusUpdate {
+            signature,
+            status: TransactionStatus {
+                slot: 42,
+                confirmations: None,
+                status: Ok(()),
+                err: None,
+                confirmation_status: Some(TransactionConfirmationStatus::Confirmed),
+            },
+            last_valid_blockheight: 150,
+        }
+    }
+
+    #[test]
+    fn test_update_statuses_matches_single_updates() {
+        let batched = tx_store();
+        let single = tx_store();
+        for store in [&batched, &single] {
+            for i in (0..1000).step_by(2) {
+                store.insert(
+                    signature(i),
+                    TxProps {
+                        status: None,
+                        last_valid_blockheight: 100,
+                        sent_by_lite_rpc: true,
+                        finalized_at: None,
+                    },
+                );
+            }
+        }
+
+        let updates = (0..1000).map(|i| update(signature(i))).collect::<Vec<_>>();
+        let sent_by_lite_rpc = updates
+            .iter()
+            .filter(|update| {
+                single.update_status(
+                    &update.signature,
+                    update.status.clone(),
+                    update.last_valid_blockheight,
+                )
+            })
+            .count();
+        assert_eq!(batched.update_statuses(updates), sent_by_lite_rpc);
+        assert_eq!(sent_by_lite_rpc, 500);
+
+        assert_eq!(batched.len(), single.len());
+        for i in 0..1000 {
+            let (batched, single) = (
+                batched.get(&signature(i)).unwrap(),
+                single.get(&signature(i)).unwrap(),
+            );
+            assert_eq!(batched.status, single.status);
+            assert_eq!(
+                batched.last_valid_blockheight,
+                single.last_valid_blockheight
+            );
+            assert_eq!(batched.sent_by_lite_rpc, single.sent_by_lite_rpc);
+        }
+    }
+
+    #[test]
+    fn test_evict_keeps_transactions_sent_by_lite_rpc() {
+        let store = tx_store();
+        let sent = signature(100);
+        store.insert(
+            sent,
+            TxProps {
+                status: None,
+                last_valid_blockheight: 100,
+                sent_by_lite_rpc: true,
+                finalized_at: None,
+            },
+        );
+        let mut updates = vec![update(sent)];
+        for slot in 0..10 {
+            let mut update = update(signature(slot));
+            update.status.slot = slot;
+            updates.push(update);
+        }
+        store.update_statuses(updates);
+
+        let freed = futures::executor::block_on(store.evict(3 * TX_ENTRY_SIZE));
+        assert_eq!(freed, 3 * TX_ENTRY_SIZE);
+        assert_eq!(store.len(), 8);
+        assert!(store.contains_key(&sent));
+        assert!(!store.contains_key(&signature(2)));
+        assert!(store.contains_key(&signature(3)));
+
+        futures::executor::block_on(store.evict(usize::MAX));
+        assert_eq!(store.len(), 1);
+        assert!(store.contains_key(&sent));
+    }
+
+    #[test]
+    fn test_clean_keeps_finalized_statuses_for_the_retention() {
+        let store = tx_store();
+        let (confirmed, finalized, expired) = (signature(1), signature(2), signature(3));
+        for signature in [confirmed, finalized, expired] {
+            store.insert(
+                signature,
+                TxProps {
+                    status: None,
+                    last_valid_blockheight: 100,
+                    sent_by_lite_rpc: true,
+                    finalized_at: None,
+                },
+            );
+        }
+        let mut finalized_update = update(finalized);
+        finalized_update.status.confirmation_status =
+            Some(TransactionConfirmationStatus::Finalized);
+        store.update_statuses(vec![update(confirmed), finalized_update]);
+        assert!(store.get(&finalized).unwrap().finalized_at.is_some());
+        assert!(store.get(&confirmed).unwrap().finalized_at.is_none());
+
+        // nothing expired and the finalized status is within the retention
+        store.clean(100, Duration::from_secs(60));
+        assert_eq!(store.len(), 3);
+
+        // the blockhash expired, the confirmed transaction landed on a fork
+        store.clean(101, Duration::from_secs(60));
+        assert_eq!(store.len(), 1);
+        assert!(store.contains_key(&finalized));
+
+        store.clean(101, Duration::ZERO);
+        assert!(store.is_empty());
+    }
 }
@@ -1,9 +1,56 @@
+use bytes::Bytes;
 use solana_rpc_client_api::filter::RpcFilterType;
-use solana_sdk::{account::Account, pubkey::Pubkey, slot_history::Slot};
+use solana_sdk::{account::ReadableAccount, clock::Epoch, pubkey::Pubkey, slot_history::Slot};
 use tokio::sync::broadcast::Receiver;
 
 use crate::commitment_utils::Commitment;
 
+/// Account as streamed and stored by lite-rpc. The data is reference counted, so the account stream,
+/// the account store and the subscribers share the buffer decoded from the grpc update instead of
+/// copying it; it is only copied when encoded for a response.
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Bytes,
+    pub owner: Pubkey,
+    pub executable: bool,
+    pub rent_epoch: Epoch,
+}
+
+impl From<solana_sdk::account::Account> for Account {
+    fn from(account: solana_sdk::account::Account) -> Self {
+        Self {
+            lamports: account.lamports,
+            data: account.data.into(),
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        }
+    }
+}
+
+impl ReadableAccount for Account {
+    fn lamports(&self) -> u64 {
+        self.lamports
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn owner(&self) -> &Pubkey {
+        &self.owner
+    }
+
+    fn executable(&self) -> bool {
+        self.executable
+    }
+
+    fn rent_epoch(&self) -> Epoch {
+        self.rent_epoch
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct AccountData {
     pub pubkey: Pubkey,
@@ -12,6 +59,11 @@ pub struct AccountData {
 }
 
 impl AccountData {
+    /// an account left without lamports is closed, the runtime purges it at the end of the slot
+    pub fn is_closed(&self) -> bool {
+        self.account.lamports == 0
+    }
+
     pub fn allows(&self, filter: &RpcFilterType) -> bool {
         match filter {
             RpcFilterType::DataSize(size) => self.account.data.len() as u64 == *size,
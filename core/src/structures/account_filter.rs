@@ -1,6 +1,15 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    str::FromStr,
+};
+
+use base64::Engine;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use solana_rpc_client_api::filter::{Memcmp as RpcMemcmp, MemcmpEncodedBytes, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+
+use super::account_data::AccountData;
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -10,6 +19,19 @@ pub enum MemcmpFilterData {
     Base64(String),
 }
 
+impl MemcmpFilterData {
+    /// the decoded bytes, none if the data is not valid base58 or base64
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        match self {
+            MemcmpFilterData::Bytes(bytes) => Some(bytes.clone()),
+            MemcmpFilterData::Base58(data) => bs58::decode(data).into_vec().ok(),
+            MemcmpFilterData::Base64(data) => {
+                base64::engine::general_purpose::STANDARD.decode(data).ok()
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct MemcmpFilter {
@@ -57,10 +79,124 @@ impl AccountFilter {
                 .collect_vec()
         })
     }
+
+    /// the account is one of the accounts or owned by the program and matches all its filters,
+    /// as the account is subscribed from the geyser sources
+    pub fn allows(&self, account_data: &AccountData) -> bool {
+        ParsedAccountFilter::new(self).allows(account_data)
+    }
+}
+
+/// an account filter with its pubkeys and memcmp data decoded once, to match every account update
+/// against it without parsing the filter again
+#[derive(Clone, Debug)]
+pub struct ParsedAccountFilter {
+    accounts: HashSet<Pubkey>,
+    program_id: Option<Pubkey>,
+    filters: Vec<RpcFilterType>,
+}
+
+impl ParsedAccountFilter {
+    pub fn new(filter: &AccountFilter) -> Self {
+        let filters = filter
+            .filters
+            .iter()
+            .flatten()
+            .map(|filter| match filter {
+                AccountFilterType::Datasize(size) => RpcFilterType::DataSize(*size),
+                AccountFilterType::Memcmp(memcmp) => {
+                    // undecodable data matches no account, as the rpc filter it would be converted to
+                    let encoded_bytes = match memcmp.data.bytes() {
+                        Some(bytes) => MemcmpEncodedBytes::Bytes(bytes),
+                        None => MemcmpEncodedBytes::Base58(String::from("0")),
+                    };
+                    RpcFilterType::Memcmp(RpcMemcmp::new(memcmp.offset as usize, encoded_bytes))
+                }
+            })
+            .collect();
+        Self {
+            accounts: filter
+                .accounts
+                .iter()
+                .filter_map(|account| Pubkey::from_str(account).ok())
+                .collect(),
+            program_id: filter
+                .program_id
+                .as_ref()
+                .and_then(|program_id| Pubkey::from_str(program_id).ok()),
+            filters,
+        }
+    }
+
+    pub fn allows(&self, account_data: &AccountData) -> bool {
+        if self.accounts.contains(&account_data.pubkey) {
+            return true;
+        }
+        self.program_id == Some(account_data.account.owner)
+            && self
+                .filters
+                .iter()
+                .all(|filter| account_data.allows(filter))
+    }
 }
 
 pub type AccountFilters = Vec<AccountFilter>;
 
+/// named account filters, each set gets its own account stream and store
+pub type AccountFilterSets = BTreeMap<String, AccountFilters>;
+
+#[test]
+fn test_account_filter_allows() {
+    use crate::structures::account_data::Account;
+
+    let program_id = Pubkey::new_unique();
+    let listed = Pubkey::new_unique();
+    let filter = AccountFilter {
+        accounts: vec![listed.to_string()],
+        program_id: Some(program_id.to_string()),
+        filters: Some(vec![
+            AccountFilterType::Datasize(4),
+            AccountFilterType::Memcmp(MemcmpFilter {
+                offset: 1,
+                data: MemcmpFilterData::Base58(bs58::encode([2, 3]).into_string()),
+            }),
+        ]),
+    };
+    let account_data = |pubkey: Pubkey, owner: Pubkey, data: Vec<u8>| AccountData {
+        pubkey,
+        account: Account {
+            lamports: 1,
+            data: data.into(),
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        },
+        updated_slot: 1,
+    };
+
+    assert!(filter.allows(&account_data(listed, Pubkey::new_unique(), vec![])));
+    assert!(filter.allows(&account_data(
+        Pubkey::new_unique(),
+        program_id,
+        vec![1, 2, 3, 4]
+    )));
+    assert!(!filter.allows(&account_data(
+        Pubkey::new_unique(),
+        program_id,
+        vec![1, 2, 4, 4]
+    )));
+    assert!(!filter.allows(&account_data(
+        Pubkey::new_unique(),
+        program_id,
+        vec![1, 2, 3]
+    )));
+    assert!(!filter.allows(&account_data(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        vec![1, 2, 3, 4]
+    )));
+}
+
 #[test]
 fn test_accounts_filters_deserialization() {
     let str = "[
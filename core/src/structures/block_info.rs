@@ -0,0 +1,32 @@
+use solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, pubkey::Pubkey, slot_history::Slot,
+};
+
+use super::produced_block::ProducedBlock;
+
+/// The metadata of a block without its transactions, for consumers which do not need the full
+/// block and should not clone it for every notification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockInfo {
+    pub slot: Slot,
+    pub blockhash: Hash,
+    pub parent_slot: Slot,
+    pub block_height: u64,
+    pub leader_id: Option<Pubkey>,
+    pub transaction_count: usize,
+    pub commitment_config: CommitmentConfig,
+}
+
+impl From<&ProducedBlock> for BlockInfo {
+    fn from(block: &ProducedBlock) -> Self {
+        Self {
+            slot: block.slot,
+            blockhash: block.blockhash,
+            parent_slot: block.parent_slot,
+            block_height: block.block_height,
+            leader_id: block.leader_id,
+            transaction_count: block.transactions.len(),
+            commitment_config: block.commitment_config,
+        }
+    }
+}
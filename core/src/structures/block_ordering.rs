@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash, slot_history::Slot};
+
+use super::produced_block::ProducedBlock;
+
+// emitted slots remembered per commitment to detect conflicting blocks
+const MAX_TRACKED_SLOTS: usize = 512;
+
+/// An irregularity in the blocks of one commitment level received from the sources.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockOrderingEvent {
+    /// the block arrived after a block of a higher slot was emitted at its commitment, it is dropped
+    OutOfOrder {
+        slot: Slot,
+        blockhash: Hash,
+        commitment_config: CommitmentConfig,
+        last_emitted_slot: Slot,
+    },
+    /// another block of the slot was emitted at the same commitment before; a processed block of
+    /// another fork is emitted as well, a confirmed or finalized one is dropped
+    Conflict {
+        slot: Slot,
+        blockhash: Hash,
+        previous_blockhash: Hash,
+        commitment_config: CommitmentConfig,
+    },
+}
+
+impl BlockOrderingEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BlockOrderingEvent::OutOfOrder { .. } => "out_of_order",
+            BlockOrderingEvent::Conflict { .. } => "conflict",
+        }
+    }
+
+    pub fn commitment_config(&self) -> CommitmentConfig {
+        match self {
+            BlockOrderingEvent::OutOfOrder {
+                commitment_config, ..
+            }
+            | BlockOrderingEvent::Conflict {
+                commitment_config, ..
+            } => *commitment_config,
+        }
+    }
+}
+
+#[derive(Default)]
+struct CommitmentOrdering {
+    pending: BTreeMap<(Slot, Hash), (ProducedBlock, Instant)>,
+    last_emitted_slot: Option<Slot>,
+    // blockhash of the recently emitted slots
+    emitted: BTreeMap<Slot, Hash>,
+}
+
+impl CommitmentOrdering {
+    /// the lowest pending block if its parent was emitted, it waited long enough or too many are pending
+    fn pop_due(
+        &mut self,
+        now: Instant,
+        max_delay: Duration,
+        max_pending: usize,
+    ) -> Option<ProducedBlock> {
+        let (block, received_at) = self.pending.values().next()?;
+        let parent_emitted = self
+            .last_emitted_slot
+            .is_some_and(|last_emitted_slot| block.parent_slot <= last_emitted_slot);
+        if parent_emitted
+            || now.saturating_duration_since(*received_at) >= max_delay
+            || self.pending.len() > max_pending
+        {
+            self.pending.pop_first().map(|(_, (block, _))| block)
+        } else {
+            None
+        }
+    }
+
+    fn pop_up_to(&mut self, slot: Slot) -> Option<ProducedBlock> {
+        match self.pending.first_key_value() {
+            Some(((pending_slot, _), _)) if *pending_slot <= slot => {
+                self.pending.pop_first().map(|(_, (block, _))| block)
+            }
+            _ => None,
+        }
+    }
+
+    fn mark_emitted(&mut self, block: &ProducedBlock) {
+        self.last_emitted_slot = Some(
+            self.last_emitted_slot
+                .map_or(block.slot, |last_emitted_slot| {
+                    last_emitted_slot.max(block.slot)
+                }),
+        );
+        self.emitted.insert(block.slot, block.blockhash);
+        while self.emitted.len() > MAX_TRACKED_SLOTS {
+            self.emitted.pop_first();
+        }
+    }
+}
+
+/// Emits the blocks of every commitment in nondecreasing slot order. A block waits until the block of
+/// its parent slot was emitted, at most `max_delay`, so blocks of the sources arriving slightly out of
+/// order are sorted; blocks arriving after a higher slot was emitted are dropped. The blocks of lower
+/// commitments of a slot are emitted before the block of a higher commitment.
+pub struct BlockOrderingBuffer {
+    max_delay: Duration,
+    max_pending: usize,
+    // processed, confirmed, finalized
+    commitments: [CommitmentOrdering; 3],
+}
+
+impl BlockOrderingBuffer {
+    pub fn new(max_delay: Duration, max_pending: usize) -> Self {
+        Self {
+            max_delay,
+            max_pending,
+            commitments: Default::default(),
+        }
+    }
+
+    /// buffers the block, returns the event if it is out of order or conflicts with an emitted block
+    pub fn insert(&mut self, block: ProducedBlock, now: Instant) -> Option<BlockOrderingEvent> {
+        let ordering = &mut self.commitments[rank(block.commitment_config)];
+        let previous_blockhash = ordering.emitted.get(&block.slot).copied();
+        if previous_blockhash == Some(block.blockhash) {
+            // duplicate of another source
+            return None;
+        }
+        if let Some(last_emitted_slot) = ordering.last_emitted_slot {
+            if block.slot < last_emitted_slot {
+                return Some(BlockOrderingEvent::OutOfOrder {
+                    slot: block.slot,
+                    blockhash: block.blockhash,
+                    commitment_config: block.commitment_config,
+                    last_emitted_slot,
+                });
+            }
+        }
+
+        let event = previous_blockhash.map(|previous_blockhash| BlockOrderingEvent::Conflict {
+            slot: block.slot,
+            blockhash: block.blockhash,
+            previous_blockhash,
+            commitment_config: block.commitment_config,
+        });
+        if event.is_none() || block.commitment_config.is_processed() {
+            ordering
+                .pending
+                .entry((block.slot, block.blockhash))
+                .or_insert((block, now));
+        }
+        event
+    }
+
+    /// the blocks which are due, in the order they have to be emitted
+    pub fn pop_ready(&mut self, now: Instant) -> Vec<ProducedBlock> {
+        let mut ready = vec![];
+        for commitment in 0..self.commitments.len() {
+            while let Some(block) =
+                self.commitments[commitment].pop_due(now, self.max_delay, self.max_pending)
+            {
+                for lower_commitment in 0..commitment {
+                    let ordering = &mut self.commitments[lower_commitment];
+                    while let Some(lower_block) = ordering.pop_up_to(block.slot) {
+                        ordering.mark_emitted(&lower_block);
+                        ready.push(lower_block);
+                    }
+                }
+                self.commitments[commitment].mark_emitted(&block);
+                ready.push(block);
+            }
+        }
+        ready
+    }
+
+    pub fn pending(&self) -> usize {
+        self.commitments
+            .iter()
+            .map(|ordering| ordering.pending.len())
+            .sum()
+    }
+}
+
+fn rank(commitment_config: CommitmentConfig) -> usize {
+    if commitment_config.is_finalized() {
+        2
+    } else if commitment_config.is_confirmed() {
+        1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_DELAY: Duration = Duration::from_millis(200);
+
+    fn block(slot: Slot, parent_slot: Slot, commitment_config: CommitmentConfig) -> ProducedBlock {
+        ProducedBlock {
+            parent_slot,
+            ..ProducedBlock::new_for_tests(slot, commitment_config)
+        }
+    }
+
+    fn slots(blocks: &[ProducedBlock]) -> Vec<Slot> {
+        blocks.iter().map(|block| block.slot).collect()
+    }
+
+    #[test]
+    fn test_sorts_blocks_arriving_out_of_order() {
+        let mut buffer = BlockOrderingBuffer::new(MAX_DELAY, 32);
+        let now = Instant::now();
+
+        assert_eq!(
+            buffer.insert(block(10, 9, CommitmentConfig::processed()), now),
+            None
+        );
+        // the first block waits for blocks of lower slots
+        assert!(buffer.pop_ready(now).is_empty());
+        assert_eq!(slots(&buffer.pop_ready(now + MAX_DELAY)), vec![10]);
+
+        buffer.insert(block(12, 11, CommitmentConfig::processed()), now);
+        assert!(buffer.pop_ready(now + MAX_DELAY).is_empty());
+        buffer.insert(block(11, 10, CommitmentConfig::processed()), now);
+        assert_eq!(slots(&buffer.pop_ready(now + MAX_DELAY)), vec![11, 12]);
+    }
+
+    #[test]
+    fn test_drops_late_blocks() {
+        let mut buffer = BlockOrderingBuffer::new(MAX_DELAY, 32);
+        let now = Instant::now();
+        buffer.insert(block(10, 9, CommitmentConfig::confirmed()), now);
+        buffer.insert(block(12, 11, CommitmentConfig::confirmed()), now);
+        assert_eq!(slots(&buffer.pop_ready(now + MAX_DELAY)), vec![10, 12]);
+
+        let late = block(11, 10, CommitmentConfig::confirmed());
+        assert_eq!(
+            buffer.insert(late.clone(), now),
+            Some(BlockOrderingEvent::OutOfOrder {
+                slot: 11,
+                blockhash: late.blockhash,
+                commitment_config: CommitmentConfig::confirmed(),
+                last_emitted_slot: 12,
+            })
+        );
+        assert_eq!(buffer.pending(), 0);
+    }
+
+    #[test]
+    fn test_reports_conflicting_blocks() {
+        let mut buffer = BlockOrderingBuffer::new(MAX_DELAY, 32);
+        let now = Instant::now();
+        let processed = block(10, 9, CommitmentConfig::processed());
+        let finalized = processed.to_finalized_block();
+        buffer.insert(processed.clone(), now);
+        buffer.insert(finalized.clone(), now);
+        assert_eq!(slots(&buffer.pop_ready(now + MAX_DELAY)), vec![10, 10]);
+
+        // duplicates of other sources are skipped silently
+        assert_eq!(buffer.insert(finalized, now), None);
+
+        // processed blocks of another fork are emitted, finalized ones are not
+        let fork = block(10, 8, CommitmentConfig::processed());
+        assert!(matches!(
+            buffer.insert(fork, now),
+            Some(BlockOrderingEvent::Conflict { slot: 10, .. })
+        ));
+        let conflicting = block(10, 8, CommitmentConfig::finalized());
+        assert!(matches!(
+            buffer.insert(conflicting, now),
+            Some(BlockOrderingEvent::Conflict { slot: 10, .. })
+        ));
+        assert_eq!(slots(&buffer.pop_ready(now + MAX_DELAY)), vec![10]);
+    }
+
+    #[test]
+    fn test_emits_lower_commitments_of_a_slot_first() {
+        let mut buffer = BlockOrderingBuffer::new(MAX_DELAY, 32);
+        let now = Instant::now();
+        buffer.insert(block(10, 9, CommitmentConfig::confirmed()), now);
+        buffer.insert(block(10, 9, CommitmentConfig::processed()), now);
+        assert!(buffer.pop_ready(now).is_empty());
+
+        let ready = buffer.pop_ready(now + MAX_DELAY);
+        assert_eq!(slots(&ready), vec![10, 10]);
+        assert!(ready[0].commitment_config.is_processed());
+        assert!(ready[1].commitment_config.is_confirmed());
+    }
+}
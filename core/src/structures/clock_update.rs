@@ -0,0 +1,87 @@
+use serde::Serialize;
+use solana_sdk::{
+    account::{from_account, ReadableAccount},
+    clock::{Clock, Epoch, Slot, UnixTimestamp},
+    sysvar,
+};
+
+use super::account_data::AccountData;
+
+/// The Clock sysvar of a slot, the on-chain time as the runtime sees it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockUpdate {
+    pub slot: Slot,
+    pub epoch: Epoch,
+    pub leader_schedule_epoch: Epoch,
+    /// stake weighted timestamp of the votes, in seconds since the unix epoch
+    pub unix_timestamp: UnixTimestamp,
+    pub epoch_start_timestamp: UnixTimestamp,
+}
+
+impl ClockUpdate {
+    /// none if the account is not the Clock sysvar
+    pub fn from_account_data(account_data: &AccountData) -> Option<Self> {
+        if account_data.pubkey != sysvar::clock::id() {
+            return None;
+        }
+        Self::from_account(&account_data.account)
+    }
+
+    pub fn from_account(account: &impl ReadableAccount) -> Option<Self> {
+        from_account::<Clock, _>(account).map(Self::from)
+    }
+}
+
+impl From<Clock> for ClockUpdate {
+    fn from(clock: Clock) -> Self {
+        Self {
+            slot: clock.slot,
+            epoch: clock.epoch,
+            leader_schedule_epoch: clock.leader_schedule_epoch,
+            unix_timestamp: clock.unix_timestamp,
+            epoch_start_timestamp: clock.epoch_start_timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::structures::account_data::Account;
+
+    #[test]
+    fn test_decodes_the_clock_sysvar() {
+        let clock = Clock {
+            slot: 250_000_000,
+            epoch_start_timestamp: 1_700_000_000,
+            epoch: 578,
+            leader_schedule_epoch: 579,
+            unix_timestamp: 1_700_100_000,
+        };
+        let account_data = AccountData {
+            pubkey: sysvar::clock::id(),
+            account: Account {
+                lamports: 1_169_280,
+                data: bincode::serialize(&clock).unwrap().into(),
+                owner: sysvar::id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+            updated_slot: clock.slot,
+        };
+
+        let update = ClockUpdate::from_account_data(&account_data).unwrap();
+        assert_eq!(update.slot, 250_000_000);
+        assert_eq!(update.epoch, 578);
+        assert_eq!(update.unix_timestamp, 1_700_100_000);
+
+        let other = AccountData {
+            pubkey: Pubkey::new_unique(),
+            ..account_data
+        };
+        assert_eq!(ClockUpdate::from_account_data(&other), None);
+    }
+}
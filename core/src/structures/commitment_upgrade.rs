@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    hash::Hash,
+    slot_history::Slot,
+};
+
+use super::block_info::BlockInfo;
+
+// slots which never reach finalized (on abandoned forks) are dropped beyond this
+const MAX_TRACKED_SLOTS: usize = 4096;
+
+/// A slot reached confirmed or finalized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockCommitmentUpgrade {
+    pub slot: Slot,
+    pub blockhash: Hash,
+    pub commitment_config: CommitmentConfig,
+    /// the commitment the slot was seen at before, none if it is seen for the first time
+    pub previous_commitment: Option<CommitmentLevel>,
+    pub previous_blockhash: Option<Hash>,
+    /// the block of the slot is not the one seen at the previous commitment, views built from
+    /// the previous block have to be rolled back
+    pub conflict: bool,
+}
+
+/// Turns the blocks of all commitments into the commitment upgrades of their slots.
+#[derive(Default)]
+pub struct CommitmentUpgradeTracker {
+    slots: BTreeMap<Slot, (Hash, CommitmentConfig)>,
+}
+
+impl CommitmentUpgradeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the upgrade if the block is of a higher commitment than the block seen for its slot before
+    pub fn update(&mut self, block: &BlockInfo) -> Option<BlockCommitmentUpgrade> {
+        let previous = self.slots.get(&block.slot).copied();
+        if let Some((_, previous_commitment)) = previous {
+            if rank(previous_commitment) > rank(block.commitment_config) {
+                return None;
+            }
+            if rank(previous_commitment) == rank(block.commitment_config) {
+                // a processed block of another fork replaces the one seen before
+                if block.commitment_config.is_processed() {
+                    self.slots
+                        .insert(block.slot, (block.blockhash, block.commitment_config));
+                }
+                return None;
+            }
+        }
+
+        self.slots
+            .insert(block.slot, (block.blockhash, block.commitment_config));
+        if block.commitment_config.is_finalized() {
+            // older slots cannot be confirmed anymore, the finalized one is kept to skip duplicates
+            self.slots = self.slots.split_off(&block.slot);
+        }
+        while self.slots.len() > MAX_TRACKED_SLOTS {
+            self.slots.pop_first();
+        }
+
+        if block.commitment_config.is_processed() {
+            return None;
+        }
+        let previous_blockhash = previous.map(|(blockhash, _)| blockhash);
+        Some(BlockCommitmentUpgrade {
+            slot: block.slot,
+            blockhash: block.blockhash,
+            commitment_config: block.commitment_config,
+            previous_commitment: previous.map(|(_, commitment)| commitment.commitment),
+            previous_blockhash,
+            conflict: previous_blockhash.is_some_and(|blockhash| blockhash != block.blockhash),
+        })
+    }
+}
+
+fn rank(commitment_config: CommitmentConfig) -> u8 {
+    if commitment_config.is_finalized() {
+        2
+    } else if commitment_config.is_confirmed() {
+        1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(slot: Slot, blockhash: Hash, commitment_config: CommitmentConfig) -> BlockInfo {
+        BlockInfo {
+            slot,
+            blockhash,
+            parent_slot: slot - 1,
+            block_height: slot,
+            leader_id: None,
+            transaction_count: 0,
+            commitment_config,
+        }
+    }
+
+    #[test]
+    fn test_upgrades_processed_to_confirmed_to_finalized() {
+        let mut tracker = CommitmentUpgradeTracker::new();
+        let blockhash = Hash::new_unique();
+
+        assert_eq!(
+            tracker.update(&block(10, blockhash, CommitmentConfig::processed())),
+            None
+        );
+        let confirmed = tracker
+            .update(&block(10, blockhash, CommitmentConfig::confirmed()))
+            .unwrap();
+        assert_eq!(
+            confirmed.previous_commitment,
+            Some(CommitmentLevel::Processed)
+        );
+        assert!(!confirmed.conflict);
+        let finalized = tracker
+            .update(&block(10, blockhash, CommitmentConfig::finalized()))
+            .unwrap();
+        assert_eq!(
+            finalized.previous_commitment,
+            Some(CommitmentLevel::Confirmed)
+        );
+        assert!(!finalized.conflict);
+
+        // duplicates and late blocks of lower commitments are no upgrades
+        assert_eq!(
+            tracker.update(&block(10, blockhash, CommitmentConfig::finalized())),
+            None
+        );
+        assert_eq!(
+            tracker.update(&block(10, blockhash, CommitmentConfig::confirmed())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_flags_a_changed_block_as_conflict() {
+        let mut tracker = CommitmentUpgradeTracker::new();
+        let forked = Hash::new_unique();
+        let blockhash = Hash::new_unique();
+
+        tracker.update(&block(10, forked, CommitmentConfig::processed()));
+        let confirmed = tracker
+            .update(&block(10, blockhash, CommitmentConfig::confirmed()))
+            .unwrap();
+        assert!(confirmed.conflict);
+        assert_eq!(confirmed.previous_blockhash, Some(forked));
+        assert_eq!(confirmed.blockhash, blockhash);
+    }
+
+    #[test]
+    fn test_upgrade_of_an_unseen_slot() {
+        let mut tracker = CommitmentUpgradeTracker::new();
+        let blockhash = Hash::new_unique();
+
+        let finalized = tracker
+            .update(&block(10, blockhash, CommitmentConfig::finalized()))
+            .unwrap();
+        assert_eq!(finalized.previous_commitment, None);
+        assert!(!finalized.conflict);
+    }
+}
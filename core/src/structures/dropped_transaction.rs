@@ -0,0 +1,42 @@
+use prometheus::{opts, register_int_counter_vec, IntCounterVec};
+
+lazy_static::lazy_static! {
+    static ref TXS_DROPPED: IntCounterVec =
+    register_int_counter_vec!(opts!("literpc_txs_dropped", "Number of transactions dropped before reaching a leader, by reason"), &["reason"]).unwrap();
+}
+
+/// why a transaction (or one send attempt of it) was dropped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropReason {
+    /// the recent blockhash is unknown, the transaction is rejected on submission
+    BlockhashNotFound,
+    /// the transaction expired before it was confirmed
+    BlockhashExpired,
+    /// the transaction was already sent
+    Duplicate,
+    /// the send queue was closed or the receiver of a leader connection fell behind
+    QueueFull,
+    /// there was no connection to any leader or sending over the connection failed
+    ConnectionFailure,
+    /// the send rate to the leader was reduced and too many sends were already waiting
+    SendPaced,
+}
+
+impl DropReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DropReason::BlockhashNotFound => "blockhash_not_found",
+            DropReason::BlockhashExpired => "blockhash_expired",
+            DropReason::Duplicate => "duplicate",
+            DropReason::QueueFull => "queue_full",
+            DropReason::ConnectionFailure => "connection_failure",
+            DropReason::SendPaced => "send_paced",
+        }
+    }
+}
+
+pub fn record_dropped_transactions(reason: DropReason, count: u64) {
+    TXS_DROPPED
+        .with_label_values(&[reason.as_str()])
+        .inc_by(count);
+}
@@ -0,0 +1,42 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::time_utils::unix_timestamp_ms;
+
+/// progress of a long running loop, beaten on every iteration including the idle ones
+#[derive(Clone)]
+pub struct Heartbeat {
+    // unix timestamp in ms
+    last_beat: Arc<AtomicU64>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            last_beat: Arc::new(AtomicU64::new(unix_timestamp_ms())),
+        }
+    }
+}
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        self.last_beat.store(unix_timestamp_ms(), Ordering::Relaxed);
+    }
+
+    /// time since the last beat
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(
+            unix_timestamp_ms().saturating_sub(self.last_beat.load(Ordering::Relaxed)),
+        )
+    }
+}
+
+/// heartbeats of the loops the liveness of lite-rpc is derived from
+#[derive(Clone, Default)]
+pub struct LoopHeartbeats {
+    /// block processing of the data caching service
+    pub blocks: Heartbeat,
+    /// batching and forwarding of the sent transactions
+    pub transactions: Heartbeat,
+}
@@ -29,15 +29,18 @@ impl Default for IdentityStakesData {
 
 #[derive(Debug, Clone)]
 pub struct IdentityStakes {
-    identity: Pubkey,
+    identity: Arc<RwLock<Pubkey>>,
     stakes_data: Arc<RwLock<IdentityStakesData>>,
+    // activated stake by node identity, of the last vote accounts update
+    node_stakes: Arc<RwLock<HashMap<Pubkey, u64>>>,
 }
 
 impl IdentityStakes {
     pub fn new(identity: Pubkey) -> Self {
         Self {
-            identity,
+            identity: Arc::new(RwLock::new(identity)),
             stakes_data: Arc::new(RwLock::new(IdentityStakesData::default())),
+            node_stakes: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -45,6 +48,21 @@ impl IdentityStakes {
         *self.stakes_data.read().await
     }
 
+    /// activated stake of a node, none before the first vote accounts update or if it has no vote account
+    pub async fn get_node_stake(&self, node: &Pubkey) -> Option<u64> {
+        self.node_stakes.read().await.get(node).copied()
+    }
+
+    pub async fn get_identity(&self) -> Pubkey {
+        *self.identity.read().await
+    }
+
+    // stakes are reset to unstaked until the next vote accounts update for the new identity
+    pub async fn set_identity(&self, identity: Pubkey) {
+        *self.identity.write().await = identity;
+        *self.stakes_data.write().await = IdentityStakesData::default();
+    }
+
     pub async fn update_stakes_for_identity(&self, vote_accounts: RpcVoteAccountStatus) {
         let map_of_stakes: HashMap<String, u64> = vote_accounts
             .current
@@ -52,8 +70,13 @@ impl IdentityStakes {
             .chain(vote_accounts.delinquent.iter())
             .map(|x| (x.node_pubkey.clone(), x.activated_stake))
             .collect();
+        *self.node_stakes.write().await = map_of_stakes
+            .iter()
+            .filter_map(|(node, stakes)| Some((node.parse().ok()?, *stakes)))
+            .collect();
 
-        if let Some(stakes) = map_of_stakes.get(&self.identity.to_string()) {
+        let identity = self.get_identity().await;
+        if let Some(stakes) = map_of_stakes.get(&identity.to_string()) {
             let only_stakes = map_of_stakes.iter().map(|x| *x.1).collect_vec();
             let identity_stakes = IdentityStakesData {
                 peer_type: ConnectionPeerType::Staked,
@@ -2,7 +2,13 @@
 
 pub mod account_data;
 pub mod account_filter;
+pub mod block_info;
+pub mod block_ordering;
+pub mod clock_update;
+pub mod commitment_upgrade;
+pub mod dropped_transaction;
 pub mod epoch;
+pub mod heartbeat;
 pub mod identity_stakes;
 pub mod leader_data;
 pub mod leaderschedule;
@@ -1,40 +1,123 @@
+use std::fmt;
+
+use base64::Engine;
+use bytes::Bytes;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
 use solana_sdk::message::v0::MessageAddressTableLookup;
+use solana_sdk::message::VersionedMessage;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
 use solana_sdk::{slot_history::Slot, transaction::TransactionError};
 use solana_transaction_status::Reward;
 
-#[derive(Debug, Clone)]
+// signatures, hashes and pubkeys are serialized as their base58 string, so stored blocks keep their format
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionInfo {
-    pub signature: String,
+    #[serde(with = "serde_base58")]
+    pub signature: Signature,
     pub is_vote: bool,
     pub err: Option<TransactionError>,
     pub cu_requested: Option<u32>,
     pub prioritization_fees: Option<u64>,
     pub cu_consumed: Option<u64>,
-    pub recent_blockhash: String,
-    pub message: String,
+    #[serde(with = "serde_base58")]
+    pub recent_blockhash: Hash,
+    pub message: TransactionMessage,
     pub writable_accounts: Vec<Pubkey>,
     pub readable_accounts: Vec<Pubkey>,
     pub address_lookup_tables: Vec<MessageAddressTableLookup>,
+    // programs invoked by the top level instructions, deduplicated
+    pub program_ids: Vec<Pubkey>,
+}
+
+/// Serialized transaction message. The bytes are kept as they are and only base64 encoded where a
+/// consumer asks for it; serde uses the base64 string, so stored blocks keep their format.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct TransactionMessage(Bytes);
+
+impl TransactionMessage {
+    pub fn new(bytes: impl Into<Bytes>) -> Self {
+        Self(bytes.into())
+    }
+
+    pub fn from_base64(encoded: &str) -> anyhow::Result<Self> {
+        Ok(Self(
+            base64::engine::general_purpose::STANDARD
+                .decode(encoded)?
+                .into(),
+        ))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(&self.0)
+    }
+
+    pub fn to_versioned_message(&self) -> anyhow::Result<VersionedMessage> {
+        Ok(bincode::deserialize(&self.0)?)
+    }
+}
+
+impl fmt::Debug for TransactionMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TransactionMessage({} bytes)", self.0.len())
+    }
+}
+
+impl Serialize for TransactionMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_base64())
+    }
+}
+
+impl<'de> Deserialize<'de> for TransactionMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        Self::from_base64(&encoded).map_err(serde::de::Error::custom)
+    }
 }
 
 // TODO try to remove Clone
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProducedBlock {
     pub transactions: Vec<TransactionInfo>,
-    pub leader_id: Option<String>,
-    pub blockhash: String,
+    #[serde(with = "serde_base58::option")]
+    pub leader_id: Option<Pubkey>,
+    #[serde(with = "serde_base58")]
+    pub blockhash: Hash,
     pub block_height: u64,
     pub slot: Slot,
     pub parent_slot: Slot,
     pub block_time: u64,
     pub commitment_config: CommitmentConfig,
-    pub previous_blockhash: String,
+    #[serde(with = "serde_base58")]
+    pub previous_blockhash: Hash,
     pub rewards: Option<Vec<Reward>>,
 }
 
 impl ProducedBlock {
+    /// Use only for test.
+    /// an empty block with unique blockhashes on top of the previous slot, its block height is the slot
+    pub fn new_for_tests(slot: Slot, commitment_config: CommitmentConfig) -> Self {
+        ProducedBlock {
+            transactions: vec![],
+            leader_id: None,
+            blockhash: Hash::new_unique(),
+            block_height: slot,
+            slot,
+            parent_slot: slot.saturating_sub(1),
+            block_time: 0,
+            commitment_config,
+            previous_blockhash: Hash::new_unique(),
+            rewards: None,
+        }
+    }
+
     /// moving commitment level to finalized
     pub fn to_finalized_block(&self) -> Self {
         ProducedBlock {
@@ -50,4 +133,128 @@ impl ProducedBlock {
             ..self.clone()
         }
     }
+
+    /// estimated heap memory of the block in bytes, for memory accounting
+    pub fn estimated_size(&self) -> usize {
+        let transactions: usize = self
+            .transactions
+            .iter()
+            .map(|tx| {
+                std::mem::size_of::<TransactionInfo>()
+                    + tx.message.as_bytes().len()
+                    + (tx.writable_accounts.len()
+                        + tx.readable_accounts.len()
+                        + tx.program_ids.len())
+                        * std::mem::size_of::<Pubkey>()
+                    + tx.address_lookup_tables.len()
+                        * std::mem::size_of::<MessageAddressTableLookup>()
+            })
+            .sum();
+        std::mem::size_of::<ProducedBlock>()
+            + self
+                .rewards
+                .as_ref()
+                .map_or(0, |rewards| rewards.len() * std::mem::size_of::<Reward>())
+            + transactions
+    }
+}
+
+mod serde_base58 {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<T: Display, S: Serializer>(
+            value: &Option<T>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => serializer.serialize_some(&value.to_string()),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+        where
+            T: FromStr,
+            T::Err: Display,
+            D: Deserializer<'de>,
+        {
+            Option::<String>::deserialize(deserializer)?
+                .map(|value| value.parse().map_err(serde::de::Error::custom))
+                .transpose()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transaction_message_is_serialized_like_the_base64_string() {
+        let message = TransactionMessage::new(vec![1u8, 2, 3, 255]);
+        let encoded = message.to_base64();
+
+        assert_eq!(
+            bincode::serialize(&message).unwrap(),
+            bincode::serialize(&encoded).unwrap()
+        );
+        let decoded: TransactionMessage =
+            bincode::deserialize(&bincode::serialize(&encoded).unwrap()).unwrap();
+        assert_eq!(decoded, message);
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            format!("\"{encoded}\"")
+        );
+    }
+
+    #[test]
+    fn block_hashes_and_leader_are_serialized_as_base58_strings() {
+        let blockhash = Hash::new_unique();
+        let leader_id = Pubkey::new_unique();
+        let block = ProducedBlock {
+            transactions: vec![],
+            leader_id: Some(leader_id),
+            blockhash,
+            block_height: 1,
+            slot: 1,
+            parent_slot: 0,
+            block_time: 0,
+            commitment_config: CommitmentConfig::confirmed(),
+            previous_blockhash: Hash::default(),
+            rewards: None,
+        };
+
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(json["blockhash"], blockhash.to_string());
+        assert_eq!(json["leader_id"], leader_id.to_string());
+
+        let decoded: ProducedBlock =
+            bincode::deserialize(&bincode::serialize(&block).unwrap()).unwrap();
+        assert_eq!(decoded.blockhash, blockhash);
+        assert_eq!(decoded.leader_id, Some(leader_id));
+        assert_eq!(decoded.previous_blockhash, Hash::default());
+    }
 }
@@ -1,11 +1,13 @@
-use solana_sdk::slot_history::Slot;
+use solana_sdk::{signature::Signature, slot_history::Slot};
 
 pub type WireTransaction = Vec<u8>;
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub struct SentTransactionInfo {
-    pub signature: String,
+    pub signature: Signature,
     pub slot: Slot,
     pub transaction: WireTransaction,
     pub last_valid_block_height: u64,
+    /// id of the rpc request which submitted the transaction, used to correlate logs
+    pub request_id: Option<String>,
 }
@@ -0,0 +1,13 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// unix timestamp in ms of now
+pub fn unix_timestamp_ms() -> u64 {
+    to_unix_timestamp_ms(SystemTime::now())
+}
+
+/// unix timestamp in ms of `time`, 0 for times before the epoch
+pub fn to_unix_timestamp_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
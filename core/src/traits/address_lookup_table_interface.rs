@@ -1,3 +1,4 @@
+use crate::structures::produced_block::TransactionInfo;
 use async_trait::async_trait;
 use solana_sdk::{message::v0::MessageAddressTableLookup, pubkey::Pubkey};
 
@@ -12,4 +13,29 @@ pub trait AddressLookupTableInterface: Send + Sync {
         &self,
         message_address_table_lookups: &[&MessageAddressTableLookup],
     );
+
+    /// writable and readable accounts of the transaction, including the ones loaded from its lookup tables;
+    /// addresses of tables which are not loaded yet are missing unless the block source already added them
+    async fn resolve_transaction_accounts(
+        &self,
+        transaction: &TransactionInfo,
+    ) -> (Vec<Pubkey>, Vec<Pubkey>) {
+        let mut writable_accounts = transaction.writable_accounts.clone();
+        let mut readable_accounts = transaction.readable_accounts.clone();
+        for lookup in &transaction.address_lookup_tables {
+            let (alts_w, alts_r) = self.resolve_addresses_from_lookup_table(lookup).await;
+            // an account is loaded once per transaction, known ones come from the block source
+            writable_accounts.extend(
+                alts_w
+                    .into_iter()
+                    .filter(|account| !transaction.writable_accounts.contains(account)),
+            );
+            readable_accounts.extend(
+                alts_r
+                    .into_iter()
+                    .filter(|account| !transaction.readable_accounts.contains(account)),
+            );
+        }
+        (writable_accounts, readable_accounts)
+    }
 }
@@ -0,0 +1,52 @@
+use std::ops::RangeInclusive;
+
+use async_trait::async_trait;
+use solana_sdk::clock::UnixTimestamp;
+use solana_sdk::signature::Signature;
+use solana_sdk::slot_history::Slot;
+
+use crate::structures::produced_block::{ProducedBlock, TransactionInfo};
+
+/// Persistent storage for block history, e.g. postgres or an object store
+#[async_trait]
+pub trait BlockStorage: Send + Sync {
+    // stores the block; storing a slot again must not fail
+    async fn save_block(&self, block: &ProducedBlock) -> anyhow::Result<()>;
+
+    // read-only storages are not given the new blocks and refuse to save them
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    async fn query_block(&self, slot: Slot) -> anyhow::Result<ProducedBlock>;
+
+    // oldest and newest stored slot, might contain gaps
+    async fn get_slot_range(&self) -> RangeInclusive<Slot>;
+
+    // first stored slot of the range, None if none of its slots is stored; errors of the storage are
+    // returned instead of being taken for skipped slots
+    async fn query_first_slot(&self, slots: RangeInclusive<Slot>) -> anyhow::Result<Option<Slot>>;
+
+    // block time of a stored block, None if the slot is not stored; storages which can read it
+    // without loading the transactions of the block override it
+    async fn query_block_time(&self, slot: Slot) -> anyhow::Result<Option<UnixTimestamp>> {
+        if self.query_first_slot(slot..=slot).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.query_block(slot).await?.block_time as UnixTimestamp,
+        ))
+    }
+
+    async fn is_block_in_range(&self, slot: Slot) -> bool {
+        self.get_slot_range().await.contains(&slot)
+    }
+
+    // slot and transaction by signature; storages without a signature index do not find any
+    async fn query_transaction(
+        &self,
+        _signature: &Signature,
+    ) -> anyhow::Result<Option<(Slot, TransactionInfo)>> {
+        Ok(None)
+    }
+}
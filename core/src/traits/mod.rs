@@ -1,3 +1,4 @@
 pub mod address_lookup_table_interface;
+pub mod block_storage;
 pub mod leaders_fetcher_interface;
 pub mod subscription_sink;
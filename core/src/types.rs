@@ -1,15 +1,31 @@
 use std::sync::Arc;
 
 use solana_rpc_client_api::response::{RpcContactInfo, RpcVoteAccountStatus};
-use tokio::sync::broadcast::Receiver;
+use tokio::sync::{broadcast::Receiver, watch};
 
 use crate::{
-    structures::{produced_block::ProducedBlock, slot_notification::SlotNotification},
+    stores::block_information_store::BlockInformation,
+    structures::{
+        block_info::BlockInfo, block_ordering::BlockOrderingEvent, clock_update::ClockUpdate,
+        commitment_upgrade::BlockCommitmentUpgrade, epoch::Epoch, produced_block::ProducedBlock,
+        slot_notification::SlotNotification,
+    },
     traits::subscription_sink::SubscriptionSink,
 };
 
 pub type BlockStream = Receiver<ProducedBlock>;
+pub type BlockInfoStream = Receiver<BlockInfo>;
+pub type CommitmentUpgradeStream = Receiver<BlockCommitmentUpgrade>;
+pub type BlockOrderingEventStream = Receiver<BlockOrderingEvent>;
+pub type ClockStream = Receiver<ClockUpdate>;
 pub type SlotStream = Receiver<SlotNotification>;
 pub type VoteAccountStream = Receiver<RpcVoteAccountStatus>;
+pub type EpochStream = Receiver<Epoch>;
 pub type ClusterInfoStream = Receiver<Vec<RpcContactInfo>>;
 pub type SubscptionHanderSink = Arc<dyn SubscriptionSink>;
+
+// views on the latest value for consumers which do not need every notification, they cannot lag
+pub type LatestSlot = watch::Receiver<SlotNotification>;
+pub type LatestBlock = watch::Receiver<BlockInformation>;
+// none until the first clock was received
+pub type LatestClock = watch::Receiver<Option<ClockUpdate>>;
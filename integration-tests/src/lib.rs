@@ -0,0 +1,27 @@
+use std::{future::Future, time::Duration};
+
+pub mod lite_rpc_node;
+pub mod test_validator;
+
+/// a port which was free a moment ago
+pub fn free_port() -> anyhow::Result<u16> {
+    Ok(std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port())
+}
+
+/// polls the condition every 500ms until it holds, fails after the timeout
+pub async fn wait_until<F, Fut>(timeout: Duration, mut condition: F) -> anyhow::Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let started = tokio::time::Instant::now();
+    while !condition().await {
+        if started.elapsed() > timeout {
+            anyhow::bail!("condition not met after {timeout:?}");
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use lite_rpc::{
+    builder::{LiteRpc, LiteRpcBuilder, RpcServerConfig, TransactionSendingConfig},
+    MAX_NB_OF_CONNECTIONS_WITH_LEADERS,
+};
+use log::info;
+use solana_lite_rpc_cluster_endpoints::geyser_grpc_connector::{
+    GrpcConnectionTimeouts, GrpcSourceConfig,
+};
+use solana_lite_rpc_services::{
+    quic_connection_utils::QuicConnectionParameters,
+    tpu_utils::{
+        send_pacer::SendPacingConfig, tpu_connection_path::TpuConnectionPath,
+        tpu_service::TpuServiceConfig,
+    },
+};
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::{free_port, test_validator::TestValidator, wait_until};
+
+// lite-rpc waits for the first finalized block, 32 slots on the test validator
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// lite-rpc embedded in the test process, streaming from the geyser plugin of the validator if it
+/// has one and polling its rpc otherwise
+pub struct LiteRpcNode {
+    lite_rpc: LiteRpc,
+    http_addr: String,
+}
+
+impl LiteRpcNode {
+    /// starts lite-rpc with transaction sending and the json rpc server, returns once it answers
+    pub async fn start(validator: &TestValidator) -> anyhow::Result<Self> {
+        let http_addr = format!("127.0.0.1:{}", free_port()?);
+        let ws_addr = format!("127.0.0.1:{}", free_port()?);
+
+        let mut builder =
+            LiteRpcBuilder::new(Arc::new(RpcClient::new(validator.rpc_addr().to_string())))
+                .with_transaction_sending(TransactionSendingConfig::new(tpu_config()))
+                .with_rpc_server(RpcServerConfig::new(http_addr.clone(), ws_addr));
+        if let Some(grpc_addr) = validator.grpc_addr() {
+            info!("Streaming from the geyser plugin at {grpc_addr}");
+            let timeouts = GrpcConnectionTimeouts {
+                connect_timeout: Duration::from_secs(5),
+                request_timeout: Duration::from_secs(5),
+                subscribe_timeout: Duration::from_secs(5),
+                receive_timeout: Duration::from_secs(5),
+            };
+            builder = builder.with_grpc_sources(vec![GrpcSourceConfig::new(
+                grpc_addr.to_string(),
+                None,
+                None,
+                timeouts,
+            )]);
+        }
+        let lite_rpc = tokio::time::timeout(STARTUP_TIMEOUT, builder.start())
+            .await
+            .context("lite-rpc did not start")??;
+
+        let node = Self {
+            lite_rpc,
+            http_addr,
+        };
+        let rpc_client = node.rpc_client();
+        wait_until(Duration::from_secs(30), || async {
+            rpc_client.get_slot().await.is_ok()
+        })
+        .await
+        .context("lite-rpc does not answer")?;
+        info!("lite-rpc serving at {}", node.http_addr);
+        Ok(node)
+    }
+
+    pub fn rpc_client(&self) -> RpcClient {
+        RpcClient::new_with_commitment(
+            format!("http://{}", self.http_addr),
+            CommitmentConfig::confirmed(),
+        )
+    }
+
+    pub fn lite_rpc(&self) -> &LiteRpc {
+        &self.lite_rpc
+    }
+}
+
+impl Drop for LiteRpcNode {
+    fn drop(&mut self) {
+        self.lite_rpc.shutdown();
+    }
+}
+
+fn tpu_config() -> TpuServiceConfig {
+    TpuServiceConfig {
+        fanout_slots: 4,
+        maximum_transaction_in_queue: 20000,
+        quic_connection_params: QuicConnectionParameters {
+            connection_timeout: Duration::from_secs(1),
+            connection_retry_count: 10,
+            finalize_timeout: Duration::from_millis(1000),
+            max_number_of_connections: MAX_NB_OF_CONNECTIONS_WITH_LEADERS,
+            unistream_timeout: Duration::from_millis(500),
+            write_timeout: Duration::from_secs(1),
+            number_of_transactions_per_unistream: 1,
+            send_pacing: SendPacingConfig::disabled(),
+        },
+        tpu_connection_path: TpuConnectionPath::QuicDirectPath,
+        slot_edge_forwarding_window: None,
+    }
+}
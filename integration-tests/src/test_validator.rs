@@ -0,0 +1,142 @@
+use std::{
+    env,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use log::info;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+
+use crate::{free_port, wait_until};
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// how the test validator is started, read from the environment by [`TestValidatorConfig::from_env`]
+#[derive(Clone, Debug)]
+pub struct TestValidatorConfig {
+    /// `SOLANA_TEST_VALIDATOR`, `solana-test-validator` from the PATH by default
+    pub binary: String,
+    /// `GEYSER_PLUGIN_CONFIG`, config of a yellowstone gRPC plugin loaded by the validator
+    pub geyser_plugin_config: Option<PathBuf>,
+    /// `GEYSER_GRPC_ADDR`, the address the plugin listens on, used with the plugin config
+    pub geyser_grpc_addr: String,
+}
+
+impl TestValidatorConfig {
+    pub fn from_env() -> Self {
+        Self {
+            binary: env::var("SOLANA_TEST_VALIDATOR")
+                .unwrap_or_else(|_| "solana-test-validator".to_string()),
+            geyser_plugin_config: env::var("GEYSER_PLUGIN_CONFIG").ok().map(PathBuf::from),
+            geyser_grpc_addr: env::var("GEYSER_GRPC_ADDR")
+                .unwrap_or_else(|_| "http://127.0.0.1:10000".to_string()),
+        }
+    }
+}
+
+/// a solana-test-validator process with a fresh ledger on random ports, killed when dropped
+pub struct TestValidator {
+    process: Child,
+    ledger: PathBuf,
+    rpc_addr: String,
+    ws_addr: String,
+    grpc_addr: Option<String>,
+}
+
+impl TestValidator {
+    /// starts the validator and waits until its rpc answers healthy
+    pub async fn start(config: TestValidatorConfig) -> anyhow::Result<Self> {
+        let rpc_port = free_port()?;
+        let ledger = env::temp_dir().join(format!("lite-rpc-test-ledger-{rpc_port}"));
+        let mut command = Command::new(&config.binary);
+        command
+            .arg("--ledger")
+            .arg(&ledger)
+            .arg("--reset")
+            .arg("--quiet")
+            .args(["--bind-address", "127.0.0.1"])
+            .args(["--rpc-port", &rpc_port.to_string()])
+            .args(["--faucet-port", &free_port()?.to_string()])
+            .args(["--gossip-port", &free_port()?.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit());
+        if let Some(geyser_plugin_config) = &config.geyser_plugin_config {
+            command
+                .arg("--geyser-plugin-config")
+                .arg(geyser_plugin_config);
+        }
+        let process = command
+            .spawn()
+            .with_context(|| format!("start {}, is it installed?", config.binary))?;
+
+        let mut validator = Self {
+            process,
+            ledger,
+            rpc_addr: format!("http://127.0.0.1:{rpc_port}"),
+            // the websocket port is the next one after the rpc port
+            ws_addr: format!("ws://127.0.0.1:{}", rpc_port + 1),
+            grpc_addr: config
+                .geyser_plugin_config
+                .map(|_| config.geyser_grpc_addr.clone()),
+        };
+        info!("Starting test validator at {}", validator.rpc_addr);
+
+        let rpc_client = validator.rpc_client();
+        let started = Instant::now();
+        loop {
+            if let Some(status) = validator.process.try_wait()? {
+                bail!("test validator exited with {status}");
+            }
+            if rpc_client.get_health().await.is_ok() {
+                break;
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                bail!("test validator not healthy after {STARTUP_TIMEOUT:?}");
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        Ok(validator)
+    }
+
+    pub fn rpc_addr(&self) -> &str {
+        &self.rpc_addr
+    }
+
+    pub fn ws_addr(&self) -> &str {
+        &self.ws_addr
+    }
+
+    /// the address of the geyser plugin if the validator was started with one
+    pub fn grpc_addr(&self) -> Option<&str> {
+        self.grpc_addr.as_deref()
+    }
+
+    pub fn rpc_client(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.rpc_addr.clone(), CommitmentConfig::confirmed())
+    }
+
+    /// airdrops from the faucet of the validator and waits for the confirmation
+    pub async fn airdrop(&self, pubkey: &Pubkey, lamports: u64) -> anyhow::Result<()> {
+        let rpc_client = self.rpc_client();
+        let signature = rpc_client.request_airdrop(pubkey, lamports).await?;
+        wait_until(Duration::from_secs(30), || async {
+            rpc_client
+                .confirm_transaction(&signature)
+                .await
+                .unwrap_or_default()
+        })
+        .await
+        .context("airdrop not confirmed")
+    }
+}
+
+impl Drop for TestValidator {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = std::fs::remove_dir_all(&self.ledger);
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use solana_lite_rpc_integration_tests::{
+    lite_rpc_node::LiteRpcNode,
+    test_validator::{TestValidator, TestValidatorConfig},
+    wait_until,
+};
+use solana_rpc_client_api::config::RpcBlockConfig;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_transaction,
+};
+use solana_transaction_status::{TransactionConfirmationStatus, UiTransactionEncoding};
+use tracing_subscriber::EnvFilter;
+
+// run with: cargo test -p solana-lite-rpc-integration-tests -- --ignored --test-threads=1
+// every test starts its own validator and lite-rpc
+
+fn init_logging() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+}
+
+async fn start() -> (TestValidator, LiteRpcNode) {
+    init_logging();
+    let validator = TestValidator::start(TestValidatorConfig::from_env())
+        .await
+        .unwrap();
+    let lite_rpc = LiteRpcNode::start(&validator).await.unwrap();
+    (validator, lite_rpc)
+}
+
+#[tokio::test]
+#[ignore = "requires solana-test-validator"]
+async fn send_land_status_and_get_block() {
+    let (validator, lite_rpc) = start().await;
+    let rpc_client = lite_rpc.rpc_client();
+
+    let payer = Keypair::new();
+    validator
+        .airdrop(&payer.pubkey(), LAMPORTS_PER_SOL)
+        .await
+        .unwrap();
+
+    // send
+    let blockhash = rpc_client.get_latest_blockhash().await.unwrap();
+    let tx = system_transaction::transfer(&payer, &Pubkey::new_unique(), 1_000_000, blockhash);
+    let signature = rpc_client.send_transaction(&tx).await.unwrap();
+    assert_eq!(signature, tx.signatures[0]);
+
+    // land and status
+    let slot = wait_for_status(
+        &lite_rpc,
+        &signature,
+        TransactionConfirmationStatus::Finalized,
+    )
+    .await;
+
+    // getBlock
+    let block = rpc_client
+        .get_block_with_config(
+            slot,
+            RpcBlockConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(CommitmentConfig::finalized()),
+                max_supported_transaction_version: Some(0),
+                ..RpcBlockConfig::default()
+            },
+        )
+        .await
+        .unwrap();
+    let landed = block
+        .transactions
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|tx| tx.transaction.decode())
+        .any(|tx| tx.signatures[0] == signature);
+    assert!(landed, "transaction {signature} not in block {slot}");
+
+    // the validator agrees
+    assert!(
+        validator
+            .rpc_client()
+            .confirm_transaction_with_commitment(&signature, CommitmentConfig::finalized())
+            .await
+            .unwrap()
+            .value
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires solana-test-validator"]
+async fn blockhash_and_slot_follow_the_validator() {
+    let (validator, lite_rpc) = start().await;
+    let rpc_client = lite_rpc.rpc_client();
+
+    let first_blockhash = rpc_client.get_latest_blockhash().await.unwrap();
+    assert!(rpc_client
+        .is_blockhash_valid(&first_blockhash, CommitmentConfig::confirmed())
+        .await
+        .unwrap());
+    wait_until(Duration::from_secs(10), || async {
+        rpc_client.get_latest_blockhash().await.unwrap() != first_blockhash
+    })
+    .await
+    .unwrap();
+
+    let validator_slot = validator.rpc_client().get_slot().await.unwrap();
+    wait_until(Duration::from_secs(10), || async {
+        rpc_client.get_slot().await.unwrap() >= validator_slot
+    })
+    .await
+    .unwrap();
+}
+
+// slot of the transaction once it reached the confirmation status
+async fn wait_for_status(
+    lite_rpc: &LiteRpcNode,
+    signature: &Signature,
+    confirmation_status: TransactionConfirmationStatus,
+) -> u64 {
+    let rpc_client = lite_rpc.rpc_client();
+    let started = Instant::now();
+    loop {
+        let status = rpc_client
+            .get_signature_statuses(&[*signature])
+            .await
+            .unwrap()
+            .value
+            .remove(0);
+        if let Some(status) = status {
+            if status.confirmation_status() == confirmation_status {
+                assert!(status.err.is_none(), "transaction failed: {:?}", status.err);
+                return status.slot;
+            }
+        }
+        assert!(
+            started.elapsed() < Duration::from_secs(60),
+            "transaction {signature} not {confirmation_status:?}"
+        );
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
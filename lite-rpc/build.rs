@@ -0,0 +1,13 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protobuf_src::protoc());
+
+    tonic_build::compile_protos("proto/lite_rpc.proto")?;
+    // the sink records are also published as JSON
+    tonic_build::configure()
+        .build_client(false)
+        .build_server(false)
+        .type_attribute(".lite_rpc.sink", "#[derive(serde::Serialize)]")
+        .type_attribute(".lite_rpc.sink", "#[serde(rename_all = \"camelCase\")]")
+        .compile(&["proto/sink.proto"], &["proto"])?;
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::Context;
+use log::{info, warn};
+use prometheus::{opts, register_int_counter_vec, IntCounterVec};
+use solana_lite_rpc_accounts::{
+    account_service::AccountService, account_store_interface::AccountStorageInterface,
+    inmemory_account_store::InmemoryAccountStore,
+};
+use solana_lite_rpc_core::{
+    commitment_utils::Commitment,
+    structures::{
+        account_data::{AccountNotificationMessage, AccountStream},
+        account_filter::{AccountFilterSets, AccountFilters, ParsedAccountFilter},
+    },
+    types::BlockStream,
+    AnyhowJoinHandle,
+};
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+lazy_static::lazy_static! {
+    static ref ACCOUNT_FILTER_SET_UPDATES: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_account_filter_set_updates", "Processed account updates streamed into the account filter sets"), &["set"]).unwrap();
+}
+
+const ACCOUNT_FILTER_SET_CHANNEL_SIZE: usize = 1024;
+
+pub fn parse_account_filter_sets(
+    account_filter_sets: &Option<String>,
+) -> anyhow::Result<AccountFilterSets> {
+    match account_filter_sets {
+        Some(account_filter_sets) => {
+            serde_json::from_str(account_filter_sets).context("Account filter sets should be valid")
+        }
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+/// the filters of all sets, subscribed from the geyser sources next to the account filters
+pub fn merged_account_filters(account_filter_sets: &AccountFilterSets) -> AccountFilters {
+    account_filter_sets.values().flatten().cloned().collect()
+}
+
+struct AccountFilterSet {
+    filters: AccountFilters,
+    account_service: AccountService,
+    account_store: Arc<InmemoryAccountStore>,
+    sender: broadcast::Sender<AccountNotificationMessage>,
+}
+
+/// splits the processed account stream into one account store and notification stream per named
+/// filter set, so consumers of a set are isolated from the updates of the other sets
+pub struct AccountFilterSetsService {
+    sets: BTreeMap<String, AccountFilterSet>,
+}
+
+impl AccountFilterSetsService {
+    pub fn new(account_filter_sets: AccountFilterSets) -> Self {
+        let sets = account_filter_sets
+            .into_iter()
+            .map(|(name, filters)| {
+                let account_store = Arc::new(InmemoryAccountStore::new());
+                let account_storage: Arc<dyn AccountStorageInterface> = account_store.clone();
+                let (sender, _) = broadcast::channel(ACCOUNT_FILTER_SET_CHANNEL_SIZE);
+                let account_service = AccountService::new(account_storage);
+                account_service.set_filters(filters.clone());
+                let set = AccountFilterSet {
+                    filters,
+                    account_service,
+                    account_store,
+                    sender,
+                };
+                (name, set)
+            })
+            .collect();
+        Self { sets }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    /// the account service of a set, its notification sender streams the accounts of the set
+    pub fn account_service(&self, name: &str) -> Option<AccountService> {
+        self.sets.get(name).map(|set| set.account_service.clone())
+    }
+
+    pub fn account_stores(&self) -> Vec<Arc<InmemoryAccountStore>> {
+        self.sets
+            .values()
+            .map(|set| set.account_store.clone())
+            .collect()
+    }
+
+    pub async fn populate_from_rpc(
+        &self,
+        rpc_client: Arc<RpcClient>,
+        max_request_in_parallel: usize,
+    ) -> anyhow::Result<()> {
+        for (name, set) in &self.sets {
+            info!("Loading the accounts of filter set {name}");
+            set.account_service
+                .populate_from_rpc(rpc_client.clone(), &set.filters, max_request_in_parallel)
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub fn start(
+        &self,
+        mut account_stream: AccountStream,
+        block_stream: BlockStream,
+    ) -> AnyhowJoinHandle {
+        let sets = self
+            .sets
+            .iter()
+            .map(|(name, set)| {
+                set.account_service
+                    .process_account_stream(set.sender.subscribe(), block_stream.resubscribe());
+                (
+                    name.clone(),
+                    set.filters
+                        .iter()
+                        .map(ParsedAccountFilter::new)
+                        .collect::<Vec<_>>(),
+                    set.account_store.clone(),
+                    set.sender.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        tokio::spawn(async move {
+            loop {
+                let account = match account_stream.recv().await {
+                    Ok(account) => account,
+                    Err(RecvError::Lagged(lagged)) => {
+                        warn!("account filter sets lagged by {lagged} account updates - continue");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => {
+                        anyhow::bail!("account filter sets account stream closed");
+                    }
+                };
+
+                for (name, filters, account_store, sender) in &sets {
+                    let matches = filters.iter().any(|filter| filter.allows(&account.data))
+                        // closed accounts are reassigned to the system program
+                        || account_store
+                            .get_account(account.data.pubkey, Commitment::Processed)
+                            .await
+                            .is_some();
+                    if matches {
+                        ACCOUNT_FILTER_SET_UPDATES.with_label_values(&[name]).inc();
+                        let _ = sender.send(account.clone());
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_lite_rpc_core::structures::{
+        account_data::{Account, AccountData},
+        account_filter::AccountFilter,
+    };
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+
+    fn account(pubkey: Pubkey, owner: Pubkey) -> AccountNotificationMessage {
+        AccountNotificationMessage {
+            data: AccountData {
+                pubkey,
+                account: Account {
+                    lamports: 1,
+                    data: vec![1, 2, 3].into(),
+                    owner,
+                    executable: false,
+                    rent_epoch: 0,
+                },
+                updated_slot: 1,
+            },
+            commitment: Commitment::Processed,
+        }
+    }
+
+    fn program_filter(program_id: Pubkey) -> AccountFilters {
+        vec![AccountFilter {
+            accounts: vec![],
+            program_id: Some(program_id.to_string()),
+            filters: None,
+        }]
+    }
+
+    #[tokio::test]
+    async fn test_accounts_are_routed_to_the_matching_sets() {
+        let program_a = Pubkey::new_unique();
+        let program_b = Pubkey::new_unique();
+        let service = AccountFilterSetsService::new(AccountFilterSets::from([
+            ("a".to_string(), program_filter(program_a)),
+            ("b".to_string(), program_filter(program_b)),
+        ]));
+        let mut set_a = service.sets["a"].sender.subscribe();
+        let mut set_b = service.sets["b"].sender.subscribe();
+        let (account_sender, account_stream) = broadcast::channel(16);
+        let (_block_sender, block_stream) = broadcast::channel(16);
+        let routing = service.start(account_stream, block_stream);
+
+        let of_a = Pubkey::new_unique();
+        let of_b = Pubkey::new_unique();
+        account_sender.send(account(of_a, program_a)).unwrap();
+        account_sender
+            .send(account(Pubkey::new_unique(), Pubkey::new_unique()))
+            .unwrap();
+        account_sender.send(account(of_b, program_b)).unwrap();
+
+        assert_eq!(set_a.recv().await.unwrap().data.pubkey, of_a);
+        assert_eq!(set_b.recv().await.unwrap().data.pubkey, of_b);
+        assert!(set_a.try_recv().is_err());
+        assert!(set_b.try_recv().is_err());
+        routing.abort();
+    }
+}
@@ -0,0 +1,213 @@
+use std::{collections::HashSet, str::FromStr, time::Duration};
+
+use anyhow::Context;
+use base64::Engine;
+use log::{debug, info, warn};
+use prometheus::{opts, register_int_counter, IntCounter};
+use serde::{Deserialize, Serialize};
+use solana_lite_rpc_core::{
+    commitment_utils::Commitment,
+    structures::account_data::{AccountNotificationMessage, AccountStream},
+    AnyhowJoinHandle,
+};
+use solana_rpc_client_api::client_error::reqwest::{self, Url};
+use solana_sdk::{commitment_config::CommitmentLevel, pubkey::Pubkey, slot_history::Slot};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::webhook_notifier::WebhookNotifier;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNT_WEBHOOKS_SENT: IntCounter =
+        register_int_counter!(opts!("literpc_account_webhooks_sent", "Number of account change webhooks delivered")).unwrap();
+    static ref ACCOUNT_WEBHOOKS_FAILED: IntCounter =
+        register_int_counter!(opts!("literpc_account_webhooks_failed", "Number of account change webhooks which could not be delivered")).unwrap();
+}
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+const WEBHOOK_ATTEMPTS: u32 = 5;
+// doubled after every failed attempt
+const WEBHOOK_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// an account sub-filter and the urls its changes are posted to, the accounts have to be
+/// covered by the account filters of lite-rpc to be streamed at all
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountWebhookConfig {
+    pub urls: Vec<String>,
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    #[serde(default)]
+    pub owners: Vec<String>,
+    /// base64 encoded account data in the payload
+    #[serde(default)]
+    pub include_data: bool,
+    #[serde(default = "AccountWebhookConfig::default_commitment")]
+    pub commitment: CommitmentLevel,
+    /// posts the changes of a named account filter set instead of all streamed accounts
+    #[serde(default)]
+    pub filter_set: Option<String>,
+}
+
+impl AccountWebhookConfig {
+    pub const fn default_commitment() -> CommitmentLevel {
+        CommitmentLevel::Confirmed
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountChangePayload {
+    pub pubkey: String,
+    pub slot: Slot,
+    pub commitment: CommitmentLevel,
+    pub owner: String,
+    pub lamports: u64,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    pub data_len: usize,
+    pub data: Option<String>,
+}
+
+struct AccountWebhook {
+    urls: Vec<Url>,
+    accounts: HashSet<Pubkey>,
+    owners: HashSet<Pubkey>,
+    include_data: bool,
+    commitment: Commitment,
+}
+
+impl AccountWebhook {
+    fn new(config: AccountWebhookConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(!config.urls.is_empty(), "account webhook needs a url");
+        anyhow::ensure!(
+            !config.accounts.is_empty() || !config.owners.is_empty(),
+            "account webhook needs accounts or owners"
+        );
+        let parse_pubkeys = |pubkeys: &[String]| {
+            pubkeys
+                .iter()
+                .map(|pubkey| Pubkey::from_str(pubkey).context("invalid account webhook pubkey"))
+                .collect::<anyhow::Result<HashSet<_>>>()
+        };
+        Ok(Self {
+            urls: config
+                .urls
+                .iter()
+                .map(|url| WebhookNotifier::parse_http_url(url))
+                .collect::<anyhow::Result<_>>()?,
+            accounts: parse_pubkeys(&config.accounts)?,
+            owners: parse_pubkeys(&config.owners)?,
+            include_data: config.include_data,
+            commitment: config.commitment.into(),
+        })
+    }
+
+    // accounts and owners are both applied if set
+    fn matches(&self, account: &AccountNotificationMessage) -> bool {
+        account.commitment == self.commitment
+            && (self.accounts.is_empty() || self.accounts.contains(&account.data.pubkey))
+            && (self.owners.is_empty() || self.owners.contains(&account.data.account.owner))
+    }
+
+    fn payload(&self, account: &AccountNotificationMessage) -> AccountChangePayload {
+        let data = &account.data;
+        AccountChangePayload {
+            pubkey: data.pubkey.to_string(),
+            slot: data.updated_slot,
+            commitment: account.commitment.into_commitment_level(),
+            owner: data.account.owner.to_string(),
+            lamports: data.account.lamports,
+            executable: data.account.executable,
+            rent_epoch: data.account.rent_epoch,
+            data_len: data.account.data.len(),
+            data: self
+                .include_data
+                .then(|| base64::engine::general_purpose::STANDARD.encode(&data.account.data)),
+        }
+    }
+}
+
+/// POSTs the changes of the accounts matching a sub-filter to webhook urls, meant for low-rate
+/// accounts like config or treasury accounts
+pub struct AccountWebhookSink {
+    webhooks: Vec<AccountWebhook>,
+    client: reqwest::Client,
+}
+
+impl AccountWebhookSink {
+    pub fn new(configs: Vec<AccountWebhookConfig>) -> anyhow::Result<Self> {
+        Ok(Self {
+            webhooks: configs
+                .into_iter()
+                .map(AccountWebhook::new)
+                .collect::<anyhow::Result<_>>()?,
+            client: reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()
+                .expect("webhook http client"),
+        })
+    }
+
+    pub fn start(self, mut account_stream: AccountStream) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            info!(
+                "Posting account changes to {} webhooks",
+                self.webhooks.len()
+            );
+            loop {
+                let account = match account_stream.recv().await {
+                    Ok(account) => account,
+                    Err(RecvError::Lagged(lagged)) => {
+                        warn!("account webhooks lagged by {lagged} account updates - continue");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => {
+                        anyhow::bail!("account webhooks account stream closed");
+                    }
+                };
+
+                for webhook in self.webhooks.iter().filter(|w| w.matches(&account)) {
+                    let body = serde_json::to_vec(&webhook.payload(&account))
+                        .expect("account change payload serializable");
+                    for url in &webhook.urls {
+                        self.post(url.clone(), body.clone(), account.data.pubkey);
+                    }
+                }
+            }
+        })
+    }
+
+    fn post(&self, url: Url, body: Vec<u8>, pubkey: Pubkey) {
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            let mut backoff = WEBHOOK_INITIAL_BACKOFF;
+            for attempt in 1..=WEBHOOK_ATTEMPTS {
+                let result = client
+                    .post(url.clone())
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body.clone())
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                match result {
+                    Ok(_) => {
+                        ACCOUNT_WEBHOOKS_SENT.inc();
+                        return;
+                    }
+                    Err(e) => {
+                        debug!("account webhook for {pubkey} to {url} failed: {e}");
+                    }
+                }
+                if attempt < WEBHOOK_ATTEMPTS {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+            ACCOUNT_WEBHOOKS_FAILED.inc();
+            warn!(
+                "giving up on account webhook for {pubkey} to {url} after {} attempts",
+                WEBHOOK_ATTEMPTS
+            );
+        });
+    }
+}
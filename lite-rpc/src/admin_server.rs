@@ -0,0 +1,578 @@
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    fmt::{Debug, Write},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use hyper::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Value};
+#[cfg(feature = "accounts")]
+use solana_lite_rpc_accounts::account_service::AccountService;
+use solana_lite_rpc_cluster_endpoints::grpc_source_metrics::grpc_source_states;
+use solana_lite_rpc_core::{
+    memory_budget::MemoryBudget, stores::data_cache::DataCache, time_utils::unix_timestamp_ms,
+    AnyhowJoinHandle,
+};
+use solana_lite_rpc_services::{
+    transaction_replayer::MESSAGES_IN_REPLAY_QUEUE,
+    transaction_service::{DrainSwitch, TransactionService},
+    tx_sender::TXS_IN_CHANNEL,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use tracing::{field::Field, field::Visit, Event, Subscriber};
+use tracing_subscriber::{layer::Context, Layer};
+
+#[cfg(feature = "postgres")]
+use crate::postgres_logger::MESSAGES_IN_POSTGRES_CHANNEL;
+use crate::{
+    allocator_stats::allocator_stats, config_reloader::ConfigReloader,
+    identity_reloader::IdentityReloader, rpc_metrics::active_subscriptions, utils::text_response,
+    webhook_notifier::WEBHOOKS_PENDING,
+};
+
+const RECENT_ERRORS_CAPACITY: usize = 100;
+
+lazy_static::lazy_static! {
+    static ref RECENT_ERRORS: Mutex<VecDeque<RecentError>> = Mutex::new(VecDeque::with_capacity(RECENT_ERRORS_CAPACITY));
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RecentError {
+    /// unix timestamp in ms
+    pub timestamp_ms: u64,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// tracing layer keeping the last log events for the admin api, filter it to the levels of interest
+pub struct RecentErrorsLayer;
+
+impl<S: Subscriber> Layer<S> for RecentErrorsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let recent_error = RecentError {
+            timestamp_ms: unix_timestamp_ms(),
+            level: event.metadata().level().to_string(),
+            target: event.metadata().target().to_string(),
+            message: visitor.0,
+        };
+        let mut recent_errors = RECENT_ERRORS.lock().unwrap();
+        if recent_errors.len() >= RECENT_ERRORS_CAPACITY {
+            recent_errors.pop_front();
+        }
+        recent_errors.push_back(recent_error);
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        } else {
+            let _ = write!(self.0, " {}={value:?}", field.name());
+        }
+    }
+}
+
+// leaders listed in the state dump, starting at the current slot
+const STATE_DUMP_LEADER_SLOTS: u64 = 64;
+
+/// json api to inspect a running instance, every request needs `Authorization: Bearer <token>`
+#[derive(Clone)]
+pub struct AdminServer {
+    data_cache: DataCache,
+    config_reloader: Arc<ConfigReloader>,
+    token: String,
+    state_dump_dir: PathBuf,
+    drain_switch: Option<DrainSwitch>,
+    memory_budget: Option<MemoryBudget>,
+    identity_reloader: Option<IdentityReloader>,
+    #[cfg(feature = "accounts")]
+    accounts_service: Option<AccountService>,
+}
+
+impl AdminServer {
+    /// the configuration is shown without secrets and reloaded by `POST /reload`
+    pub fn new(
+        data_cache: DataCache,
+        config_reloader: Arc<ConfigReloader>,
+        token: String,
+        state_dump_dir: PathBuf,
+    ) -> Self {
+        Self {
+            data_cache,
+            config_reloader,
+            token,
+            state_dump_dir,
+            drain_switch: None,
+            memory_budget: None,
+            identity_reloader: None,
+            #[cfg(feature = "accounts")]
+            accounts_service: None,
+        }
+    }
+
+    /// drains the transaction service on `POST /drain`
+    pub fn with_transaction_service(mut self, transaction_service: TransactionService) -> Self {
+        self.drain_switch = Some(transaction_service.drain_switch());
+        self
+    }
+
+    /// break down the memory usage by cache in `/memory`
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
+    /// reloads the identity keypair on `POST /identity`
+    pub fn with_identity_reloader(mut self, identity_reloader: IdentityReloader) -> Self {
+        self.identity_reloader = Some(identity_reloader);
+        self
+    }
+
+    /// include the account store in the state dump
+    #[cfg(feature = "accounts")]
+    pub fn with_accounts_service(mut self, accounts_service: AccountService) -> Self {
+        self.accounts_service = Some(accounts_service);
+        self
+    }
+
+    pub fn start(self, addr: SocketAddr) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            let make_service = make_service_fn(move |_| {
+                let admin_server = self.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request| {
+                        let admin_server = admin_server.clone();
+                        async move { Ok::<_, Infallible>(admin_server.handle(request).await) }
+                    }))
+                }
+            });
+
+            log::info!("Admin server started at {addr:?}");
+            Server::try_bind(&addr)?.serve(make_service).await?;
+            anyhow::bail!("Admin server stopped");
+        })
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let expected = format!("Bearer {}", self.token);
+        let authorized = request
+            .headers()
+            .get(AUTHORIZATION)
+            .is_some_and(|value| token_matches(value.as_bytes(), expected.as_bytes()));
+        if !authorized {
+            return text_response(StatusCode::UNAUTHORIZED, "unauthorized");
+        }
+        if request.method() == Method::POST && request.uri().path() == "/dump" {
+            return match self.dump_state().await {
+                Ok(path) => text_response(StatusCode::OK, path.display().to_string()),
+                Err(err) => {
+                    log::error!("state dump failed: {err:?}");
+                    text_response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                }
+            };
+        }
+        if request.method() == Method::POST && request.uri().path() == "/reload" {
+            return match self.config_reloader.reload().await {
+                Ok(applied) => json_response(json!({ "applied": applied })),
+                Err(err) => {
+                    log::error!("configuration reload failed: {err:?}");
+                    text_response(StatusCode::BAD_REQUEST, format!("{err:#}"))
+                }
+            };
+        }
+        if request.method() == Method::POST && request.uri().path() == "/identity" {
+            let Some(identity_reloader) = &self.identity_reloader else {
+                return text_response(StatusCode::NOT_FOUND, "no identity keypair file configured");
+            };
+            return match identity_reloader.reload().await {
+                Ok(identity) => json_response(json!({ "identity": identity.to_string() })),
+                Err(err) => {
+                    log::error!("identity reload failed: {err:?}");
+                    text_response(StatusCode::BAD_REQUEST, format!("{err:#}"))
+                }
+            };
+        }
+        if request.method() == Method::POST && matches!(request.uri().path(), "/drain" | "/resume")
+        {
+            let Some(drain_switch) = &self.drain_switch else {
+                return text_response(StatusCode::NOT_FOUND, "transaction sending is disabled");
+            };
+            if request.uri().path() == "/drain" {
+                drain_switch.start_draining();
+            } else {
+                drain_switch.stop_draining();
+            }
+            return json_response(self.drain().await);
+        }
+        if request.method() != Method::GET {
+            return text_response(StatusCode::METHOD_NOT_ALLOWED, "only GET is supported");
+        }
+
+        let body = match request.uri().path() {
+            "/" | "/status" => json!({
+                "slots": self.slots().await,
+                "sources": self.sources(),
+                "subscriptions": self.subscriptions(),
+                "queues": self.queues(),
+                "drain": self.drain().await,
+                "memory": self.memory().await,
+                "errors": recent_errors(),
+                "config": self.config_reloader.config().await,
+            }),
+            "/slots" => self.slots().await,
+            "/sources" => self.sources(),
+            "/subscriptions" => self.subscriptions(),
+            "/queues" => self.queues(),
+            "/drain" => self.drain().await,
+            "/memory" => self.memory().await,
+            "/errors" => recent_errors(),
+            "/config" => self.config_reloader.config().await,
+            _ => return text_response(StatusCode::NOT_FOUND, "not found"),
+        };
+
+        json_response(body)
+    }
+
+    /// the node can be stopped without losing transactions once draining and nothing is in flight
+    async fn drain(&self) -> Value {
+        let draining = self
+            .drain_switch
+            .as_ref()
+            .is_some_and(|drain_switch| drain_switch.is_draining());
+        // sent transactions which did not land yet and whose blockhash did not expire, the expired
+        // ones are not counted until the tx store is cleaned
+        let block_height = self
+            .data_cache
+            .block_information_store
+            .get_latest_block(CommitmentConfig::processed())
+            .await
+            .block_height;
+        let in_flight =
+            self.data_cache.txs.count_in_flight(block_height) as i64 + TXS_IN_CHANNEL.get();
+        json!({
+            "draining": draining,
+            "txs_in_flight": in_flight,
+            "drained": draining && in_flight == 0,
+        })
+    }
+
+    /// allocator statistics next to the estimated memory of the caches
+    async fn memory(&self) -> Value {
+        let consumers: serde_json::Map<String, Value> = match &self.memory_budget {
+            Some(memory_budget) => memory_budget
+                .usages()
+                .await
+                .into_iter()
+                .map(|(name, usage)| (name.to_string(), json!(usage)))
+                .collect(),
+            None => serde_json::Map::new(),
+        };
+        json!({
+            "allocator": allocator_stats(),
+            "budget_bytes": self
+                .memory_budget
+                .as_ref()
+                .and_then(|memory_budget| memory_budget.budget_bytes()),
+            "consumers": consumers,
+        })
+    }
+
+    async fn slots(&self) -> Value {
+        let mut slots = json!({
+            "current_slot": self.data_cache.slot_cache.get_current_slot(),
+            "estimated_slot": self.data_cache.slot_cache.get_estimated_slot(),
+        });
+        for commitment_config in [
+            CommitmentConfig::processed(),
+            CommitmentConfig::confirmed(),
+            CommitmentConfig::finalized(),
+        ] {
+            let block = self
+                .data_cache
+                .block_information_store
+                .get_latest_block(commitment_config)
+                .await;
+            slots[commitment_config.commitment.to_string()] = json!({
+                "slot": block.slot,
+                "block_height": block.block_height,
+                "blockhash": block.blockhash.to_string(),
+            });
+        }
+        slots
+    }
+
+    /// writes the internal state to a new json file in the state dump directory and returns its path
+    async fn dump_state(&self) -> anyhow::Result<PathBuf> {
+        let timestamp_ms = unix_timestamp_ms();
+        let current_slot = self.data_cache.slot_cache.get_current_slot();
+
+        let blockhash_cache: serde_json::Map<String, Value> = self
+            .data_cache
+            .block_information_store
+            .slot_range_by_commitment()
+            .into_iter()
+            .map(|(commitment, (blocks, min_slot, max_slot))| {
+                (
+                    commitment.to_string(),
+                    json!({ "blocks": blocks, "min_slot": min_slot, "max_slot": max_slot }),
+                )
+            })
+            .collect();
+
+        let leaders = self
+            .data_cache
+            .leader_schedule
+            .read()
+            .await
+            .get_slot_leaders(
+                current_slot,
+                STATE_DUMP_LEADER_SLOTS,
+                self.data_cache.epoch_data.get_epoch_schedule(),
+            )
+            .await
+            .map(|leaders| {
+                leaders
+                    .iter()
+                    .enumerate()
+                    .map(|(index, leader)| json!({ "slot": current_slot + index as u64, "leader": leader.to_string() }))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let unconfirmed_txs = self
+            .data_cache
+            .txs
+            .store
+            .iter()
+            .filter(|tx| tx.status.is_none())
+            .count();
+
+        let dump = json!({
+            "timestamp_ms": timestamp_ms,
+            "slots": self.slots().await,
+            "sources": self.sources(),
+            "blockhash_cache": blockhash_cache,
+            "leader_schedule": {
+                "epoch": self.data_cache.epoch_data.get_epoch_at_slot(current_slot).epoch,
+                "leaders": leaders,
+            },
+            "transactions": {
+                "in_store": self.data_cache.txs.len(),
+                "unconfirmed": unconfirmed_txs,
+                "in_channel": TXS_IN_CHANNEL.get(),
+                "in_replay_queue": MESSAGES_IN_REPLAY_QUEUE.get(),
+            },
+            "subscriptions": self.subscriptions(),
+            "account_store": self.account_store().await,
+            "errors": recent_errors(),
+            "config": self.config_reloader.config().await,
+        });
+
+        tokio::fs::create_dir_all(&self.state_dump_dir).await?;
+        let path = self
+            .state_dump_dir
+            .join(format!("lite-rpc-state-{timestamp_ms}.json"));
+        tokio::fs::write(&path, serde_json::to_vec_pretty(&dump)?).await?;
+        log::info!("Dumped internal state to {}", path.display());
+        Ok(path)
+    }
+
+    #[cfg(feature = "accounts")]
+    async fn account_store(&self) -> Value {
+        match &self.accounts_service {
+            Some(accounts_service) => {
+                let stats = accounts_service.stats().await;
+                json!({
+                    "accounts": stats.accounts,
+                    "owners": stats.owners,
+                    "confirmed_slots": stats.confirmed_slots,
+                })
+            }
+            None => Value::Null,
+        }
+    }
+
+    #[cfg(not(feature = "accounts"))]
+    async fn account_store(&self) -> Value {
+        Value::Null
+    }
+
+    fn sources(&self) -> Value {
+        json!({ "grpc": grpc_source_states() })
+    }
+
+    fn subscriptions(&self) -> Value {
+        let mut subscriptions = json!(active_subscriptions());
+        subscriptions["signature"] = json!(self.data_cache.tx_subs.number_of_subscribers());
+        subscriptions["webhooks_pending"] = json!(WEBHOOKS_PENDING.get());
+        subscriptions
+    }
+
+    fn queues(&self) -> Value {
+        #[cfg(feature = "postgres")]
+        let messages_in_postgres_channel = MESSAGES_IN_POSTGRES_CHANNEL.get();
+        #[cfg(not(feature = "postgres"))]
+        let messages_in_postgres_channel = 0;
+        json!({
+            "txs_in_channel": TXS_IN_CHANNEL.get(),
+            "txs_in_replay_queue": MESSAGES_IN_REPLAY_QUEUE.get(),
+            "txs_in_store": self.data_cache.txs.len(),
+            "messages_in_postgres_channel": messages_in_postgres_channel,
+            "blocks_in_store": self.data_cache.block_information_store.number_of_blocks_in_store(),
+            "blockhashes_in_cache": self.data_cache.blockhash_cache.len(),
+        })
+    }
+}
+
+fn recent_errors() -> Value {
+    let recent_errors = RECENT_ERRORS.lock().unwrap();
+    json!(recent_errors.iter().rev().collect::<Vec<_>>())
+}
+
+/// compares in constant time, the response time must not reveal how much of the token matched
+fn token_matches(provided: &[u8], expected: &[u8]) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn json_response(body: Value) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_lite_rpc_core::stores::{
+        block_information_store::BlockInformation, tx_store::TxProps,
+    };
+    use solana_sdk::{hash::Hash, signature::Signature};
+
+    use super::*;
+    use crate::cli::Config;
+
+    fn admin_server(data_cache: DataCache) -> AdminServer {
+        let config: Config = serde_json::from_str("{}").unwrap();
+        AdminServer::new(
+            data_cache,
+            Arc::new(ConfigReloader::new(&config).unwrap()),
+            "token".to_string(),
+            std::env::temp_dir(),
+        )
+    }
+
+    async fn call(admin_server: &AdminServer, method: Method, path: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(AUTHORIZATION, "Bearer token")
+            .body(Body::empty())
+            .unwrap();
+        let response = admin_server.handle(request).await;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&body)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).to_string())),
+        )
+    }
+
+    fn sent_transaction(last_valid_blockheight: u64) -> TxProps {
+        TxProps {
+            status: None,
+            last_valid_blockheight,
+            sent_by_lite_rpc: true,
+            finalized_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_transactions_are_not_in_flight() {
+        let data_cache = DataCache::new_for_tests();
+        data_cache
+            .block_information_store
+            .add_block(BlockInformation {
+                slot: 10,
+                block_height: 200,
+                cleanup_slot: 1000,
+                blockhash: Hash::new_unique(),
+                commitment_config: CommitmentConfig::confirmed(),
+                block_time: 0,
+            })
+            .await;
+        data_cache
+            .txs
+            .insert(Signature::new_unique(), sent_transaction(250));
+        data_cache
+            .txs
+            .insert(Signature::new_unique(), sent_transaction(200));
+        // expired, until the tx store is cleaned
+        data_cache
+            .txs
+            .insert(Signature::new_unique(), sent_transaction(199));
+        let admin_server = admin_server(data_cache);
+
+        let (status, drain) = call(&admin_server, Method::GET, "/drain").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            drain["txs_in_flight"].as_i64().unwrap() - TXS_IN_CHANNEL.get(),
+            2
+        );
+        assert_eq!(drain["draining"], false);
+        assert_eq!(drain["drained"], false);
+    }
+
+    #[tokio::test]
+    async fn drain_and_resume() {
+        let mut admin_server = admin_server(DataCache::new_for_tests());
+        let (status, _) = call(&admin_server, Method::POST, "/drain").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let drain_switch = DrainSwitch::default();
+        admin_server.drain_switch = Some(drain_switch.clone());
+        let (status, drain) = call(&admin_server, Method::POST, "/drain").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(drain_switch.is_draining());
+        assert_eq!(drain["draining"], true);
+        assert_eq!(
+            drain["drained"],
+            drain["txs_in_flight"].as_i64().unwrap() == 0
+        );
+
+        let (status, drain) = call(&admin_server, Method::POST, "/resume").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!drain_switch.is_draining());
+        assert_eq!(drain["draining"], false);
+        assert_eq!(drain["drained"], false);
+    }
+
+    #[test]
+    fn token_must_match_completely() {
+        assert!(token_matches(b"Bearer token", b"Bearer token"));
+        assert!(!token_matches(b"Bearer tokem", b"Bearer token"));
+        assert!(!token_matches(b"Bearer toke", b"Bearer token"));
+        assert!(!token_matches(b"", b"Bearer token"));
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use prometheus::{opts, register_int_gauge_vec, IntGaugeVec};
+use serde::Serialize;
+use solana_lite_rpc_core::AnyhowJoinHandle;
+
+#[cfg(all(feature = "jemalloc", feature = "mimalloc"))]
+compile_error!("the features jemalloc and mimalloc are mutually exclusive");
+
+lazy_static::lazy_static! {
+    static ref ALLOCATOR_BYTES: IntGaugeVec =
+    register_int_gauge_vec!(opts!("literpc_allocator_bytes", "Memory statistics of the global allocator in bytes"), &["kind"]).unwrap();
+}
+
+/// statistics of the global allocator, the fields it does not report are none
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct AllocatorStats {
+    pub allocator: &'static str,
+    /// bytes allocated by the application
+    pub allocated_bytes: Option<u64>,
+    /// bytes in the pages holding allocations, including fragmentation
+    pub active_bytes: Option<u64>,
+    /// physical memory held by the allocator
+    pub resident_bytes: Option<u64>,
+    /// virtual memory mapped (jemalloc) or committed (mimalloc) by the allocator
+    pub mapped_bytes: Option<u64>,
+    /// virtual memory retained by jemalloc for reuse instead of being unmapped
+    pub retained_bytes: Option<u64>,
+}
+
+impl AllocatorStats {
+    fn by_kind(&self) -> [(&'static str, Option<u64>); 5] {
+        [
+            ("allocated", self.allocated_bytes),
+            ("active", self.active_bytes),
+            ("resident", self.resident_bytes),
+            ("mapped", self.mapped_bytes),
+            ("retained", self.retained_bytes),
+        ]
+    }
+}
+
+#[cfg(feature = "jemalloc")]
+pub fn allocator_stats() -> AllocatorStats {
+    use tikv_jemalloc_ctl::{epoch, stats};
+
+    // the statistics are a snapshot taken when the epoch advances
+    if let Err(err) = epoch::advance() {
+        log::warn!("failed to refresh the jemalloc statistics: {err}");
+    }
+    let read = |value: tikv_jemalloc_ctl::Result<usize>| value.ok().map(|bytes| bytes as u64);
+    AllocatorStats {
+        allocator: "jemalloc",
+        allocated_bytes: read(stats::allocated::read()),
+        active_bytes: read(stats::active::read()),
+        resident_bytes: read(stats::resident::read()),
+        mapped_bytes: read(stats::mapped::read()),
+        retained_bytes: read(stats::retained::read()),
+    }
+}
+
+#[cfg(feature = "mimalloc")]
+pub fn allocator_stats() -> AllocatorStats {
+    let (mut elapsed_msecs, mut user_msecs, mut system_msecs) = (0, 0, 0);
+    let (mut current_rss, mut peak_rss, mut current_commit, mut peak_commit, mut page_faults) =
+        (0, 0, 0, 0, 0);
+    // SAFETY: mi_process_info only writes to the given pointers
+    unsafe {
+        libmimalloc_sys::mi_process_info(
+            &mut elapsed_msecs,
+            &mut user_msecs,
+            &mut system_msecs,
+            &mut current_rss,
+            &mut peak_rss,
+            &mut current_commit,
+            &mut peak_commit,
+            &mut page_faults,
+        );
+    }
+    AllocatorStats {
+        allocator: "mimalloc",
+        resident_bytes: Some(current_rss as u64),
+        mapped_bytes: Some(current_commit as u64),
+        ..Default::default()
+    }
+}
+
+/// the system allocator does not report statistics
+#[cfg(not(any(feature = "jemalloc", feature = "mimalloc")))]
+pub fn allocator_stats() -> AllocatorStats {
+    AllocatorStats {
+        allocator: "system",
+        ..Default::default()
+    }
+}
+
+/// exports the allocator statistics in `literpc_allocator_bytes`
+pub fn start_allocator_stats_task(interval: Duration) -> AnyhowJoinHandle {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            for (kind, bytes) in allocator_stats().by_kind() {
+                if let Some(bytes) = bytes {
+                    ALLOCATOR_BYTES.with_label_values(&[kind]).set(bytes as i64);
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,418 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context as _;
+use hyper::{http::uri::PathAndQuery, Body, Request, Response, StatusCode, Uri};
+use log::{error, info, warn};
+use prometheus::{opts, register_int_counter, register_int_counter_vec, IntCounter, IntCounterVec};
+use solana_lite_rpc_core::request_id::with_api_key_id;
+use solana_lite_rpc_services::{
+    dry_run::DryRun,
+    fee_guardrails::{FeeGuardrails, FeeLimitOverrides},
+};
+use tower::{Layer, Service};
+use tracing::Instrument;
+use url::form_urlencoded;
+
+lazy_static::lazy_static! {
+    static ref API_KEY_REQUESTS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_api_key_requests", "HTTP requests and websocket connections per api key"), &["key_id"]).unwrap();
+    static ref API_KEY_REJECTED: IntCounter =
+        register_int_counter!(opts!("literpc_api_key_rejected", "Requests rejected because of a missing or unknown api key")).unwrap();
+}
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+pub const API_KEY_QUERY_PARAM: &str = "api-key";
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct ApiKeyEntry {
+    /// identifies the key in metrics and logs
+    pub id: String,
+    #[serde(skip_serializing)]
+    pub key: String,
+    /// replace the default fee limits for the transactions sent with this key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_limits: Option<FeeLimitOverrides>,
+    /// the transactions sent with this key are validated but not forwarded
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// request extension with the id of the api key the request was authenticated with
+#[derive(Clone, Debug)]
+pub struct ApiKeyId(pub String);
+
+/// api keys from the configuration and an optional json file (`[{"id": "..", "key": ".."}]`) which
+/// can be replaced at runtime
+#[derive(Clone)]
+pub struct ApiKeys {
+    config_keys: Vec<ApiKeyEntry>,
+    keys_file: Option<String>,
+    // key -> key id
+    keys: Arc<RwLock<HashMap<String, String>>>,
+    // receives the fee limits of the keys on every reload
+    fee_guardrails: Option<FeeGuardrails>,
+    // receives the dry-run keys on every reload
+    dry_run: Option<DryRun>,
+}
+
+impl ApiKeys {
+    pub fn new(config_keys: Vec<ApiKeyEntry>, keys_file: Option<String>) -> anyhow::Result<Self> {
+        let api_keys = Self {
+            config_keys,
+            keys_file,
+            keys: Arc::new(RwLock::new(HashMap::new())),
+            fee_guardrails: None,
+            dry_run: None,
+        };
+        api_keys.reload()?;
+        Ok(api_keys)
+    }
+
+    pub fn with_fee_guardrails(mut self, fee_guardrails: FeeGuardrails) -> anyhow::Result<Self> {
+        self.fee_guardrails = Some(fee_guardrails);
+        self.reload()?;
+        Ok(self)
+    }
+
+    pub fn with_dry_run(mut self, dry_run: DryRun) -> anyhow::Result<Self> {
+        self.dry_run = Some(dry_run);
+        self.reload()?;
+        Ok(self)
+    }
+
+    /// returns the number of keys in use after the reload
+    pub fn reload(&self) -> anyhow::Result<usize> {
+        let mut entries = self.config_keys.clone();
+        if let Some(keys_file) = &self.keys_file {
+            let content = std::fs::read_to_string(keys_file)
+                .with_context(|| format!("Cannot read api keys file {keys_file}"))?;
+            let file_entries: Vec<ApiKeyEntry> = serde_json::from_str(&content)
+                .with_context(|| format!("Invalid api keys file {keys_file}"))?;
+            entries.extend(file_entries);
+        }
+        anyhow::ensure!(
+            entries.iter().all(|entry| !entry.key.is_empty()),
+            "api keys must not be empty"
+        );
+        if let Some(fee_guardrails) = &self.fee_guardrails {
+            fee_guardrails.set_key_limits(
+                entries
+                    .iter()
+                    .filter_map(|entry| Some((entry.id.clone(), entry.fee_limits.clone()?)))
+                    .collect(),
+            );
+        }
+        if let Some(dry_run) = &self.dry_run {
+            dry_run.set_keys(
+                entries
+                    .iter()
+                    .filter(|entry| entry.dry_run)
+                    .map(|entry| entry.id.clone())
+                    .collect(),
+            );
+        }
+        let keys: HashMap<String, String> = entries
+            .into_iter()
+            .map(|entry| (entry.key, entry.id))
+            .collect();
+        let number_of_keys = keys.len();
+        *self.keys.write().unwrap() = keys;
+        Ok(number_of_keys)
+    }
+
+    pub fn key_id(&self, key: &str) -> Option<String> {
+        self.keys.read().unwrap().get(key).cloned()
+    }
+
+    /// the id of the api key in the `x-api-key` metadata of a gRPC request
+    pub fn grpc_key_id(
+        &self,
+        metadata: &tonic::metadata::MetadataMap,
+    ) -> Result<String, tonic::Status> {
+        metadata
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|key| self.key_id(key))
+            .ok_or_else(|| tonic::Status::unauthenticated("missing or invalid api key"))
+    }
+
+    fn last_modified(&self) -> Option<SystemTime> {
+        self.keys_file
+            .as_ref()
+            .and_then(|keys_file| std::fs::metadata(keys_file).ok())
+            .and_then(|metadata| metadata.modified().ok())
+    }
+
+    /// polls the api keys file for modifications, a broken file keeps the previous keys
+    pub fn start_watching(&self, poll_interval: Duration) -> tokio::task::JoinHandle<()> {
+        let this = self.clone();
+        tokio::spawn(async move {
+            let Some(keys_file) = this.keys_file.clone() else {
+                return;
+            };
+            let mut last_modified = this.last_modified();
+            loop {
+                tokio::time::sleep(poll_interval).await;
+                let modified = this.last_modified();
+                if modified.is_none() {
+                    warn!("Cannot read api keys file {keys_file}");
+                    continue;
+                }
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                match this.reload() {
+                    Ok(number_of_keys) => info!("Reloaded {number_of_keys} api keys"),
+                    Err(e) => error!("Failed to reload api keys: {e:?}"),
+                }
+            }
+        })
+    }
+}
+
+/// tower layer rejecting requests without a known api key, passed as `X-Api-Key` header, `api-key` query
+/// parameter or as first path segment (`/<key>`, removed before the request is served); does nothing if
+/// no api keys are configured
+#[derive(Clone)]
+pub struct ApiKeyAuthLayer {
+    api_keys: Option<ApiKeys>,
+}
+
+impl ApiKeyAuthLayer {
+    pub fn new(api_keys: Option<ApiKeys>) -> Self {
+        Self { api_keys }
+    }
+}
+
+impl<S> Layer<S> for ApiKeyAuthLayer {
+    type Service = ApiKeyAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeyAuthService {
+            inner,
+            api_keys: self.api_keys.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ApiKeyAuthService<S> {
+    inner: S,
+    api_keys: Option<ApiKeys>,
+}
+
+impl<S> Service<Request<Body>> for ApiKeyAuthService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let Some(api_keys) = &self.api_keys else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        let Some(key_id) = authenticate(api_keys, &mut request) else {
+            API_KEY_REJECTED.inc();
+            return Box::pin(async {
+                Ok(Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .body(Body::from("missing or invalid api key"))
+                    .unwrap())
+            });
+        };
+
+        API_KEY_REQUESTS.with_label_values(&[&key_id]).inc();
+        request.extensions_mut().insert(ApiKeyId(key_id.clone()));
+        let span = tracing::info_span!("api_key", key_id = %key_id);
+        Box::pin(with_api_key_id(
+            key_id,
+            self.inner.call(request).instrument(span),
+        ))
+    }
+}
+
+/// returns the id of the key the request carries, a key in the path is removed from the uri
+fn authenticate(api_keys: &ApiKeys, request: &mut Request<Body>) -> Option<String> {
+    if let Some(key) = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return api_keys.key_id(key);
+    }
+
+    // percent-encoded like any other query parameter
+    if let Some(key) = request.uri().query().and_then(|query| {
+        form_urlencoded::parse(query.as_bytes())
+            .find(|(name, _)| name == API_KEY_QUERY_PARAM)
+            .map(|(_, value)| value.into_owned())
+    }) {
+        return api_keys.key_id(&key);
+    }
+
+    // authority-form requests (`CONNECT host:port`) have no path
+    let path = request.uri().path().strip_prefix('/')?;
+    let (key, rest) = match path.find('/') {
+        Some(index) => path.split_at(index),
+        None => (path, ""),
+    };
+    let key_id = api_keys.key_id(key)?;
+    let rest = if rest.is_empty() { "/" } else { rest };
+    let path_and_query = match request.uri().query() {
+        Some(query) => format!("{rest}?{query}"),
+        None => rest.to_string(),
+    };
+    let mut parts = request.uri().clone().into_parts();
+    parts.path_and_query = Some(PathAndQuery::try_from(path_and_query).ok()?);
+    *request.uri_mut() = Uri::from_parts(parts).ok()?;
+    Some(key_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::Method;
+
+    use super::*;
+
+    fn api_keys() -> ApiKeys {
+        ApiKeys::new(
+            vec![ApiKeyEntry {
+                id: "client".to_string(),
+                key: "s3cr+t/key".to_string(),
+                fee_limits: None,
+                dry_run: false,
+            }],
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn grpc_key_in_metadata() {
+        let api_keys = api_keys();
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        assert_eq!(
+            api_keys.grpc_key_id(&metadata).unwrap_err().code(),
+            tonic::Code::Unauthenticated
+        );
+        metadata.insert(API_KEY_HEADER, "unknown".parse().unwrap());
+        assert!(api_keys.grpc_key_id(&metadata).is_err());
+        metadata.insert(API_KEY_HEADER, "s3cr+t/key".parse().unwrap());
+        assert_eq!(api_keys.grpc_key_id(&metadata).unwrap(), "client");
+    }
+
+    fn request(uri: &str) -> Request<Body> {
+        Request::builder().uri(uri).body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn key_in_header() {
+        let api_keys = api_keys();
+        let mut request = request("/");
+        request
+            .headers_mut()
+            .insert(API_KEY_HEADER, "s3cr+t/key".parse().unwrap());
+        assert_eq!(
+            authenticate(&api_keys, &mut request),
+            Some("client".to_string())
+        );
+
+        let mut request = self::request("/s3cr+t/key");
+        request
+            .headers_mut()
+            .insert(API_KEY_HEADER, "unknown".parse().unwrap());
+        assert_eq!(authenticate(&api_keys, &mut request), None);
+    }
+
+    #[test]
+    fn key_in_query_is_percent_decoded() {
+        let api_keys = api_keys();
+        let mut request = request("/?commitment=finalized&api-key=s3cr%2Bt%2Fkey");
+        assert_eq!(
+            authenticate(&api_keys, &mut request),
+            Some("client".to_string())
+        );
+        assert_eq!(
+            request.uri(),
+            "/?commitment=finalized&api-key=s3cr%2Bt%2Fkey"
+        );
+
+        assert_eq!(
+            authenticate(&api_keys, &mut self::request("/?api-key=s3cr+t/key")),
+            None
+        );
+        assert_eq!(
+            authenticate(&api_keys, &mut self::request("/?api-key=%zz")),
+            None
+        );
+        assert_eq!(
+            authenticate(&api_keys, &mut self::request("/?api-key")),
+            None
+        );
+    }
+
+    #[test]
+    fn key_in_path_is_removed() {
+        let api_keys = ApiKeys::new(
+            vec![ApiKeyEntry {
+                id: "client".to_string(),
+                key: "key".to_string(),
+                fee_limits: None,
+                dry_run: false,
+            }],
+            None,
+        )
+        .unwrap();
+
+        let mut request = request("/key/health?verbose=true");
+        assert_eq!(
+            authenticate(&api_keys, &mut request),
+            Some("client".to_string())
+        );
+        assert_eq!(request.uri(), "/health?verbose=true");
+
+        let mut request = self::request("/key");
+        assert_eq!(
+            authenticate(&api_keys, &mut request),
+            Some("client".to_string())
+        );
+        assert_eq!(request.uri(), "/");
+
+        let mut request = self::request("/other/health");
+        assert_eq!(authenticate(&api_keys, &mut request), None);
+        assert_eq!(request.uri(), "/other/health");
+    }
+
+    #[test]
+    fn malformed_requests_are_rejected() {
+        let api_keys = api_keys();
+        let mut request = Request::builder()
+            .method(Method::CONNECT)
+            .uri("example.com:443")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(request.uri().path(), "");
+        assert_eq!(authenticate(&api_keys, &mut request), None);
+
+        assert_eq!(authenticate(&api_keys, &mut self::request("*")), None);
+        assert_eq!(authenticate(&api_keys, &mut self::request("/")), None);
+        assert_eq!(authenticate(&api_keys, &mut self::request("//")), None);
+    }
+}
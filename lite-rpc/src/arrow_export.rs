@@ -0,0 +1,356 @@
+use std::{
+    future::Future,
+    ops::RangeInclusive,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use arrow_array::{
+    builder::{ListBuilder, StringBuilder},
+    ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, UInt32Array, UInt64Array,
+};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use futures::StreamExt;
+use hyper::{body::Sender, Body, Method, Request, Response, StatusCode};
+use log::debug;
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_core::structures::produced_block::{ProducedBlock, TransactionInfo};
+use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    pubkey::Pubkey,
+    slot_history::Slot,
+};
+use tower::{Layer, Service};
+
+use crate::{block_range::BlockRangeReader, utils::text_response};
+
+lazy_static::lazy_static! {
+    static ref ARROW_EXPORT_REQUESTS: IntCounter =
+        register_int_counter!(opts!("literpc_arrow_export_requests", "Arrow IPC exports of blocks or transactions started")).unwrap();
+    static ref ARROW_EXPORT_ROWS: IntCounter =
+        register_int_counter!(opts!("literpc_arrow_export_rows", "Rows streamed in Arrow IPC exports")).unwrap();
+    static ref ARROW_EXPORT_ABORTED: IntCounter =
+        register_int_counter!(opts!("literpc_arrow_export_aborted", "Arrow IPC exports which were not streamed to the end")).unwrap();
+}
+
+/// GET with `from_slot`, `to_slot` and optionally `commitment` parameters, one row per block
+pub const ARROW_BLOCKS_PATH: &str = "/arrow/blocks";
+/// GET with the parameters of the blocks export and optionally `include_votes`, one row per transaction
+pub const ARROW_TRANSACTIONS_PATH: &str = "/arrow/transactions";
+
+const ARROW_STREAM_CONTENT_TYPE: &str = "application/vnd.apache.arrow.stream";
+// about 11 hours of slots
+const MAX_SLOT_RANGE: u64 = 100_000;
+// blocks per record batch
+const BLOCKS_PER_BATCH: usize = 100;
+
+#[derive(Clone, Copy)]
+enum ArrowExport {
+    Blocks,
+    Transactions { include_votes: bool },
+}
+
+impl ArrowExport {
+    fn schema(&self) -> SchemaRef {
+        let accounts = || DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)));
+        let fields = match self {
+            ArrowExport::Blocks => vec![
+                Field::new("slot", DataType::UInt64, false),
+                Field::new("block_height", DataType::UInt64, false),
+                Field::new("blockhash", DataType::Utf8, false),
+                Field::new("parent_slot", DataType::UInt64, false),
+                Field::new("previous_blockhash", DataType::Utf8, false),
+                Field::new("block_time", DataType::Int64, false),
+                Field::new("commitment", DataType::Utf8, false),
+                Field::new("leader", DataType::Utf8, true),
+                Field::new("transaction_count", DataType::UInt64, false),
+                Field::new("vote_transaction_count", DataType::UInt64, false),
+            ],
+            ArrowExport::Transactions { .. } => vec![
+                Field::new("slot", DataType::UInt64, false),
+                Field::new("block_time", DataType::Int64, false),
+                Field::new("signature", DataType::Utf8, false),
+                Field::new("is_vote", DataType::Boolean, false),
+                Field::new("err", DataType::Utf8, true),
+                Field::new("cu_requested", DataType::UInt32, true),
+                Field::new("cu_consumed", DataType::UInt64, true),
+                Field::new("prioritization_fees", DataType::UInt64, true),
+                Field::new("writable_accounts", accounts(), false),
+                Field::new("readable_accounts", accounts(), false),
+                Field::new("program_ids", accounts(), false),
+            ],
+        };
+        Arc::new(Schema::new(fields))
+    }
+
+    fn record_batch(
+        &self,
+        schema: SchemaRef,
+        blocks: &[Arc<ProducedBlock>],
+    ) -> Result<RecordBatch, ArrowError> {
+        let columns: Vec<ArrayRef> = match self {
+            ArrowExport::Blocks => vec![
+                Arc::new(UInt64Array::from_iter_values(blocks.iter().map(|b| b.slot))),
+                Arc::new(UInt64Array::from_iter_values(
+                    blocks.iter().map(|b| b.block_height),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    blocks.iter().map(|b| b.blockhash.to_string()),
+                )),
+                Arc::new(UInt64Array::from_iter_values(
+                    blocks.iter().map(|b| b.parent_slot),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    blocks.iter().map(|b| b.previous_blockhash.to_string()),
+                )),
+                Arc::new(Int64Array::from_iter_values(
+                    blocks.iter().map(|b| b.block_time as i64),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    blocks
+                        .iter()
+                        .map(|b| b.commitment_config.commitment.to_string()),
+                )),
+                Arc::new(StringArray::from_iter(
+                    blocks
+                        .iter()
+                        .map(|b| b.leader_id.map(|leader_id| leader_id.to_string())),
+                )),
+                Arc::new(UInt64Array::from_iter_values(
+                    blocks.iter().map(|b| b.transactions.len() as u64),
+                )),
+                Arc::new(UInt64Array::from_iter_values(blocks.iter().map(|b| {
+                    b.transactions.iter().filter(|tx| tx.is_vote).count() as u64
+                }))),
+            ],
+            ArrowExport::Transactions { include_votes } => {
+                let rows = blocks
+                    .iter()
+                    .flat_map(|block| {
+                        block
+                            .transactions
+                            .iter()
+                            .filter(|tx| *include_votes || !tx.is_vote)
+                            .map(move |tx| (block, tx))
+                    })
+                    .collect::<Vec<_>>();
+                let accounts = |accounts_of: fn(&TransactionInfo) -> &[Pubkey]| {
+                    let mut builder = ListBuilder::new(StringBuilder::new());
+                    for (_, tx) in &rows {
+                        for account in accounts_of(tx) {
+                            builder.values().append_value(account.to_string());
+                        }
+                        builder.append(true);
+                    }
+                    Arc::new(builder.finish()) as ArrayRef
+                };
+                vec![
+                    Arc::new(UInt64Array::from_iter_values(
+                        rows.iter().map(|(block, _)| block.slot),
+                    )),
+                    Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|(block, _)| block.block_time as i64),
+                    )),
+                    Arc::new(StringArray::from_iter_values(
+                        rows.iter().map(|(_, tx)| tx.signature.to_string()),
+                    )),
+                    Arc::new(BooleanArray::from_iter(
+                        rows.iter().map(|(_, tx)| Some(tx.is_vote)),
+                    )),
+                    Arc::new(StringArray::from_iter(
+                        rows.iter()
+                            .map(|(_, tx)| tx.err.as_ref().map(ToString::to_string)),
+                    )),
+                    Arc::new(UInt32Array::from_iter(
+                        rows.iter().map(|(_, tx)| tx.cu_requested),
+                    )),
+                    Arc::new(UInt64Array::from_iter(
+                        rows.iter().map(|(_, tx)| tx.cu_consumed),
+                    )),
+                    Arc::new(UInt64Array::from_iter(
+                        rows.iter().map(|(_, tx)| tx.prioritization_fees),
+                    )),
+                    accounts(|tx| tx.writable_accounts.as_slice()),
+                    accounts(|tx| tx.readable_accounts.as_slice()),
+                    accounts(|tx| tx.program_ids.as_slice()),
+                ]
+            }
+        };
+        RecordBatch::try_new(schema, columns)
+    }
+}
+
+struct ExportParams {
+    slots: RangeInclusive<Slot>,
+    commitment_config: CommitmentConfig,
+    include_votes: bool,
+}
+
+impl ExportParams {
+    fn parse(query: Option<&str>) -> Result<Self, String> {
+        let mut from_slot = None;
+        let mut to_slot = None;
+        let mut commitment_config = CommitmentConfig::confirmed();
+        let mut include_votes = false;
+        for parameter in query.unwrap_or_default().split('&') {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            match name {
+                "from_slot" => {
+                    from_slot = Some(value.parse::<Slot>().map_err(|_| "invalid from_slot")?)
+                }
+                "to_slot" => to_slot = Some(value.parse::<Slot>().map_err(|_| "invalid to_slot")?),
+                "commitment" => {
+                    commitment_config = CommitmentConfig {
+                        commitment: CommitmentLevel::from_str(value)
+                            .map_err(|_| "invalid commitment")?,
+                    }
+                }
+                "include_votes" => {
+                    include_votes = value.parse().map_err(|_| "invalid include_votes")?
+                }
+                _ => {}
+            }
+        }
+        let (Some(from_slot), Some(to_slot)) = (from_slot, to_slot) else {
+            return Err("from_slot and to_slot are required".to_string());
+        };
+        if to_slot < from_slot || to_slot - from_slot >= MAX_SLOT_RANGE {
+            return Err(format!(
+                "slot range must contain 1 to {MAX_SLOT_RANGE} slots"
+            ));
+        }
+        Ok(Self {
+            slots: from_slot..=to_slot,
+            commitment_config,
+            include_votes,
+        })
+    }
+}
+
+/// tower layer streaming blocks and transactions known to lite-rpc as Arrow IPC streams, for
+/// analytical consumers like pandas or polars; does nothing if not configured
+#[derive(Clone)]
+pub struct ArrowExportLayer {
+    block_range_reader: Option<BlockRangeReader>,
+}
+
+impl ArrowExportLayer {
+    pub fn new(block_range_reader: Option<BlockRangeReader>) -> Self {
+        Self { block_range_reader }
+    }
+}
+
+impl<S> Layer<S> for ArrowExportLayer {
+    type Service = ArrowExportService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ArrowExportService {
+            inner,
+            block_range_reader: self.block_range_reader.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ArrowExportService<S> {
+    inner: S,
+    block_range_reader: Option<BlockRangeReader>,
+}
+
+impl<S> Service<Request<Body>> for ArrowExportService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let block_range_reader = match &self.block_range_reader {
+            Some(block_range_reader) if request.method() == Method::GET => {
+                block_range_reader.clone()
+            }
+            _ => return Box::pin(self.inner.call(request)),
+        };
+        let is_transactions_export = match request.uri().path() {
+            ARROW_BLOCKS_PATH => false,
+            ARROW_TRANSACTIONS_PATH => true,
+            _ => return Box::pin(self.inner.call(request)),
+        };
+        let params = ExportParams::parse(request.uri().query());
+
+        Box::pin(async move {
+            let params = match params {
+                Ok(params) => params,
+                Err(e) => return Ok(text_response(StatusCode::BAD_REQUEST, e)),
+            };
+            let export = if is_transactions_export {
+                ArrowExport::Transactions {
+                    include_votes: params.include_votes,
+                }
+            } else {
+                ArrowExport::Blocks
+            };
+            ARROW_EXPORT_REQUESTS.inc();
+            let (mut sender, body) = Body::channel();
+            tokio::spawn(async move {
+                if let Err(e) = stream_export(block_range_reader, export, params, &mut sender).await
+                {
+                    ARROW_EXPORT_ABORTED.inc();
+                    debug!("arrow export aborted: {e:?}");
+                    // the client sees a truncated stream instead of a complete one
+                    sender.abort();
+                }
+            });
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .header(hyper::header::CONTENT_TYPE, ARROW_STREAM_CONTENT_TYPE)
+                .body(body)
+                .unwrap())
+        })
+    }
+}
+
+// writes the schema, a record batch per BLOCKS_PER_BATCH blocks and the end of stream marker
+async fn stream_export(
+    block_range_reader: BlockRangeReader,
+    export: ArrowExport,
+    params: ExportParams,
+    sender: &mut Sender,
+) -> anyhow::Result<()> {
+    let schema = export.schema();
+    let mut writer = StreamWriter::try_new(Vec::new(), &schema)?;
+    flush(&mut writer, sender).await?;
+
+    let mut blocks = block_range_reader
+        .stream(params.slots, params.commitment_config)
+        .await
+        .chunks(BLOCKS_PER_BATCH);
+    while let Some(blocks) = blocks.next().await {
+        let batch = export.record_batch(schema.clone(), &blocks)?;
+        if batch.num_rows() == 0 {
+            continue;
+        }
+        writer.write(&batch)?;
+        ARROW_EXPORT_ROWS.inc_by(batch.num_rows() as u64);
+        flush(&mut writer, sender).await?;
+    }
+    writer.finish()?;
+    flush(&mut writer, sender).await
+}
+
+// sends the bytes written since the last flush to the client
+async fn flush(writer: &mut StreamWriter<Vec<u8>>, sender: &mut Sender) -> anyhow::Result<()> {
+    let bytes = std::mem::take(writer.get_mut());
+    sender.send_data(bytes.into()).await?;
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::{collections::HashMap, time::Duration};
+
+use futures::StreamExt;
+use log::{info, warn};
+use prometheus::{
+    histogram_opts, opts, register_histogram, register_int_counter_vec, Histogram, IntCounterVec,
+};
+use solana_lite_rpc_core::{
+    stores::data_cache::SlotCache, types::BlockInfoStream, AnyhowJoinHandle,
+};
+use solana_pubsub_client::nonblocking::pubsub_client::PubsubClient;
+use solana_sdk::clock::Slot;
+use tokio::{sync::broadcast::error::RecvError, time::Instant};
+
+lazy_static::lazy_static! {
+    static ref BLOCK_ARRIVAL_LEAD: Histogram = register_histogram!(histogram_opts!(
+        "literpc_block_arrival_lead_ms",
+        "Time in ms a slot was seen by lite-rpc before the reference rpc, negative if the reference was faster",
+        vec![-1000.0, -500.0, -250.0, -100.0, -50.0, -25.0, -10.0, 0.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0]
+    ))
+    .unwrap();
+    static ref BLOCK_ARRIVAL_UNMATCHED: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_block_arrival_unmatched", "Slots only seen by one side within the comparison window"), &["seen_by"]).unwrap();
+}
+
+// slots older than this (relative to the highest slot seen) are evicted without a match
+const COMPARISON_WINDOW_SLOTS: Slot = 64;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+struct Arrival {
+    lite_rpc: Option<Instant>,
+    reference: Option<Instant>,
+    // later blocks of the same slot (other commitments) must not be counted again
+    compared: bool,
+}
+
+/// measures when each slot is seen by lite-rpc (first block of any commitment) compared to the
+/// slot notification of a reference rpc websocket
+pub struct BlockArrivalComparator {
+    reference_ws_addr: String,
+    slot_cache: Option<SlotCache>,
+}
+
+impl BlockArrivalComparator {
+    pub fn new(reference_ws_addr: String) -> Self {
+        Self {
+            reference_ws_addr,
+            slot_cache: None,
+        }
+    }
+
+    /// the slots of the reference rpc node advance the estimated slot when the sources lag
+    pub fn with_slot_cache(mut self, slot_cache: SlotCache) -> Self {
+        self.slot_cache = Some(slot_cache);
+        self
+    }
+
+    pub fn start(self, mut block_info_stream: BlockInfoStream) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            let mut arrivals: HashMap<Slot, Arrival> = HashMap::new();
+            let mut highest_slot: Slot = 0;
+
+            loop {
+                let pubsub_client = match PubsubClient::new(&self.reference_ws_addr).await {
+                    Ok(pubsub_client) => pubsub_client,
+                    Err(err) => {
+                        warn!("block arrival reference connection failed: {err}");
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                };
+                let (mut slot_stream, _unsubscribe) = match pubsub_client.slot_subscribe().await {
+                    Ok(subscription) => subscription,
+                    Err(err) => {
+                        warn!("block arrival reference slot subscription failed: {err}");
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                };
+                info!("comparing block arrival against {}", self.reference_ws_addr);
+
+                loop {
+                    let slot = tokio::select! {
+                        block = block_info_stream.recv() => match block {
+                            Ok(block) => {
+                                let arrival = arrivals.entry(block.slot).or_default();
+                                arrival.lite_rpc.get_or_insert_with(Instant::now);
+                                block.slot
+                            }
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => anyhow::bail!("block stream closed"),
+                        },
+                        slot_info = slot_stream.next() => match slot_info {
+                            Some(slot_info) => {
+                                if let Some(slot_cache) = &self.slot_cache {
+                                    slot_cache.update_reference_slot(slot_info.slot);
+                                }
+                                let arrival = arrivals.entry(slot_info.slot).or_default();
+                                arrival.reference.get_or_insert_with(Instant::now);
+                                slot_info.slot
+                            }
+                            None => {
+                                warn!("block arrival reference slot subscription closed - reconnecting");
+                                break;
+                            }
+                        },
+                    };
+
+                    if let Some(Arrival {
+                        lite_rpc: Some(lite_rpc),
+                        reference: Some(reference),
+                        compared: compared @ false,
+                    }) = arrivals.get_mut(&slot)
+                    {
+                        let lead_ms = if reference >= lite_rpc {
+                            reference.duration_since(*lite_rpc).as_secs_f64() * 1000.0
+                        } else {
+                            -lite_rpc.duration_since(*reference).as_secs_f64() * 1000.0
+                        };
+                        BLOCK_ARRIVAL_LEAD.observe(lead_ms);
+                        *compared = true;
+                    }
+
+                    if slot > highest_slot {
+                        highest_slot = slot;
+                        arrivals.retain(|slot, arrival| {
+                            let keep = *slot + COMPARISON_WINDOW_SLOTS > highest_slot;
+                            if !keep && !arrival.compared {
+                                let seen_by = if arrival.lite_rpc.is_some() {
+                                    "lite_rpc"
+                                } else {
+                                    "reference"
+                                };
+                                BLOCK_ARRIVAL_UNMATCHED.with_label_values(&[seen_by]).inc();
+                            }
+                            keep
+                        });
+                    }
+                }
+            }
+        })
+    }
+}
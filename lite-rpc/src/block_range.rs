@@ -0,0 +1,72 @@
+use std::{ops::RangeInclusive, sync::Arc};
+
+use futures::{stream::BoxStream, StreamExt};
+use log::debug;
+use solana_lite_rpc_core::{
+    stores::data_cache::DataCache, structures::produced_block::ProducedBlock,
+    traits::block_storage::BlockStorage,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, slot_history::Slot};
+
+// block storage lookups of a range in parallel
+const MAX_STORAGE_QUERIES_IN_PARALLEL: usize = 16;
+
+/// Blocks of slot ranges from the block cache, falling back to the block storage for slots which
+/// are no longer cached.
+#[derive(Clone)]
+pub struct BlockRangeReader {
+    data_cache: DataCache,
+    block_storage: Option<Arc<dyn BlockStorage>>,
+}
+
+impl BlockRangeReader {
+    pub fn new(data_cache: DataCache, block_storage: Option<Arc<dyn BlockStorage>>) -> Self {
+        Self {
+            data_cache,
+            block_storage,
+        }
+    }
+
+    /// blocks in slot order, skipped slots and slots unknown to lite-rpc are left out
+    pub async fn stream(
+        &self,
+        slots: RangeInclusive<Slot>,
+        commitment_config: CommitmentConfig,
+    ) -> BoxStream<'static, Arc<ProducedBlock>> {
+        let stored_slots = match &self.block_storage {
+            Some(block_storage) => Some(block_storage.get_slot_range().await),
+            None => None,
+        };
+        let reader = self.clone();
+        futures::stream::iter(slots)
+            .map(move |slot| {
+                let reader = reader.clone();
+                let is_stored = stored_slots
+                    .as_ref()
+                    .map_or(false, |stored_slots| stored_slots.contains(&slot));
+                async move { reader.block(slot, commitment_config, is_stored).await }
+            })
+            .buffered(MAX_STORAGE_QUERIES_IN_PARALLEL)
+            .filter_map(|block| async move { block })
+            .boxed()
+    }
+
+    async fn block(
+        &self,
+        slot: Slot,
+        commitment_config: CommitmentConfig,
+        is_stored: bool,
+    ) -> Option<Arc<ProducedBlock>> {
+        if let Some(block) = self.data_cache.block_cache.get(slot, commitment_config) {
+            return Some(block);
+        }
+        let block_storage = self.block_storage.as_ref().filter(|_| is_stored)?;
+        match block_storage.query_block(slot).await {
+            Ok(block) => Some(Arc::new(block)),
+            Err(err) => {
+                debug!("block {slot} not in block storage: {err}");
+                None
+            }
+        }
+    }
+}
@@ -1,49 +1,71 @@
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use itertools::Itertools;
 use jsonrpsee::core::RpcResult;
 use prometheus::{opts, register_int_counter, IntCounter};
-use solana_account_decoder::UiAccount;
-use solana_lite_rpc_accounts::account_service::AccountService;
-use solana_lite_rpc_prioritization_fees::account_prio_service::AccountPrioService;
-use solana_lite_rpc_prioritization_fees::prioritization_fee_calculation_method::PrioritizationFeeCalculationMethod;
+use serde_json::value::RawValue;
 use solana_rpc_client::nonblocking::rpc_client::RpcClient;
-use solana_rpc_client_api::config::RpcAccountInfoConfig;
-use solana_rpc_client_api::response::{OptionalContext, RpcKeyedAccount};
 use solana_rpc_client_api::{
     config::{
-        RpcBlocksConfigWrapper, RpcContextConfig, RpcGetVoteAccountsConfig,
-        RpcLeaderScheduleConfig, RpcProgramAccountsConfig, RpcRequestAirdropConfig,
-        RpcSignatureStatusConfig, RpcSignaturesForAddressConfig,
+        RpcBlockConfig, RpcBlocksConfigWrapper, RpcContextConfig, RpcEncodingConfigWrapper,
+        RpcEpochConfig, RpcGetVoteAccountsConfig, RpcLargestAccountsConfig,
+        RpcLeaderScheduleConfig, RpcRequestAirdropConfig, RpcSignatureStatusConfig,
+        RpcSignaturesForAddressConfig, RpcSupplyConfig, RpcTransactionConfig,
     },
+    request::{RpcRequest, MAX_GET_SLOT_LEADERS, MAX_MULTIPLE_ACCOUNTS},
     response::{
-        Response as RpcResponse, RpcBlockhash, RpcConfirmedTransactionStatusWithSignature,
-        RpcContactInfo, RpcPerfSample, RpcPrioritizationFee, RpcResponseContext, RpcVersionInfo,
-        RpcVoteAccountStatus,
+        Response as RpcResponse, RpcConfirmedTransactionStatusWithSignature, RpcContactInfo,
+        RpcInflationRate, RpcInflationReward, RpcPerfSample, RpcResponseContext,
+        RpcTokenAccountBalance, RpcVersionInfo, RpcVoteAccountStatus,
     },
 };
 use solana_sdk::epoch_info::EpochInfo;
+use solana_sdk::hash::Hash;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, slot_history::Slot};
-use solana_transaction_status::{TransactionStatus, UiConfirmedBlock};
+use solana_transaction_status::{RewardType, UiConfirmedBlock};
 
+#[cfg(feature = "accounts")]
+use solana_account_decoder::parse_token::token_amount_to_ui_amount;
+#[cfg(feature = "accounts")]
+use solana_lite_rpc_accounts::account_service::AccountService;
 use solana_lite_rpc_blockstore::history::History;
 use solana_lite_rpc_core::{
     encoding,
-    stores::{block_information_store::BlockInformation, data_cache::DataCache},
+    stores::{data_cache::DataCache, tx_lifecycle_store::TxLifecycle},
+    structures::clock_update::ClockUpdate,
+    types::LatestClock,
 };
 use solana_lite_rpc_services::{
-    transaction_service::TransactionService, tx_sender::TXS_IN_CHANNEL,
+    fee_guardrails::FeeLimitExceeded,
+    tpu_utils::tpu_service::UpcomingLeader,
+    transaction_sanitizer::TransactionSanitizeError,
+    transaction_service::{NodeDraining, TransactionService},
+    tx_sender::TXS_IN_CHANNEL,
 };
 
+use crate::cached_proxy::CachedProxy;
+use crate::epoch_value_cache::EpochValueCaches;
+use crate::health_endpoint::HealthState;
+use crate::response_cache::ResponseCache;
 use crate::rpc_errors::RpcErrors;
+use crate::webhook_notifier::WebhookNotifier;
+use crate::DEFAULT_PROXY_CACHE_TTL_SECS;
 use crate::{
     configs::{IsBlockHashValidConfig, SendTransactionConfig},
     rpc::LiteRpcServer,
 };
-use solana_lite_rpc_prioritization_fees::rpc_data::{AccountPrioFeesStats, PrioFeesStats};
-use solana_lite_rpc_prioritization_fees::PrioFeesService;
+
+// same as solana-rpc
+#[cfg(feature = "accounts")]
+const NUM_LARGEST_TOKEN_ACCOUNTS: usize = 20;
+// slots of getUpcomingLeaders without a parameter, the leaders of the next minute
+const DEFAULT_UPCOMING_LEADER_SLOTS: u64 = 150;
 
 lazy_static::lazy_static! {
     static ref RPC_SEND_TX: IntCounter =
@@ -54,10 +76,42 @@ lazy_static::lazy_static! {
     register_int_counter!(opts!("literpc_rpc_is_blockhash_valid", "RPC call to check if blockhash is vali calld")).unwrap();
     static ref RPC_GET_SIGNATURE_STATUSES: IntCounter =
     register_int_counter!(opts!("literpc_rpc_get_signature_statuses", "RPC call to get signature statuses")).unwrap();
+    static ref RPC_GET_UPCOMING_LEADERS: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_upcoming_leaders", "RPC call to get upcoming leaders")).unwrap();
+    static ref RPC_GET_CLOCK: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_clock", "RPC call to get the clock sysvar")).unwrap();
+    static ref RPC_GET_TRANSACTION_LIFECYCLE: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_transaction_lifecycle", "RPC call to get transaction lifecycle")).unwrap();
     static ref RPC_GET_VERSION: IntCounter =
     register_int_counter!(opts!("literpc_rpc_get_version", "RPC call to version")).unwrap();
+    static ref RPC_GET_BLOCK: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_block", "RPC call to get block")).unwrap();
+    static ref RPC_GET_BLOCK_TIME: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_block_time", "RPC call to get block time")).unwrap();
+    static ref RPC_GET_TRANSACTION: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_transaction", "RPC call to get transaction")).unwrap();
     static ref RPC_REQUEST_AIRDROP: IntCounter =
     register_int_counter!(opts!("literpc_rpc_airdrop", "RPC call to request airdrop")).unwrap();
+    static ref RPC_GET_SUPPLY: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_supply", "RPC call to get supply")).unwrap();
+    static ref RPC_GET_LARGEST_ACCOUNTS: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_largest_accounts", "RPC call to get largest accounts")).unwrap();
+    static ref RPC_GET_MAX_RETRANSMIT_SLOT: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_max_retransmit_slot", "RPC call to get max retransmit slot")).unwrap();
+    static ref RPC_GET_MAX_SHRED_INSERT_SLOT: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_max_shred_insert_slot", "RPC call to get max shred insert slot")).unwrap();
+    static ref RPC_GET_HIGHEST_SNAPSHOT_SLOT: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_highest_snapshot_slot", "RPC call to get highest snapshot slot")).unwrap();
+    static ref RPC_MINIMUM_LEDGER_SLOT: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_minimum_ledger_slot", "RPC call to get minimum ledger slot")).unwrap();
+    static ref RPC_GET_TOKEN_LARGEST_ACCOUNTS: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_token_largest_accounts", "RPC call to get token largest accounts")).unwrap();
+    static ref RPC_GET_STAKE_MINIMUM_DELEGATION: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_stake_minimum_delegation", "RPC call to get stake minimum delegation")).unwrap();
+    static ref RPC_GET_INFLATION_RATE: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_inflation_rate", "RPC call to get inflation rate")).unwrap();
+    static ref RPC_GET_INFLATION_REWARD: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_get_inflation_reward", "RPC call to get inflation reward")).unwrap();
 }
 
 /// A bridge between clients and tpu
@@ -67,48 +121,132 @@ pub struct LiteBridge {
     data_cache: DataCache,
     transaction_service: TransactionService,
     history: History,
-    prio_fees_service: PrioFeesService,
-    account_priofees_service: AccountPrioService,
+    webhook_notifier: WebhookNotifier,
+    health_state: HealthState,
+    response_cache: ResponseCache,
+    epoch_value_caches: EpochValueCaches,
+    cached_proxy: CachedProxy,
+    clock: Option<LatestClock>,
+    #[cfg(feature = "accounts")]
     accounts_service: Option<AccountService>,
 }
 
 impl LiteBridge {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         rpc_client: Arc<RpcClient>,
         data_cache: DataCache,
         transaction_service: TransactionService,
         history: History,
-        prio_fees_service: PrioFeesService,
-        account_priofees_service: AccountPrioService,
-        accounts_service: Option<AccountService>,
+        webhook_notifier: WebhookNotifier,
+        health_state: HealthState,
     ) -> Self {
         Self {
+            response_cache: ResponseCache::new(&data_cache),
+            epoch_value_caches: EpochValueCaches::new(rpc_client.clone()),
+            cached_proxy: CachedProxy::new(
+                rpc_client.clone(),
+                Duration::from_secs(DEFAULT_PROXY_CACHE_TTL_SECS),
+            ),
+            clock: None,
+            #[cfg(feature = "accounts")]
+            accounts_service: None,
             rpc_client,
             data_cache,
             transaction_service,
             history,
-            prio_fees_service,
-            account_priofees_service,
-            accounts_service,
+            webhook_notifier,
+            health_state,
+        }
+    }
+
+    /// how long the answers of getSupply, getLargestAccounts and the other cached rpc node methods
+    /// are reused
+    pub fn with_proxy_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cached_proxy = CachedProxy::new(self.rpc_client.clone(), ttl);
+        self
+    }
+
+    /// epoch bound values refreshed by the epoch notifier, without it they are fetched by the first request
+    /// of an epoch
+    pub fn with_epoch_value_caches(mut self, epoch_value_caches: EpochValueCaches) -> Self {
+        self.epoch_value_caches = epoch_value_caches;
+        self
+    }
+
+    /// getClock is answered from the clock stream, from the rpc node without it
+    pub fn with_clock(mut self, clock: LatestClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// getTokenLargestAccounts of the mints in the account store is answered from their token accounts
+    #[cfg(feature = "accounts")]
+    pub fn with_accounts_service(mut self, accounts_service: AccountService) -> Self {
+        self.accounts_service = Some(accounts_service);
+        self
+    }
+
+    /// blockhashes and statuses are incomplete until the block stream caught up after the start
+    async fn ensure_caught_up(&self) -> RpcResult<()> {
+        self.health_state.is_caught_up().await.map_err(|reason| {
+            jsonrpsee::types::ErrorObject::owned(
+                RpcErrors::NodeUnavailable as i32,
+                format!("node is starting: {reason}"),
+                None::<()>,
+            )
+        })
+    }
+
+    /// the slot derived from the slot stream once it caught up, the answer of the rpc node before
+    async fn slot_telemetry_or_proxy(&self, request: RpcRequest, slot: Slot) -> RpcResult<Slot> {
+        if self.health_state.is_caught_up().await.is_ok() {
+            return Ok(slot);
         }
+        self.rpc_client
+            .send::<Slot>(request, serde_json::json!([]))
+            .await
+            .map_err(|err| {
+                log::debug!("{request} failed: {err:?}");
+                jsonrpsee::types::error::ErrorCode::InternalError.into()
+            })
+    }
+
+    async fn send_cached(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> RpcResult<Box<RawValue>> {
+        self.cached_proxy
+            .send(request, params)
+            .await
+            .map_err(|err| {
+                log::debug!("{request} failed: {err:?}");
+                jsonrpsee::types::error::ErrorCode::InternalError.into()
+            })
     }
 }
 
 #[jsonrpsee::core::async_trait]
 impl LiteRpcServer for LiteBridge {
-    async fn get_block(&self, _slot: u64) -> RpcResult<Option<UiConfirmedBlock>> {
-        // let block = self.blockstore.block_storage.query_block(slot).await;
-        // if block.is_ok() {
-        //     // TO DO Convert to UIConfirmed Block
-        //     Err(jsonrpsee::core::Error::HttpNotImplemented)
-        // } else {
-        //     Ok(None)
-        // }
-
-        // TODO get_block might deserve different implementation based on whether we serve from "blockstore module" vs. from "send tx module"
+    #[tracing::instrument(skip(self, config), level = "debug")]
+    async fn get_block(
+        &self,
+        slot: u64,
+        config: Option<RpcEncodingConfigWrapper<RpcBlockConfig>>,
+    ) -> RpcResult<Option<UiConfirmedBlock>> {
+        RPC_GET_BLOCK.inc();
+        let config = config
+            .map(|config| config.convert_to_current())
+            .unwrap_or_default();
 
-        // under progress
-        Err(jsonrpsee::types::error::ErrorCode::MethodNotFound.into())
+        match self.history.get_block(slot, config).await {
+            Ok(block) => Ok(block),
+            Err(err) => {
+                log::debug!("getBlock {slot} failed: {err:?}");
+                Err(jsonrpsee::types::error::ErrorCode::InternalError.into())
+            }
+        }
     }
 
     async fn get_blocks(
@@ -130,6 +268,32 @@ impl LiteRpcServer for LiteBridge {
         Err(jsonrpsee::types::error::ErrorCode::MethodNotFound.into())
     }
 
+    #[tracing::instrument(skip_all, level = "debug")]
+    async fn get_transaction(
+        &self,
+        signature_str: String,
+        config: Option<RpcEncodingConfigWrapper<RpcTransactionConfig>>,
+    ) -> RpcResult<Option<serde_json::Value>> {
+        RPC_GET_TRANSACTION.inc();
+        let Ok(signature) = Signature::from_str(&signature_str) else {
+            return Err(invalid_params(format!("Invalid param: {signature_str}")));
+        };
+        let config = config
+            .map(|config| config.convert_to_current())
+            .unwrap_or_default();
+
+        // unknown signatures are answered with null
+        match self.history.get_transaction(&signature, config).await {
+            Ok(transaction) => Ok(transaction.map(|transaction| {
+                serde_json::to_value(transaction).expect("transaction should serialize")
+            })),
+            Err(err) => {
+                log::debug!("getTransaction {signature} failed: {err:?}");
+                Err(jsonrpsee::types::error::ErrorCode::InternalError.into())
+            }
+        }
+    }
+
     async fn get_cluster_nodes(&self) -> RpcResult<Vec<RpcContactInfo>> {
         Ok(self
             .data_cache
@@ -140,17 +304,12 @@ impl LiteRpcServer for LiteBridge {
             .collect_vec())
     }
 
-    async fn get_slot(&self, config: Option<RpcContextConfig>) -> RpcResult<Slot> {
+    async fn get_slot(&self, config: Option<RpcContextConfig>) -> RpcResult<Box<RawValue>> {
         let commitment_config = config
             .map(|config| config.commitment.unwrap_or_default())
             .unwrap_or_default();
 
-        let BlockInformation { slot, .. } = self
-            .data_cache
-            .block_information_store
-            .get_latest_block(commitment_config)
-            .await;
-        Ok(slot)
+        Ok(self.response_cache.get_slot(commitment_config))
     }
 
     async fn get_block_height(&self, config: Option<RpcContextConfig>) -> RpcResult<u64> {
@@ -166,13 +325,33 @@ impl LiteRpcServer for LiteBridge {
     }
 
     async fn get_block_time(&self, slot: u64) -> RpcResult<u64> {
-        let block_info = self
+        RPC_GET_BLOCK_TIME.inc();
+        if let Some(info) = self
             .data_cache
             .block_information_store
-            .get_block_info_by_slot(slot);
-        match block_info {
-            Some(info) => Ok(info.block_time),
-            None => Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into()),
+            .get_block_info_by_slot(slot)
+        {
+            return Ok(info.block_time);
+        }
+        if let Some(block) = self
+            .data_cache
+            .block_cache
+            .get(slot, CommitmentConfig::processed())
+        {
+            return Ok(block.block_time);
+        }
+
+        match self.history.get_block_time(slot).await {
+            Ok(Some(block_time)) => Ok(block_time as u64),
+            Ok(None) => Err(jsonrpsee::types::ErrorObject::owned(
+                RpcErrors::BlockNotAvailable as i32,
+                format!("Block not available for slot {slot}"),
+                None::<()>,
+            )),
+            Err(err) => {
+                log::debug!("getBlockTime {slot} failed: {err:?}");
+                Err(jsonrpsee::types::error::ErrorCode::InternalError.into())
+            }
         }
     }
 
@@ -184,36 +363,15 @@ impl LiteRpcServer for LiteBridge {
     async fn get_latest_blockhash(
         &self,
         config: Option<RpcContextConfig>,
-    ) -> RpcResult<RpcResponse<RpcBlockhash>> {
+    ) -> RpcResult<Box<RawValue>> {
         RPC_GET_LATEST_BLOCKHASH.inc();
+        self.ensure_caught_up().await?;
 
         let commitment_config = config
             .map(|config| config.commitment.unwrap_or_default())
             .unwrap_or_default();
 
-        let BlockInformation {
-            slot,
-            block_height,
-            blockhash,
-            ..
-        } = self
-            .data_cache
-            .block_information_store
-            .get_latest_block(commitment_config)
-            .await;
-
-        log::trace!("glb {blockhash} {slot} {block_height}");
-
-        Ok(RpcResponse {
-            context: RpcResponseContext {
-                slot,
-                api_version: None,
-            },
-            value: RpcBlockhash {
-                blockhash,
-                last_valid_block_height: block_height + 150,
-            },
-        })
+        Ok(self.response_cache.get_latest_blockhash(commitment_config))
     }
 
     async fn is_blockhash_valid(
@@ -222,15 +380,23 @@ impl LiteRpcServer for LiteBridge {
         config: Option<IsBlockHashValidConfig>,
     ) -> RpcResult<RpcResponse<bool>> {
         RPC_IS_BLOCKHASH_VALID.inc();
+        self.ensure_caught_up().await?;
 
+        let blockhash = Hash::from_str(&blockhash)
+            .map_err(|_| invalid_params(format!("Invalid param: {blockhash}")))?;
         let commitment = config.unwrap_or_default().commitment.unwrap_or_default();
         let commitment = CommitmentConfig { commitment };
 
-        let (is_valid, slot) = self
+        let slot = self
             .data_cache
             .block_information_store
-            .is_blockhash_valid(&blockhash, commitment)
-            .await;
+            .get_latest_block(commitment)
+            .await
+            .slot;
+        let is_valid = self
+            .data_cache
+            .blockhash_cache
+            .is_valid(&blockhash, commitment);
 
         Ok(RpcResponse {
             context: RpcResponseContext {
@@ -275,76 +441,67 @@ impl LiteRpcServer for LiteBridge {
     async fn get_signature_statuses(
         &self,
         sigs: Vec<String>,
-        _config: Option<RpcSignatureStatusConfig>,
-    ) -> RpcResult<RpcResponse<Vec<Option<TransactionStatus>>>> {
+        config: Option<RpcSignatureStatusConfig>,
+    ) -> RpcResult<RpcResponse<Vec<Option<Box<RawValue>>>>> {
         RPC_GET_SIGNATURE_STATUSES.inc();
+        self.ensure_caught_up().await?;
+        let search_transaction_history = config
+            .map(|config| config.search_transaction_history)
+            .unwrap_or_default();
 
-        let sig_statuses = sigs
-            .iter()
-            .map(|sig| self.data_cache.txs.get(sig).and_then(|v| v.status))
-            .collect();
+        let mut sig_statuses = Vec::with_capacity(sigs.len());
+        for sig in &sigs {
+            // unknown and malformed signatures are both reported without status
+            let Ok(sig) = Signature::from_str(sig) else {
+                sig_statuses.push(None);
+                continue;
+            };
+            let mut status = self.response_cache.get_signature_status(&sig);
+            if status.is_none() && search_transaction_history {
+                status = match self.history.get_signature_status(&sig).await {
+                    Ok(stored) => stored.map(|stored| {
+                        serde_json::value::to_raw_value(&stored).expect("status serializes")
+                    }),
+                    Err(err) => {
+                        log::debug!(
+                            "Failed to look up the status of {sig} in the block storage: {err:?}"
+                        );
+                        None
+                    }
+                };
+            }
+            sig_statuses.push(status);
+        }
 
         Ok(RpcResponse {
             context: RpcResponseContext {
-                slot: self
-                    .data_cache
-                    .block_information_store
-                    .get_latest_block_info(CommitmentConfig::finalized())
-                    .await
-                    .slot,
+                slot: self.response_cache.finalized_slot(),
                 api_version: None,
             },
             value: sig_statuses,
         })
     }
 
-    async fn get_recent_prioritization_fees(
+    async fn get_transaction_lifecycle(
         &self,
-        pubkey_strs: Vec<String>,
-    ) -> RpcResult<Vec<RpcPrioritizationFee>> {
-        // This method will get the latest global and account prioritization fee stats and then send the maximum p75
-        const PERCENTILE: f32 = 0.75;
-        let accounts = pubkey_strs
-            .iter()
-            .filter_map(|pubkey| Pubkey::from_str(pubkey).ok())
-            .collect_vec();
-        if accounts.len() != pubkey_strs.len() {
-            // if lengths do not match it means some of the accounts are invalid
-            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
-        }
-
-        let global_prio_fees = self.prio_fees_service.get_latest_priofees().await;
-        let max_p75 = global_prio_fees
-            .map(|(_, fees)| {
-                let fees = fees.get_percentile(PERCENTILE).unwrap_or_default();
-                std::cmp::max(fees.0, fees.1)
-            })
-            .unwrap_or_default();
+        signature_str: String,
+    ) -> RpcResult<Option<TxLifecycle>> {
+        RPC_GET_TRANSACTION_LIFECYCLE.inc();
 
-        let ret: Vec<RpcPrioritizationFee> = accounts
-            .iter()
-            .map(|account| {
-                let (slot, stats) = self.account_priofees_service.get_latest_stats(account);
-                let stat = stats
-                    .all_stats
-                    .get_percentile(PERCENTILE)
-                    .unwrap_or_default();
-                RpcPrioritizationFee {
-                    slot,
-                    prioritization_fee: std::cmp::max(max_p75, std::cmp::max(stat.0, stat.1)),
-                }
-            })
-            .collect_vec();
-
-        Ok(ret)
+        let Ok(signature) = Signature::from_str(&signature_str) else {
+            return Err(invalid_params(format!("Invalid param: {signature_str}")));
+        };
+        Ok(self.data_cache.tx_lifecycles.get(&signature))
     }
 
+    #[tracing::instrument(skip_all, level = "debug")]
     async fn send_transaction(
         &self,
         tx: String,
         send_transaction_config: Option<SendTransactionConfig>,
     ) -> RpcResult<String> {
         RPC_SEND_TX.inc();
+        self.ensure_caught_up().await?;
 
         // Copied these constants from solana labs code
         const MAX_BASE58_SIZE: usize = 1683;
@@ -353,6 +510,7 @@ impl LiteRpcServer for LiteBridge {
         let SendTransactionConfig {
             encoding,
             max_retries,
+            callback_url,
         } = send_transaction_config.unwrap_or_default();
 
         let expected_size = match encoding {
@@ -360,27 +518,79 @@ impl LiteRpcServer for LiteBridge {
             encoding::BinaryEncoding::Base64 => MAX_BASE64_SIZE,
         };
         if tx.len() > expected_size {
-            return Err(jsonrpsee::types::error::ErrorCode::OversizedRequest.into());
+            return Err(invalid_params(format!(
+                "{} encoded solana_sdk::transaction::VersionedTransaction too large: {} bytes (max: encoded/raw {expected_size}/{PACKET_DATA_SIZE})",
+                encoding_name(encoding),
+                tx.len()
+            )));
         }
 
         let raw_tx = match encoding.decode(tx) {
             Ok(raw_tx) => raw_tx,
-            Err(_) => {
-                return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+            Err(err) => {
+                return Err(invalid_params(format!(
+                    "invalid {} encoding: {err}",
+                    encoding_name(encoding)
+                )));
             }
         };
 
+        let webhook = match callback_url {
+            Some(callback_url) => {
+                let url = self
+                    .webhook_notifier
+                    .parse_callback_url(&callback_url)
+                    .map_err(|err| invalid_params(format!("invalid callbackUrl: {err}")))?;
+                let tx = bincode::deserialize::<VersionedTransaction>(&raw_tx).map_err(|err| {
+                    invalid_params(format!(
+                        "failed to deserialize solana_sdk::transaction::VersionedTransaction: {err}"
+                    ))
+                })?;
+                // the expiry reported to the callback needs the last valid block height of the blockhash
+                let Some(blockhash_info) = self
+                    .data_cache
+                    .blockhash_cache
+                    .get(tx.message.recent_blockhash())
+                else {
+                    return Err(invalid_params("blockhash not found".to_string()));
+                };
+                Some((url, blockhash_info.last_valid_block_height))
+            }
+            None => None,
+        };
+
         match self
             .transaction_service
             .send_transaction(raw_tx, max_retries)
             .await
         {
+            // a dry run never lands, its webhook would only report the expiry
+            Ok(sig) if self.transaction_service.dry_run.is_active() => Ok(sig.to_string()),
             Ok(sig) => {
                 TXS_IN_CHANNEL.inc();
 
-                Ok(sig)
+                if let Some((url, last_valid_block_height)) = webhook {
+                    self.webhook_notifier
+                        .register(sig, url, last_valid_block_height);
+                }
+
+                Ok(sig.to_string())
+            }
+            Err(err) => {
+                if let Some(sanitize_error) = err.downcast_ref::<TransactionSanitizeError>() {
+                    Err(sanitize_error_object(sanitize_error))
+                } else if let Some(fee_limit_exceeded) = err.downcast_ref::<FeeLimitExceeded>() {
+                    Err(invalid_params(fee_limit_exceeded.to_string()))
+                } else if let Some(node_draining) = err.downcast_ref::<NodeDraining>() {
+                    Err(jsonrpsee::types::ErrorObject::owned(
+                        RpcErrors::NodeUnavailable as i32,
+                        node_draining.to_string(),
+                        None::<()>,
+                    ))
+                } else {
+                    Err(jsonrpsee::types::error::ErrorCode::InternalError.into())
+                }
             }
-            Err(_) => Err(jsonrpsee::types::error::ErrorCode::InternalError.into()),
         }
     }
 
@@ -434,6 +644,49 @@ impl LiteRpcServer for LiteBridge {
             })
     }
 
+    async fn get_upcoming_leaders(&self, slots: Option<u64>) -> RpcResult<Vec<UpcomingLeader>> {
+        RPC_GET_UPCOMING_LEADERS.inc();
+
+        let slots = slots.unwrap_or(DEFAULT_UPCOMING_LEADER_SLOTS);
+        if slots == 0 || slots > MAX_GET_SLOT_LEADERS as u64 {
+            return Err(invalid_params(format!(
+                "Invalid slots; must be between 1 and {MAX_GET_SLOT_LEADERS}"
+            )));
+        }
+        self.transaction_service
+            .tpu_service
+            .upcoming_leaders(slots)
+            .await
+            .map_err(|err| {
+                log::error!("Error resolving the upcoming leaders : {err:?}");
+                jsonrpsee::types::error::ErrorCode::InternalError.into()
+            })
+    }
+
+    async fn get_clock(&self) -> RpcResult<ClockUpdate> {
+        RPC_GET_CLOCK.inc();
+
+        if let Some(clock) = self.clock.as_ref().and_then(|clock| clock.borrow().clone()) {
+            return Ok(clock);
+        }
+        let account = self
+            .rpc_client
+            .get_account_with_commitment(
+                &solana_sdk::sysvar::clock::id(),
+                CommitmentConfig::processed(),
+            )
+            .await
+            .map_err(|err| {
+                log::debug!("Error fetching the clock sysvar : {err:?}");
+                jsonrpsee::types::error::ErrorCode::InternalError
+            })?
+            .value;
+        account
+            .as_ref()
+            .and_then(ClockUpdate::from_account)
+            .ok_or_else(|| jsonrpsee::types::error::ErrorCode::InternalError.into())
+    }
+
     async fn get_vote_accounts(
         &self,
         _config: Option<RpcGetVoteAccountsConfig>,
@@ -442,179 +695,258 @@ impl LiteRpcServer for LiteBridge {
         Err(jsonrpsee::types::error::ErrorCode::MethodNotFound.into())
     }
 
-    async fn get_latest_block_priofees(
+    async fn get_stake_minimum_delegation(
         &self,
-        method: Option<PrioritizationFeeCalculationMethod>,
-    ) -> RpcResult<RpcResponse<PrioFeesStats>> {
-        let method = method.unwrap_or_default();
-        let res = match method {
-            PrioritizationFeeCalculationMethod::Latest => {
-                self.prio_fees_service.get_latest_priofees().await
-            }
-            PrioritizationFeeCalculationMethod::LastNBlocks(nb) => {
-                self.prio_fees_service
-                    .get_last_n_priofees_aggregate(nb)
-                    .await
-            }
-            _ => {
-                // method is invalid
-                return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
-            }
-        };
+        config: Option<RpcContextConfig>,
+    ) -> RpcResult<RpcResponse<u64>> {
+        RPC_GET_STAKE_MINIMUM_DELEGATION.inc();
+        let commitment_config = config
+            .map(|config| config.commitment.unwrap_or_default())
+            .unwrap_or_default();
+        let slot = self
+            .data_cache
+            .block_information_store
+            .get_latest_block(commitment_config)
+            .await
+            .slot;
+        let epoch = self.data_cache.epoch_data.get_epoch_at_slot(slot).epoch;
 
-        match res {
-            Some((confirmation_slot, priofees)) => Ok(RpcResponse {
-                context: RpcResponseContext {
-                    slot: confirmation_slot,
-                    api_version: None,
-                },
-                value: priofees,
-            }),
-            None => Err(jsonrpsee::types::error::ErrorCode::InternalError.into()),
-        }
+        let minimum_delegation = self
+            .epoch_value_caches
+            .stake_minimum_delegation
+            .get(epoch)
+            .await
+            .map_err(|err| {
+                log::debug!("getStakeMinimumDelegation failed: {err:?}");
+                jsonrpsee::types::ErrorObjectOwned::from(
+                    jsonrpsee::types::error::ErrorCode::InternalError,
+                )
+            })?;
+
+        Ok(RpcResponse {
+            context: RpcResponseContext {
+                slot,
+                api_version: None,
+            },
+            value: minimum_delegation,
+        })
     }
 
-    async fn get_latest_account_priofees(
+    async fn get_supply(&self, config: Option<RpcSupplyConfig>) -> RpcResult<Box<RawValue>> {
+        RPC_GET_SUPPLY.inc();
+        self.send_cached(RpcRequest::GetSupply, serde_json::json!([config]))
+            .await
+    }
+
+    async fn get_largest_accounts(
         &self,
-        account: String,
-        method: Option<PrioritizationFeeCalculationMethod>,
-    ) -> RpcResult<RpcResponse<AccountPrioFeesStats>> {
-        if let Ok(account) = Pubkey::from_str(&account) {
-            let method = method.unwrap_or_default();
-            let (slot, value) = match method {
-                PrioritizationFeeCalculationMethod::Latest => {
-                    self.account_priofees_service.get_latest_stats(&account)
-                }
-                PrioritizationFeeCalculationMethod::LastNBlocks(nb) => {
-                    self.account_priofees_service.get_n_last_stats(&account, nb)
-                }
-                _ => return Err(jsonrpsee::types::error::ErrorCode::InternalError.into()),
-            };
-            Ok(RpcResponse {
-                context: RpcResponseContext {
-                    slot,
-                    api_version: None,
-                },
-                value,
-            })
-        } else {
-            // Account key is invalid
-            Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into())
-        }
+        config: Option<RpcLargestAccountsConfig>,
+    ) -> RpcResult<Box<RawValue>> {
+        RPC_GET_LARGEST_ACCOUNTS.inc();
+        self.send_cached(RpcRequest::GetLargestAccounts, serde_json::json!([config]))
+            .await
+    }
+
+    async fn get_max_retransmit_slot(&self) -> RpcResult<Slot> {
+        RPC_GET_MAX_RETRANSMIT_SLOT.inc();
+        // the slot the cluster is estimated to be at is the one shreds are retransmitted for
+        let slot = self.data_cache.slot_cache.get_estimated_slot();
+        self.slot_telemetry_or_proxy(RpcRequest::GetMaxRetransmitSlot, slot)
+            .await
+    }
+
+    async fn get_max_shred_insert_slot(&self) -> RpcResult<Slot> {
+        RPC_GET_MAX_SHRED_INSERT_SLOT.inc();
+        let slot_cache = &self.data_cache.slot_cache;
+        let slot = slot_cache
+            .get_current_slot()
+            .max(slot_cache.get_estimated_slot());
+        self.slot_telemetry_or_proxy(RpcRequest::GetMaxShredInsertSlot, slot)
+            .await
+    }
+
+    async fn get_highest_snapshot_slot(&self) -> RpcResult<Box<RawValue>> {
+        RPC_GET_HIGHEST_SNAPSHOT_SLOT.inc();
+        self.send_cached(RpcRequest::GetHighestSnapshotSlot, serde_json::json!([]))
+            .await
+    }
+
+    async fn minimum_ledger_slot(&self) -> RpcResult<Box<RawValue>> {
+        RPC_MINIMUM_LEDGER_SLOT.inc();
+        self.send_cached(RpcRequest::MinimumLedgerSlot, serde_json::json!([]))
+            .await
     }
 
-    async fn get_account_info(
+    async fn get_token_largest_accounts(
         &self,
-        pubkey_str: String,
-        config: Option<RpcAccountInfoConfig>,
-    ) -> RpcResult<RpcResponse<Option<UiAccount>>> {
-        let Ok(pubkey) = Pubkey::from_str(&pubkey_str) else {
-            // pubkey is invalid
-            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        mint_str: String,
+        commitment: Option<CommitmentConfig>,
+    ) -> RpcResult<RpcResponse<Vec<RpcTokenAccountBalance>>> {
+        RPC_GET_TOKEN_LARGEST_ACCOUNTS.inc();
+        let Ok(mint) = Pubkey::from_str(&mint_str) else {
+            return Err(invalid_params(format!("Invalid param: {mint_str}")));
         };
-        if let Some(account_service) = &self.accounts_service {
-            match account_service.get_account(pubkey, config).await {
-                Ok((slot, ui_account)) => Ok(RpcResponse {
+
+        #[cfg(feature = "accounts")]
+        if let Some(accounts_service) = &self.accounts_service {
+            if let Some((slot, decimals, largest)) = accounts_service
+                .get_token_largest_accounts(
+                    mint,
+                    commitment.unwrap_or_default(),
+                    NUM_LARGEST_TOKEN_ACCOUNTS,
+                )
+                .await
+            {
+                return Ok(RpcResponse {
                     context: RpcResponseContext {
                         slot,
                         api_version: None,
                     },
-                    value: ui_account,
-                }),
-                Err(_) => {
-                    // account not found
-                    Err(jsonrpsee::types::error::ErrorCode::ServerError(
-                        RpcErrors::AccountNotFound as i32,
-                    )
-                    .into())
-                }
+                    value: largest
+                        .into_iter()
+                        .map(|(address, amount)| RpcTokenAccountBalance {
+                            address: address.to_string(),
+                            amount: token_amount_to_ui_amount(amount, decimals),
+                        })
+                        .collect(),
+                });
             }
-        } else {
-            // accounts are disabled
-            Err(jsonrpsee::types::error::ErrorCode::MethodNotFound.into())
         }
+
+        // mints which are not in the account store
+        let largest = self
+            .send_cached(
+                RpcRequest::GetTokenLargestAccounts,
+                serde_json::json!([mint.to_string(), commitment]),
+            )
+            .await?;
+        serde_json::from_str(largest.get()).map_err(|err| {
+            log::debug!("getTokenLargestAccounts answer of the rpc node is invalid: {err:?}");
+            jsonrpsee::types::error::ErrorCode::InternalError.into()
+        })
+    }
+
+    async fn get_inflation_rate(&self) -> RpcResult<RpcInflationRate> {
+        RPC_GET_INFLATION_RATE.inc();
+        let epoch = self
+            .data_cache
+            .get_current_epoch(CommitmentConfig::finalized())
+            .await
+            .epoch;
+
+        self.epoch_value_caches
+            .inflation_rate
+            .get(epoch)
+            .await
+            .map_err(|err| {
+                log::debug!("getInflationRate for epoch {epoch} failed: {err:?}");
+                jsonrpsee::types::error::ErrorCode::InternalError.into()
+            })
     }
 
-    async fn get_multiple_accounts(
+    async fn get_inflation_reward(
         &self,
-        pubkey_strs: Vec<String>,
-        config: Option<RpcAccountInfoConfig>,
-    ) -> RpcResult<RpcResponse<Vec<Option<UiAccount>>>> {
-        let pubkeys = pubkey_strs
+        address_strs: Vec<String>,
+        config: Option<RpcEpochConfig>,
+    ) -> RpcResult<Vec<Option<RpcInflationReward>>> {
+        RPC_GET_INFLATION_REWARD.inc();
+        if address_strs.len() > MAX_MULTIPLE_ACCOUNTS {
+            return Err(invalid_params(format!(
+                "Too many inputs provided; max {MAX_MULTIPLE_ACCOUNTS}"
+            )));
+        }
+        let addresses = address_strs
             .iter()
-            .map(|key| Pubkey::from_str(key))
-            .collect_vec();
-        if pubkeys.iter().any(|res| res.is_err()) {
-            return Err(jsonrpsee::types::error::ErrorCode::InternalError.into());
-        };
+            .map(|address| {
+                Pubkey::from_str(address)
+                    .map_err(|_| invalid_params(format!("Invalid param: {address}")))
+            })
+            .collect::<RpcResult<Vec<_>>>()?;
 
-        if let Some(account_service) = &self.accounts_service {
-            let mut ui_accounts = vec![];
-            let mut max_slot = 0;
-            for pubkey in pubkeys {
-                match account_service
-                    .get_account(pubkey.unwrap(), config.clone())
-                    .await
-                {
-                    Ok((slot, ui_account)) => {
-                        if slot > max_slot {
-                            max_slot = slot;
-                        }
-                        ui_accounts.push(ui_account);
-                    }
-                    Err(_) => {
-                        // internal error while fetching multiple accounts
-                        return Err(jsonrpsee::types::error::ErrorCode::ServerError(
-                            RpcErrors::AccountNotFound as i32,
-                        )
-                        .into());
-                    }
-                }
+        let config = config.unwrap_or_default();
+        let current_epoch = self
+            .data_cache
+            .get_current_epoch(config.commitment.unwrap_or_default())
+            .await
+            .epoch;
+        // the rewards of an epoch are paid out in the first block of the next epoch
+        let epoch = config.epoch.unwrap_or(current_epoch.saturating_sub(1));
+        if epoch >= current_epoch {
+            return Err(invalid_params(format!("Epoch {epoch} is not complete yet")));
+        }
+        let first_slot = self
+            .data_cache
+            .epoch_data
+            .get_first_slot_in_epoch(epoch + 1);
+
+        let (effective_slot, rewards) = match self.history.get_first_block_rewards(first_slot).await
+        {
+            Ok(Some(block_rewards)) => block_rewards,
+            Ok(None) => {
+                return Err(jsonrpsee::types::ErrorObject::owned(
+                    RpcErrors::BlockNotAvailable as i32,
+                    format!("Block not available for slot {first_slot}"),
+                    None::<()>,
+                ))
+            }
+            Err(err) => {
+                log::debug!("getInflationReward for epoch {epoch} failed: {err:?}");
+                return Err(jsonrpsee::types::error::ErrorCode::InternalError.into());
             }
-            Ok(RpcResponse {
-                context: RpcResponseContext {
-                    slot: max_slot,
-                    api_version: None,
-                },
-                value: ui_accounts,
+        };
+
+        let rewards: HashMap<String, _> = rewards
+            .into_iter()
+            .filter(|reward| {
+                matches!(
+                    reward.reward_type,
+                    Some(RewardType::Staking) | Some(RewardType::Voting)
+                )
             })
-        } else {
-            // accounts are disabled
-            Err(jsonrpsee::types::error::ErrorCode::MethodNotFound.into())
-        }
+            .map(|reward| (reward.pubkey.clone(), reward))
+            .collect();
+        Ok(addresses
+            .iter()
+            .map(|address| {
+                rewards
+                    .get(&address.to_string())
+                    .map(|reward| RpcInflationReward {
+                        epoch,
+                        effective_slot,
+                        amount: reward.lamports.unsigned_abs(),
+                        post_balance: reward.post_balance,
+                        commission: reward.commission,
+                    })
+            })
+            .collect())
     }
+}
 
-    async fn get_program_accounts(
-        &self,
-        program_id_str: String,
-        config: Option<RpcProgramAccountsConfig>,
-    ) -> RpcResult<OptionalContext<Vec<RpcKeyedAccount>>> {
-        let Ok(program_id) = Pubkey::from_str(&program_id_str) else {
-            return Err(jsonrpsee::types::error::ErrorCode::InternalError.into());
-        };
+fn encoding_name(encoding: encoding::BinaryEncoding) -> &'static str {
+    match encoding {
+        encoding::BinaryEncoding::Base58 => "base58",
+        encoding::BinaryEncoding::Base64 => "base64",
+    }
+}
 
-        if let Some(account_service) = &self.accounts_service {
-            match account_service
-                .get_program_accounts(program_id, config)
-                .await
-            {
-                Ok((slot, ui_account)) => Ok(OptionalContext::Context(RpcResponse {
-                    context: RpcResponseContext {
-                        slot,
-                        api_version: None,
-                    },
-                    value: ui_account,
-                })),
-                Err(_) => {
-                    return Err(jsonrpsee::types::error::ErrorCode::ServerError(
-                        RpcErrors::AccountNotFound as i32,
-                    )
-                    .into());
-                }
-            }
-        } else {
-            // accounts are disabled
-            Err(jsonrpsee::types::error::ErrorCode::MethodNotFound.into())
+fn invalid_params(message: String) -> jsonrpsee::types::ErrorObjectOwned {
+    jsonrpsee::types::ErrorObject::owned(
+        jsonrpsee::types::error::INVALID_PARAMS_CODE,
+        message,
+        None::<()>,
+    )
+}
+
+// the error codes of solana-rpc for transactions it refuses to send
+fn sanitize_error_object(err: &TransactionSanitizeError) -> jsonrpsee::types::ErrorObjectOwned {
+    let code = match err {
+        TransactionSanitizeError::SignatureVerificationFailure => {
+            RpcErrors::TransactionSignatureVerificationFailure as i32
         }
-    }
+        TransactionSanitizeError::SignatureLenMismatch => {
+            RpcErrors::TransactionSignatureLenMismatch as i32
+        }
+        _ => jsonrpsee::types::error::INVALID_PARAMS_CODE,
+    };
+    jsonrpsee::types::ErrorObject::owned(code, err.to_string(), None::<()>)
 }
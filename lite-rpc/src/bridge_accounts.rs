@@ -0,0 +1,580 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use itertools::Itertools;
+use jsonrpsee::core::RpcResult;
+use solana_account_decoder::UiAccount;
+use solana_lite_rpc_accounts::account_service::AccountService;
+use solana_rpc_client_api::config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_rpc_client_api::filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType};
+use solana_rpc_client_api::response::{
+    OptionalContext, Response as RpcResponse, RpcKeyedAccount, RpcResponseContext,
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::configs::{StakeAccountsConfig, StakeAuthority};
+use crate::rpc::LiteAccountsRpcServer;
+use crate::rpc_errors::RpcErrors;
+#[cfg(feature = "ws-server")]
+use crate::rpc_metrics::ActiveSubscription;
+#[cfg(feature = "ws-server")]
+use crate::rpc_pubsub::LiteAccountsPubSubServer;
+#[cfg(feature = "ws-server")]
+use jsonrpsee::{
+    core::{StringError, SubscriptionResult},
+    DisconnectError, PendingSubscriptionSink,
+};
+#[cfg(feature = "ws-server")]
+use prometheus::{opts, register_int_counter, IntCounter};
+#[cfg(feature = "ws-server")]
+use solana_lite_rpc_core::{
+    commitment_utils::Commitment, structures::account_data::AccountNotificationMessage,
+};
+#[cfg(feature = "ws-server")]
+use tokio::sync::broadcast::error::RecvError::{Closed, Lagged};
+
+#[cfg(feature = "ws-server")]
+lazy_static::lazy_static! {
+    static ref RPC_ACCOUNT_SUBSCRIBE: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_account_subscribe", "RPC call to subscribe to account")).unwrap();
+    static ref RPC_PROGRAM_ACCOUNT_SUBSCRIBE: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_program_account_subscribe", "RPC call to subscribe to program account")).unwrap();
+}
+
+// offsets of the authorities in a bincode serialized StakeStateV2: enum tag (4 bytes),
+// rent_exempt_reserve (8 bytes), staker, withdrawer
+const STAKER_OFFSET: usize = 12;
+const WITHDRAWER_OFFSET: usize = 44;
+
+/// methods and subscriptions of the accounts streamed from the grpc sources
+#[derive(Clone)]
+pub struct LiteAccountsBridge {
+    accounts_service: AccountService,
+}
+
+impl LiteAccountsBridge {
+    pub fn new(accounts_service: AccountService) -> Self {
+        Self { accounts_service }
+    }
+}
+
+#[jsonrpsee::core::async_trait]
+impl LiteAccountsRpcServer for LiteAccountsBridge {
+    async fn get_account_info(
+        &self,
+        pubkey_str: String,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> RpcResult<RpcResponse<Option<UiAccount>>> {
+        let Ok(pubkey) = Pubkey::from_str(&pubkey_str) else {
+            // pubkey is invalid
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        };
+        match self.accounts_service.get_account(pubkey, config).await {
+            Ok((slot, ui_account)) => Ok(RpcResponse {
+                context: RpcResponseContext {
+                    slot,
+                    api_version: None,
+                },
+                value: ui_account,
+            }),
+            Err(_) => {
+                // account not found
+                Err(jsonrpsee::types::error::ErrorCode::ServerError(
+                    RpcErrors::AccountNotFound as i32,
+                )
+                .into())
+            }
+        }
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkey_strs: Vec<String>,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> RpcResult<RpcResponse<Vec<Option<UiAccount>>>> {
+        let pubkeys = pubkey_strs
+            .iter()
+            .map(|key| Pubkey::from_str(key))
+            .collect_vec();
+        if pubkeys.iter().any(|res| res.is_err()) {
+            return Err(jsonrpsee::types::error::ErrorCode::InternalError.into());
+        };
+
+        let mut ui_accounts = vec![];
+        let mut max_slot = 0;
+        for pubkey in pubkeys {
+            match self
+                .accounts_service
+                .get_account(pubkey.unwrap(), config.clone())
+                .await
+            {
+                Ok((slot, ui_account)) => {
+                    if slot > max_slot {
+                        max_slot = slot;
+                    }
+                    ui_accounts.push(ui_account);
+                }
+                Err(_) => {
+                    // internal error while fetching multiple accounts
+                    return Err(jsonrpsee::types::error::ErrorCode::ServerError(
+                        RpcErrors::AccountNotFound as i32,
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(RpcResponse {
+            context: RpcResponseContext {
+                slot: max_slot,
+                api_version: None,
+            },
+            value: ui_accounts,
+        })
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id_str: String,
+        config: Option<RpcProgramAccountsConfig>,
+    ) -> RpcResult<OptionalContext<Vec<RpcKeyedAccount>>> {
+        let Ok(program_id) = Pubkey::from_str(&program_id_str) else {
+            return Err(jsonrpsee::types::error::ErrorCode::InternalError.into());
+        };
+
+        match self
+            .accounts_service
+            .get_program_accounts(program_id, config)
+            .await
+        {
+            Ok((slot, ui_account)) => Ok(OptionalContext::Context(RpcResponse {
+                context: RpcResponseContext {
+                    slot,
+                    api_version: None,
+                },
+                value: ui_account,
+            })),
+            Err(_) => Err(jsonrpsee::types::error::ErrorCode::ServerError(
+                RpcErrors::AccountNotFound as i32,
+            )
+            .into()),
+        }
+    }
+
+    async fn get_stake_accounts_by_authority(
+        &self,
+        authority_str: String,
+        config: Option<StakeAccountsConfig>,
+    ) -> RpcResult<RpcResponse<Vec<RpcKeyedAccount>>> {
+        let Ok(authority) = Pubkey::from_str(&authority_str) else {
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        };
+        let config = config.unwrap_or_default();
+        let offsets: &[usize] = match config.authority {
+            Some(StakeAuthority::Staker) => &[STAKER_OFFSET],
+            Some(StakeAuthority::Withdrawer) => &[WITHDRAWER_OFFSET],
+            None => &[STAKER_OFFSET, WITHDRAWER_OFFSET],
+        };
+
+        let mut max_slot = 0;
+        let mut stake_accounts: Vec<RpcKeyedAccount> = vec![];
+        let mut listed = HashSet::new();
+        for offset in offsets {
+            let program_config = RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new(
+                    *offset,
+                    MemcmpEncodedBytes::Bytes(authority.to_bytes().to_vec()),
+                ))]),
+                account_config: config.account_config.clone(),
+                with_context: Some(true),
+            };
+            match self
+                .accounts_service
+                .get_program_accounts(solana_sdk::stake::program::id(), Some(program_config))
+                .await
+            {
+                Ok((slot, accounts)) => {
+                    max_slot = max_slot.max(slot);
+                    // an account with the address as staker and withdrawer is listed once
+                    for account in accounts {
+                        if listed.insert(account.pubkey.clone()) {
+                            stake_accounts.push(account);
+                        }
+                    }
+                }
+                Err(err) => {
+                    return Err(jsonrpsee::types::ErrorObject::owned(
+                        RpcErrors::AccountNotFound as i32,
+                        err.to_string(),
+                        None::<()>,
+                    ))
+                }
+            }
+        }
+
+        Ok(RpcResponse {
+            context: RpcResponseContext {
+                slot: max_slot,
+                api_version: None,
+            },
+            value: stake_accounts,
+        })
+    }
+}
+
+#[cfg(feature = "ws-server")]
+#[jsonrpsee::core::async_trait]
+impl LiteAccountsPubSubServer for LiteAccountsBridge {
+    async fn account_subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+        account: String,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> SubscriptionResult {
+        let Ok(account) = Pubkey::from_str(&account) else {
+            return Err(StringError::from("Invalid account".to_string()));
+        };
+
+        let sink = pending.accept().await?;
+        let mut accounts_stream = self
+            .accounts_service
+            .account_notification_sender
+            .subscribe();
+        let accounts_service = self.accounts_service.clone();
+
+        tokio::spawn(async move {
+            let _subscription = ActiveSubscription::new("account");
+            RPC_ACCOUNT_SUBSCRIBE.inc();
+
+            loop {
+                match accounts_stream.recv().await {
+                    Ok(AccountNotificationMessage { data, commitment }) => {
+                        if sink.is_closed() {
+                            // sink is already closed
+                            return;
+                        }
+
+                        if data.pubkey != account {
+                            // notification is different account
+                            continue;
+                        }
+                        let account_config = config.clone().unwrap_or_default();
+                        let config_commitment = account_config.commitment.unwrap_or_default();
+                        let min_context_slot = account_config.min_context_slot.unwrap_or_default();
+                        // check config
+                        // check if commitment match
+                        if Commitment::from(config_commitment) != commitment {
+                            continue;
+                        }
+                        // check for min context slot
+                        if data.updated_slot < min_context_slot {
+                            continue;
+                        }
+
+                        let additional_data = accounts_service
+                            .additional_data(&data, config.as_ref())
+                            .await;
+                        let result_message =
+                            jsonrpsee::SubscriptionMessage::from_json(&RpcResponse {
+                                context: RpcResponseContext {
+                                    slot: data.updated_slot,
+                                    api_version: None,
+                                },
+                                value: AccountService::convert_account_data_to_ui_account(
+                                    &data,
+                                    config.clone(),
+                                    additional_data,
+                                ),
+                            });
+
+                        match sink.send(result_message.unwrap()).await {
+                            Ok(()) => {
+                                // success
+                                continue;
+                            }
+                            Err(DisconnectError(_subscription_message)) => {
+                                log::debug!("Stopping subscription task on disconnect");
+                                return;
+                            }
+                        };
+                    }
+                    Err(Lagged(lagged)) => {
+                        // this usually happens if there is one "slow receiver", see https://docs.rs/tokio/latest/tokio/sync/broadcast/index.html#lagging
+                        log::warn!(
+                            "subscriber laggs some({}) accounts messages - continue",
+                            lagged
+                        );
+                        continue;
+                    }
+                    Err(Closed) => {
+                        log::error!(
+                            "failed to receive account notifications, sender closed - aborting"
+                        );
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn program_subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+        pubkey_str: String,
+        config: Option<RpcProgramAccountsConfig>,
+    ) -> SubscriptionResult {
+        let Ok(program_id) = Pubkey::from_str(&pubkey_str) else {
+            return Err(StringError::from("Invalid account".to_string()));
+        };
+
+        let sink = pending.accept().await?;
+        let mut accounts_stream = self
+            .accounts_service
+            .account_notification_sender
+            .subscribe();
+        let accounts_service = self.accounts_service.clone();
+
+        tokio::spawn(async move {
+            let _subscription = ActiveSubscription::new("program");
+            RPC_ACCOUNT_SUBSCRIBE.inc();
+
+            loop {
+                match accounts_stream.recv().await {
+                    Ok(AccountNotificationMessage { data, commitment }) => {
+                        if sink.is_closed() {
+                            // sink is already closed
+                            return;
+                        }
+                        if data.account.owner != program_id {
+                            // wrong program owner
+                            continue;
+                        }
+
+                        let program_config = config.clone().unwrap_or_default();
+                        let config_commitment =
+                            program_config.account_config.commitment.unwrap_or_default();
+                        let min_context_slot = program_config
+                            .account_config
+                            .min_context_slot
+                            .unwrap_or_default();
+                        // check config
+                        // check if commitment match
+                        if Commitment::from(config_commitment) != commitment {
+                            continue;
+                        }
+                        // check for min context slot
+                        if data.updated_slot < min_context_slot {
+                            continue;
+                        }
+                        // check filters
+                        if let Some(filters) = program_config.filters {
+                            if filters.iter().any(|filter| !data.allows(filter)) {
+                                // filters not stasfied
+                                continue;
+                            }
+                        }
+
+                        let account_config = config.clone().map(|x| x.account_config);
+                        let additional_data = accounts_service
+                            .additional_data(&data, account_config.as_ref())
+                            .await;
+                        let value = RpcKeyedAccount {
+                            pubkey: data.pubkey.to_string(),
+                            account: AccountService::convert_account_data_to_ui_account(
+                                &data,
+                                account_config,
+                                additional_data,
+                            ),
+                        };
+
+                        let result_message =
+                            jsonrpsee::SubscriptionMessage::from_json(&RpcResponse {
+                                context: RpcResponseContext {
+                                    slot: data.updated_slot,
+                                    api_version: None,
+                                },
+                                value,
+                            });
+
+                        match sink.send(result_message.unwrap()).await {
+                            Ok(()) => {
+                                // success
+                                continue;
+                            }
+                            Err(DisconnectError(_subscription_message)) => {
+                                log::debug!("Stopping subscription task on disconnect");
+                                return;
+                            }
+                        };
+                    }
+                    Err(Lagged(lagged)) => {
+                        // this usually happens if there is one "slow receiver", see https://docs.rs/tokio/latest/tokio/sync/broadcast/index.html#lagging
+                        log::warn!(
+                            "subscriber laggs some({}) program accounts messages - continue",
+                            lagged
+                        );
+                        continue;
+                    }
+                    Err(Closed) => {
+                        log::error!(
+                            "failed to receive account notifications, sender closed - aborting"
+                        );
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use solana_lite_rpc_accounts::{
+        account_store_interface::AccountStorageInterface,
+        inmemory_account_store::InmemoryAccountStore,
+    };
+    use solana_lite_rpc_core::structures::account_data::{Account, AccountData};
+    use solana_sdk::stake::stake_flags::StakeFlags;
+    use solana_sdk::stake::state::{Authorized, Delegation, Lockup, Meta, Stake, StakeStateV2};
+
+    use super::*;
+
+    fn meta(staker: Pubkey, withdrawer: Pubkey) -> Meta {
+        Meta {
+            rent_exempt_reserve: 2_282_880,
+            authorized: Authorized { staker, withdrawer },
+            lockup: Lockup::default(),
+        }
+    }
+
+    fn stake_account(state: StakeStateV2) -> AccountData {
+        AccountData {
+            pubkey: Pubkey::new_unique(),
+            account: Account {
+                lamports: 2_282_880,
+                data: bincode::serialize(&state).unwrap().into(),
+                owner: solana_sdk::stake::program::id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+            updated_slot: 10,
+        }
+    }
+
+    async fn accounts_bridge(accounts: &[AccountData]) -> LiteAccountsBridge {
+        let store = Arc::new(InmemoryAccountStore::new());
+        for account in accounts {
+            store.initilize_account(account.clone()).await;
+        }
+        LiteAccountsBridge::new(AccountService::new(store))
+    }
+
+    async fn stake_accounts(
+        bridge: &LiteAccountsBridge,
+        authority: Pubkey,
+        matched: Option<StakeAuthority>,
+    ) -> Vec<String> {
+        let config = StakeAccountsConfig {
+            authority: matched,
+            account_config: RpcAccountInfoConfig::default(),
+        };
+        let response = bridge
+            .get_stake_accounts_by_authority(authority.to_string(), Some(config))
+            .await
+            .unwrap();
+        let mut pubkeys: Vec<String> = response
+            .value
+            .into_iter()
+            .map(|account| account.pubkey)
+            .collect();
+        pubkeys.sort();
+        pubkeys
+    }
+
+    fn pubkeys(accounts: &[&AccountData]) -> Vec<String> {
+        let mut pubkeys: Vec<String> = accounts
+            .iter()
+            .map(|account| account.pubkey.to_string())
+            .collect();
+        pubkeys.sort();
+        pubkeys
+    }
+
+    #[test]
+    fn test_authority_offsets_of_initialized_and_delegated_stake_accounts() {
+        let staker = Pubkey::new_unique();
+        let withdrawer = Pubkey::new_unique();
+        let delegated = StakeStateV2::Stake(
+            meta(staker, withdrawer),
+            Stake {
+                delegation: Delegation::new(&Pubkey::new_unique(), 1_000_000_000, 100),
+                credits_observed: 0,
+            },
+            StakeFlags::empty(),
+        );
+        for state in [
+            StakeStateV2::Initialized(meta(staker, withdrawer)),
+            delegated,
+        ] {
+            let data = bincode::serialize(&state).unwrap();
+            assert_eq!(&data[STAKER_OFFSET..STAKER_OFFSET + 32], staker.as_ref());
+            assert_eq!(
+                &data[WITHDRAWER_OFFSET..WITHDRAWER_OFFSET + 32],
+                withdrawer.as_ref()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stake_accounts_are_matched_by_the_requested_authority() {
+        let authority = Pubkey::new_unique();
+        let staked_by = stake_account(StakeStateV2::Initialized(meta(
+            authority,
+            Pubkey::new_unique(),
+        )));
+        let withdrawn_by = stake_account(StakeStateV2::Initialized(meta(
+            Pubkey::new_unique(),
+            authority,
+        )));
+        let both = stake_account(StakeStateV2::Initialized(meta(authority, authority)));
+        let other = stake_account(StakeStateV2::Initialized(meta(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        )));
+        let bridge =
+            accounts_bridge(&[staked_by.clone(), withdrawn_by.clone(), both.clone(), other]).await;
+
+        assert_eq!(
+            stake_accounts(&bridge, authority, Some(StakeAuthority::Staker)).await,
+            pubkeys(&[&staked_by, &both])
+        );
+        assert_eq!(
+            stake_accounts(&bridge, authority, Some(StakeAuthority::Withdrawer)).await,
+            pubkeys(&[&withdrawn_by, &both])
+        );
+        // the account with the address as staker and withdrawer is listed once
+        assert_eq!(
+            stake_accounts(&bridge, authority, None).await,
+            pubkeys(&[&staked_by, &withdrawn_by, &both])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stake_accounts_errors_carry_the_account_service_error() {
+        // the store holds no account of the stake program
+        let bridge = accounts_bridge(&[]).await;
+
+        let err = bridge
+            .get_stake_accounts_by_authority(Pubkey::new_unique().to_string(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), RpcErrors::AccountNotFound as i32);
+        assert!(err
+            .message()
+            .contains("does not satisfy any configured filters"));
+    }
+}
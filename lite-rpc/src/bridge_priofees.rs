@@ -0,0 +1,548 @@
+use std::str::FromStr;
+
+use itertools::Itertools;
+use jsonrpsee::core::RpcResult;
+use solana_lite_rpc_core::encoding;
+use solana_lite_rpc_prioritization_fees::account_prio_service::AccountPrioService;
+use solana_lite_rpc_prioritization_fees::prioritization_fee_calculation_method::PrioritizationFeeCalculationMethod;
+use solana_lite_rpc_prioritization_fees::program_priofees::ProgramPrioService;
+use solana_lite_rpc_prioritization_fees::rpc_data::{
+    AccountContention, AccountPrioFeesStats, BlockUtilizationStats, MessageFeeRecommendation,
+    PrioFeesPercentiles, PrioFeesStats, ProgramPrioFeesStats,
+};
+use solana_lite_rpc_prioritization_fees::PrioFeesService;
+use solana_rpc_client_api::response::{
+    Response as RpcResponse, RpcPrioritizationFee, RpcResponseContext,
+};
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+
+#[cfg(feature = "ws-server")]
+use crate::rpc_metrics::ActiveSubscription;
+#[cfg(feature = "ws-server")]
+use crate::rpc_pubsub::LitePrioFeesPubSubServer;
+use crate::{
+    configs::PrioFeeForMessageConfig, rpc::LitePrioFeesRpcServer,
+    DEFAULT_BLOCK_UTILIZATION_LOOKBACK_BLOCKS, DEFAULT_HOTTEST_ACCOUNTS_LIMIT,
+    DEFAULT_HOTTEST_ACCOUNTS_LOOKBACK_SLOTS, DEFAULT_PRIOFEES_PERCENTILES_LOOKBACK_BLOCKS,
+    DEFAULT_PRIOFEE_RECOMMENDATION_LOOKBACK_SLOTS, DEFAULT_PRIOFEE_RECOMMENDATION_PERCENTILE,
+    MAX_HOTTEST_ACCOUNTS_LIMIT,
+};
+#[cfg(feature = "ws-server")]
+use jsonrpsee::{
+    core::{StringError, SubscriptionResult},
+    DisconnectError, PendingSubscriptionSink,
+};
+#[cfg(feature = "ws-server")]
+use prometheus::{opts, register_int_counter, IntCounter};
+#[cfg(feature = "ws-server")]
+use solana_lite_rpc_prioritization_fees::rpc_data::{
+    AccountPrioFeesUpdateMessage, PrioFeesUpdateMessage,
+};
+#[cfg(feature = "ws-server")]
+use tokio::sync::broadcast::error::RecvError::{Closed, Lagged};
+
+#[cfg(feature = "ws-server")]
+lazy_static::lazy_static! {
+    static ref RPC_BLOCK_PRIOFEES_SUBSCRIBE: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_block_priofees_subscribe", "RPC call to subscribe to block prio fees")).unwrap();
+    static ref RPC_BLOCK_PRIOFEES_SUMMARY_SUBSCRIBE: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_block_priofees_summary_subscribe", "RPC call to subscribe to block prio fees summaries")).unwrap();
+    static ref RPC_ACCOUNT_PRIOFEES_SUBSCRIBE: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_account_priofees_subscribe", "RPC call to subscribe to account prio fees")).unwrap();
+}
+
+/// methods and subscriptions of the block, account and program prioritization fee statistics
+#[derive(Clone)]
+pub struct LitePrioFeesBridge {
+    prio_fees_service: PrioFeesService,
+    account_priofees_service: AccountPrioService,
+    program_priofees_service: ProgramPrioService,
+}
+
+impl LitePrioFeesBridge {
+    pub fn new(
+        prio_fees_service: PrioFeesService,
+        account_priofees_service: AccountPrioService,
+        program_priofees_service: ProgramPrioService,
+    ) -> Self {
+        Self {
+            prio_fees_service,
+            account_priofees_service,
+            program_priofees_service,
+        }
+    }
+}
+
+#[jsonrpsee::core::async_trait]
+impl LitePrioFeesRpcServer for LitePrioFeesBridge {
+    async fn get_recent_prioritization_fees(
+        &self,
+        pubkey_strs: Vec<String>,
+    ) -> RpcResult<Vec<RpcPrioritizationFee>> {
+        // This method will get the latest global and account prioritization fee stats and then send the maximum p75
+        const PERCENTILE: f32 = 0.75;
+        let accounts = pubkey_strs
+            .iter()
+            .filter_map(|pubkey| Pubkey::from_str(pubkey).ok())
+            .collect_vec();
+        if accounts.len() != pubkey_strs.len() {
+            // if lengths do not match it means some of the accounts are invalid
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        }
+
+        let global_prio_fees = self.prio_fees_service.get_latest_priofees().await;
+        let max_p75 = global_prio_fees
+            .map(|(_, fees)| {
+                let fees = fees.get_percentile(PERCENTILE).unwrap_or_default();
+                std::cmp::max(fees.0, fees.1)
+            })
+            .unwrap_or_default();
+
+        let ret: Vec<RpcPrioritizationFee> = accounts
+            .iter()
+            .map(|account| {
+                let (slot, stats) = self.account_priofees_service.get_latest_stats(account);
+                let stat = stats
+                    .all_stats
+                    .get_percentile(PERCENTILE)
+                    .unwrap_or_default();
+                RpcPrioritizationFee {
+                    slot,
+                    prioritization_fee: std::cmp::max(max_p75, std::cmp::max(stat.0, stat.1)),
+                }
+            })
+            .collect_vec();
+
+        Ok(ret)
+    }
+
+    async fn get_latest_block_priofees(
+        &self,
+        method: Option<PrioritizationFeeCalculationMethod>,
+    ) -> RpcResult<RpcResponse<PrioFeesStats>> {
+        let method = method.unwrap_or_default();
+        let res = match method {
+            PrioritizationFeeCalculationMethod::Latest => {
+                self.prio_fees_service.get_latest_priofees().await
+            }
+            PrioritizationFeeCalculationMethod::LastNBlocks(nb) => {
+                self.prio_fees_service
+                    .get_last_n_priofees_aggregate(nb)
+                    .await
+            }
+            PrioritizationFeeCalculationMethod::LastNSlots(nb_slots) => {
+                self.prio_fees_service
+                    .get_last_n_slots_priofees_aggregate(nb_slots)
+                    .await
+            }
+            _ => {
+                // method is invalid
+                return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+            }
+        };
+
+        match res {
+            Some((confirmation_slot, priofees)) => Ok(RpcResponse {
+                context: RpcResponseContext {
+                    slot: confirmation_slot,
+                    api_version: None,
+                },
+                value: priofees,
+            }),
+            None => Err(jsonrpsee::types::error::ErrorCode::InternalError.into()),
+        }
+    }
+
+    async fn get_latest_account_priofees(
+        &self,
+        account: String,
+        method: Option<PrioritizationFeeCalculationMethod>,
+    ) -> RpcResult<RpcResponse<AccountPrioFeesStats>> {
+        if let Ok(account) = Pubkey::from_str(&account) {
+            let method = method.unwrap_or_default();
+            let (slot, value) = match method {
+                PrioritizationFeeCalculationMethod::Latest => {
+                    self.account_priofees_service.get_latest_stats(&account)
+                }
+                PrioritizationFeeCalculationMethod::LastNBlocks(nb) => {
+                    self.account_priofees_service.get_n_last_stats(&account, nb)
+                }
+                PrioritizationFeeCalculationMethod::LastNSlots(nb_slots) => self
+                    .account_priofees_service
+                    .get_n_last_slots_stats(&account, nb_slots),
+                _ => return Err(jsonrpsee::types::error::ErrorCode::InternalError.into()),
+            };
+            Ok(RpcResponse {
+                context: RpcResponseContext {
+                    slot,
+                    api_version: None,
+                },
+                value,
+            })
+        } else {
+            // Account key is invalid
+            Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into())
+        }
+    }
+
+    async fn get_program_priofees(
+        &self,
+        program_id: String,
+        method: Option<PrioritizationFeeCalculationMethod>,
+    ) -> RpcResult<RpcResponse<ProgramPrioFeesStats>> {
+        let Ok(program_id) = Pubkey::from_str(&program_id) else {
+            // program id is invalid
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        };
+        let method = method.unwrap_or_default();
+        let (slot, program_stats) = match method {
+            PrioritizationFeeCalculationMethod::Latest => {
+                self.program_priofees_service.get_latest_stats(&program_id)
+            }
+            PrioritizationFeeCalculationMethod::LastNBlocks(nb) => self
+                .program_priofees_service
+                .get_n_last_stats(&program_id, nb),
+            PrioritizationFeeCalculationMethod::LastNSlots(nb_slots) => self
+                .program_priofees_service
+                .get_n_last_slots_stats(&program_id, nb_slots),
+            _ => return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into()),
+        };
+        let market_stats = self.get_latest_block_priofees(Some(method)).await?.value;
+        Ok(RpcResponse {
+            context: RpcResponseContext {
+                slot,
+                api_version: None,
+            },
+            value: ProgramPrioFeesStats {
+                program_stats,
+                market_stats,
+            },
+        })
+    }
+
+    async fn get_priofees_percentiles(
+        &self,
+        lookback_blocks: Option<usize>,
+    ) -> RpcResult<RpcResponse<PrioFeesPercentiles>> {
+        let lookback_blocks =
+            lookback_blocks.unwrap_or(DEFAULT_PRIOFEES_PERCENTILES_LOOKBACK_BLOCKS);
+        if lookback_blocks == 0 {
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        }
+
+        match self
+            .prio_fees_service
+            .get_last_n_priofees_percentiles(lookback_blocks)
+            .await
+        {
+            Some((slot, percentiles)) => Ok(RpcResponse {
+                context: RpcResponseContext {
+                    slot,
+                    api_version: None,
+                },
+                value: percentiles,
+            }),
+            None => Err(jsonrpsee::types::error::ErrorCode::InternalError.into()),
+        }
+    }
+
+    async fn get_write_lock_priofees(
+        &self,
+        account: String,
+        lookback_blocks: Option<usize>,
+    ) -> RpcResult<RpcResponse<PrioFeesPercentiles>> {
+        let Ok(account) = Pubkey::from_str(&account) else {
+            // Account key is invalid
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        };
+        let lookback_blocks =
+            lookback_blocks.unwrap_or(DEFAULT_PRIOFEES_PERCENTILES_LOOKBACK_BLOCKS);
+        if lookback_blocks == 0 {
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        }
+
+        let (slot, value) = self
+            .account_priofees_service
+            .get_write_lock_percentiles(&account, lookback_blocks);
+        Ok(RpcResponse {
+            context: RpcResponseContext {
+                slot,
+                api_version: None,
+            },
+            value,
+        })
+    }
+
+    async fn get_block_utilization(
+        &self,
+        lookback_blocks: Option<usize>,
+    ) -> RpcResult<RpcResponse<BlockUtilizationStats>> {
+        let lookback_blocks = lookback_blocks.unwrap_or(DEFAULT_BLOCK_UTILIZATION_LOOKBACK_BLOCKS);
+        if lookback_blocks == 0 {
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        }
+
+        match self
+            .prio_fees_service
+            .get_last_n_block_utilization(lookback_blocks)
+            .await
+        {
+            Some((slot, utilization)) => Ok(RpcResponse {
+                context: RpcResponseContext {
+                    slot,
+                    api_version: None,
+                },
+                value: utilization,
+            }),
+            None => Err(jsonrpsee::types::error::ErrorCode::InternalError.into()),
+        }
+    }
+
+    async fn get_hottest_accounts(
+        &self,
+        lookback_slots: Option<u64>,
+        limit: Option<usize>,
+    ) -> RpcResult<RpcResponse<Vec<AccountContention>>> {
+        let lookback_slots = lookback_slots.unwrap_or(DEFAULT_HOTTEST_ACCOUNTS_LOOKBACK_SLOTS);
+        let limit = limit.unwrap_or(DEFAULT_HOTTEST_ACCOUNTS_LIMIT);
+        if lookback_slots == 0 || limit == 0 || limit > MAX_HOTTEST_ACCOUNTS_LIMIT {
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        }
+
+        let (slot, value) = self
+            .account_priofees_service
+            .get_hottest_accounts(lookback_slots, limit);
+        Ok(RpcResponse {
+            context: RpcResponseContext {
+                slot,
+                api_version: None,
+            },
+            value,
+        })
+    }
+
+    async fn get_recommended_priofee_for_message(
+        &self,
+        message: String,
+        config: Option<PrioFeeForMessageConfig>,
+    ) -> RpcResult<RpcResponse<MessageFeeRecommendation>> {
+        let PrioFeeForMessageConfig {
+            percentile,
+            lookback_slots,
+        } = config.unwrap_or_default();
+        let percentile = percentile.unwrap_or(DEFAULT_PRIOFEE_RECOMMENDATION_PERCENTILE);
+        let lookback_slots =
+            lookback_slots.unwrap_or(DEFAULT_PRIOFEE_RECOMMENDATION_LOOKBACK_SLOTS);
+        if percentile > 100 || percentile % 5 != 0 || lookback_slots == 0 {
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        }
+
+        let Ok(raw_message) = encoding::BinaryEncoding::Base64.decode(message) else {
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        };
+        let Ok(message) = bincode::deserialize::<VersionedMessage>(&raw_message) else {
+            return Err(jsonrpsee::types::error::ErrorCode::InvalidParams.into());
+        };
+
+        let (slot, mut value) = self
+            .account_priofees_service
+            .recommend_fee_for_message(&message, lookback_slots, percentile)
+            .await;
+        if value.contended_accounts.is_empty() {
+            // no competition for the accounts of the message, the block wide fee is enough to get included
+            if let Some((_, stats)) = self
+                .prio_fees_service
+                .get_last_n_slots_priofees_aggregate(lookback_slots)
+                .await
+            {
+                value.recommended_cu_price = stats
+                    .get_percentile(percentile as f32 / 100.0)
+                    .map(|(by_tx, _by_cu)| by_tx)
+                    .unwrap_or_default();
+            }
+        }
+
+        Ok(RpcResponse {
+            context: RpcResponseContext {
+                slot,
+                api_version: None,
+            },
+            value,
+        })
+    }
+}
+
+#[cfg(feature = "ws-server")]
+#[jsonrpsee::core::async_trait]
+impl LitePrioFeesPubSubServer for LitePrioFeesBridge {
+    // use websocket-tungstenite-retry->examples/consume_literpc_priofees.rs to test
+    async fn latest_block_priofees_subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+    ) -> SubscriptionResult {
+        let sink = pending.accept().await?;
+
+        let mut block_fees_stream = self.prio_fees_service.block_fees_stream.subscribe();
+        tokio::spawn(async move {
+            let _subscription = ActiveSubscription::new("block_priofees");
+            RPC_BLOCK_PRIOFEES_SUBSCRIBE.inc();
+
+            'recv_loop: loop {
+                match block_fees_stream.recv().await {
+                    Ok(PrioFeesUpdateMessage {
+                        slot: confirmation_slot,
+                        priofees_stats,
+                    }) => {
+                        let result_message =
+                            jsonrpsee::SubscriptionMessage::from_json(&RpcResponse {
+                                context: RpcResponseContext {
+                                    slot: confirmation_slot,
+                                    api_version: None,
+                                },
+                                value: priofees_stats,
+                            });
+
+                        match sink.send(result_message.unwrap()).await {
+                            Ok(()) => {
+                                // success
+                                continue 'recv_loop;
+                            }
+                            Err(DisconnectError(_subscription_message)) => {
+                                log::debug!("Stopping subscription task on disconnect");
+                                return;
+                            }
+                        };
+                    }
+                    Err(Lagged(lagged)) => {
+                        // this usually happens if there is one "slow receiver", see https://docs.rs/tokio/latest/tokio/sync/broadcast/index.html#lagging
+                        log::warn!(
+                            "subscriber laggs some({}) priofees update messages - continue",
+                            lagged
+                        );
+                        continue 'recv_loop;
+                    }
+                    Err(Closed) => {
+                        log::error!("failed to receive block, sender closed - aborting");
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn block_priofees_summary_subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+    ) -> SubscriptionResult {
+        let sink = pending.accept().await?;
+
+        let mut block_fees_stream = self.prio_fees_service.block_fees_stream.subscribe();
+        tokio::spawn(async move {
+            let _subscription = ActiveSubscription::new("block_priofees_summary");
+            RPC_BLOCK_PRIOFEES_SUMMARY_SUBSCRIBE.inc();
+
+            'recv_loop: loop {
+                match block_fees_stream.recv().await {
+                    Ok(PrioFeesUpdateMessage {
+                        slot,
+                        priofees_stats,
+                    }) => {
+                        let result_message = jsonrpsee::SubscriptionMessage::from_json(
+                            &priofees_stats.summary(slot),
+                        );
+
+                        match sink.send(result_message.unwrap()).await {
+                            Ok(()) => {
+                                continue 'recv_loop;
+                            }
+                            Err(DisconnectError(_subscription_message)) => {
+                                log::debug!("Stopping subscription task on disconnect");
+                                return;
+                            }
+                        };
+                    }
+                    Err(Lagged(lagged)) => {
+                        log::warn!(
+                            "subscriber laggs some({}) priofees summary messages - continue",
+                            lagged
+                        );
+                        continue 'recv_loop;
+                    }
+                    Err(Closed) => {
+                        log::error!("failed to receive block, sender closed - aborting");
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn latest_account_priofees_subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+        account: String,
+    ) -> SubscriptionResult {
+        let Ok(account) = Pubkey::from_str(&account) else {
+            return Err(StringError::from("Invalid account".to_string()));
+        };
+        let sink = pending.accept().await?;
+        let mut account_fees_stream = self
+            .account_priofees_service
+            .priofees_update_sender
+            .subscribe();
+        tokio::spawn(async move {
+            let _subscription = ActiveSubscription::new("account_priofees");
+            RPC_BLOCK_PRIOFEES_SUBSCRIBE.inc();
+
+            'recv_loop: loop {
+                match account_fees_stream.recv().await {
+                    Ok(AccountPrioFeesUpdateMessage {
+                        slot,
+                        accounts_data,
+                    }) => {
+                        if let Some(account_data) = accounts_data.get(&account) {
+                            let result_message =
+                                jsonrpsee::SubscriptionMessage::from_json(&RpcResponse {
+                                    context: RpcResponseContext {
+                                        slot,
+                                        api_version: None,
+                                    },
+                                    value: account_data,
+                                });
+
+                            match sink.send(result_message.unwrap()).await {
+                                Ok(()) => {
+                                    // success
+                                    continue 'recv_loop;
+                                }
+                                Err(DisconnectError(_subscription_message)) => {
+                                    log::debug!("Stopping subscription task on disconnect");
+                                    return;
+                                }
+                            };
+                        }
+                    }
+                    Err(Lagged(lagged)) => {
+                        // this usually happens if there is one "slow receiver", see https://docs.rs/tokio/latest/tokio/sync/broadcast/index.html#lagging
+                        log::warn!(
+                            "subscriber laggs some({}) priofees update messages - continue",
+                            lagged
+                        );
+                        continue 'recv_loop;
+                    }
+                    Err(Closed) => {
+                        log::error!("failed to receive block, sender closed - aborting");
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
@@ -1,72 +1,285 @@
 use prometheus::{opts, register_int_counter, IntCounter};
-use solana_lite_rpc_accounts::account_service::AccountService;
 use solana_lite_rpc_core::{
-    commitment_utils::Commitment, stores::data_cache::DataCache,
-    structures::account_data::AccountNotificationMessage, types::BlockStream,
+    stores::data_cache::DataCache,
+    structures::{
+        commitment_upgrade::BlockCommitmentUpgrade,
+        produced_block::{ProducedBlock, TransactionInfo},
+    },
+    types::{BlockStream, CommitmentUpgradeStream},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{Arc, Mutex, Once},
+};
+use tokio::sync::{
+    broadcast::error::RecvError::{Closed, Lagged},
+    mpsc,
 };
-use std::{str::FromStr, sync::Arc};
-use tokio::sync::broadcast::error::RecvError::{Closed, Lagged};
 
 use crate::{
+    configs::{TransactionSubscribeConfig, TransactionSubscribeFilter},
     jsonrpsee_subscrption_handler_sink::JsonRpseeSubscriptionHandlerSink,
+    rpc_metrics::ActiveSubscription,
     rpc_pubsub::LiteRpcPubSubServer,
+    sink_records::{proto, transaction_record},
 };
 use jsonrpsee::{
     core::{StringError, SubscriptionResult},
     DisconnectError, PendingSubscriptionSink,
 };
-use solana_lite_rpc_prioritization_fees::{
-    account_prio_service::AccountPrioService,
-    rpc_data::{AccountPrioFeesUpdateMessage, PrioFeesUpdateMessage},
-    PrioFeesService,
-};
 use solana_rpc_client_api::{
     config::{
-        RpcAccountInfoConfig, RpcBlockSubscribeConfig, RpcBlockSubscribeFilter,
-        RpcProgramAccountsConfig, RpcSignatureSubscribeConfig, RpcTransactionLogsConfig,
-        RpcTransactionLogsFilter,
+        RpcBlockSubscribeConfig, RpcBlockSubscribeFilter, RpcSignatureSubscribeConfig,
+        RpcTransactionLogsConfig, RpcTransactionLogsFilter,
     },
-    response::{Response as RpcResponse, RpcKeyedAccount, RpcResponseContext, SlotInfo},
+    response::SlotInfo,
 };
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{commitment_config::CommitmentLevel, pubkey::Pubkey, signature::Signature};
 
 lazy_static::lazy_static! {
     static ref RPC_SIGNATURE_SUBSCRIBE: IntCounter =
     register_int_counter!(opts!("literpc_rpc_signature_subscribe", "RPC call to subscribe to signature")).unwrap();
-    static ref RPC_BLOCK_PRIOFEES_SUBSCRIBE: IntCounter =
-    register_int_counter!(opts!("literpc_rpc_block_priofees_subscribe", "RPC call to subscribe to block prio fees")).unwrap();
-    static ref RPC_ACCOUNT_PRIOFEES_SUBSCRIBE: IntCounter =
-    register_int_counter!(opts!("literpc_rpc_account_priofees_subscribe", "RPC call to subscribe to account prio fees")).unwrap();
-    static ref RPC_ACCOUNT_SUBSCRIBE: IntCounter =
-    register_int_counter!(opts!("literpc_rpc_account_subscribe", "RPC call to subscribe to account")).unwrap();
-    static ref RPC_PROGRAM_ACCOUNT_SUBSCRIBE: IntCounter =
-    register_int_counter!(opts!("literpc_rpc_program_account_subscribe", "RPC call to subscribe to program account")).unwrap();
+    static ref RPC_ROOT_SUBSCRIBE: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_root_subscribe", "RPC call to subscribe to roots")).unwrap();
+    static ref RPC_BLOCK_COMMITMENT_SUBSCRIBE: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_block_commitment_subscribe", "RPC call to subscribe to the commitment upgrades of blocks")).unwrap();
+    static ref RPC_TRANSACTION_SUBSCRIBE: IntCounter =
+    register_int_counter!(opts!("literpc_rpc_transaction_subscribe", "RPC call to subscribe to transactions by account")).unwrap();
+    static ref TRANSACTION_SUBSCRIPTIONS_DROPPED: IntCounter =
+    register_int_counter!(opts!("literpc_transaction_subscriptions_dropped", "Transaction subscriptions closed for falling behind the notifications")).unwrap();
+}
+
+// every transaction subscription is matched against every transaction of the blocks by its accounts
+const MAX_TRANSACTION_SUBSCRIPTIONS: usize = 1000;
+const MAX_FILTER_ACCOUNTS: usize = 256;
+// notifications queued per transaction subscription, a subscriber falling further behind is dropped
+const TRANSACTION_NOTIFICATION_BUFFER: usize = 1024;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockCommitmentNotification {
+    slot: u64,
+    blockhash: String,
+    commitment: CommitmentLevel,
+    previous_commitment: Option<CommitmentLevel>,
+    previous_blockhash: Option<String>,
+    conflict: bool,
+}
+
+impl From<BlockCommitmentUpgrade> for BlockCommitmentNotification {
+    fn from(upgrade: BlockCommitmentUpgrade) -> Self {
+        Self {
+            slot: upgrade.slot,
+            blockhash: upgrade.blockhash.to_string(),
+            commitment: upgrade.commitment_config.commitment,
+            previous_commitment: upgrade.previous_commitment,
+            previous_blockhash: upgrade
+                .previous_blockhash
+                .map(|blockhash| blockhash.to_string()),
+            conflict: upgrade.conflict,
+        }
+    }
+}
+
+// the parsed filter of transactionSubscribe
+struct AccountsFilter {
+    include: HashSet<Pubkey>,
+    exclude: HashSet<Pubkey>,
+    vote: bool,
+}
+
+impl AccountsFilter {
+    fn parse(filter: TransactionSubscribeFilter) -> Result<Self, StringError> {
+        // an empty accountInclude would be a firehose of all transactions
+        if filter.account_include.is_empty()
+            || filter.account_include.len() + filter.account_exclude.len() > MAX_FILTER_ACCOUNTS
+        {
+            return Err(format!(
+                "accountInclude must not be empty and the filter must contain at most {MAX_FILTER_ACCOUNTS} accounts"
+            )
+            .into());
+        }
+        let parse = |accounts: Vec<String>| {
+            accounts
+                .iter()
+                .map(|account| {
+                    Pubkey::from_str(account)
+                        .map_err(|_| StringError::from(format!("Invalid account {account}")))
+                })
+                .collect::<Result<HashSet<_>, _>>()
+        };
+        Ok(Self {
+            include: parse(filter.account_include)?,
+            exclude: parse(filter.account_exclude)?,
+            vote: filter.vote,
+        })
+    }
+
+    fn matches(&self, tx: &TransactionInfo) -> bool {
+        if tx.is_vote && !self.vote {
+            return false;
+        }
+        let mut accounts = tx.writable_accounts.iter().chain(&tx.readable_accounts);
+        if accounts
+            .clone()
+            .any(|account| self.exclude.contains(account))
+        {
+            return false;
+        }
+        accounts.any(|account| self.include.contains(account))
+    }
+}
+
+struct TransactionSubscription {
+    filter: AccountsFilter,
+    commitment: CommitmentLevel,
+    sender: mpsc::Sender<proto::Transaction>,
+}
+
+/// the transaction subscriptions, served from a single stream of the blocks
+#[derive(Default)]
+struct TransactionSubscriptions {
+    next_id: u64,
+    subscriptions: HashMap<u64, TransactionSubscription>,
+    // accountInclude to the subscriptions, only their transactions are matched
+    by_account: HashMap<Pubkey, HashSet<u64>>,
+}
+
+impl TransactionSubscriptions {
+    fn insert(
+        &mut self,
+        filter: AccountsFilter,
+        commitment: CommitmentLevel,
+    ) -> Result<(u64, mpsc::Receiver<proto::Transaction>), StringError> {
+        if self.subscriptions.len() >= MAX_TRANSACTION_SUBSCRIPTIONS {
+            return Err(format!(
+                "at most {MAX_TRANSACTION_SUBSCRIPTIONS} transaction subscriptions are served"
+            )
+            .into());
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        for account in &filter.include {
+            self.by_account.entry(*account).or_default().insert(id);
+        }
+        let (sender, receiver) = mpsc::channel(TRANSACTION_NOTIFICATION_BUFFER);
+        self.subscriptions.insert(
+            id,
+            TransactionSubscription {
+                filter,
+                commitment,
+                sender,
+            },
+        );
+        Ok((id, receiver))
+    }
+
+    fn remove(&mut self, id: u64) {
+        let Some(subscription) = self.subscriptions.remove(&id) else {
+            return;
+        };
+        for account in &subscription.filter.include {
+            if let Some(ids) = self.by_account.get_mut(account) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.by_account.remove(account);
+                }
+            }
+        }
+    }
+
+    /// queues the matching transactions of the block, subscriptions not keeping up are dropped
+    fn notify(&mut self, block: &ProducedBlock) {
+        let mut dropped = HashSet::new();
+        for tx in &block.transactions {
+            let mut notified = HashSet::new();
+            for account in tx.writable_accounts.iter().chain(&tx.readable_accounts) {
+                let Some(ids) = self.by_account.get(account) else {
+                    continue;
+                };
+                for id in ids {
+                    if dropped.contains(id) || !notified.insert(*id) {
+                        continue;
+                    }
+                    let subscription = &self.subscriptions[id];
+                    if subscription.commitment != block.commitment_config.commitment
+                        || !subscription.filter.matches(tx)
+                    {
+                        continue;
+                    }
+                    if let Err(mpsc::error::TrySendError::Full(_)) =
+                        subscription.sender.try_send(transaction_record(block, tx))
+                    {
+                        log::debug!("Dropping transaction subscription {id} falling behind");
+                        TRANSACTION_SUBSCRIPTIONS_DROPPED.inc();
+                        dropped.insert(*id);
+                    }
+                }
+            }
+        }
+        for id in dropped {
+            self.remove(id);
+        }
+    }
+}
+
+// removes the subscription once its task ends
+struct TransactionSubscriptionGuard {
+    subscriptions: Arc<Mutex<TransactionSubscriptions>>,
+    id: u64,
+}
+
+impl Drop for TransactionSubscriptionGuard {
+    fn drop(&mut self) {
+        self.subscriptions.lock().unwrap().remove(self.id);
+    }
 }
 
 pub struct LitePubSubBridge {
     data_cache: DataCache,
-    prio_fees_service: PrioFeesService,
-    account_priofees_service: AccountPrioService,
     block_stream: BlockStream,
-    accounts_service: Option<AccountService>,
+    commitment_upgrade_stream: CommitmentUpgradeStream,
+    transaction_subscriptions: Arc<Mutex<TransactionSubscriptions>>,
+    // the blocks are only dispatched to the transaction subscriptions after the first one
+    transaction_dispatch: Once,
 }
 
 impl LitePubSubBridge {
     pub fn new(
         data_cache: DataCache,
-        prio_fees_service: PrioFeesService,
-        account_priofees_service: AccountPrioService,
         block_stream: BlockStream,
-        accounts_service: Option<AccountService>,
+        commitment_upgrade_stream: CommitmentUpgradeStream,
     ) -> Self {
         Self {
             data_cache,
-            prio_fees_service,
-            account_priofees_service,
             block_stream,
-            accounts_service,
+            commitment_upgrade_stream,
+            transaction_subscriptions: Arc::default(),
+            transaction_dispatch: Once::new(),
         }
     }
+
+    fn start_transaction_dispatch(&self) {
+        self.transaction_dispatch.call_once(|| {
+            let mut block_stream = self.block_stream.resubscribe();
+            let transaction_subscriptions = self.transaction_subscriptions.clone();
+            tokio::spawn(async move {
+                loop {
+                    match block_stream.recv().await {
+                        Ok(produced_block) => transaction_subscriptions
+                            .lock()
+                            .unwrap()
+                            .notify(&produced_block),
+                        Err(Closed) => break,
+                        Err(Lagged(_)) => {
+                            log::error!("Transaction subscription stream lagged");
+                        }
+                    }
+                }
+            });
+        });
+    }
 }
 
 #[jsonrpsee::core::async_trait]
@@ -75,6 +288,7 @@ impl LiteRpcPubSubServer for LitePubSubBridge {
         let sink = pending.accept().await?;
         let mut block_stream = self.block_stream.resubscribe();
         tokio::spawn(async move {
+            let _subscription = ActiveSubscription::new("slot");
             loop {
                 match block_stream.recv().await {
                     Ok(produced_block) => {
@@ -114,6 +328,130 @@ impl LiteRpcPubSubServer for LitePubSubBridge {
         Ok(())
     }
 
+    async fn root_subscribe(&self, pending: PendingSubscriptionSink) -> SubscriptionResult {
+        RPC_ROOT_SUBSCRIBE.inc();
+        let sink = pending.accept().await?;
+        let mut block_stream = self.block_stream.resubscribe();
+        tokio::spawn(async move {
+            let _subscription = ActiveSubscription::new("root");
+            let mut last_root = 0;
+            loop {
+                match block_stream.recv().await {
+                    Ok(produced_block) => {
+                        // blocks might be finalized out of order after a reconnect of the source
+                        if !produced_block.commitment_config.is_finalized()
+                            || produced_block.slot <= last_root
+                        {
+                            continue;
+                        }
+                        last_root = produced_block.slot;
+                        let result_message =
+                            jsonrpsee::SubscriptionMessage::from_json(&produced_block.slot);
+
+                        match sink.send(result_message.unwrap()).await {
+                            Ok(()) => {
+                                // success
+                                continue;
+                            }
+                            Err(DisconnectError(_subscription_message)) => {
+                                log::debug!("Stopping subscription task on disconnect");
+                                return;
+                            }
+                        };
+                    }
+                    Err(e) => match e {
+                        Closed => {
+                            break;
+                        }
+                        Lagged(_) => {
+                            log::error!("Root subscription stream lagged");
+                            continue;
+                        }
+                    },
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn block_commitment_subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+    ) -> SubscriptionResult {
+        RPC_BLOCK_COMMITMENT_SUBSCRIBE.inc();
+        let sink = pending.accept().await?;
+        // the upgrades are tracked once for all subscribers
+        let mut commitment_upgrade_stream = self.commitment_upgrade_stream.resubscribe();
+        tokio::spawn(async move {
+            let _subscription = ActiveSubscription::new("block_commitment");
+            loop {
+                match commitment_upgrade_stream.recv().await {
+                    Ok(upgrade) => {
+                        let result_message = jsonrpsee::SubscriptionMessage::from_json(
+                            &BlockCommitmentNotification::from(upgrade),
+                        );
+                        if let Err(DisconnectError(_subscription_message)) =
+                            sink.send(result_message.unwrap()).await
+                        {
+                            log::debug!("Stopping subscription task on disconnect");
+                            return;
+                        }
+                    }
+                    Err(e) => match e {
+                        Closed => {
+                            break;
+                        }
+                        Lagged(_) => {
+                            log::error!("Block commitment subscription stream lagged");
+                            continue;
+                        }
+                    },
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn transaction_subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+        filter: TransactionSubscribeFilter,
+        config: Option<TransactionSubscribeConfig>,
+    ) -> SubscriptionResult {
+        RPC_TRANSACTION_SUBSCRIBE.inc();
+        let filter = AccountsFilter::parse(filter)?;
+        let commitment = config
+            .and_then(|config| config.commitment)
+            .unwrap_or_default()
+            .commitment;
+        let (id, mut notifications) = self
+            .transaction_subscriptions
+            .lock()
+            .unwrap()
+            .insert(filter, commitment)?;
+        let guard = TransactionSubscriptionGuard {
+            subscriptions: self.transaction_subscriptions.clone(),
+            id,
+        };
+        self.start_transaction_dispatch();
+        let sink = pending.accept().await?;
+        tokio::spawn(async move {
+            let _guard = guard;
+            let _subscription = ActiveSubscription::new("transaction");
+            // closed when the subscription is dropped for falling behind
+            while let Some(record) = notifications.recv().await {
+                let result_message = jsonrpsee::SubscriptionMessage::from_json(&record);
+                if let Err(DisconnectError(_subscription_message)) =
+                    sink.send(result_message.unwrap()).await
+                {
+                    log::debug!("Stopping subscription task on disconnect");
+                    return;
+                }
+            }
+        });
+        Ok(())
+    }
+
     async fn block_subscribe(
         &self,
         _pending: PendingSubscriptionSink,
@@ -140,6 +478,9 @@ impl LiteRpcPubSubServer for LitePubSubBridge {
         config: RpcSignatureSubscribeConfig,
     ) -> SubscriptionResult {
         RPC_SIGNATURE_SUBSCRIBE.inc();
+        let Ok(signature) = Signature::from_str(&signature) else {
+            return Err(StringError::from("Invalid signature".to_string()));
+        };
         let sink = pending.accept().await?;
 
         let jsonrpsee_sink = JsonRpseeSubscriptionHandlerSink::new(sink);
@@ -162,318 +503,186 @@ impl LiteRpcPubSubServer for LitePubSubBridge {
     async fn vote_subscribe(&self, _pending: PendingSubscriptionSink) -> SubscriptionResult {
         todo!()
     }
+}
 
-    // use websocket-tungstenite-retry->examples/consume_literpc_priofees.rs to test
-    async fn latest_block_priofees_subscribe(
-        &self,
-        pending: PendingSubscriptionSink,
-    ) -> SubscriptionResult {
-        let sink = pending.accept().await?;
-
-        let mut block_fees_stream = self.prio_fees_service.block_fees_stream.subscribe();
-        tokio::spawn(async move {
-            RPC_BLOCK_PRIOFEES_SUBSCRIBE.inc();
-
-            'recv_loop: loop {
-                match block_fees_stream.recv().await {
-                    Ok(PrioFeesUpdateMessage {
-                        slot: confirmation_slot,
-                        priofees_stats,
-                    }) => {
-                        let result_message =
-                            jsonrpsee::SubscriptionMessage::from_json(&RpcResponse {
-                                context: RpcResponseContext {
-                                    slot: confirmation_slot,
-                                    api_version: None,
-                                },
-                                value: priofees_stats,
-                            });
-
-                        match sink.send(result_message.unwrap()).await {
-                            Ok(()) => {
-                                // success
-                                continue 'recv_loop;
-                            }
-                            Err(DisconnectError(_subscription_message)) => {
-                                log::debug!("Stopping subscription task on disconnect");
-                                return;
-                            }
-                        };
-                    }
-                    Err(Lagged(lagged)) => {
-                        // this usually happens if there is one "slow receiver", see https://docs.rs/tokio/latest/tokio/sync/broadcast/index.html#lagging
-                        log::warn!(
-                            "subscriber laggs some({}) priofees update messages - continue",
-                            lagged
-                        );
-                        continue 'recv_loop;
-                    }
-                    Err(Closed) => {
-                        log::error!("failed to receive block, sender closed - aborting");
-                        return;
-                    }
-                }
-            }
-        });
-
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash};
+
+    use super::*;
+
+    fn transaction(
+        writable_accounts: Vec<Pubkey>,
+        readable_accounts: Vec<Pubkey>,
+        is_vote: bool,
+    ) -> TransactionInfo {
+        TransactionInfo {
+            signature: Signature::new_unique(),
+            is_vote,
+            err: None,
+            cu_requested: None,
+            prioritization_fees: None,
+            cu_consumed: None,
+            recent_blockhash: Hash::default(),
+            message: Default::default(),
+            writable_accounts,
+            readable_accounts,
+            address_lookup_tables: vec![],
+            program_ids: vec![],
+        }
     }
 
-    async fn latest_account_priofees_subscribe(
-        &self,
-        pending: PendingSubscriptionSink,
-        account: String,
-    ) -> SubscriptionResult {
-        let Ok(account) = Pubkey::from_str(&account) else {
-            return Err(StringError::from("Invalid account".to_string()));
-        };
-        let sink = pending.accept().await?;
-        let mut account_fees_stream = self
-            .account_priofees_service
-            .priofees_update_sender
-            .subscribe();
-        tokio::spawn(async move {
-            RPC_BLOCK_PRIOFEES_SUBSCRIBE.inc();
-
-            'recv_loop: loop {
-                match account_fees_stream.recv().await {
-                    Ok(AccountPrioFeesUpdateMessage {
-                        slot,
-                        accounts_data,
-                    }) => {
-                        if let Some(account_data) = accounts_data.get(&account) {
-                            let result_message =
-                                jsonrpsee::SubscriptionMessage::from_json(&RpcResponse {
-                                    context: RpcResponseContext {
-                                        slot,
-                                        api_version: None,
-                                    },
-                                    value: account_data,
-                                });
-
-                            match sink.send(result_message.unwrap()).await {
-                                Ok(()) => {
-                                    // success
-                                    continue 'recv_loop;
-                                }
-                                Err(DisconnectError(_subscription_message)) => {
-                                    log::debug!("Stopping subscription task on disconnect");
-                                    return;
-                                }
-                            };
-                        }
-                    }
-                    Err(Lagged(lagged)) => {
-                        // this usually happens if there is one "slow receiver", see https://docs.rs/tokio/latest/tokio/sync/broadcast/index.html#lagging
-                        log::warn!(
-                            "subscriber laggs some({}) priofees update messages - continue",
-                            lagged
-                        );
-                        continue 'recv_loop;
-                    }
-                    Err(Closed) => {
-                        log::error!("failed to receive block, sender closed - aborting");
-                        return;
-                    }
-                }
-            }
-        });
-
-        Ok(())
+    fn block(
+        commitment_config: CommitmentConfig,
+        transactions: Vec<TransactionInfo>,
+    ) -> ProducedBlock {
+        ProducedBlock {
+            transactions,
+            ..ProducedBlock::new_for_tests(42, commitment_config)
+        }
     }
 
-    async fn account_subscribe(
-        &self,
-        pending: PendingSubscriptionSink,
-        account: String,
-        config: Option<RpcAccountInfoConfig>,
-    ) -> SubscriptionResult {
-        let Ok(account) = Pubkey::from_str(&account) else {
-            return Err(StringError::from("Invalid account".to_string()));
-        };
-
-        let Some(accounts_service) = &self.accounts_service else {
-            return Err(StringError::from(
-                "Accounts service not configured".to_string(),
-            ));
-        };
-        let sink = pending.accept().await?;
-        let mut accounts_stream = accounts_service.account_notification_sender.subscribe();
-
-        tokio::spawn(async move {
-            RPC_ACCOUNT_SUBSCRIBE.inc();
-
-            loop {
-                match accounts_stream.recv().await {
-                    Ok(AccountNotificationMessage { data, commitment }) => {
-                        if sink.is_closed() {
-                            // sink is already closed
-                            return;
-                        }
-
-                        if data.pubkey != account {
-                            // notification is different account
-                            continue;
-                        }
-                        let account_config = config.clone().unwrap_or_default();
-                        let config_commitment = account_config.commitment.unwrap_or_default();
-                        let min_context_slot = account_config.min_context_slot.unwrap_or_default();
-                        // check config
-                        // check if commitment match
-                        if Commitment::from(config_commitment) != commitment {
-                            continue;
-                        }
-                        // check for min context slot
-                        if data.updated_slot < min_context_slot {
-                            continue;
-                        }
-
-                        let result_message =
-                            jsonrpsee::SubscriptionMessage::from_json(&RpcResponse {
-                                context: RpcResponseContext {
-                                    slot: data.updated_slot,
-                                    api_version: None,
-                                },
-                                value: AccountService::convert_account_data_to_ui_account(
-                                    &data,
-                                    config.clone(),
-                                ),
-                            });
-
-                        match sink.send(result_message.unwrap()).await {
-                            Ok(()) => {
-                                // success
-                                continue;
-                            }
-                            Err(DisconnectError(_subscription_message)) => {
-                                log::debug!("Stopping subscription task on disconnect");
-                                return;
-                            }
-                        };
-                    }
-                    Err(Lagged(lagged)) => {
-                        // this usually happens if there is one "slow receiver", see https://docs.rs/tokio/latest/tokio/sync/broadcast/index.html#lagging
-                        log::warn!(
-                            "subscriber laggs some({}) accounts messages - continue",
-                            lagged
-                        );
-                        continue;
-                    }
-                    Err(Closed) => {
-                        log::error!(
-                            "failed to receive account notifications, sender closed - aborting"
-                        );
-                        return;
-                    }
-                }
-            }
-        });
-
-        Ok(())
+    fn filter(include: &[Pubkey], exclude: &[Pubkey], vote: bool) -> AccountsFilter {
+        AccountsFilter::parse(TransactionSubscribeFilter {
+            account_include: include.iter().map(Pubkey::to_string).collect(),
+            account_exclude: exclude.iter().map(Pubkey::to_string).collect(),
+            vote,
+        })
+        .unwrap()
     }
 
-    async fn program_subscribe(
-        &self,
-        pending: PendingSubscriptionSink,
-        pubkey_str: String,
-        config: Option<RpcProgramAccountsConfig>,
-    ) -> SubscriptionResult {
-        let Ok(program_id) = Pubkey::from_str(&pubkey_str) else {
-            return Err(StringError::from("Invalid account".to_string()));
-        };
+    #[test]
+    fn test_filter_matches_the_accounts() {
+        let (included, excluded, other) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let accounts_filter = filter(&[included], &[excluded], false);
+
+        assert!(accounts_filter.matches(&transaction(vec![included], vec![], false)));
+        assert!(accounts_filter.matches(&transaction(vec![other], vec![included], false)));
+        assert!(!accounts_filter.matches(&transaction(vec![other], vec![], false)));
+        // excluded accounts win, read or written
+        assert!(!accounts_filter.matches(&transaction(vec![included], vec![excluded], false)));
+        assert!(!accounts_filter.matches(&transaction(vec![excluded, included], vec![], false)));
+        // votes only if asked for
+        assert!(!accounts_filter.matches(&transaction(vec![included], vec![], true)));
+        assert!(filter(&[included], &[], true).matches(&transaction(vec![included], vec![], true)));
+    }
 
-        let Some(accounts_service) = &self.accounts_service else {
-            return Err(StringError::from(
-                "Accounts service not configured".to_string(),
-            ));
+    #[test]
+    fn test_filters_are_bounded() {
+        let parse = |include: usize, exclude: usize| {
+            AccountsFilter::parse(TransactionSubscribeFilter {
+                account_include: (0..include)
+                    .map(|_| Pubkey::new_unique().to_string())
+                    .collect(),
+                account_exclude: (0..exclude)
+                    .map(|_| Pubkey::new_unique().to_string())
+                    .collect(),
+                vote: false,
+            })
         };
-        let sink = pending.accept().await?;
-        let mut accounts_stream = accounts_service.account_notification_sender.subscribe();
-
-        tokio::spawn(async move {
-            RPC_ACCOUNT_SUBSCRIBE.inc();
-
-            loop {
-                match accounts_stream.recv().await {
-                    Ok(AccountNotificationMessage { data, commitment }) => {
-                        if sink.is_closed() {
-                            // sink is already closed
-                            return;
-                        }
-                        if data.account.owner != program_id {
-                            // wrong program owner
-                            continue;
-                        }
-
-                        let program_config = config.clone().unwrap_or_default();
-                        let config_commitment =
-                            program_config.account_config.commitment.unwrap_or_default();
-                        let min_context_slot = program_config
-                            .account_config
-                            .min_context_slot
-                            .unwrap_or_default();
-                        // check config
-                        // check if commitment match
-                        if Commitment::from(config_commitment) != commitment {
-                            continue;
-                        }
-                        // check for min context slot
-                        if data.updated_slot < min_context_slot {
-                            continue;
-                        }
-                        // check filters
-                        if let Some(filters) = program_config.filters {
-                            if filters.iter().any(|filter| !data.allows(filter)) {
-                                // filters not stasfied
-                                continue;
-                            }
-                        }
+        assert!(parse(0, 1).is_err());
+        assert!(parse(1, MAX_FILTER_ACCOUNTS - 1).is_ok());
+        assert!(parse(1, MAX_FILTER_ACCOUNTS).is_err());
+        assert!(AccountsFilter::parse(TransactionSubscribeFilter {
+            account_include: vec!["not a pubkey".to_string()],
+            account_exclude: vec![],
+            vote: false,
+        })
+        .is_err());
+    }
 
-                        let value = RpcKeyedAccount {
-                            pubkey: data.pubkey.to_string(),
-                            account: AccountService::convert_account_data_to_ui_account(
-                                &data,
-                                config.clone().map(|x| x.account_config),
-                            ),
-                        };
+    #[test]
+    fn test_blocks_are_dispatched_to_the_matching_subscriptions() {
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut subscriptions = TransactionSubscriptions::default();
+        let (_, mut first_notifications) = subscriptions
+            .insert(filter(&[first], &[], false), CommitmentLevel::Confirmed)
+            .unwrap();
+        let (second_id, mut second_notifications) = subscriptions
+            .insert(
+                filter(&[first, second], &[], false),
+                CommitmentLevel::Confirmed,
+            )
+            .unwrap();
+
+        let both = transaction(vec![first], vec![second], false);
+        let second_only = transaction(vec![second], vec![], false);
+        let signatures = [
+            both.signature.to_string(),
+            second_only.signature.to_string(),
+        ];
+        subscriptions.notify(&block(
+            CommitmentConfig::confirmed(),
+            vec![
+                both,
+                second_only,
+                transaction(vec![Pubkey::new_unique()], vec![], false),
+            ],
+        ));
+        // other commitments are not notified
+        subscriptions.notify(&block(
+            CommitmentConfig::finalized(),
+            vec![transaction(vec![first], vec![], false)],
+        ));
+
+        assert_eq!(
+            first_notifications.try_recv().unwrap().signature,
+            signatures[0]
+        );
+        assert!(first_notifications.try_recv().is_err());
+        // a transaction matching several accounts is notified once
+        assert_eq!(
+            second_notifications.try_recv().unwrap().signature,
+            signatures[0]
+        );
+        assert_eq!(
+            second_notifications.try_recv().unwrap().signature,
+            signatures[1]
+        );
+        assert!(second_notifications.try_recv().is_err());
 
-                        let result_message =
-                            jsonrpsee::SubscriptionMessage::from_json(&RpcResponse {
-                                context: RpcResponseContext {
-                                    slot: data.updated_slot,
-                                    api_version: None,
-                                },
-                                value,
-                            });
+        subscriptions.remove(second_id);
+        assert_eq!(subscriptions.subscriptions.len(), 1);
+        assert!(!subscriptions.by_account.contains_key(&second));
+    }
 
-                        match sink.send(result_message.unwrap()).await {
-                            Ok(()) => {
-                                // success
-                                continue;
-                            }
-                            Err(DisconnectError(_subscription_message)) => {
-                                log::debug!("Stopping subscription task on disconnect");
-                                return;
-                            }
-                        };
-                    }
-                    Err(Lagged(lagged)) => {
-                        // this usually happens if there is one "slow receiver", see https://docs.rs/tokio/latest/tokio/sync/broadcast/index.html#lagging
-                        log::warn!(
-                            "subscriber laggs some({}) program accounts messages - continue",
-                            lagged
-                        );
-                        continue;
-                    }
-                    Err(Closed) => {
-                        log::error!(
-                            "failed to receive account notifications, sender closed - aborting"
-                        );
-                        return;
-                    }
-                }
-            }
-        });
-        Ok(())
+    #[test]
+    fn test_subscriptions_are_capped_and_dropped_when_behind() {
+        let account = Pubkey::new_unique();
+        let mut subscriptions = TransactionSubscriptions::default();
+        let receivers = (0..MAX_TRANSACTION_SUBSCRIPTIONS)
+            .map(|_| {
+                subscriptions
+                    .insert(filter(&[account], &[], false), CommitmentLevel::Confirmed)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert!(subscriptions
+            .insert(filter(&[account], &[], false), CommitmentLevel::Confirmed)
+            .is_err());
+        drop(receivers);
+
+        let mut subscriptions = TransactionSubscriptions::default();
+        let (_, mut notifications) = subscriptions
+            .insert(filter(&[account], &[], false), CommitmentLevel::Confirmed)
+            .unwrap();
+        let transactions = (0..=TRANSACTION_NOTIFICATION_BUFFER)
+            .map(|_| transaction(vec![account], vec![], false))
+            .collect();
+        subscriptions.notify(&block(CommitmentConfig::confirmed(), transactions));
+        assert!(subscriptions.subscriptions.is_empty());
+        assert!(subscriptions.by_account.is_empty());
+        // the queued notifications are still sent, then the subscription ends
+        for _ in 0..TRANSACTION_NOTIFICATION_BUFFER {
+            assert!(notifications.try_recv().is_ok());
+        }
+        assert_eq!(
+            notifications.try_recv(),
+            Err(mpsc::error::TryRecvError::Disconnected)
+        );
     }
 }
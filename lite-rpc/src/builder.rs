@@ -0,0 +1,871 @@
+use std::{sync::Arc, time::Duration};
+
+use dashmap::DashMap;
+use log::{debug, info, warn};
+#[cfg(feature = "accounts")]
+use solana_lite_rpc_accounts::{
+    account_service::AccountService, account_store_interface::AccountStorageInterface,
+    inmemory_account_store::InmemoryAccountStore,
+};
+use solana_lite_rpc_blockstore::history::History;
+use solana_lite_rpc_cluster_endpoints::{
+    clock_stream::create_latest_clock, commitment_upgrade_stream::create_commitment_upgrade_stream,
+    endpoint_stremers::EndpointStreaming, geyser_grpc_connector::GrpcSourceConfig,
+    grpc_subscription::create_grpc_subscription, json_rpc_leaders_getter::JsonRpcLeaderGetter,
+    json_rpc_subscription::create_json_rpc_polling_subscription,
+    rpc_polling::poll_blocks::NUM_PARALLEL_TASKS_DEFAULT, rpc_pool::RpcPool,
+};
+#[cfg(feature = "accounts")]
+use solana_lite_rpc_core::structures::account_data::AccountStream;
+#[cfg(feature = "priofees")]
+use solana_lite_rpc_core::traits::address_lookup_table_interface::AddressLookupTableInterface;
+use solana_lite_rpc_core::{
+    stores::{
+        block_cache::BlockCache,
+        block_information_store::{BlockInformation, BlockInformationStore},
+        blockhash_cache::BlockhashCache,
+        cluster_info_store::ClusterInfo,
+        data_cache::{DataCache, SlotCache},
+        signature_status_cache::SignatureStatusCache,
+        subscription_store::SubscriptionStore,
+        tx_lifecycle_store::TxLifecycleStore,
+        tx_store::TxStore,
+    },
+    structures::{
+        account_filter::AccountFilters, epoch::EpochCache, heartbeat::LoopHeartbeats,
+        identity_stakes::IdentityStakes, leaderschedule::CalculatedSchedule,
+        produced_block::ProducedBlock,
+    },
+    types::{
+        BlockInfoStream, BlockOrderingEventStream, BlockStream, ClockStream,
+        CommitmentUpgradeStream, LatestBlock, LatestSlot, SlotStream,
+    },
+    AnyhowJoinHandle,
+};
+#[cfg(feature = "priofees")]
+use solana_lite_rpc_prioritization_fees::{
+    account_prio_service::AccountPrioService, priofees_window::PrioFeesWindowConfig,
+    program_priofees::ProgramPrioService, start_block_priofees_task, PrioFeesService,
+};
+use solana_lite_rpc_services::{
+    data_caching_service::DataCachingService,
+    dry_run::DryRun,
+    fee_guardrails::{FeeGuardrails, FeeLimits},
+    tpu_utils::{
+        send_strategy::DefaultSendStrategy,
+        tpu_service::{TpuService, TpuServiceConfig},
+    },
+    transaction_replayer::TransactionReplayer,
+    transaction_service::{TransactionService, TransactionServiceBuilder},
+    tx_sender::TxSender,
+};
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Keypair, signer::Signer,
+};
+#[cfg(feature = "priofees")]
+use tokio::task::JoinHandle;
+use tokio::{
+    sync::{broadcast::error::RecvError, RwLock},
+    time::{timeout, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "accounts")]
+use crate::bridge_accounts::LiteAccountsBridge;
+#[cfg(feature = "priofees")]
+use crate::bridge_priofees::LitePrioFeesBridge;
+#[cfg(feature = "ws-server")]
+use crate::bridge_pubsub::LitePubSubBridge;
+#[cfg(feature = "priofees")]
+use crate::DEFAULT_BLOCK_CU_LIMIT;
+use crate::{
+    bridge::LiteBridge,
+    cli::Config,
+    cors::CorsConfig,
+    health_endpoint::{HealthState, StartupGate},
+    http_limits::HttpLimits,
+    start_server::{start_servers, DataApis, RpcMethods},
+    webhook_notifier::WebhookNotifier,
+    DEFAULT_BLOCK_CACHE_SIZE, DEFAULT_MAX_NUMBER_OF_TXS_IN_QUEUE, DEFAULT_READINESS_MAX_SLOT_LAG,
+    DEFAULT_RETRY_TIMEOUT, DEFAULT_SERVER_WORKERS, DEFAULT_STARTUP_MAX_SLOT_LAG,
+    DEFAULT_STARTUP_MIN_BLOCKS, DEFAULT_TX_STATUS_RETENTION_SECS, MAX_RETRIES,
+};
+
+// accounts are fetched from the rpc node with that many requests in parallel on startup
+#[cfg(feature = "accounts")]
+const MAX_ACCOUNT_REQUESTS_IN_PARALLEL: usize = 10;
+
+/// waits for the first block of the given commitment on the block stream
+pub async fn get_latest_block(
+    mut block_stream: BlockStream,
+    commitment_config: CommitmentConfig,
+) -> anyhow::Result<ProducedBlock> {
+    let started = Instant::now();
+    loop {
+        match timeout(Duration::from_millis(500), block_stream.recv()).await {
+            Ok(Ok(block)) => {
+                if block.commitment_config == commitment_config {
+                    return Ok(block);
+                }
+            }
+            Err(_elapsed) => {
+                debug!(
+                    "waiting for latest block ({}) ... {:.02}ms",
+                    commitment_config.commitment,
+                    started.elapsed().as_secs_f32() * 1000.0
+                );
+            }
+            Ok(Err(RecvError::Lagged(skipped))) => {
+                warn!("skipped {skipped} blocks while waiting for the latest block");
+            }
+            Ok(Err(RecvError::Closed)) => {
+                anyhow::bail!(
+                    "block stream closed before a {} block was received",
+                    commitment_config.commitment
+                );
+            }
+        }
+    }
+}
+
+/// the caches of the cluster state, starting at the first finalized block
+pub fn create_data_cache(
+    finalized_block: &ProducedBlock,
+    epoch_data: EpochCache,
+    identity: Pubkey,
+    block_cache_size: usize,
+) -> DataCache {
+    let blockhash_cache = BlockhashCache::default();
+    blockhash_cache.add_block(finalized_block);
+    DataCache {
+        block_information_store: BlockInformationStore::new(BlockInformation::from_block(
+            finalized_block,
+        )),
+        blockhash_cache,
+        block_cache: BlockCache::new(block_cache_size),
+        cluster_info: ClusterInfo::default(),
+        identity_stakes: IdentityStakes::new(identity),
+        slot_cache: SlotCache::new(finalized_block.slot),
+        tx_subs: SubscriptionStore::default(),
+        txs: TxStore {
+            store: Arc::new(DashMap::new()),
+        },
+        signature_statuses: SignatureStatusCache::default(),
+        tx_lifecycles: TxLifecycleStore::default(),
+        epoch_data,
+        leader_schedule: Arc::new(RwLock::new(CalculatedSchedule::default())),
+        heartbeats: LoopHeartbeats::default(),
+    }
+}
+
+/// the in-memory account store fed by the account stream of the gRPC sources, the accounts of the
+/// filters are loaded from the rpc node before it is returned
+#[cfg(feature = "accounts")]
+pub async fn start_account_service(
+    account_stream: AccountStream,
+    blocks_notifier: BlockStream,
+    rpc_pool: RpcPool,
+    account_filters: &AccountFilters,
+) -> anyhow::Result<(
+    AccountService,
+    Arc<InmemoryAccountStore>,
+    Vec<(&'static str, AnyhowJoinHandle)>,
+)> {
+    let inmemory_account_store = Arc::new(InmemoryAccountStore::new());
+    let inmemory_account_storage: Arc<dyn AccountStorageInterface> = inmemory_account_store.clone();
+    let accounts_service =
+        AccountService::new(inmemory_account_storage).with_rpc_pool(rpc_pool.clone());
+    accounts_service.set_filters(account_filters.clone());
+    let tasks = accounts_service
+        .process_account_stream(account_stream, blocks_notifier)
+        .into_iter()
+        .map(|task| ("account stream", task))
+        .collect();
+    accounts_service
+        .populate_from_rpc(
+            rpc_pool.primary(),
+            account_filters,
+            MAX_ACCOUNT_REQUESTS_IN_PARALLEL,
+        )
+        .await?;
+    Ok((accounts_service, inmemory_account_store, tasks))
+}
+
+/// the prioritization fees of the blocks, accounts and programs
+#[cfg(feature = "priofees")]
+pub struct PrioFeesServices {
+    pub block_priofees_service: PrioFeesService,
+    pub account_priofees_service: AccountPrioService,
+    pub program_priofees_service: ProgramPrioService,
+    pub tasks: Vec<(&'static str, AnyhowJoinHandle)>,
+}
+
+#[cfg(feature = "priofees")]
+pub fn start_priofees_services(
+    blocks_notifier: &BlockStream,
+    window: PrioFeesWindowConfig,
+    block_cu_limit: u64,
+    address_lookup_tables: Option<Arc<dyn AddressLookupTableInterface>>,
+) -> PrioFeesServices {
+    let (block_priofees_task, block_priofees_service) =
+        start_block_priofees_task(blocks_notifier.resubscribe(), window, block_cu_limit);
+    let (account_priofees_task, account_priofees_service) =
+        AccountPrioService::start_account_priofees_task(
+            blocks_notifier.resubscribe(),
+            window,
+            address_lookup_tables,
+        );
+    let (program_priofees_task, program_priofees_service) =
+        ProgramPrioService::start_program_priofees_task(blocks_notifier.resubscribe(), window);
+    PrioFeesServices {
+        block_priofees_service,
+        account_priofees_service,
+        program_priofees_service,
+        tasks: vec![
+            ("block prioritization fees", unit_task(block_priofees_task)),
+            (
+                "account prioritization fees",
+                unit_task(account_priofees_task),
+            ),
+            (
+                "program prioritization fees",
+                unit_task(program_priofees_task),
+            ),
+        ],
+    }
+}
+
+/// the error of the first of the named tasks to stop, never returns without tasks
+pub async fn first_stopped_task(tasks: Vec<(&'static str, AnyhowJoinHandle)>) -> anyhow::Error {
+    if tasks.is_empty() {
+        return std::future::pending().await;
+    }
+    let (names, tasks): (Vec<&'static str>, Vec<AnyhowJoinHandle>) = tasks.into_iter().unzip();
+    let (res, index, _) = futures::future::select_all(tasks).await;
+    anyhow::anyhow!("{} stopped {res:?}", names[index])
+}
+
+/// listen addresses of the json rpc servers started by an embedded lite-rpc
+#[derive(Clone, Debug)]
+pub struct RpcServerConfig {
+    pub http_addr: String,
+    pub ws_addr: String,
+    /// accept loops per server, more than one share the ports with SO_REUSEPORT
+    pub workers: usize,
+}
+
+impl RpcServerConfig {
+    /// with the server workers default of the lite-rpc binary
+    pub fn new(http_addr: String, ws_addr: String) -> Self {
+        Self {
+            http_addr,
+            ws_addr,
+            workers: DEFAULT_SERVER_WORKERS,
+        }
+    }
+}
+
+/// sending transactions to the leaders
+#[derive(Clone)]
+pub struct TransactionSendingConfig {
+    pub tpu_config: TpuServiceConfig,
+    /// a random identity is used if not set
+    pub identity: Option<Arc<Keypair>>,
+    pub max_retries: usize,
+    pub retry_after: Duration,
+    /// verify the signatures of the sent transactions before forwarding them
+    pub verify_signatures: bool,
+    /// limits of the fees and compute units of the sent transactions
+    pub fee_limits: FeeLimits,
+    /// validate the sent transactions without forwarding them
+    pub dry_run: bool,
+    /// hosts the `callbackUrl` of `sendTransaction` may point to, callbacks are rejected if empty
+    pub callback_url_allowed_hosts: Vec<String>,
+}
+
+impl TransactionSendingConfig {
+    /// with the retry defaults of the lite-rpc binary
+    pub fn new(tpu_config: TpuServiceConfig) -> Self {
+        Self {
+            tpu_config,
+            identity: None,
+            max_retries: MAX_RETRIES,
+            retry_after: Duration::from_secs(DEFAULT_RETRY_TIMEOUT),
+            verify_signatures: true,
+            fee_limits: FeeLimits::default(),
+            dry_run: false,
+            callback_url_allowed_hosts: vec![],
+        }
+    }
+}
+
+/// runs the subsystems of lite-rpc inside another application instead of as a separate process;
+/// only the block, slot and cluster streams and the caches are always started
+pub struct LiteRpcBuilder {
+    rpc_client: Arc<RpcClient>,
+    rpc_fallbacks: Vec<Arc<RpcClient>>,
+    grpc_sources: Vec<GrpcSourceConfig>,
+    account_filters: AccountFilters,
+    block_cache_size: usize,
+    tx_status_retention: Duration,
+    transaction_sending: Option<TransactionSendingConfig>,
+    #[cfg(feature = "priofees")]
+    prioritization_fees: Option<PrioFeesWindowConfig>,
+    #[cfg(feature = "priofees")]
+    block_cu_limit: u64,
+    rpc_server: Option<RpcServerConfig>,
+    shutdown: CancellationToken,
+}
+
+impl LiteRpcBuilder {
+    /// without gRPC sources the blocks are polled from the rpc node
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        Self {
+            rpc_client,
+            rpc_fallbacks: vec![],
+            grpc_sources: vec![],
+            account_filters: vec![],
+            block_cache_size: DEFAULT_BLOCK_CACHE_SIZE,
+            tx_status_retention: Duration::from_secs(DEFAULT_TX_STATUS_RETENTION_SECS),
+            transaction_sending: None,
+            #[cfg(feature = "priofees")]
+            prioritization_fees: None,
+            #[cfg(feature = "priofees")]
+            block_cu_limit: DEFAULT_BLOCK_CU_LIMIT,
+            rpc_server: None,
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// rpc nodes the vote account and cluster info polling fails over to
+    pub fn with_rpc_fallbacks(mut self, rpc_fallbacks: Vec<Arc<RpcClient>>) -> Self {
+        self.rpc_fallbacks = rpc_fallbacks;
+        self
+    }
+
+    pub fn with_grpc_sources(mut self, grpc_sources: Vec<GrpcSourceConfig>) -> Self {
+        self.grpc_sources = grpc_sources;
+        self
+    }
+
+    /// streams the matching accounts from the gRPC sources and keeps them in memory
+    #[cfg(feature = "accounts")]
+    pub fn with_account_filters(mut self, account_filters: AccountFilters) -> Self {
+        self.account_filters = account_filters;
+        self
+    }
+
+    pub fn with_block_cache_size(mut self, block_cache_size: usize) -> Self {
+        self.block_cache_size = block_cache_size;
+        self
+    }
+
+    /// how long the statuses of finalized transactions are kept
+    pub fn with_tx_status_retention(mut self, tx_status_retention: Duration) -> Self {
+        self.tx_status_retention = tx_status_retention;
+        self
+    }
+
+    pub fn with_transaction_sending(mut self, config: TransactionSendingConfig) -> Self {
+        self.transaction_sending = Some(config);
+        self
+    }
+
+    #[cfg(feature = "priofees")]
+    pub fn with_prioritization_fees(mut self, window: PrioFeesWindowConfig) -> Self {
+        self.prioritization_fees = Some(window);
+        self
+    }
+
+    /// compute units a leader may pack into a block, the block utilization is relative to it
+    #[cfg(feature = "priofees")]
+    pub fn with_block_cu_limit(mut self, block_cu_limit: u64) -> Self {
+        self.block_cu_limit = block_cu_limit;
+        self
+    }
+
+    /// serves the json rpc and pubsub api, requires transaction sending; the prioritization fee
+    /// and account methods are served if those subsystems are started
+    pub fn with_rpc_server(mut self, config: RpcServerConfig) -> Self {
+        self.rpc_server = Some(config);
+        self
+    }
+
+    /// stops the rpc servers when cancelled, defaults to a token cancelled by [`LiteRpc::shutdown`]
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// connects to the sources, waits for the first finalized block and starts the selected subsystems
+    pub async fn start(self) -> anyhow::Result<LiteRpc> {
+        anyhow::ensure!(
+            self.rpc_server.is_none() || self.transaction_sending.is_some(),
+            "The rpc server requires transaction sending"
+        );
+        anyhow::ensure!(
+            self.account_filters.is_empty() || !self.grpc_sources.is_empty(),
+            "Account filters require gRPC sources"
+        );
+
+        let rpc_client = self.rpc_client;
+        let rpc_pool = RpcPool::new(rpc_client.clone(), self.rpc_fallbacks);
+        let mut tasks: Vec<(&'static str, AnyhowJoinHandle)> = vec![];
+
+        let (subscriptions, cluster_endpoint_tasks) = if self.grpc_sources.is_empty() {
+            info!("Creating RPC poll subscription...");
+            create_json_rpc_polling_subscription(rpc_pool.clone(), NUM_PARALLEL_TASKS_DEFAULT)?
+        } else {
+            info!("Creating geyser subscription...");
+            create_grpc_subscription(
+                rpc_pool.clone(),
+                self.grpc_sources,
+                self.account_filters.clone(),
+            )?
+        };
+        tasks.extend(
+            cluster_endpoint_tasks
+                .into_iter()
+                .map(|task| ("cluster endpoint", task)),
+        );
+        let EndpointStreaming {
+            blocks_notifier,
+            block_info_notifier,
+            cluster_info_notifier,
+            slot_notifier,
+            vote_account_notifier,
+            #[cfg(feature = "accounts")]
+            processed_account_stream,
+            block_ordering_notifier,
+            clock_notifier,
+            ..
+        } = subscriptions;
+
+        #[cfg(feature = "accounts")]
+        let accounts_service = match processed_account_stream {
+            Some(account_stream) => {
+                let (accounts_service, _, account_tasks) = start_account_service(
+                    account_stream,
+                    blocks_notifier.resubscribe(),
+                    rpc_pool.clone(),
+                    &self.account_filters,
+                )
+                .await?;
+                tasks.extend(account_tasks);
+                Some(accounts_service)
+            }
+            None => None,
+        };
+
+        info!("Waiting for first finalized block...");
+        let finalized_block =
+            get_latest_block(blocks_notifier.resubscribe(), CommitmentConfig::finalized()).await?;
+        info!("Got finalized block: {:?}", finalized_block.slot);
+
+        let (epoch_data, _current_epoch_info) = EpochCache::bootstrap_epoch(&rpc_client).await?;
+
+        let identity = self
+            .transaction_sending
+            .as_ref()
+            .and_then(|config| config.identity.clone())
+            .unwrap_or_else(|| Arc::new(Keypair::new()));
+
+        let data_cache = create_data_cache(
+            &finalized_block,
+            epoch_data,
+            identity.pubkey(),
+            self.block_cache_size,
+        );
+
+        tasks.extend(
+            DataCachingService {
+                data_cache: data_cache.clone(),
+                clean_duration: Duration::from_secs(120),
+                tx_status_retention: self.tx_status_retention,
+                optimistically_confirmed_blocks: None,
+            }
+            .listen(
+                blocks_notifier.resubscribe(),
+                slot_notifier.resubscribe(),
+                cluster_info_notifier,
+                vote_account_notifier,
+            )
+            .into_iter()
+            .map(|task| ("data caching service", task)),
+        );
+
+        #[cfg(feature = "priofees")]
+        let (block_priofees_service, account_priofees_service, program_priofees_service) =
+            match self.prioritization_fees {
+                Some(window) => {
+                    let priofees_services = start_priofees_services(
+                        &blocks_notifier,
+                        window,
+                        self.block_cu_limit,
+                        None,
+                    );
+                    tasks.extend(priofees_services.tasks);
+                    (
+                        Some(priofees_services.block_priofees_service),
+                        Some(priofees_services.account_priofees_service),
+                        Some(priofees_services.program_priofees_service),
+                    )
+                }
+                None => (None, None, None),
+            };
+
+        let health_state = HealthState::new(
+            data_cache.clone(),
+            DEFAULT_READINESS_MAX_SLOT_LAG,
+            StartupGate {
+                min_blocks: DEFAULT_STARTUP_MIN_BLOCKS,
+                max_slot_lag: DEFAULT_STARTUP_MAX_SLOT_LAG,
+            },
+        );
+        tasks.push((
+            "health",
+            health_state.start(block_info_notifier.resubscribe()),
+        ));
+        let (commitment_upgrade_notifier, commitment_upgrade_task) =
+            create_commitment_upgrade_stream(block_info_notifier.resubscribe());
+        tasks.push(("commitment upgrades", commitment_upgrade_task));
+
+        let callback_url_allowed_hosts = self
+            .transaction_sending
+            .as_ref()
+            .map(|config| config.callback_url_allowed_hosts.clone())
+            .unwrap_or_default();
+        let transaction_service = match self.transaction_sending {
+            Some(config) => {
+                let leader_schedule =
+                    Arc::new(JsonRpcLeaderGetter::new(rpc_client.clone(), 1024, 128));
+                let tpu_service = TpuService::new(
+                    config.tpu_config,
+                    identity,
+                    leader_schedule,
+                    Arc::new(DefaultSendStrategy),
+                    data_cache.clone(),
+                )
+                .await?;
+                let tx_sender = TxSender::new(data_cache.clone(), tpu_service.clone());
+                let tx_replayer = TransactionReplayer::new(
+                    tpu_service.clone(),
+                    data_cache.txs.clone(),
+                    data_cache.tx_lifecycles.clone(),
+                    data_cache.blockhash_cache.clone(),
+                    config.retry_after,
+                );
+                let (transaction_service, tx_service_jh) = TransactionServiceBuilder::new(
+                    tx_sender,
+                    tx_replayer,
+                    tpu_service,
+                    DEFAULT_MAX_NUMBER_OF_TXS_IN_QUEUE,
+                )
+                .with_signature_verification(config.verify_signatures)
+                .with_fee_guardrails(FeeGuardrails::new(config.fee_limits.clone()))
+                .with_dry_run(DryRun::new(config.dry_run))
+                .start(
+                    None,
+                    data_cache.blockhash_cache.clone(),
+                    config.max_retries,
+                    data_cache.slot_cache.subscribe(),
+                );
+                tasks.push((
+                    "transaction service",
+                    health_state.track_tpu_service(tx_service_jh),
+                ));
+                Some(transaction_service)
+            }
+            None => None,
+        };
+
+        if let (Some(server_config), Some(transaction_service)) =
+            (self.rpc_server, transaction_service.clone())
+        {
+            let webhook_notifier =
+                WebhookNotifier::new().with_allowed_hosts(callback_url_allowed_hosts);
+            tasks.push((
+                "webhook notifier",
+                webhook_notifier.start(blocks_notifier.resubscribe()),
+            ));
+
+            let bridge = LiteBridge::new(
+                rpc_client.clone(),
+                data_cache.clone(),
+                transaction_service.clone(),
+                History::new(rpc_client.clone(), None),
+                webhook_notifier,
+                health_state.clone(),
+            );
+            let bridge = match &clock_notifier {
+                Some(clock_notifier) => {
+                    let (latest_clock, latest_clock_task) =
+                        create_latest_clock(clock_notifier.resubscribe());
+                    tasks.push(("latest clock", latest_clock_task));
+                    bridge.with_clock(latest_clock)
+                }
+                None => bridge,
+            };
+            #[cfg(feature = "accounts")]
+            let bridge = match accounts_service.clone() {
+                Some(accounts_service) => bridge.with_accounts_service(accounts_service),
+                None => bridge,
+            };
+            let methods = RpcMethods::new(bridge);
+            #[cfg(feature = "ws-server")]
+            let methods = methods.with_pubsub(LitePubSubBridge::new(
+                data_cache.clone(),
+                blocks_notifier.resubscribe(),
+                commitment_upgrade_notifier.resubscribe(),
+            ))?;
+            #[cfg(feature = "priofees")]
+            let methods = match (
+                block_priofees_service.clone(),
+                account_priofees_service,
+                program_priofees_service,
+            ) {
+                (
+                    Some(block_priofees_service),
+                    Some(account_priofees_service),
+                    Some(program_priofees_service),
+                ) => methods.with_priofees(LitePrioFeesBridge::new(
+                    block_priofees_service,
+                    account_priofees_service,
+                    program_priofees_service,
+                ))?,
+                _ => methods,
+            };
+            #[cfg(feature = "accounts")]
+            let methods = match accounts_service.clone() {
+                Some(accounts_service) => {
+                    methods.with_accounts(LiteAccountsBridge::new(accounts_service))?
+                }
+                None => methods,
+            };
+            let cors = CorsConfig {
+                allowed_origins: Config::default_cors_allowed_origins(),
+                allowed_methods: Config::default_cors_allowed_methods(),
+                allowed_headers: Config::default_cors_allowed_headers(),
+                max_age: Duration::from_secs(Config::default_cors_max_age_secs()),
+            }
+            .layer()?;
+            tasks.push((
+                "rpc server",
+                tokio::spawn(start_servers(
+                    methods,
+                    server_config.ws_addr,
+                    server_config.http_addr,
+                    server_config.workers,
+                    transaction_service,
+                    health_state.clone(),
+                    None,
+                    None,
+                    cors,
+                    None,
+                    None,
+                    DataApis::default(),
+                    HttpLimits::default(),
+                    Config::default_enable_compression(),
+                    self.shutdown.clone(),
+                )),
+            ));
+        }
+
+        Ok(LiteRpc {
+            data_cache,
+            blocks_notifier,
+            block_info_notifier,
+            commitment_upgrade_notifier,
+            block_ordering_notifier,
+            clock_notifier,
+            slot_notifier,
+            transaction_service,
+            #[cfg(feature = "priofees")]
+            block_priofees_service,
+            #[cfg(feature = "accounts")]
+            accounts_service,
+            health_state,
+            tasks,
+            shutdown: self.shutdown,
+        })
+    }
+}
+
+/// handle of an embedded lite-rpc, the subsystems keep running as long as the runtime does
+pub struct LiteRpc {
+    data_cache: DataCache,
+    blocks_notifier: BlockStream,
+    block_info_notifier: BlockInfoStream,
+    commitment_upgrade_notifier: CommitmentUpgradeStream,
+    block_ordering_notifier: Option<BlockOrderingEventStream>,
+    clock_notifier: Option<ClockStream>,
+    slot_notifier: SlotStream,
+    transaction_service: Option<TransactionService>,
+    #[cfg(feature = "priofees")]
+    block_priofees_service: Option<PrioFeesService>,
+    #[cfg(feature = "accounts")]
+    accounts_service: Option<AccountService>,
+    health_state: HealthState,
+    tasks: Vec<(&'static str, AnyhowJoinHandle)>,
+    shutdown: CancellationToken,
+}
+
+impl LiteRpc {
+    pub fn data_cache(&self) -> &DataCache {
+        &self.data_cache
+    }
+
+    /// a new receiver of the blocks of all commitments
+    pub fn blocks(&self) -> BlockStream {
+        self.blocks_notifier.resubscribe()
+    }
+
+    /// a new receiver of the metadata of the blocks of all commitments, without their transactions
+    pub fn block_infos(&self) -> BlockInfoStream {
+        self.block_info_notifier.resubscribe()
+    }
+
+    /// a new receiver of the slots reaching confirmed and finalized, flagged if the block changed
+    pub fn commitment_upgrades(&self) -> CommitmentUpgradeStream {
+        self.commitment_upgrade_notifier.resubscribe()
+    }
+
+    /// a new receiver of the blocks the gRPC multiplexer dropped for being out of order or
+    /// conflicting, none without gRPC sources
+    pub fn block_ordering_events(&self) -> Option<BlockOrderingEventStream> {
+        self.block_ordering_notifier
+            .as_ref()
+            .map(|notifier| notifier.resubscribe())
+    }
+
+    /// a new receiver of the decoded Clock sysvar, none for a replay of the block storage
+    pub fn clock(&self) -> Option<ClockStream> {
+        self.clock_notifier
+            .as_ref()
+            .map(|notifier| notifier.resubscribe())
+    }
+
+    /// a new receiver of the processed slots
+    pub fn slots(&self) -> SlotStream {
+        self.slot_notifier.resubscribe()
+    }
+
+    /// the latest processed slot, for consumers which do not need every slot
+    pub fn latest_slot(&self) -> LatestSlot {
+        self.data_cache.slot_cache.subscribe()
+    }
+
+    /// the latest block and blockhash of the commitment, processed is served as confirmed
+    pub fn latest_block(&self, commitment_config: CommitmentConfig) -> LatestBlock {
+        self.data_cache
+            .block_information_store
+            .subscribe_latest_block(commitment_config)
+    }
+
+    pub fn transaction_service(&self) -> Option<&TransactionService> {
+        self.transaction_service.as_ref()
+    }
+
+    #[cfg(feature = "priofees")]
+    pub fn prioritization_fees(&self) -> Option<&PrioFeesService> {
+        self.block_priofees_service.as_ref()
+    }
+
+    #[cfg(feature = "accounts")]
+    pub fn accounts_service(&self) -> Option<&AccountService> {
+        self.accounts_service.as_ref()
+    }
+
+    /// fails until the block stream caught up after the start, see [`HealthState::is_caught_up`]
+    pub async fn is_caught_up(&self) -> Result<(), String> {
+        self.health_state.is_caught_up().await
+    }
+
+    /// stops the rpc servers, the other subsystems end with the runtime
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    /// returns once the shutdown was requested or fails as soon as a subsystem stopped
+    pub async fn join(self) -> anyhow::Result<()> {
+        let Self {
+            tasks, shutdown, ..
+        } = self;
+        tokio::select! {
+            biased;
+            _ = shutdown.cancelled() => Ok(()),
+            err = first_stopped_task(tasks) => Err(err),
+        }
+    }
+}
+
+#[cfg(feature = "priofees")]
+fn unit_task(task: JoinHandle<()>) -> AnyhowJoinHandle {
+    tokio::spawn(async move {
+        task.await?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_latest_block_skips_lagged_blocks_and_other_commitments() {
+        let (sender, receiver) = tokio::sync::broadcast::channel(2);
+        for slot in 1..=3 {
+            sender
+                .send(ProducedBlock::new_for_tests(
+                    slot,
+                    CommitmentConfig::finalized(),
+                ))
+                .unwrap();
+        }
+        sender
+            .send(ProducedBlock::new_for_tests(
+                4,
+                CommitmentConfig::confirmed(),
+            ))
+            .unwrap();
+        sender
+            .send(ProducedBlock::new_for_tests(
+                5,
+                CommitmentConfig::finalized(),
+            ))
+            .unwrap();
+
+        let latest_block = get_latest_block(receiver, CommitmentConfig::finalized())
+            .await
+            .unwrap();
+        assert_eq!(latest_block.slot, 5);
+    }
+
+    #[tokio::test]
+    async fn test_latest_block_fails_on_a_closed_stream() {
+        let (sender, receiver) = tokio::sync::broadcast::channel(2);
+        sender
+            .send(ProducedBlock::new_for_tests(
+                1,
+                CommitmentConfig::confirmed(),
+            ))
+            .unwrap();
+        drop(sender);
+
+        assert!(get_latest_block(receiver, CommitmentConfig::finalized())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_first_stopped_task_is_named() {
+        let tasks: Vec<(&'static str, AnyhowJoinHandle)> = vec![
+            ("pending", tokio::spawn(std::future::pending())),
+            ("failing", tokio::spawn(async { anyhow::bail!("failed") })),
+        ];
+
+        let err = first_stopped_task(tasks).await;
+        assert!(err.to_string().starts_with("failing stopped"));
+    }
+}
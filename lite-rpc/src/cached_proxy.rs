@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use prometheus::{opts, register_int_counter_vec, IntCounterVec};
+use serde_json::value::RawValue;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::request::RpcRequest;
+use tokio::sync::Mutex;
+
+lazy_static::lazy_static! {
+    static ref CACHED_PROXY_REQUESTS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_cached_proxy_requests", "Calls of expensive rpc node methods by whether they were served from the cache"), &["method", "result"]).unwrap();
+}
+
+// answer of the rpc node, pre-serialized, and when it was fetched
+type Entry = Arc<Mutex<Option<(Instant, Box<RawValue>)>>>;
+
+/// Answers of rpc node methods which are too expensive to be called for every request, like
+/// `getSupply`. An answer is kept for the ttl per method and params; concurrent requests for an
+/// expired answer wait for one call to the rpc node instead of calling it each.
+pub struct CachedProxy {
+    rpc_client: Arc<RpcClient>,
+    ttl: Duration,
+    entries: DashMap<String, Entry>,
+}
+
+impl CachedProxy {
+    pub fn new(rpc_client: Arc<RpcClient>, ttl: Duration) -> Self {
+        Self {
+            rpc_client,
+            ttl,
+            entries: DashMap::new(),
+        }
+    }
+
+    pub async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> anyhow::Result<Box<RawValue>> {
+        let method = request.to_string();
+        let key = format!("{method}{params}");
+        let entry = self.entries.entry(key).or_default().clone();
+
+        let mut entry = entry.lock().await;
+        if let Some((fetched_at, answer)) = entry.as_ref() {
+            if fetched_at.elapsed() < self.ttl {
+                CACHED_PROXY_REQUESTS
+                    .with_label_values(&[&method, "hit"])
+                    .inc();
+                return Ok(answer.clone());
+            }
+        }
+
+        CACHED_PROXY_REQUESTS
+            .with_label_values(&[&method, "miss"])
+            .inc();
+        let answer = self
+            .rpc_client
+            .send::<serde_json::Value>(request, params)
+            .await?;
+        let answer = serde_json::value::to_raw_value(&answer)?;
+        *entry = Some((Instant::now(), answer.clone()));
+        drop(entry);
+
+        self.remove_expired();
+        Ok(answer)
+    }
+
+    // keeps the entries of params which are not asked for anymore from piling up
+    fn remove_expired(&self) {
+        self.entries.retain(|_, entry| match entry.try_lock() {
+            Ok(entry) => entry
+                .as_ref()
+                .is_some_and(|(fetched_at, _)| fetched_at.elapsed() < self.ttl),
+            // a request is fetching it
+            Err(_) => true,
+        });
+    }
+}
@@ -0,0 +1,235 @@
+use std::{collections::BTreeMap, convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+
+use hyper::{
+    client::HttpConnector, service::service_fn, Body, Client, Request, Response, StatusCode,
+};
+use log::{debug, info};
+use prometheus::{Encoder, TextEncoder};
+use solana_lite_rpc_core::AnyhowJoinHandle;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+use crate::{cluster_router::local_url, http_limits::ConnectionLimiter, start_server::serve_tcp};
+
+// the clusters answer from memory, a cluster not answering in time is left out of the scrape
+const CLUSTER_SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// serves the metrics of the instance and of its clusters, the clusters run in their own
+/// processes and the samples of their metrics get a `cluster` label
+pub struct ClusterMetrics {
+    // cluster name to the url of its prometheus endpoint
+    clusters: Vec<(String, String)>,
+    client: Client<HttpConnector>,
+}
+
+impl ClusterMetrics {
+    pub fn new(clusters: impl IntoIterator<Item = (String, String)>) -> anyhow::Result<Self> {
+        let clusters = clusters
+            .into_iter()
+            .map(|(name, prometheus_addr)| Ok((name, local_url(&prometheus_addr)?)))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            clusters,
+            client: Client::new(),
+        })
+    }
+
+    pub fn start(self, addr: SocketAddr, shutdown: CancellationToken) -> AnyhowJoinHandle {
+        let metrics = Arc::new(self);
+        tokio::spawn(async move {
+            let listener = TcpListener::bind(addr).await?;
+            let make_service = move |_remote_addr: SocketAddr| {
+                let metrics = metrics.clone();
+                service_fn(move |_request: Request<Body>| {
+                    let metrics = metrics.clone();
+                    async move { Ok::<_, Infallible>(metrics.scrape().await) }
+                })
+            };
+
+            info!("Prometheus of the clusters started at {addr:?}");
+            serve_tcp(
+                listener,
+                make_service,
+                ConnectionLimiter::unlimited(),
+                shutdown,
+            )
+            .await
+        })
+    }
+
+    async fn scrape(&self) -> Response<Body> {
+        let mut own_metrics = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&prometheus::gather(), &mut own_metrics) {
+            debug!("Encoding the metrics failed: {err}");
+        }
+        let own_metrics = String::from_utf8_lossy(&own_metrics).into_owned();
+
+        let cluster_metrics =
+            futures::future::join_all(self.clusters.iter().map(|(name, url)| async move {
+                (name.as_str(), self.scrape_cluster(name, url).await)
+            }))
+            .await;
+
+        let expositions = std::iter::once((None, own_metrics.as_str())).chain(
+            cluster_metrics
+                .iter()
+                .filter_map(|(name, metrics)| Some((Some(*name), metrics.as_deref()?))),
+        );
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(
+                hyper::header::CONTENT_TYPE,
+                TextEncoder::new().format_type(),
+            )
+            .body(Body::from(merge_expositions(expositions)))
+            .unwrap()
+    }
+
+    async fn scrape_cluster(&self, name: &str, url: &str) -> Option<String> {
+        let scrape = async {
+            let response = self.client.get(url.parse()?).await?;
+            let body = hyper::body::to_bytes(response.into_body()).await?;
+            anyhow::Ok(String::from_utf8(body.to_vec())?)
+        };
+        match tokio::time::timeout(CLUSTER_SCRAPE_TIMEOUT, scrape).await {
+            Ok(Ok(metrics)) => Some(metrics),
+            Ok(Err(err)) => {
+                debug!("Scraping the metrics of cluster {name} failed: {err:?}");
+                None
+            }
+            Err(_elapsed) => {
+                debug!("Scraping the metrics of cluster {name} timed out");
+                None
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct MetricFamily {
+    help: Option<String>,
+    kind: Option<String>,
+    samples: Vec<String>,
+}
+
+/// merges prometheus text expositions, each metric family is written once with the samples
+/// of all expositions, the samples of a cluster are labeled with its name
+fn merge_expositions<'a>(
+    expositions: impl IntoIterator<Item = (Option<&'a str>, &'a str)>,
+) -> String {
+    let mut families: BTreeMap<String, MetricFamily> = BTreeMap::new();
+    for (cluster, exposition) in expositions {
+        let mut family_name = String::new();
+        for line in exposition.lines() {
+            if let Some(comment) = line.strip_prefix("# ") {
+                let mut words = comment.splitn(3, ' ');
+                let (Some(keyword), Some(name)) = (words.next(), words.next()) else {
+                    continue;
+                };
+                family_name = name.to_string();
+                let family = families.entry(family_name.clone()).or_default();
+                match keyword {
+                    "HELP" => family.help.get_or_insert_with(|| line.to_string()),
+                    "TYPE" => family.kind.get_or_insert_with(|| line.to_string()),
+                    _ => continue,
+                };
+            } else if !line.trim().is_empty() {
+                let sample = match cluster {
+                    Some(cluster) => with_cluster_label(line, cluster),
+                    None => line.to_string(),
+                };
+                families
+                    .entry(family_name.clone())
+                    .or_default()
+                    .samples
+                    .push(sample);
+            }
+        }
+    }
+
+    let mut merged = String::new();
+    for family in families.into_values() {
+        for line in family
+            .help
+            .iter()
+            .chain(family.kind.iter())
+            .chain(family.samples.iter())
+        {
+            merged.push_str(line);
+            merged.push('\n');
+        }
+    }
+    merged
+}
+
+// `name{labels} value` or `name value`
+fn with_cluster_label(sample: &str, cluster: &str) -> String {
+    let cluster = cluster.replace('\\', "\\\\").replace('"', "\\\"");
+    let name_end = sample.find(['{', ' ']).unwrap_or(sample.len());
+    let (name, rest) = sample.split_at(name_end);
+    match rest.strip_prefix('{') {
+        Some(labels) if labels.starts_with('}') => format!("{name}{{cluster=\"{cluster}\"{labels}"),
+        Some(labels) => format!("{name}{{cluster=\"{cluster}\",{labels}"),
+        None => format!("{name}{{cluster=\"{cluster}\"}}{rest}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_of_the_clusters_are_labeled() {
+        assert_eq!(
+            with_cluster_label("literpc_slot 42", "devnet"),
+            "literpc_slot{cluster=\"devnet\"} 42"
+        );
+        assert_eq!(
+            with_cluster_label("literpc_rpc_calls{method=\"getSlot\"} 3", "devnet"),
+            "literpc_rpc_calls{cluster=\"devnet\",method=\"getSlot\"} 3"
+        );
+        assert_eq!(
+            with_cluster_label("literpc_slot{} 42", "devnet"),
+            "literpc_slot{cluster=\"devnet\"} 42"
+        );
+    }
+
+    #[test]
+    fn test_families_are_merged() {
+        let devnet = "# HELP literpc_slot Current slot\n\
+                      # TYPE literpc_slot gauge\n\
+                      literpc_slot 100\n\
+                      # HELP literpc_latency Latency\n\
+                      # TYPE literpc_latency histogram\n\
+                      literpc_latency_bucket{le=\"1\"} 2\n\
+                      literpc_latency_sum 1.5\n\
+                      literpc_latency_count 2\n";
+        let testnet = "# HELP literpc_slot Current slot\n\
+                       # TYPE literpc_slot gauge\n\
+                       literpc_slot 200\n";
+        let own = "# HELP literpc_cluster_requests HTTP requests routed to the clusters\n\
+                   # TYPE literpc_cluster_requests counter\n\
+                   literpc_cluster_requests{cluster=\"devnet\"} 7\n";
+
+        let merged = merge_expositions([
+            (None, own),
+            (Some("devnet"), devnet),
+            (Some("testnet"), testnet),
+        ]);
+        assert_eq!(
+            merged,
+            "# HELP literpc_cluster_requests HTTP requests routed to the clusters\n\
+             # TYPE literpc_cluster_requests counter\n\
+             literpc_cluster_requests{cluster=\"devnet\"} 7\n\
+             # HELP literpc_latency Latency\n\
+             # TYPE literpc_latency histogram\n\
+             literpc_latency_bucket{cluster=\"devnet\",le=\"1\"} 2\n\
+             literpc_latency_sum{cluster=\"devnet\"} 1.5\n\
+             literpc_latency_count{cluster=\"devnet\"} 2\n\
+             # HELP literpc_slot Current slot\n\
+             # TYPE literpc_slot gauge\n\
+             literpc_slot{cluster=\"devnet\"} 100\n\
+             literpc_slot{cluster=\"testnet\"} 200\n"
+        );
+    }
+}
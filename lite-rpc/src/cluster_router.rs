@@ -0,0 +1,431 @@
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, sync::Arc};
+
+use hyper::{
+    client::HttpConnector,
+    header::{HeaderValue, CONNECTION, HOST, TRANSFER_ENCODING, UPGRADE},
+    service::service_fn,
+    upgrade::OnUpgrade,
+    Body, Client, Request, Response, StatusCode,
+};
+use log::{debug, info};
+use prometheus::{opts, register_int_counter_vec, IntCounterVec};
+use solana_lite_rpc_core::AnyhowJoinHandle;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    http_limits::{ConnectionLimiter, HttpLimitLayer, HttpLimits},
+    rate_limit::FORWARDED_FOR_HEADER,
+    start_server::serve_tcp,
+    utils::text_response,
+};
+
+lazy_static::lazy_static! {
+    static ref CLUSTER_REQUESTS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_cluster_requests", "HTTP requests routed to the clusters"), &["cluster"]).unwrap();
+}
+
+/// header naming the cluster of a request, the alternative to the path prefix
+pub const CLUSTER_HEADER: &str = "x-cluster";
+// label of the requests which match no cluster
+const UNKNOWN_CLUSTER_LABEL: &str = "unknown";
+
+/// routes the http requests to the servers of the clusters by the `X-Cluster` header
+/// or the first path segment, e.g. `/devnet` or `/devnet/health`; websocket upgrades are
+/// tunneled to the server of the cluster once it accepted them
+#[derive(Clone)]
+pub struct ClusterRouter {
+    // cluster name to the base url of its http server
+    clusters: Arc<HashMap<String, String>>,
+    limits: HttpLimits,
+    client: Client<HttpConnector>,
+}
+
+impl ClusterRouter {
+    /// the requests to the router have the limits of the http servers of the clusters
+    pub fn new(
+        clusters: impl IntoIterator<Item = (String, String)>,
+        limits: HttpLimits,
+    ) -> anyhow::Result<Self> {
+        let mut routes = HashMap::new();
+        for (name, http_addr) in clusters {
+            anyhow::ensure!(
+                routes
+                    .insert(name.clone(), local_url(&http_addr)?)
+                    .is_none(),
+                "cluster {name} is configured twice"
+            );
+        }
+        Ok(Self {
+            clusters: Arc::new(routes),
+            limits,
+            client: Client::new(),
+        })
+    }
+
+    pub fn start(self, addr: SocketAddr, shutdown: CancellationToken) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            let listener = TcpListener::bind(addr).await?;
+            let connection_limiter = ConnectionLimiter::new(&self.limits);
+            info!("Cluster router started at {addr:?}");
+            self.serve(listener, connection_limiter, shutdown).await
+        })
+    }
+
+    /// the router of the websocket servers, like them it does not limit the connections
+    pub fn start_ws(self, addr: SocketAddr, shutdown: CancellationToken) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            let listener = TcpListener::bind(addr).await?;
+            info!("Cluster websocket router started at {addr:?}");
+            self.serve(listener, ConnectionLimiter::unlimited(), shutdown)
+                .await
+        })
+    }
+
+    async fn serve(
+        self,
+        listener: TcpListener,
+        connection_limiter: ConnectionLimiter,
+        shutdown: CancellationToken,
+    ) -> anyhow::Result<()> {
+        // the request bodies are read under the size limit and the request timeout
+        // before they are forwarded, the responses are streamed
+        let limit_layer = HttpLimitLayer::new(&self.limits);
+        let make_service = move |remote_addr: SocketAddr| {
+            let router = self.clone();
+            tower::ServiceBuilder::new()
+                .layer(limit_layer.clone())
+                .service(service_fn(move |request| {
+                    let router = router.clone();
+                    async move { Ok::<_, Infallible>(router.route(request, remote_addr).await) }
+                }))
+        };
+        serve_tcp(listener, make_service, connection_limiter, shutdown).await
+    }
+
+    /// the cluster of a request and the url of the request on its server
+    fn resolve(
+        &self,
+        path_and_query: &str,
+        header_cluster: Option<&str>,
+    ) -> Option<(&str, String)> {
+        match header_cluster {
+            Some(name) => self
+                .clusters
+                .get_key_value(name)
+                .map(|(name, base_url)| (name.as_str(), format!("{base_url}{path_and_query}"))),
+            None => {
+                let path = path_and_query.trim_start_matches('/');
+                let (name, rest) = path
+                    .find(['/', '?'])
+                    .map_or((path, ""), |end| path.split_at(end));
+                self.clusters.get_key_value(name).map(|(name, base_url)| {
+                    let url = if rest.starts_with('/') {
+                        format!("{base_url}{rest}")
+                    } else {
+                        format!("{base_url}/{rest}")
+                    };
+                    (name.as_str(), url)
+                })
+            }
+        }
+    }
+
+    async fn route(&self, request: Request<Body>, remote_addr: SocketAddr) -> Response<Body> {
+        let path_and_query = request
+            .uri()
+            .path_and_query()
+            .map(|path_and_query| path_and_query.as_str())
+            .unwrap_or("/");
+        let header_cluster = request
+            .headers()
+            .get(CLUSTER_HEADER)
+            .and_then(|value| value.to_str().ok());
+        let Some((name, url)) = self.resolve(path_and_query, header_cluster) else {
+            CLUSTER_REQUESTS
+                .with_label_values(&[UNKNOWN_CLUSTER_LABEL])
+                .inc();
+            return text_response(StatusCode::NOT_FOUND, "unknown cluster".to_string());
+        };
+        CLUSTER_REQUESTS.with_label_values(&[name]).inc();
+
+        match self.forward(request, &url, remote_addr).await {
+            Ok(response) => response,
+            Err(err) => {
+                debug!("forwarding to cluster {name} failed: {err:?}");
+                text_response(
+                    StatusCode::BAD_GATEWAY,
+                    format!("cluster {name} unavailable"),
+                )
+            }
+        }
+    }
+
+    async fn forward(
+        &self,
+        mut request: Request<Body>,
+        url: &str,
+        remote_addr: SocketAddr,
+    ) -> anyhow::Result<Response<Body>> {
+        let client_upgrade =
+            is_upgrade(request.headers()).then(|| hyper::upgrade::on(&mut request));
+        let (mut parts, body) = request.into_parts();
+        parts.uri = url.parse()?;
+        // the rate limiter of the cluster sees the client when the router is a trusted proxy
+        let forwarded_for = match parts
+            .headers
+            .get(FORWARDED_FOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(forwarded_for) => format!("{forwarded_for}, {}", remote_addr.ip()),
+            None => remote_addr.ip().to_string(),
+        };
+        // the client sets the host of the cluster
+        parts.headers.remove(HOST);
+        remove_hop_by_hop(&mut parts.headers);
+        parts
+            .headers
+            .insert(FORWARDED_FOR_HEADER, HeaderValue::from_str(&forwarded_for)?);
+        if client_upgrade.is_some() {
+            parts
+                .headers
+                .insert(CONNECTION, HeaderValue::from_static("upgrade"));
+        }
+
+        let mut response = self
+            .client
+            .request(Request::from_parts(parts, body))
+            .await?;
+        remove_hop_by_hop(response.headers_mut());
+        if let Some(client_upgrade) = client_upgrade {
+            if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+                response
+                    .headers_mut()
+                    .insert(CONNECTION, HeaderValue::from_static("upgrade"));
+                let cluster_upgrade = hyper::upgrade::on(&mut response);
+                tokio::spawn(async move {
+                    if let Err(err) = tunnel(client_upgrade, cluster_upgrade).await {
+                        debug!("websocket tunnel to the cluster closed: {err:?}");
+                    }
+                });
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// the url of a server of the instance, listening on all interfaces when configured so
+pub fn local_url(addr: &str) -> anyhow::Result<String> {
+    let addr: SocketAddr = addr.parse()?;
+    let addr = if addr.ip().is_unspecified() {
+        SocketAddr::new([127, 0, 0, 1].into(), addr.port())
+    } else {
+        addr
+    };
+    Ok(format!("http://{addr}"))
+}
+
+fn is_upgrade(headers: &hyper::HeaderMap) -> bool {
+    headers
+        .get(UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
+/// copies the websocket frames between the client and the cluster until either side closes
+async fn tunnel(client: OnUpgrade, cluster: OnUpgrade) -> anyhow::Result<()> {
+    let (mut client, mut cluster) = tokio::try_join!(client, cluster)?;
+    tokio::io::copy_bidirectional(&mut client, &mut cluster).await?;
+    Ok(())
+}
+
+// headers of a single connection, not forwarded
+fn remove_hop_by_hop(headers: &mut hyper::HeaderMap) {
+    for name in [CONNECTION, TRANSFER_ENCODING] {
+        headers.remove(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    fn router() -> ClusterRouter {
+        ClusterRouter::new(
+            [
+                ("devnet".to_string(), "0.0.0.0:9010".to_string()),
+                ("testnet".to_string(), "127.0.0.1:9020".to_string()),
+            ],
+            HttpLimits::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_path_prefix_selects_the_cluster() {
+        let router = router();
+        assert_eq!(
+            router.resolve("/devnet", None),
+            Some(("devnet", "http://127.0.0.1:9010/".to_string()))
+        );
+        assert_eq!(
+            router.resolve("/devnet/health", None),
+            Some(("devnet", "http://127.0.0.1:9010/health".to_string()))
+        );
+        assert_eq!(
+            router.resolve("/testnet?api-key=abc", None),
+            Some(("testnet", "http://127.0.0.1:9020/?api-key=abc".to_string()))
+        );
+        assert_eq!(router.resolve("/", None), None);
+        assert_eq!(router.resolve("/devnet2/health", None), None);
+        assert_eq!(router.resolve("/mainnet", None), None);
+    }
+
+    #[test]
+    fn test_header_selects_the_cluster_and_keeps_the_path() {
+        let router = router();
+        assert_eq!(
+            router.resolve("/health", Some("testnet")),
+            Some(("testnet", "http://127.0.0.1:9020/health".to_string()))
+        );
+        // the header takes precedence over the path prefix
+        assert_eq!(
+            router.resolve("/devnet", Some("testnet")),
+            Some(("testnet", "http://127.0.0.1:9020/devnet".to_string()))
+        );
+        assert_eq!(router.resolve("/devnet", Some("mainnet")), None);
+    }
+
+    #[test]
+    fn test_clusters_are_unique() {
+        assert!(ClusterRouter::new(
+            [
+                ("devnet".to_string(), "127.0.0.1:9010".to_string()),
+                ("devnet".to_string(), "127.0.0.1:9020".to_string()),
+            ],
+            HttpLimits::default(),
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_forwarded_to_the_cluster() {
+        let cluster = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let cluster_addr = cluster.local_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        // echoes the path and the forwarded for header
+        tokio::spawn(serve_tcp(
+            cluster,
+            |_remote_addr: SocketAddr| {
+                service_fn(|request: Request<Body>| async move {
+                    let forwarded_for = request.headers()[FORWARDED_FOR_HEADER].clone();
+                    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                    Ok::<_, Infallible>(
+                        Response::builder()
+                            .header(FORWARDED_FOR_HEADER, forwarded_for)
+                            .body(Body::from(body))
+                            .unwrap(),
+                    )
+                })
+            },
+            ConnectionLimiter::unlimited(),
+            shutdown.clone(),
+        ));
+        let router = ClusterRouter::new(
+            [("devnet".to_string(), cluster_addr.to_string())],
+            HttpLimits::default(),
+        )
+        .unwrap();
+
+        let response = router
+            .route(
+                Request::post("/devnet")
+                    .header(FORWARDED_FOR_HEADER, "10.0.0.1")
+                    .body(Body::from("request"))
+                    .unwrap(),
+                "10.0.0.2:1000".parse().unwrap(),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[FORWARDED_FOR_HEADER],
+            "10.0.0.1, 10.0.0.2"
+        );
+        assert_eq!(
+            hyper::body::to_bytes(response.into_body()).await.unwrap(),
+            "request"
+        );
+
+        let response = router
+            .route(
+                Request::get("/mainnet").body(Body::empty()).unwrap(),
+                "10.0.0.2:1000".parse().unwrap(),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_websocket_upgrades_are_tunneled_to_the_cluster() {
+        let cluster = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let cluster_addr = cluster.local_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        // accepts the upgrade and echoes the bytes of the upgraded connection
+        tokio::spawn(serve_tcp(
+            cluster,
+            |_remote_addr: SocketAddr| {
+                service_fn(|mut request: Request<Body>| async move {
+                    assert_eq!(request.uri().path(), "/");
+                    let upgrade = hyper::upgrade::on(&mut request);
+                    tokio::spawn(async move {
+                        let upgraded = upgrade.await.unwrap();
+                        let (mut reader, mut writer) = tokio::io::split(upgraded);
+                        tokio::io::copy(&mut reader, &mut writer).await.unwrap();
+                    });
+                    Ok::<_, Infallible>(
+                        Response::builder()
+                            .status(StatusCode::SWITCHING_PROTOCOLS)
+                            .header(CONNECTION, "upgrade")
+                            .header(UPGRADE, "websocket")
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                })
+            },
+            ConnectionLimiter::unlimited(),
+            shutdown.clone(),
+        ));
+        let router = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let router_addr = router.local_addr().unwrap();
+        tokio::spawn(
+            ClusterRouter::new(
+                [("devnet".to_string(), cluster_addr.to_string())],
+                HttpLimits::default(),
+            )
+            .unwrap()
+            .serve(router, ConnectionLimiter::unlimited(), shutdown.clone()),
+        );
+
+        let mut client = tokio::net::TcpStream::connect(router_addr).await.unwrap();
+        client
+            .write_all(
+                b"GET /devnet HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(client.read_u8().await.unwrap());
+        }
+        assert!(head.starts_with(b"HTTP/1.1 101"));
+
+        client.write_all(b"frame").await.unwrap();
+        let mut echo = [0u8; 5];
+        client.read_exact(&mut echo).await.unwrap();
+        assert_eq!(&echo, b"frame");
+        shutdown.cancel();
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use hyper::{body::HttpBody, header::CONTENT_ENCODING, Body, Request, Response};
+use prometheus::{opts, register_int_counter_vec, IntCounterVec};
+use tower::{Layer, Service};
+use tower_http::compression::{Compression, CompressionLayer};
+
+lazy_static::lazy_static! {
+    static ref HTTP_COMPRESSED_RESPONSES: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_http_compressed_responses", "HTTP responses sent compressed"), &["encoding"]).unwrap();
+}
+
+/// compresses the responses with gzip or brotli as accepted by the client in `Accept-Encoding`,
+/// small responses are sent as they are; the compressed body is streamed, so streaming responses
+/// like the arrow export are not buffered
+#[derive(Clone)]
+pub struct ResponseCompressionLayer {
+    enabled: bool,
+}
+
+impl ResponseCompressionLayer {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl<S> Layer<S> for ResponseCompressionLayer {
+    type Service = ResponseCompressionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseCompressionService {
+            compression_layer: self.enabled.then(|| {
+                CompressionLayer::new()
+                    .gzip(true)
+                    .br(true)
+                    .no_deflate()
+                    .no_zstd()
+            }),
+            service: inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ResponseCompressionService<S> {
+    // none if compression is disabled
+    compression_layer: Option<CompressionLayer>,
+    service: S,
+}
+
+impl<S> Service<Request<Body>> for ResponseCompressionService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let Some(compression_layer) = &self.compression_layer else {
+            return Box::pin(self.service.call(request));
+        };
+        // the ready service is taken, a clone waits for the next request
+        let clone = self.service.clone();
+        let service = std::mem::replace(&mut self.service, clone);
+        let mut compression: Compression<S> = compression_layer.layer(service);
+        Box::pin(async move {
+            let response = compression.call(request).await?;
+            let (parts, body) = response.into_parts();
+            if let Some(encoding) = parts
+                .headers
+                .get(CONTENT_ENCODING)
+                .and_then(|encoding| encoding.to_str().ok())
+            {
+                HTTP_COMPRESSED_RESPONSES
+                    .with_label_values(&[encoding])
+                    .inc();
+            }
+            // the servers expect a hyper body, the compressed chunks are forwarded as they are
+            // encoded and an error of the inner body aborts the response
+            let chunks = futures::stream::unfold(Box::pin(body), |mut body| async move {
+                let chunk = body.data().await?;
+                if let Err(err) = &chunk {
+                    log::debug!("Compressing the response failed: {err}");
+                }
+                Some((chunk, body))
+            });
+            Ok(Response::from_parts(parts, Body::wrap_stream(chunks)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::ACCEPT_ENCODING;
+    use tower_http::decompression::DecompressionLayer;
+
+    use super::*;
+
+    async fn streaming_response(
+        _request: Request<Body>,
+    ) -> Result<Response<Body>, std::convert::Infallible> {
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            for _ in 0..3 {
+                let chunk = vec![b'a'; 1024];
+                if sender.send_data(chunk.into()).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(body))
+    }
+
+    #[tokio::test]
+    async fn streaming_bodies_are_compressed_as_a_stream() {
+        let mut service =
+            ResponseCompressionLayer::new(true).layer(tower::service_fn(streaming_response));
+        let request = Request::builder()
+            .header(ACCEPT_ENCODING, "gzip")
+            .body(Body::empty())
+            .unwrap();
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+
+        // decompressed as a client would
+        let mut service = DecompressionLayer::new().layer(service);
+        let response = service.call(Request::new(Body::empty())).await.unwrap();
+        let decoded = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(decoded, vec![b'a'; 3 * 1024]);
+    }
+
+    #[tokio::test]
+    async fn an_aborted_stream_aborts_the_response() {
+        let mut service = ResponseCompressionLayer::new(true).layer(tower::service_fn(
+            |_request: Request<Body>| async {
+                let (mut sender, body) = Body::channel();
+                tokio::spawn(async move {
+                    let _ = sender.send_data(vec![b'a'; 1024].into()).await;
+                    sender.abort();
+                });
+                Ok::<_, std::convert::Infallible>(Response::new(body))
+            },
+        ));
+        let request = Request::builder()
+            .header(ACCEPT_ENCODING, "gzip")
+            .body(Body::empty())
+            .unwrap();
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::OK);
+        assert!(hyper::body::to_bytes(response.into_body()).await.is_err());
+    }
+}
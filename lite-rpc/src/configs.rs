@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use solana_lite_rpc_core::encoding::BinaryEncoding;
-use solana_sdk::commitment_config::CommitmentLevel;
+use solana_rpc_client_api::config::RpcAccountInfoConfig;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -13,6 +14,9 @@ pub struct SendTransactionConfig {
     pub encoding: BinaryEncoding,
     pub max_retries: Option<u16>,
     //    pub min_context_slot: Option<Slot>,
+    /// lite-rpc extension: url which gets a POST when the transaction is confirmed, finalized or expired
+    #[serde(default)]
+    pub callback_url: Option<String>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -21,3 +25,50 @@ pub struct IsBlockHashValidConfig {
     pub commitment: Option<CommitmentLevel>,
     //    pub minContextSlot: Option<u64>,
 }
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrioFeeForMessageConfig {
+    /// percentile of the fees paid on the writable accounts, must be a multiple of 5
+    pub percentile: Option<u32>,
+    pub lookback_slots: Option<u64>,
+}
+
+/// the authority of a stake account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StakeAuthority {
+    Staker,
+    Withdrawer,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StakeAccountsConfig {
+    /// the authority the address is matched against, any of both if not set
+    pub authority: Option<StakeAuthority>,
+    #[serde(flatten)]
+    pub account_config: RpcAccountInfoConfig,
+}
+
+/// the filter of `transactionSubscribe`, matched against the writable and readable accounts of the
+/// transactions
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSubscribeFilter {
+    /// transactions touching any of the accounts, must not be empty
+    #[serde(default)]
+    pub account_include: Vec<String>,
+    /// transactions touching any of the accounts are skipped
+    #[serde(default)]
+    pub account_exclude: Vec<String>,
+    /// vote transactions are only included if set
+    #[serde(default)]
+    pub vote: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSubscribeConfig {
+    pub commitment: Option<CommitmentConfig>,
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    Method,
+};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+
+use crate::request_id::REQUEST_ID_HEADER;
+
+// matches every origin, method or header
+const WILDCARD: &str = "*";
+
+/// CORS policy of the HTTP server; preflight (`OPTIONS`) requests are answered by the layer
+/// before they reach the api key check or the rpc methods
+#[derive(Clone, Debug)]
+pub struct CorsConfig {
+    /// origins like `https://app.example.com`, `*` allows any origin
+    pub allowed_origins: Vec<String>,
+    /// `*` allows any method
+    pub allowed_methods: Vec<String>,
+    /// request headers browsers may send, `*` allows any header
+    pub allowed_headers: Vec<String>,
+    /// how long browsers may cache the preflight response
+    pub max_age: Duration,
+}
+
+impl CorsConfig {
+    pub fn layer(&self) -> anyhow::Result<CorsLayer> {
+        let allow_origin = if self.allowed_origins.iter().any(|origin| origin == WILDCARD) {
+            AllowOrigin::from(Any)
+        } else {
+            let origins = self
+                .allowed_origins
+                .iter()
+                .map(|origin| {
+                    HeaderValue::from_str(origin.trim_end_matches('/'))
+                        .with_context(|| format!("Invalid CORS origin {origin}"))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            AllowOrigin::list(origins)
+        };
+
+        let allow_methods = if self.allowed_methods.iter().any(|method| method == WILDCARD) {
+            AllowMethods::from(Any)
+        } else {
+            let methods = self
+                .allowed_methods
+                .iter()
+                .map(|method| {
+                    Method::from_bytes(method.to_uppercase().as_bytes())
+                        .with_context(|| format!("Invalid CORS method {method}"))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            AllowMethods::list(methods)
+        };
+
+        let allow_headers = if self.allowed_headers.iter().any(|header| header == WILDCARD) {
+            AllowHeaders::from(Any)
+        } else {
+            let headers = self
+                .allowed_headers
+                .iter()
+                .map(|header| {
+                    HeaderName::from_bytes(header.to_lowercase().as_bytes())
+                        .with_context(|| format!("Invalid CORS header {header}"))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            AllowHeaders::list(headers)
+        };
+
+        Ok(CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(allow_methods)
+            .allow_headers(allow_headers)
+            .max_age(self.max_age)
+            .expose_headers([HeaderName::from_static(REQUEST_ID_HEADER)]))
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use solana_lite_rpc_core::{
+    epoch_notifier::{EpochAwareCache, EpochNotifier},
+    structures::epoch::Epoch,
+};
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::response::RpcInflationRate;
+
+type FetchFn<T> = Box<dyn Fn(u64) -> BoxFuture<'static, anyhow::Result<T>> + Send + Sync>;
+
+/// A value of the rpc node which only changes at epoch boundaries, like the inflation rate. It is
+/// refreshed by the epoch notifier when an epoch starts; requests only fetch it while that refresh
+/// has not succeeded yet.
+pub struct EpochValueCache<T> {
+    name: &'static str,
+    value: RwLock<Option<(u64, T)>>,
+    fetch: FetchFn<T>,
+}
+
+impl<T: Clone + Send + Sync> EpochValueCache<T> {
+    pub fn new<F>(name: &'static str, fetch: F) -> Self
+    where
+        F: Fn(u64) -> BoxFuture<'static, anyhow::Result<T>> + Send + Sync + 'static,
+    {
+        Self {
+            name,
+            value: RwLock::new(None),
+            fetch: Box::new(fetch),
+        }
+    }
+
+    /// value of the epoch, served from the cache unless it was not refreshed for the epoch yet
+    pub async fn get(&self, epoch: u64) -> anyhow::Result<T> {
+        if let Some((cached_epoch, value)) = self.value.read().unwrap().as_ref() {
+            if *cached_epoch == epoch {
+                return Ok(value.clone());
+            }
+        }
+        self.refresh(epoch).await
+    }
+
+    async fn refresh(&self, epoch: u64) -> anyhow::Result<T> {
+        let value = (self.fetch)(epoch).await?;
+        let mut cached = self.value.write().unwrap();
+        // a request of an older commitment must not replace the value of the new epoch
+        if cached
+            .as_ref()
+            .map_or(true, |(cached_epoch, _)| *cached_epoch <= epoch)
+        {
+            *cached = Some((epoch, value.clone()));
+        }
+        Ok(value)
+    }
+}
+
+#[async_trait]
+impl<T: Clone + Send + Sync> EpochAwareCache for EpochValueCache<T> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn on_new_epoch(&self, epoch: &Epoch) -> anyhow::Result<()> {
+        self.refresh(epoch.epoch).await.map(|_| ())
+    }
+}
+
+/// the epoch bound values served by the bridge
+#[derive(Clone)]
+pub struct EpochValueCaches {
+    pub inflation_rate: Arc<EpochValueCache<RpcInflationRate>>,
+    pub stake_minimum_delegation: Arc<EpochValueCache<u64>>,
+}
+
+impl EpochValueCaches {
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        let inflation_rpc_client = rpc_client.clone();
+        Self {
+            inflation_rate: Arc::new(EpochValueCache::new("inflation_rate", move |epoch| {
+                let rpc_client = inflation_rpc_client.clone();
+                Box::pin(async move {
+                    let rate = rpc_client.get_inflation_rate().await?;
+                    // the rpc node might not have reached the epoch yet, the refresh is retried
+                    anyhow::ensure!(
+                        rate.epoch == epoch,
+                        "inflation rate of epoch {} instead of {epoch}",
+                        rate.epoch
+                    );
+                    Ok(rate)
+                })
+            })),
+            // only changes with the activation of a feature, which happens at an epoch boundary
+            stake_minimum_delegation: Arc::new(EpochValueCache::new(
+                "stake_minimum_delegation",
+                move |_| {
+                    let rpc_client = rpc_client.clone();
+                    Box::pin(async move { Ok(rpc_client.get_stake_minimum_delegation().await?) })
+                },
+            )),
+        }
+    }
+
+    pub fn register(&self, epoch_notifier: EpochNotifier) -> EpochNotifier {
+        epoch_notifier
+            .with_cache(self.inflation_rate.clone())
+            .with_cache(self.stake_minimum_delegation.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    fn counting_cache(fetches: Arc<AtomicU64>) -> EpochValueCache<u64> {
+        EpochValueCache::new("test", move |epoch| {
+            let fetches = fetches.clone();
+            Box::pin(async move {
+                fetches.fetch_add(1, Ordering::Relaxed);
+                Ok(epoch * 10)
+            })
+        })
+    }
+
+    #[tokio::test]
+    async fn test_value_is_fetched_once_per_epoch() {
+        let fetches = Arc::new(AtomicU64::new(0));
+        let cache = counting_cache(fetches.clone());
+
+        assert_eq!(cache.get(1).await.unwrap(), 10);
+        assert_eq!(cache.get(1).await.unwrap(), 10);
+        assert_eq!(fetches.load(Ordering::Relaxed), 1);
+
+        // refreshed by the epoch notifier, requests of the epoch are served from the cache
+        cache.refresh(2).await.unwrap();
+        assert_eq!(cache.get(2).await.unwrap(), 20);
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_older_epoch_does_not_replace_the_new_one() {
+        let fetches = Arc::new(AtomicU64::new(0));
+        let cache = counting_cache(fetches.clone());
+
+        cache.refresh(2).await.unwrap();
+        assert_eq!(cache.get(1).await.unwrap(), 10);
+        assert_eq!(cache.get(2).await.unwrap(), 20);
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+    }
+}
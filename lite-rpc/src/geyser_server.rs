@@ -0,0 +1,999 @@
+use std::{collections::HashSet, net::SocketAddr, pin::Pin, str::FromStr};
+
+use futures::Stream;
+use log::{debug, info, warn};
+use prometheus::{opts, register_int_counter, register_int_gauge, IntCounter, IntGauge};
+use solana_lite_rpc_core::{
+    commitment_utils::Commitment,
+    stores::data_cache::DataCache,
+    structures::{
+        account_data::{AccountNotificationMessage, AccountStream},
+        produced_block::{ProducedBlock, TransactionInfo},
+    },
+    types::{BlockStream, SlotStream},
+    AnyhowJoinHandle,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, message::VersionedMessage, pubkey::Pubkey,
+    signature::Signature,
+};
+use solana_transaction_status::{Reward, RewardType};
+use tokio::sync::{broadcast::error::RecvError, mpsc, watch};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response, Status, Streaming};
+use yellowstone_grpc_proto::{
+    geyser::{
+        geyser_server::{Geyser, GeyserServer},
+        subscribe_update::UpdateOneof,
+        CommitmentLevel, GetBlockHeightRequest, GetBlockHeightResponse, GetLatestBlockhashRequest,
+        GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse, GetVersionRequest,
+        GetVersionResponse, IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest,
+        PongResponse, SubscribeRequest, SubscribeRequestFilterBlocks,
+        SubscribeRequestFilterTransactions, SubscribeUpdate, SubscribeUpdateAccount,
+        SubscribeUpdateAccountInfo, SubscribeUpdateBlock, SubscribeUpdateBlockMeta,
+        SubscribeUpdatePong, SubscribeUpdateSlot, SubscribeUpdateTransaction,
+        SubscribeUpdateTransactionInfo,
+    },
+    solana::storage::confirmed_block,
+};
+
+use crate::api_key_auth::ApiKeys;
+
+lazy_static::lazy_static! {
+    static ref GEYSER_SUBSCRIPTIONS: IntGauge =
+        register_int_gauge!(opts!("literpc_geyser_server_subscriptions", "Open subscriptions of the re-exporting geyser gRPC server")).unwrap();
+    static ref GEYSER_UPDATES_SENT: IntCounter =
+        register_int_counter!(opts!("literpc_geyser_server_updates_sent", "Updates sent by the re-exporting geyser gRPC server")).unwrap();
+}
+
+// updates buffered per subscription before the subscriber is considered too slow
+const SUBSCRIPTION_BUFFER: usize = 1024;
+
+/// transaction filter with the semantics of yellowstone: the fields are combined with AND
+#[derive(Default)]
+struct TransactionsFilter {
+    vote: Option<bool>,
+    failed: Option<bool>,
+    signature: Option<Signature>,
+    account_include: HashSet<Pubkey>,
+    account_exclude: HashSet<Pubkey>,
+    account_required: HashSet<Pubkey>,
+}
+
+impl TransactionsFilter {
+    fn parse(filter: SubscribeRequestFilterTransactions) -> Result<Self, Status> {
+        let signature = filter
+            .signature
+            .map(|signature| {
+                Signature::from_str(&signature)
+                    .map_err(|_| Status::invalid_argument(format!("invalid signature {signature}")))
+            })
+            .transpose()?;
+        Ok(Self {
+            vote: filter.vote,
+            failed: filter.failed,
+            signature,
+            account_include: parse_pubkeys(&filter.account_include)?,
+            account_exclude: parse_pubkeys(&filter.account_exclude)?,
+            account_required: parse_pubkeys(&filter.account_required)?,
+        })
+    }
+
+    fn matches(&self, tx: &TransactionInfo) -> bool {
+        let touches = |account: &Pubkey| {
+            tx.writable_accounts.contains(account) || tx.readable_accounts.contains(account)
+        };
+        self.vote.map_or(true, |vote| vote == tx.is_vote)
+            && self
+                .failed
+                .map_or(true, |failed| failed == tx.err.is_some())
+            && self
+                .signature
+                .map_or(true, |signature| signature == tx.signature)
+            && (self.account_include.is_empty() || self.account_include.iter().any(touches))
+            && !self.account_exclude.iter().any(touches)
+            && self.account_required.iter().all(touches)
+    }
+}
+
+struct BlocksFilter {
+    account_include: HashSet<Pubkey>,
+    include_transactions: bool,
+}
+
+impl BlocksFilter {
+    fn parse(filter: SubscribeRequestFilterBlocks) -> Result<Self, Status> {
+        if filter.include_accounts.unwrap_or_default() || filter.include_entries.unwrap_or_default()
+        {
+            return Err(Status::unimplemented(
+                "lite-rpc does not keep the accounts and entries of the blocks",
+            ));
+        }
+        Ok(Self {
+            account_include: parse_pubkeys(&filter.account_include)?,
+            include_transactions: filter.include_transactions.unwrap_or(true),
+        })
+    }
+
+    fn matches(&self, tx: &TransactionInfo) -> bool {
+        self.account_include.is_empty()
+            || tx
+                .writable_accounts
+                .iter()
+                .chain(&tx.readable_accounts)
+                .any(|account| self.account_include.contains(account))
+    }
+}
+
+/// filters of a subscribe request by name
+struct SubscriptionFilter {
+    commitment: Commitment,
+    // name and filter_by_commitment
+    slots: Vec<(String, bool)>,
+    blocks_meta: Vec<String>,
+    blocks: Vec<(String, BlocksFilter)>,
+    transactions: Vec<(String, TransactionsFilter)>,
+    // name, pubkeys and owners
+    accounts: Vec<(String, HashSet<Pubkey>, HashSet<Pubkey>)>,
+}
+
+impl Default for SubscriptionFilter {
+    fn default() -> Self {
+        Self {
+            commitment: Commitment::Processed,
+            slots: vec![],
+            blocks_meta: vec![],
+            blocks: vec![],
+            transactions: vec![],
+            accounts: vec![],
+        }
+    }
+}
+
+impl SubscriptionFilter {
+    fn parse(request: SubscribeRequest) -> Result<Self, Status> {
+        let commitment = match request.commitment.map(CommitmentLevel::try_from) {
+            None => Commitment::Processed,
+            Some(Ok(CommitmentLevel::Processed)) => Commitment::Processed,
+            Some(Ok(CommitmentLevel::Confirmed)) => Commitment::Confirmed,
+            Some(Ok(CommitmentLevel::Finalized)) => Commitment::Finalized,
+            Some(Err(_)) => return Err(Status::invalid_argument("invalid commitment")),
+        };
+
+        let mut accounts = vec![];
+        for (name, filter) in request.accounts {
+            if !filter.filters.is_empty() {
+                return Err(Status::unimplemented(
+                    "memcmp and datasize account filters are not supported",
+                ));
+            }
+            accounts.push((
+                name,
+                parse_pubkeys(&filter.account)?,
+                parse_pubkeys(&filter.owner)?,
+            ));
+        }
+
+        let blocks = request
+            .blocks
+            .into_iter()
+            .map(|(name, filter)| Ok((name, BlocksFilter::parse(filter)?)))
+            .collect::<Result<_, Status>>()?;
+        let transactions = request
+            .transactions
+            .into_iter()
+            .map(|(name, filter)| Ok((name, TransactionsFilter::parse(filter)?)))
+            .collect::<Result<_, Status>>()?;
+
+        Ok(Self {
+            commitment,
+            slots: request
+                .slots
+                .into_iter()
+                .map(|(name, filter)| (name, filter.filter_by_commitment.unwrap_or_default()))
+                .collect(),
+            blocks_meta: request.blocks_meta.into_keys().collect(),
+            blocks,
+            transactions,
+            accounts,
+        })
+    }
+
+    /// the block and transaction updates of a block of the commitment level of the subscription
+    fn block_updates(&self, block: &ProducedBlock) -> Vec<(Vec<String>, UpdateOneof)> {
+        let mut updates = vec![];
+        for (name, filter) in &self.blocks {
+            let transactions = if filter.include_transactions {
+                block
+                    .transactions
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, tx)| filter.matches(tx))
+                    .map(|(index, tx)| transaction_info(index, tx))
+                    .collect()
+            } else {
+                vec![]
+            };
+            updates.push((
+                vec![name.clone()],
+                UpdateOneof::Block(block_update(block, transactions)),
+            ));
+        }
+        if !self.transactions.is_empty() {
+            for (index, tx) in block.transactions.iter().enumerate() {
+                let filters: Vec<String> = self
+                    .transactions
+                    .iter()
+                    .filter(|(_, filter)| filter.matches(tx))
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                if !filters.is_empty() {
+                    updates.push((
+                        filters,
+                        UpdateOneof::Transaction(SubscribeUpdateTransaction {
+                            transaction: Some(transaction_info(index, tx)),
+                            slot: block.slot,
+                        }),
+                    ));
+                }
+            }
+        }
+        updates
+    }
+
+    fn slot_filters(&self, commitment: Commitment) -> Vec<String> {
+        self.slots
+            .iter()
+            .filter(|(_, filter_by_commitment)| {
+                !filter_by_commitment || commitment == self.commitment
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    fn account_filters(&self, account: &AccountNotificationMessage) -> Vec<String> {
+        if account.commitment != self.commitment {
+            return vec![];
+        }
+        // fields are combined with AND, values of a field with OR, as in yellowstone
+        self.accounts
+            .iter()
+            .filter(|(_, pubkeys, owners)| {
+                (pubkeys.is_empty() || pubkeys.contains(&account.data.pubkey))
+                    && (owners.is_empty() || owners.contains(&account.data.account.owner))
+            })
+            .map(|(name, _, _)| name.clone())
+            .collect()
+    }
+}
+
+fn parse_pubkeys(pubkeys: &[String]) -> Result<HashSet<Pubkey>, Status> {
+    pubkeys
+        .iter()
+        .map(|pubkey| {
+            Pubkey::from_str(pubkey)
+                .map_err(|_| Status::invalid_argument(format!("invalid pubkey {pubkey}")))
+        })
+        .collect()
+}
+
+fn commitment_level(commitment: Commitment) -> CommitmentLevel {
+    match commitment {
+        Commitment::Processed => CommitmentLevel::Processed,
+        Commitment::Confirmed => CommitmentLevel::Confirmed,
+        Commitment::Finalized => CommitmentLevel::Finalized,
+    }
+}
+
+fn commitment_config(commitment: Option<i32>) -> Result<CommitmentConfig, Status> {
+    match commitment.map(CommitmentLevel::try_from) {
+        None => Ok(CommitmentConfig::finalized()),
+        Some(Ok(CommitmentLevel::Processed)) => Ok(CommitmentConfig::processed()),
+        Some(Ok(CommitmentLevel::Confirmed)) => Ok(CommitmentConfig::confirmed()),
+        Some(Ok(CommitmentLevel::Finalized)) => Ok(CommitmentConfig::finalized()),
+        Some(Err(_)) => Err(Status::invalid_argument("invalid commitment")),
+    }
+}
+
+fn reward(reward: &Reward) -> confirmed_block::Reward {
+    let reward_type = match reward.reward_type {
+        None => confirmed_block::RewardType::Unspecified,
+        Some(RewardType::Fee) => confirmed_block::RewardType::Fee,
+        Some(RewardType::Rent) => confirmed_block::RewardType::Rent,
+        Some(RewardType::Staking) => confirmed_block::RewardType::Staking,
+        Some(RewardType::Voting) => confirmed_block::RewardType::Voting,
+    };
+    confirmed_block::Reward {
+        pubkey: reward.pubkey.clone(),
+        lamports: reward.lamports,
+        post_balance: reward.post_balance,
+        reward_type: reward_type as i32,
+        commission: reward
+            .commission
+            .map(|commission| commission.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn block_meta_update(block: &ProducedBlock) -> SubscribeUpdateBlockMeta {
+    SubscribeUpdateBlockMeta {
+        slot: block.slot,
+        blockhash: block.blockhash.to_string(),
+        rewards: block
+            .rewards
+            .as_ref()
+            .map(|rewards| confirmed_block::Rewards {
+                rewards: rewards.iter().map(reward).collect(),
+            }),
+        block_time: Some(confirmed_block::UnixTimestamp {
+            timestamp: block.block_time as i64,
+        }),
+        block_height: Some(confirmed_block::BlockHeight {
+            block_height: block.block_height,
+        }),
+        parent_slot: block.parent_slot,
+        parent_blockhash: block.previous_blockhash.to_string(),
+        executed_transaction_count: block.transactions.len() as u64,
+    }
+}
+
+fn block_update(
+    block: &ProducedBlock,
+    transactions: Vec<SubscribeUpdateTransactionInfo>,
+) -> SubscribeUpdateBlock {
+    let meta = block_meta_update(block);
+    SubscribeUpdateBlock {
+        slot: meta.slot,
+        blockhash: meta.blockhash,
+        rewards: meta.rewards,
+        block_time: meta.block_time,
+        block_height: meta.block_height,
+        parent_slot: meta.parent_slot,
+        parent_blockhash: meta.parent_blockhash,
+        executed_transaction_count: meta.executed_transaction_count,
+        transactions,
+        ..Default::default()
+    }
+}
+
+/// lite-rpc keeps the message, the first signature, the error and the consumed compute units of a
+/// transaction, the other signatures and the rest of the status meta are left empty
+fn transaction_info(index: usize, tx: &TransactionInfo) -> SubscribeUpdateTransactionInfo {
+    let signature = tx.signature.as_ref().to_vec();
+    let message = match tx.message.to_versioned_message() {
+        Ok(message) => Some(message_proto(&message)),
+        Err(e) => {
+            debug!("cannot decode the message of {}: {e}", tx.signature);
+            None
+        }
+    };
+    SubscribeUpdateTransactionInfo {
+        signature: signature.clone(),
+        is_vote: tx.is_vote,
+        transaction: Some(confirmed_block::Transaction {
+            signatures: vec![signature],
+            message,
+        }),
+        meta: Some(confirmed_block::TransactionStatusMeta {
+            err: tx
+                .err
+                .as_ref()
+                .map(|err| confirmed_block::TransactionError {
+                    err: bincode::serialize(err).expect("transaction error serializable"),
+                }),
+            compute_units_consumed: tx.cu_consumed,
+            ..Default::default()
+        }),
+        index: index as u64,
+    }
+}
+
+fn message_proto(message: &VersionedMessage) -> confirmed_block::Message {
+    let header = message.header();
+    confirmed_block::Message {
+        header: Some(confirmed_block::MessageHeader {
+            num_required_signatures: header.num_required_signatures as u32,
+            num_readonly_signed_accounts: header.num_readonly_signed_accounts as u32,
+            num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts as u32,
+        }),
+        account_keys: message
+            .static_account_keys()
+            .iter()
+            .map(|key| key.to_bytes().to_vec())
+            .collect(),
+        recent_blockhash: message.recent_blockhash().to_bytes().to_vec(),
+        instructions: message
+            .instructions()
+            .iter()
+            .map(|instruction| confirmed_block::CompiledInstruction {
+                program_id_index: instruction.program_id_index as u32,
+                accounts: instruction.accounts.clone(),
+                data: instruction.data.clone(),
+            })
+            .collect(),
+        versioned: matches!(message, VersionedMessage::V0(_)),
+        address_table_lookups: message
+            .address_table_lookups()
+            .unwrap_or_default()
+            .iter()
+            .map(|lookup| confirmed_block::MessageAddressTableLookup {
+                account_key: lookup.account_key.to_bytes().to_vec(),
+                writable_indexes: lookup.writable_indexes.clone(),
+                readonly_indexes: lookup.readonly_indexes.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// the pong of a keepalive request, which must not replace the filters of the subscription
+fn pong(request: &SubscribeRequest) -> Option<SubscribeUpdate> {
+    request.ping.as_ref().map(|ping| SubscribeUpdate {
+        filters: vec![],
+        update_oneof: Some(UpdateOneof::Pong(SubscribeUpdatePong { id: ping.id })),
+    })
+}
+
+fn account_update(account: &AccountNotificationMessage) -> SubscribeUpdateAccount {
+    let data = &account.data;
+    SubscribeUpdateAccount {
+        account: Some(SubscribeUpdateAccountInfo {
+            pubkey: data.pubkey.to_bytes().to_vec(),
+            lamports: data.account.lamports,
+            owner: data.account.owner.to_bytes().to_vec(),
+            executable: data.account.executable,
+            rent_epoch: data.account.rent_epoch,
+            data: data.account.data.to_vec(),
+            write_version: 0,
+            txn_signature: None,
+        }),
+        slot: data.updated_slot,
+        is_startup: false,
+    }
+}
+
+type SubscribeStream =
+    Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send + 'static>>;
+
+/// yellowstone compatible gRPC server re-exporting the multiplexed and deduplicated streams of lite-rpc,
+/// so downstream services share one geyser subscription; the accounts are limited to the account
+/// filters of lite-rpc and the transactions to what lite-rpc keeps of them
+#[derive(Clone)]
+pub struct GeyserReexportService {
+    data_cache: DataCache,
+    blocks_notifier: BlockStream,
+    slot_notifier: SlotStream,
+    account_stream: Option<AccountStream>,
+    api_keys: Option<ApiKeys>,
+    // ends the open subscriptions, the server only stops once their streams are closed
+    shutdown: CancellationToken,
+}
+
+impl GeyserReexportService {
+    pub fn new(
+        data_cache: DataCache,
+        blocks_notifier: BlockStream,
+        slot_notifier: SlotStream,
+        account_stream: Option<AccountStream>,
+    ) -> Self {
+        Self {
+            data_cache,
+            blocks_notifier,
+            slot_notifier,
+            account_stream,
+            api_keys: None,
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// requires an api key in the `x-api-key` metadata of every call
+    pub fn with_api_keys(mut self, api_keys: Option<ApiKeys>) -> Self {
+        self.api_keys = api_keys;
+        self
+    }
+
+    pub fn start(mut self, addr: SocketAddr, shutdown: CancellationToken) -> AnyhowJoinHandle {
+        self.shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            info!("Geyser gRPC server started at {addr:?}");
+            let stopped = shutdown.clone();
+            let api_keys = self.api_keys.clone();
+            let service = GeyserServer::with_interceptor(self, move |request: Request<()>| {
+                if let Some(api_keys) = &api_keys {
+                    api_keys.grpc_key_id(request.metadata())?;
+                }
+                Ok(request)
+            });
+            tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_shutdown(addr, async move { stopped.cancelled().await })
+                .await?;
+            if !shutdown.is_cancelled() {
+                anyhow::bail!("Geyser gRPC server stopped");
+            }
+            Ok(())
+        })
+    }
+
+    /// forwards the updates matching the latest subscribe request until the client disconnects
+    /// or the server shuts down
+    async fn forward_updates(
+        mut blocks: BlockStream,
+        mut slots: SlotStream,
+        mut accounts: Option<AccountStream>,
+        filter: watch::Receiver<SubscriptionFilter>,
+        sender: mpsc::Sender<Result<SubscribeUpdate, Status>>,
+        shutdown: CancellationToken,
+    ) {
+        loop {
+            let updates: Vec<(Vec<String>, UpdateOneof)> = tokio::select! {
+                slot = slots.recv() => match slot {
+                    Ok(slot) => {
+                        let filters = filter.borrow().slot_filters(Commitment::Processed);
+                        vec![(filters, UpdateOneof::Slot(SubscribeUpdateSlot {
+                            slot: slot.processed_slot,
+                            parent: None,
+                            status: CommitmentLevel::Processed as i32,
+                        }))]
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+                block = blocks.recv() => match block {
+                    Ok(block) => {
+                        let commitment = Commitment::from(block.commitment_config);
+                        let filter = filter.borrow();
+                        let mut updates = vec![];
+                        // processed slots come from the slot stream
+                        if commitment != Commitment::Processed {
+                            updates.push((filter.slot_filters(commitment), UpdateOneof::Slot(SubscribeUpdateSlot {
+                                slot: block.slot,
+                                parent: Some(block.parent_slot),
+                                status: commitment_level(commitment) as i32,
+                            })));
+                        }
+                        if commitment == filter.commitment {
+                            updates.push((filter.blocks_meta.clone(), UpdateOneof::BlockMeta(block_meta_update(&block))));
+                            updates.extend(filter.block_updates(&block));
+                        }
+                        updates
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+                account = async { accounts.as_mut().unwrap().recv().await }, if accounts.is_some() => match account {
+                    Ok(account) => {
+                        let filters = filter.borrow().account_filters(&account);
+                        vec![(filters, UpdateOneof::Account(account_update(&account)))]
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => {
+                        accounts = None;
+                        continue;
+                    }
+                },
+                _ = sender.closed() => break,
+                _ = shutdown.cancelled() => {
+                    let _ = sender.try_send(Err(Status::unavailable("server is shutting down")));
+                    break;
+                }
+            };
+
+            for (filters, update) in updates {
+                if filters.is_empty() {
+                    continue;
+                }
+                let update = SubscribeUpdate {
+                    filters,
+                    update_oneof: Some(update),
+                };
+                match sender.try_send(Ok(update)) {
+                    Ok(()) => GEYSER_UPDATES_SENT.inc(),
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        warn!("geyser subscriber too slow, closing the subscription");
+                        let _ = sender
+                            .send(Err(Status::resource_exhausted("subscriber too slow")))
+                            .await;
+                        return;
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => return,
+                }
+            }
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Geyser for GeyserReexportService {
+    type SubscribeStream = SubscribeStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<Streaming<SubscribeRequest>>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let mut requests = request.into_inner();
+        let (sender, receiver) = mpsc::channel(SUBSCRIPTION_BUFFER);
+        let (filter_sender, filter) = watch::channel(SubscriptionFilter::default());
+
+        // a new subscribe request on the stream replaces the filters, a ping only gets a pong
+        let reply_sender = sender.clone();
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            loop {
+                let request = tokio::select! {
+                    request = requests.next() => match request {
+                        Some(request) => request,
+                        None => break,
+                    },
+                    _ = shutdown.cancelled() => break,
+                };
+                if let Some(pong) = request.as_ref().ok().and_then(pong) {
+                    if reply_sender.send(Ok(pong)).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+                let filter = match request.map(SubscriptionFilter::parse) {
+                    Ok(Ok(filter)) => filter,
+                    Ok(Err(status)) => {
+                        let _ = reply_sender.send(Err(status)).await;
+                        break;
+                    }
+                    Err(status) => {
+                        debug!("geyser subscription closed by client: {status}");
+                        break;
+                    }
+                };
+                if filter_sender.send(filter).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let blocks = self.blocks_notifier.resubscribe();
+        let slots = self.slot_notifier.resubscribe();
+        let accounts = self
+            .account_stream
+            .as_ref()
+            .map(|account_stream| account_stream.resubscribe());
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            GEYSER_SUBSCRIPTIONS.inc();
+            Self::forward_updates(blocks, slots, accounts, filter, sender, shutdown).await;
+            GEYSER_SUBSCRIPTIONS.dec();
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+
+    async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
+        Ok(Response::new(PongResponse {
+            count: request.into_inner().count,
+        }))
+    }
+
+    async fn get_latest_blockhash(
+        &self,
+        request: Request<GetLatestBlockhashRequest>,
+    ) -> Result<Response<GetLatestBlockhashResponse>, Status> {
+        let commitment_config = commitment_config(request.into_inner().commitment)?;
+        let block_info = self
+            .data_cache
+            .block_information_store
+            .get_latest_block(commitment_config)
+            .await;
+        Ok(Response::new(GetLatestBlockhashResponse {
+            slot: block_info.slot,
+            blockhash: block_info.blockhash.to_string(),
+            last_valid_block_height: self
+                .data_cache
+                .blockhash_cache
+                .last_valid_block_height(&block_info.blockhash, block_info.block_height),
+        }))
+    }
+
+    async fn get_block_height(
+        &self,
+        request: Request<GetBlockHeightRequest>,
+    ) -> Result<Response<GetBlockHeightResponse>, Status> {
+        let commitment_config = commitment_config(request.into_inner().commitment)?;
+        let block_info = self
+            .data_cache
+            .block_information_store
+            .get_latest_block(commitment_config)
+            .await;
+        Ok(Response::new(GetBlockHeightResponse {
+            block_height: block_info.block_height,
+        }))
+    }
+
+    async fn get_slot(
+        &self,
+        request: Request<GetSlotRequest>,
+    ) -> Result<Response<GetSlotResponse>, Status> {
+        let commitment_config = commitment_config(request.into_inner().commitment)?;
+        let block_info = self
+            .data_cache
+            .block_information_store
+            .get_latest_block(commitment_config)
+            .await;
+        Ok(Response::new(GetSlotResponse {
+            slot: block_info.slot,
+        }))
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        request: Request<IsBlockhashValidRequest>,
+    ) -> Result<Response<IsBlockhashValidResponse>, Status> {
+        let request = request.into_inner();
+        let commitment_config = commitment_config(request.commitment)?;
+        let blockhash = Hash::from_str(&request.blockhash).map_err(|_| {
+            Status::invalid_argument(format!("invalid blockhash {}", request.blockhash))
+        })?;
+        let slot = self
+            .data_cache
+            .block_information_store
+            .get_latest_block(commitment_config)
+            .await
+            .slot;
+        let valid = self
+            .data_cache
+            .blockhash_cache
+            .is_valid(&blockhash, commitment_config);
+        Ok(Response::new(IsBlockhashValidResponse { slot, valid }))
+    }
+
+    async fn get_version(
+        &self,
+        _request: Request<GetVersionRequest>,
+    ) -> Result<Response<GetVersionResponse>, Status> {
+        Ok(Response::new(GetVersionResponse {
+            version: format!(
+                "lite-rpc {} (solana {})",
+                env!("CARGO_PKG_VERSION"),
+                solana_version::Version::default()
+            ),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use solana_lite_rpc_core::structures::{
+        produced_block::TransactionMessage, slot_notification::SlotNotification,
+    };
+    use solana_sdk::{
+        instruction::InstructionError, message::Message, system_instruction,
+        transaction::TransactionError,
+    };
+    use tokio::sync::broadcast;
+    use yellowstone_grpc_proto::geyser::{SubscribeRequestFilterBlocksMeta, SubscribeRequestPing};
+
+    use super::*;
+
+    fn transaction(payer: Pubkey, recipient: Pubkey, is_vote: bool) -> TransactionInfo {
+        let message = Message::new(
+            &[system_instruction::transfer(&payer, &recipient, 1)],
+            Some(&payer),
+        );
+        TransactionInfo {
+            signature: Signature::new_unique(),
+            is_vote,
+            err: None,
+            cu_requested: None,
+            prioritization_fees: None,
+            cu_consumed: Some(150),
+            recent_blockhash: Hash::default(),
+            message: TransactionMessage::new(
+                bincode::serialize(&VersionedMessage::Legacy(message)).unwrap(),
+            ),
+            writable_accounts: vec![payer, recipient],
+            readable_accounts: vec![solana_sdk::system_program::id()],
+            address_lookup_tables: vec![],
+            program_ids: vec![solana_sdk::system_program::id()],
+        }
+    }
+
+    fn block(slot: u64, transactions: Vec<TransactionInfo>) -> ProducedBlock {
+        ProducedBlock {
+            transactions,
+            ..ProducedBlock::new_for_tests(slot, CommitmentConfig::confirmed())
+        }
+    }
+
+    fn transactions_filter(
+        account_include: &[Pubkey],
+        account_exclude: &[Pubkey],
+    ) -> SubscribeRequestFilterTransactions {
+        SubscribeRequestFilterTransactions {
+            vote: Some(false),
+            account_include: account_include.iter().map(Pubkey::to_string).collect(),
+            account_exclude: account_exclude.iter().map(Pubkey::to_string).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_a_ping_is_answered_and_keeps_the_filters() {
+        let ping = SubscribeRequest {
+            ping: Some(SubscribeRequestPing { id: 7 }),
+            ..Default::default()
+        };
+        let update = pong(&ping).unwrap();
+        assert!(matches!(
+            update.update_oneof,
+            Some(UpdateOneof::Pong(SubscribeUpdatePong { id: 7 }))
+        ));
+        assert!(pong(&SubscribeRequest::default()).is_none());
+    }
+
+    #[test]
+    fn test_transaction_filters() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let tx = transaction(payer, recipient, false);
+
+        let matches = |filter: SubscribeRequestFilterTransactions| {
+            TransactionsFilter::parse(filter).unwrap().matches(&tx)
+        };
+        assert!(matches(transactions_filter(&[], &[])));
+        assert!(matches(transactions_filter(&[recipient], &[])));
+        assert!(!matches(transactions_filter(&[Pubkey::new_unique()], &[])));
+        assert!(!matches(transactions_filter(&[recipient], &[payer])));
+        assert!(!matches(SubscribeRequestFilterTransactions {
+            vote: Some(true),
+            ..Default::default()
+        }));
+        assert!(!matches(SubscribeRequestFilterTransactions {
+            failed: Some(true),
+            ..Default::default()
+        }));
+        assert!(matches(SubscribeRequestFilterTransactions {
+            signature: Some(tx.signature.to_string()),
+            account_required: vec![payer.to_string(), recipient.to_string()],
+            ..Default::default()
+        }));
+        assert!(!matches(SubscribeRequestFilterTransactions {
+            account_required: vec![payer.to_string(), Pubkey::new_unique().to_string()],
+            ..Default::default()
+        }));
+        assert!(
+            TransactionsFilter::parse(SubscribeRequestFilterTransactions {
+                signature: Some("invalid".to_string()),
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_block_accounts_and_entries_are_unimplemented() {
+        let status = BlocksFilter::parse(SubscribeRequestFilterBlocks {
+            include_accounts: Some(true),
+            ..Default::default()
+        })
+        .err()
+        .unwrap();
+        assert_eq!(status.code(), tonic::Code::Unimplemented);
+    }
+
+    #[test]
+    fn test_block_and_transaction_updates() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let mut failed = transaction(payer, Pubkey::new_unique(), false);
+        failed.err = Some(TransactionError::InstructionError(
+            0,
+            InstructionError::InsufficientFunds,
+        ));
+        let block = block(
+            42,
+            vec![
+                transaction(Pubkey::new_unique(), Pubkey::new_unique(), true),
+                transaction(payer, recipient, false),
+                failed,
+            ],
+        );
+        let filter = SubscriptionFilter::parse(SubscribeRequest {
+            blocks: HashMap::from([(
+                "blocks".to_string(),
+                SubscribeRequestFilterBlocks {
+                    account_include: vec![payer.to_string()],
+                    ..Default::default()
+                },
+            )]),
+            transactions: HashMap::from([(
+                "transfers".to_string(),
+                transactions_filter(&[recipient], &[]),
+            )]),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let updates = filter.block_updates(&block);
+        assert_eq!(updates.len(), 2);
+        let (filters, UpdateOneof::Block(block_update)) = &updates[0] else {
+            panic!("expected a block update");
+        };
+        assert_eq!(filters, &["blocks"]);
+        assert_eq!(block_update.slot, 42);
+        assert_eq!(block_update.executed_transaction_count, 3);
+        assert_eq!(
+            block_update
+                .transactions
+                .iter()
+                .map(|tx| tx.index)
+                .collect::<Vec<_>>(),
+            [1, 2]
+        );
+        let failed_meta = block_update.transactions[1].meta.as_ref().unwrap();
+        assert_eq!(
+            bincode::deserialize::<TransactionError>(&failed_meta.err.as_ref().unwrap().err)
+                .unwrap(),
+            block.transactions[2].err.clone().unwrap()
+        );
+
+        let (filters, UpdateOneof::Transaction(transaction_update)) = &updates[1] else {
+            panic!("expected a transaction update");
+        };
+        assert_eq!(filters, &["transfers"]);
+        let info = transaction_update.transaction.as_ref().unwrap();
+        assert_eq!(info.signature, block.transactions[1].signature.as_ref());
+        assert_eq!(info.index, 1);
+        let message = info.transaction.as_ref().unwrap().message.as_ref().unwrap();
+        assert!(!message.versioned);
+        assert_eq!(message.account_keys[0], payer.to_bytes());
+        assert_eq!(message.account_keys[1], recipient.to_bytes());
+        assert_eq!(message.instructions.len(), 1);
+        assert_eq!(message.header.as_ref().unwrap().num_required_signatures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_updates_of_the_subscribed_commitment_are_forwarded() {
+        let (blocks_sender, blocks) = broadcast::channel(8);
+        let (_slots_sender, slots) = broadcast::channel::<SlotNotification>(8);
+        let (filter_sender, filter) = watch::channel(SubscriptionFilter::default());
+        let (sender, mut receiver) = mpsc::channel(8);
+        filter_sender
+            .send(
+                SubscriptionFilter::parse(SubscribeRequest {
+                    commitment: Some(CommitmentLevel::Confirmed as i32),
+                    blocks_meta: HashMap::from([(
+                        "metas".to_string(),
+                        SubscribeRequestFilterBlocksMeta::default(),
+                    )]),
+                    ..Default::default()
+                })
+                .unwrap(),
+            )
+            .unwrap();
+        let forward = tokio::spawn(GeyserReexportService::forward_updates(
+            blocks,
+            slots,
+            None,
+            filter,
+            sender,
+            CancellationToken::new(),
+        ));
+
+        let mut processed = block(41, vec![]);
+        processed.commitment_config = CommitmentConfig::processed();
+        blocks_sender.send(processed).unwrap();
+        blocks_sender.send(block(42, vec![])).unwrap();
+
+        // the confirmed slot is not subscribed, only the block meta of the confirmed block
+        let update = receiver.recv().await.unwrap().unwrap();
+        assert_eq!(update.filters, ["metas"]);
+        let Some(UpdateOneof::BlockMeta(meta)) = update.update_oneof else {
+            panic!("expected a block meta");
+        };
+        assert_eq!(meta.slot, 42);
+
+        drop(receiver);
+        drop(blocks_sender);
+        forward.await.unwrap();
+    }
+}
@@ -0,0 +1,635 @@
+use std::{
+    future::Future,
+    ops::RangeInclusive,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use async_graphql::{
+    http::parse_query_string, BatchRequest, EmptyMutation, EmptySubscription, Enum, Object, Schema,
+    SimpleObject,
+};
+use futures::StreamExt;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_account_decoder::{UiAccountData, UiAccountEncoding, UiDataSliceConfig};
+#[cfg(feature = "accounts")]
+use solana_lite_rpc_accounts::account_service::AccountService;
+use solana_lite_rpc_blockstore::history::History;
+use solana_lite_rpc_core::structures::produced_block::{ProducedBlock, TransactionInfo};
+use solana_rpc_client_api::{
+    config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionConfig},
+    response::RpcKeyedAccount,
+};
+use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    pubkey::Pubkey,
+    signature::Signature,
+    slot_history::Slot,
+};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    UiTransactionEncoding,
+};
+use tower::{Layer, Service};
+
+use crate::{block_range::BlockRangeReader, utils::text_response};
+
+lazy_static::lazy_static! {
+    static ref GRAPHQL_REQUESTS: IntCounter =
+        register_int_counter!(opts!("literpc_graphql_requests", "GraphQL requests served")).unwrap();
+    static ref GRAPHQL_ERRORS: IntCounter =
+        register_int_counter!(opts!("literpc_graphql_errors", "GraphQL requests answered with errors")).unwrap();
+}
+
+/// POST a GraphQL request or a batch of requests, or GET with `query` and `variables` parameters
+pub const GRAPHQL_PATH: &str = "/graphql";
+
+const MAX_REQUEST_BODY_SIZE: usize = 1024 * 1024;
+// slots a single blocks or transactionsByAddress query may scan
+const MAX_SLOT_RANGE: u64 = 1000;
+const MAX_QUERY_DEPTH: usize = 8;
+// the complexity of a list field is the complexity of its items times the list size
+const MAX_QUERY_COMPLEXITY: usize = 10_000;
+const MAX_TRANSACTIONS_LIMIT: usize = 1000;
+const MAX_ACCOUNTS_LIMIT: usize = 1000;
+// the transactions of a block are not known before it is loaded
+const ESTIMATED_BLOCK_TRANSACTIONS: usize = 1000;
+const MAX_BATCH_SIZE: usize = 16;
+
+pub type GraphQlSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl From<Commitment> for CommitmentConfig {
+    fn from(commitment: Commitment) -> Self {
+        match commitment {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+impl From<CommitmentLevel> for Commitment {
+    fn from(commitment: CommitmentLevel) -> Self {
+        match commitment {
+            CommitmentLevel::Finalized => Commitment::Finalized,
+            CommitmentLevel::Confirmed => Commitment::Confirmed,
+            _ => Commitment::Processed,
+        }
+    }
+}
+
+pub struct Block(Arc<ProducedBlock>);
+
+#[Object]
+impl Block {
+    async fn slot(&self) -> Slot {
+        self.0.slot
+    }
+
+    async fn block_height(&self) -> u64 {
+        self.0.block_height
+    }
+
+    async fn blockhash(&self) -> String {
+        self.0.blockhash.to_string()
+    }
+
+    async fn previous_blockhash(&self) -> String {
+        self.0.previous_blockhash.to_string()
+    }
+
+    async fn parent_slot(&self) -> Slot {
+        self.0.parent_slot
+    }
+
+    async fn block_time(&self) -> u64 {
+        self.0.block_time
+    }
+
+    async fn commitment(&self) -> Commitment {
+        self.0.commitment_config.commitment.into()
+    }
+
+    async fn leader(&self) -> Option<String> {
+        self.0.leader_id.map(|leader_id| leader_id.to_string())
+    }
+
+    async fn transaction_count(&self) -> usize {
+        self.0.transactions.len()
+    }
+
+    #[graphql(complexity = "ESTIMATED_BLOCK_TRANSACTIONS * child_complexity")]
+    async fn transactions(&self, #[graphql(default)] include_votes: bool) -> Vec<Transaction> {
+        self.0
+            .transactions
+            .iter()
+            .filter(|transaction| include_votes || !transaction.is_vote)
+            .map(|transaction| Transaction::from_block(&self.0, transaction))
+            .collect()
+    }
+}
+
+/// compute unit and prioritization fee fields are only known for blocks seen by lite-rpc
+#[derive(SimpleObject)]
+pub struct Transaction {
+    signature: String,
+    slot: Slot,
+    block_time: Option<i64>,
+    is_vote: bool,
+    err: Option<String>,
+    cu_requested: Option<u32>,
+    cu_consumed: Option<u64>,
+    prioritization_fees: Option<u64>,
+    writable_accounts: Vec<String>,
+    readable_accounts: Vec<String>,
+    program_ids: Vec<String>,
+}
+
+impl Transaction {
+    fn from_block(block: &ProducedBlock, transaction: &TransactionInfo) -> Self {
+        let to_strings = |pubkeys: &[Pubkey]| pubkeys.iter().map(Pubkey::to_string).collect();
+        Self {
+            signature: transaction.signature.to_string(),
+            slot: block.slot,
+            block_time: Some(block.block_time as i64),
+            is_vote: transaction.is_vote,
+            err: transaction.err.as_ref().map(ToString::to_string),
+            cu_requested: transaction.cu_requested,
+            cu_consumed: transaction.cu_consumed,
+            prioritization_fees: transaction.prioritization_fees,
+            writable_accounts: to_strings(&transaction.writable_accounts),
+            readable_accounts: to_strings(&transaction.readable_accounts),
+            program_ids: to_strings(&transaction.program_ids),
+        }
+    }
+
+    // from a base64 encoded transaction of the history
+    fn from_history(transaction: EncodedConfirmedTransactionWithStatusMeta) -> Option<Self> {
+        let meta = transaction.transaction.meta;
+        let versioned_transaction = transaction.transaction.transaction.decode()?;
+        let message = versioned_transaction.message;
+        let account_keys = message.static_account_keys();
+
+        let mut writable_accounts = vec![];
+        let mut readable_accounts = vec![];
+        for (index, account) in account_keys.iter().enumerate() {
+            if message.is_maybe_writable(index) {
+                writable_accounts.push(account.to_string());
+            } else {
+                readable_accounts.push(account.to_string());
+            }
+        }
+        if let Some(OptionSerializer::Some(loaded_addresses)) =
+            meta.as_ref().map(|meta| &meta.loaded_addresses)
+        {
+            writable_accounts.extend(loaded_addresses.writable.iter().cloned());
+            readable_accounts.extend(loaded_addresses.readonly.iter().cloned());
+        }
+        let program_ids = message
+            .instructions()
+            .iter()
+            .filter_map(|instruction| account_keys.get(instruction.program_id_index as usize))
+            .collect::<Vec<_>>();
+
+        Some(Self {
+            signature: versioned_transaction.signatures.first()?.to_string(),
+            slot: transaction.slot,
+            block_time: transaction.block_time,
+            is_vote: program_ids
+                .iter()
+                .any(|program_id| **program_id == solana_sdk::vote::program::id()),
+            err: meta
+                .as_ref()
+                .and_then(|meta| meta.err.as_ref())
+                .map(ToString::to_string),
+            cu_requested: None,
+            cu_consumed: meta.and_then(|meta| meta.compute_units_consumed.into()),
+            prioritization_fees: None,
+            writable_accounts,
+            readable_accounts,
+            program_ids: program_ids.iter().map(ToString::to_string).collect(),
+        })
+    }
+
+    fn mentions(transaction: &TransactionInfo, address: &Pubkey) -> bool {
+        transaction.writable_accounts.contains(address)
+            || transaction.readable_accounts.contains(address)
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Account {
+    pubkey: String,
+    owner: String,
+    lamports: u64,
+    executable: bool,
+    rent_epoch: u64,
+    space: Option<u64>,
+    /// base64 encoded, only if requested with includeData
+    data: Option<String>,
+}
+
+/// read only queries over the blocks known to lite-rpc, the history and the account store
+pub struct QueryRoot {
+    block_range_reader: BlockRangeReader,
+    history: History,
+    #[cfg(feature = "accounts")]
+    accounts_service: Option<AccountService>,
+}
+
+impl QueryRoot {
+    pub fn new(block_range_reader: BlockRangeReader, history: History) -> Self {
+        Self {
+            block_range_reader,
+            history,
+            #[cfg(feature = "accounts")]
+            accounts_service: None,
+        }
+    }
+
+    /// serve accountsByOwner from the account store
+    #[cfg(feature = "accounts")]
+    pub fn with_accounts_service(mut self, accounts_service: AccountService) -> Self {
+        self.accounts_service = Some(accounts_service);
+        self
+    }
+
+    pub fn schema(self) -> GraphQlSchema {
+        Schema::build(self, EmptyMutation, EmptySubscription)
+            .limit_depth(MAX_QUERY_DEPTH)
+            .limit_complexity(MAX_QUERY_COMPLEXITY)
+            .finish()
+    }
+
+    async fn blocks_in_range(
+        &self,
+        slots: RangeInclusive<Slot>,
+        commitment_config: CommitmentConfig,
+    ) -> async_graphql::Result<Vec<Arc<ProducedBlock>>> {
+        if slots.is_empty() || slots.end() - slots.start() >= MAX_SLOT_RANGE {
+            return Err(format!("slot range must contain 1 to {MAX_SLOT_RANGE} slots").into());
+        }
+        Ok(self
+            .block_range_reader
+            .stream(slots, commitment_config)
+            .await
+            .collect()
+            .await)
+    }
+
+    #[cfg(feature = "accounts")]
+    async fn program_accounts(
+        &self,
+        owner: Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> async_graphql::Result<Vec<RpcKeyedAccount>> {
+        let Some(accounts_service) = &self.accounts_service else {
+            return Err("account streaming is not enabled".into());
+        };
+        let (_, accounts) = accounts_service
+            .get_program_accounts(owner, Some(config))
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok(accounts)
+    }
+
+    #[cfg(not(feature = "accounts"))]
+    async fn program_accounts(
+        &self,
+        _owner: Pubkey,
+        _config: RpcProgramAccountsConfig,
+    ) -> async_graphql::Result<Vec<RpcKeyedAccount>> {
+        Err("lite-rpc was built without the accounts feature".into())
+    }
+}
+
+#[Object]
+impl QueryRoot {
+    async fn block(
+        &self,
+        slot: Slot,
+        #[graphql(default_with = "Commitment::Confirmed")] commitment: Commitment,
+    ) -> async_graphql::Result<Option<Block>> {
+        let mut blocks = self.blocks_in_range(slot..=slot, commitment.into()).await?;
+        Ok(blocks.pop().map(Block))
+    }
+
+    /// blocks of at most 1000 slots known to lite-rpc, ascending by slot
+    #[graphql(complexity = "slot_range_size(from_slot, to_slot) * child_complexity")]
+    async fn blocks(
+        &self,
+        from_slot: Slot,
+        to_slot: Slot,
+        #[graphql(default_with = "Commitment::Confirmed")] commitment: Commitment,
+    ) -> async_graphql::Result<Vec<Block>> {
+        let blocks = self
+            .blocks_in_range(from_slot..=to_slot, commitment.into())
+            .await?;
+        Ok(blocks.into_iter().map(Block).collect())
+    }
+
+    /// transaction from the rpc node or faithful_history
+    async fn transaction(
+        &self,
+        signature: String,
+        #[graphql(default_with = "Commitment::Confirmed")] commitment: Commitment,
+    ) -> async_graphql::Result<Option<Transaction>> {
+        let signature = Signature::from_str(&signature)?;
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(commitment.into()),
+            max_supported_transaction_version: Some(0),
+        };
+        let transaction = self
+            .history
+            .get_transaction(&signature, config)
+            .await
+            .map_err(|err| format!("transaction {signature} not available: {err}"))?;
+        Ok(transaction.and_then(Transaction::from_history))
+    }
+
+    /// at most 1000 transactions reading or writing the address in blocks of at most 1000 slots
+    /// known to lite-rpc, ascending by slot
+    #[graphql(complexity = "limit.min(MAX_TRANSACTIONS_LIMIT) * child_complexity")]
+    async fn transactions_by_address(
+        &self,
+        address: String,
+        from_slot: Slot,
+        to_slot: Slot,
+        #[graphql(default_with = "Commitment::Confirmed")] commitment: Commitment,
+        #[graphql(default)] include_votes: bool,
+        #[graphql(default = 100)] limit: usize,
+    ) -> async_graphql::Result<Vec<Transaction>> {
+        let address = Pubkey::from_str(&address)?;
+        let blocks = self
+            .blocks_in_range(from_slot..=to_slot, commitment.into())
+            .await?;
+        Ok(blocks
+            .iter()
+            .flat_map(|block| {
+                block
+                    .transactions
+                    .iter()
+                    .filter(|transaction| include_votes || !transaction.is_vote)
+                    .filter(|transaction| Transaction::mentions(transaction, &address))
+                    .map(|transaction| Transaction::from_block(block, transaction))
+            })
+            .take(limit.min(MAX_TRANSACTIONS_LIMIT))
+            .collect())
+    }
+
+    /// at most 1000 accounts of the owner from the account store, the owner has to be covered by
+    /// the account filters
+    #[graphql(complexity = "limit.min(MAX_ACCOUNTS_LIMIT) * child_complexity")]
+    async fn accounts_by_owner(
+        &self,
+        owner: String,
+        #[graphql(default_with = "Commitment::Confirmed")] commitment: Commitment,
+        #[graphql(default)] include_data: bool,
+        #[graphql(default = 100)] limit: usize,
+    ) -> async_graphql::Result<Vec<Account>> {
+        let owner = Pubkey::from_str(&owner)?;
+        let config = RpcProgramAccountsConfig {
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                data_slice: (!include_data).then_some(UiDataSliceConfig {
+                    offset: 0,
+                    length: 0,
+                }),
+                commitment: Some(commitment.into()),
+                min_context_slot: None,
+            },
+            ..Default::default()
+        };
+        let accounts = self.program_accounts(owner, config).await?;
+        Ok(accounts
+            .into_iter()
+            .take(limit.min(MAX_ACCOUNTS_LIMIT))
+            .map(|keyed_account| {
+                let account = keyed_account.account;
+                Account {
+                    pubkey: keyed_account.pubkey,
+                    owner: account.owner,
+                    lamports: account.lamports,
+                    executable: account.executable,
+                    rent_epoch: account.rent_epoch,
+                    space: account.space,
+                    data: match account.data {
+                        UiAccountData::Binary(data, _) if include_data => Some(data),
+                        _ => None,
+                    },
+                }
+            })
+            .collect())
+    }
+}
+
+/// tower layer serving the GraphQL endpoint in front of the json rpc http server, does nothing if
+/// not configured
+#[derive(Clone)]
+pub struct GraphQlLayer {
+    schema: Option<GraphQlSchema>,
+}
+
+impl GraphQlLayer {
+    pub fn new(schema: Option<GraphQlSchema>) -> Self {
+        Self { schema }
+    }
+}
+
+impl<S> Layer<S> for GraphQlLayer {
+    type Service = GraphQlService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GraphQlService {
+            inner,
+            schema: self.schema.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GraphQlService<S> {
+    inner: S,
+    schema: Option<GraphQlSchema>,
+}
+
+impl<S> Service<Request<Body>> for GraphQlService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let schema = match &self.schema {
+            Some(schema) if request.uri().path() == GRAPHQL_PATH => schema.clone(),
+            _ => return Box::pin(self.inner.call(request)),
+        };
+
+        match *request.method() {
+            Method::POST => Box::pin(async move { Ok(execute_post(schema, request).await) }),
+            Method::GET => {
+                let query = request.uri().query().unwrap_or_default().to_string();
+                Box::pin(async move {
+                    let graphql_request = match parse_query_string(&query) {
+                        Ok(graphql_request) => graphql_request,
+                        Err(e) => return Ok(text_response(StatusCode::BAD_REQUEST, e.to_string())),
+                    };
+                    Ok(execute(schema, BatchRequest::Single(graphql_request)).await)
+                })
+            }
+            _ => Box::pin(async move {
+                Ok(text_response(
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    "use GET or POST".to_string(),
+                ))
+            }),
+        }
+    }
+}
+
+async fn execute_post(schema: GraphQlSchema, request: Request<Body>) -> Response<Body> {
+    let content_length = request
+        .headers()
+        .get(hyper::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.map_or(false, |length| length > MAX_REQUEST_BODY_SIZE) {
+        return text_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("body exceeds {MAX_REQUEST_BODY_SIZE} bytes"),
+        );
+    }
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) if body.len() <= MAX_REQUEST_BODY_SIZE => body,
+        Ok(_) => {
+            return text_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("body exceeds {MAX_REQUEST_BODY_SIZE} bytes"),
+            )
+        }
+        Err(e) => return text_response(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    match serde_json::from_slice::<BatchRequest>(&body) {
+        Ok(BatchRequest::Batch(requests)) if requests.len() > MAX_BATCH_SIZE => text_response(
+            StatusCode::BAD_REQUEST,
+            format!("batch exceeds {MAX_BATCH_SIZE} requests"),
+        ),
+        Ok(batch_request) => execute(schema, batch_request).await,
+        Err(e) => text_response(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+// slots of the range, an empty or too long range fails before any block is loaded
+fn slot_range_size(from_slot: Slot, to_slot: Slot) -> usize {
+    to_slot
+        .saturating_sub(from_slot)
+        .saturating_add(1)
+        .min(MAX_SLOT_RANGE) as usize
+}
+
+async fn execute(schema: GraphQlSchema, batch_request: BatchRequest) -> Response<Body> {
+    GRAPHQL_REQUESTS.inc();
+    let batch_response = schema.execute_batch(batch_request).await;
+    if !batch_response.is_ok() {
+        GRAPHQL_ERRORS.inc();
+    }
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            serde_json::to_vec(&batch_response).expect("graphql response serializes"),
+        ))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_lite_rpc_core::stores::data_cache::DataCache;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+
+    use super::*;
+
+    fn schema() -> GraphQlSchema {
+        let history = History::new(
+            Arc::new(RpcClient::new("http://127.0.0.1:1".to_string())),
+            None,
+        );
+        QueryRoot::new(
+            BlockRangeReader::new(DataCache::new_for_tests(), None),
+            history,
+        )
+        .schema()
+    }
+
+    fn post(body: String) -> Request<Body> {
+        Request::post(GRAPHQL_PATH).body(Body::from(body)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_batches_are_capped() {
+        let query = r#"{"query": "{ blocks(fromSlot: 1, toSlot: 2) { slot } }"}"#;
+        let batch = |size: usize| format!("[{}]", vec![query; size].join(","));
+
+        let response = execute_post(schema(), post(batch(MAX_BATCH_SIZE))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = execute_post(schema(), post(batch(MAX_BATCH_SIZE + 1))).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_list_fields_are_weighed_by_their_size() {
+        let schema = schema();
+        // 10 slots with 3 fields each
+        let response = schema
+            .execute("{ blocks(fromSlot: 1, toSlot: 10) { slot blockhash parentSlot } }")
+            .await;
+        assert!(response.is_ok(), "{:?}", response.errors);
+
+        // 1000 slots with 11 fields each
+        let response = schema
+            .execute(
+                "{ blocks(fromSlot: 1, toSlot: 1000) { slot blockHeight blockhash previousBlockhash \
+                 parentSlot blockTime commitment leader transactionCount slot2: slot blockhash2: blockhash } }",
+            )
+            .await;
+        assert!(!response.is_ok());
+
+        // the transactions of the blocks of 10 slots
+        let response = schema
+            .execute("{ blocks(fromSlot: 1, toSlot: 10) { transactions { signature slot } } }")
+            .await;
+        assert!(!response.is_ok());
+
+        let response = schema
+            .execute(
+                r#"{ transactionsByAddress(address: "11111111111111111111111111111111", fromSlot: 1, toSlot: 10) { signature slot } }"#,
+            )
+            .await;
+        assert!(response.is_ok(), "{:?}", response.errors);
+        let response = schema
+            .execute(
+                r#"{ transactionsByAddress(address: "11111111111111111111111111111111", fromSlot: 1, toSlot: 10, limit: 1000) { signature slot isVote err cuConsumed writableAccounts readableAccounts programIds blockTime cuRequested prioritizationFees } }"#,
+            )
+            .await;
+        assert!(!response.is_ok());
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use async_trait::async_trait;
+use futures::Stream;
+use log::{debug, info, warn};
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_core::{
+    request_id::with_api_key_id, stores::data_cache::DataCache,
+    traits::subscription_sink::SubscriptionSink, AnyhowJoinHandle,
+};
+use solana_lite_rpc_services::transaction_service::{NodeDraining, TransactionService};
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature, slot_history::Slot};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tokio_util::sync::CancellationToken;
+use tonic::{metadata::MetadataMap, Request, Response, Status, Streaming};
+
+use crate::{api_key_auth::ApiKeys, health_endpoint::HealthState};
+
+use self::proto::{
+    transaction_submission_server::{TransactionSubmission, TransactionSubmissionServer},
+    SendTransactionRequest, SendTransactionResponse, TransactionStatus, TransactionStatusUpdate,
+};
+
+pub mod proto {
+    tonic::include_proto!("lite_rpc");
+}
+
+lazy_static::lazy_static! {
+    static ref GRPC_SEND_TX: IntCounter =
+        register_int_counter!(opts!("literpc_grpc_send_tx", "Transactions submitted over gRPC")).unwrap();
+    static ref GRPC_SEND_TX_REJECTED: IntCounter =
+        register_int_counter!(opts!("literpc_grpc_send_tx_rejected", "Transactions submitted over gRPC which were rejected")).unwrap();
+    static ref GRPC_STATUS_STREAMS_OVERFLOWED: IntCounter =
+        register_int_counter!(opts!("literpc_grpc_status_streams_overflowed", "gRPC transaction streams closed because the client did not read its status updates")).unwrap();
+}
+
+// status updates buffered per stream, a client which lets the buffer fill up loses its stream
+const STATUS_UPDATE_BUFFER: usize = 1024;
+
+#[derive(Clone)]
+pub struct GrpcTransactionService {
+    transaction_service: TransactionService,
+    data_cache: DataCache,
+    health_state: HealthState,
+    api_keys: Option<ApiKeys>,
+}
+
+impl GrpcTransactionService {
+    pub fn new(
+        transaction_service: TransactionService,
+        data_cache: DataCache,
+        health_state: HealthState,
+    ) -> Self {
+        Self {
+            transaction_service,
+            data_cache,
+            health_state,
+            api_keys: None,
+        }
+    }
+
+    /// requests have to carry a known key in the `x-api-key` metadata, the per key fee limits and
+    /// dry-run mode apply to the transactions sent with it
+    pub fn with_api_keys(mut self, api_keys: Option<ApiKeys>) -> Self {
+        self.api_keys = api_keys;
+        self
+    }
+
+    /// on shutdown no new requests are accepted and the task ends once the requests in flight are answered
+    pub fn start(self, addr: SocketAddr, shutdown: CancellationToken) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            info!("gRPC transaction service started at {addr:?}");
+            let stopped = shutdown.clone();
+            tonic::transport::Server::builder()
+                .add_service(TransactionSubmissionServer::new(self))
+                .serve_with_shutdown(addr, async move { stopped.cancelled().await })
+                .await?;
+            if !shutdown.is_cancelled() {
+                anyhow::bail!("gRPC transaction service stopped");
+            }
+            Ok(())
+        })
+    }
+
+    async fn ensure_caught_up(&self) -> Result<(), Status> {
+        self.health_state
+            .is_caught_up()
+            .await
+            .map_err(|reason| Status::unavailable(format!("node is starting: {reason}")))
+    }
+
+    /// the id of the api key of the request, `None` if no api keys are configured
+    fn authenticate(&self, metadata: &MetadataMap) -> Result<Option<String>, Status> {
+        self.api_keys
+            .as_ref()
+            .map(|api_keys| api_keys.grpc_key_id(metadata))
+            .transpose()
+    }
+
+    async fn submit(&self, request: SendTransactionRequest) -> Result<Signature, String> {
+        GRPC_SEND_TX.inc();
+        let max_retries = request.max_retries.map(|x| x.min(u16::MAX as u32) as u16);
+        self.transaction_service
+            .send_transaction(request.transaction, max_retries)
+            .await
+            .map_err(|e| {
+                GRPC_SEND_TX_REJECTED.inc();
+                e.to_string()
+            })
+    }
+}
+
+type StatusUpdateStream =
+    Pin<Box<dyn Stream<Item = Result<TransactionStatusUpdate, Status>> + Send + 'static>>;
+
+#[tonic::async_trait]
+impl TransactionSubmission for GrpcTransactionService {
+    type SendTransactionStreamStream = StatusUpdateStream;
+
+    async fn send_transaction(
+        &self,
+        request: Request<SendTransactionRequest>,
+    ) -> Result<Response<SendTransactionResponse>, Status> {
+        self.ensure_caught_up().await?;
+        if self.transaction_service.is_draining() {
+            return Err(Status::unavailable(NodeDraining.to_string()));
+        }
+        let api_key_id = self.authenticate(request.metadata())?;
+        match with_optional_api_key_id(api_key_id, self.submit(request.into_inner())).await {
+            Ok(signature) => Ok(Response::new(SendTransactionResponse {
+                signature: signature.to_string(),
+            })),
+            Err(e) => Err(Status::invalid_argument(e)),
+        }
+    }
+
+    async fn send_transaction_stream(
+        &self,
+        request: Request<Streaming<SendTransactionRequest>>,
+    ) -> Result<Response<Self::SendTransactionStreamStream>, Status> {
+        self.ensure_caught_up().await?;
+        let api_key_id = self.authenticate(request.metadata())?;
+        let mut requests = request.into_inner();
+        let (sender, receiver) = mpsc::channel(STATUS_UPDATE_BUFFER);
+        let overflowed = Arc::new(AtomicBool::new(false));
+        let this = self.clone();
+
+        tokio::spawn(async move {
+            while let Some(request) = requests.next().await {
+                if overflowed.load(Ordering::Relaxed) {
+                    let _ = sender
+                        .send(Err(Status::resource_exhausted(
+                            "status updates were not read, the stream is closed",
+                        )))
+                        .await;
+                    break;
+                }
+                let request = match request {
+                    Ok(request) => request,
+                    Err(status) => {
+                        debug!("gRPC transaction stream closed by client: {status}");
+                        break;
+                    }
+                };
+                let request_id = request.request_id;
+                let update = match with_optional_api_key_id(
+                    api_key_id.clone(),
+                    this.submit(request),
+                )
+                .await
+                {
+                    Ok(signature) => {
+                        for (commitment_config, status) in [
+                            (CommitmentConfig::confirmed(), TransactionStatus::Confirmed),
+                            (CommitmentConfig::finalized(), TransactionStatus::Finalized),
+                        ] {
+                            this.data_cache.tx_subs.signature_subscribe(
+                                signature,
+                                commitment_config,
+                                Arc::new(GrpcStatusSink {
+                                    request_id,
+                                    signature: signature.to_string(),
+                                    status,
+                                    sender: sender.clone(),
+                                    overflowed: overflowed.clone(),
+                                }),
+                            );
+                        }
+                        TransactionStatusUpdate {
+                            request_id,
+                            signature: signature.to_string(),
+                            status: TransactionStatus::Accepted as i32,
+                            slot: 0,
+                            error: None,
+                        }
+                    }
+                    Err(error) => TransactionStatusUpdate {
+                        request_id,
+                        signature: String::new(),
+                        status: TransactionStatus::Rejected as i32,
+                        slot: 0,
+                        error: Some(error),
+                    },
+                };
+                if sender.send(Ok(update)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+}
+
+// the sent transactions are attributed to the api key like the ones sent over http
+async fn with_optional_api_key_id<F: Future>(api_key_id: Option<String>, future: F) -> F::Output {
+    match api_key_id {
+        Some(api_key_id) => with_api_key_id(api_key_id, future).await,
+        None => future.await,
+    }
+}
+
+/// forwards signature notifications from the subscription store into a gRPC response stream,
+/// never waits on the client: the store notifies the subscribers of every block in turn
+struct GrpcStatusSink {
+    request_id: u64,
+    signature: String,
+    status: TransactionStatus,
+    sender: mpsc::Sender<Result<TransactionStatusUpdate, Status>>,
+    // set once the buffer of the stream was full, the remaining subscriptions of the stream are dropped
+    overflowed: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl SubscriptionSink for GrpcStatusSink {
+    async fn send(&self, slot: Slot, message: serde_json::Value) {
+        let error = message
+            .get("err")
+            .filter(|err| !err.is_null())
+            .map(|err| err.to_string());
+        let update = TransactionStatusUpdate {
+            request_id: self.request_id,
+            signature: self.signature.clone(),
+            status: self.status as i32,
+            slot,
+            error,
+        };
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.sender.try_send(Ok(update)) {
+            if !self.overflowed.swap(true, Ordering::Relaxed) {
+                GRPC_STATUS_STREAMS_OVERFLOWED.inc();
+                warn!("gRPC transaction stream is not read, dropping its status subscriptions");
+            }
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.sender.is_closed() || self.overflowed.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use hyper::{Body, Method, Request, Response, StatusCode};
+use solana_lite_rpc_core::{
+    stores::data_cache::DataCache, time_utils::unix_timestamp_ms, types::BlockInfoStream,
+    AnyhowJoinHandle,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use tokio::sync::broadcast::error::RecvError;
+use tower::{Layer, Service};
+
+use crate::utils::text_response;
+
+/// ready to serve traffic: block source delivers, slot lag is below the threshold and the TPU service runs
+pub const READINESS_PATH: &str = "/readyz";
+/// the block and transaction loops make progress; restarting is the only remedy if this fails
+pub const LIVENESS_PATH: &str = "/livez";
+
+// no processed block for this long means the block source (geyser or rpc polling) is disconnected
+const MAX_BLOCK_SILENCE: Duration = Duration::from_secs(30);
+const MAX_HEARTBEAT_DELAY: Duration = Duration::from_secs(10);
+
+/// state behind the readiness and liveness endpoints, shared with the tasks reporting into it
+#[derive(Clone)]
+pub struct HealthState {
+    data_cache: DataCache,
+    max_slot_lag: u64,
+    // unix timestamps in ms, 0 if never
+    last_block_received: Arc<AtomicU64>,
+    tpu_running: Arc<AtomicBool>,
+    blocks_received: Arc<AtomicU64>,
+    startup_gate: StartupGate,
+    caught_up: Arc<AtomicBool>,
+}
+
+/// blockhash, send and status requests are rejected until the block stream caught up after the start
+#[derive(Clone, Copy, Debug)]
+pub struct StartupGate {
+    /// blocks to receive from the block stream, 0 disables the gate
+    pub min_blocks: u64,
+    /// maximal lag of the latest processed block behind the estimated cluster slot
+    pub max_slot_lag: u64,
+}
+
+impl HealthState {
+    pub fn new(data_cache: DataCache, max_slot_lag: u64, startup_gate: StartupGate) -> Self {
+        Self {
+            data_cache,
+            max_slot_lag,
+            last_block_received: Arc::new(AtomicU64::new(0)),
+            tpu_running: Arc::new(AtomicBool::new(false)),
+            blocks_received: Arc::new(AtomicU64::new(0)),
+            startup_gate,
+            caught_up: Arc::new(AtomicBool::new(startup_gate.min_blocks == 0)),
+        }
+    }
+
+    /// records incoming blocks
+    pub fn start(&self, mut block_info_stream: BlockInfoStream) -> AnyhowJoinHandle {
+        let last_block_received = self.last_block_received.clone();
+        let blocks_received = self.blocks_received.clone();
+        tokio::spawn(async move {
+            loop {
+                match block_info_stream.recv().await {
+                    Ok(_) => {
+                        last_block_received.store(unix_timestamp_ms(), Ordering::Relaxed);
+                        blocks_received.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(RecvError::Lagged(lagged)) => {
+                        last_block_received.store(unix_timestamp_ms(), Ordering::Relaxed);
+                        blocks_received.fetch_add(lagged, Ordering::Relaxed);
+                    }
+                    Err(RecvError::Closed) => anyhow::bail!("block stream closed"),
+                }
+            }
+        })
+    }
+
+    /// the TPU service counts as running until the returned handle completes
+    pub fn track_tpu_service(&self, tpu_service_jh: AnyhowJoinHandle) -> AnyhowJoinHandle {
+        let tpu_running = self.tpu_running.clone();
+        tpu_running.store(true, Ordering::Relaxed);
+        tokio::spawn(async move {
+            let result = tpu_service_jh.await;
+            tpu_running.store(false, Ordering::Relaxed);
+            result?
+        })
+    }
+
+    /// the block loop of the data caching service and, while the TPU service runs, the transaction loop beat
+    pub fn is_alive(&self) -> Result<(), String> {
+        let heartbeats = &self.data_cache.heartbeats;
+        let block_loop_delay = heartbeats.blocks.elapsed();
+        if block_loop_delay > MAX_HEARTBEAT_DELAY {
+            return Err(format!(
+                "block loop stalled for {}ms",
+                block_loop_delay.as_millis()
+            ));
+        }
+        let transaction_loop_delay = heartbeats.transactions.elapsed();
+        if self.tpu_running.load(Ordering::Relaxed) && transaction_loop_delay > MAX_HEARTBEAT_DELAY
+        {
+            return Err(format!(
+                "transaction loop stalled for {}ms",
+                transaction_loop_delay.as_millis()
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn is_ready(&self) -> Result<(), String> {
+        self.is_alive()?;
+        let last_block_received = self.last_block_received.load(Ordering::Relaxed);
+        if last_block_received == 0 {
+            return Err("no block received yet".to_string());
+        }
+        let block_age = age_ms(last_block_received);
+        if block_age > MAX_BLOCK_SILENCE.as_millis() as u64 {
+            return Err(format!("no block received for {block_age}ms"));
+        }
+
+        let (processed_slot, slot_lag) = self.slot_lag().await;
+        if slot_lag > self.max_slot_lag {
+            return Err(format!(
+                "processed block {processed_slot} lags {slot_lag} slots behind the cluster"
+            ));
+        }
+
+        if !self.tpu_running.load(Ordering::Relaxed) {
+            return Err("tpu service is not running".to_string());
+        }
+        Ok(())
+    }
+
+    /// fails until the startup gate opened once, a later lag only affects the readiness
+    pub async fn is_caught_up(&self) -> Result<(), String> {
+        if self.caught_up.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let blocks_received = self.blocks_received.load(Ordering::Relaxed);
+        if blocks_received < self.startup_gate.min_blocks {
+            return Err(format!(
+                "received {blocks_received} of {} blocks",
+                self.startup_gate.min_blocks
+            ));
+        }
+        let (processed_slot, slot_lag) = self.slot_lag().await;
+        if slot_lag > self.startup_gate.max_slot_lag {
+            return Err(format!(
+                "processed block {processed_slot} lags {slot_lag} slots behind the cluster"
+            ));
+        }
+        if !self.caught_up.swap(true, Ordering::Relaxed) {
+            log::info!(
+                "Block stream caught up after {blocks_received} blocks, serving all requests"
+            );
+        }
+        Ok(())
+    }
+
+    /// the latest processed slot and how far it is behind the estimated cluster slot
+    async fn slot_lag(&self) -> (u64, u64) {
+        let processed_slot = self
+            .data_cache
+            .block_information_store
+            .get_latest_block(CommitmentConfig::processed())
+            .await
+            .slot;
+        let slot_lag = self
+            .data_cache
+            .slot_cache
+            .get_estimated_slot()
+            .saturating_sub(processed_slot);
+        (processed_slot, slot_lag)
+    }
+}
+
+/// tower layer answering the health endpoints in front of the json rpc http server
+#[derive(Clone)]
+pub struct HealthLayer {
+    health_state: HealthState,
+}
+
+impl HealthLayer {
+    pub fn new(health_state: HealthState) -> Self {
+        Self { health_state }
+    }
+}
+
+impl<S> Layer<S> for HealthLayer {
+    type Service = HealthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HealthService {
+            inner,
+            health_state: self.health_state.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HealthService<S> {
+    inner: S,
+    health_state: HealthState,
+}
+
+impl<S> Service<Request<Body>> for HealthService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        if request.method() != Method::GET {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let health_state = self.health_state.clone();
+        match request.uri().path() {
+            READINESS_PATH => {
+                Box::pin(async move { Ok(health_response(health_state.is_ready().await)) })
+            }
+            LIVENESS_PATH => Box::pin(async move { Ok(health_response(health_state.is_alive())) }),
+            _ => Box::pin(self.inner.call(request)),
+        }
+    }
+}
+
+fn health_response(result: Result<(), String>) -> Response<Body> {
+    let (status, text) = match result {
+        Ok(()) => (StatusCode::OK, "ok".to_string()),
+        Err(reason) => (StatusCode::SERVICE_UNAVAILABLE, reason),
+    };
+    text_response(status, text)
+}
+
+fn age_ms(timestamp_ms: u64) -> u64 {
+    unix_timestamp_ms().saturating_sub(timestamp_ms)
+}
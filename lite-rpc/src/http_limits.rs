@@ -0,0 +1,600 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use hyper::{
+    body::{Bytes, HttpBody},
+    header::CONTENT_LENGTH,
+    server::conn::Http,
+    service::Service,
+    Body, Request, Response, StatusCode,
+};
+use log::debug;
+use prometheus::{opts, register_int_counter_vec, register_int_gauge, IntCounterVec, IntGauge};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+use tokio_util::sync::CancellationToken;
+use tower::Layer;
+use tower_http::add_extension::AddExtension;
+
+use crate::{
+    rate_limit::{ConnectionClient, MeteredStream, RateLimiter},
+    utils::text_response,
+    DEFAULT_HTTP_HEADER_READ_TIMEOUT_SECS, DEFAULT_HTTP_KEEP_ALIVE_TIMEOUT_SECS,
+    DEFAULT_HTTP_MAX_CONNECTIONS, DEFAULT_HTTP_MAX_REQUEST_BODY_SIZE,
+    DEFAULT_HTTP_REQUEST_TIMEOUT_SECS,
+};
+
+lazy_static::lazy_static! {
+    static ref HTTP_REJECTIONS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_http_rejections", "Connections and requests rejected by the limits of the HTTP server"), &["reason"]).unwrap();
+    static ref HTTP_CONNECTIONS: IntGauge =
+        register_int_gauge!(opts!("literpc_http_connections", "Open connections of the HTTP server")).unwrap();
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpLimits {
+    /// connections served at once, further connections are closed right after the accept
+    pub max_connections: usize,
+    /// connections without a request in flight for this long are closed
+    pub keep_alive_timeout: Duration,
+    /// larger request bodies are answered with 413
+    pub max_request_body_size: u64,
+    /// time to receive the headers and body of a request and answer it, answered with 503 when over
+    pub request_timeout: Duration,
+    /// time to receive the headers of a request, the connection is closed when over
+    pub header_read_timeout: Duration,
+}
+
+impl Default for HttpLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: DEFAULT_HTTP_MAX_CONNECTIONS,
+            keep_alive_timeout: Duration::from_secs(DEFAULT_HTTP_KEEP_ALIVE_TIMEOUT_SECS),
+            max_request_body_size: DEFAULT_HTTP_MAX_REQUEST_BODY_SIZE,
+            request_timeout: Duration::from_secs(DEFAULT_HTTP_REQUEST_TIMEOUT_SECS),
+            header_read_timeout: Duration::from_secs(DEFAULT_HTTP_HEADER_READ_TIMEOUT_SECS),
+        }
+    }
+}
+
+/// limits the connections of a server and closes idle ones, shared by the accept loops of the server
+#[derive(Clone)]
+pub struct ConnectionLimiter {
+    connection_slots: Option<Arc<Semaphore>>,
+    keep_alive_timeout: Option<Duration>,
+    header_read_timeout: Option<Duration>,
+    message_meter: Option<RateLimiter>,
+}
+
+impl ConnectionLimiter {
+    /// the connections of the websocket server are long lived and not limited
+    pub fn unlimited() -> Self {
+        Self {
+            connection_slots: None,
+            keep_alive_timeout: None,
+            header_read_timeout: None,
+            message_meter: None,
+        }
+    }
+
+    pub fn new(limits: &HttpLimits) -> Self {
+        Self {
+            connection_slots: Some(Arc::new(Semaphore::new(limits.max_connections))),
+            keep_alive_timeout: Some(limits.keep_alive_timeout),
+            // slow-loris clients trickling the headers are not even seen by the request timeout
+            header_read_timeout: Some(limits.header_read_timeout),
+            message_meter: None,
+        }
+    }
+
+    /// the messages received on the connections take the tokens of their client, see [`MeteredStream`]
+    pub fn with_message_meter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.message_meter = Some(rate_limiter);
+        self
+    }
+
+    /// a slot for an accepted connection, none if the server is full and the connection has to be closed
+    pub fn try_open(&self) -> Option<ConnectionSlot> {
+        let permit = match &self.connection_slots {
+            Some(connection_slots) => match connection_slots.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    HTTP_REJECTIONS
+                        .with_label_values(&["max_connections"])
+                        .inc();
+                    return None;
+                }
+            },
+            None => None,
+        };
+        if permit.is_some() {
+            HTTP_CONNECTIONS.inc();
+        }
+        Some(ConnectionSlot { permit })
+    }
+
+    /// serves the connection until it is closed, idle for the keep-alive timeout or the shutdown,
+    /// on shutdown the request in flight is answered first
+    pub async fn serve<I, S>(
+        &self,
+        http: Http,
+        io: I,
+        service: S,
+        remote_addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) where
+        I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        S: Service<Request<Body>, Response = Response<Body>> + Send + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        S::Future: Send + 'static,
+    {
+        match &self.message_meter {
+            Some(rate_limiter) => {
+                let client = ConnectionClient::default();
+                let io = MeteredStream::new(io, rate_limiter.clone(), client.clone());
+                let service = AddExtension::new(service, client);
+                self.serve_connection(http, io, service, remote_addr, shutdown)
+                    .await
+            }
+            None => {
+                self.serve_connection(http, io, service, remote_addr, shutdown)
+                    .await
+            }
+        }
+    }
+
+    async fn serve_connection<I, S>(
+        &self,
+        mut http: Http,
+        io: I,
+        service: S,
+        remote_addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) where
+        I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        S: Service<Request<Body>, Response = Response<Body>> + Send + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        S::Future: Send + 'static,
+    {
+        if let Some(header_read_timeout) = self.header_read_timeout {
+            http.http1_header_read_timeout(header_read_timeout);
+        }
+        let activity = Arc::new(ConnectionActivity::new());
+        let connection = http
+            .serve_connection(
+                io,
+                TrackActivity {
+                    inner: service,
+                    activity: activity.clone(),
+                },
+            )
+            .with_upgrades();
+        tokio::pin!(connection);
+        let keep_alive_timeout = self.keep_alive_timeout;
+        let idle = async move {
+            match keep_alive_timeout {
+                Some(keep_alive_timeout) => activity.idle(keep_alive_timeout).await,
+                None => futures::future::pending().await,
+            }
+        };
+        let result = tokio::select! {
+            result = connection.as_mut() => result,
+            _ = shutdown.cancelled() => {
+                // answers the request in flight and closes the connection
+                connection.as_mut().graceful_shutdown();
+                connection.await
+            }
+            _ = idle => {
+                debug!("Closing idle connection with {remote_addr}");
+                connection.as_mut().graceful_shutdown();
+                connection.await
+            }
+        };
+        if let Err(err) = result {
+            debug!("Connection with {remote_addr} closed: {err}");
+        }
+    }
+}
+
+/// held while a connection is open
+pub struct ConnectionSlot {
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        if self.permit.is_some() {
+            HTTP_CONNECTIONS.dec();
+        }
+    }
+}
+
+struct ConnectionActivity {
+    requests_in_flight: AtomicUsize,
+    last_active: Mutex<Instant>,
+}
+
+impl ConnectionActivity {
+    fn new() -> Self {
+        Self {
+            requests_in_flight: AtomicUsize::new(0),
+            last_active: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// completes once no request was in flight for `timeout`
+    async fn idle(&self, timeout: Duration) {
+        loop {
+            let last_active = *self.last_active.lock().unwrap();
+            if self.requests_in_flight.load(Ordering::Acquire) > 0 {
+                tokio::time::sleep(timeout).await;
+            } else if last_active.elapsed() >= timeout {
+                return;
+            } else {
+                tokio::time::sleep_until(last_active + timeout).await;
+            }
+        }
+    }
+}
+
+// marks the connection as active until the response of the request is sent or dropped
+struct RequestInFlight(Arc<ConnectionActivity>);
+
+impl RequestInFlight {
+    fn new(activity: Arc<ConnectionActivity>) -> Self {
+        activity.requests_in_flight.fetch_add(1, Ordering::AcqRel);
+        Self(activity)
+    }
+}
+
+impl Drop for RequestInFlight {
+    fn drop(&mut self) {
+        *self.0.last_active.lock().unwrap() = Instant::now();
+        self.0.requests_in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+struct TrackActivity<S> {
+    inner: S,
+    activity: Arc<ConnectionActivity>,
+}
+
+impl<S> Service<Request<Body>> for TrackActivity<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let request_in_flight = RequestInFlight::new(self.activity.clone());
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            drop(request_in_flight);
+            response
+        })
+    }
+}
+
+/// rejects requests with a body over the size limit and answers requests taking longer than the
+/// request timeout, reading the body included
+#[derive(Clone)]
+pub struct HttpLimitLayer {
+    max_request_body_size: u64,
+    request_timeout: Duration,
+}
+
+impl HttpLimitLayer {
+    pub fn new(limits: &HttpLimits) -> Self {
+        Self {
+            max_request_body_size: limits.max_request_body_size,
+            request_timeout: limits.request_timeout,
+        }
+    }
+}
+
+impl<S> Layer<S> for HttpLimitLayer {
+    type Service = HttpLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HttpLimitService {
+            inner,
+            max_request_body_size: self.max_request_body_size,
+            request_timeout: self.request_timeout,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HttpLimitService<S> {
+    inner: S,
+    max_request_body_size: u64,
+    request_timeout: Duration,
+}
+
+impl<S> Service<Request<Body>> for HttpLimitService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let content_length = request
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if content_length.is_some_and(|content_length| content_length > self.max_request_body_size)
+        {
+            HTTP_REJECTIONS.with_label_values(&["body_size"]).inc();
+            return Box::pin(async move { Ok(body_too_large()) });
+        }
+
+        // the ready service is taken, a clone waits for the next request
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let max_request_body_size = self.max_request_body_size;
+        let request_timeout = self.request_timeout;
+        Box::pin(async move {
+            let response = async move {
+                let (parts, body) = request.into_parts();
+                match read_body(body, max_request_body_size).await {
+                    Ok(body) => {
+                        inner
+                            .call(Request::from_parts(parts, Body::from(body)))
+                            .await
+                    }
+                    Err(response) => Ok(response),
+                }
+            };
+            match tokio::time::timeout(request_timeout, response).await {
+                Ok(response) => response,
+                Err(_elapsed) => {
+                    HTTP_REJECTIONS.with_label_values(&["timeout"]).inc();
+                    Ok(text_response(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "request timed out",
+                    ))
+                }
+            }
+        })
+    }
+}
+
+/// the size of bodies without a content length is only known once they are read
+pub(crate) async fn read_body(
+    mut body: Body,
+    max_request_body_size: u64,
+) -> Result<Bytes, Response<Body>> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|err| {
+            debug!("Reading the request body failed: {err}");
+            text_response(StatusCode::BAD_REQUEST, "invalid request body")
+        })?;
+        if (bytes.len() + chunk.len()) as u64 > max_request_body_size {
+            HTTP_REJECTIONS.with_label_values(&["body_size"]).inc();
+            return Err(body_too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(bytes))
+}
+
+fn body_too_large() -> Response<Body> {
+    text_response(StatusCode::PAYLOAD_TOO_LARGE, "request body too large")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::service::service_fn;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use tower::util::BoxCloneService;
+
+    use super::*;
+
+    fn limits() -> HttpLimits {
+        HttpLimits {
+            max_connections: 1,
+            keep_alive_timeout: Duration::from_secs(60),
+            max_request_body_size: 16,
+            request_timeout: Duration::from_secs(90),
+            header_read_timeout: Duration::from_secs(10),
+        }
+    }
+
+    // answers with the request body after the delay in the path, e.g. `/5` for 5 seconds
+    fn echo() -> BoxCloneService<Request<Body>, Response<Body>, Infallible> {
+        BoxCloneService::new(service_fn(|request: Request<Body>| async move {
+            let delay = request.uri().path()[1..].parse().unwrap_or(0);
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+            let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+            Ok::<_, Infallible>(Response::new(Body::from(body)))
+        }))
+    }
+
+    // the connections are closed by the keep-alive timeout before the header read timeout
+    fn connection_limiter() -> ConnectionLimiter {
+        ConnectionLimiter::new(&HttpLimits {
+            header_read_timeout: Duration::from_secs(300),
+            ..limits()
+        })
+    }
+
+    // serves a connection over an in memory stream, the client end is returned
+    fn connect(limiter: &ConnectionLimiter) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(1 << 16);
+        let limiter = limiter.clone();
+        tokio::spawn(async move {
+            limiter
+                .serve(
+                    Http::new(),
+                    server,
+                    echo(),
+                    "127.0.0.1:1000".parse().unwrap(),
+                    CancellationToken::new(),
+                )
+                .await
+        });
+        client
+    }
+
+    // reads until the connection is closed
+    async fn read_to_close(client: &mut DuplexStream) -> String {
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).await.unwrap();
+        String::from_utf8(received).unwrap()
+    }
+
+    #[test]
+    fn test_connections_are_capped() {
+        let limiter = ConnectionLimiter::new(&limits());
+        let slot = limiter.try_open();
+        assert!(slot.is_some());
+        assert!(limiter.try_open().is_none());
+        drop(slot);
+        assert!(limiter.try_open().is_some());
+
+        let unlimited = ConnectionLimiter::unlimited();
+        let slots = (0..10).map(|_| unlimited.try_open()).collect::<Vec<_>>();
+        assert!(slots.iter().all(Option::is_some));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_connections_are_closed() {
+        let limiter = connection_limiter();
+        let mut client = connect(&limiter);
+        client
+            .write_all(b"GET /0 HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+
+        let start = Instant::now();
+        let received = read_to_close(&mut client).await;
+        assert!(received.starts_with("HTTP/1.1 200 OK"), "{received}");
+        assert!(start.elapsed() >= Duration::from_secs(60));
+        assert!(start.elapsed() < Duration::from_secs(300));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_in_flight_keep_the_connection_open() {
+        let limiter = connection_limiter();
+        let mut client = connect(&limiter);
+        // answered after the keep-alive timeout
+        client
+            .write_all(b"GET /80 HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+
+        let start = Instant::now();
+        let received = read_to_close(&mut client).await;
+        assert!(received.starts_with("HTTP/1.1 200 OK"), "{received}");
+        assert!(start.elapsed() >= Duration::from_secs(140));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_headers_close_the_connection() {
+        let limiter = ConnectionLimiter::new(&limits());
+        let mut client = connect(&limiter);
+        client.write_all(b"GET /0 HTTP/1.1\r\n").await.unwrap();
+
+        let start = Instant::now();
+        read_to_close(&mut client).await;
+        assert!(start.elapsed() < Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_large_bodies_are_rejected() {
+        let mut service = HttpLimitLayer::new(&limits()).layer(echo());
+
+        let response = service
+            .call(Request::post("/").body(Body::from("small")).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            hyper::body::to_bytes(response.into_body()).await.unwrap(),
+            "small"
+        );
+
+        let response = service
+            .call(
+                Request::post("/")
+                    .header(CONTENT_LENGTH, 17)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // chunked, the size is only known while reading
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            for _ in 0..3 {
+                if sender.send_data("10 bytes..".into()).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let response = service
+            .call(Request::post("/").body(body).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_requests_are_answered_with_503() {
+        let mut service = HttpLimitLayer::new(&limits()).layer(echo());
+
+        let response = service
+            .call(Request::get("/89").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = service
+            .call(Request::get("/91").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // a body never completed counts as well
+        let (_sender, body) = Body::channel();
+        let response = service
+            .call(Request::post("/0").body(body).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,82 @@
+use std::{sync::Arc, time::Duration, time::SystemTime};
+
+use anyhow::Context;
+use log::{error, info, warn};
+use solana_lite_rpc_core::{keypair_loader::load_identity_keypair, AnyhowJoinHandle};
+use solana_lite_rpc_services::tpu_utils::tpu_service::TpuService;
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
+use tokio::sync::RwLock;
+
+/// Reloads the identity keypair from disk and hands it over to the tpu service without a restart.
+/// The file is polled for modifications; a reload can also be triggered explicitly.
+#[derive(Clone)]
+pub struct IdentityReloader {
+    identity_path: String,
+    tpu_service: TpuService,
+    current_identity: Arc<RwLock<Pubkey>>,
+}
+
+impl IdentityReloader {
+    pub fn new(identity_path: String, tpu_service: TpuService, current_identity: Pubkey) -> Self {
+        Self {
+            identity_path,
+            tpu_service,
+            current_identity: Arc::new(RwLock::new(current_identity)),
+        }
+    }
+
+    pub async fn current_identity(&self) -> Pubkey {
+        *self.current_identity.read().await
+    }
+
+    /// returns the identity in use after the reload
+    pub async fn reload(&self) -> anyhow::Result<Pubkey> {
+        let identity = load_identity_keypair(Some(self.identity_path.clone()))
+            .await?
+            .context("identity file did not contain a keypair")?;
+
+        let mut current_identity = self.current_identity.write().await;
+        if identity.pubkey() == *current_identity {
+            info!("Identity {} unchanged - skip rotation", identity.pubkey());
+            return Ok(*current_identity);
+        }
+
+        self.tpu_service.update_identity(&identity).await;
+        info!(
+            "Identity rotated from {} to {}",
+            *current_identity,
+            identity.pubkey()
+        );
+        *current_identity = identity.pubkey();
+        Ok(*current_identity)
+    }
+
+    async fn last_modified(&self) -> Option<SystemTime> {
+        tokio::fs::metadata(&self.identity_path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    pub fn start_watching(&self, poll_interval: Duration) -> AnyhowJoinHandle {
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut last_modified = this.last_modified().await;
+            loop {
+                tokio::time::sleep(poll_interval).await;
+                let modified = this.last_modified().await;
+                if modified.is_none() {
+                    warn!("Cannot read identity file {}", this.identity_path);
+                    continue;
+                }
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                if let Err(e) = this.reload().await {
+                    error!("Failed to reload identity keypair: {e:?}");
+                }
+            }
+        })
+    }
+}
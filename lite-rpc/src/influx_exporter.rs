@@ -0,0 +1,259 @@
+use std::{
+    fmt::Write,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use log::{debug, info, warn};
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_core::{stores::data_cache::DataCache, AnyhowJoinHandle};
+#[cfg(feature = "priofees")]
+use solana_lite_rpc_prioritization_fees::rpc_data::{PrioFeesSummary, PrioFeesUpdateMessage};
+use solana_sdk::commitment_config::CommitmentConfig;
+use tokio::net::UdpSocket;
+#[cfg(feature = "priofees")]
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+lazy_static::lazy_static! {
+    static ref INFLUX_PACKETS_SENT: IntCounter =
+        register_int_counter!(opts!("literpc_influx_packets_sent", "UDP packets with line protocol sent to influxdb")).unwrap();
+    static ref INFLUX_PACKETS_FAILED: IntCounter =
+        register_int_counter!(opts!("literpc_influx_packets_failed", "UDP packets with line protocol which could not be sent to influxdb")).unwrap();
+}
+
+// keeps the datagrams below the usual MTU
+const MAX_PACKET_SIZE: usize = 1400;
+
+// counters of the prometheus registry the landing rate is derived from
+const TXS_SENT_METRIC: &str = "literpc_txs_sent";
+const TXS_CONFIRMED_METRIC: &str = "literpc_txs_confirmed";
+
+#[derive(Clone, Debug)]
+pub struct InfluxExporterConfig {
+    /// `host:port` of the influxdb or telegraf UDP listener
+    pub addr: String,
+    pub interval: Duration,
+    /// `key=value` pairs added to every line, e.g. `host=lite-rpc-1,region=eu`
+    pub tags: Option<String>,
+}
+
+/// pushes slot lag, transaction landing rate and prioritization fee percentiles in the influxdb
+/// line protocol over UDP, for push based monitoring next to the prometheus endpoint
+pub struct InfluxExporter {
+    config: InfluxExporterConfig,
+    data_cache: DataCache,
+    block_fees: BlockFees,
+}
+
+impl InfluxExporter {
+    pub fn new(config: InfluxExporterConfig, data_cache: DataCache) -> Self {
+        Self {
+            config,
+            data_cache,
+            block_fees: BlockFees::default(),
+        }
+    }
+
+    /// push the percentiles of the latest block
+    #[cfg(feature = "priofees")]
+    pub fn with_block_fees(mut self, block_fees_stream: Receiver<PrioFeesUpdateMessage>) -> Self {
+        self.block_fees.stream = Some(block_fees_stream);
+        self
+    }
+
+    pub fn start(mut self) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            let socket = UdpSocket::bind("0.0.0.0:0").await?;
+            socket
+                .connect(&self.config.addr)
+                .await
+                .with_context(|| format!("Cannot resolve influxdb address {}", self.config.addr))?;
+            info!(
+                "Pushing metrics to influxdb {} every {:?}",
+                self.config.addr, self.config.interval
+            );
+
+            let mut previous_counters = transaction_counters();
+            let mut interval = tokio::time::interval(self.config.interval);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    res = self.block_fees.recv() => {
+                        res?;
+                        continue;
+                    }
+                }
+
+                let counters = transaction_counters();
+                let lines = self.lines(&previous_counters, &counters).await;
+                previous_counters = counters;
+                for packet in packets(&lines) {
+                    match socket.send(packet.as_bytes()).await {
+                        Ok(_) => INFLUX_PACKETS_SENT.inc(),
+                        Err(err) => {
+                            INFLUX_PACKETS_FAILED.inc();
+                            debug!("failed to send metrics to influxdb: {err}");
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    async fn lines(
+        &mut self,
+        previous_counters: &(u64, u64),
+        counters: &(u64, u64),
+    ) -> Vec<String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let tags = match &self.config.tags {
+            Some(tags) => format!(",{tags}"),
+            None => String::new(),
+        };
+        let mut lines = vec![];
+
+        let estimated_slot = self.data_cache.slot_cache.get_estimated_slot();
+        for commitment_config in [
+            CommitmentConfig::processed(),
+            CommitmentConfig::confirmed(),
+            CommitmentConfig::finalized(),
+        ] {
+            let block_slot = self
+                .data_cache
+                .block_information_store
+                .get_latest_block(commitment_config)
+                .await
+                .slot;
+            lines.push(format!(
+                "literpc_slot_lag,commitment={}{tags} \
+                 slot_lag={}i,block_slot={}i,estimated_slot={}i {timestamp}",
+                commitment_config.commitment,
+                estimated_slot.saturating_sub(block_slot),
+                block_slot,
+                estimated_slot,
+            ));
+        }
+
+        let sent = counters.0.saturating_sub(previous_counters.0);
+        let confirmed = counters.1.saturating_sub(previous_counters.1);
+        let mut landing = format!("literpc_landing{tags} sent={sent}i,confirmed={confirmed}i");
+        // no rate in intervals without sent transactions
+        if sent > 0 {
+            let _ = write!(landing, ",rate={}", confirmed as f64 / sent as f64);
+        }
+        let _ = write!(landing, " {timestamp}");
+        lines.push(landing);
+
+        if let Some(line) = self.block_fees.take_line(&tags, timestamp) {
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+/// prioritization fees of the latest block received since the last push, empty if lite-rpc was
+/// built without the priofees feature
+#[derive(Default)]
+struct BlockFees {
+    #[cfg(feature = "priofees")]
+    stream: Option<Receiver<PrioFeesUpdateMessage>>,
+    #[cfg(feature = "priofees")]
+    latest: Option<PrioFeesSummary>,
+}
+
+#[cfg(feature = "priofees")]
+impl BlockFees {
+    // never returns without a stream
+    async fn recv(&mut self) -> anyhow::Result<()> {
+        let Some(stream) = &mut self.stream else {
+            return std::future::pending().await;
+        };
+        match stream.recv().await {
+            Ok(PrioFeesUpdateMessage {
+                slot,
+                priofees_stats,
+            }) => {
+                self.latest = Some(priofees_stats.summary(slot));
+            }
+            Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => {
+                anyhow::bail!("influx exporter priofees stream closed")
+            }
+        }
+        Ok(())
+    }
+
+    fn take_line(&mut self, tags: &str, timestamp: u128) -> Option<String> {
+        let fees = self.latest.take()?;
+        Some(format!(
+            "literpc_priofees{tags} slot={}i,tx_count={}i,\
+             p50_by_tx={}i,p75_by_tx={}i,p90_by_tx={}i,p95_by_tx={}i,\
+             p50_by_cu={}i,p75_by_cu={}i,p90_by_cu={}i,p95_by_cu={}i {timestamp}",
+            fees.slot,
+            fees.tx_count.total,
+            fees.by_tx.p50,
+            fees.by_tx.p75,
+            fees.by_tx.p90,
+            fees.by_tx.p95,
+            fees.by_cu.p50,
+            fees.by_cu.p75,
+            fees.by_cu.p90,
+            fees.by_cu.p95,
+        ))
+    }
+}
+
+#[cfg(not(feature = "priofees"))]
+impl BlockFees {
+    async fn recv(&mut self) -> anyhow::Result<()> {
+        std::future::pending().await
+    }
+
+    fn take_line(&mut self, _tags: &str, _timestamp: u128) -> Option<String> {
+        None
+    }
+}
+
+// (sent, confirmed) transactions since startup
+fn transaction_counters() -> (u64, u64) {
+    let mut counters = (0, 0);
+    for family in prometheus::gather() {
+        let counter = match family.get_name() {
+            TXS_SENT_METRIC => &mut counters.0,
+            TXS_CONFIRMED_METRIC => &mut counters.1,
+            _ => continue,
+        };
+        *counter = family
+            .get_metric()
+            .iter()
+            .map(|metric| metric.get_counter().get_value() as u64)
+            .sum();
+    }
+    counters
+}
+
+// newline separated lines, split into datagrams of at most MAX_PACKET_SIZE
+fn packets(lines: &[String]) -> Vec<String> {
+    let mut packets = vec![];
+    let mut packet = String::new();
+    for line in lines {
+        if !packet.is_empty() && packet.len() + line.len() + 1 > MAX_PACKET_SIZE {
+            packets.push(std::mem::take(&mut packet));
+        }
+        if line.len() > MAX_PACKET_SIZE {
+            warn!(
+                "influx line of {} bytes exceeds the packet size",
+                line.len()
+            );
+        }
+        packet.push_str(line);
+        packet.push('\n');
+    }
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+    packets
+}
@@ -0,0 +1,149 @@
+use std::{collections::HashMap, time::Duration};
+
+use log::{info, warn};
+use prometheus::{opts, register_int_counter, IntCounter};
+use rdkafka::{
+    error::KafkaError,
+    producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer},
+    types::RDKafkaErrorCode,
+    ClientConfig, ClientContext, Message,
+};
+use solana_lite_rpc_core::{types::BlockStream, AnyhowJoinHandle};
+use solana_sdk::commitment_config::CommitmentLevel;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+
+use crate::sink_records::{block_record, transaction_records, SinkFormat};
+
+lazy_static::lazy_static! {
+    static ref KAFKA_RECORDS_PUBLISHED: IntCounter =
+        register_int_counter!(opts!("literpc_kafka_records_published", "Records delivered to kafka")).unwrap();
+    static ref KAFKA_RECORDS_FAILED: IntCounter =
+        register_int_counter!(opts!("literpc_kafka_records_failed", "Records which could not be delivered to kafka")).unwrap();
+    static ref KAFKA_RECORDS_DROPPED: IntCounter =
+        register_int_counter!(opts!("literpc_kafka_records_dropped", "Records dropped because the kafka producer queue was full")).unwrap();
+}
+
+// records not delivered after this time are counted as failed
+const MESSAGE_TIMEOUT_MS: &str = "30000";
+
+#[derive(Clone, Debug)]
+pub struct KafkaSinkConfig {
+    /// comma separated `host:port` list
+    pub brokers: String,
+    /// topic for one record per block, not published if not set
+    pub blocks_topic: Option<String>,
+    /// topic for one record per transaction, not published if not set
+    pub transactions_topic: Option<String>,
+    pub format: SinkFormat,
+    /// blocks are published once they reach this commitment
+    pub commitment: CommitmentLevel,
+    pub include_votes: bool,
+    /// further librdkafka properties, e.g. for authentication
+    pub producer_config: HashMap<String, String>,
+}
+
+struct DeliveryCounter;
+
+impl ClientContext for DeliveryCounter {}
+
+impl ProducerContext for DeliveryCounter {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, delivery_result: &DeliveryResult<'_>, _: Self::DeliveryOpaque) {
+        match delivery_result {
+            Ok(_) => KAFKA_RECORDS_PUBLISHED.inc(),
+            Err((err, message)) => {
+                KAFKA_RECORDS_FAILED.inc();
+                warn!(
+                    "failed to deliver record to kafka topic {}: {err}",
+                    message.topic()
+                );
+            }
+        }
+    }
+}
+
+/// publishes the blocks and transactions of the block stream to kafka topics
+pub struct KafkaSink {
+    config: KafkaSinkConfig,
+    producer: ThreadedProducer<DeliveryCounter>,
+}
+
+impl KafkaSink {
+    pub fn new(config: KafkaSinkConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            config.blocks_topic.is_some() || config.transactions_topic.is_some(),
+            "Kafka sink needs a blocks or transactions topic"
+        );
+        let mut client_config = ClientConfig::new();
+        client_config
+            .set("bootstrap.servers", &config.brokers)
+            .set("message.timeout.ms", MESSAGE_TIMEOUT_MS);
+        for (key, value) in &config.producer_config {
+            client_config.set(key, value);
+        }
+        let producer = client_config.create_with_context(DeliveryCounter)?;
+        Ok(Self { config, producer })
+    }
+
+    /// on shutdown the queued records are flushed before the task ends
+    pub fn start(
+        self,
+        mut block_stream: BlockStream,
+        shutdown: CancellationToken,
+        flush_timeout: Duration,
+    ) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            info!(
+                "Publishing {} blocks to kafka {}",
+                self.config.commitment, self.config.brokers
+            );
+            loop {
+                let block = tokio::select! {
+                    block = block_stream.recv() => match block {
+                        Ok(block) => block,
+                        Err(RecvError::Lagged(lagged)) => {
+                            warn!("kafka sink lagged by {lagged} blocks - continue");
+                            continue;
+                        }
+                        Err(RecvError::Closed) => anyhow::bail!("kafka sink block stream closed"),
+                    },
+                    _ = shutdown.cancelled() => break,
+                };
+                if block.commitment_config.commitment != self.config.commitment {
+                    continue;
+                }
+
+                if let Some(topic) = &self.config.blocks_topic {
+                    let payload = self.config.format.encode(&block_record(&block));
+                    self.send(topic, &block.slot.to_string(), &payload);
+                }
+                if let Some(topic) = &self.config.transactions_topic {
+                    for record in transaction_records(&block, self.config.include_votes) {
+                        let payload = self.config.format.encode(&record);
+                        self.send(topic, &record.signature, &payload);
+                    }
+                }
+            }
+
+            let producer = self.producer;
+            tokio::task::spawn_blocking(move || producer.flush(flush_timeout)).await??;
+            info!("kafka sink flushed");
+            Ok(())
+        })
+    }
+
+    fn send(&self, topic: &str, key: &str, payload: &[u8]) {
+        let record = BaseRecord::to(topic).key(key).payload(payload);
+        if let Err((err, _)) = self.producer.send(record) {
+            KAFKA_RECORDS_DROPPED.inc();
+            if !matches!(
+                err,
+                KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull)
+            ) {
+                warn!("failed to queue record for kafka topic {topic}: {err}");
+            }
+        }
+    }
+}
@@ -1,24 +1,92 @@
 use const_env::from_env;
 use solana_transaction_status::TransactionConfirmationStatus;
 
+#[cfg(feature = "accounts")]
+pub mod account_filter_sets;
+pub mod account_webhooks;
+pub mod admin_server;
+pub mod allocator_stats;
+pub mod api_key_auth;
+#[cfg(feature = "arrow-export")]
+pub mod arrow_export;
+pub mod block_arrival_comparator;
+pub mod block_range;
 pub mod bridge;
+#[cfg(feature = "accounts")]
+pub mod bridge_accounts;
+#[cfg(feature = "priofees")]
+pub mod bridge_priofees;
+#[cfg(feature = "ws-server")]
 pub mod bridge_pubsub;
+pub mod builder;
+pub mod cached_proxy;
 pub mod cli;
+pub mod cluster_metrics;
+pub mod cluster_router;
+pub mod compression;
+pub mod config_reloader;
 pub mod configs;
+pub mod cors;
+pub mod epoch_value_cache;
 pub mod errors;
+pub mod geyser_server;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod grpc_tx_service;
+pub mod health_endpoint;
+pub mod http_limits;
+pub mod identity_reloader;
+pub mod influx_exporter;
+#[cfg(feature = "ws-server")]
 pub mod jsonrpsee_subscrption_handler_sink;
+#[cfg(feature = "kafka")]
+pub mod kafka_sink;
+pub mod openrpc;
+#[cfg(feature = "postgres")]
 pub mod postgres_logger;
+pub mod preflight;
+#[cfg(feature = "pubsub-sink")]
+pub mod pubsub_sink;
+pub mod rate_limit;
+pub mod raw_transaction_endpoint;
+pub mod request_id;
+pub mod response_cache;
 pub mod rpc;
 pub mod rpc_errors;
+pub mod rpc_metrics;
+#[cfg(feature = "ws-server")]
 pub mod rpc_pubsub;
 pub mod service_spawner;
+pub mod sink_records;
+pub mod slot_lag_watchdog;
 pub mod start_server;
+pub mod tls;
+pub mod upstream_proxy;
+pub mod utils;
+pub mod webhook_notifier;
 
 #[from_env]
 pub const DEFAULT_RPC_ADDR: &str = "http://0.0.0.0:8899";
 #[from_env]
 pub const DEFAULT_WS_ADDR: &str = "ws://0.0.0.0:8900";
 
+// accept loops per json rpc server, more than one share the port with SO_REUSEPORT
+#[from_env]
+pub const DEFAULT_SERVER_WORKERS: usize = 1;
+
+// limits of the json rpc http server against clients holding connections and requests open;
+// the request timeout leaves room for the retried upstream proxy requests
+pub const DEFAULT_HTTP_MAX_CONNECTIONS: usize = 10_000;
+pub const DEFAULT_HTTP_KEEP_ALIVE_TIMEOUT_SECS: u64 = 60;
+pub const DEFAULT_HTTP_MAX_REQUEST_BODY_SIZE: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_HTTP_REQUEST_TIMEOUT_SECS: u64 = 90;
+// short, clients trickling the headers hold a connection slot until it passes
+pub const DEFAULT_HTTP_HEADER_READ_TIMEOUT_SECS: u64 = 10;
+
+// worker threads of the main runtime, read from RUNTIME_WORKER_THREADS before the config is loaded
+#[from_env]
+pub const DEFAULT_RUNTIME_WORKER_THREADS: usize = 16;
+
 #[from_env]
 pub const DEFAULT_MAX_NUMBER_OF_TXS_IN_QUEUE: usize = 200_000;
 
@@ -39,6 +107,10 @@ pub const DEFAULT_TRANSACTION_CONFIRMATION_STATUS: TransactionConfirmationStatus
 #[from_env]
 pub const DEFAULT_GRPC_ADDR: &str = "http://localhost:10000";
 
+// larger updates from a grpc source are rejected while decoding, full blocks of busy slots stay well below
+#[from_env]
+pub const DEFAULT_GRPC_MAX_MESSAGE_SIZE_MB: usize = 64;
+
 #[from_env]
 pub const GRPC_VERSION: &str = "1.16.1";
 
@@ -51,3 +123,107 @@ pub const MAX_NB_OF_CONNECTIONS_WITH_LEADERS: usize = 8;
 
 #[from_env]
 pub const ENABLE_ADDRESS_LOOKUP_TABLES: bool = false;
+
+// window of blocks kept in memory for prioritization fee stats
+#[from_env]
+pub const DEFAULT_PRIOFEES_SLOTS_TO_RETAIN: u64 = 100;
+// bound for the transactions kept in the prioritization fee window
+#[from_env]
+pub const DEFAULT_PRIOFEES_MAX_DATA_POINTS: usize = 1_000_000;
+// compute units a leader may pack into a block, raised by feature activations over time
+#[from_env]
+pub const DEFAULT_BLOCK_CU_LIMIT: u64 = 60_000_000;
+
+// prioritization fee history in postgres is deleted after that many slots (~2 epochs)
+#[from_env]
+pub const DEFAULT_PRIOFEES_HISTORY_RETENTION_SLOTS: u64 = 864_000;
+
+// number of (slot, commitment) entries in the shared block cache; every slot is seen with up to 3 commitments
+#[from_env]
+pub const DEFAULT_BLOCK_CACHE_SIZE: usize = 300;
+
+// statuses of finalized transactions are kept this long for getSignatureStatuses
+#[from_env]
+pub const DEFAULT_TX_STATUS_RETENTION_SECS: u64 = 60;
+
+// slots replayed from the block storage on startup, covers the blockhash validity (150 slots) and the priofees window
+#[from_env]
+pub const DEFAULT_WARM_START_SLOTS: u64 = 300;
+
+// blocks replayed from the block storage are streamed at the pace of the cluster
+pub const DEFAULT_REPLAY_SPEED: f64 = 1.0;
+
+// /readyz fails if the node falls further behind the cluster
+#[from_env]
+pub const DEFAULT_READINESS_MAX_SLOT_LAG: u64 = 10;
+pub const DEFAULT_STARTUP_MIN_BLOCKS: u64 = 10;
+pub const DEFAULT_STARTUP_MAX_SLOT_LAG: u64 = 10;
+
+// slot lag alert thresholds per commitment, finalized normally trails the cluster by ~32 slots
+pub const DEFAULT_SLOT_LAG_ALERT_PROCESSED: u64 = 10;
+pub const DEFAULT_SLOT_LAG_ALERT_CONFIRMED: u64 = 20;
+pub const DEFAULT_SLOT_LAG_ALERT_FINALIZED: u64 = 64;
+pub const DEFAULT_SLOT_LAG_ALERT_SECS: u64 = 30;
+
+// share of traces exported to OTLP_ENDPOINT
+pub const DEFAULT_OTLP_SAMPLE_RATIO: f64 = 0.01;
+
+// number of blocks aggregated by getPrioFeesPercentiles if the request does not specify it
+pub const DEFAULT_PRIOFEES_PERCENTILES_LOOKBACK_BLOCKS: usize = 20;
+
+// number of blocks returned by getBlockUtilization if the request does not specify it
+pub const DEFAULT_BLOCK_UTILIZATION_LOOKBACK_BLOCKS: usize = 20;
+
+// defaults of getRecommendedPrioFeeForMessage
+pub const DEFAULT_PRIOFEE_RECOMMENDATION_PERCENTILE: u32 = 75;
+pub const DEFAULT_PRIOFEE_RECOMMENDATION_LOOKBACK_SLOTS: u64 = 20;
+
+// defaults and bound of getHottestAccounts
+pub const DEFAULT_HOTTEST_ACCOUNTS_LOOKBACK_SLOTS: u64 = 20;
+pub const DEFAULT_HOTTEST_ACCOUNTS_LIMIT: usize = 10;
+pub const MAX_HOTTEST_ACCOUNTS_LIMIT: usize = 100;
+
+// transactions sent this long before the end of a slot are also sent to the leader of the next slot
+#[from_env]
+pub const DEFAULT_SLOT_EDGE_FORWARDING_WINDOW_MS: u64 = 100;
+
+// bounds for adaptive send pacing towards a single leader, in transactions per second
+pub const DEFAULT_SEND_PACING_MIN_TPS: u64 = 100;
+pub const DEFAULT_SEND_PACING_MAX_TPS: u64 = 10_000;
+
+// how often the identity keypair file is checked for changes
+pub const IDENTITY_RELOAD_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+// how often the api keys file is checked for changes
+pub const API_KEYS_RELOAD_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+// how often the TLS certificate and key files are checked for changes if enabled
+pub const TLS_RELOAD_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+// how often the memory of the caches is checked against the memory budget if enabled
+pub const MEMORY_BUDGET_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+// how often the statistics of the global allocator are exported
+pub const ALLOCATOR_STATS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+// browsers cache CORS preflight responses for a day
+pub const DEFAULT_CORS_MAX_AGE_SECS: u64 = 86400;
+
+// time for each step of the graceful shutdown (answering requests, flushing transactions, saving blocks)
+pub const SHUTDOWN_PHASE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+// the redis or nats channels of the pub-sub sink are <prefix>.slots, <prefix>.blocks and <prefix>.transactions
+pub const DEFAULT_PUBSUB_SINK_CHANNEL_PREFIX: &str = "literpc";
+
+// interval of the line protocol push to INFLUX_UDP_ADDR
+pub const DEFAULT_INFLUX_PUSH_INTERVAL_SECS: u64 = 10;
+
+// unimplemented methods proxied to the rpc node, a timeout applies to every attempt
+pub const DEFAULT_UPSTREAM_PROXY_TIMEOUT_MS: u64 = 30_000;
+pub const DEFAULT_UPSTREAM_PROXY_RETRIES: usize = 1;
+
+// answers of expensive rpc node methods like getSupply are reused for this long
+pub const DEFAULT_PROXY_CACHE_TTL_SECS: u64 = 60;
+
+// getHealth of the rpc node and its fallbacks, closing the circuit breakers of recovered nodes
+pub const DEFAULT_RPC_HEALTH_CHECK_INTERVAL_SECS: u64 = 10;
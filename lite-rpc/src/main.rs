@@ -2,140 +2,330 @@ pub mod rpc_tester;
 
 use crate::rpc_tester::RpcTester;
 use anyhow::bail;
-use dashmap::DashMap;
+use anyhow::Context;
+#[cfg(feature = "accounts")]
+use itertools::Itertools;
+#[cfg(feature = "accounts")]
+use lite_rpc::account_filter_sets::{
+    merged_account_filters, parse_account_filter_sets, AccountFilterSetsService,
+};
+#[cfg(feature = "accounts")]
+use lite_rpc::account_webhooks::{AccountWebhookConfig, AccountWebhookSink};
+use lite_rpc::admin_server::{AdminServer, RecentErrorsLayer};
+use lite_rpc::allocator_stats::start_allocator_stats_task;
+use lite_rpc::api_key_auth::ApiKeys;
+use lite_rpc::block_arrival_comparator::BlockArrivalComparator;
+#[cfg(any(feature = "graphql", feature = "arrow-export"))]
+use lite_rpc::block_range::BlockRangeReader;
 use lite_rpc::bridge::LiteBridge;
+#[cfg(feature = "accounts")]
+use lite_rpc::bridge_accounts::LiteAccountsBridge;
+#[cfg(feature = "priofees")]
+use lite_rpc::bridge_priofees::LitePrioFeesBridge;
+#[cfg(feature = "ws-server")]
 use lite_rpc::bridge_pubsub::LitePubSubBridge;
-use lite_rpc::cli::Config;
-use lite_rpc::postgres_logger::PostgresLogger;
+#[cfg(feature = "accounts")]
+use lite_rpc::builder::start_account_service;
+use lite_rpc::builder::{create_data_cache, first_stopped_task, get_latest_block};
+#[cfg(feature = "priofees")]
+use lite_rpc::builder::{start_priofees_services, PrioFeesServices};
+use lite_rpc::cli::{Command, Config, GrpcSource};
+use lite_rpc::cluster_metrics::ClusterMetrics;
+use lite_rpc::cluster_router::ClusterRouter;
+use lite_rpc::config_reloader::{ConfigReloader, LogFilterHandle};
+use lite_rpc::cors::CorsConfig;
+use lite_rpc::epoch_value_cache::EpochValueCaches;
+use lite_rpc::geyser_server::GeyserReexportService;
+#[cfg(feature = "graphql")]
+use lite_rpc::graphql::QueryRoot;
+use lite_rpc::grpc_tx_service::GrpcTransactionService;
+use lite_rpc::health_endpoint::{HealthState, StartupGate};
+use lite_rpc::identity_reloader::IdentityReloader;
+use lite_rpc::influx_exporter::{InfluxExporter, InfluxExporterConfig};
+#[cfg(feature = "kafka")]
+use lite_rpc::kafka_sink::{KafkaSink, KafkaSinkConfig};
+#[cfg(feature = "postgres")]
+use lite_rpc::postgres_logger::{PostgresLogger, PostgresPrioFeesLogger, PostgresTxStatusNotifier};
+use lite_rpc::preflight;
+#[cfg(feature = "pubsub-sink")]
+use lite_rpc::pubsub_sink::{PubSubSink, PubSubSinkConfig};
+use lite_rpc::rate_limit::{RateLimitConfig, RateLimiter};
 use lite_rpc::service_spawner::ServiceSpawner;
-use lite_rpc::start_server::start_servers;
-use lite_rpc::{DEFAULT_MAX_NUMBER_OF_TXS_IN_QUEUE, MAX_NB_OF_CONNECTIONS_WITH_LEADERS};
+use lite_rpc::slot_lag_watchdog::{SlotLagThresholds, SlotLagWatchdog};
+use lite_rpc::start_server::{start_servers, DataApis, RpcMethods};
+use lite_rpc::tls::{CertificateReloader, TlsConfig};
+use lite_rpc::upstream_proxy::UpstreamProxyConfig;
+use lite_rpc::webhook_notifier::WebhookNotifier;
+use lite_rpc::{
+    ALLOCATOR_STATS_INTERVAL, API_KEYS_RELOAD_INTERVAL, DEFAULT_MAX_NUMBER_OF_TXS_IN_QUEUE,
+    DEFAULT_OTLP_SAMPLE_RATIO, DEFAULT_RUNTIME_WORKER_THREADS, IDENTITY_RELOAD_INTERVAL,
+    MAX_NB_OF_CONNECTIONS_WITH_LEADERS, MEMORY_BUDGET_CHECK_INTERVAL, SHUTDOWN_PHASE_TIMEOUT,
+    TLS_RELOAD_INTERVAL,
+};
 use log::{debug, info};
-use solana_lite_rpc_accounts::account_service::AccountService;
-use solana_lite_rpc_accounts::account_store_interface::AccountStorageInterface;
-use solana_lite_rpc_accounts::inmemory_account_store::InmemoryAccountStore;
+#[cfg(feature = "priofees")]
 use solana_lite_rpc_address_lookup_tables::address_lookup_table_store::AddressLookupTableStore;
+#[cfg(feature = "priofees")]
+use solana_lite_rpc_address_lookup_tables::resolved_block_stream::create_resolved_block_stream;
+use solana_lite_rpc_blockstore::block_stores::bigtable_block_storage::BigTableBlockStorage;
+use solana_lite_rpc_blockstore::block_stores::block_storage_service::BlockStorageService;
+use solana_lite_rpc_blockstore::block_stores::object_store_block_storage::ObjectStoreBlockStorage;
+#[cfg(feature = "postgres")]
+use solana_lite_rpc_blockstore::block_stores::postgres::{
+    postgres_block_storage::PostgresBlockStorage,
+    postgres_block_store_pruner::{BlockStoreRetentionConfig, PostgresBlockStorePruner},
+    postgres_block_store_query::PostgresQueryBlockStore,
+    postgres_block_store_writer::PostgresBlockStore,
+    postgres_epoch_manager::PostgresEpochManager,
+    PayloadCompression, PostgresSessionConfig,
+};
+use solana_lite_rpc_blockstore::block_stores::warm_start::load_recent_blocks;
 use solana_lite_rpc_blockstore::history::History;
+use solana_lite_rpc_blockstore::replay::{create_replay_subscription, ReplayConfig};
+use solana_lite_rpc_cluster_endpoints::clock_stream::create_latest_clock;
+#[cfg(feature = "ws-server")]
+use solana_lite_rpc_cluster_endpoints::commitment_upgrade_stream::create_commitment_upgrade_stream;
 use solana_lite_rpc_cluster_endpoints::endpoint_stremers::EndpointStreaming;
 use solana_lite_rpc_cluster_endpoints::grpc_inspect::{
     debugtask_blockstream_confirmation_sequence, debugtask_blockstream_slot_progression,
 };
-use solana_lite_rpc_cluster_endpoints::grpc_subscription::create_grpc_subscription;
+use solana_lite_rpc_cluster_endpoints::grpc_source_metrics;
+use solana_lite_rpc_cluster_endpoints::grpc_subscription::create_reloadable_grpc_subscription;
 use solana_lite_rpc_cluster_endpoints::json_rpc_leaders_getter::JsonRpcLeaderGetter;
 use solana_lite_rpc_cluster_endpoints::json_rpc_subscription::create_json_rpc_polling_subscription;
 use solana_lite_rpc_cluster_endpoints::rpc_polling::poll_blocks::NUM_PARALLEL_TASKS_DEFAULT;
+use solana_lite_rpc_cluster_endpoints::rpc_pool::RpcPool;
+use solana_lite_rpc_cluster_endpoints::stake_leader_schedule::StakeLeaderSchedule;
+use solana_lite_rpc_core::epoch_notifier::{EpochAwareCache, EpochNotifier};
 use solana_lite_rpc_core::keypair_loader::load_identity_keypair;
-use solana_lite_rpc_core::stores::{
-    block_information_store::{BlockInformation, BlockInformationStore},
-    cluster_info_store::ClusterInfo,
-    data_cache::{DataCache, SlotCache},
-    subscription_store::SubscriptionStore,
-    tx_store::TxStore,
-};
+use solana_lite_rpc_core::memory_budget::MemoryBudget;
 use solana_lite_rpc_core::structures::account_filter::AccountFilters;
-use solana_lite_rpc_core::structures::leaderschedule::CalculatedSchedule;
-use solana_lite_rpc_core::structures::{
-    epoch::EpochCache, identity_stakes::IdentityStakes, notifications::NotificationSender,
-    produced_block::ProducedBlock,
-};
+use solana_lite_rpc_core::structures::epoch::EpochCache;
+#[cfg(feature = "postgres")]
+use solana_lite_rpc_core::structures::notifications::NotificationSender;
+#[cfg(feature = "priofees")]
 use solana_lite_rpc_core::traits::address_lookup_table_interface::AddressLookupTableInterface;
+use solana_lite_rpc_core::traits::block_storage::BlockStorage;
+use solana_lite_rpc_core::traits::leaders_fetcher_interface::LeaderFetcherInterface;
 use solana_lite_rpc_core::types::BlockStream;
 use solana_lite_rpc_core::AnyhowJoinHandle;
-use solana_lite_rpc_prioritization_fees::account_prio_service::AccountPrioService;
 use solana_lite_rpc_services::data_caching_service::DataCachingService;
+use solana_lite_rpc_services::dry_run::DryRun;
+use solana_lite_rpc_services::fee_guardrails::{FeeGuardrails, FeeLimits, GuardrailAction};
+use solana_lite_rpc_services::optimistic_confirmation::OptimisticConfirmationTracker;
 use solana_lite_rpc_services::quic_connection_utils::QuicConnectionParameters;
+use solana_lite_rpc_services::tpu_utils::send_pacer::SendPacingConfig;
+use solana_lite_rpc_services::tpu_utils::send_runtime::{start_send_runtime, SendRuntimeConfig};
+use solana_lite_rpc_services::tpu_utils::send_strategy::DefaultSendStrategy;
 use solana_lite_rpc_services::tpu_utils::tpu_connection_path::TpuConnectionPath;
 use solana_lite_rpc_services::tpu_utils::tpu_service::{TpuService, TpuServiceConfig};
 use solana_lite_rpc_services::transaction_replayer::TransactionReplayer;
 use solana_lite_rpc_services::tx_sender::TxSender;
 
+#[cfg(feature = "postgres")]
 use lite_rpc::postgres_logger;
-use solana_lite_rpc_cluster_endpoints::geyser_grpc_connector::{
-    GrpcConnectionTimeouts, GrpcSourceConfig,
-};
-use solana_lite_rpc_prioritization_fees::start_block_priofees_task;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::trace::Sampler;
+use opentelemetry_sdk::Resource;
+#[cfg(feature = "priofees")]
+use solana_lite_rpc_prioritization_fees::priofees_window::PrioFeesWindowConfig;
 use solana_rpc_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::signature::Keypair;
 use solana_sdk::signer::Signer;
+#[cfg(feature = "quic-proxy")]
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::io::AsyncReadExt;
+use tokio::signal::unix::{signal, SignalKind};
+#[cfg(feature = "postgres")]
 use tokio::sync::mpsc;
-use tokio::sync::RwLock;
-use tokio::time::{timeout, Instant};
+use tokio::sync::watch;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+use tracing_subscriber::filter::{LevelFilter, Targets};
 use tracing_subscriber::fmt::format::FmtSpan;
-use tracing_subscriber::EnvFilter;
-
-async fn get_latest_block(
-    mut block_stream: BlockStream,
-    commitment_config: CommitmentConfig,
-) -> ProducedBlock {
-    let started = Instant::now();
-    loop {
-        match timeout(Duration::from_millis(500), block_stream.recv()).await {
-            Ok(Ok(block)) => {
-                if block.commitment_config == commitment_config {
-                    return block;
-                }
-            }
-            Err(_elapsed) => {
-                debug!(
-                    "waiting for latest block ({}) ... {:.02}ms",
-                    commitment_config.commitment,
-                    started.elapsed().as_secs_f32() * 1000.0
-                );
-            }
-            Ok(Err(_error)) => {
-                panic!("Did not recv blocks");
-            }
-        }
-    }
-}
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::reload;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[cfg(feature = "mimalloc")]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+// names the cluster of a process started by start_clusters
+const CLUSTER_ENV: &str = "LITE_RPC_CLUSTER";
 
+#[cfg(feature = "postgres")]
 pub async fn start_postgres(
     config: Option<postgres_logger::PostgresSessionConfig>,
-) -> anyhow::Result<(Option<NotificationSender>, AnyhowJoinHandle)> {
+    auto_migrate: bool,
+) -> anyhow::Result<(
+    Option<NotificationSender>,
+    Option<postgres_logger::PostgresSessionCache>,
+    AnyhowJoinHandle,
+)> {
     let Some(config) = config else {
-        return Ok((
-            None,
-            tokio::spawn(async {
-                std::future::pending::<()>().await;
-                unreachable!()
-            }),
-        ));
+        return Ok((None, None, pending_task()));
     };
 
     let (postgres_send, postgres_recv) = mpsc::unbounded_channel();
 
     let postgres_session_cache = postgres_logger::PostgresSessionCache::new(config).await?;
-    let postgres = PostgresLogger::start(postgres_session_cache, postgres_recv);
+    if auto_migrate {
+        postgres_logger::run_migrations(&postgres_session_cache.get_session().await?).await?;
+    }
+    let postgres = PostgresLogger::start(postgres_session_cache.clone(), postgres_recv);
 
-    Ok((Some(postgres_send), postgres))
+    Ok((Some(postgres_send), Some(postgres_session_cache), postgres))
 }
 
-pub async fn start_lite_rpc(args: Config, rpc_client: Arc<RpcClient>) -> anyhow::Result<()> {
+pub async fn start_lite_rpc(
+    args: Config,
+    rpc_client: Arc<RpcClient>,
+    log_filter: LogFilterHandle,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
     let grpc_sources = args.get_grpc_sources();
     log::info!("grpc_sources:{grpc_sources:?}");
+    let http_limits = args.http_limits();
+    let config_reloader = ConfigReloader::new(&args)?.with_log_filter(log_filter);
     let Config {
+        rpc_fallback_addrs,
+        rpc_health_check_interval_secs,
         lite_rpc_ws_addr,
         lite_rpc_http_addr,
+        server_workers,
+        enable_compression,
         fanout_size,
+        #[cfg(feature = "postgres")]
         postgres,
+        #[cfg(feature = "postgres")]
+        postgres_auto_migrate,
+        #[cfg(feature = "postgres")]
+        postgres_notify_transactions_channel,
+        #[cfg(feature = "postgres")]
+        postgres_notify_blocks_channel,
         prometheus_addr,
         identity_keypair,
         maximum_retries_per_tx,
         transaction_retry_after_secs,
+        verify_transaction_signatures,
+        max_compute_unit_price,
+        max_priority_fee_lamports,
+        max_compute_units,
+        fee_limits_warn_only,
+        dry_run,
         quic_proxy_addr,
         use_grpc,
         enable_grpc_stream_inspection,
+        grpc_max_message_size_mb,
+        #[cfg(feature = "priofees")]
         enable_address_lookup_tables,
+        #[cfg(feature = "priofees")]
         address_lookup_tables_binary,
         account_filters,
+        #[cfg(feature = "accounts")]
+        account_filter_sets,
+        #[cfg(feature = "accounts")]
+        account_webhooks,
+        enable_send_pacing,
+        send_pacing_min_tps,
+        send_pacing_max_tps,
+        send_runtime_threads,
+        send_runtime_cores,
+        local_leader_schedule,
+        optimistic_confirmation,
+        grpc_tx_service_addr,
+        geyser_grpc_addr,
+        slot_edge_forwarding_window_ms,
+        callback_url_allowed_hosts,
+        #[cfg(feature = "priofees")]
+        priofees_slots_to_retain,
+        #[cfg(feature = "priofees")]
+        priofees_max_data_points,
+        #[cfg(feature = "priofees")]
+        block_cu_limit,
+        #[cfg(all(feature = "postgres", feature = "priofees"))]
+        enable_priofees_history,
+        #[cfg(all(feature = "postgres", feature = "priofees"))]
+        priofees_history_retention_slots,
+        faithful_rpc_addr,
+        block_cache_size,
+        tx_status_retention_secs,
+        memory_budget_mb,
+        block_storage_url,
+        warm_start_slots,
+        replay_from_slot,
+        replay_to_slot,
+        replay_speed,
+        readiness_max_slot_lag,
+        startup_min_blocks,
+        startup_max_slot_lag,
+        admin_http_addr,
+        admin_token,
+        state_dump_dir,
+        block_arrival_reference_ws_addr,
+        slot_lag_alert_processed,
+        slot_lag_alert_confirmed,
+        slot_lag_alert_finalized,
+        slot_lag_alert_secs,
+        slot_lag_alert_webhook_url,
+        tls_cert_path,
+        tls_key_path,
+        tls_reload_on_change,
+        api_keys,
+        api_keys_file,
+        cors_allowed_origins,
+        cors_allowed_methods,
+        cors_allowed_headers,
+        cors_max_age_secs,
+        rate_limit_rps,
+        rate_limit_burst,
+        trusted_proxies,
+        #[cfg(feature = "kafka")]
+        kafka_brokers,
+        #[cfg(feature = "kafka")]
+        kafka_blocks_topic,
+        #[cfg(feature = "kafka")]
+        kafka_transactions_topic,
+        #[cfg(feature = "kafka")]
+        kafka_format,
+        #[cfg(feature = "kafka")]
+        kafka_commitment,
+        #[cfg(feature = "kafka")]
+        kafka_include_votes,
+        #[cfg(feature = "kafka")]
+        kafka_producer_config,
+        #[cfg(feature = "pubsub-sink")]
+        pubsub_sink_url,
+        #[cfg(feature = "pubsub-sink")]
+        pubsub_sink_channel_prefix,
+        #[cfg(feature = "pubsub-sink")]
+        pubsub_sink_format,
+        influx_udp_addr,
+        influx_push_interval_secs,
+        influx_tags,
+        enable_upstream_proxy,
+        upstream_proxy_timeout_ms,
+        upstream_proxy_retries,
+        proxy_cache_ttl_secs,
+        #[cfg(feature = "graphql")]
+        enable_graphql,
+        #[cfg(feature = "arrow-export")]
+        enable_arrow_export,
         ..
     } = args;
 
+    let identity_path = identity_keypair.clone();
     let validator_identity = Arc::new(
         load_identity_keypair(identity_keypair)
             .await?
@@ -152,37 +342,143 @@ pub async fn start_lite_rpc(args: Config, rpc_client: Arc<RpcClient>) -> anyhow:
     } else {
         vec![]
     };
+    #[cfg(feature = "accounts")]
+    let account_filter_sets = parse_account_filter_sets(&account_filter_sets)?;
+    // the accounts of the sets are subscribed with the account filters
+    #[cfg(feature = "accounts")]
+    let set_account_filters = merged_account_filters(&account_filter_sets);
+    #[cfg(feature = "accounts")]
+    let subscribed_account_filters = account_filters
+        .iter()
+        .chain(set_account_filters.iter())
+        .cloned()
+        .collect::<AccountFilters>();
+    #[cfg(not(feature = "accounts"))]
+    let subscribed_account_filters = account_filters.clone();
 
-    let (subscriptions, cluster_endpoint_tasks) = if use_grpc {
-        info!("Creating geyser subscription...");
-
-        let timeouts = GrpcConnectionTimeouts {
-            connect_timeout: Duration::from_secs(5),
-            request_timeout: Duration::from_secs(5),
-            subscribe_timeout: Duration::from_secs(5),
-            receive_timeout: Duration::from_secs(5),
-        };
-        create_grpc_subscription(
-            rpc_client.clone(),
-            grpc_sources
-                .iter()
-                .map(|s| {
-                    GrpcSourceConfig::new(s.addr.clone(), s.x_token.clone(), None, timeouts.clone())
-                })
-                .collect(),
-            account_filters.clone(),
-        )?
+    // the cluster polling and the upstream proxy fail over to the fallback rpc nodes
+    let rpc_pool = RpcPool::new(
+        rpc_client.clone(),
+        rpc_fallback_addrs
+            .into_iter()
+            .map(|rpc_addr| Arc::new(RpcClient::new(rpc_addr)))
+            .collect(),
+    );
+    let rpc_health_check_task = if rpc_pool.len() > 1 {
+        info!("Using {} fallback rpc nodes", rpc_pool.len() - 1);
+        rpc_pool
+            .clone()
+            .start_health_checks(Duration::from_secs(rpc_health_check_interval_secs))
     } else {
-        info!("Creating RPC poll subscription...");
-        create_json_rpc_polling_subscription(rpc_client.clone(), NUM_PARALLEL_TASKS_DEFAULT)?
+        pending_task()
     };
+
+    // sources and filters are replaced when the configuration is reloaded
+    #[cfg_attr(not(feature = "accounts"), allow(unused_variables))]
+    let (account_filters_sender, account_filters_rx) =
+        watch::channel(subscribed_account_filters.clone());
+    let (epoch_data, _current_epoch_info) = EpochCache::bootstrap_epoch(&rpc_client).await?;
+    let (block_storage, block_store_pruner_task, block_store_epoch_manager): (
+        Option<Arc<dyn BlockStorage>>,
+        _,
+        Option<Arc<dyn EpochAwareCache>>,
+    ) = match block_storage_url {
+        // postgres://... keeps one schema per epoch, the pruner drops the epochs out of the retention
+        #[cfg(feature = "postgres")]
+        Some(block_storage_url) if is_postgres_url(&block_storage_url) => {
+            let (block_storage, pruner, epoch_manager) = create_postgres_block_storage(
+                block_storage_url,
+                epoch_data.clone(),
+                postgres_notify_blocks_channel,
+            )
+            .await?;
+            (
+                Some(block_storage),
+                pruner.start(),
+                Some(Arc::new(epoch_manager)),
+            )
+        }
+        #[cfg(not(feature = "postgres"))]
+        Some(block_storage_url) if is_postgres_url(&block_storage_url) => {
+            bail!("lite-rpc was built without the postgres feature, use an object store or bigtable block storage")
+        }
+        // bigtable://<instance name> reads the blocks uploaded by a validator in the solana-validator schema,
+        // with ?write=true the finalized blocks are written to it as well
+        Some(block_storage_url) => match block_storage_url.strip_prefix("bigtable://") {
+            Some(instance) => {
+                let (instance_name, read_only) = match instance.strip_suffix("?write=true") {
+                    Some(instance_name) => (instance_name, false),
+                    None => (instance, true),
+                };
+                let block_storage =
+                    BigTableBlockStorage::new(instance_name.to_string(), None, read_only).await?;
+                let slot_range_refresh = block_storage.start_slot_range_refresh();
+                (Some(Arc::new(block_storage)), slot_range_refresh, None)
+            }
+            None => (
+                Some(Arc::new(
+                    ObjectStoreBlockStorage::new(&block_storage_url).await?,
+                )),
+                pending_task(),
+                None,
+            ),
+        },
+        None => (None, pending_task(), None),
+    };
+
+    let (subscriptions, cluster_endpoint_tasks, config_reloader) =
+        if let Some(from_slot) = replay_from_slot {
+            let Some(block_storage) = block_storage.clone() else {
+                bail!("replaying blocks requires a block storage");
+            };
+            info!("Creating replay of the stored blocks...");
+            let (subscriptions, cluster_endpoint_tasks) = create_replay_subscription(
+                block_storage,
+                rpc_pool.clone(),
+                ReplayConfig {
+                    from_slot,
+                    to_slot: replay_to_slot,
+                    speed: replay_speed,
+                },
+            )?;
+            (subscriptions, cluster_endpoint_tasks, config_reloader)
+        } else if use_grpc {
+            info!("Creating geyser subscription...");
+            grpc_source_metrics::set_max_message_size(grpc_max_message_size_mb * 1024 * 1024);
+
+            let (grpc_sources_sender, grpc_sources_rx) = watch::channel(
+                grpc_sources
+                    .iter()
+                    .map(GrpcSource::to_source_config)
+                    .collect(),
+            );
+            let (subscriptions, cluster_endpoint_tasks) = create_reloadable_grpc_subscription(
+                rpc_pool.clone(),
+                grpc_sources_rx,
+                account_filters_rx,
+            )?;
+            (
+                subscriptions,
+                cluster_endpoint_tasks,
+                config_reloader.with_grpc_sources(grpc_sources_sender),
+            )
+        } else {
+            info!("Creating RPC poll subscription...");
+            let (subscriptions, cluster_endpoint_tasks) =
+                create_json_rpc_polling_subscription(rpc_pool.clone(), NUM_PARALLEL_TASKS_DEFAULT)?;
+            (subscriptions, cluster_endpoint_tasks, config_reloader)
+        };
     let EndpointStreaming {
         // note: blocks_notifier will be dropped at some point
         blocks_notifier,
+        block_info_notifier,
         cluster_info_notifier,
         slot_notifier,
         vote_account_notifier,
         processed_account_stream,
+        // counted and logged by the multiplexer
+        block_ordering_notifier: _,
+        clock_notifier,
     } = subscriptions;
 
     if enable_grpc_stream_inspection {
@@ -191,100 +487,364 @@ pub async fn start_lite_rpc(args: Config, rpc_client: Arc<RpcClient>) -> anyhow:
         info!("Disabled grpc stream inspection");
     }
 
-    let accounts_service = if let Some(account_stream) = processed_account_stream {
-        // lets use inmemory storage for now
-        let inmemory_account_storage: Arc<dyn AccountStorageInterface> =
-            Arc::new(InmemoryAccountStore::new());
-        const MAX_CONNECTIONS_IN_PARALLEL: usize = 10;
-        let account_service = AccountService::new(inmemory_account_storage);
+    // re-exported by the geyser gRPC server
+    let reexported_account_stream = processed_account_stream
+        .as_ref()
+        .map(|account_stream| account_stream.resubscribe());
+    // applied to the cached address lookup tables
+    #[cfg(feature = "priofees")]
+    let alt_account_stream = processed_account_stream
+        .as_ref()
+        .filter(|_| enable_address_lookup_tables.unwrap_or_default())
+        .map(|account_stream| account_stream.resubscribe());
+    #[cfg(feature = "priofees")]
+    let (address_lookup_tables, alt_updates_task): (
+        Option<Arc<dyn AddressLookupTableInterface>>,
+        AnyhowJoinHandle,
+    ) = if enable_address_lookup_tables.unwrap_or_default() {
+        log::info!("ALTs enabled");
+        let alts_store = AddressLookupTableStore::new(rpc_client.clone());
+        if let Some(address_lookup_tables_binary) = address_lookup_tables_binary {
+            match tokio::fs::File::open(address_lookup_tables_binary).await {
+                Ok(mut alts_file) => {
+                    let mut buf = vec![];
+                    alts_file.read_to_end(&mut buf).await.unwrap();
+                    alts_store.load_binary(buf);
 
-        account_service
-            .process_account_stream(account_stream.resubscribe(), blocks_notifier.resubscribe());
+                    log::info!("{} ALTs loaded from binary file", alts_store.map.len());
+                }
+                Err(e) => {
+                    log::error!("Error loading address lookup tables binary : {e:?}");
+                    anyhow::bail!(e.to_string());
+                }
+            }
+        }
+        let alt_updates_task = match alt_account_stream {
+            Some(account_stream) => alts_store.start_account_updates(account_stream),
+            None => pending_task(),
+        };
+        (Some(Arc::new(alts_store)), alt_updates_task)
+    } else {
+        log::info!("ALTs disabled");
+        (None, pending_task())
+    };
+    // every consumer of the blocks gets the accounts loaded from address lookup tables
+    #[cfg(feature = "priofees")]
+    let (blocks_notifier, alt_resolution_task) = match &address_lookup_tables {
+        Some(address_lookup_tables) => {
+            create_resolved_block_stream(blocks_notifier, address_lookup_tables.clone())
+        }
+        None => (blocks_notifier, pending_task()),
+    };
+    #[cfg(not(feature = "priofees"))]
+    let (alt_updates_task, alt_resolution_task) = (pending_task(), pending_task());
 
-        account_service
-            .populate_from_rpc(
-                rpc_client.clone(),
-                &account_filters,
-                MAX_CONNECTIONS_IN_PARALLEL,
+    #[cfg(feature = "accounts")]
+    const MAX_CONNECTIONS_IN_PARALLEL: usize = 10;
+    #[cfg(feature = "accounts")]
+    let account_filter_sets_service = AccountFilterSetsService::new(account_filter_sets);
+    #[cfg(feature = "accounts")]
+    let (accounts_service, inmemory_account_store, account_filter_sets_task, account_stream_tasks) =
+        if let Some(account_stream) = processed_account_stream {
+            // lets use inmemory storage for now
+            let (account_service, inmemory_account_store, account_stream_tasks) =
+                start_account_service(
+                    account_stream.resubscribe(),
+                    blocks_notifier.resubscribe(),
+                    rpc_pool.clone(),
+                    &subscribed_account_filters,
+                )
+                .await?;
+
+            let account_filter_sets_task = if account_filter_sets_service.is_empty() {
+                pending_task()
+            } else {
+                let task = account_filter_sets_service
+                    .start(account_stream.resubscribe(), blocks_notifier.resubscribe());
+                account_filter_sets_service
+                    .populate_from_rpc(rpc_client.clone(), MAX_CONNECTIONS_IN_PARALLEL)
+                    .await?;
+                task
+            };
+            (
+                Some(account_service),
+                Some(inmemory_account_store),
+                account_filter_sets_task,
+                account_stream_tasks,
             )
-            .await?;
-        Some(account_service)
-    } else {
-        None
+        } else {
+            (None, None, pending_task(), vec![])
+        };
+    #[cfg(not(feature = "accounts"))]
+    let (account_filter_sets_task, account_stream_tasks) = (pending_task(), vec![]);
+
+    #[cfg(feature = "accounts")]
+    let account_webhooks_task = match (account_webhooks, &accounts_service) {
+        (Some(account_webhooks), Some(accounts_service)) => {
+            let account_webhooks =
+                serde_json::from_str::<Vec<AccountWebhookConfig>>(&account_webhooks)
+                    .context("Account webhooks should be valid")?;
+            // one sink per stream, the webhooks of a filter set only see the accounts of the set
+            let mut tasks = vec![];
+            for (filter_set, account_webhooks) in account_webhooks
+                .into_iter()
+                .into_group_map_by(|account_webhook| account_webhook.filter_set.clone())
+            {
+                let account_service = match filter_set {
+                    Some(filter_set) => account_filter_sets_service
+                        .account_service(&filter_set)
+                        .with_context(|| format!("unknown account filter set {filter_set}"))?,
+                    None => accounts_service.clone(),
+                };
+                tasks.push(
+                    AccountWebhookSink::new(account_webhooks)?
+                        .start(account_service.account_notification_sender.subscribe()),
+                );
+            }
+            if tasks.is_empty() {
+                pending_task()
+            } else {
+                tokio::spawn(async move { futures::future::select_all(tasks).await.0? })
+            }
+        }
+        (Some(_), None) => bail!("account webhooks require account filters and a grpc source"),
+        (None, _) => pending_task(),
+    };
+    #[cfg(not(feature = "accounts"))]
+    let account_webhooks_task = pending_task();
+    #[cfg(feature = "accounts")]
+    let config_reloader = match &accounts_service {
+        Some(accounts_service) => config_reloader.with_account_filters(
+            account_filters_sender,
+            accounts_service.clone(),
+            rpc_client.clone(),
+            set_account_filters,
+        ),
+        None => config_reloader,
     };
 
     info!("Waiting for first finalized block...");
     let finalized_block =
-        get_latest_block(blocks_notifier.resubscribe(), CommitmentConfig::finalized()).await;
+        get_latest_block(blocks_notifier.resubscribe(), CommitmentConfig::finalized()).await?;
     info!("Got finalized block: {:?}", finalized_block.slot);
 
-    let (epoch_data, _current_epoch_info) = EpochCache::bootstrap_epoch(&rpc_client).await?;
+    // replay the newest stored blocks before serving requests, a replay of the history starts without them
+    let warm_start_blocks = match &block_storage {
+        Some(block_storage) if replay_from_slot.is_none() => {
+            load_recent_blocks(block_storage.as_ref(), warm_start_slots).await
+        }
+        _ => vec![],
+    };
 
-    let block_information_store =
-        BlockInformationStore::new(BlockInformation::from_block(&finalized_block));
-
-    let data_cache = DataCache {
-        block_information_store,
-        cluster_info: ClusterInfo::default(),
-        identity_stakes: IdentityStakes::new(validator_identity.pubkey()),
-        slot_cache: SlotCache::new(finalized_block.slot),
-        tx_subs: SubscriptionStore::default(),
-        txs: TxStore {
-            store: Arc::new(DashMap::new()),
-        },
+    let data_cache = create_data_cache(
+        &finalized_block,
         epoch_data,
-        leader_schedule: Arc::new(RwLock::new(CalculatedSchedule::default())),
-    };
+        validator_identity.pubkey(),
+        block_cache_size,
+    );
 
-    let data_cache_service = DataCachingService {
+    let mut data_cache_service = DataCachingService {
         data_cache: data_cache.clone(),
         clean_duration: Duration::from_secs(120),
+        tx_status_retention: Duration::from_secs(tx_status_retention_secs),
+        optimistically_confirmed_blocks: None,
+    };
+    data_cache_service.warm_start(&warm_start_blocks).await;
+
+    // blocks confirmed by the observed votes, for sources which only stream processed blocks
+    let optimistic_confirmation_task = if optimistic_confirmation {
+        // the vote account stream is polled rarely, its first update was already sent
+        let mut tracker = OptimisticConfirmationTracker::default();
+        tracker.update_stakes(&rpc_client.get_vote_accounts().await?);
+        let (confirmed_blocks, tracker_task) = tracker.start(
+            blocks_notifier.resubscribe(),
+            vote_account_notifier.resubscribe(),
+        );
+        data_cache_service.optimistically_confirmed_blocks = Some(confirmed_blocks);
+        Some(tracker_task)
+    } else {
+        None
     };
 
     // to avoid laggin we resubscribe to block notification
-    let data_caching_service = data_cache_service.listen(
+    let mut data_caching_service = data_cache_service.listen(
         blocks_notifier.resubscribe(),
         slot_notifier.resubscribe(),
         cluster_info_notifier,
         vote_account_notifier,
     );
+    data_caching_service.extend(optimistic_confirmation_task);
 
-    let (block_priofees_task, block_priofees_service) =
-        start_block_priofees_task(blocks_notifier.resubscribe(), 100);
-
-    let address_lookup_tables: Option<Arc<dyn AddressLookupTableInterface>> =
-        if enable_address_lookup_tables.unwrap_or_default() {
-            log::info!("ALTs enabled");
-            let alts_store = AddressLookupTableStore::new(rpc_client.clone());
-            if let Some(address_lookup_tables_binary) = address_lookup_tables_binary {
-                match tokio::fs::File::open(address_lookup_tables_binary).await {
-                    Ok(mut alts_file) => {
-                        let mut buf = vec![];
-                        alts_file.read_to_end(&mut buf).await.unwrap();
-                        alts_store.load_binary(buf);
-
-                        log::info!("{} ALTs loaded from binary file", alts_store.map.len());
-                    }
-                    Err(e) => {
-                        log::error!("Error loading address lookup tables binary : {e:?}");
-                        anyhow::bail!(e.to_string());
-                    }
-                }
-            }
-            Some(Arc::new(alts_store))
-        } else {
-            log::info!("ALTs disabled");
-            None
+    #[cfg(feature = "priofees")]
+    let PrioFeesServices {
+        block_priofees_service,
+        account_priofees_service,
+        program_priofees_service,
+        tasks: priofees_tasks,
+    } = {
+        let priofees_services = start_priofees_services(
+            &blocks_notifier,
+            PrioFeesWindowConfig {
+                slots_to_retain: priofees_slots_to_retain,
+                max_data_points: priofees_max_data_points,
+            },
+            block_cu_limit,
+            address_lookup_tables,
+        );
+        priofees_services
+            .block_priofees_service
+            .warm_start(&warm_start_blocks)
+            .await;
+        priofees_services
+            .account_priofees_service
+            .warm_start(&warm_start_blocks)
+            .await;
+        priofees_services
+            .program_priofees_service
+            .warm_start(&warm_start_blocks);
+        priofees_services
+    };
+    #[cfg(not(feature = "priofees"))]
+    let priofees_tasks = vec![];
+    drop(warm_start_blocks);
+
+    #[cfg(feature = "postgres")]
+    let (notification_channel, postgres_session_cache, postgres) =
+        start_postgres(postgres, postgres_auto_migrate).await?;
+    #[cfg(not(feature = "postgres"))]
+    let (notification_channel, postgres) = (None, pending_task());
+
+    #[cfg(all(feature = "postgres", feature = "priofees"))]
+    let priofees_history_task = match (postgres_session_cache.clone(), enable_priofees_history) {
+        (Some(postgres_session_cache), true) => PostgresPrioFeesLogger::start(
+            postgres_session_cache,
+            block_priofees_service.block_fees_stream.subscribe(),
+            account_priofees_service.priofees_update_sender.subscribe(),
+            priofees_history_retention_slots,
+        ),
+        (None, true) => bail!("prioritization fee history requires postgres to be enabled"),
+        _ => pending_task(),
+    };
+    #[cfg(not(all(feature = "postgres", feature = "priofees")))]
+    let priofees_history_task = pending_task();
+
+    #[cfg(feature = "postgres")]
+    let postgres_notifier_task =
+        match (postgres_session_cache, postgres_notify_transactions_channel) {
+            (Some(postgres_session_cache), Some(channel)) => PostgresTxStatusNotifier::start(
+                postgres_session_cache,
+                blocks_notifier.resubscribe(),
+                data_cache.txs.clone(),
+                channel,
+            ),
+            (None, Some(_)) => bail!("postgres notifications require postgres to be enabled"),
+            _ => pending_task(),
         };
+    #[cfg(not(feature = "postgres"))]
+    let postgres_notifier_task = pending_task();
 
-    let (account_priofees_task, account_priofees_service) =
-        AccountPrioService::start_account_priofees_task(
+    // replayed blocks are already stored
+    let mut block_storage_task = match block_storage
+        .clone()
+        .filter(|block_storage| replay_from_slot.is_none() && !block_storage.is_read_only())
+    {
+        Some(block_storage) => BlockStorageService::start(
+            block_storage,
             blocks_notifier.resubscribe(),
-            100,
-            address_lookup_tables,
-        );
+            shutdown.clone(),
+        ),
+        None => stopped_on_shutdown(shutdown.clone()),
+    };
 
-    let (notification_channel, postgres) = start_postgres(postgres).await?;
+    #[cfg(feature = "kafka")]
+    let mut kafka_sink_task = match kafka_brokers {
+        Some(brokers) => KafkaSink::new(KafkaSinkConfig {
+            brokers,
+            blocks_topic: kafka_blocks_topic,
+            transactions_topic: kafka_transactions_topic,
+            format: kafka_format,
+            commitment: kafka_commitment,
+            include_votes: kafka_include_votes,
+            producer_config: kafka_producer_config,
+        })?
+        .start(
+            blocks_notifier.resubscribe(),
+            shutdown.clone(),
+            SHUTDOWN_PHASE_TIMEOUT,
+        ),
+        None => stopped_on_shutdown(shutdown.clone()),
+    };
+    #[cfg(not(feature = "kafka"))]
+    let mut kafka_sink_task = stopped_on_shutdown(shutdown.clone());
+
+    #[cfg(feature = "pubsub-sink")]
+    let pubsub_sink_task: AnyhowJoinHandle = match pubsub_sink_url {
+        Some(url) => PubSubSink::connect(PubSubSinkConfig {
+            url,
+            channel_prefix: pubsub_sink_channel_prefix,
+            format: pubsub_sink_format,
+        })
+        .await?
+        .start(
+            slot_notifier.resubscribe(),
+            blocks_notifier.resubscribe(),
+            data_cache.txs.clone(),
+        ),
+        None => pending_task(),
+    };
+    #[cfg(not(feature = "pubsub-sink"))]
+    let pubsub_sink_task = pending_task();
+
+    let memory_budget = match memory_budget_mb {
+        Some(budget_mb) => MemoryBudget::new(budget_mb as usize * 1024 * 1024),
+        // the memory of the caches is still exported
+        None => MemoryBudget::unbounded(),
+    }
+    .with_consumer(Arc::new(data_cache.block_cache.clone()));
+    #[cfg(feature = "priofees")]
+    let memory_budget =
+        memory_budget.with_consumer(Arc::new(block_priofees_service.block_fees_store.clone()));
+    let memory_budget = memory_budget
+        .with_consumer(Arc::new(data_cache.txs.clone()))
+        .with_consumer(Arc::new(data_cache.signature_statuses.clone()))
+        .with_consumer(Arc::new(data_cache.tx_lifecycles.clone()));
+    #[cfg(feature = "accounts")]
+    let memory_budget = match inmemory_account_store {
+        Some(inmemory_account_store) => memory_budget.with_consumer(inmemory_account_store),
+        None => memory_budget,
+    };
+    #[cfg(feature = "accounts")]
+    let memory_budget = account_filter_sets_service
+        .account_stores()
+        .into_iter()
+        .fold(memory_budget, |memory_budget, account_store| {
+            memory_budget.with_consumer(account_store)
+        });
+    // without a budget the usage is only exported, as often as the allocator statistics
+    let memory_budget_interval = match memory_budget.budget_bytes() {
+        Some(_) => MEMORY_BUDGET_CHECK_INTERVAL,
+        None => ALLOCATOR_STATS_INTERVAL,
+    };
+    let memory_budget_task = memory_budget.clone().start(memory_budget_interval);
+    let allocator_stats_task = start_allocator_stats_task(ALLOCATOR_STATS_INTERVAL);
+
+    let influx_exporter_task: AnyhowJoinHandle = match influx_udp_addr {
+        Some(addr) => {
+            let influx_exporter = InfluxExporter::new(
+                InfluxExporterConfig {
+                    addr,
+                    interval: Duration::from_secs(influx_push_interval_secs),
+                    tags: influx_tags,
+                },
+                data_cache.clone(),
+            );
+            #[cfg(feature = "priofees")]
+            let influx_exporter = influx_exporter
+                .with_block_fees(block_priofees_service.block_fees_stream.subscribe());
+            influx_exporter.start()
+        }
+        None => pending_task(),
+    };
 
     let tpu_config = TpuServiceConfig {
         fanout_slots: fanout_size,
@@ -299,8 +859,15 @@ pub async fn start_lite_rpc(args: Config, rpc_client: Arc<RpcClient>) -> anyhow:
             unistream_timeout: Duration::from_millis(500),
             write_timeout: Duration::from_secs(1),
             number_of_transactions_per_unistream: 1,
+            send_pacing: SendPacingConfig {
+                enabled: enable_send_pacing,
+                min_txs_per_second: send_pacing_min_tps,
+                max_txs_per_second: send_pacing_max_tps,
+            },
         },
         tpu_connection_path,
+        slot_edge_forwarding_window: (slot_edge_forwarding_window_ms > 0)
+            .then_some(Duration::from_millis(slot_edge_forwarding_window_ms)),
     };
 
     let spawner = ServiceSpawner {
@@ -308,17 +875,99 @@ pub async fn start_lite_rpc(args: Config, rpc_client: Arc<RpcClient>) -> anyhow:
         data_cache: data_cache.clone(),
     };
     //init grpc leader schedule and vote account is configured.
-    let leader_schedule = Arc::new(JsonRpcLeaderGetter::new(rpc_client.clone(), 1024, 128));
-    let tpu_service: TpuService = TpuService::new(
+    // caches refreshed at the epoch boundaries register with the epoch notifier
+    let epoch_value_caches = EpochValueCaches::new(rpc_client.clone());
+    let epoch_notifier =
+        epoch_value_caches.register(EpochNotifier::new(data_cache.epoch_data.clone()));
+    let epoch_notifier = match block_store_epoch_manager {
+        Some(epoch_manager) => epoch_notifier.with_cache(epoch_manager),
+        None => epoch_notifier,
+    };
+    let (leader_schedule, epoch_notifier): (Arc<dyn LeaderFetcherInterface>, _) =
+        if local_leader_schedule {
+            let stake_leader_schedule = StakeLeaderSchedule::new(
+                rpc_client.clone(),
+                data_cache.epoch_data.clone(),
+                data_cache.leader_schedule.clone(),
+            );
+            stake_leader_schedule
+                .bootstrap(finalized_block.slot)
+                .await?;
+            (
+                Arc::new(stake_leader_schedule.leader_getter()),
+                epoch_notifier.with_cache(Arc::new(stake_leader_schedule)),
+            )
+        } else {
+            (
+                Arc::new(JsonRpcLeaderGetter::new(rpc_client.clone(), 1024, 128)),
+                epoch_notifier,
+            )
+        };
+    let epoch_notifier_task = epoch_notifier.start(slot_notifier.resubscribe());
+    // the send path shares the main runtime unless it is given threads of its own
+    let send_runtime = match send_runtime_threads {
+        Some(worker_threads) => start_send_runtime(&SendRuntimeConfig {
+            worker_threads,
+            core_ids: send_runtime_cores,
+        })?,
+        None => tokio::runtime::Handle::current(),
+    };
+    let tpu_service: TpuService = TpuService::new_on_runtime(
         tpu_config,
-        validator_identity,
+        validator_identity.clone(),
         leader_schedule,
+        Arc::new(DefaultSendStrategy),
         data_cache.clone(),
+        send_runtime,
     )
     .await?;
+    let identity_reloader = identity_path.map(|identity_path| {
+        IdentityReloader::new(
+            identity_path,
+            tpu_service.clone(),
+            validator_identity.pubkey(),
+        )
+    });
+    let identity_reload_task: AnyhowJoinHandle = match &identity_reloader {
+        Some(identity_reloader) => identity_reloader.start_watching(IDENTITY_RELOAD_INTERVAL),
+        None => pending_task(),
+    };
+
+    let rate_limiter = match rate_limit_rps {
+        Some(requests_per_second) => Some(RateLimiter::new(RateLimitConfig {
+            requests_per_second,
+            burst: rate_limit_burst.unwrap_or(requests_per_second),
+            trusted_proxies,
+        })?),
+        None => None,
+    };
+
+    let config_reloader = config_reloader.with_tpu_service(tpu_service.clone());
+    let config_reloader = Arc::new(match &rate_limiter {
+        Some(rate_limiter) => config_reloader.with_rate_limiter(rate_limiter.clone()),
+        None => config_reloader,
+    });
+    let config_reload_task = config_reloader.clone().start();
     let tx_sender = TxSender::new(data_cache.clone(), tpu_service.clone());
-    let tx_replayer =
-        TransactionReplayer::new(tpu_service.clone(), data_cache.txs.clone(), retry_after);
+    let fee_guardrails = FeeGuardrails::new(FeeLimits {
+        max_compute_unit_price,
+        max_priority_fee_lamports,
+        max_compute_units,
+        action: if fee_limits_warn_only {
+            GuardrailAction::Warn
+        } else {
+            GuardrailAction::Reject
+        },
+    });
+    // global, or for the api keys with `dry_run` set on every reload of the keys
+    let dry_run = DryRun::new(dry_run);
+    let tx_replayer = TransactionReplayer::new(
+        tpu_service.clone(),
+        data_cache.txs.clone(),
+        data_cache.tx_lifecycles.clone(),
+        data_cache.blockhash_cache.clone(),
+        retry_after,
+    );
     let (transaction_service, tx_service_jh) = spawner.spawn_tx_service(
         tx_sender,
         tx_replayer,
@@ -326,49 +975,278 @@ pub async fn start_lite_rpc(args: Config, rpc_client: Arc<RpcClient>) -> anyhow:
         DEFAULT_MAX_NUMBER_OF_TXS_IN_QUEUE,
         notification_channel.clone(),
         maximum_retries_per_tx,
-        slot_notifier.resubscribe(),
+        verify_transaction_signatures,
+        fee_guardrails.clone(),
+        dry_run.clone(),
     );
+    // the slots, blockhashes and leaders of a replay are historical, transactions sent with them
+    // would not land, the admin api cannot resume the sending
+    if replay_from_slot.is_some() {
+        info!("Rejecting transactions while replaying the stored blocks");
+        transaction_service.start_draining();
+    }
+
+    let health_state = HealthState::new(
+        data_cache.clone(),
+        readiness_max_slot_lag,
+        StartupGate {
+            min_blocks: startup_min_blocks,
+            max_slot_lag: startup_max_slot_lag,
+        },
+    );
+    let health_task = health_state.start(block_info_notifier.resubscribe());
+    let tx_service_jh = health_state.track_tpu_service(tx_service_jh);
 
     let support_service = tokio::spawn(async move { spawner.spawn_support_services().await });
 
-    let history = History::new();
+    let api_keys = if !api_keys.is_empty() || api_keys_file.is_some() {
+        let api_keys = ApiKeys::new(api_keys, api_keys_file)?
+            .with_fee_guardrails(fee_guardrails)?
+            .with_dry_run(dry_run)?;
+        api_keys.start_watching(API_KEYS_RELOAD_INTERVAL);
+        Some(api_keys)
+    } else {
+        None
+    };
+
+    let mut geyser_server: AnyhowJoinHandle = match geyser_grpc_addr {
+        Some(addr) => GeyserReexportService::new(
+            data_cache.clone(),
+            blocks_notifier.resubscribe(),
+            slot_notifier.resubscribe(),
+            reexported_account_stream,
+        )
+        .with_api_keys(api_keys.clone())
+        .start(addr.parse()?, shutdown.clone()),
+        None => stopped_on_shutdown(shutdown.clone()),
+    };
+
+    let mut grpc_tx_service: AnyhowJoinHandle = match grpc_tx_service_addr {
+        Some(addr) => GrpcTransactionService::new(
+            transaction_service.clone(),
+            data_cache.clone(),
+            health_state.clone(),
+        )
+        .with_api_keys(api_keys.clone())
+        .start(addr.parse()?, shutdown.clone()),
+        None => stopped_on_shutdown(shutdown.clone()),
+    };
 
-    let rpc_service = LiteBridge::new(
+    let faithful_rpc_client = faithful_rpc_addr.map(|faithful_rpc_addr| {
+        info!("Serving history beyond the rpc node ledger from {faithful_rpc_addr}");
+        Arc::new(RpcClient::new(faithful_rpc_addr))
+    });
+    let new_history = || match &block_storage {
+        Some(block_storage) => History::new(rpc_client.clone(), faithful_rpc_client.clone())
+            .with_block_storage(block_storage.clone()),
+        None => History::new(rpc_client.clone(), faithful_rpc_client.clone()),
+    };
+    let history = new_history();
+    let data_apis = DataApis::default();
+    #[cfg(any(feature = "graphql", feature = "arrow-export"))]
+    let block_range_reader = BlockRangeReader::new(data_cache.clone(), block_storage.clone());
+    #[cfg(feature = "graphql")]
+    let data_apis = if enable_graphql {
+        let query_root = QueryRoot::new(block_range_reader.clone(), new_history());
+        #[cfg(feature = "accounts")]
+        let query_root = match accounts_service.clone() {
+            Some(accounts_service) => query_root.with_accounts_service(accounts_service),
+            None => query_root,
+        };
+        data_apis.with_graphql(query_root.schema())
+    } else {
+        data_apis
+    };
+    #[cfg(feature = "arrow-export")]
+    let data_apis = if enable_arrow_export {
+        data_apis.with_arrow_export(block_range_reader)
+    } else {
+        data_apis
+    };
+
+    let admin_server_task: AnyhowJoinHandle = match admin_http_addr {
+        Some(addr) => {
+            let admin_server = AdminServer::new(
+                data_cache.clone(),
+                config_reloader.clone(),
+                admin_token.expect("ADMIN_TOKEN is required"),
+                state_dump_dir
+                    .map(PathBuf::from)
+                    .unwrap_or_else(std::env::temp_dir),
+            )
+            .with_memory_budget(memory_budget);
+            let admin_server = if replay_from_slot.is_none() {
+                admin_server.with_transaction_service(transaction_service.clone())
+            } else {
+                admin_server
+            };
+            let admin_server = match identity_reloader {
+                Some(identity_reloader) => admin_server.with_identity_reloader(identity_reloader),
+                None => admin_server,
+            };
+            #[cfg(feature = "accounts")]
+            let admin_server = match accounts_service.clone() {
+                Some(accounts_service) => admin_server.with_accounts_service(accounts_service),
+                None => admin_server,
+            };
+            admin_server.start(addr.parse()?)
+        }
+        None => pending_task(),
+    };
+
+    let block_arrival_task: AnyhowJoinHandle = match block_arrival_reference_ws_addr {
+        Some(reference_ws_addr) => BlockArrivalComparator::new(reference_ws_addr)
+            .with_slot_cache(data_cache.slot_cache.clone())
+            .start(block_info_notifier.resubscribe()),
+        None => pending_task(),
+    };
+
+    let slot_lag_alert_webhook_url = slot_lag_alert_webhook_url
+        .map(|url| WebhookNotifier::parse_http_url(&url))
+        .transpose()?;
+    let slot_lag_watchdog_task = SlotLagWatchdog::new(
+        data_cache.clone(),
+        SlotLagThresholds {
+            processed: slot_lag_alert_processed,
+            confirmed: slot_lag_alert_confirmed,
+            finalized: slot_lag_alert_finalized,
+            duration: Duration::from_secs(slot_lag_alert_secs),
+        },
+        slot_lag_alert_webhook_url,
+    )
+    .start();
+
+    let (latest_clock, latest_clock_task) = match clock_notifier {
+        Some(clock_notifier) => {
+            let (latest_clock, task) = create_latest_clock(clock_notifier);
+            (Some(latest_clock), task)
+        }
+        None => (None, pending_task()),
+    };
+
+    // the commitment upgrades are only consumed by the websocket subscriptions
+    #[cfg(feature = "ws-server")]
+    let (commitment_upgrade_notifier, commitment_upgrade_task) =
+        create_commitment_upgrade_stream(block_info_notifier.resubscribe());
+    #[cfg(not(feature = "ws-server"))]
+    let commitment_upgrade_task = pending_task();
+
+    let webhook_notifier = WebhookNotifier::new().with_allowed_hosts(callback_url_allowed_hosts);
+    let webhook_notifier_task = webhook_notifier.start(blocks_notifier.resubscribe());
+
+    let bridge = LiteBridge::new(
         rpc_client.clone(),
         data_cache.clone(),
-        transaction_service,
+        transaction_service.clone(),
         history,
-        block_priofees_service.clone(),
-        account_priofees_service.clone(),
-        accounts_service.clone(),
-    );
-
-    let pubsub_service = LitePubSubBridge::new(
+        webhook_notifier,
+        health_state.clone(),
+    )
+    .with_proxy_cache_ttl(Duration::from_secs(proxy_cache_ttl_secs))
+    .with_epoch_value_caches(epoch_value_caches);
+    let bridge = match latest_clock {
+        Some(latest_clock) => bridge.with_clock(latest_clock),
+        None => bridge,
+    };
+    #[cfg(feature = "accounts")]
+    let bridge = match accounts_service.clone() {
+        Some(accounts_service) => bridge.with_accounts_service(accounts_service),
+        None => bridge,
+    };
+    let methods = RpcMethods::new(bridge);
+    #[cfg(feature = "ws-server")]
+    let methods = methods.with_pubsub(LitePubSubBridge::new(
         data_cache.clone(),
+        blocks_notifier,
+        commitment_upgrade_notifier,
+    ))?;
+    #[cfg(not(feature = "ws-server"))]
+    drop(blocks_notifier);
+    drop(block_info_notifier);
+    #[cfg(feature = "priofees")]
+    let methods = methods.with_priofees(LitePrioFeesBridge::new(
         block_priofees_service,
         account_priofees_service,
-        blocks_notifier,
-        accounts_service.clone(),
-    );
+        program_priofees_service,
+    ))?;
+    #[cfg(feature = "accounts")]
+    let methods = match accounts_service {
+        Some(accounts_service) => {
+            methods.with_accounts(LiteAccountsBridge::new(accounts_service))?
+        }
+        None => methods,
+    };
 
-    let bridge_service = tokio::spawn(start_servers(
-        rpc_service,
-        pubsub_service,
+    let tls = match (tls_cert_path, tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let certificate_reloader = CertificateReloader::new(TlsConfig {
+                cert_path,
+                key_path,
+            })?;
+            if tls_reload_on_change {
+                certificate_reloader.start_watching(TLS_RELOAD_INTERVAL);
+            }
+            Some(certificate_reloader)
+        }
+        _ => None,
+    };
+
+    let cors = CorsConfig {
+        allowed_origins: cors_allowed_origins,
+        allowed_methods: cors_allowed_methods,
+        allowed_headers: cors_allowed_headers,
+        max_age: Duration::from_secs(cors_max_age_secs),
+    }
+    .layer()?;
+
+    let upstream_proxy = enable_upstream_proxy.then(|| UpstreamProxyConfig {
+        rpc_pool,
+        timeout: Duration::from_millis(upstream_proxy_timeout_ms),
+        retries: upstream_proxy_retries,
+    });
+    let mut bridge_service = tokio::spawn(start_servers(
+        methods,
         lite_rpc_ws_addr,
         lite_rpc_http_addr,
+        server_workers,
+        transaction_service.clone(),
+        health_state,
+        tls,
+        api_keys,
+        cors,
+        rate_limiter,
+        upstream_proxy,
+        data_apis,
+        http_limits,
+        enable_compression,
+        shutdown.clone(),
     ));
     drop(slot_notifier);
 
     tokio::select! {
+        biased;
+        _ = shutdown.cancelled() => {}
         res = tx_service_jh => {
             anyhow::bail!("Tx Services {res:?}")
         }
         res = support_service => {
             anyhow::bail!("Support Services {res:?}")
         }
-        res = bridge_service => {
+        res = &mut bridge_service => {
             anyhow::bail!("Server {res:?}")
         }
+        res = &mut grpc_tx_service => {
+            anyhow::bail!("gRPC transaction service {res:?}")
+        }
+        res = &mut geyser_server => {
+            anyhow::bail!("Geyser gRPC server {res:?}")
+        }
+        res = commitment_upgrade_task => {
+            anyhow::bail!("commitment upgrade stream failed {res:?}")
+        }
+        res = webhook_notifier_task => {
+            anyhow::bail!("Webhook notifier {res:?}")
+        }
         // allow it to fail
         // res = block_priofees_task => {
         //     anyhow::bail!("Prio Fees Service {res:?}")
@@ -382,13 +1260,176 @@ pub async fn start_lite_rpc(args: Config, rpc_client: Arc<RpcClient>) -> anyhow:
         res = futures::future::select_all(cluster_endpoint_tasks) => {
             anyhow::bail!("cluster endpoint failure {res:?}")
         }
-        res = block_priofees_task => {
-            anyhow::bail!("block prioritization fees task failed {res:?}")
+        err = first_stopped_task(priofees_tasks) => {
+            return Err(err);
+        }
+        err = first_stopped_task(account_stream_tasks) => {
+            return Err(err);
+        }
+        res = alt_updates_task => {
+            anyhow::bail!("address lookup table updates failed {res:?}")
+        }
+        res = alt_resolution_task => {
+            anyhow::bail!("address lookup table resolution failed {res:?}")
+        }
+        res = priofees_history_task => {
+            anyhow::bail!("prioritization fees history task failed {res:?}")
+        }
+        res = postgres_notifier_task => {
+            anyhow::bail!("postgres notifier task failed {res:?}")
+        }
+        res = &mut block_storage_task => {
+            anyhow::bail!("block storage task failed {res:?}")
+        }
+        res = block_store_pruner_task => {
+            anyhow::bail!("block store pruner failed {res:?}")
+        }
+        res = &mut kafka_sink_task => {
+            anyhow::bail!("kafka sink failed {res:?}")
+        }
+        res = pubsub_sink_task => {
+            anyhow::bail!("pub-sub sink failed {res:?}")
+        }
+        res = account_filter_sets_task => {
+            anyhow::bail!("account filter sets failed {res:?}")
+        }
+        res = account_webhooks_task => {
+            anyhow::bail!("account webhooks failed {res:?}")
+        }
+        res = latest_clock_task => {
+            anyhow::bail!("clock stream failed {res:?}")
+        }
+        res = influx_exporter_task => {
+            anyhow::bail!("influx exporter failed {res:?}")
+        }
+        res = memory_budget_task => {
+            anyhow::bail!("memory budget failed {res:?}")
+        }
+        res = allocator_stats_task => {
+            anyhow::bail!("allocator stats failed {res:?}")
         }
-        res = account_priofees_task => {
-            anyhow::bail!("account prioritization fees task failed {res:?}")
+        res = health_task => {
+            anyhow::bail!("health task failed {res:?}")
         }
+        res = admin_server_task => {
+            anyhow::bail!("Admin server {res:?}")
+        }
+        res = block_arrival_task => {
+            anyhow::bail!("block arrival comparator failed {res:?}")
+        }
+        res = slot_lag_watchdog_task => {
+            anyhow::bail!("slot lag watchdog failed {res:?}")
+        }
+        res = config_reload_task => {
+            anyhow::bail!("configuration reloader failed {res:?}")
+        }
+        res = identity_reload_task => {
+            anyhow::bail!("identity reloader failed {res:?}")
+        }
+        res = epoch_notifier_task => {
+            anyhow::bail!("epoch notifier failed {res:?}")
+        }
+        res = rpc_health_check_task => {
+            anyhow::bail!("rpc health check failed {res:?}")
+        }
+    }
+
+    // the endpoint tasks keep running until the servers are stopped, the queued transactions are sent
+    // and the pending blocks are saved; they are cancelled when the runtime shuts down
+    info!("Shutting down gracefully");
+    match timeout(
+        SHUTDOWN_PHASE_TIMEOUT,
+        futures::future::join3(bridge_service, grpc_tx_service, geyser_server),
+    )
+    .await
+    {
+        Ok((servers, grpc_tx_service, geyser_server)) => debug!("servers stopped {servers:?}, gRPC transaction service stopped {grpc_tx_service:?}, geyser gRPC server stopped {geyser_server:?}"),
+        Err(_) => log::warn!("Servers still serving open streams after {SHUTDOWN_PHASE_TIMEOUT:?}, closing them"),
+    }
+
+    if !transaction_service.flush(SHUTDOWN_PHASE_TIMEOUT).await {
+        log::warn!("Transactions still queued after {SHUTDOWN_PHASE_TIMEOUT:?}, dropping them");
+    }
+
+    match timeout(SHUTDOWN_PHASE_TIMEOUT, block_storage_task).await {
+        Ok(res) => debug!("block storage stopped {res:?}"),
+        Err(_) => {
+            log::warn!("Blocks still pending after {SHUTDOWN_PHASE_TIMEOUT:?}, dropping them")
+        }
+    }
+    match timeout(SHUTDOWN_PHASE_TIMEOUT, kafka_sink_task).await {
+        Ok(res) => debug!("kafka sink stopped {res:?}"),
+        Err(_) => log::warn!("Kafka records still queued after {SHUTDOWN_PHASE_TIMEOUT:?}"),
     }
+    info!("Shutdown complete");
+    Ok(())
+}
+
+fn is_postgres_url(block_storage_url: &str) -> bool {
+    block_storage_url.starts_with("postgres://") || block_storage_url.starts_with("postgresql://")
+}
+
+/// block storage for a postgres url, the retention is configured with the BLOCKSTORE_* environment variables;
+/// the epoch manager prepares the schemas and prunes at the epoch boundaries
+#[cfg(feature = "postgres")]
+async fn create_postgres_block_storage(
+    pg_config: String,
+    epoch_data: EpochCache,
+    notify_channel: Option<String>,
+) -> anyhow::Result<(
+    Arc<PostgresBlockStorage>,
+    PostgresBlockStorePruner,
+    PostgresEpochManager,
+)> {
+    let pg_session_config = PostgresSessionConfig::new(pg_config)?;
+    let block_store_writer = PostgresBlockStore::new(epoch_data.clone(), pg_session_config.clone())
+        .await?
+        .with_payload_compression(PayloadCompression::new_from_env()?);
+    let block_store_writer = match notify_channel {
+        Some(channel) => block_store_writer.with_notify_channel(channel),
+        None => block_store_writer,
+    };
+    let block_store_query =
+        PostgresQueryBlockStore::new(epoch_data.clone(), pg_session_config).await;
+    let pruner = PostgresBlockStorePruner::new(
+        block_store_query.clone(),
+        block_store_writer.clone(),
+        BlockStoreRetentionConfig::new_from_env()?,
+    )?;
+    let epoch_manager = PostgresEpochManager::new(block_store_writer.clone(), Some(pruner.clone()));
+    let block_storage =
+        PostgresBlockStorage::new(block_store_writer, block_store_query, epoch_data);
+    Ok((Arc::new(block_storage), pruner, epoch_manager))
+}
+
+/// placeholder for optional tasks which are not started, never finishes
+fn pending_task() -> AnyhowJoinHandle {
+    tokio::spawn(std::future::pending())
+}
+
+/// placeholder for optional tasks which have nothing to do on shutdown
+fn stopped_on_shutdown(shutdown: CancellationToken) -> AnyhowJoinHandle {
+    tokio::spawn(async move {
+        shutdown.cancelled().await;
+        Ok(())
+    })
+}
+
+/// starts the graceful shutdown on SIGTERM or ctrl+c, a second signal exits immediately
+async fn shutdown_on_signal(shutdown: CancellationToken) -> anyhow::Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        _ = terminate.recv() => info!("Received SIGTERM"),
+        _ = tokio::signal::ctrl_c() => info!("Received ctrl+c signal"),
+    }
+    shutdown.cancel();
+
+    tokio::select! {
+        _ = terminate.recv() => {},
+        _ = tokio::signal::ctrl_c() => {},
+    }
+    log::warn!("Received a second signal during the shutdown, exiting immediately");
+    std::process::exit(1)
 }
 
 fn setup_grpc_stream_debugging(blocks_notifier: &BlockStream) {
@@ -405,13 +1446,73 @@ fn setup_grpc_stream_debugging(blocks_notifier: &BlockStream) {
     debugtask_blockstream_confirmation_sequence(blocks_notifier.resubscribe());
 }
 
-#[tokio::main(flavor = "multi_thread", worker_threads = 16)]
-pub async fn main() -> anyhow::Result<()> {
-    setup_tracing_subscriber();
+pub fn main() -> anyhow::Result<()> {
+    // the runtime is built before the configuration is loaded, so its size only comes from the environment
+    dotenv::dotenv().ok();
+    let worker_threads = match std::env::var("RUNTIME_WORKER_THREADS") {
+        Ok(threads) => threads
+            .parse()
+            .context("RUNTIME_WORKER_THREADS must be a number")?,
+        Err(_) => DEFAULT_RUNTIME_WORKER_THREADS,
+    };
+    anyhow::ensure!(
+        worker_threads > 0,
+        "RUNTIME_WORKER_THREADS must be greater than 0"
+    );
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(worker_threads)
+        .enable_all()
+        .build()
+        .context("Cannot build the main runtime")?
+        .block_on(run())
+}
+
+async fn run() -> anyhow::Result<()> {
+    let log_filter = setup_tracing_subscriber();
+
+    let mut config = Config::load().await?;
+    // a cluster process started by the instance, see start_clusters
+    let cluster = std::env::var(CLUSTER_ENV).ok();
+    if let Some(cluster) = &cluster {
+        config = config.cluster_config(cluster)?;
+    }
+    if config.log_level.is_some() {
+        log_filter(config.log_level.as_deref())?;
+    }
+
+    #[cfg(feature = "postgres")]
+    if config.command == Some(Command::Migrate) {
+        let Some(postgres) = config.postgres else {
+            bail!("postgres is not configured, set PG_ENABLED and PG_CONFIG");
+        };
+        let session = postgres_logger::PostgresSession::new(postgres).await?;
+        let applied = postgres_logger::run_migrations(&session).await?;
+        info!("Applied {} postgres migrations", applied);
+        return Ok(());
+    }
+    #[cfg(not(feature = "postgres"))]
+    if config.command == Some(Command::Migrate) {
+        bail!("lite-rpc was built without the postgres feature");
+    }
+
+    if config.command == Some(Command::ValidateConfig) {
+        return preflight::validate_config(&config).await;
+    }
 
-    let config = Config::load().await?;
+    if config.command == Some(Command::Probe) {
+        return preflight::probe_sources(&config).await;
+    }
 
-    let ctrl_c_signal = tokio::signal::ctrl_c();
+    let shutdown = CancellationToken::new();
+    tokio::spawn(shutdown_on_signal(shutdown.clone()));
+    if cluster.is_some() {
+        tokio::spawn(shutdown_on_stdin_closed(shutdown.clone()));
+    }
+    if !config.clusters.is_empty() {
+        let result = start_clusters(config, shutdown).await;
+        opentelemetry::global::shutdown_tracer_provider();
+        return result;
+    }
     let Config { rpc_addr, .. } = &config;
     // rpc client
     let rpc_client = Arc::new(RpcClient::new(rpc_addr.clone()));
@@ -419,38 +1520,165 @@ pub async fn main() -> anyhow::Result<()> {
 
     info!("Use RPC address: {}", obfuscate_rpcurl(rpc_addr));
 
-    let main = start_lite_rpc(config, rpc_client);
+    let main = start_lite_rpc(config, rpc_client, log_filter, shutdown);
 
-    tokio::select! {
+    let result = tokio::select! {
         err = rpc_tester => {
             log::error!("{err:?}");
             Ok(())
         }
-        res = main => {
-            // This should never happen
-            log::error!("Services quit unexpectedly {res:?}");
-            bail!("")
+        res = main => match res {
+            // graceful shutdown
+            Ok(()) => Ok(()),
+            Err(err) => {
+                log::error!("Services quit unexpectedly {err:?}");
+                bail!("")
+            }
+        },
+    };
+    // flush the spans which are not yet exported
+    opentelemetry::global::shutdown_tracer_provider();
+    result
+}
+
+/// serves every cluster of the config with its own services in a process of its own, so the
+/// metrics of the clusters stay apart; the http and websocket requests are routed to them by
+/// lite_rpc_http_addr and lite_rpc_ws_addr and their metrics are served with a `cluster` label
+async fn start_clusters(config: Config, shutdown: CancellationToken) -> anyhow::Result<()> {
+    let router = ClusterRouter::new(
+        config
+            .clusters
+            .iter()
+            .map(|cluster| (cluster.name.clone(), cluster.lite_rpc_http_addr.clone())),
+        config.http_limits(),
+    )?
+    .start(config.lite_rpc_http_addr.parse()?, shutdown.clone());
+    #[cfg(feature = "ws-server")]
+    let ws_router = ClusterRouter::new(
+        config
+            .clusters
+            .iter()
+            .map(|cluster| (cluster.name.clone(), cluster.lite_rpc_ws_addr.clone())),
+        config.http_limits(),
+    )?
+    .start_ws(config.lite_rpc_ws_addr.parse()?, shutdown.clone());
+    #[cfg(not(feature = "ws-server"))]
+    let ws_router = pending_task();
+    let prometheus = ClusterMetrics::new(
+        config
+            .clusters
+            .iter()
+            .map(|cluster| (cluster.name.clone(), cluster.prometheus_addr.clone())),
+    )?
+    .start(config.prometheus_addr.parse()?, shutdown.clone());
+
+    let executable = std::env::current_exe().context("Cannot find the lite-rpc executable")?;
+    let mut clusters = vec![];
+    for cluster in &config.clusters {
+        let cluster_config = config.cluster_config(&cluster.name)?;
+        info!(
+            "Use RPC address for cluster {}: {}",
+            cluster.name,
+            obfuscate_rpcurl(&cluster_config.rpc_addr)
+        );
+        clusters.push(start_cluster_process(
+            &executable,
+            cluster.name.clone(),
+            shutdown.clone(),
+        )?);
+    }
+    let mut clusters = Box::pin(futures::future::try_join_all(
+        clusters
+            .into_iter()
+            .map(|cluster| async move { cluster.await? }),
+    ));
+
+    tokio::select! {
+        res = &mut clusters => {
+            if let Err(err) = &res {
+                log::error!("Services quit unexpectedly {err:?}");
+            }
+            return res.map(|_| ());
         }
-        _ = ctrl_c_signal => {
-            log::info!("Received ctrl+c signal");
-            Ok(())
+        res = router => {
+            if !shutdown.is_cancelled() {
+                bail!("Cluster router {res:?}");
+            }
+        }
+        res = ws_router => {
+            if !shutdown.is_cancelled() {
+                bail!("Cluster websocket router {res:?}");
+            }
+        }
+        res = prometheus => {
+            if !shutdown.is_cancelled() {
+                bail!("Prometheus {res:?}");
+            }
         }
     }
+    // the router stops on shutdown, the clusters shut down gracefully
+    clusters.await.map(|_| ())
+}
+
+/// runs lite-rpc with the arguments of the instance for one of its clusters, the cluster is shut
+/// down by closing its stdin on shutdown and killed if the instance quits unexpectedly
+fn start_cluster_process(
+    executable: &Path,
+    name: String,
+    shutdown: CancellationToken,
+) -> anyhow::Result<AnyhowJoinHandle> {
+    let mut process = tokio::process::Command::new(executable)
+        .args(std::env::args_os().skip(1))
+        .env(CLUSTER_ENV, &name)
+        .stdin(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Cannot start cluster {name}"))?;
+    Ok(tokio::spawn(async move {
+        tokio::select! {
+            status = process.wait() => bail!("cluster {name} exited with {}", status?),
+            _ = shutdown.cancelled() => {}
+        }
+        drop(process.stdin.take());
+        let status = process.wait().await?;
+        anyhow::ensure!(status.success(), "cluster {name} exited with {status}");
+        Ok(())
+    }))
+}
+
+/// a cluster process shuts down once the instance closes its stdin or quits
+async fn shutdown_on_stdin_closed(shutdown: CancellationToken) -> anyhow::Result<()> {
+    let mut stdin = tokio::io::stdin();
+    let mut buffer = [0u8; 64];
+    while stdin.read(&mut buffer).await? > 0 {}
+    info!("Stdin closed by the instance");
+    shutdown.cancel();
+    Ok(())
 }
 
 fn configure_tpu_connection_path(quic_proxy_addr: Option<String>) -> TpuConnectionPath {
     match quic_proxy_addr {
         None => TpuConnectionPath::QuicDirectPath,
-        Some(prox_address) => {
-            let proxy_socket_addr = parse_host_port(prox_address.as_str()).unwrap();
+        #[cfg(feature = "quic-proxy")]
+        Some(prox_addresses) => {
+            // e.g. "127.0.0.1:11111" or "localhost:11111,proxy-eu:11111"
+            let forward_proxy_addresses = prox_addresses
+                .split(',')
+                .map(str::trim)
+                .filter(|prox_address| !prox_address.is_empty())
+                .map(|prox_address| parse_host_port(prox_address).unwrap())
+                .collect();
             TpuConnectionPath::QuicForwardProxyPath {
-                // e.g. "127.0.0.1:11111" or "localhost:11111"
-                forward_proxy_address: proxy_socket_addr,
+                forward_proxy_addresses,
             }
         }
+        // rejected when the configuration is loaded
+        #[cfg(not(feature = "quic-proxy"))]
+        Some(_) => unreachable!("lite-rpc was built without the quic-proxy feature"),
     }
 }
 
+#[cfg(feature = "quic-proxy")]
 fn parse_host_port(host_port: &str) -> Result<SocketAddr, String> {
     let addrs: Vec<_> = host_port
         .to_socket_addrs()
@@ -473,20 +1701,73 @@ fn obfuscate_rpcurl(rpc_addr: &str) -> String {
     rpc_addr.to_string()
 }
 
-fn setup_tracing_subscriber() {
+fn setup_tracing_subscriber() -> LogFilterHandle {
     let enable_instrument_tracing = std::env::var("ENABLE_INSTRUMENT_TRACING")
         .unwrap_or("false".to_string())
         .parse::<bool>()
         .expect("flag must be true or false");
 
-    if enable_instrument_tracing {
-        tracing_subscriber::fmt::fmt()
-            .with_env_filter(EnvFilter::from_default_env())
-            // not sure if "CLOSE" is exactly what we want
-            // ex. "close time.busy=14.7ms time.idle=14.0µs"
-            .with_span_events(FmtSpan::CLOSE)
-            .init();
+    let fmt_layer = if enable_instrument_tracing {
+        // not sure if "CLOSE" is exactly what we want
+        // ex. "close time.busy=14.7ms time.idle=14.0µs"
+        tracing_subscriber::fmt::layer().with_span_events(FmtSpan::CLOSE)
     } else {
-        tracing_subscriber::fmt::init();
-    }
+        tracing_subscriber::fmt::layer()
+    };
+
+    // spans are exported independent of RUST_LOG, only those of the lite-rpc crates (`lite_rpc` and
+    // the `solana_lite_rpc_*` crates by target prefix); the sampling ratio limits the volume
+    let otlp_layer = std::env::var("OTLP_ENDPOINT").ok().map(|otlp_endpoint| {
+        let sample_ratio = std::env::var("OTLP_SAMPLE_RATIO")
+            .map(|ratio| {
+                ratio
+                    .parse::<f64>()
+                    .expect("OTLP_SAMPLE_RATIO must be a number")
+            })
+            .unwrap_or(DEFAULT_OTLP_SAMPLE_RATIO);
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(otlp_endpoint),
+            )
+            .with_trace_config(
+                opentelemetry_sdk::trace::config()
+                    .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                        sample_ratio,
+                    ))))
+                    .with_resource(Resource::new(vec![KeyValue::new(
+                        "service.name",
+                        "lite-rpc",
+                    )])),
+            )
+            .install_batch(opentelemetry_sdk::runtime::Tokio)
+            .expect("OTLP trace exporter");
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(
+                Targets::new()
+                    .with_target("lite_rpc", LevelFilter::DEBUG)
+                    .with_target("solana_lite_rpc", LevelFilter::DEBUG),
+            )
+    });
+
+    // the log level can be changed by a configuration reload
+    let (env_filter, env_filter_handle) = reload::Layer::new(EnvFilter::from_default_env());
+
+    tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(env_filter))
+        .with(otlp_layer)
+        .with(RecentErrorsLayer.with_filter(LevelFilter::WARN))
+        .init();
+
+    Arc::new(move |directives: Option<&str>| {
+        let env_filter = match directives {
+            Some(directives) => EnvFilter::try_new(directives)?,
+            None => EnvFilter::from_default_env(),
+        };
+        env_filter_handle.reload(env_filter)?;
+        Ok(())
+    })
 }
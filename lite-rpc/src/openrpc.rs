@@ -0,0 +1,465 @@
+use jsonrpsee::{types::ErrorObjectOwned, Methods, RpcModule};
+use serde_json::{json, Value};
+
+const OPENRPC_VERSION: &str = "1.2.6";
+const DISCOVER_METHOD: &str = "rpc.discover";
+
+/// json schema of a parameter or a result, kept coarse: the solana config objects are documented upstream
+#[derive(Clone, Copy)]
+enum Schema {
+    Integer,
+    String,
+    Boolean,
+    Object,
+    Any,
+    Array(&'static Schema),
+    Nullable(&'static Schema),
+    // RpcResponse with the slot as context
+    Response(&'static Schema),
+}
+
+impl Schema {
+    fn to_json(self) -> Value {
+        match self {
+            Schema::Integer => json!({ "type": "integer", "minimum": 0 }),
+            Schema::String => json!({ "type": "string" }),
+            Schema::Boolean => json!({ "type": "boolean" }),
+            Schema::Object => json!({ "type": "object" }),
+            Schema::Any => json!({}),
+            Schema::Array(items) => json!({ "type": "array", "items": items.to_json() }),
+            Schema::Nullable(inner) => json!({ "oneOf": [inner.to_json(), { "type": "null" }] }),
+            Schema::Response(value) => json!({
+                "type": "object",
+                "properties": {
+                    "context": {
+                        "type": "object",
+                        "properties": { "slot": Schema::Integer.to_json() },
+                    },
+                    "value": value.to_json(),
+                },
+                "required": ["context", "value"],
+            }),
+        }
+    }
+}
+
+struct Param {
+    name: &'static str,
+    required: bool,
+    schema: Schema,
+}
+
+const fn required(name: &'static str, schema: Schema) -> Param {
+    Param {
+        name,
+        required: true,
+        schema,
+    }
+}
+
+const fn optional(name: &'static str, schema: Schema) -> Param {
+    Param {
+        name,
+        required: false,
+        schema,
+    }
+}
+
+struct MethodSpec {
+    name: &'static str,
+    params: &'static [Param],
+    result: Schema,
+    // notification and unsubscribe method of a subscription
+    subscription: Option<(&'static str, &'static str)>,
+}
+
+const fn method(name: &'static str, params: &'static [Param], result: Schema) -> MethodSpec {
+    MethodSpec {
+        name,
+        params,
+        result,
+        subscription: None,
+    }
+}
+
+const fn subscription(
+    name: &'static str,
+    params: &'static [Param],
+    notification: &'static str,
+    unsubscribe: &'static str,
+) -> MethodSpec {
+    MethodSpec {
+        name,
+        params,
+        // the subscription id
+        result: Schema::Integer,
+        subscription: Some((notification, unsubscribe)),
+    }
+}
+
+const CONFIG: Param = optional("config", Schema::Object);
+
+/// signatures of the methods and subscriptions lite-rpc implements, only the ones registered on
+/// an endpoint end up in its document
+const METHODS: &[MethodSpec] = &[
+    method(
+        "getBlock",
+        &[required("slot", Schema::Integer), CONFIG],
+        Schema::Nullable(&Schema::Object),
+    ),
+    method(
+        "getBlocks",
+        &[
+            required("startSlot", Schema::Integer),
+            optional("endSlotOrConfig", Schema::Any),
+            optional("commitment", Schema::Object),
+        ],
+        Schema::Array(&Schema::Integer),
+    ),
+    method(
+        "getSignaturesForAddress",
+        &[required("address", Schema::String), CONFIG],
+        Schema::Array(&Schema::Object),
+    ),
+    method(
+        "getTransaction",
+        &[required("signature", Schema::String), CONFIG],
+        Schema::Nullable(&Schema::Object),
+    ),
+    method("getClusterNodes", &[], Schema::Array(&Schema::Object)),
+    method("getSlot", &[CONFIG], Schema::Integer),
+    method("getBlockHeight", &[CONFIG], Schema::Integer),
+    method(
+        "getBlockTime",
+        &[required("slot", Schema::Integer)],
+        Schema::Integer,
+    ),
+    method("getFirstAvailableBlock", &[], Schema::Integer),
+    method(
+        "getLatestBlockhash",
+        &[CONFIG],
+        Schema::Response(&Schema::Object),
+    ),
+    method(
+        "isBlockhashValid",
+        &[required("blockhash", Schema::String), CONFIG],
+        Schema::Response(&Schema::Boolean),
+    ),
+    method(
+        "getRecentPerformanceSamples",
+        &[optional("limit", Schema::Integer)],
+        Schema::Array(&Schema::Object),
+    ),
+    method(
+        "getSignatureStatuses",
+        &[
+            required("signatures", Schema::Array(&Schema::String)),
+            CONFIG,
+        ],
+        Schema::Response(&Schema::Array(&Schema::Nullable(&Schema::Object))),
+    ),
+    method(
+        "getTransactionLifecycle",
+        &[required("signature", Schema::String)],
+        Schema::Nullable(&Schema::Object),
+    ),
+    method(
+        "sendTransaction",
+        &[required("transaction", Schema::String), CONFIG],
+        Schema::String,
+    ),
+    method("getVersion", &[], Schema::Object),
+    method(
+        "requestAirdrop",
+        &[
+            required("pubkey", Schema::String),
+            required("lamports", Schema::Integer),
+            CONFIG,
+        ],
+        Schema::String,
+    ),
+    method("getEpochInfo", &[CONFIG], Schema::Object),
+    method(
+        "getLeaderSchedule",
+        &[optional("slot", Schema::Integer), CONFIG],
+        Schema::Nullable(&Schema::Object),
+    ),
+    method(
+        "getSlotLeaders",
+        &[
+            required("startSlot", Schema::Integer),
+            required("limit", Schema::Integer),
+        ],
+        Schema::Array(&Schema::String),
+    ),
+    method(
+        "getUpcomingLeaders",
+        &[optional("slots", Schema::Integer)],
+        Schema::Array(&Schema::Object),
+    ),
+    method("getClock", &[], Schema::Object),
+    method("getVoteAccounts", &[CONFIG], Schema::Object),
+    method(
+        "getStakeMinimumDelegation",
+        &[CONFIG],
+        Schema::Response(&Schema::Integer),
+    ),
+    method("getSupply", &[CONFIG], Schema::Response(&Schema::Object)),
+    method(
+        "getLargestAccounts",
+        &[CONFIG],
+        Schema::Response(&Schema::Array(&Schema::Object)),
+    ),
+    method("getMaxRetransmitSlot", &[], Schema::Integer),
+    method("getMaxShredInsertSlot", &[], Schema::Integer),
+    method("getHighestSnapshotSlot", &[], Schema::Object),
+    method("minimumLedgerSlot", &[], Schema::Integer),
+    method("getMinimumLedgerSlot", &[], Schema::Integer),
+    method(
+        "getTokenLargestAccounts",
+        &[
+            required("mint", Schema::String),
+            optional("commitment", Schema::Object),
+        ],
+        Schema::Response(&Schema::Array(&Schema::Object)),
+    ),
+    method("getInflationRate", &[], Schema::Object),
+    method(
+        "getInflationReward",
+        &[
+            required("addresses", Schema::Array(&Schema::String)),
+            CONFIG,
+        ],
+        Schema::Array(&Schema::Nullable(&Schema::Object)),
+    ),
+    method(
+        "getRecentPrioritizationFees",
+        &[required("addresses", Schema::Array(&Schema::String))],
+        Schema::Array(&Schema::Object),
+    ),
+    method(
+        "getLatestBlockPrioFees",
+        &[optional("method", Schema::String)],
+        Schema::Response(&Schema::Object),
+    ),
+    method(
+        "getLatestAccountPrioFees",
+        &[
+            required("account", Schema::String),
+            optional("method", Schema::String),
+        ],
+        Schema::Response(&Schema::Object),
+    ),
+    method(
+        "getProgramPrioFees",
+        &[
+            required("programId", Schema::String),
+            optional("method", Schema::String),
+        ],
+        Schema::Response(&Schema::Object),
+    ),
+    method(
+        "getPrioFeesPercentiles",
+        &[optional("lookbackBlocks", Schema::Integer)],
+        Schema::Response(&Schema::Object),
+    ),
+    method(
+        "getWriteLockPrioFees",
+        &[
+            required("account", Schema::String),
+            optional("lookbackBlocks", Schema::Integer),
+        ],
+        Schema::Response(&Schema::Object),
+    ),
+    method(
+        "getBlockUtilization",
+        &[optional("lookbackBlocks", Schema::Integer)],
+        Schema::Response(&Schema::Object),
+    ),
+    method(
+        "getHottestAccounts",
+        &[
+            optional("lookbackSlots", Schema::Integer),
+            optional("limit", Schema::Integer),
+        ],
+        Schema::Response(&Schema::Array(&Schema::Object)),
+    ),
+    method(
+        "getRecommendedPrioFeeForMessage",
+        &[required("message", Schema::String), CONFIG],
+        Schema::Response(&Schema::Object),
+    ),
+    method(
+        "getAccountInfo",
+        &[required("pubkey", Schema::String), CONFIG],
+        Schema::Response(&Schema::Nullable(&Schema::Object)),
+    ),
+    method(
+        "getMultipleAccounts",
+        &[required("pubkeys", Schema::Array(&Schema::String)), CONFIG],
+        Schema::Response(&Schema::Array(&Schema::Nullable(&Schema::Object))),
+    ),
+    // with or without context depending on withContext of the config
+    method(
+        "getProgramAccounts",
+        &[required("programId", Schema::String), CONFIG],
+        Schema::Any,
+    ),
+    method(
+        "getStakeAccountsByAuthority",
+        &[required("authority", Schema::String), CONFIG],
+        Schema::Response(&Schema::Array(&Schema::Object)),
+    ),
+    subscription("slotSubscribe", &[], "slotNotification", "slotUnsubscribe"),
+    subscription("rootSubscribe", &[], "rootNotification", "rootUnsubscribe"),
+    subscription(
+        "blockCommitmentSubscribe",
+        &[],
+        "blockCommitmentNotification",
+        "blockCommitmentUnsubscribe",
+    ),
+    subscription(
+        "blockSubscribe",
+        &[required("filter", Schema::Any), CONFIG],
+        "blockNotification",
+        "blockUnsubscribe",
+    ),
+    subscription(
+        "transactionSubscribe",
+        &[required("filter", Schema::Object), CONFIG],
+        "transactionNotification",
+        "transactionUnsubscribe",
+    ),
+    subscription(
+        "logsSubscribe",
+        &[required("filter", Schema::Any), CONFIG],
+        "logsNotification",
+        "logsUnsubscribe",
+    ),
+    subscription(
+        "signatureSubscribe",
+        &[
+            required("signature", Schema::String),
+            required("config", Schema::Object),
+        ],
+        "signatureNotification",
+        "signatureUnsubscribe",
+    ),
+    subscription(
+        "slotUpdatesSubscribe",
+        &[],
+        "slotUpdatesNotification",
+        "slotUpdatesUnsubscribe",
+    ),
+    subscription("voteSubscribe", &[], "voteNotification", "voteUnsubscribe"),
+    subscription(
+        "blockPrioritizationFeesSubscribe",
+        &[],
+        "blockPrioritizationFeesNotification",
+        "blockPrioritizationFeesUnsubscribe",
+    ),
+    subscription(
+        "blockPrioritizationFeesSummarySubscribe",
+        &[],
+        "blockPrioritizationFeesSummaryNotification",
+        "blockPrioritizationFeesSummaryUnsubscribe",
+    ),
+    subscription(
+        "accountPrioritizationFeesSubscribe",
+        &[required("account", Schema::String)],
+        "accountPrioritizationFeesNotification",
+        "accountPrioritizationFeesUnsubscribe",
+    ),
+    subscription(
+        "accountSubscribe",
+        &[required("pubkey", Schema::String), CONFIG],
+        "accountNotification",
+        "accountUnsubscribe",
+    ),
+    subscription(
+        "programSubscribe",
+        &[required("programId", Schema::String), CONFIG],
+        "programNotification",
+        "programUnsubscribe",
+    ),
+];
+
+impl MethodSpec {
+    fn to_json(&self) -> Value {
+        let params = self
+            .params
+            .iter()
+            .map(|param| {
+                json!({
+                    "name": param.name,
+                    "required": param.required,
+                    "schema": param.schema.to_json(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut method = json!({
+            "name": self.name,
+            "paramStructure": "by-position",
+            "params": params,
+            "result": { "name": "result", "schema": self.result.to_json() },
+        });
+        if let Some((notification, unsubscribe)) = self.subscription {
+            method["x-notification"] = json!(notification);
+            method["x-unsubscribe"] = json!(unsubscribe);
+        }
+        method
+    }
+}
+
+/// OpenRPC document of the methods registered in `methods`, unsubscribe methods are listed with
+/// their subscription and methods without a known signature without params; `rpc.discover` itself
+/// is not listed
+pub fn openrpc_document(methods: &Methods) -> Value {
+    let enabled = methods.method_names().collect::<Vec<_>>();
+    let mut documented = METHODS
+        .iter()
+        .filter(|spec| enabled.contains(&spec.name))
+        .map(MethodSpec::to_json)
+        .collect::<Vec<_>>();
+
+    let mut undocumented = enabled
+        .iter()
+        .filter(|name| **name != DISCOVER_METHOD)
+        .filter(|name| {
+            !METHODS.iter().any(|spec| {
+                spec.name == **name
+                    || spec
+                        .subscription
+                        .is_some_and(|(_, unsubscribe)| unsubscribe == **name)
+            })
+        })
+        .collect::<Vec<_>>();
+    undocumented.sort();
+    documented.extend(undocumented.into_iter().map(|name| {
+        json!({
+            "name": name,
+            "params": [],
+            "result": { "name": "result", "schema": {} },
+        })
+    }));
+
+    json!({
+        "openrpc": OPENRPC_VERSION,
+        "info": {
+            "title": "lite-rpc",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "methods": documented,
+    })
+}
+
+/// registers `rpc.discover` answering the OpenRPC document of the methods registered so far
+pub fn register_discover(methods: &mut Methods) -> anyhow::Result<()> {
+    let document = openrpc_document(methods);
+    let mut module = RpcModule::new(());
+    module.register_method(DISCOVER_METHOD, move |_, _| {
+        Ok::<_, ErrorObjectOwned>(document.clone())
+    })?;
+    methods.merge(module)?;
+    Ok(())
+}
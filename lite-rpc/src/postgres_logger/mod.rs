@@ -1,7 +1,13 @@
 mod postgres_config;
+mod postgres_migrations;
+mod postgres_notifier;
+mod postgres_priofees;
 mod postgres_session;
 
 pub use crate::postgres_logger::postgres_config::PostgresSessionConfig;
+pub use crate::postgres_logger::postgres_migrations::{run_migrations, Migration, MIGRATIONS};
+pub use crate::postgres_logger::postgres_notifier::PostgresTxStatusNotifier;
+pub use crate::postgres_logger::postgres_priofees::PostgresPrioFeesLogger;
 pub use crate::postgres_logger::postgres_session::{PostgresSession, PostgresSessionCache};
 
 use anyhow::bail;
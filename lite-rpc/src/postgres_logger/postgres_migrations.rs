@@ -0,0 +1,17 @@
+use solana_lite_rpc_blockstore::block_stores::postgres::postgres_migrations;
+pub use solana_lite_rpc_blockstore::block_stores::postgres::postgres_migrations::Migration;
+
+use super::postgres_session::PostgresSession;
+
+// append new migrations here, never change applied ones
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    name: "lite_rpc_schema",
+    sql: include_str!("../../../migrations/versions/V001__lite_rpc_schema.sql"),
+}];
+
+/// Applies the migrations of the transaction and prioritization fee history which are not yet recorded
+/// in lite_rpc.SchemaMigrations. Returns the number of applied migrations.
+pub async fn run_migrations(postgres_session: &PostgresSession) -> anyhow::Result<usize> {
+    postgres_migrations::run_migrations(&postgres_session.client, "lite_rpc", MIGRATIONS).await
+}
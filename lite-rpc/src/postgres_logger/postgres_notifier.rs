@@ -0,0 +1,77 @@
+use log::{info, warn};
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_core::{stores::tx_store::TxStore, types::BlockStream, AnyhowJoinHandle};
+use tokio::sync::broadcast::error::RecvError;
+
+use super::PostgresSessionCache;
+use crate::sink_records::{transaction_status_records, SinkFormat};
+
+lazy_static::lazy_static! {
+    static ref POSTGRES_NOTIFICATIONS_SENT: IntCounter =
+        register_int_counter!(opts!("literpc_postgres_notifications_sent", "Transaction status notifications sent with postgres NOTIFY")).unwrap();
+    static ref POSTGRES_NOTIFICATIONS_FAILED: IntCounter =
+        register_int_counter!(opts!("literpc_postgres_notifications_failed", "Transaction status notifications which could not be sent with postgres NOTIFY")).unwrap();
+}
+
+/// Sends a NOTIFY with a json payload on `channel` when a transaction sent through lite-rpc lands in
+/// a confirmed or finalized block, so workers can `LISTEN` instead of polling `lite_rpc.Txs`.
+/// Notifications are best effort, the statuses of a block are dropped if postgres is unavailable.
+pub struct PostgresTxStatusNotifier {}
+
+impl PostgresTxStatusNotifier {
+    pub fn start(
+        postgres_session_cache: PostgresSessionCache,
+        mut block_stream: BlockStream,
+        txs: TxStore,
+        channel: String,
+    ) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            info!("start postgres transaction status notifications on channel {channel}");
+            loop {
+                let block = match block_stream.recv().await {
+                    Ok(block) => block,
+                    Err(RecvError::Lagged(lagged)) => {
+                        warn!("postgres notifier lagged by {lagged} blocks - continue");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => {
+                        anyhow::bail!("postgres notifier block stream closed")
+                    }
+                };
+
+                let payloads = transaction_status_records(&block, &txs)
+                    .map(|record| {
+                        String::from_utf8(SinkFormat::Json.encode(&record))
+                            .expect("json is valid utf8")
+                    })
+                    .collect::<Vec<_>>();
+                if payloads.is_empty() {
+                    continue;
+                }
+
+                // one round trip per block
+                let result = match postgres_session_cache.get_session().await {
+                    Ok(session) => session
+                        .execute(
+                            "SELECT pg_notify($1, payload) FROM unnest($2::text[]) AS payload",
+                            &[&channel, &payloads],
+                        )
+                        .await
+                        .map_err(anyhow::Error::from),
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(_) => POSTGRES_NOTIFICATIONS_SENT.inc_by(payloads.len() as u64),
+                    Err(e) => {
+                        POSTGRES_NOTIFICATIONS_FAILED.inc_by(payloads.len() as u64);
+                        warn!(
+                            "failed to notify {} transaction statuses of block {}: {e:?}",
+                            payloads.len(),
+                            block.slot
+                        );
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,260 @@
+use log::{info, warn};
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_core::AnyhowJoinHandle;
+use solana_lite_rpc_prioritization_fees::rpc_data::{
+    AccountPrioFeesUpdateMessage, PrioFeesSummary, PrioFeesUpdateMessage,
+};
+use solana_sdk::slot_history::Slot;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tokio_postgres::types::ToSql;
+
+use super::{PostgresSession, PostgresSessionCache};
+
+lazy_static::lazy_static! {
+    static ref PRIOFEES_HISTORY_BLOCKS_WRITTEN: IntCounter =
+        register_int_counter!(opts!("literpc_priofees_history_blocks_written", "Number of blocks written to the prioritization fee history in postgres")).unwrap();
+    static ref PRIOFEES_HISTORY_WRITE_ERRORS: IntCounter =
+        register_int_counter!(opts!("literpc_priofees_history_write_errors", "Number of failed writes to the prioritization fee history in postgres")).unwrap();
+}
+
+// postgres allows at most 65535 parameters per statement
+const ACCOUNT_ROWS_PER_STATEMENT: usize = 4000;
+// old rows are deleted every that many slots
+const PRUNE_INTERVAL_SLOTS: Slot = 1000;
+
+struct PostgresBlockPrioFees {
+    slot: i64,
+    tx_count: i64,
+    nonvote_tx_count: i64,
+    cu_consumed: i64,
+    nonvote_cu_consumed: i64,
+    p50_by_tx: i64,
+    p75_by_tx: i64,
+    p90_by_tx: i64,
+    p95_by_tx: i64,
+    p50_by_cu: i64,
+    p75_by_cu: i64,
+    p90_by_cu: i64,
+    p95_by_cu: i64,
+}
+
+impl From<&PrioFeesSummary> for PostgresBlockPrioFees {
+    fn from(value: &PrioFeesSummary) -> Self {
+        Self {
+            slot: value.slot as i64,
+            tx_count: value.tx_count.total as i64,
+            nonvote_tx_count: value.tx_count.nonvote as i64,
+            cu_consumed: value.cu_consumed.total as i64,
+            nonvote_cu_consumed: value.cu_consumed.nonvote as i64,
+            p50_by_tx: value.by_tx.p50 as i64,
+            p75_by_tx: value.by_tx.p75 as i64,
+            p90_by_tx: value.by_tx.p90 as i64,
+            p95_by_tx: value.by_tx.p95 as i64,
+            p50_by_cu: value.by_cu.p50 as i64,
+            p75_by_cu: value.by_cu.p75 as i64,
+            p90_by_cu: value.by_cu.p90 as i64,
+            p95_by_cu: value.by_cu.p95 as i64,
+        }
+    }
+}
+
+// fees of the transactions write-locking an account in a block
+struct PostgresAccountPrioFees {
+    slot: i64,
+    account: String,
+    tx_count: i64,
+    cu_consumed: i64,
+    p50_by_tx: i64,
+    p75_by_tx: i64,
+    p90_by_tx: i64,
+    p95_by_tx: i64,
+}
+
+async fn insert_block_priofees(
+    postgres_session: &PostgresSession,
+    row: &PostgresBlockPrioFees,
+) -> anyhow::Result<()> {
+    const NB_ARGUMENTS: usize = 13;
+    let args: [&(dyn ToSql + Sync); NB_ARGUMENTS] = [
+        &row.slot,
+        &row.tx_count,
+        &row.nonvote_tx_count,
+        &row.cu_consumed,
+        &row.nonvote_cu_consumed,
+        &row.p50_by_tx,
+        &row.p75_by_tx,
+        &row.p90_by_tx,
+        &row.p95_by_tx,
+        &row.p50_by_cu,
+        &row.p75_by_cu,
+        &row.p90_by_cu,
+        &row.p95_by_cu,
+    ];
+
+    let values = PostgresSession::values_vecvec(NB_ARGUMENTS, 1, &[]);
+    let statement = format!(
+        r#"
+            INSERT INTO lite_rpc.BlockPrioFees
+            (slot, tx_count, nonvote_tx_count, cu_consumed, nonvote_cu_consumed, p50_by_tx, p75_by_tx, p90_by_tx, p95_by_tx, p50_by_cu, p75_by_cu, p90_by_cu, p95_by_cu)
+            VALUES {}
+            ON CONFLICT (slot) DO NOTHING
+        "#,
+        values
+    );
+
+    postgres_session.execute(&statement, &args).await?;
+    Ok(())
+}
+
+async fn insert_account_priofees(
+    postgres_session: &PostgresSession,
+    rows: &[PostgresAccountPrioFees],
+) -> anyhow::Result<()> {
+    const NB_ARGUMENTS: usize = 8;
+
+    for chunk in rows.chunks(ACCOUNT_ROWS_PER_STATEMENT) {
+        let mut args: Vec<&(dyn ToSql + Sync)> = Vec::with_capacity(NB_ARGUMENTS * chunk.len());
+        for row in chunk {
+            let PostgresAccountPrioFees {
+                slot,
+                account,
+                tx_count,
+                cu_consumed,
+                p50_by_tx,
+                p75_by_tx,
+                p90_by_tx,
+                p95_by_tx,
+            } = row;
+
+            args.push(slot);
+            args.push(account);
+            args.push(tx_count);
+            args.push(cu_consumed);
+            args.push(p50_by_tx);
+            args.push(p75_by_tx);
+            args.push(p90_by_tx);
+            args.push(p95_by_tx);
+        }
+
+        let values = PostgresSession::values_vecvec(NB_ARGUMENTS, chunk.len(), &[]);
+        let statement = format!(
+            r#"
+                INSERT INTO lite_rpc.AccountPrioFees
+                (slot, account, tx_count, cu_consumed, p50_by_tx, p75_by_tx, p90_by_tx, p95_by_tx)
+                VALUES {}
+                ON CONFLICT (slot, account) DO NOTHING
+            "#,
+            values
+        );
+
+        postgres_session.execute(&statement, &args).await?;
+    }
+    Ok(())
+}
+
+async fn prune(postgres_session: &PostgresSession, min_slot: Slot) -> anyhow::Result<()> {
+    let min_slot = min_slot as i64;
+    postgres_session
+        .execute(
+            "DELETE FROM lite_rpc.BlockPrioFees WHERE slot < $1",
+            &[&min_slot],
+        )
+        .await?;
+    postgres_session
+        .execute(
+            "DELETE FROM lite_rpc.AccountPrioFees WHERE slot < $1",
+            &[&min_slot],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Writes the per block and per account prioritization fee aggregates to postgres and deletes
+/// the rows older than `retention_slots`. Writes are best effort, failed blocks are skipped.
+pub struct PostgresPrioFeesLogger {}
+
+impl PostgresPrioFeesLogger {
+    pub fn start(
+        postgres_session_cache: PostgresSessionCache,
+        mut block_fees_stream: Receiver<PrioFeesUpdateMessage>,
+        mut account_fees_stream: Receiver<AccountPrioFeesUpdateMessage>,
+        retention_slots: u64,
+    ) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            info!("start postgres prioritization fee history, retention {retention_slots} slots");
+            let mut last_pruned_slot: Slot = 0;
+
+            loop {
+                let (slot, write_result) = tokio::select! {
+                    block_fees = block_fees_stream.recv() => {
+                        let PrioFeesUpdateMessage { slot, priofees_stats } = match block_fees {
+                            Ok(message) => message,
+                            Err(RecvError::Lagged(lagged)) => {
+                                warn!("priofees history lagged by {lagged} blocks - continue");
+                                continue;
+                            }
+                            Err(RecvError::Closed) => anyhow::bail!("block priofees stream closed"),
+                        };
+                        let row = PostgresBlockPrioFees::from(&priofees_stats.summary(slot));
+                        let result = match postgres_session_cache.get_session().await {
+                            Ok(session) => insert_block_priofees(&session, &row).await,
+                            Err(e) => Err(e),
+                        };
+                        if result.is_ok() {
+                            PRIOFEES_HISTORY_BLOCKS_WRITTEN.inc();
+                        }
+                        (slot, result)
+                    }
+                    account_fees = account_fees_stream.recv() => {
+                        let AccountPrioFeesUpdateMessage { slot, accounts_data } = match account_fees {
+                            Ok(message) => message,
+                            Err(RecvError::Lagged(lagged)) => {
+                                warn!("account priofees history lagged by {lagged} blocks - continue");
+                                continue;
+                            }
+                            Err(RecvError::Closed) => anyhow::bail!("account priofees stream closed"),
+                        };
+                        let rows = accounts_data
+                            .iter()
+                            .filter(|(_, stats)| stats.write_stats.tx_count.total > 0)
+                            .map(|(account, stats)| {
+                                let summary = stats.write_stats.summary(slot);
+                                PostgresAccountPrioFees {
+                                    slot: slot as i64,
+                                    account: account.to_string(),
+                                    tx_count: summary.tx_count.total as i64,
+                                    cu_consumed: summary.cu_consumed.total as i64,
+                                    p50_by_tx: summary.by_tx.p50 as i64,
+                                    p75_by_tx: summary.by_tx.p75 as i64,
+                                    p90_by_tx: summary.by_tx.p90 as i64,
+                                    p95_by_tx: summary.by_tx.p95 as i64,
+                                }
+                            })
+                            .collect::<Vec<_>>();
+                        let result = match postgres_session_cache.get_session().await {
+                            Ok(session) => insert_account_priofees(&session, &rows).await,
+                            Err(e) => Err(e),
+                        };
+                        (slot, result)
+                    }
+                };
+
+                if let Err(e) = write_result {
+                    PRIOFEES_HISTORY_WRITE_ERRORS.inc();
+                    warn!("failed to write priofees history for slot {slot}: {e:?}");
+                    continue;
+                }
+
+                if slot >= last_pruned_slot + PRUNE_INTERVAL_SLOTS {
+                    last_pruned_slot = slot;
+                    let min_slot = slot.saturating_sub(retention_slots);
+                    if let Ok(session) = postgres_session_cache.get_session().await {
+                        if let Err(e) = prune(&session, min_slot).await {
+                            warn!("failed to prune priofees history before slot {min_slot}: {e:?}");
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
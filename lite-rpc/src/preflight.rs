@@ -0,0 +1,425 @@
+use std::{
+    net::SocketAddr,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use base64::Engine;
+use futures::StreamExt;
+use solana_lite_rpc_core::{
+    keypair_loader::load_identity_keypair,
+    structures::account_filter::{
+        AccountFilterSets, AccountFilterType, AccountFilters, MemcmpFilterData,
+    },
+};
+use solana_pubsub_client::nonblocking::pubsub_client::PubsubClient;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signer::Signer};
+use tonic::transport::ClientTlsConfig;
+use yellowstone_grpc_client::GeyserGrpcClient;
+use yellowstone_grpc_proto::geyser::CommitmentLevel;
+
+#[cfg(feature = "kafka")]
+use crate::kafka_sink::{KafkaSink, KafkaSinkConfig};
+use crate::{
+    account_webhooks::{AccountWebhookConfig, AccountWebhookSink},
+    api_key_auth::ApiKeys,
+    cli::{url_obfuscate_api_token, Config},
+    cors::CorsConfig,
+    rate_limit::{RateLimitConfig, RateLimiter},
+    tls::{CertificateReloader, TlsConfig},
+};
+
+// every probe of a source has to finish within this time
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// checks the parts of the configuration which are only used after startup and prints a report,
+/// fails if any check failed
+pub async fn validate_config(config: &Config) -> anyhow::Result<()> {
+    let mut failed = 0;
+    let mut report = |check: &str, result: anyhow::Result<String>| match result {
+        Ok(details) => println!("ok      {check}: {details}"),
+        Err(err) => {
+            failed += 1;
+            println!("failed  {check}: {err:#}");
+        }
+    };
+
+    report("configuration", Ok("parsed".to_string()));
+
+    report(
+        "identity",
+        load_identity_keypair(config.identity_keypair.clone())
+            .await
+            .map(|identity| match identity {
+                Some(identity) => identity.pubkey().to_string(),
+                None => "not set, a random identity is used".to_string(),
+            }),
+    );
+
+    report(
+        "account filters",
+        validate_account_filters(&config.account_filters),
+    );
+
+    if let Some(account_filter_sets) = &config.account_filter_sets {
+        report(
+            "account filter sets",
+            serde_json::from_str::<AccountFilterSets>(account_filter_sets)
+                .context("Account filter sets should be valid")
+                .and_then(|account_filter_sets| {
+                    for (name, account_filters) in &account_filter_sets {
+                        check_account_filters(account_filters)
+                            .with_context(|| format!("filter set {name}"))?;
+                    }
+                    Ok(format!("{} filter sets", account_filter_sets.len()))
+                }),
+        );
+    }
+
+    if let Some(account_webhooks) = &config.account_webhooks {
+        report(
+            "account webhooks",
+            serde_json::from_str::<Vec<AccountWebhookConfig>>(account_webhooks)
+                .context("Account webhooks should be valid")
+                .and_then(|webhooks| {
+                    anyhow::ensure!(
+                        config.account_filters.is_some() || config.account_filter_sets.is_some(),
+                        "requires account filters"
+                    );
+                    let count = webhooks.len();
+                    AccountWebhookSink::new(webhooks).map(|_| format!("{count} webhooks"))
+                }),
+        );
+    }
+
+    if config.use_grpc {
+        for source in config.get_grpc_sources() {
+            report(
+                &format!("grpc source {}", source.addr),
+                tonic::transport::Endpoint::from_shared(source.addr.clone())
+                    .map(|_| "valid address".to_string())
+                    .context("Invalid gRPC address"),
+            );
+        }
+    }
+
+    if let Some(quic_proxy_addrs) = &config.quic_proxy_addr {
+        for quic_proxy_addr in quic_proxy_addrs.split(',').map(str::trim) {
+            report(
+                "quic proxy",
+                std::net::ToSocketAddrs::to_socket_addrs(quic_proxy_addr)
+                    .context("Cannot resolve quic proxy address")
+                    .map(|addrs| format!("{:?}", addrs.collect::<Vec<SocketAddr>>())),
+            );
+        }
+    }
+
+    if let Some(influx_udp_addr) = &config.influx_udp_addr {
+        report(
+            "influxdb",
+            std::net::ToSocketAddrs::to_socket_addrs(influx_udp_addr.as_str())
+                .context("Cannot resolve influxdb address")
+                .map(|addrs| format!("{:?}", addrs.collect::<Vec<SocketAddr>>())),
+        );
+    }
+
+    #[cfg(feature = "postgres")]
+    if config.enable_priofees_history {
+        report(
+            "prioritization fees history",
+            match config.postgres {
+                Some(_) => Ok("postgres configured".to_string()),
+                None => Err(anyhow::anyhow!(
+                    "requires postgres, set PG_ENABLED and PG_CONFIG"
+                )),
+            },
+        );
+    }
+
+    #[cfg(feature = "postgres")]
+    if config.postgres_notify_transactions_channel.is_some() {
+        report(
+            "postgres notifications",
+            match config.postgres {
+                Some(_) => Ok("postgres configured".to_string()),
+                None => Err(anyhow::anyhow!(
+                    "requires postgres, set PG_ENABLED and PG_CONFIG"
+                )),
+            },
+        );
+    }
+
+    if let (Some(cert_path), Some(key_path)) = (&config.tls_cert_path, &config.tls_key_path) {
+        report(
+            "tls",
+            CertificateReloader::new(TlsConfig {
+                cert_path: cert_path.clone(),
+                key_path: key_path.clone(),
+            })
+            .map(|_| format!("certificate {cert_path} loaded")),
+        );
+    }
+
+    if !config.api_keys.is_empty() || config.api_keys_file.is_some() {
+        report(
+            "api keys",
+            ApiKeys::new(config.api_keys.clone(), config.api_keys_file.clone())
+                .and_then(|api_keys| api_keys.reload())
+                .map(|number_of_keys| format!("{number_of_keys} keys")),
+        );
+    }
+
+    report(
+        "cors",
+        CorsConfig {
+            allowed_origins: config.cors_allowed_origins.clone(),
+            allowed_methods: config.cors_allowed_methods.clone(),
+            allowed_headers: config.cors_allowed_headers.clone(),
+            max_age: Duration::from_secs(config.cors_max_age_secs),
+        }
+        .layer()
+        .map(|_| format!("origins {:?}", config.cors_allowed_origins)),
+    );
+
+    if let Some(requests_per_second) = config.rate_limit_rps {
+        report(
+            "rate limit",
+            RateLimiter::new(RateLimitConfig {
+                requests_per_second,
+                burst: config.rate_limit_burst.unwrap_or(requests_per_second),
+                trusted_proxies: config.trusted_proxies.clone(),
+            })
+            .map(|_| format!("{requests_per_second} requests per second")),
+        );
+    }
+
+    #[cfg(feature = "kafka")]
+    if let Some(brokers) = &config.kafka_brokers {
+        report(
+            "kafka",
+            KafkaSink::new(KafkaSinkConfig {
+                brokers: brokers.clone(),
+                blocks_topic: config.kafka_blocks_topic.clone(),
+                transactions_topic: config.kafka_transactions_topic.clone(),
+                format: config.kafka_format,
+                commitment: config.kafka_commitment,
+                include_votes: config.kafka_include_votes,
+                producer_config: config.kafka_producer_config.clone(),
+            })
+            .map(|_| format!("{:?} records to {brokers}", config.kafka_format)),
+        );
+    }
+
+    anyhow::ensure!(failed == 0, "{failed} configuration checks failed");
+    println!("configuration is valid");
+    Ok(())
+}
+
+fn validate_account_filters(account_filters: &Option<String>) -> anyhow::Result<String> {
+    let Some(account_filters) = account_filters else {
+        return Ok("not set".to_string());
+    };
+    let account_filters: AccountFilters =
+        serde_json::from_str(account_filters).context("Account filters should be valid")?;
+    check_account_filters(&account_filters)?;
+    Ok(format!("{} filters", account_filters.len()))
+}
+
+fn check_account_filters(account_filters: &AccountFilters) -> anyhow::Result<()> {
+    for (index, filter) in account_filters.iter().enumerate() {
+        anyhow::ensure!(
+            !filter.accounts.is_empty() || filter.program_id.is_some(),
+            "filter {index} needs accounts or a program id"
+        );
+        for account in &filter.accounts {
+            Pubkey::from_str(account)
+                .with_context(|| format!("filter {index}: invalid account {account}"))?;
+        }
+        if let Some(program_id) = &filter.program_id {
+            Pubkey::from_str(program_id)
+                .with_context(|| format!("filter {index}: invalid program id {program_id}"))?;
+        }
+        for filter_type in filter.filters.iter().flatten() {
+            if let AccountFilterType::Memcmp(memcmp) = filter_type {
+                match &memcmp.data {
+                    MemcmpFilterData::Bytes(_) => {}
+                    MemcmpFilterData::Base58(data) => {
+                        bs58::decode(data).into_vec().with_context(|| {
+                            format!("filter {index}: invalid base58 memcmp data {data}")
+                        })?;
+                    }
+                    MemcmpFilterData::Base64(data) => {
+                        base64::engine::general_purpose::STANDARD
+                            .decode(data)
+                            .with_context(|| {
+                                format!("filter {index}: invalid base64 memcmp data {data}")
+                            })?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// connects to the rpc node, its websocket and every gRPC source and prints latency, version and slot,
+/// fails if any source is unreachable; api tokens in the urls are obfuscated in the output
+pub async fn probe_sources(config: &Config) -> anyhow::Result<()> {
+    let mut failed = 0;
+    let mut report = |kind: &str, addr: &str, result: anyhow::Result<String>| {
+        let shown_addr = url_obfuscate_api_token(addr);
+        match result {
+            Ok(details) => println!("ok      {kind} {shown_addr}: {details}"),
+            Err(err) => {
+                failed += 1;
+                println!(
+                    "failed  {kind} {shown_addr}: {}",
+                    obfuscate_addr_in(&format!("{err:#}"), addr)
+                );
+            }
+        }
+    };
+
+    report(
+        "rpc",
+        &config.rpc_addr,
+        with_timeout(probe_rpc(config.rpc_addr.clone())).await,
+    );
+    report(
+        "websocket",
+        &config.ws_addr,
+        with_timeout(probe_websocket(config.ws_addr.clone())).await,
+    );
+    if let Some(faithful_rpc_addr) = &config.faithful_rpc_addr {
+        report(
+            "faithful rpc",
+            faithful_rpc_addr,
+            with_timeout(probe_rpc(faithful_rpc_addr.clone())).await,
+        );
+    }
+    if config.use_grpc {
+        for source in config.get_grpc_sources() {
+            report(
+                "grpc",
+                &source.addr,
+                with_timeout(probe_grpc(source.addr.clone(), source.x_token.clone())).await,
+            );
+        }
+    }
+
+    anyhow::ensure!(failed == 0, "{failed} sources failed");
+    println!("all sources reachable");
+    Ok(())
+}
+
+/// client errors quote the url they failed on, either as configured or in its normalized form
+fn obfuscate_addr_in(message: &str, addr: &str) -> String {
+    let mut message = message.replace(addr, &url_obfuscate_api_token(addr));
+    if let Ok(normalized) = url::Url::parse(addr) {
+        message = message.replace(
+            normalized.as_str(),
+            &url_obfuscate_api_token(normalized.as_str()),
+        );
+    }
+    message
+}
+
+async fn with_timeout(
+    probe: impl std::future::Future<Output = anyhow::Result<String>>,
+) -> anyhow::Result<String> {
+    tokio::time::timeout(PROBE_TIMEOUT, probe)
+        .await
+        .map_err(|_| anyhow::anyhow!("timed out after {PROBE_TIMEOUT:?}"))?
+}
+
+async fn probe_rpc(rpc_addr: String) -> anyhow::Result<String> {
+    let rpc_client = RpcClient::new(rpc_addr);
+    let started_at = Instant::now();
+    let slot = rpc_client
+        .get_slot_with_commitment(CommitmentConfig::processed())
+        .await?;
+    let latency = started_at.elapsed();
+    let version = rpc_client.get_version().await?;
+    let finalized_slot = rpc_client
+        .get_slot_with_commitment(CommitmentConfig::finalized())
+        .await?;
+    let health = match rpc_client.get_health().await {
+        Ok(()) => "healthy".to_string(),
+        Err(err) => format!("unhealthy ({err})"),
+    };
+    Ok(format!(
+        "latency {latency:?}, version {} (feature set {:?}), processed slot {slot}, finalized slot {finalized_slot}, {health}",
+        version.solana_core, version.feature_set
+    ))
+}
+
+async fn probe_websocket(ws_addr: String) -> anyhow::Result<String> {
+    let started_at = Instant::now();
+    let pubsub_client = PubsubClient::new(&ws_addr).await?;
+    let latency = started_at.elapsed();
+    let (mut slots, unsubscribe) = pubsub_client.slot_subscribe().await?;
+    let slot = slots
+        .next()
+        .await
+        .context("slot subscription closed without a notification")?;
+    unsubscribe().await;
+    Ok(format!(
+        "connect latency {latency:?}, slot subscription works, slot {}",
+        slot.slot
+    ))
+}
+
+async fn probe_grpc(grpc_addr: String, x_token: Option<String>) -> anyhow::Result<String> {
+    let tls_config = grpc_addr.starts_with("https").then(ClientTlsConfig::new);
+    let started_at = Instant::now();
+    let mut client = GeyserGrpcClient::connect_with_timeout(
+        grpc_addr,
+        x_token,
+        tls_config,
+        Some(PROBE_TIMEOUT),
+        Some(PROBE_TIMEOUT),
+        false,
+    )
+    .await?;
+    let connect_latency = started_at.elapsed();
+
+    let started_at = Instant::now();
+    let processed_slot = client
+        .get_slot(Some(CommitmentLevel::Processed))
+        .await?
+        .slot;
+    let latency = started_at.elapsed();
+    let confirmed_slot = client
+        .get_slot(Some(CommitmentLevel::Confirmed))
+        .await?
+        .slot;
+    let finalized_slot = client
+        .get_slot(Some(CommitmentLevel::Finalized))
+        .await?
+        .slot;
+    let version = client.get_version().await?.version;
+    Ok(format!(
+        "connect latency {connect_latency:?}, latency {latency:?}, version {version}, processed slot {processed_slot}, confirmed slot {confirmed_slot}, finalized slot {finalized_slot}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_tokens_are_obfuscated_in_probe_errors() {
+        let addr = "https://rpc.example.com?api-key=secret";
+        let message = obfuscate_addr_in(
+            "error sending request for url (https://rpc.example.com/?api-key=secret): timed out",
+            addr,
+        );
+        assert!(!message.contains("=secret"), "{message}");
+        assert!(!message.contains("api-key"), "{message}");
+
+        let message = obfuscate_addr_in(&format!("failed to connect to {addr}"), addr);
+        assert!(!message.contains("api-key"), "{message}");
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::Context;
+use bytes::Bytes;
+use log::{debug, info, warn};
+use prometheus::{opts, register_int_counter, IntCounter};
+use redis::{aio::ConnectionManager, AsyncCommands};
+use solana_lite_rpc_core::{
+    stores::tx_store::TxStore,
+    types::{BlockStream, SlotStream},
+    AnyhowJoinHandle,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::sink_records::{block_record, slot_record, transaction_status_records, SinkFormat};
+
+lazy_static::lazy_static! {
+    static ref PUBSUB_MESSAGES_PUBLISHED: IntCounter =
+        register_int_counter!(opts!("literpc_pubsub_sink_messages_published", "Messages published to the redis or nats sink")).unwrap();
+    static ref PUBSUB_MESSAGES_FAILED: IntCounter =
+        register_int_counter!(opts!("literpc_pubsub_sink_messages_failed", "Messages which could not be published to the redis or nats sink")).unwrap();
+}
+
+#[derive(Clone, Debug)]
+pub struct PubSubSinkConfig {
+    /// `redis://`, `rediss://`, `nats://` or `tls://` url
+    pub url: String,
+    /// channels are `<prefix>.slots`, `<prefix>.blocks` and `<prefix>.transactions`
+    pub channel_prefix: String,
+    pub format: SinkFormat,
+}
+
+enum Publisher {
+    Redis(ConnectionManager),
+    Nats(async_nats::Client),
+}
+
+impl Publisher {
+    async fn connect(url: &str) -> anyhow::Result<Self> {
+        if url.starts_with("redis://") || url.starts_with("rediss://") {
+            let client = redis::Client::open(url).context("Invalid redis url")?;
+            let connection = ConnectionManager::new(client)
+                .await
+                .context("Cannot connect to redis")?;
+            Ok(Self::Redis(connection))
+        } else if url.starts_with("nats://") || url.starts_with("tls://") {
+            let client = async_nats::connect(url)
+                .await
+                .context("Cannot connect to nats")?;
+            Ok(Self::Nats(client))
+        } else {
+            anyhow::bail!("Pub-sub sink url must start with redis://, rediss://, nats:// or tls://")
+        }
+    }
+
+    async fn publish(&mut self, channel: &str, payload: Vec<u8>) -> anyhow::Result<()> {
+        match self {
+            Self::Redis(connection) => {
+                connection.publish::<_, _, i64>(channel, payload).await?;
+            }
+            Self::Nats(client) => {
+                client
+                    .publish(channel.to_string(), Bytes::from(payload))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// publishes slot updates, block summaries and status changes of the transactions sent through lite-rpc
+/// to redis or nats, for services which do not want to hold a websocket connection
+pub struct PubSubSink {
+    config: PubSubSinkConfig,
+    publisher: Publisher,
+}
+
+impl PubSubSink {
+    pub async fn connect(config: PubSubSinkConfig) -> anyhow::Result<Self> {
+        let publisher = Publisher::connect(&config.url).await?;
+        Ok(Self { config, publisher })
+    }
+
+    pub fn start(
+        mut self,
+        mut slot_stream: SlotStream,
+        mut block_stream: BlockStream,
+        txs: TxStore,
+    ) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            info!(
+                "Publishing slots, blocks and transaction statuses to {}.*",
+                self.config.channel_prefix
+            );
+            let slots_channel = format!("{}.slots", self.config.channel_prefix);
+            let blocks_channel = format!("{}.blocks", self.config.channel_prefix);
+            let transactions_channel = format!("{}.transactions", self.config.channel_prefix);
+            loop {
+                tokio::select! {
+                    slot = slot_stream.recv() => match slot {
+                        Ok(slot) => {
+                            let payload = self.config.format.encode(&slot_record(&slot));
+                            self.publish(&slots_channel, payload).await;
+                        }
+                        Err(RecvError::Lagged(lagged)) => {
+                            warn!("pub-sub sink lagged by {lagged} slots - continue");
+                        }
+                        Err(RecvError::Closed) => anyhow::bail!("pub-sub sink slot stream closed"),
+                    },
+                    block = block_stream.recv() => match block {
+                        Ok(block) => {
+                            let payload = self.config.format.encode(&block_record(&block));
+                            self.publish(&blocks_channel, payload).await;
+                            for record in transaction_status_records(&block, &txs) {
+                                let payload = self.config.format.encode(&record);
+                                self.publish(&transactions_channel, payload).await;
+                            }
+                        }
+                        Err(RecvError::Lagged(lagged)) => {
+                            warn!("pub-sub sink lagged by {lagged} blocks - continue");
+                        }
+                        Err(RecvError::Closed) => anyhow::bail!("pub-sub sink block stream closed"),
+                    },
+                }
+            }
+        })
+    }
+
+    async fn publish(&mut self, channel: &str, payload: Vec<u8>) {
+        match self.publisher.publish(channel, payload).await {
+            Ok(()) => PUBSUB_MESSAGES_PUBLISHED.inc(),
+            Err(err) => {
+                PUBSUB_MESSAGES_FAILED.inc();
+                debug!("failed to publish to {channel}: {err:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,665 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    io,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    sync::{Arc, Mutex, OnceLock},
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context as _;
+use hyper::{header::RETRY_AFTER, http::HeaderMap, Body, Method, Request, Response, StatusCode};
+use prometheus::{opts, register_int_counter, register_int_gauge, IntCounter, IntGauge};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tower::{Layer, Service};
+
+use crate::{api_key_auth::ApiKeyId, utils::text_response};
+
+lazy_static::lazy_static! {
+    static ref RATE_LIMITED_REQUESTS: IntCounter =
+        register_int_counter!(opts!("literpc_rate_limited_requests", "Requests rejected by the per ip rate limit")).unwrap();
+    static ref RATE_LIMITED_WS_MESSAGES: IntCounter =
+        register_int_counter!(opts!("literpc_rate_limited_ws_messages", "Websocket messages delayed by the per ip rate limit")).unwrap();
+    static ref RATE_LIMIT_TRACKED_IPS: IntGauge =
+        register_int_gauge!(opts!("literpc_rate_limit_tracked_ips", "Client ips with a rate limit bucket")).unwrap();
+}
+
+pub const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+// buckets which are full again are removed this often
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+// an ipv6 client usually gets a whole /64, the addresses within it share a bucket
+const IPV6_BUCKET_PREFIX_LEN: u32 = 64;
+
+/// request extension with the address of the tcp peer, set by the servers for every connection
+#[derive(Clone, Copy, Debug)]
+pub struct RemoteAddr(pub SocketAddr);
+
+#[derive(Clone, Debug)]
+pub struct RateLimitConfig {
+    /// tokens added per second and client ip
+    pub requests_per_second: u64,
+    /// size of the bucket, the number of requests a client can send at once
+    pub burst: u64,
+    /// proxies (`10.0.0.1` or `10.0.0.0/8`) whose `X-Forwarded-For` header is used to find the client ip
+    pub trusted_proxies: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TrustedProxy {
+    network: u128,
+    prefix_len: u32,
+}
+
+impl TrustedProxy {
+    fn parse(proxy: &str) -> anyhow::Result<Self> {
+        let (addr, prefix_len) = match proxy.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (proxy, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .with_context(|| format!("Invalid trusted proxy {proxy}"))?;
+        let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse::<u32>()
+                .ok()
+                .filter(|prefix_len| *prefix_len <= max_prefix_len)
+                .with_context(|| format!("Invalid prefix length in trusted proxy {proxy}"))?,
+            None => max_prefix_len,
+        };
+        // ipv4 addresses are compared as ipv4 mapped ipv6 addresses
+        let prefix_len = prefix_len + (128 - max_prefix_len);
+        Ok(Self {
+            network: to_bits(addr) & mask(prefix_len),
+            prefix_len,
+        })
+    }
+
+    fn contains(&self, addr: IpAddr) -> bool {
+        to_bits(addr) & mask(self.prefix_len) == self.network
+    }
+}
+
+fn to_bits(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(addr) => u128::from(addr.to_ipv6_mapped()),
+        IpAddr::V6(addr) => match addr.to_ipv4_mapped() {
+            Some(addr) => u128::from(addr.to_ipv6_mapped()),
+            None => u128::from(addr),
+        },
+    }
+}
+
+fn mask(prefix_len: u32) -> u128 {
+    u128::MAX.checked_shl(128 - prefix_len).unwrap_or(0)
+}
+
+/// the ipv6 addresses of a /64 are one client
+fn bucket_key(client_ip: IpAddr) -> IpAddr {
+    match client_ip {
+        IpAddr::V6(addr) if addr.to_ipv4_mapped().is_none() => IpAddr::V6(Ipv6Addr::from(
+            u128::from(addr) & mask(IPV6_BUCKET_PREFIX_LEN),
+        )),
+        client_ip => client_ip,
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+struct Buckets {
+    // the limits are kept with the buckets so a reload applies to all of them at once
+    requests_per_second: f64,
+    burst: f64,
+    by_ip: HashMap<IpAddr, Bucket>,
+    last_cleanup: Instant,
+}
+
+/// token buckets per client ip, shared by the HTTP and WebSocket servers
+#[derive(Clone)]
+pub struct RateLimiter {
+    trusted_proxies: Arc<Vec<TrustedProxy>>,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> anyhow::Result<Self> {
+        ensure_limits(config.requests_per_second)?;
+        let trusted_proxies = config
+            .trusted_proxies
+            .iter()
+            .map(|proxy| TrustedProxy::parse(proxy))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            trusted_proxies: Arc::new(trusted_proxies),
+            buckets: Arc::new(Mutex::new(Buckets {
+                requests_per_second: config.requests_per_second as f64,
+                burst: config.burst.max(1) as f64,
+                by_ip: HashMap::new(),
+                last_cleanup: Instant::now(),
+            })),
+        })
+    }
+
+    /// changes the limits of all clients, the tokens of a client are capped at the new burst
+    pub fn update_limits(&self, requests_per_second: u64, burst: u64) -> anyhow::Result<()> {
+        ensure_limits(requests_per_second)?;
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.requests_per_second = requests_per_second as f64;
+        buckets.burst = burst.max(1) as f64;
+        Ok(())
+    }
+
+    fn is_trusted(&self, addr: IpAddr) -> bool {
+        self.trusted_proxies
+            .iter()
+            .any(|proxy| proxy.contains(addr))
+    }
+
+    /// the peer address, or the last address in `X-Forwarded-For` not added by a trusted proxy
+    pub fn client_ip(&self, remote_addr: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client_ip = remote_addr;
+        if !self.is_trusted(client_ip) {
+            return client_ip;
+        }
+        let forwarded_for: Vec<&str> = headers
+            .get_all(FORWARDED_FOR_HEADER)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+        for hop in forwarded_for.into_iter().rev() {
+            let Ok(hop) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client_ip = hop;
+            if !self.is_trusted(hop) {
+                break;
+            }
+        }
+        client_ip
+    }
+
+    /// takes a token of the client, or returns how long to wait for the next one
+    pub fn try_acquire(&self, client_ip: IpAddr) -> Result<(), Duration> {
+        self.try_acquire_many(client_ip, 1)
+    }
+
+    /// the number of tokens a client can take at once
+    pub fn burst(&self) -> u64 {
+        self.buckets.lock().unwrap().burst as u64
+    }
+
+    /// takes `tokens` tokens of the client at once, or returns how long to wait until it has them;
+    /// more tokens than the burst are never available
+    pub fn try_acquire_many(&self, client_ip: IpAddr, tokens: u64) -> Result<(), Duration> {
+        let tokens = tokens as f64;
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let (requests_per_second, burst) = (buckets.requests_per_second, buckets.burst);
+
+        if now.duration_since(buckets.last_cleanup) >= CLEANUP_INTERVAL {
+            buckets.by_ip.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens + elapsed * requests_per_second < burst
+            });
+            buckets.last_cleanup = now;
+        }
+
+        let bucket = buckets
+            .by_ip
+            .entry(bucket_key(client_ip))
+            .or_insert(Bucket {
+                tokens: burst,
+                last_refill: now,
+            });
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * requests_per_second).min(burst);
+        bucket.last_refill = now;
+
+        let result = if bucket.tokens >= tokens {
+            bucket.tokens -= tokens;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (tokens - bucket.tokens) / requests_per_second,
+            ))
+        };
+        RATE_LIMIT_TRACKED_IPS.set(buckets.by_ip.len() as i64);
+        result
+    }
+}
+
+fn ensure_limits(requests_per_second: u64) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        requests_per_second > 0,
+        "Rate limit must be at least one request per second"
+    );
+    Ok(())
+}
+
+/// tower layer rejecting requests of clients which exceeded their rate with `429`, a json rpc batch
+/// takes a token per request; requests authenticated with an api key are not limited, does nothing
+/// if no rate limit is configured
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    pub fn new(rate_limiter: Option<RateLimiter>) -> Self {
+        Self { rate_limiter }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            rate_limiter: self.rate_limiter.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl<S> Service<Request<Body>> for RateLimitService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let (Some(rate_limiter), Some(RemoteAddr(remote_addr))) = (
+            self.rate_limiter.clone(),
+            request.extensions().get::<RemoteAddr>().copied(),
+        ) else {
+            return Box::pin(self.inner.call(request));
+        };
+        if request.extensions().get::<ApiKeyId>().is_some() {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let client_ip = rate_limiter.client_ip(remote_addr.ip(), request.headers());
+        // the messages of a websocket connection take the tokens of its client
+        if let Some(ConnectionClient(client)) = request.extensions().get::<ConnectionClient>() {
+            let _ = client.set(client_ip);
+        }
+        // the ready service is taken, a clone waits for the next request
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            // only json rpc requests are posted, e.g. the websocket upgrade request has no body
+            if request.method() != Method::POST {
+                return match rate_limiter.try_acquire(client_ip) {
+                    Ok(()) => inner.call(request).await,
+                    Err(retry_after) => Ok(rate_limited(client_ip, retry_after)),
+                };
+            }
+            // the body was read within the size limit by the http limit layer
+            let (parts, body) = request.into_parts();
+            let body = match hyper::body::to_bytes(body).await {
+                Ok(body) => body,
+                Err(err) => {
+                    log::debug!("Reading the request body failed: {err}");
+                    return Ok(text_response(
+                        StatusCode::BAD_REQUEST,
+                        "invalid request body",
+                    ));
+                }
+            };
+            let cost = request_cost(&body);
+            if cost > rate_limiter.burst() {
+                RATE_LIMITED_REQUESTS.inc();
+                return Ok(text_response(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "batch larger than the rate limit burst",
+                ));
+            }
+            match rate_limiter.try_acquire_many(client_ip, cost) {
+                Ok(()) => {
+                    inner
+                        .call(Request::from_parts(parts, Body::from(body)))
+                        .await
+                }
+                Err(retry_after) => Ok(rate_limited(client_ip, retry_after)),
+            }
+        })
+    }
+}
+
+fn rate_limited(client_ip: IpAddr, retry_after: Duration) -> Response<Body> {
+    RATE_LIMITED_REQUESTS.inc();
+    log::debug!("Rate limited request of {client_ip}");
+    let retry_after_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header(RETRY_AFTER, retry_after_secs)
+        .body(Body::from("rate limit exceeded"))
+        .unwrap()
+}
+
+// a json rpc batch takes a token per request, everything else one token
+fn request_cost(body: &[u8]) -> u64 {
+    if body.iter().find(|byte| !byte.is_ascii_whitespace()) != Some(&b'[') {
+        return 1;
+    }
+    serde_json::from_slice::<Vec<serde::de::IgnoredAny>>(body)
+        .map_or(1, |batch| batch.len().max(1) as u64)
+}
+
+/// request extension of the connections of the websocket server, the rate limit layer sets the
+/// client of the upgrade request and the messages of the connection take its tokens
+#[derive(Clone, Default)]
+pub struct ConnectionClient(Arc<OnceLock<IpAddr>>);
+
+/// the stream of a websocket connection, a message received while its client has no token left
+/// delays the reads of the connection until the client has one
+pub struct MeteredStream<I> {
+    inner: I,
+    rate_limiter: RateLimiter,
+    client: ConnectionClient,
+    frames: FrameCounter,
+    // messages received which did not take a token yet
+    unpaid_messages: u64,
+    wait: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl<I> MeteredStream<I> {
+    pub fn new(inner: I, rate_limiter: RateLimiter, client: ConnectionClient) -> Self {
+        Self {
+            inner,
+            rate_limiter,
+            client,
+            frames: FrameCounter::default(),
+            unpaid_messages: 0,
+            wait: None,
+        }
+    }
+}
+
+impl<I: AsyncRead + Unpin> AsyncRead for MeteredStream<I> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(wait) = this.wait.as_mut() {
+                ready!(wait.as_mut().poll(cx));
+                this.wait = None;
+            }
+            if this.unpaid_messages == 0 {
+                break;
+            }
+            // not limited, e.g. authenticated with an api key
+            let Some(client_ip) = this.client.0.get().copied() else {
+                this.unpaid_messages = 0;
+                break;
+            };
+            match this.rate_limiter.try_acquire(client_ip) {
+                Ok(()) => this.unpaid_messages -= 1,
+                Err(retry_after) => {
+                    RATE_LIMITED_WS_MESSAGES.inc();
+                    this.wait = Some(Box::pin(tokio::time::sleep(retry_after)));
+                }
+            }
+        }
+
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.unpaid_messages += this.frames.feed(&buf.filled()[filled..]);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<I: AsyncWrite + Unpin> AsyncWrite for MeteredStream<I> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+const HTTP_HEADERS_END: &[u8] = b"\r\n\r\n";
+
+enum FrameState {
+    // the upgrade request, up to the end of its headers
+    HttpHeaders { matched: usize },
+    FrameHeader(Vec<u8>),
+    Payload(u64),
+}
+
+/// counts the frames a websocket client sends, continuation frames are part of the message
+/// of the frame before
+struct FrameCounter {
+    state: FrameState,
+}
+
+impl Default for FrameCounter {
+    fn default() -> Self {
+        Self {
+            state: FrameState::HttpHeaders { matched: 0 },
+        }
+    }
+}
+
+impl FrameCounter {
+    /// the messages started in the received bytes
+    fn feed(&mut self, mut bytes: &[u8]) -> u64 {
+        let mut messages = 0;
+        while let Some((&byte, rest)) = bytes.split_first() {
+            match &mut self.state {
+                FrameState::HttpHeaders { matched } => {
+                    *matched = if byte == HTTP_HEADERS_END[*matched] {
+                        *matched + 1
+                    } else {
+                        usize::from(byte == b'\r')
+                    };
+                    if *matched == HTTP_HEADERS_END.len() {
+                        self.state = FrameState::FrameHeader(Vec::new());
+                    }
+                    bytes = rest;
+                }
+                FrameState::FrameHeader(header) => {
+                    header.push(byte);
+                    if let Some((opcode, payload_len)) = frame_header(header) {
+                        if opcode != 0 {
+                            messages += 1;
+                        }
+                        self.state = if payload_len > 0 {
+                            FrameState::Payload(payload_len)
+                        } else {
+                            FrameState::FrameHeader(Vec::new())
+                        };
+                    }
+                    bytes = rest;
+                }
+                FrameState::Payload(remaining) => {
+                    let skipped = (*remaining).min(bytes.len() as u64);
+                    *remaining -= skipped;
+                    if *remaining == 0 {
+                        self.state = FrameState::FrameHeader(Vec::new());
+                    }
+                    bytes = &bytes[skipped as usize..];
+                }
+            }
+        }
+        messages
+    }
+}
+
+// opcode and payload length once the header of a frame is complete
+fn frame_header(header: &[u8]) -> Option<(u8, u64)> {
+    let (first, second) = (*header.first()?, *header.get(1)?);
+    let (extended_len_bytes, payload_len) = match second & 0x7f {
+        126 => (2, None),
+        127 => (8, None),
+        payload_len => (0, Some(u64::from(payload_len))),
+    };
+    let mask_bytes = if second & 0x80 != 0 { 4 } else { 0 };
+    if header.len() < 2 + extended_len_bytes + mask_bytes {
+        return None;
+    }
+    let payload_len = payload_len.unwrap_or_else(|| {
+        header[2..2 + extended_len_bytes]
+            .iter()
+            .fold(0, |payload_len, byte| payload_len << 8 | u64::from(*byte))
+    });
+    Some((first & 0x0f, payload_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_limiter(requests_per_second: u64, burst: u64) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            requests_per_second,
+            burst,
+            trusted_proxies: vec![],
+        })
+        .unwrap()
+    }
+
+    // a masked client frame
+    fn frame(opcode: u8, fin: bool, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![opcode | if fin { 0x80 } else { 0 }];
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&[1, 2, 3, 4]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn test_batches_take_a_token_per_request() {
+        assert_eq!(request_cost(b""), 1);
+        assert_eq!(
+            request_cost(br#"{"jsonrpc":"2.0","id":1,"method":"getSlot"}"#),
+            1
+        );
+        assert_eq!(
+            request_cost(br#" [{"method":"getSlot"},{"method":"getHealth"},{}]"#),
+            3
+        );
+        assert_eq!(request_cost(b"[]"), 1);
+        assert_eq!(request_cost(b"[invalid"), 1);
+
+        let rate_limiter = rate_limiter(1, 5);
+        let client_ip: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!(rate_limiter.try_acquire_many(client_ip, 3).is_ok());
+        assert!(rate_limiter.try_acquire_many(client_ip, 3).is_err());
+        assert!(rate_limiter.try_acquire_many(client_ip, 2).is_ok());
+    }
+
+    #[test]
+    fn test_ipv6_clients_are_limited_per_64() {
+        let rate_limiter = rate_limiter(1, 1);
+        let first: IpAddr = "2001:db8:1:2::1".parse().unwrap();
+        let same_64: IpAddr = "2001:db8:1:2:ffff::2".parse().unwrap();
+        let other_64: IpAddr = "2001:db8:1:3::1".parse().unwrap();
+        assert!(rate_limiter.try_acquire(first).is_ok());
+        assert!(rate_limiter.try_acquire(same_64).is_err());
+        assert!(rate_limiter.try_acquire(other_64).is_ok());
+
+        // ipv4 addresses keep a bucket each
+        assert!(rate_limiter
+            .try_acquire("10.0.0.1".parse().unwrap())
+            .is_ok());
+        assert!(rate_limiter
+            .try_acquire("10.0.0.2".parse().unwrap())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_websocket_messages_are_counted() {
+        let mut frames = FrameCounter::default();
+        assert_eq!(
+            frames.feed(b"GET / HTTP/1.1\r\nUpgrade: websocket\r\n\r\n"),
+            0
+        );
+
+        let mut stream = frame(0x1, true, br#"{"method":"slotSubscribe"}"#);
+        // a message in two frames
+        stream.extend(frame(0x1, false, &[b'a'; 300]));
+        stream.extend(frame(0x0, true, &[b'b'; 70_000]));
+        // control frames take a token as well
+        stream.extend(frame(0x9, true, b""));
+        // split at every possible position of the headers
+        let mut messages = 0;
+        for chunk in stream.chunks(3) {
+            messages += frames.feed(chunk);
+        }
+        assert_eq!(messages, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_websocket_messages_wait_for_tokens() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let rate_limiter = rate_limiter(1, 2);
+        let client = ConnectionClient::default();
+        let (mut client_stream, server_stream) = tokio::io::duplex(1 << 16);
+        let mut metered = MeteredStream::new(server_stream, rate_limiter, client.clone());
+
+        client_stream
+            .write_all(b"GET / HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut buffer = vec![0; 1 << 16];
+        metered.read(&mut buffer).await.unwrap();
+        client.0.set("10.0.0.1".parse().unwrap()).unwrap();
+
+        let started_at = tokio::time::Instant::now();
+        for _ in 0..4 {
+            client_stream
+                .write_all(&frame(0x1, true, b"{}"))
+                .await
+                .unwrap();
+            metered.read(&mut buffer).await.unwrap();
+        }
+        // the burst covers two messages, the third waits a second for its token
+        assert_eq!(started_at.elapsed(), Duration::from_secs(1));
+    }
+}
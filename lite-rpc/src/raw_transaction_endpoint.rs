@@ -0,0 +1,215 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use hyper::{Body, Method, Request, Response, StatusCode};
+use prometheus::{opts, register_int_counter, IntCounter};
+use solana_lite_rpc_services::transaction_service::{NodeDraining, TransactionService};
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use tower::{Layer, Service};
+
+use crate::{health_endpoint::HealthState, utils::text_response};
+
+lazy_static::lazy_static! {
+    static ref RAW_SEND_TX: IntCounter =
+        register_int_counter!(opts!("literpc_raw_send_tx", "Transactions submitted over the raw binary HTTP endpoint")).unwrap();
+}
+
+/// POST a single wire-format transaction as the request body
+pub const RAW_TRANSACTION_PATH: &str = "/raw/transaction";
+/// POST a batch of transactions, each prefixed with its length as u16 little endian
+pub const RAW_TRANSACTION_BATCH_PATH: &str = "/raw/transactions";
+
+const MAX_TRANSACTIONS_IN_BATCH: usize = 1024;
+const MAX_BATCH_BODY_SIZE: usize = MAX_TRANSACTIONS_IN_BATCH * (PACKET_DATA_SIZE + 2);
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+enum RawSendResult {
+    Signature(String),
+    Error(String),
+}
+
+/// tower layer serving the raw transaction endpoints in front of the json rpc http server
+#[derive(Clone)]
+pub struct RawTransactionLayer {
+    transaction_service: TransactionService,
+    health_state: HealthState,
+}
+
+impl RawTransactionLayer {
+    pub fn new(transaction_service: TransactionService, health_state: HealthState) -> Self {
+        Self {
+            transaction_service,
+            health_state,
+        }
+    }
+}
+
+impl<S> Layer<S> for RawTransactionLayer {
+    type Service = RawTransactionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RawTransactionService {
+            inner,
+            transaction_service: self.transaction_service.clone(),
+            health_state: self.health_state.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RawTransactionService<S> {
+    inner: S,
+    transaction_service: TransactionService,
+    health_state: HealthState,
+}
+
+impl<S> Service<Request<Body>> for RawTransactionService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        if request.method() != Method::POST {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let transaction_service = self.transaction_service.clone();
+        let health_state = self.health_state.clone();
+        match request.uri().path() {
+            RAW_TRANSACTION_PATH => Box::pin(async move {
+                if let Err(reason) = health_state.is_caught_up().await {
+                    return Ok(node_starting_response(reason));
+                }
+                Ok(send_single(transaction_service, request).await)
+            }),
+            RAW_TRANSACTION_BATCH_PATH => Box::pin(async move {
+                if let Err(reason) = health_state.is_caught_up().await {
+                    return Ok(node_starting_response(reason));
+                }
+                Ok(send_batch(transaction_service, request).await)
+            }),
+            _ => Box::pin(self.inner.call(request)),
+        }
+    }
+}
+
+async fn read_body(request: Request<Body>, max_size: usize) -> Result<hyper::body::Bytes, String> {
+    let content_length = request
+        .headers()
+        .get(hyper::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.map_or(false, |length| length > max_size) {
+        return Err(format!("body exceeds {max_size} bytes"));
+    }
+    let body = hyper::body::to_bytes(request.into_body())
+        .await
+        .map_err(|e| e.to_string())?;
+    if body.len() > max_size {
+        return Err(format!("body exceeds {max_size} bytes"));
+    }
+    Ok(body)
+}
+
+async fn send_single(
+    transaction_service: TransactionService,
+    request: Request<Body>,
+) -> Response<Body> {
+    let body = match read_body(request, PACKET_DATA_SIZE).await {
+        Ok(body) => body,
+        Err(e) => return text_response(StatusCode::PAYLOAD_TOO_LARGE, e),
+    };
+    RAW_SEND_TX.inc();
+    match transaction_service
+        .send_transaction(body.to_vec(), None)
+        .await
+    {
+        Ok(signature) => text_response(StatusCode::OK, signature),
+        Err(e) if e.is::<NodeDraining>() => {
+            text_response(StatusCode::SERVICE_UNAVAILABLE, e.to_string())
+        }
+        Err(e) => text_response(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+async fn send_batch(
+    transaction_service: TransactionService,
+    request: Request<Body>,
+) -> Response<Body> {
+    let body = match read_body(request, MAX_BATCH_BODY_SIZE).await {
+        Ok(body) => body,
+        Err(e) => return text_response(StatusCode::PAYLOAD_TOO_LARGE, e),
+    };
+    let transactions = match split_length_prefixed(&body) {
+        Ok(transactions) => transactions,
+        Err(e) => return text_response(StatusCode::BAD_REQUEST, e),
+    };
+    // the whole batch is retried on another node
+    if transaction_service.is_draining() {
+        return text_response(StatusCode::SERVICE_UNAVAILABLE, NodeDraining.to_string());
+    }
+
+    let mut results = Vec::with_capacity(transactions.len());
+    for transaction in transactions {
+        RAW_SEND_TX.inc();
+        let result = match transaction_service
+            .send_transaction(transaction.to_vec(), None)
+            .await
+        {
+            Ok(signature) => RawSendResult::Signature(signature),
+            Err(e) => RawSendResult::Error(e.to_string()),
+        };
+        results.push(result);
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(&results).unwrap()))
+        .unwrap()
+}
+
+fn split_length_prefixed(body: &[u8]) -> Result<Vec<&[u8]>, String> {
+    let mut transactions = vec![];
+    let mut rest = body;
+    while !rest.is_empty() {
+        if rest.len() < 2 {
+            return Err("truncated length prefix".to_string());
+        }
+        let length = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+        if length > PACKET_DATA_SIZE {
+            return Err(format!("transaction exceeds {PACKET_DATA_SIZE} bytes"));
+        }
+        if rest.len() < 2 + length {
+            return Err("truncated transaction".to_string());
+        }
+        transactions.push(&rest[2..2 + length]);
+        rest = &rest[2 + length..];
+        if transactions.len() > MAX_TRANSACTIONS_IN_BATCH {
+            return Err(format!(
+                "more than {MAX_TRANSACTIONS_IN_BATCH} transactions in batch"
+            ));
+        }
+    }
+    Ok(transactions)
+}
+
+fn node_starting_response(reason: String) -> Response<Body> {
+    text_response(
+        StatusCode::SERVICE_UNAVAILABLE,
+        format!("node is starting: {reason}"),
+    )
+}
@@ -0,0 +1,84 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+};
+
+use hyper::{header::HeaderValue, Body, Request, Response};
+use solana_lite_rpc_core::{request_id::with_request_id, time_utils::unix_timestamp_ms};
+use tower::{Layer, Service};
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+// longer ids sent by clients are replaced by a generated one
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+lazy_static::lazy_static! {
+    // makes generated ids unique across restarts
+    static ref INSTANCE_PREFIX: String = format!("{:x}", unix_timestamp_ms());
+}
+static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// tower layer assigning every http request an id (taken from `X-Request-Id` if valid); the id is attached
+/// to the tracing span of the request, to transactions submitted by it and returned as `X-Request-Id`
+#[derive(Clone, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for RequestIdService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let request_id = request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LEN)
+            .map(str::to_string)
+            .unwrap_or_else(generate_request_id);
+
+        let span = tracing::info_span!("rpc_request", request_id = %request_id);
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = with_request_id(request_id.clone(), response)
+                .instrument(span)
+                .await?;
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                response.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            Ok(response)
+        })
+    }
+}
+
+fn generate_request_id() -> String {
+    format!(
+        "{}-{:x}",
+        *INSTANCE_PREFIX,
+        REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
@@ -0,0 +1,132 @@
+use std::sync::{Arc, RwLock};
+
+use prometheus::{opts, register_int_counter_vec, IntCounterVec};
+use serde::Serialize;
+use serde_json::value::RawValue;
+use solana_lite_rpc_core::{
+    stores::{
+        blockhash_cache::BlockhashCache, data_cache::DataCache,
+        signature_status_cache::SignatureStatusCache, tx_store::TxStore,
+    },
+    types::LatestBlock,
+};
+use solana_rpc_client_api::response::{Response as RpcResponse, RpcBlockhash, RpcResponseContext};
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature, slot_history::Slot};
+
+lazy_static::lazy_static! {
+    static ref RESPONSE_CACHE_REFRESHES: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_response_cache_refreshes", "Pre-serialized JSON responses rebuilt because the underlying state changed"), &["response"]).unwrap();
+}
+
+/// responses which only depend on the latest block of a commitment
+struct LatestBlockResponses {
+    slot: Slot,
+    get_slot: Box<RawValue>,
+    get_latest_blockhash: Box<RawValue>,
+}
+
+/// Pre-serialized JSON fragments of the hot read methods. The fragments are rebuilt by the first
+/// request after the underlying state changed, every other request only copies the bytes.
+pub struct ResponseCache {
+    txs: TxStore,
+    blockhash_cache: BlockhashCache,
+    // processed is served as confirmed like the block information store does
+    confirmed_block: LatestBlock,
+    finalized_block: LatestBlock,
+    confirmed: RwLock<Option<Arc<LatestBlockResponses>>>,
+    finalized: RwLock<Option<Arc<LatestBlockResponses>>>,
+    // pruned with the tx store by the cleaning task of the data cache
+    statuses: SignatureStatusCache,
+}
+
+impl ResponseCache {
+    pub fn new(data_cache: &DataCache) -> Self {
+        let block_information_store = &data_cache.block_information_store;
+        Self {
+            txs: data_cache.txs.clone(),
+            blockhash_cache: data_cache.blockhash_cache.clone(),
+            confirmed_block: block_information_store
+                .subscribe_latest_block(CommitmentConfig::confirmed()),
+            finalized_block: block_information_store
+                .subscribe_latest_block(CommitmentConfig::finalized()),
+            confirmed: RwLock::new(None),
+            finalized: RwLock::new(None),
+            statuses: data_cache.signature_statuses.clone(),
+        }
+    }
+
+    /// result of getSlot
+    pub fn get_slot(&self, commitment_config: CommitmentConfig) -> Box<RawValue> {
+        self.latest_block_responses(commitment_config)
+            .get_slot
+            .clone()
+    }
+
+    /// result of getLatestBlockhash
+    pub fn get_latest_blockhash(&self, commitment_config: CommitmentConfig) -> Box<RawValue> {
+        self.latest_block_responses(commitment_config)
+            .get_latest_blockhash
+            .clone()
+    }
+
+    /// latest finalized slot, the context of getSignatureStatuses
+    pub fn finalized_slot(&self) -> Slot {
+        self.latest_block_responses(CommitmentConfig::finalized())
+            .slot
+    }
+
+    /// status of getSignatureStatuses, none for unknown signatures
+    pub fn get_signature_status(&self, signature: &Signature) -> Option<Box<RawValue>> {
+        let status = self.txs.get(signature)?.status?;
+        Some(self.statuses.get_or_serialize(signature, status, |status| {
+            RESPONSE_CACHE_REFRESHES
+                .with_label_values(&["signature_status"])
+                .inc();
+            to_raw_value(status)
+        }))
+    }
+
+    fn latest_block_responses(
+        &self,
+        commitment_config: CommitmentConfig,
+    ) -> Arc<LatestBlockResponses> {
+        let (latest_block, cached) = if commitment_config.is_finalized() {
+            (&self.finalized_block, &self.finalized)
+        } else {
+            (&self.confirmed_block, &self.confirmed)
+        };
+        let latest_block = latest_block.borrow().clone();
+        if let Some(responses) = cached.read().unwrap().as_ref() {
+            if responses.slot >= latest_block.slot {
+                return responses.clone();
+            }
+        }
+
+        RESPONSE_CACHE_REFRESHES
+            .with_label_values(&["latest_block"])
+            .inc();
+        let responses = Arc::new(LatestBlockResponses {
+            slot: latest_block.slot,
+            get_slot: to_raw_value(&latest_block.slot),
+            get_latest_blockhash: to_raw_value(&RpcResponse {
+                context: RpcResponseContext {
+                    slot: latest_block.slot,
+                    api_version: None,
+                },
+                value: RpcBlockhash {
+                    blockhash: latest_block.blockhash.to_string(),
+                    last_valid_block_height: self.blockhash_cache.last_valid_block_height(
+                        &latest_block.blockhash,
+                        latest_block.block_height,
+                    ),
+                },
+            }),
+        });
+        *cached.write().unwrap() = Some(responses.clone());
+        responses
+    }
+}
+
+fn to_raw_value<T: Serialize>(value: &T) -> Box<RawValue> {
+    serde_json::value::to_raw_value(value).expect("rpc response serializes")
+}
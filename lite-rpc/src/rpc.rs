@@ -1,24 +1,45 @@
+#[cfg(feature = "priofees")]
+use crate::configs::PrioFeeForMessageConfig;
+#[cfg(feature = "accounts")]
+use crate::configs::StakeAccountsConfig;
 use crate::configs::{IsBlockHashValidConfig, SendTransactionConfig};
 use jsonrpsee::core::RpcResult;
 use jsonrpsee::proc_macros::rpc;
+use serde_json::value::RawValue;
+#[cfg(feature = "accounts")]
 use solana_account_decoder::UiAccount;
+use solana_lite_rpc_core::stores::tx_lifecycle_store::TxLifecycle;
+use solana_lite_rpc_core::structures::clock_update::ClockUpdate;
+#[cfg(feature = "priofees")]
 use solana_lite_rpc_prioritization_fees::prioritization_fee_calculation_method::PrioritizationFeeCalculationMethod;
-use solana_lite_rpc_prioritization_fees::rpc_data::{AccountPrioFeesStats, PrioFeesStats};
+#[cfg(feature = "priofees")]
+use solana_lite_rpc_prioritization_fees::rpc_data::{
+    AccountContention, AccountPrioFeesStats, BlockUtilizationStats, MessageFeeRecommendation,
+    PrioFeesPercentiles, PrioFeesStats, ProgramPrioFeesStats,
+};
+use solana_lite_rpc_services::tpu_utils::tpu_service::UpcomingLeader;
+#[cfg(feature = "accounts")]
+use solana_rpc_client_api::config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
 use solana_rpc_client_api::config::{
-    RpcAccountInfoConfig, RpcBlocksConfigWrapper, RpcContextConfig, RpcGetVoteAccountsConfig,
-    RpcLeaderScheduleConfig, RpcProgramAccountsConfig, RpcRequestAirdropConfig,
-    RpcSignatureStatusConfig, RpcSignaturesForAddressConfig,
+    RpcBlockConfig, RpcBlocksConfigWrapper, RpcContextConfig, RpcEncodingConfigWrapper,
+    RpcEpochConfig, RpcGetVoteAccountsConfig, RpcLargestAccountsConfig, RpcLeaderScheduleConfig,
+    RpcRequestAirdropConfig, RpcSignatureStatusConfig, RpcSignaturesForAddressConfig,
+    RpcSupplyConfig, RpcTransactionConfig,
 };
+#[cfg(feature = "priofees")]
+use solana_rpc_client_api::response::RpcPrioritizationFee;
+#[cfg(feature = "accounts")]
+use solana_rpc_client_api::response::{OptionalContext, RpcKeyedAccount};
 use solana_rpc_client_api::response::{
-    OptionalContext, Response as RpcResponse, RpcBlockhash,
-    RpcConfirmedTransactionStatusWithSignature, RpcContactInfo, RpcKeyedAccount, RpcPerfSample,
-    RpcPrioritizationFee, RpcVersionInfo, RpcVoteAccountStatus,
+    Response as RpcResponse, RpcConfirmedTransactionStatusWithSignature, RpcContactInfo,
+    RpcInflationRate, RpcInflationReward, RpcPerfSample, RpcTokenAccountBalance, RpcVersionInfo,
+    RpcVoteAccountStatus,
 };
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::epoch_info::EpochInfo;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::slot_history::Slot;
-use solana_transaction_status::{TransactionStatus, UiConfirmedBlock};
+use solana_transaction_status::UiConfirmedBlock;
 use std::collections::HashMap;
 
 #[rpc(server)]
@@ -28,7 +49,11 @@ pub trait LiteRpc {
     // ***********************
 
     #[method(name = "getBlock")]
-    async fn get_block(&self, slot: u64) -> RpcResult<Option<UiConfirmedBlock>>;
+    async fn get_block(
+        &self,
+        slot: u64,
+        config: Option<RpcEncodingConfigWrapper<RpcBlockConfig>>,
+    ) -> RpcResult<Option<UiConfirmedBlock>>;
 
     #[method(name = "getBlocks")]
     async fn get_blocks(
@@ -45,14 +70,14 @@ pub trait LiteRpc {
         config: Option<RpcSignaturesForAddressConfig>,
     ) -> RpcResult<Vec<RpcConfirmedTransactionStatusWithSignature>>;
 
-    // issue:  solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta does not implement Clone
-    //
-    //#[method(name = "getTransaction")]
-    //async fn get_transaction(
-    //    &self,
-    //    signature_str: String,
-    //    config: Option<RpcEncodingConfigWrapper<RpcTransactionConfig>>,
-    //) -> Result<Option<EncodedConfirmedTransactionWithStatusMeta>>;
+    // solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta does not implement Clone,
+    // so the transaction is returned as json value
+    #[method(name = "getTransaction")]
+    async fn get_transaction(
+        &self,
+        signature_str: String,
+        config: Option<RpcEncodingConfigWrapper<RpcTransactionConfig>>,
+    ) -> RpcResult<Option<serde_json::Value>>;
 
     // ***********************
     // Cluster Domain
@@ -65,8 +90,9 @@ pub trait LiteRpc {
     // Validator Domain
     // ***********************
 
+    // Slot, pre-serialized
     #[method(name = "getSlot")]
-    async fn get_slot(&self, config: Option<RpcContextConfig>) -> RpcResult<Slot>;
+    async fn get_slot(&self, config: Option<RpcContextConfig>) -> RpcResult<Box<RawValue>>;
 
     #[method(name = "getBlockHeight")]
     async fn get_block_height(&self, config: Option<RpcContextConfig>) -> RpcResult<u64>;
@@ -77,11 +103,12 @@ pub trait LiteRpc {
     #[method(name = "getFirstAvailableBlock")]
     async fn get_first_available_block(&self) -> RpcResult<u64>;
 
+    // RpcResponse<RpcBlockhash>, pre-serialized
     #[method(name = "getLatestBlockhash")]
     async fn get_latest_blockhash(
         &self,
         config: Option<RpcContextConfig>,
-    ) -> RpcResult<RpcResponse<RpcBlockhash>>;
+    ) -> RpcResult<Box<RawValue>>;
 
     #[method(name = "isBlockhashValid")]
     async fn is_blockhash_valid(
@@ -117,13 +144,15 @@ pub trait LiteRpc {
         &self,
         signature_strs: Vec<String>,
         config: Option<RpcSignatureStatusConfig>,
-    ) -> RpcResult<RpcResponse<Vec<Option<TransactionStatus>>>>;
+    ) -> RpcResult<RpcResponse<Vec<Option<Box<RawValue>>>>>;
 
-    #[method(name = "getRecentPrioritizationFees")]
-    async fn get_recent_prioritization_fees(
+    // lite-rpc extension: the states a transaction sent through this node passed until it was
+    // finalized, expired or dropped, null for unknown signatures
+    #[method(name = "getTransactionLifecycle")]
+    async fn get_transaction_lifecycle(
         &self,
-        pubkey_strs: Vec<String>,
-    ) -> RpcResult<Vec<RpcPrioritizationFee>>;
+        signature_str: String,
+    ) -> RpcResult<Option<TxLifecycle>>;
 
     // ***********************
     // Send Transaction Domain
@@ -166,12 +195,81 @@ pub trait LiteRpc {
     #[method(name = "getSlotLeaders")]
     async fn get_slot_leaders(&self, start_slot: u64, limit: u64) -> RpcResult<Vec<Pubkey>>;
 
+    // lite-rpc extension: the leaders of the next slots with the tpu quic address and stake used to
+    // forward the transactions, for senders and dashboards reusing the leader resolution
+    #[method(name = "getUpcomingLeaders")]
+    async fn get_upcoming_leaders(&self, slots: Option<u64>) -> RpcResult<Vec<UpcomingLeader>>;
+
+    // lite-rpc extension: the decoded Clock sysvar of the latest processed slot
+    #[method(name = "getClock")]
+    async fn get_clock(&self) -> RpcResult<ClockUpdate>;
+
     #[method(name = "getVoteAccounts")]
     async fn get_vote_accounts(
         &self,
         config: Option<RpcGetVoteAccountsConfig>,
     ) -> RpcResult<RpcVoteAccountStatus>;
 
+    #[method(name = "getStakeMinimumDelegation")]
+    async fn get_stake_minimum_delegation(
+        &self,
+        config: Option<RpcContextConfig>,
+    ) -> RpcResult<RpcResponse<u64>>;
+
+    // RpcResponse<RpcSupply>, pre-serialized
+    #[method(name = "getSupply")]
+    async fn get_supply(&self, config: Option<RpcSupplyConfig>) -> RpcResult<Box<RawValue>>;
+
+    // RpcResponse<Vec<RpcAccountBalance>>, pre-serialized
+    #[method(name = "getLargestAccounts")]
+    async fn get_largest_accounts(
+        &self,
+        config: Option<RpcLargestAccountsConfig>,
+    ) -> RpcResult<Box<RawValue>>;
+
+    #[method(name = "getMaxRetransmitSlot")]
+    async fn get_max_retransmit_slot(&self) -> RpcResult<Slot>;
+
+    #[method(name = "getMaxShredInsertSlot")]
+    async fn get_max_shred_insert_slot(&self) -> RpcResult<Slot>;
+
+    // RpcSnapshotSlotInfo of the rpc node, pre-serialized
+    #[method(name = "getHighestSnapshotSlot")]
+    async fn get_highest_snapshot_slot(&self) -> RpcResult<Box<RawValue>>;
+
+    // Slot of the rpc node, pre-serialized
+    #[method(name = "minimumLedgerSlot", aliases = ["getMinimumLedgerSlot"])]
+    async fn minimum_ledger_slot(&self) -> RpcResult<Box<RawValue>>;
+
+    #[method(name = "getTokenLargestAccounts")]
+    async fn get_token_largest_accounts(
+        &self,
+        mint_str: String,
+        commitment: Option<CommitmentConfig>,
+    ) -> RpcResult<RpcResponse<Vec<RpcTokenAccountBalance>>>;
+
+    #[method(name = "getInflationRate")]
+    async fn get_inflation_rate(&self) -> RpcResult<RpcInflationRate>;
+
+    // staking and voting rewards of the addresses paid out at the end of the epoch
+    #[method(name = "getInflationReward")]
+    async fn get_inflation_reward(
+        &self,
+        address_strs: Vec<String>,
+        config: Option<RpcEpochConfig>,
+    ) -> RpcResult<Vec<Option<RpcInflationReward>>>;
+}
+
+/// methods of the prioritization fee statistics, only registered if compiled in
+#[cfg(feature = "priofees")]
+#[rpc(server)]
+pub trait LitePrioFeesRpc {
+    #[method(name = "getRecentPrioritizationFees")]
+    async fn get_recent_prioritization_fees(
+        &self,
+        pubkey_strs: Vec<String>,
+    ) -> RpcResult<Vec<RpcPrioritizationFee>>;
+
     // ***********************
     // expose prio fees distribution per block
     // (this is special method not available in solana rpc)
@@ -190,10 +288,57 @@ pub trait LiteRpc {
         method: Option<PrioritizationFeeCalculationMethod>,
     ) -> RpcResult<RpcResponse<AccountPrioFeesStats>>;
 
-    // **************************
-    // Accounts
-    // **************************
+    // stats of the transactions invoking the program in a top level instruction
+    #[method(name = "getProgramPrioFees")]
+    async fn get_program_priofees(
+        &self,
+        program_id: String,
+        method: Option<PrioritizationFeeCalculationMethod>,
+    ) -> RpcResult<RpcResponse<ProgramPrioFeesStats>>;
+
+    // p25/p50/p75/p90/p95 of the compute unit price over the last blocks, split by successful and failed transactions
+    #[method(name = "getPrioFeesPercentiles")]
+    async fn get_priofees_percentiles(
+        &self,
+        lookback_blocks: Option<usize>,
+    ) -> RpcResult<RpcResponse<PrioFeesPercentiles>>;
+
+    // same as getPrioFeesPercentiles but only for transactions write-locking the given account
+    #[method(name = "getWriteLockPrioFees")]
+    async fn get_write_lock_priofees(
+        &self,
+        account: String,
+        lookback_blocks: Option<usize>,
+    ) -> RpcResult<RpcResponse<PrioFeesPercentiles>>;
+
+    // compute units consumed by the last blocks relative to the block limit, vote and non-vote split
+    #[method(name = "getBlockUtilization")]
+    async fn get_block_utilization(
+        &self,
+        lookback_blocks: Option<usize>,
+    ) -> RpcResult<RpcResponse<BlockUtilizationStats>>;
+
+    // accounts write-locked by the most compute units in the last slots
+    #[method(name = "getHottestAccounts")]
+    async fn get_hottest_accounts(
+        &self,
+        lookback_slots: Option<u64>,
+        limit: Option<usize>,
+    ) -> RpcResult<RpcResponse<Vec<AccountContention>>>;
 
+    // compute unit price for a base64 encoded message based on the recent write-lock contention on its writable accounts
+    #[method(name = "getRecommendedPrioFeeForMessage")]
+    async fn get_recommended_priofee_for_message(
+        &self,
+        message: String,
+        config: Option<PrioFeeForMessageConfig>,
+    ) -> RpcResult<RpcResponse<MessageFeeRecommendation>>;
+}
+
+/// methods of the account store, only registered if account streaming is enabled
+#[cfg(feature = "accounts")]
+#[rpc(server)]
+pub trait LiteAccountsRpc {
     #[method(name = "getAccountInfo")]
     async fn get_account_info(
         &self,
@@ -214,4 +359,13 @@ pub trait LiteRpc {
         program_id_str: String,
         config: Option<RpcProgramAccountsConfig>,
     ) -> RpcResult<OptionalContext<Vec<RpcKeyedAccount>>>;
+
+    // lite-rpc extension: stake accounts of a staker or withdraw authority, only served if the
+    // stake program is in the account filters
+    #[method(name = "getStakeAccountsByAuthority")]
+    async fn get_stake_accounts_by_authority(
+        &self,
+        authority_str: String,
+        config: Option<StakeAccountsConfig>,
+    ) -> RpcResult<RpcResponse<Vec<RpcKeyedAccount>>>;
 }
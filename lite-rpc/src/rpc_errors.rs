@@ -3,4 +3,12 @@
 pub enum RpcErrors {
     // Account does not satisfy any account filters or account does not exists.
     AccountNotFound = 0,
+    // The block stream did not catch up since the start or the node drains, same code as a node
+    // behind in solana so clients retry.
+    NodeUnavailable = -32005,
+    // No block or block time of the slot in any history source, same code as solana-rpc.
+    BlockNotAvailable = -32004,
+    // A sent transaction with an invalid signature, same codes as solana-rpc.
+    TransactionSignatureVerificationFailure = -32003,
+    TransactionSignatureLenMismatch = -32013,
 }
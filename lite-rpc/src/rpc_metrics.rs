@@ -0,0 +1,141 @@
+use std::{collections::BTreeMap, net::SocketAddr, time::Instant};
+
+use jsonrpsee::{
+    core::server::helpers::MethodResponseResult,
+    server::logger::{HttpRequest, Logger, MethodKind, Params, TransportProtocol},
+    types::error::ErrorCode,
+};
+use prometheus::{
+    histogram_opts, opts, register_histogram_vec, register_int_counter_vec, register_int_gauge_vec,
+    HistogramVec, IntCounterVec, IntGaugeVec,
+};
+
+lazy_static::lazy_static! {
+    static ref RPC_METHOD_REQUESTS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_rpc_method_requests", "Number of JSON-RPC calls per method"), &["method", "transport"]).unwrap();
+    static ref RPC_METHOD_LATENCY: HistogramVec = register_histogram_vec!(
+        histogram_opts!(
+            "literpc_rpc_method_latency",
+            "Time in seconds from receiving a JSON-RPC request until the method completed",
+            vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]
+        ),
+        &["method", "transport"]
+    )
+    .unwrap();
+    static ref RPC_METHOD_ERRORS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_rpc_method_errors", "Number of JSON-RPC calls per method which failed, by error code"), &["method", "transport", "code"]).unwrap();
+    static ref RPC_ACTIVE_SUBSCRIPTIONS: IntGaugeVec =
+        register_int_gauge_vec!(opts!("literpc_rpc_active_subscriptions", "Open websocket subscriptions served by a task of their own, by kind"), &["kind"]).unwrap();
+}
+
+/// kinds of the websocket subscriptions served by a task of their own, signature subscriptions live in the subscription store
+pub const SUBSCRIPTION_KINDS: [&str; 9] = [
+    "slot",
+    "root",
+    "block_commitment",
+    "transaction",
+    "account",
+    "program",
+    "block_priofees",
+    "block_priofees_summary",
+    "account_priofees",
+];
+
+/// counts an open subscription while the task serving it holds it
+pub struct ActiveSubscription(&'static str);
+
+impl ActiveSubscription {
+    pub fn new(kind: &'static str) -> Self {
+        debug_assert!(SUBSCRIPTION_KINDS.contains(&kind));
+        RPC_ACTIVE_SUBSCRIPTIONS.with_label_values(&[kind]).inc();
+        Self(kind)
+    }
+}
+
+impl Drop for ActiveSubscription {
+    fn drop(&mut self) {
+        RPC_ACTIVE_SUBSCRIPTIONS.with_label_values(&[self.0]).dec();
+    }
+}
+
+/// open subscriptions per kind
+pub fn active_subscriptions() -> BTreeMap<&'static str, i64> {
+    SUBSCRIPTION_KINDS
+        .iter()
+        .map(|kind| {
+            (
+                *kind,
+                RPC_ACTIVE_SUBSCRIPTIONS.with_label_values(&[kind]).get(),
+            )
+        })
+        .collect()
+}
+
+/// jsonrpsee logger exporting per-method request counts, latencies and error codes for the http and ws servers
+#[derive(Clone, Default)]
+pub struct RpcMetricsLogger;
+
+impl Logger for RpcMetricsLogger {
+    type Instant = Instant;
+
+    fn on_connect(&self, _remote_addr: SocketAddr, _request: &HttpRequest, _t: TransportProtocol) {}
+
+    fn on_request(&self, _transport: TransportProtocol) -> Self::Instant {
+        Instant::now()
+    }
+
+    fn on_call(
+        &self,
+        method_name: &str,
+        _params: Params,
+        kind: MethodKind,
+        transport: TransportProtocol,
+    ) {
+        // unknown methods would allow clients to create arbitrary label values
+        if matches!(kind, MethodKind::Unknown) {
+            return;
+        }
+        RPC_METHOD_REQUESTS
+            .with_label_values(&[method_name, transport_label(transport)])
+            .inc();
+    }
+
+    fn on_result(
+        &self,
+        method_name: &str,
+        success_or_error: MethodResponseResult,
+        started_at: Self::Instant,
+        transport: TransportProtocol,
+    ) {
+        let error_code = success_or_error.as_error_code();
+        if error_code == Some(ErrorCode::MethodNotFound.code()) {
+            return;
+        }
+        let transport = transport_label(transport);
+        RPC_METHOD_LATENCY
+            .with_label_values(&[method_name, transport])
+            .observe(started_at.elapsed().as_secs_f64());
+        if let Some(code) = error_code {
+            RPC_METHOD_ERRORS
+                .with_label_values(&[method_name, transport, &code.to_string()])
+                .inc();
+        }
+    }
+
+    fn on_response(
+        &self,
+        _result: &str,
+        _started_at: Self::Instant,
+        _transport: TransportProtocol,
+    ) {
+    }
+
+    fn on_disconnect(&self, _remote_addr: SocketAddr, _transport: TransportProtocol) {}
+}
+
+fn transport_label(transport: TransportProtocol) -> &'static str {
+    match transport {
+        TransportProtocol::Http => "http",
+        TransportProtocol::WebSocket => "ws",
+    }
+}
@@ -1,11 +1,14 @@
 use jsonrpsee::core::SubscriptionResult;
 use jsonrpsee::proc_macros::rpc;
+#[cfg(feature = "accounts")]
+use solana_rpc_client_api::config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
 use solana_rpc_client_api::config::{
-    RpcAccountInfoConfig, RpcBlockSubscribeConfig, RpcBlockSubscribeFilter,
-    RpcProgramAccountsConfig, RpcSignatureSubscribeConfig, RpcTransactionLogsConfig,
-    RpcTransactionLogsFilter,
+    RpcBlockSubscribeConfig, RpcBlockSubscribeFilter, RpcSignatureSubscribeConfig,
+    RpcTransactionLogsConfig, RpcTransactionLogsFilter,
 };
 
+use crate::configs::{TransactionSubscribeConfig, TransactionSubscribeFilter};
+
 pub type Result<T> = std::result::Result<T, jsonrpsee::core::Error>;
 
 #[rpc(server)]
@@ -17,6 +20,14 @@ pub trait LiteRpcPubSub {
     #[subscription(name = "slotSubscribe" => "slotNotification", unsubscribe="slotUnsubscribe", item=Slot)]
     async fn slot_subscribe(&self) -> SubscriptionResult;
 
+    // the finalized slots, lite-rpc has no view of the roots of the rpc node
+    #[subscription(name = "rootSubscribe" => "rootNotification", unsubscribe="rootUnsubscribe", item=Slot)]
+    async fn root_subscribe(&self) -> SubscriptionResult;
+
+    // the slots reaching confirmed and finalized, flagged as conflict if the block changed
+    #[subscription(name = "blockCommitmentSubscribe" => "blockCommitmentNotification", unsubscribe="blockCommitmentUnsubscribe", item=BlockCommitmentNotification)]
+    async fn block_commitment_subscribe(&self) -> SubscriptionResult;
+
     #[subscription(name = "blockSubscribe" => "blockNotification", unsubscribe="blockUnsubscribe", item=RpcResponse<UiConfirmedBlock>)]
     async fn block_subscribe(
         &self,
@@ -24,13 +35,15 @@ pub trait LiteRpcPubSub {
         config: Option<RpcBlockSubscribeConfig>,
     ) -> SubscriptionResult;
 
+    // the transactions of the blocks touching the accounts of the filter, like
     // [transactionSubscribe](https://github.com/solana-foundation/solana-improvement-documents/pull/69)
-    //
-    //#[subscription(name = "transactionSubscribe" => "transactionNotification", unsubscribe="transactionUnsubscribe", item=RpcResponse<RpcConfirmedTransactionStatusWithSignature>)]
-    //async fn transaction_subscribe(
-    //    &self,
-    //    commitment_config: CommitmentConfig,
-    //) -> SubscriptionResult;
+    // but with the transaction records of the sinks instead of the full transactions
+    #[subscription(name = "transactionSubscribe" => "transactionNotification", unsubscribe="transactionUnsubscribe", item=Transaction)]
+    async fn transaction_subscribe(
+        &self,
+        filter: TransactionSubscribeFilter,
+        config: Option<TransactionSubscribeConfig>,
+    ) -> SubscriptionResult;
 
     // ***********************
     // Indirect Subscription Domain
@@ -56,14 +69,28 @@ pub trait LiteRpcPubSub {
 
     #[subscription(name = "voteSubscribe" => "voteNotification", unsubscribe="voteUnsubscribe", item=RpcVote)]
     async fn vote_subscribe(&self) -> SubscriptionResult;
+}
 
+/// subscriptions to the prioritization fee statistics, only registered if compiled in
+#[cfg(feature = "priofees")]
+#[rpc(server)]
+pub trait LitePrioFeesPubSub {
     /// subscribe to prio fees distribution per block; uses confirmation level "confirmed"
     #[subscription(name = "blockPrioritizationFeesSubscribe" => "blockPrioritizationFeesNotification", unsubscribe="blockPrioritizationFeesUnsubscribe", item=PrioFeesStats)]
     async fn latest_block_priofees_subscribe(&self) -> SubscriptionResult;
 
+    /// subscribe to a compact prio fees summary (fee percentiles, tx and cu totals) per processed block
+    #[subscription(name = "blockPrioritizationFeesSummarySubscribe" => "blockPrioritizationFeesSummaryNotification", unsubscribe="blockPrioritizationFeesSummaryUnsubscribe", item=PrioFeesSummary)]
+    async fn block_priofees_summary_subscribe(&self) -> SubscriptionResult;
+
     #[subscription(name = "accountPrioritizationFeesSubscribe" => "accountPrioritizationFeesNotification", unsubscribe="accountPrioritizationFeesUnsubscribe", item=AccountPrioFeesStats)]
     async fn latest_account_priofees_subscribe(&self, account: String) -> SubscriptionResult;
+}
 
+/// subscriptions to the account store, only registered if account streaming is enabled
+#[cfg(feature = "accounts")]
+#[rpc(server)]
+pub trait LiteAccountsPubSub {
     #[subscription(name = "accountSubscribe" => "accountNotification", unsubscribe="accountUnsubscribe", item=RpcResponse<UiAccount>)]
     async fn account_subscribe(
         &self,
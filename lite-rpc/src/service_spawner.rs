@@ -6,6 +6,8 @@ use solana_lite_rpc_core::{
 };
 use solana_lite_rpc_services::{
     data_caching_service::DataCachingService,
+    dry_run::DryRun,
+    fee_guardrails::FeeGuardrails,
     metrics_capture::MetricsCapture,
     prometheus_sync::PrometheusSync,
     tpu_utils::tpu_service::TpuService,
@@ -14,6 +16,9 @@ use solana_lite_rpc_services::{
     tx_sender::TxSender,
 };
 use std::time::Duration;
+
+use crate::DEFAULT_TX_STATUS_RETENTION_SECS;
+
 pub struct ServiceSpawner {
     pub prometheus_addr: String,
     pub data_cache: DataCache,
@@ -48,6 +53,8 @@ impl ServiceSpawner {
         let data_service = DataCachingService {
             data_cache: self.data_cache.clone(),
             clean_duration: Duration::from_secs(120),
+            tx_status_retention: Duration::from_secs(DEFAULT_TX_STATUS_RETENTION_SECS),
+            optimistically_confirmed_blocks: None,
         };
 
         data_service.listen(
@@ -67,19 +74,24 @@ impl ServiceSpawner {
         max_nb_txs_in_queue: usize,
         notifier: Option<NotificationSender>,
         max_retries: usize,
-        slot_notifications: SlotStream,
+        verify_signatures: bool,
+        fee_guardrails: FeeGuardrails,
+        dry_run: DryRun,
     ) -> (TransactionService, AnyhowJoinHandle) {
         let service_builder = TransactionServiceBuilder::new(
             tx_sender,
             tx_replayer,
             tpu_service,
             max_nb_txs_in_queue,
-        );
+        )
+        .with_signature_verification(verify_signatures)
+        .with_fee_guardrails(fee_guardrails)
+        .with_dry_run(dry_run);
         service_builder.start(
             notifier,
-            self.data_cache.block_information_store.clone(),
+            self.data_cache.blockhash_cache.clone(),
             max_retries,
-            slot_notifications,
+            self.data_cache.slot_cache.subscribe(),
         )
     }
 }
@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+use prost::Message;
+use solana_lite_rpc_core::stores::tx_store::TxStore;
+use solana_lite_rpc_core::structures::{
+    produced_block::{ProducedBlock, TransactionInfo},
+    slot_notification::SlotNotification,
+};
+
+pub mod proto {
+    tonic::include_proto!("lite_rpc.sink");
+}
+
+/// encoding of the records published to external sinks
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkFormat {
+    #[default]
+    Json,
+    Protobuf,
+}
+
+impl FromStr for SinkFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> anyhow::Result<Self> {
+        match format {
+            "json" => Ok(Self::Json),
+            "protobuf" => Ok(Self::Protobuf),
+            _ => anyhow::bail!("Unknown sink format {format}, expected json or protobuf"),
+        }
+    }
+}
+
+impl SinkFormat {
+    pub fn encode<R: Message + serde::Serialize>(&self, record: &R) -> Vec<u8> {
+        match self {
+            Self::Json => serde_json::to_vec(record).expect("sink records serialize to json"),
+            Self::Protobuf => record.encode_to_vec(),
+        }
+    }
+}
+
+pub fn block_record(block: &ProducedBlock) -> proto::Block {
+    proto::Block {
+        slot: block.slot,
+        parent_slot: block.parent_slot,
+        block_height: block.block_height,
+        blockhash: block.blockhash.to_string(),
+        previous_blockhash: block.previous_blockhash.to_string(),
+        leader_id: block.leader_id.map(|leader_id| leader_id.to_string()),
+        block_time: block.block_time,
+        commitment: block.commitment_config.commitment.to_string(),
+        transaction_count: block.transactions.len() as u32,
+    }
+}
+
+/// one record per transaction of the block, vote transactions only if `include_votes` is set
+pub fn transaction_records(
+    block: &ProducedBlock,
+    include_votes: bool,
+) -> impl Iterator<Item = proto::Transaction> + '_ {
+    block
+        .transactions
+        .iter()
+        .filter(move |tx| include_votes || !tx.is_vote)
+        .map(move |tx| transaction_record(block, tx))
+}
+
+pub fn transaction_record(block: &ProducedBlock, tx: &TransactionInfo) -> proto::Transaction {
+    proto::Transaction {
+        signature: tx.signature.to_string(),
+        slot: block.slot,
+        commitment: block.commitment_config.commitment.to_string(),
+        is_vote: tx.is_vote,
+        err: tx.err.as_ref().map(|err| err.to_string()),
+        cu_requested: tx.cu_requested,
+        cu_consumed: tx.cu_consumed,
+        prioritization_fees: tx.prioritization_fees,
+        recent_blockhash: tx.recent_blockhash.to_string(),
+        writable_accounts: tx.writable_accounts.iter().map(|a| a.to_string()).collect(),
+        readable_accounts: tx.readable_accounts.iter().map(|a| a.to_string()).collect(),
+        program_ids: tx.program_ids.iter().map(|p| p.to_string()).collect(),
+    }
+}
+
+pub fn slot_record(slot_notification: &SlotNotification) -> proto::Slot {
+    proto::Slot {
+        processed_slot: slot_notification.processed_slot,
+        estimated_processed_slot: slot_notification.estimated_processed_slot,
+    }
+}
+
+/// statuses of the transactions of a confirmed or finalized block which were sent through lite-rpc
+pub fn transaction_status_records<'a>(
+    block: &'a ProducedBlock,
+    txs: &'a TxStore,
+) -> impl Iterator<Item = proto::TransactionStatus> + 'a {
+    let commitment = block.commitment_config.commitment.to_string();
+    let transactions = if block.commitment_config.is_processed() {
+        &[][..]
+    } else {
+        &block.transactions[..]
+    };
+    transactions
+        .iter()
+        .filter(move |tx| txs.contains_key(&tx.signature))
+        .map(move |tx| proto::TransactionStatus {
+            signature: tx.signature.to_string(),
+            slot: block.slot,
+            commitment: commitment.clone(),
+            err: tx.err.as_ref().map(|err| err.to_string()),
+        })
+}
@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use log::{info, warn};
+use prometheus::{
+    opts, register_int_counter_vec, register_int_gauge_vec, IntCounterVec, IntGaugeVec,
+};
+use serde::Serialize;
+use solana_lite_rpc_core::{stores::data_cache::DataCache, AnyhowJoinHandle};
+use solana_rpc_client_api::client_error::reqwest::{self, Url};
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig};
+use tokio::time::Instant;
+
+lazy_static::lazy_static! {
+    static ref SLOT_LAG: IntGaugeVec =
+        register_int_gauge_vec!(opts!("literpc_slot_lag", "Slots the latest block lags behind the estimated cluster slot"), &["commitment"]).unwrap();
+    static ref SLOT_LAG_ALERT: IntGaugeVec =
+        register_int_gauge_vec!(opts!("literpc_slot_lag_alert", "1 while the slot lag alert is firing"), &["commitment"]).unwrap();
+    static ref SLOT_LAG_ALERTS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_slot_lag_alerts", "Number of slot lag alerts fired"), &["commitment"]).unwrap();
+}
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct SlotLagThresholds {
+    pub processed: Slot,
+    pub confirmed: Slot,
+    pub finalized: Slot,
+    /// the lag must exceed the threshold for this long before the alert fires
+    pub duration: Duration,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotLagAlert {
+    pub commitment: String,
+    pub firing: bool,
+    pub slot_lag: Slot,
+    pub threshold: Slot,
+    pub block_slot: Slot,
+    pub estimated_slot: Slot,
+}
+
+struct CommitmentWatch {
+    commitment_config: CommitmentConfig,
+    threshold: Slot,
+    exceeded_since: Option<Instant>,
+    firing: bool,
+}
+
+/// compares the latest block per commitment with the estimated cluster slot, logs, exports and
+/// optionally POSTs an alert to `webhook_url` when the lag stays above the threshold
+pub struct SlotLagWatchdog {
+    data_cache: DataCache,
+    thresholds: SlotLagThresholds,
+    webhook_url: Option<Url>,
+    client: reqwest::Client,
+}
+
+impl SlotLagWatchdog {
+    pub fn new(
+        data_cache: DataCache,
+        thresholds: SlotLagThresholds,
+        webhook_url: Option<Url>,
+    ) -> Self {
+        Self {
+            data_cache,
+            thresholds,
+            webhook_url,
+            client: reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()
+                .expect("slot lag webhook http client"),
+        }
+    }
+
+    pub fn start(self) -> AnyhowJoinHandle {
+        tokio::spawn(async move {
+            let mut watches = [
+                (CommitmentConfig::processed(), self.thresholds.processed),
+                (CommitmentConfig::confirmed(), self.thresholds.confirmed),
+                (CommitmentConfig::finalized(), self.thresholds.finalized),
+            ]
+            .map(|(commitment_config, threshold)| CommitmentWatch {
+                commitment_config,
+                threshold,
+                exceeded_since: None,
+                firing: false,
+            });
+
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let estimated_slot = self.data_cache.slot_cache.get_estimated_slot();
+                for watch in watches.iter_mut() {
+                    let block_slot = self
+                        .data_cache
+                        .block_information_store
+                        .get_latest_block(watch.commitment_config)
+                        .await
+                        .slot;
+                    let slot_lag = estimated_slot.saturating_sub(block_slot);
+                    let commitment = watch.commitment_config.commitment.to_string();
+                    SLOT_LAG
+                        .with_label_values(&[&commitment])
+                        .set(slot_lag as i64);
+
+                    let firing = if slot_lag > watch.threshold {
+                        let exceeded_since = *watch.exceeded_since.get_or_insert_with(Instant::now);
+                        exceeded_since.elapsed() >= self.thresholds.duration
+                    } else {
+                        watch.exceeded_since = None;
+                        false
+                    };
+                    if firing == watch.firing {
+                        continue;
+                    }
+                    watch.firing = firing;
+
+                    let alert = SlotLagAlert {
+                        commitment,
+                        firing,
+                        slot_lag,
+                        threshold: watch.threshold,
+                        block_slot,
+                        estimated_slot,
+                    };
+                    SLOT_LAG_ALERT
+                        .with_label_values(&[&alert.commitment])
+                        .set(firing as i64);
+                    if firing {
+                        SLOT_LAG_ALERTS
+                            .with_label_values(&[&alert.commitment])
+                            .inc();
+                        warn!(
+                            "{} block {} lags {} slots behind the cluster (threshold {}) for {:?}",
+                            alert.commitment,
+                            block_slot,
+                            slot_lag,
+                            watch.threshold,
+                            self.thresholds.duration
+                        );
+                    } else {
+                        info!(
+                            "{} slot lag recovered to {} slots",
+                            alert.commitment, slot_lag
+                        );
+                    }
+                    self.notify(alert);
+                }
+            }
+        })
+    }
+
+    fn notify(&self, alert: SlotLagAlert) {
+        let Some(webhook_url) = self.webhook_url.clone() else {
+            return;
+        };
+        let client = self.client.clone();
+        // do not block the watchdog on a slow webhook
+        tokio::spawn(async move {
+            let body = serde_json::to_vec(&alert).expect("slot lag alert serializable");
+            let result = client
+                .post(webhook_url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(err) = result {
+                warn!("slot lag webhook failed: {err}");
+            }
+        });
+    }
+}
@@ -1,64 +1,483 @@
+#[cfg(feature = "graphql")]
+use crate::graphql::{GraphQlLayer, GraphQlSchema};
+#[cfg(all(feature = "accounts", feature = "ws-server"))]
+use crate::rpc_pubsub::LiteAccountsPubSubServer;
+#[cfg(all(feature = "priofees", feature = "ws-server"))]
+use crate::rpc_pubsub::LitePrioFeesPubSubServer;
 use crate::{
-    bridge::LiteBridge, bridge_pubsub::LitePubSubBridge, rpc::LiteRpcServer,
-    rpc_pubsub::LiteRpcPubSubServer,
+    api_key_auth::{ApiKeyAuthLayer, ApiKeys},
+    bridge::LiteBridge,
+    compression::ResponseCompressionLayer,
+    health_endpoint::{HealthLayer, HealthState},
+    http_limits::{ConnectionLimiter, HttpLimitLayer, HttpLimits},
+    openrpc::register_discover,
+    rate_limit::{RateLimitLayer, RateLimiter, RemoteAddr},
+    raw_transaction_endpoint::RawTransactionLayer,
+    request_id::RequestIdLayer,
+    rpc::LiteRpcServer,
+    rpc_metrics::RpcMetricsLogger,
+    tls::{serve_tls, CertificateReloader},
+    upstream_proxy::{UpstreamProxyConfig, UpstreamProxyLayer},
+    SHUTDOWN_PHASE_TIMEOUT,
 };
+#[cfg(feature = "arrow-export")]
+use crate::{arrow_export::ArrowExportLayer, block_range::BlockRangeReader};
+#[cfg(feature = "accounts")]
+use crate::{bridge_accounts::LiteAccountsBridge, rpc::LiteAccountsRpcServer};
+#[cfg(feature = "priofees")]
+use crate::{bridge_priofees::LitePrioFeesBridge, rpc::LitePrioFeesRpcServer};
+#[cfg(feature = "ws-server")]
+use crate::{bridge_pubsub::LitePubSubBridge, rpc_pubsub::LiteRpcPubSubServer};
+#[cfg(not(all(feature = "graphql", feature = "arrow-export")))]
+use tower::layer::util::Identity;
 
-use hyper::Method;
-use jsonrpsee::server::ServerBuilder;
+use hyper::{server::conn::Http, service::Service, Body, Request, Response};
+use jsonrpsee::{
+    server::{stop_channel, ServerBuilder, StopHandle},
+    Methods,
+};
 use solana_lite_rpc_core::AnyhowJoinHandle;
-use std::time::Duration;
-use tower_http::cors::{Any, CorsLayer};
+use solana_lite_rpc_services::transaction_service::TransactionService;
+use std::{future::Future, net::SocketAddr};
+use tokio::{
+    net::{TcpListener, TcpSocket},
+    sync::mpsc,
+    task::JoinSet,
+};
+use tokio_rustls::TlsAcceptor;
+use tokio_util::sync::CancellationToken;
+use tower_http::{add_extension::AddExtensionLayer, cors::CorsLayer};
+
+// pending connections per listener, the default of the tokio listeners
+const LISTEN_BACKLOG: u32 = 1024;
+
+/// json rpc methods and websocket subscriptions served by [`start_servers`], the methods of
+/// subsystems which are compiled out or not started are left to the upstream proxy
+pub struct RpcMethods {
+    rpc: Methods,
+    pubsub: Methods,
+}
+
+impl RpcMethods {
+    pub fn new(rpc: LiteBridge) -> Self {
+        Self {
+            rpc: rpc.into_rpc().into(),
+            pubsub: Methods::new(),
+        }
+    }
+
+    #[cfg(feature = "ws-server")]
+    pub fn with_pubsub(mut self, pubsub: LitePubSubBridge) -> anyhow::Result<Self> {
+        self.pubsub.merge(pubsub.into_rpc())?;
+        Ok(self)
+    }
+
+    #[cfg(feature = "priofees")]
+    pub fn with_priofees(mut self, priofees: LitePrioFeesBridge) -> anyhow::Result<Self> {
+        #[cfg(feature = "ws-server")]
+        self.pubsub
+            .merge(LitePrioFeesPubSubServer::into_rpc(priofees.clone()))?;
+        self.rpc.merge(LitePrioFeesRpcServer::into_rpc(priofees))?;
+        Ok(self)
+    }
+
+    #[cfg(feature = "accounts")]
+    pub fn with_accounts(mut self, accounts: LiteAccountsBridge) -> anyhow::Result<Self> {
+        #[cfg(feature = "ws-server")]
+        self.pubsub
+            .merge(LiteAccountsPubSubServer::into_rpc(accounts.clone()))?;
+        self.rpc.merge(LiteAccountsRpcServer::into_rpc(accounts))?;
+        Ok(self)
+    }
+}
+
+/// graphql and arrow export endpoints on the http server, only served if their feature is compiled in
+#[derive(Default)]
+pub struct DataApis {
+    #[cfg(feature = "graphql")]
+    graphql: Option<GraphQlSchema>,
+    #[cfg(feature = "arrow-export")]
+    arrow_export: Option<BlockRangeReader>,
+}
+
+impl DataApis {
+    #[cfg(feature = "graphql")]
+    pub fn with_graphql(mut self, graphql: GraphQlSchema) -> Self {
+        self.graphql = Some(graphql);
+        self
+    }
+
+    #[cfg(feature = "arrow-export")]
+    pub fn with_arrow_export(mut self, block_range_reader: BlockRangeReader) -> Self {
+        self.arrow_export = Some(block_range_reader);
+        self
+    }
+}
 
 pub async fn start_servers(
-    rpc: LiteBridge,
-    pubsub: LitePubSubBridge,
+    methods: RpcMethods,
     ws_addr: String,
     http_addr: String,
+    workers: usize,
+    transaction_service: TransactionService,
+    health_state: HealthState,
+    tls: Option<CertificateReloader>,
+    api_keys: Option<ApiKeys>,
+    cors: CorsLayer,
+    rate_limiter: Option<RateLimiter>,
+    upstream_proxy: Option<UpstreamProxyConfig>,
+    data_apis: DataApis,
+    http_limits: HttpLimits,
+    enable_compression: bool,
+    shutdown: CancellationToken,
 ) -> anyhow::Result<()> {
-    let rpc = rpc.into_rpc();
-    let pubsub = pubsub.into_rpc();
+    let RpcMethods {
+        mut rpc,
+        mut pubsub,
+    } = methods;
+    // both endpoints describe the methods they serve
+    register_discover(&mut rpc)?;
+    register_discover(&mut pubsub)?;
+    let upstream_proxy = UpstreamProxyLayer::new(upstream_proxy, rpc.method_names());
+    let DataApis {
+        #[cfg(feature = "graphql")]
+        graphql,
+        #[cfg(feature = "arrow-export")]
+        arrow_export,
+    } = data_apis;
+    #[cfg(feature = "graphql")]
+    let graphql = GraphQlLayer::new(graphql);
+    #[cfg(not(feature = "graphql"))]
+    let graphql = Identity::new();
+    #[cfg(feature = "arrow-export")]
+    let arrow_export = ArrowExportLayer::new(arrow_export);
+    #[cfg(not(feature = "arrow-export"))]
+    let arrow_export = Identity::new();
 
-    let ws_server_handle = ServerBuilder::default()
-        .ws_only()
-        .build(ws_addr.clone())
-        .await?
-        .start(pubsub);
-
-    let cors = CorsLayer::new()
-        .max_age(Duration::from_secs(86400))
-        // Allow `POST` when accessing the resource
-        .allow_methods([Method::POST, Method::GET, Method::OPTIONS])
-        // Allow requests from any origin
-        .allow_origin(Any)
-        .allow_headers(Any);
-
-    let middleware = tower::ServiceBuilder::new().layer(cors);
-
-    let http_server_handle = ServerBuilder::default()
-        .set_middleware(middleware)
-        .http_only()
-        .build(http_addr.clone())
-        .await?
-        .start(rpc);
-
-    let ws_server: AnyhowJoinHandle = tokio::spawn(async move {
-        log::info!("Websocket Server started at {ws_addr:?}");
-        ws_server_handle.stopped().await;
-        anyhow::bail!("Websocket server stopped");
-    });
+    let middleware = tower::ServiceBuilder::new()
+        .layer(ResponseCompressionLayer::new(enable_compression))
+        .layer(cors)
+        .layer(HttpLimitLayer::new(&http_limits))
+        .layer(RequestIdLayer)
+        .layer(HealthLayer::new(health_state.clone()))
+        // health checks do not need an api key
+        .layer(ApiKeyAuthLayer::new(api_keys.clone()))
+        .layer(RateLimitLayer::new(rate_limiter.clone()))
+        .layer(graphql)
+        .layer(arrow_export)
+        .layer(RawTransactionLayer::new(transaction_service, health_state))
+        .layer(upstream_proxy);
+
+    // jsonrpsee services are served on our own listeners to know the remote address of every connection,
+    // the services run until the handle is stopped or dropped
+    let (stop_handle, server_handle) = stop_channel();
+    let acceptor = tls.map(|certificate_reloader| certificate_reloader.acceptor());
+
+    let mut ws_server = start_ws_server(
+        pubsub,
+        ws_addr,
+        workers,
+        acceptor.clone(),
+        api_keys,
+        rate_limiter,
+        stop_handle.clone(),
+        shutdown.clone(),
+    )?;
 
-    let http_server: AnyhowJoinHandle = tokio::spawn(async move {
-        log::info!("HTTP Server started at {http_addr:?}");
-        http_server_handle.stopped().await;
-        anyhow::bail!("HTTP server stopped");
+    let http_service_builder = ServerBuilder::default()
+        .set_logger(RpcMetricsLogger)
+        .http_only()
+        .to_service_builder();
+    let http_listeners = bind_listeners(http_addr.parse()?, workers)?;
+    // the limits are shared by the accept loops of all workers
+    let connection_limiter = ConnectionLimiter::new(&http_limits);
+    let make_http_service = move |remote_addr| {
+        tower::ServiceBuilder::new()
+            .layer(AddExtensionLayer::new(RemoteAddr(remote_addr)))
+            .layer(middleware.clone())
+            .service(
+                http_service_builder
+                    .clone()
+                    .build(rpc.clone(), stop_handle.clone()),
+            )
+    };
+    let http_shutdown = shutdown.clone();
+    let mut http_server: AnyhowJoinHandle = tokio::spawn(async move {
+        match acceptor {
+            Some(acceptor) => {
+                log::info!("HTTP Server started at {http_addr:?} with {workers} acceptors (TLS)");
+                serve_on_workers(http_listeners.into_iter().map(|listener| {
+                    serve_tls(
+                        listener,
+                        acceptor.clone(),
+                        make_http_service.clone(),
+                        connection_limiter.clone(),
+                        http_shutdown.clone(),
+                    )
+                }))
+                .await?;
+            }
+            None => {
+                log::info!("HTTP Server started at {http_addr:?} with {workers} acceptors");
+                serve_on_workers(http_listeners.into_iter().map(|listener| {
+                    serve_tcp(
+                        listener,
+                        make_http_service.clone(),
+                        connection_limiter.clone(),
+                        http_shutdown.clone(),
+                    )
+                }))
+                .await?;
+            }
+        }
+        if !http_shutdown.is_cancelled() {
+            anyhow::bail!("HTTP server stopped");
+        }
+        Ok(())
     });
 
     tokio::select! {
-        res = ws_server => {
+        biased;
+        _ = shutdown.cancelled() => {},
+        res = &mut ws_server => {
             anyhow::bail!("WebSocket server {res:?}");
         },
-        res = http_server => {
+        res = &mut http_server => {
             anyhow::bail!("HTTP server {res:?}");
         },
     }
+
+    // the listeners stop accepting, websocket connections are closed with a close frame which ends
+    // their subscriptions and the requests in flight are answered
+    log::info!("Stopping the HTTP and WebSocket servers");
+    let _ = server_handle.stop();
+    if tokio::time::timeout(
+        SHUTDOWN_PHASE_TIMEOUT,
+        futures::future::join(ws_server, http_server),
+    )
+    .await
+    .is_err()
+    {
+        log::warn!("Requests still in flight after {SHUTDOWN_PHASE_TIMEOUT:?}, closing them");
+    }
+    Ok(())
+}
+
+/// serves the subscriptions on `ws_addr` until the shutdown
+#[cfg(feature = "ws-server")]
+#[allow(clippy::too_many_arguments)]
+fn start_ws_server(
+    pubsub: Methods,
+    ws_addr: String,
+    workers: usize,
+    acceptor: Option<TlsAcceptor>,
+    api_keys: Option<ApiKeys>,
+    rate_limiter: Option<RateLimiter>,
+    stop_handle: StopHandle,
+    shutdown: CancellationToken,
+) -> anyhow::Result<AnyhowJoinHandle> {
+    // the api key of a websocket connection is not known to its method calls, which run in the
+    // tasks of the connection, so per key dry-run and fee limits could not apply to them
+    anyhow::ensure!(
+        pubsub.method("sendTransaction").is_none(),
+        "transactions cannot be sent over websocket"
+    );
+    let ws_service_builder = ServerBuilder::default()
+        .set_logger(RpcMetricsLogger)
+        .ws_only()
+        .to_service_builder();
+    let ws_listeners = bind_listeners(ws_addr.parse()?, workers)?;
+    // websocket connections are authenticated and limited on the upgrade request,
+    // their messages take the tokens of the client
+    let connection_limiter = match &rate_limiter {
+        Some(rate_limiter) => {
+            ConnectionLimiter::unlimited().with_message_meter(rate_limiter.clone())
+        }
+        None => ConnectionLimiter::unlimited(),
+    };
+    let ws_middleware = tower::ServiceBuilder::new()
+        .layer(ApiKeyAuthLayer::new(api_keys))
+        .layer(RateLimitLayer::new(rate_limiter));
+    let make_ws_service = move |remote_addr| {
+        tower::ServiceBuilder::new()
+            .layer(AddExtensionLayer::new(RemoteAddr(remote_addr)))
+            .layer(ws_middleware.clone())
+            .service(
+                ws_service_builder
+                    .clone()
+                    .build(pubsub.clone(), stop_handle.clone()),
+            )
+    };
+    Ok(tokio::spawn(async move {
+        match acceptor {
+            Some(acceptor) => {
+                log::info!(
+                    "Websocket Server started at {ws_addr:?} with {workers} acceptors (TLS)"
+                );
+                serve_on_workers(ws_listeners.into_iter().map(|listener| {
+                    serve_tls(
+                        listener,
+                        acceptor.clone(),
+                        make_ws_service.clone(),
+                        connection_limiter.clone(),
+                        shutdown.clone(),
+                    )
+                }))
+                .await?;
+            }
+            None => {
+                log::info!("Websocket Server started at {ws_addr:?} with {workers} acceptors");
+                serve_on_workers(ws_listeners.into_iter().map(|listener| {
+                    serve_tcp(
+                        listener,
+                        make_ws_service.clone(),
+                        connection_limiter.clone(),
+                        shutdown.clone(),
+                    )
+                }))
+                .await?;
+            }
+        }
+        if !shutdown.is_cancelled() {
+            anyhow::bail!("Websocket server stopped");
+        }
+        Ok(())
+    }))
+}
+
+/// the websocket server is compiled out, the handle only ends with the shutdown
+#[cfg(not(feature = "ws-server"))]
+#[allow(clippy::too_many_arguments)]
+fn start_ws_server(
+    _pubsub: Methods,
+    _ws_addr: String,
+    _workers: usize,
+    _acceptor: Option<TlsAcceptor>,
+    _api_keys: Option<ApiKeys>,
+    _rate_limiter: Option<RateLimiter>,
+    _stop_handle: StopHandle,
+    shutdown: CancellationToken,
+) -> anyhow::Result<AnyhowJoinHandle> {
+    Ok(tokio::spawn(async move {
+        shutdown.cancelled().await;
+        Ok(())
+    }))
+}
+
+/// binds `workers` listeners to `addr`, with more than one they share the port with SO_REUSEPORT
+/// and the kernel balances the incoming connections between their accept loops
+fn bind_listeners(addr: SocketAddr, workers: usize) -> anyhow::Result<Vec<TcpListener>> {
+    (0..workers)
+        .map(|_| {
+            let socket = if addr.is_ipv4() {
+                TcpSocket::new_v4()?
+            } else {
+                TcpSocket::new_v6()?
+            };
+            socket.set_reuseaddr(true)?;
+            if workers > 1 {
+                #[cfg(unix)]
+                socket.set_reuseport(true)?;
+                #[cfg(not(unix))]
+                anyhow::bail!(
+                    "SO_REUSEPORT is not supported on this platform, use one server worker"
+                );
+            }
+            socket.bind(addr)?;
+            Ok(socket.listen(LISTEN_BACKLOG)?)
+        })
+        .collect()
+}
+
+/// spawns the accept loops so they are polled on different worker threads of the runtime,
+/// returns once all stopped or with the error of the first one failing, which aborts the others
+async fn serve_on_workers<F>(accept_loops: impl Iterator<Item = F>) -> anyhow::Result<()>
+where
+    F: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let mut running = JoinSet::new();
+    for accept_loop in accept_loops {
+        running.spawn(accept_loop);
+    }
+    // dropping the set on an error aborts the accept loops still running
+    while let Some(stopped) = running.join_next().await {
+        stopped??;
+    }
+    Ok(())
+}
+
+/// serves plain tcp connections on `listener` with a new service from `make_service` per connection,
+/// returns after a shutdown once the in-flight requests are answered
+pub(crate) async fn serve_tcp<S, F>(
+    listener: TcpListener,
+    make_service: F,
+    connection_limiter: ConnectionLimiter,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()>
+where
+    F: Fn(SocketAddr) -> S,
+    S: Service<Request<Body>, Response = Response<Body>> + Send + 'static,
+    S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    S::Future: Send + 'static,
+{
+    let addr = listener.local_addr()?;
+    // every connection holds a sender, the receiver completes once all connections are closed
+    let (connections_open, mut connections_closed) = mpsc::channel::<()>(1);
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = shutdown.cancelled() => break,
+        };
+        let (tcp_stream, remote_addr) = match accepted {
+            Ok(connection) => connection,
+            Err(err) => {
+                log::warn!("Failed to accept connection on {addr}: {err}");
+                continue;
+            }
+        };
+        let Some(connection_slot) = connection_limiter.try_open() else {
+            log::debug!("Too many connections, closing the connection of {remote_addr}");
+            continue;
+        };
+        let service = make_service(remote_addr);
+        let connection_limiter = connection_limiter.clone();
+        let shutdown = shutdown.clone();
+        let connection_open = connections_open.clone();
+        tokio::spawn(async move {
+            let _connection_open = connection_open;
+            let _connection_slot = connection_slot;
+            connection_limiter
+                .serve(Http::new(), tcp_stream, service, remote_addr, shutdown)
+                .await;
+        });
+    }
+    drop(connections_open);
+    let _ = connections_closed.recv().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failing_accept_loop_aborts_the_others() {
+        // the sender is dropped once the pending accept loop is aborted
+        let (sender, receiver) = oneshot::channel::<()>();
+        let pending: Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>> =
+            Box::pin(async move {
+                let _sender = sender;
+                std::future::pending().await
+            });
+        let failing: Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>> =
+            Box::pin(async { anyhow::bail!("accept failed") });
+
+        let result = serve_on_workers([pending, failing].into_iter()).await;
+        assert_eq!(result.unwrap_err().to_string(), "accept failed");
+        assert!(receiver.await.is_err());
+    }
 }
@@ -0,0 +1,211 @@
+use std::{
+    io::BufReader,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use hyper::{server::conn::Http, service::Service, Body, Request, Response};
+use log::{debug, error, info, warn};
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    Certificate, PrivateKey, ServerConfig,
+};
+use rustls_pemfile::Item;
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_rustls::TlsAcceptor;
+use tokio_util::sync::CancellationToken;
+
+use crate::http_limits::ConnectionLimiter;
+
+// clients which do not finish the handshake in time are dropped
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    /// PEM encoded certificate chain
+    pub cert_path: String,
+    /// PEM encoded private key (PKCS#8, RSA or EC)
+    pub key_path: String,
+}
+
+/// serves the certificate of the HTTP and WebSocket listeners; it is replaced when the files change on disk,
+/// existing connections keep the certificate they were established with
+#[derive(Clone)]
+pub struct CertificateReloader {
+    tls_config: TlsConfig,
+    certified_key: Arc<RwLock<Arc<CertifiedKey>>>,
+}
+
+impl CertificateReloader {
+    pub fn new(tls_config: TlsConfig) -> anyhow::Result<Self> {
+        let certified_key = load_certified_key(&tls_config)?;
+        Ok(Self {
+            tls_config,
+            certified_key: Arc::new(RwLock::new(Arc::new(certified_key))),
+        })
+    }
+
+    pub fn acceptor(&self) -> TlsAcceptor {
+        let mut server_config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(self.clone()));
+        // websocket upgrades need http/1.1
+        server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        TlsAcceptor::from(Arc::new(server_config))
+    }
+
+    pub fn reload(&self) -> anyhow::Result<()> {
+        let certified_key = load_certified_key(&self.tls_config)?;
+        *self.certified_key.write().unwrap() = Arc::new(certified_key);
+        info!(
+            "Reloaded TLS certificate from {}",
+            self.tls_config.cert_path
+        );
+        Ok(())
+    }
+
+    async fn last_modified(&self) -> Option<(SystemTime, SystemTime)> {
+        let modified = |path: String| async move {
+            tokio::fs::metadata(path)
+                .await
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+        Some((
+            modified(self.tls_config.cert_path.clone()).await?,
+            modified(self.tls_config.key_path.clone()).await?,
+        ))
+    }
+
+    /// polls the certificate and key files and reloads them once both are readable after a change
+    pub fn start_watching(&self, poll_interval: Duration) -> tokio::task::JoinHandle<()> {
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut last_modified = this.last_modified().await;
+            loop {
+                tokio::time::sleep(poll_interval).await;
+                let modified = this.last_modified().await;
+                if modified.is_none() {
+                    warn!("Cannot read TLS certificate or key file");
+                    continue;
+                }
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                if let Err(e) = this.reload() {
+                    error!("Failed to reload TLS certificate: {e:?}");
+                }
+            }
+        })
+    }
+}
+
+impl ResolvesServerCert for CertificateReloader {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.certified_key.read().unwrap().clone())
+    }
+}
+
+fn load_certified_key(tls_config: &TlsConfig) -> anyhow::Result<CertifiedKey> {
+    let cert_file = std::fs::File::open(&tls_config.cert_path)
+        .with_context(|| format!("Cannot open TLS certificate {}", tls_config.cert_path))?;
+    let certificates: Vec<Certificate> = rustls_pemfile::certs(&mut BufReader::new(cert_file))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    anyhow::ensure!(
+        !certificates.is_empty(),
+        "No certificate found in {}",
+        tls_config.cert_path
+    );
+
+    let key_file = std::fs::File::open(&tls_config.key_path)
+        .with_context(|| format!("Cannot open TLS key {}", tls_config.key_path))?;
+    let private_key = rustls_pemfile::read_all(&mut BufReader::new(key_file))?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .with_context(|| format!("No private key found in {}", tls_config.key_path))?;
+    let signing_key = rustls::sign::any_supported_type(&private_key)
+        .map_err(|_| anyhow::anyhow!("Unsupported private key type in {}", tls_config.key_path))?;
+
+    Ok(CertifiedKey::new(certificates, signing_key))
+}
+
+/// accepts TLS connections on `listener` and serves them with a new service from `make_service`,
+/// connection upgrades (websockets) are supported; on shutdown no new connections are accepted
+/// and the function returns once the in-flight requests are answered
+pub async fn serve_tls<S, F>(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    make_service: F,
+    connection_limiter: ConnectionLimiter,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()>
+where
+    F: Fn(SocketAddr) -> S,
+    S: Service<Request<Body>, Response = Response<Body>> + Send + 'static,
+    S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    S::Future: Send + 'static,
+{
+    let addr = listener.local_addr()?;
+    // every connection holds a sender, the receiver completes once all connections are closed
+    let (connections_open, mut connections_closed) = mpsc::channel::<()>(1);
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = shutdown.cancelled() => break,
+        };
+        let (tcp_stream, remote_addr) = match accepted {
+            Ok(connection) => connection,
+            Err(err) => {
+                warn!("Failed to accept connection on {addr}: {err}");
+                continue;
+            }
+        };
+        let Some(connection_slot) = connection_limiter.try_open() else {
+            debug!("Too many connections, closing the connection of {remote_addr}");
+            continue;
+        };
+        let acceptor = acceptor.clone();
+        let service = make_service(remote_addr);
+        let connection_limiter = connection_limiter.clone();
+        let shutdown = shutdown.clone();
+        let connection_open = connections_open.clone();
+        tokio::spawn(async move {
+            let _connection_open = connection_open;
+            let _connection_slot = connection_slot;
+            let tls_stream = match tokio::time::timeout(
+                TLS_HANDSHAKE_TIMEOUT,
+                acceptor.accept(tcp_stream),
+            )
+            .await
+            {
+                Ok(Ok(tls_stream)) => tls_stream,
+                Ok(Err(err)) => {
+                    debug!("TLS handshake with {remote_addr} failed: {err}");
+                    return;
+                }
+                Err(_elapsed) => {
+                    debug!("TLS handshake with {remote_addr} timed out");
+                    return;
+                }
+            };
+            let mut http = Http::new();
+            http.http1_only(true);
+            connection_limiter
+                .serve(http, tls_stream, service, remote_addr, shutdown)
+                .await;
+        });
+    }
+    drop(connections_open);
+    let _ = connections_closed.recv().await;
+    Ok(())
+}
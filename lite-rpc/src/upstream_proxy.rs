@@ -0,0 +1,562 @@
+use std::{
+    collections::HashSet,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use hyper::{body::Bytes, Body, Method, Request, Response, StatusCode};
+use jsonrpsee::types::error::ErrorCode;
+use log::{debug, warn};
+use prometheus::{
+    histogram_opts, opts, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    HistogramVec, IntCounter, IntCounterVec,
+};
+use serde_json::Value;
+use solana_lite_rpc_cluster_endpoints::rpc_pool::RpcPool;
+use solana_rpc_client_api::client_error::reqwest;
+use tower::{Layer, Service};
+
+use crate::{http_limits::read_body, utils::text_response};
+
+lazy_static::lazy_static! {
+    static ref UPSTREAM_PROXY_REQUESTS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_upstream_proxy_requests", "JSON-RPC calls of methods not implemented by lite-rpc proxied to the rpc node"), &["method"]).unwrap();
+    static ref UPSTREAM_PROXY_ERRORS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_upstream_proxy_errors", "Proxied JSON-RPC calls answered with an error by the rpc node"), &["method"]).unwrap();
+    static ref UPSTREAM_PROXY_LATENCY: HistogramVec = register_histogram_vec!(
+        histogram_opts!(
+            "literpc_upstream_proxy_latency",
+            "Time in seconds the rpc node took to answer a proxied JSON-RPC call",
+            vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+        ),
+        &["method"]
+    )
+    .unwrap();
+    static ref UPSTREAM_PROXY_FAILURES: IntCounter =
+        register_int_counter!(opts!("literpc_upstream_proxy_failures", "Proxied requests which the rpc node did not answer after all retries")).unwrap();
+}
+
+// same limit as the json rpc http server of jsonrpsee
+const MAX_REQUEST_BODY_SIZE: usize = 10 * 1024 * 1024;
+// methods unknown to the rpc node are counted with this label to bound the label values
+const UNKNOWN_METHOD_LABEL: &str = "unknown";
+// calls with side effects on the rpc node, only retried if the request could not be sent
+const NON_IDEMPOTENT_METHODS: [&str; 2] = ["sendTransaction", "requestAirdrop"];
+
+#[derive(Clone)]
+pub struct UpstreamProxyConfig {
+    /// the attempts go to the available rpc nodes in turn
+    pub rpc_pool: RpcPool,
+    pub timeout: Duration,
+    /// attempts after the first one if the rpc node cannot be reached or answers with a 5xx,
+    /// calls of non-idempotent methods are only retried if the rpc node could not be connected
+    pub retries: usize,
+}
+
+struct UpstreamProxy {
+    config: UpstreamProxyConfig,
+    client: reqwest::Client,
+    // methods served by lite-rpc itself
+    methods: HashSet<String>,
+}
+
+/// tower layer forwarding JSON-RPC calls of methods lite-rpc does not implement to the rpc node,
+/// batches mixing both are split and the responses merged; does nothing if not configured
+#[derive(Clone)]
+pub struct UpstreamProxyLayer {
+    proxy: Option<Arc<UpstreamProxy>>,
+}
+
+impl UpstreamProxyLayer {
+    pub fn new<'a>(
+        config: Option<UpstreamProxyConfig>,
+        methods: impl Iterator<Item = &'a str>,
+    ) -> Self {
+        let proxy = config.map(|config| {
+            Arc::new(UpstreamProxy {
+                client: reqwest::Client::builder()
+                    .timeout(config.timeout)
+                    .build()
+                    .expect("upstream proxy http client"),
+                config,
+                methods: methods.map(str::to_string).collect(),
+            })
+        });
+        Self { proxy }
+    }
+}
+
+impl<S> Layer<S> for UpstreamProxyLayer {
+    type Service = UpstreamProxyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        UpstreamProxyService {
+            inner,
+            proxy: self.proxy.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct UpstreamProxyService<S> {
+    inner: S,
+    proxy: Option<Arc<UpstreamProxy>>,
+}
+
+impl<S> Service<Request<Body>> for UpstreamProxyService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let Some(proxy) = self.proxy.clone() else {
+            return Box::pin(self.inner.call(request));
+        };
+        let content_length = request
+            .headers()
+            .get(hyper::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+        // jsonrpsee rejects the bodies which are too large
+        if request.method() != Method::POST
+            || content_length.is_some_and(|length| length > MAX_REQUEST_BODY_SIZE)
+        {
+            return Box::pin(self.inner.call(request));
+        }
+
+        // the ready inner service serves this request, a clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            // the size of chunked bodies is only known once they are read
+            let body = match read_body(body, MAX_REQUEST_BODY_SIZE as u64).await {
+                Ok(body) => body,
+                Err(response) => return Ok(response),
+            };
+
+            let (local, upstream, is_batch) = match serde_json::from_slice::<Value>(&body) {
+                Ok(Value::Array(calls)) => {
+                    let (local, upstream): (Vec<Value>, Vec<Value>) =
+                        calls.into_iter().partition(|call| proxy.is_local(call));
+                    (local, upstream, true)
+                }
+                Ok(call) if !proxy.is_local(&call) => (vec![], vec![call], false),
+                // invalid json and local methods are answered by lite-rpc
+                _ => (vec![], vec![], false),
+            };
+            if upstream.is_empty() {
+                return inner
+                    .call(Request::from_parts(parts, Body::from(body)))
+                    .await;
+            }
+            if !is_batch {
+                return Ok(proxy.forward_response(&upstream[0]).await);
+            }
+            if local.is_empty() {
+                return Ok(proxy.forward_response(&Value::Array(upstream)).await);
+            }
+
+            let local_body = serde_json::to_vec(&local).expect("json calls serialize");
+            let (local_response, upstream_responses) = futures::join!(
+                inner.call(Request::from_parts(parts, Body::from(local_body))),
+                proxy.forward_batch(upstream),
+            );
+            let local_body = match hyper::body::to_bytes(local_response?.into_body()).await {
+                Ok(body) => body,
+                Err(e) => {
+                    return Ok(text_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        e.to_string(),
+                    ))
+                }
+            };
+            let mut responses = json_responses(&local_body);
+            responses.extend(upstream_responses);
+            Ok(json_response(
+                StatusCode::OK,
+                Bytes::from(serde_json::to_vec(&responses).expect("json responses serialize")),
+            ))
+        })
+    }
+}
+
+impl UpstreamProxy {
+    fn is_local(&self, call: &Value) -> bool {
+        match call.get("method").and_then(Value::as_str) {
+            Some(method) => self.methods.contains(method),
+            // malformed calls get their error from lite-rpc
+            None => true,
+        }
+    }
+
+    /// forwards a call or a batch, retried on the next rpc node if the rpc node cannot be reached
+    /// or answers with a 5xx
+    async fn forward(&self, calls: &Value) -> Result<(StatusCode, Bytes), String> {
+        let started = Instant::now();
+        let body = serde_json::to_vec(calls).expect("json calls serialize");
+        let rpc_addrs = self.config.rpc_pool.urls();
+        let idempotent = is_idempotent(calls);
+        let mut last_error = String::new();
+        for attempt in 0..=self.config.retries {
+            let rpc_addr = &rpc_addrs[attempt % rpc_addrs.len()];
+            let result = self
+                .client
+                .post(rpc_addr)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .await;
+            let response = match result {
+                Ok(response) if !response.status().is_server_error() => response,
+                Ok(response) => {
+                    last_error = format!("rpc node answered with {}", response.status());
+                    debug!("upstream proxy attempt {attempt}: {last_error}");
+                    self.config.rpc_pool.report(rpc_addr, false);
+                    if !idempotent {
+                        break;
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    last_error = e.to_string();
+                    debug!("upstream proxy attempt {attempt} failed: {e}");
+                    self.config.rpc_pool.report(rpc_addr, false);
+                    // the rpc node may have processed a call whose response was lost
+                    if !idempotent && !e.is_connect() {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            let status =
+                StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+            match response.bytes().await {
+                Ok(bytes) => {
+                    self.config.rpc_pool.report(rpc_addr, true);
+                    record_metrics(calls, &bytes, started.elapsed());
+                    return Ok((status, bytes));
+                }
+                Err(e) => {
+                    last_error = e.to_string();
+                    debug!("upstream proxy attempt {attempt} failed to read the response: {e}");
+                    self.config.rpc_pool.report(rpc_addr, false);
+                    if !idempotent {
+                        break;
+                    }
+                }
+            }
+        }
+        UPSTREAM_PROXY_FAILURES.inc();
+        warn!("rpc node did not answer a proxied request: {last_error}");
+        Err(format!("rpc node unavailable: {last_error}"))
+    }
+
+    async fn forward_response(&self, calls: &Value) -> Response<Body> {
+        match self.forward(calls).await {
+            Ok((status, body)) => json_response(status, body),
+            Err(e) => text_response(StatusCode::BAD_GATEWAY, e),
+        }
+    }
+
+    /// the responses to a batch, an error response per call if the rpc node is unavailable
+    async fn forward_batch(&self, calls: Vec<Value>) -> Vec<Value> {
+        match self.forward(&Value::Array(calls.clone())).await {
+            Ok((_, body)) => json_responses(&body),
+            Err(e) => calls
+                .iter()
+                .map(|call| {
+                    serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": call.get("id").cloned().unwrap_or(Value::Null),
+                        "error": {
+                            "code": ErrorCode::InternalError.code(),
+                            "message": e,
+                        },
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+// a call or batch without methods with side effects on the rpc node
+fn is_idempotent(calls: &Value) -> bool {
+    let is_idempotent_call = |call: &Value| {
+        call.get("method")
+            .and_then(Value::as_str)
+            .map_or(true, |method| !NON_IDEMPOTENT_METHODS.contains(&method))
+    };
+    match calls {
+        Value::Array(calls) => calls.iter().all(is_idempotent_call),
+        call => is_idempotent_call(call),
+    }
+}
+
+// counts every call with its method, unless the rpc node does not know the method either
+fn record_metrics(calls: &Value, response: &[u8], elapsed: Duration) {
+    let calls = match calls {
+        Value::Array(calls) => calls.iter().collect::<Vec<_>>(),
+        call => vec![call],
+    };
+    let responses = json_responses(response);
+    for call in calls {
+        let method = call
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let error_code = responses
+            .iter()
+            .find(|response| response.get("id") == call.get("id"))
+            .and_then(|response| response.get("error"))
+            .map(|error| {
+                error
+                    .get("code")
+                    .and_then(Value::as_i64)
+                    .unwrap_or_default()
+            });
+        let method = if error_code == Some(ErrorCode::MethodNotFound.code() as i64) {
+            UNKNOWN_METHOD_LABEL
+        } else {
+            method
+        };
+        UPSTREAM_PROXY_REQUESTS.with_label_values(&[method]).inc();
+        UPSTREAM_PROXY_LATENCY
+            .with_label_values(&[method])
+            .observe(elapsed.as_secs_f64());
+        if error_code.is_some() {
+            UPSTREAM_PROXY_ERRORS.with_label_values(&[method]).inc();
+        }
+    }
+}
+
+// the responses of a batch, a single response is returned as one element
+fn json_responses(body: &[u8]) -> Vec<Value> {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Array(responses)) => responses,
+        Ok(response) => vec![response],
+        Err(_) => vec![],
+    }
+}
+
+fn json_response(status: StatusCode, body: Bytes) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, convert::Infallible, sync::Mutex};
+
+    use hyper::service::{make_service_fn, service_fn};
+    use serde_json::json;
+    use tower::{util::BoxCloneService, ServiceExt};
+
+    use super::*;
+
+    type LiteRpc = BoxCloneService<Request<Body>, Response<Body>, Infallible>;
+
+    // a response per call with the result, a batch is answered with a batch
+    fn answer(calls: &Value, result: &str) -> Vec<u8> {
+        let response = |call: &Value| json!({"jsonrpc": "2.0", "id": call["id"], "result": result});
+        let responses = match calls {
+            Value::Array(calls) => Value::Array(calls.iter().map(response).collect()),
+            call => response(call),
+        };
+        serde_json::to_vec(&responses).unwrap()
+    }
+
+    async fn read_json(body: Body) -> Value {
+        serde_json::from_slice(&hyper::body::to_bytes(body).await.unwrap()).unwrap()
+    }
+
+    // an rpc node answering with the status, the received requests are returned
+    fn start_rpc_node(status: StatusCode) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let received = requests.clone();
+        let make_service = make_service_fn(move |_| {
+            let requests = requests.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let requests = requests.clone();
+                    async move {
+                        let calls = read_json(request.into_body()).await;
+                        requests.lock().unwrap().push(calls.clone());
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::from(answer(&calls, "upstream")))
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        (url, received)
+    }
+
+    // lite-rpc serving getSlot
+    fn proxy(rpc_node_url: String, retries: usize) -> UpstreamProxyService<LiteRpc> {
+        let lite_rpc = BoxCloneService::new(service_fn(|request: Request<Body>| async move {
+            let calls = read_json(request.into_body()).await;
+            Ok::<_, Infallible>(Response::new(Body::from(answer(&calls, "local"))))
+        }));
+        UpstreamProxyLayer::new(
+            Some(UpstreamProxyConfig {
+                rpc_pool: RpcPool::from_urls(rpc_node_url, vec![]),
+                timeout: Duration::from_secs(5),
+                retries,
+            }),
+            ["getSlot"].into_iter(),
+        )
+        .layer(lite_rpc)
+    }
+
+    fn call(id: u64, method: &str) -> Value {
+        json!({"jsonrpc": "2.0", "id": id, "method": method})
+    }
+
+    async fn send(proxy: UpstreamProxyService<LiteRpc>, calls: Value) -> Response<Body> {
+        let body = serde_json::to_vec(&calls).unwrap();
+        let request = Request::post("/")
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .header(hyper::header::CONTENT_LENGTH, body.len())
+            .body(Body::from(body))
+            .unwrap();
+        proxy.oneshot(request).await.unwrap()
+    }
+
+    // the results of the responses by id
+    async fn results(response: Response<Body>) -> HashMap<u64, Value> {
+        json_responses(&hyper::body::to_bytes(response.into_body()).await.unwrap())
+            .into_iter()
+            .map(|response| {
+                let id = response["id"].as_u64().unwrap();
+                let result = match response.get("result") {
+                    Some(result) => result.clone(),
+                    None => response["error"]["message"].clone(),
+                };
+                (id, result)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_mixed_batches_are_split_and_merged() {
+        let (url, requests) = start_rpc_node(StatusCode::OK);
+
+        let response = send(
+            proxy(url, 0),
+            json!([
+                call(1, "getSlot"),
+                call(2, "getBlockTime"),
+                call(3, "getSlot")
+            ]),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            results(response).await,
+            HashMap::from([
+                (1, json!("local")),
+                (2, json!("upstream")),
+                (3, json!("local")),
+            ])
+        );
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![json!([call(2, "getBlockTime")])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_rpc_node_fails_each_upstream_call_of_a_batch() {
+        let (url, _) = start_rpc_node(StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = send(
+            proxy(url, 0),
+            json!([call(1, "getSlot"), call(2, "getBlockTime")]),
+        )
+        .await;
+        let results = results(response).await;
+        assert_eq!(results[&1], json!("local"));
+        assert!(results[&2]
+            .as_str()
+            .unwrap()
+            .starts_with("rpc node unavailable"));
+    }
+
+    #[tokio::test]
+    async fn test_bodies_without_content_length_are_proxied() {
+        let (url, requests) = start_rpc_node(StatusCode::OK);
+        let (mut sender, body) = Body::channel();
+        let request = Request::post("/")
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .unwrap();
+        // a chunked body, the chunks are sent while the proxy reads them
+        let calls = serde_json::to_vec(&call(1, "getBlockTime")).unwrap();
+        tokio::spawn(async move {
+            for chunk in calls.chunks(8) {
+                sender
+                    .send_data(Bytes::copy_from_slice(chunk))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let response = proxy(url, 0).oneshot(request).await.unwrap();
+        assert_eq!(read_json(response.into_body()).await["result"], "upstream");
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_only_idempotent_calls_are_retried_on_server_errors() {
+        let (url, requests) = start_rpc_node(StatusCode::INTERNAL_SERVER_ERROR);
+
+        let response = send(proxy(url.clone(), 2), call(1, "getBlockTime")).await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        requests.lock().unwrap().clear();
+        let response = send(
+            proxy(url.clone(), 2),
+            json!([call(1, "getBlockTime"), call(2, "requestAirdrop")]),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_batches_with_side_effects_are_not_idempotent() {
+        assert!(is_idempotent(&call(1, "getBlockTime")));
+        assert!(is_idempotent(&json!([
+            call(1, "getBlockTime"),
+            call(2, "getSlot")
+        ])));
+        assert!(!is_idempotent(&call(1, "sendTransaction")));
+        assert!(!is_idempotent(&json!([
+            call(1, "getBlockTime"),
+            call(2, "requestAirdrop")
+        ])));
+    }
+}
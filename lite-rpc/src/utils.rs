@@ -0,0 +1,9 @@
+use hyper::{Body, Response, StatusCode};
+
+/// plain text response of the http endpoints
+pub fn text_response(status: StatusCode, text: impl Into<Body>) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(text.into())
+        .unwrap()
+}
@@ -0,0 +1,420 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use log::{debug, warn};
+use prometheus::{opts, register_int_counter, register_int_gauge, IntCounter, IntGauge};
+use serde::Serialize;
+use solana_lite_rpc_core::{types::BlockStream, AnyhowJoinHandle};
+use solana_rpc_client_api::client_error::reqwest::{self, Url};
+use solana_sdk::{signature::Signature, slot_history::Slot, transaction::TransactionError};
+use tokio::sync::broadcast::error::RecvError;
+
+lazy_static::lazy_static! {
+    pub static ref WEBHOOKS_PENDING: IntGauge =
+        register_int_gauge!(opts!("literpc_webhooks_pending", "Number of transactions waiting for a webhook callback")).unwrap();
+    static ref WEBHOOKS_SENT: IntCounter =
+        register_int_counter!(opts!("literpc_webhooks_sent", "Number of webhook callbacks delivered")).unwrap();
+    static ref WEBHOOKS_FAILED: IntCounter =
+        register_int_counter!(opts!("literpc_webhooks_failed", "Number of webhook callbacks which could not be delivered")).unwrap();
+}
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+const WEBHOOK_RETRIES: u32 = 3;
+// doubled after every failed attempt
+const WEBHOOK_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookStatus {
+    Confirmed,
+    Finalized,
+    Expired,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    pub signature: String,
+    pub status: WebhookStatus,
+    pub slot: Option<Slot>,
+    pub err: Option<TransactionError>,
+}
+
+struct PendingWebhook {
+    url: Url,
+    last_valid_block_height: u64,
+    confirmed: bool,
+}
+
+/// POSTs the status of a transaction to a callback url when it gets confirmed, finalized or expires.
+/// The callback urls are given by the clients, only the hosts allowed by the operator are called
+/// and never an address of the local network.
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    pending: Arc<DashMap<Signature, PendingWebhook>>,
+    // `hooks.example.com` or `*.example.com`, callbacks are rejected if empty
+    allowed_hosts: Arc<Vec<String>>,
+}
+
+impl WebhookNotifier {
+    pub fn new() -> Self {
+        Self {
+            pending: Arc::new(DashMap::new()),
+            allowed_hosts: Arc::new(vec![]),
+        }
+    }
+
+    pub fn with_allowed_hosts(mut self, allowed_hosts: Vec<String>) -> Self {
+        self.allowed_hosts = Arc::new(allowed_hosts);
+        self
+    }
+
+    /// http(s) url configured by the operator
+    pub fn parse_http_url(url: &str) -> anyhow::Result<Url> {
+        let url = Url::parse(url)?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            anyhow::bail!("url must be http or https");
+        }
+        Ok(url)
+    }
+
+    /// callback url given by a client, its host must be allowed and must not be a local address
+    pub fn parse_callback_url(&self, callback_url: &str) -> anyhow::Result<Url> {
+        let url = Self::parse_http_url(callback_url)?;
+        let Some(host) = url.host_str() else {
+            anyhow::bail!("callback url without host");
+        };
+        if !self
+            .allowed_hosts
+            .iter()
+            .any(|allowed_host| is_allowed_host(host, allowed_host))
+        {
+            anyhow::bail!("callback host {host} is not allowed");
+        }
+        let is_local = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+            Ok(ip) => !is_public_ip(ip),
+            Err(_) => host == "localhost" || host.ends_with(".localhost"),
+        };
+        if is_local {
+            anyhow::bail!("callback host {host} is a local address");
+        }
+        Ok(url)
+    }
+
+    pub fn register(&self, signature: Signature, url: Url, last_valid_block_height: u64) {
+        self.pending.insert(
+            signature,
+            PendingWebhook {
+                url,
+                last_valid_block_height,
+                confirmed: false,
+            },
+        );
+        WEBHOOKS_PENDING.set(self.pending.len() as i64);
+    }
+
+    fn post(&self, url: Url, payload: WebhookPayload) {
+        tokio::spawn(async move {
+            // the host is resolved again for every callback, it may point to the local network by now,
+            // the connection is pinned to the vetted address so a second lookup cannot change it
+            let client = match resolve_public_addr(&url)
+                .await
+                .and_then(|addr| Ok(pinned_client(&url, addr)?))
+            {
+                Ok(client) => client,
+                Err(e) => {
+                    WEBHOOKS_FAILED.inc();
+                    warn!("not sending webhook for {}: {e}", payload.signature);
+                    return;
+                }
+            };
+            let body = serde_json::to_vec(&payload).expect("webhook payload serializable");
+            for attempt in 0..WEBHOOK_RETRIES {
+                if attempt > 0 {
+                    tokio::time::sleep(WEBHOOK_RETRY_BACKOFF * 2u32.pow(attempt - 1)).await;
+                }
+                let result = client
+                    .post(url.clone())
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body.clone())
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                match result {
+                    Ok(_) => {
+                        WEBHOOKS_SENT.inc();
+                        return;
+                    }
+                    Err(e) => {
+                        debug!("webhook for {} failed: {e}", payload.signature);
+                    }
+                }
+            }
+            WEBHOOKS_FAILED.inc();
+            warn!(
+                "giving up on webhook for {} after {} attempts",
+                payload.signature, WEBHOOK_RETRIES
+            );
+        });
+    }
+
+    pub fn start(&self, mut block_stream: BlockStream) -> AnyhowJoinHandle {
+        let this = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let block = match block_stream.recv().await {
+                    Ok(block) => block,
+                    Err(RecvError::Lagged(lagged)) => {
+                        warn!("webhook notifier lagged by {lagged} blocks - continue");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => {
+                        anyhow::bail!("webhook notifier block stream closed");
+                    }
+                };
+                if block.commitment_config.is_processed() || this.pending.is_empty() {
+                    continue;
+                }
+                let finalized = block.commitment_config.is_finalized();
+
+                for tx in &block.transactions {
+                    let status = if finalized {
+                        this.pending
+                            .remove(&tx.signature)
+                            .map(|(_, webhook)| (webhook.url, WebhookStatus::Finalized))
+                    } else {
+                        match this.pending.get_mut(&tx.signature) {
+                            Some(mut webhook) if !webhook.confirmed => {
+                                webhook.confirmed = true;
+                                Some((webhook.url.clone(), WebhookStatus::Confirmed))
+                            }
+                            _ => None,
+                        }
+                    };
+                    if let Some((url, status)) = status {
+                        this.post(
+                            url,
+                            WebhookPayload {
+                                signature: tx.signature.to_string(),
+                                status,
+                                slot: Some(block.slot),
+                                err: tx.err.clone(),
+                            },
+                        );
+                    }
+                }
+
+                if finalized {
+                    // transactions which were never confirmed and cannot land anymore
+                    let mut expired = vec![];
+                    this.pending.retain(|signature, webhook| {
+                        if webhook.confirmed
+                            || webhook.last_valid_block_height >= block.block_height
+                        {
+                            true
+                        } else {
+                            expired.push((*signature, webhook.url.clone()));
+                            false
+                        }
+                    });
+                    for (signature, url) in expired {
+                        this.post(
+                            url,
+                            WebhookPayload {
+                                signature: signature.to_string(),
+                                status: WebhookStatus::Expired,
+                                slot: None,
+                                err: None,
+                            },
+                        );
+                    }
+                }
+                WEBHOOKS_PENDING.set(this.pending.len() as i64);
+            }
+        })
+    }
+}
+
+impl Default for WebhookNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_allowed_host(host: &str, allowed_host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    let allowed_host = allowed_host.to_ascii_lowercase();
+    match allowed_host.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+        None => host == allowed_host,
+    }
+}
+
+/// false for loopback, private, link local and other non routable addresses
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || first == 0
+        // shared address space 100.64.0.0/10
+        || (first == 100 && (second & 0xc0) == 64))
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let first_segment = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // unique local fc00::/7
+        || (first_segment & 0xfe00) == 0xfc00
+        // link local fe80::/10
+        || (first_segment & 0xffc0) == 0xfe80)
+}
+
+/// the address to call, fails if any address of the host is not public
+async fn resolve_public_addr(url: &Url) -> anyhow::Result<SocketAddr> {
+    let host = url.host_str().unwrap_or_default();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await?
+        .collect();
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        anyhow::bail!("{host} resolves to the local address {}", addr.ip());
+    }
+    addrs
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("{host} does not resolve to any address"))
+}
+
+fn pinned_client(url: &Url, addr: SocketAddr) -> reqwest::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        // a redirect could lead to a host which is not allowed
+        .redirect(reqwest::redirect::Policy::none());
+    if let Some(host) = url.domain() {
+        builder = builder.resolve(host, addr);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notifier(allowed_hosts: &[&str]) -> WebhookNotifier {
+        WebhookNotifier::new()
+            .with_allowed_hosts(allowed_hosts.iter().map(|host| host.to_string()).collect())
+    }
+
+    #[test]
+    fn test_callbacks_are_rejected_without_allowed_hosts() {
+        assert!(notifier(&[])
+            .parse_callback_url("https://hooks.example.com/tx")
+            .is_err());
+    }
+
+    #[test]
+    fn test_only_allowed_hosts_are_accepted() {
+        let notifier = notifier(&["hooks.example.com", "*.callbacks.example.org"]);
+        assert!(notifier
+            .parse_callback_url("https://hooks.example.com/tx")
+            .is_ok());
+        assert!(notifier
+            .parse_callback_url("https://a.callbacks.example.org/tx")
+            .is_ok());
+        assert!(notifier
+            .parse_callback_url("https://example.com/tx")
+            .is_err());
+        assert!(notifier
+            .parse_callback_url("https://evilcallbacks.example.org/tx")
+            .is_err());
+        assert!(notifier
+            .parse_callback_url("https://callbacks.example.org/tx")
+            .is_err());
+        assert!(notifier
+            .parse_callback_url("ftp://hooks.example.com/tx")
+            .is_err());
+    }
+
+    #[test]
+    fn test_local_addresses_are_rejected_even_if_allowed() {
+        let notifier = notifier(&[
+            "127.0.0.1",
+            "10.1.2.3",
+            "169.254.169.254",
+            "[::1]",
+            "[fd00::1]",
+            "[::ffff:c0a8:101]",
+            "localhost",
+            "93.184.216.34",
+        ]);
+        for url in [
+            "http://127.0.0.1:8080/",
+            "http://10.1.2.3/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:192.168.1.1]/",
+            "http://localhost/",
+        ] {
+            assert!(notifier.parse_callback_url(url).is_err(), "{url}");
+        }
+        assert!(notifier.parse_callback_url("http://93.184.216.34/").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_hosts_resolving_to_local_addresses_are_not_called() {
+        let url = Url::parse("http://localhost:8080/").unwrap();
+        assert!(resolve_public_addr(&url).await.is_err());
+        let url = Url::parse("http://127.0.0.1:8080/").unwrap();
+        assert!(resolve_public_addr(&url).await.is_err());
+        let url = Url::parse("http://93.184.216.34:8080/").unwrap();
+        assert_eq!(
+            resolve_public_addr(&url).await.unwrap(),
+            "93.184.216.34:8080".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_wildcard_hosts_are_case_insensitive() {
+        assert!(is_allowed_host(
+            "a.callbacks.example.org",
+            "*.Callbacks.Example.org"
+        ));
+        assert!(is_allowed_host(
+            "A.CALLBACKS.example.org",
+            "*.callbacks.example.org"
+        ));
+        assert!(is_allowed_host("Hooks.Example.com", "hooks.example.com"));
+        assert!(!is_allowed_host(
+            ".callbacks.example.org",
+            "*.callbacks.example.org"
+        ));
+        assert!(!is_allowed_host(
+            "a.evilcallbacks.example.org",
+            "*.callbacks.example.org"
+        ));
+    }
+}
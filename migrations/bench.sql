@@ -0,0 +1,39 @@
+-- results of the bench, created by the bench itself when it uploads to postgres (--pg-config)
+CREATE SCHEMA IF NOT EXISTS bench;
+
+-- one bench invocation, the totals over all of its runs
+CREATE TABLE IF NOT EXISTS bench.Runs (
+  id BIGSERIAL PRIMARY KEY,
+  started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  label TEXT,
+  rpc_addr TEXT NOT NULL,
+  rpc_version TEXT,
+  bench_version TEXT NOT NULL,
+  start_slot BIGINT NOT NULL,
+  runs BIGINT NOT NULL,
+  tx_count BIGINT NOT NULL,
+  tx_mix TEXT NOT NULL,
+  compute_unit_price BIGINT,
+  compute_unit_limit BIGINT,
+  txs_sent BIGINT NOT NULL,
+  txs_confirmed BIGINT NOT NULL,
+  landing_rate DOUBLE PRECISION NOT NULL,
+  mean_confirmation_ms DOUBLE PRECISION NOT NULL,
+  p50_confirmation_ms DOUBLE PRECISION NOT NULL,
+  p90_confirmation_ms DOUBLE PRECISION NOT NULL,
+  p99_confirmation_ms DOUBLE PRECISION NOT NULL
+);
+
+-- every transaction of a bench invocation, the confirmation columns are null for lost ones
+CREATE TABLE IF NOT EXISTS bench.Txs (
+  run_id BIGINT NOT NULL REFERENCES bench.Runs (id) ON DELETE CASCADE,
+  signature VARCHAR(88) NOT NULL,
+  tx_kind TEXT NOT NULL,
+  send_target TEXT NOT NULL,
+  sent_slot BIGINT NOT NULL,
+  confirmed_slot BIGINT,
+  time_to_send_ms BIGINT NOT NULL,
+  time_to_confirm_ms BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS bench_txs_run_id ON bench.Txs (run_id);
@@ -1,4 +1,5 @@
 -- note: this schema is only used for postgres_logger
+-- lite-rpc applies the versioned migrations in ./versions on startup, this file is kept for manual setups
 CREATE SCHEMA lite_rpc;
 
 CREATE TABLE lite_rpc.Txs (
@@ -27,3 +28,34 @@ CREATE TABLE lite_rpc.AccountAddrs (
   id SERIAL PRIMARY KEY,
   addr VARCHAR(45) NOT NULL
 );
+
+-- prioritization fee history, fees in micro lamports per compute unit
+CREATE TABLE lite_rpc.BlockPrioFees (
+  slot BIGINT NOT NULL PRIMARY KEY,
+  tx_count BIGINT NOT NULL,
+  nonvote_tx_count BIGINT NOT NULL,
+  cu_consumed BIGINT NOT NULL,
+  nonvote_cu_consumed BIGINT NOT NULL,
+  p50_by_tx BIGINT NOT NULL,
+  p75_by_tx BIGINT NOT NULL,
+  p90_by_tx BIGINT NOT NULL,
+  p95_by_tx BIGINT NOT NULL,
+  p50_by_cu BIGINT NOT NULL,
+  p75_by_cu BIGINT NOT NULL,
+  p90_by_cu BIGINT NOT NULL,
+  p95_by_cu BIGINT NOT NULL
+);
+
+-- transactions write-locking the account
+CREATE TABLE lite_rpc.AccountPrioFees (
+  slot BIGINT NOT NULL,
+  account VARCHAR(44) NOT NULL,
+  tx_count BIGINT NOT NULL,
+  cu_consumed BIGINT NOT NULL,
+  p50_by_tx BIGINT NOT NULL,
+  p75_by_tx BIGINT NOT NULL,
+  p90_by_tx BIGINT NOT NULL,
+  p95_by_tx BIGINT NOT NULL,
+  PRIMARY KEY (slot, account)
+);
+CREATE INDEX idx_accountpriofees_account ON lite_rpc.AccountPrioFees(account, slot);
@@ -1,3 +1,6 @@
 DROP TABLE lite_rpc.Txs;
 DROP TABLE lite_rpc.Blocks;
 DROP TABLE lite_rpc.AccountAddrs;
+DROP TABLE lite_rpc.BlockPrioFees;
+DROP TABLE lite_rpc.AccountPrioFees;
+DROP TABLE lite_rpc.SchemaMigrations;
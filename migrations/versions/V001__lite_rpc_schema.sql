@@ -0,0 +1,59 @@
+-- initial schema of postgres_logger, equal to create.sql; IF NOT EXISTS adopts databases set up by hand
+CREATE SCHEMA IF NOT EXISTS lite_rpc;
+
+CREATE TABLE IF NOT EXISTS lite_rpc.Txs (
+  id SERIAL NOT NULL PRIMARY KEY,
+  signature VARCHAR(88) NOT NULL,
+  recent_slot BIGINT NOT NULL,
+  forwarded_slot BIGINT NOT NULL,
+  forwarded_local_time TIMESTAMP WITH TIME ZONE NOT NULL,
+  processed_slot BIGINT,
+  cu_consumed BIGINT,
+  cu_requested BIGINT,
+  cu_price BIGINT,
+  quic_response SMALLINT
+);
+
+CREATE TABLE IF NOT EXISTS lite_rpc.Blocks (
+  slot BIGINT NOT NULL PRIMARY KEY,
+  leader_id BIGINT NOT NULL,
+  parent_slot BIGINT NOT NULL,
+  cluster_time TIMESTAMP WITH TIME ZONE NOT NULL,
+  local_time TIMESTAMP WITH TIME ZONE
+);
+
+CREATE TABLE IF NOT EXISTS lite_rpc.AccountAddrs (
+  id SERIAL PRIMARY KEY,
+  addr VARCHAR(45) NOT NULL
+);
+
+-- prioritization fee history, fees in micro lamports per compute unit
+CREATE TABLE IF NOT EXISTS lite_rpc.BlockPrioFees (
+  slot BIGINT NOT NULL PRIMARY KEY,
+  tx_count BIGINT NOT NULL,
+  nonvote_tx_count BIGINT NOT NULL,
+  cu_consumed BIGINT NOT NULL,
+  nonvote_cu_consumed BIGINT NOT NULL,
+  p50_by_tx BIGINT NOT NULL,
+  p75_by_tx BIGINT NOT NULL,
+  p90_by_tx BIGINT NOT NULL,
+  p95_by_tx BIGINT NOT NULL,
+  p50_by_cu BIGINT NOT NULL,
+  p75_by_cu BIGINT NOT NULL,
+  p90_by_cu BIGINT NOT NULL,
+  p95_by_cu BIGINT NOT NULL
+);
+
+-- transactions write-locking the account
+CREATE TABLE IF NOT EXISTS lite_rpc.AccountPrioFees (
+  slot BIGINT NOT NULL,
+  account VARCHAR(44) NOT NULL,
+  tx_count BIGINT NOT NULL,
+  cu_consumed BIGINT NOT NULL,
+  p50_by_tx BIGINT NOT NULL,
+  p75_by_tx BIGINT NOT NULL,
+  p90_by_tx BIGINT NOT NULL,
+  p95_by_tx BIGINT NOT NULL,
+  PRIMARY KEY (slot, account)
+);
+CREATE INDEX IF NOT EXISTS idx_accountpriofees_account ON lite_rpc.AccountPrioFees(account, slot);
@@ -0,0 +1,29 @@
+-- signature index of the epoch schemas created before it existed, backfilled from the stored transactions;
+-- the position of backfilled transactions follows their transaction_id which is assigned in insertion order,
+-- blocks which were written in parallel chunks might be off within the block
+DO $$
+DECLARE
+    epoch_schema text;
+BEGIN
+    FOR epoch_schema IN
+        SELECT schema_name FROM information_schema.schemata WHERE schema_name ~ '^rpc2a_epoch_[0-9]+$'
+    LOOP
+        EXECUTE format(
+            'CREATE TABLE IF NOT EXISTS %I.signature_index(
+                signature text STORAGE PLAIN PRIMARY KEY WITH (FILLFACTOR=90),
+                slot bigint NOT NULL,
+                idx_in_block int NOT NULL
+            ) WITH (FILLFACTOR=90)',
+            epoch_schema);
+        EXECUTE format(
+            'INSERT INTO %1$I.signature_index(signature, slot, idx_in_block)
+            SELECT
+                tx_ids.signature,
+                transaction_blockdata.slot,
+                (row_number() OVER (PARTITION BY transaction_blockdata.slot ORDER BY transaction_blockdata.transaction_id) - 1)::int
+            FROM %1$I.transaction_blockdata
+            INNER JOIN %1$I.transaction_ids tx_ids USING (transaction_id)
+            ON CONFLICT DO NOTHING',
+            epoch_schema);
+    END LOOP;
+END $$;
@@ -0,0 +1,13 @@
+-- compressed transaction messages and block rewards in the epoch schemas created before payloads were compressed
+DO $$
+DECLARE
+    epoch_schema text;
+BEGIN
+    FOR epoch_schema IN
+        SELECT schema_name FROM information_schema.schemata WHERE schema_name ~ '^rpc2a_epoch_[0-9]+$'
+    LOOP
+        EXECUTE format('ALTER TABLE %I.blocks ADD COLUMN IF NOT EXISTS rewards_zstd BYTEA', epoch_schema);
+        EXECUTE format('ALTER TABLE %I.transaction_blockdata ADD COLUMN IF NOT EXISTS message_zstd bytea', epoch_schema);
+        EXECUTE format('ALTER TABLE %I.transaction_blockdata ALTER COLUMN message DROP NOT NULL', epoch_schema);
+    END LOOP;
+END $$;
@@ -1,14 +1,19 @@
 use std::sync::Arc;
 
 use solana_lite_rpc_core::{
+    structures::produced_block::ProducedBlock,
     traits::address_lookup_table_interface::AddressLookupTableInterface, types::BlockStream,
 };
-use solana_sdk::{pubkey::Pubkey, slot_history::Slot};
+use solana_sdk::{message::VersionedMessage, pubkey::Pubkey, slot_history::Slot};
 use tokio::{sync::broadcast::Sender, task::JoinHandle};
 
 use crate::{
     account_priofees::AccountPrioStore,
-    rpc_data::{AccountPrioFeesStats, AccountPrioFeesUpdateMessage},
+    priofees_window::PrioFeesWindowConfig,
+    rpc_data::{
+        AccountContention, AccountPrioFeesStats, AccountPrioFeesUpdateMessage,
+        MessageFeeRecommendation, PrioFeesPercentiles,
+    },
 };
 use tokio::sync::broadcast::error::RecvError::{Closed, Lagged};
 
@@ -21,10 +26,10 @@ pub struct AccountPrioService {
 impl AccountPrioService {
     pub fn start_account_priofees_task(
         mut block_stream: BlockStream,
-        slots_to_retain: usize,
+        window: PrioFeesWindowConfig,
         address_lookup_tables_impl: Option<Arc<dyn AddressLookupTableInterface>>,
     ) -> (JoinHandle<()>, AccountPrioService) {
-        let account_store = AccountPrioStore::new(slots_to_retain, address_lookup_tables_impl);
+        let account_store = AccountPrioStore::new(window, address_lookup_tables_impl);
         let (priofees_update_sender, _priofees_update_receiver) =
             tokio::sync::broadcast::channel(64);
 
@@ -67,6 +72,13 @@ impl AccountPrioService {
         )
     }
 
+    /// fills the window with blocks loaded from storage on startup, no update messages are sent
+    pub async fn warm_start(&self, blocks: &[ProducedBlock]) {
+        for block in blocks {
+            self.account_store.update(block).await;
+        }
+    }
+
     pub fn get_latest_stats(&self, account: &Pubkey) -> (Slot, AccountPrioFeesStats) {
         self.account_store.get_latest_stats(account)
     }
@@ -74,4 +86,64 @@ impl AccountPrioService {
     pub fn get_n_last_stats(&self, account: &Pubkey, nb: usize) -> (Slot, AccountPrioFeesStats) {
         self.account_store.get_n_last_stats(account, nb)
     }
+
+    pub fn get_n_last_slots_stats(
+        &self,
+        account: &Pubkey,
+        nb_slots: u64,
+    ) -> (Slot, AccountPrioFeesStats) {
+        self.account_store.get_n_last_slots_stats(account, nb_slots)
+    }
+
+    pub fn get_write_lock_percentiles(
+        &self,
+        account: &Pubkey,
+        nb: usize,
+    ) -> (Slot, PrioFeesPercentiles) {
+        self.account_store.get_write_lock_percentiles(account, nb)
+    }
+
+    pub fn get_hottest_accounts(
+        &self,
+        nb_slots: u64,
+        limit: usize,
+    ) -> (Slot, Vec<AccountContention>) {
+        self.account_store.get_hottest_accounts(nb_slots, limit)
+    }
+
+    /// recommends the highest fee paid at `percentile` (multiple of 5) on any writable account of the message
+    /// within the last `nb_slots` slots; the recommended price is 0 if none of the accounts is contended
+    pub async fn recommend_fee_for_message(
+        &self,
+        message: &VersionedMessage,
+        nb_slots: u64,
+        percentile: u32,
+    ) -> (Slot, MessageFeeRecommendation) {
+        let contended_accounts = self
+            .account_store
+            .writable_accounts(message)
+            .await
+            .iter()
+            .filter_map(|account| {
+                self.account_store
+                    .get_write_lock_fee(account, nb_slots, percentile as f32 / 100.0)
+            })
+            .collect::<Vec<_>>();
+        let recommended_cu_price = contended_accounts
+            .iter()
+            .map(|account| account.cu_price)
+            .max()
+            .unwrap_or_default();
+        (
+            self.account_store
+                .last_slot
+                .load(std::sync::atomic::Ordering::Relaxed),
+            MessageFeeRecommendation {
+                recommended_cu_price,
+                percentile,
+                lookback_slots: nb_slots,
+                contended_accounts,
+            },
+        )
+    }
 }
@@ -1,20 +1,25 @@
 use std::{
     collections::{BTreeMap, HashMap},
-    sync::{atomic::AtomicU64, Arc},
+    sync::{atomic::AtomicU64, Arc, Mutex},
 };
 
 use dashmap::DashMap;
 use itertools::Itertools;
-use prometheus::{core::GenericGauge, opts, register_int_gauge};
+use prometheus::{core::GenericGauge, opts, register_int_counter, register_int_gauge, IntCounter};
 use solana_lite_rpc_core::{
     structures::produced_block::ProducedBlock,
     traits::address_lookup_table_interface::AddressLookupTableInterface,
 };
-use solana_sdk::{pubkey::Pubkey, slot_history::Slot};
+use solana_sdk::{message::VersionedMessage, pubkey::Pubkey, slot_history::Slot};
 
 use crate::{
+    priofees_window::PrioFeesWindowConfig,
     prioritization_fee_data::{BlockPrioData, PrioFeesData},
-    rpc_data::{AccountPrioFeesStats, AccountPrioFeesUpdateMessage},
+    rpc_data::{
+        AccountContention, AccountPrioFeesStats, AccountPrioFeesUpdateMessage, AccountWriteLockFee,
+        PrioFeesPercentiles,
+    },
+    stats_calculation::calculate_supp_percentiles,
 };
 
 lazy_static::lazy_static! {
@@ -26,6 +31,18 @@ lazy_static::lazy_static! {
 
     static ref NUMBER_OF_PRIO_DATA_POINTS: GenericGauge<prometheus::core::AtomicI64> =
     register_int_gauge!(opts!("literpc_nb_of_accouts_priofees_datapoints", "Number of priofees accounts data points")).unwrap();
+
+    static ref WRITE_LOCKED_ACCOUNTS_PER_BLOCK: GenericGauge<prometheus::core::AtomicI64> =
+    register_int_gauge!(opts!("literpc_write_locked_accounts_per_block", "Number of accounts write-locked by non vote transactions in the latest block")).unwrap();
+
+    static ref MAX_WRITE_LOCKS_PER_ACCOUNT_IN_BLOCK: GenericGauge<prometheus::core::AtomicI64> =
+    register_int_gauge!(opts!("literpc_max_write_locks_per_account_in_block", "Highest number of transactions write-locking the same account in the latest block")).unwrap();
+
+    static ref MAX_CU_PER_WRITE_LOCKED_ACCOUNT_IN_BLOCK: GenericGauge<prometheus::core::AtomicI64> =
+    register_int_gauge!(opts!("literpc_max_cu_per_write_locked_account_in_block", "Highest compute units consumed by transactions write-locking the same account in the latest block")).unwrap();
+
+    static ref ACCOUNT_PRIOFEES_EVICTED_SLOTS: IntCounter =
+    register_int_counter!(opts!("literpc_account_priofees_evicted_slots", "Number of slots evicted from the account priofees window to stay within the memory bound")).unwrap();
 }
 
 pub struct AccountPrio {
@@ -36,20 +53,23 @@ pub struct AccountPrio {
 pub struct AccountPrioStore {
     pub account_by_prio_fees_all: Arc<DashMap<Pubkey, AccountPrio>>,
     pub account_by_prio_fees_writeonly: Arc<DashMap<Pubkey, AccountPrio>>,
-    pub number_of_slots_to_save: usize,
+    pub window: PrioFeesWindowConfig,
+    // number of transaction data points stored per slot, used to enforce the window bounds
+    data_points_by_slot: Arc<Mutex<BTreeMap<Slot, usize>>>,
     pub last_slot: Arc<AtomicU64>,
     pub address_lookup_tables_impl: Option<Arc<dyn AddressLookupTableInterface>>,
 }
 
 impl AccountPrioStore {
     pub fn new(
-        number_of_slots_to_save: usize,
+        window: PrioFeesWindowConfig,
         address_lookup_tables_impl: Option<Arc<dyn AddressLookupTableInterface>>,
     ) -> Self {
         Self {
             account_by_prio_fees_all: Arc::new(DashMap::new()),
             account_by_prio_fees_writeonly: Arc::new(DashMap::new()),
-            number_of_slots_to_save,
+            window,
+            data_points_by_slot: Arc::new(Mutex::new(BTreeMap::new())),
             last_slot: Arc::new(AtomicU64::new(0)),
             address_lookup_tables_impl,
         }
@@ -69,35 +89,14 @@ impl AccountPrioStore {
         let mut accounts_by_prioritization_read_write: HashMap<Pubkey, Vec<PrioFeesData>> =
             HashMap::new();
 
-        if let Some(alt_fetcher) = &self.address_lookup_tables_impl {
-            let alt_messages = transactions
-                .iter()
-                .flat_map(|x| &x.address_lookup_tables)
-                .collect_vec();
-
-            log::trace!("Checking to reload {} accounts", alt_messages.len());
-            alt_fetcher.reload_if_necessary(&alt_messages).await;
-        }
-
         for transaction in transactions {
             let value = PrioFeesData {
                 priority: transaction.prioritization_fees.unwrap_or_default(),
                 cu_consumed: transaction.cu_consumed.unwrap_or_default(),
+                failed: transaction.err.is_some(),
             };
-            let mut writable_accounts = transaction.writable_accounts.clone();
-            let mut readable_accounts = transaction.readable_accounts.clone();
-
-            if let Some(alt_fetcher) = &self.address_lookup_tables_impl {
-                for transaction_lookup_table in &transaction.address_lookup_tables {
-                    let (mut alts_w, mut alts_r) = alt_fetcher
-                        .resolve_addresses_from_lookup_table(transaction_lookup_table)
-                        .await;
-                    writable_accounts.append(&mut alts_w);
-                    readable_accounts.append(&mut alts_r);
-                }
-            }
-
-            for write_lock in &writable_accounts {
+            // accounts loaded from address lookup tables were added by the resolved block stream
+            for write_lock in &transaction.writable_accounts {
                 match accounts_by_prioritization_write.get_mut(write_lock) {
                     Some(acc_vec) => {
                         acc_vec.push(value);
@@ -117,7 +116,7 @@ impl AccountPrioStore {
                 }
             }
 
-            for readlock in &readable_accounts {
+            for readlock in &transaction.readable_accounts {
                 match accounts_by_prioritization_read_write.get_mut(readlock) {
                     Some(acc_vec) => {
                         acc_vec.push(value);
@@ -154,6 +153,22 @@ impl AccountPrioStore {
                 .map(|(key, data)| (*key, convert_to_block_prio_data(data)))
                 .collect();
 
+        WRITE_LOCKED_ACCOUNTS_PER_BLOCK.set(accounts_by_prioritization_write.len() as i64);
+        MAX_WRITE_LOCKS_PER_ACCOUNT_IN_BLOCK.set(
+            accounts_by_prioritization_write
+                .values()
+                .map(|data| data.nb_total_tx)
+                .max()
+                .unwrap_or_default() as i64,
+        );
+        MAX_CU_PER_WRITE_LOCKED_ACCOUNT_IN_BLOCK.set(
+            accounts_by_prioritization_write
+                .values()
+                .map(|data| data.total_cu_consumed)
+                .max()
+                .unwrap_or_default() as i64,
+        );
+
         for (account, data) in &accounts_by_prioritization_write {
             match self.account_by_prio_fees_writeonly.get_mut(account) {
                 Some(mut prio) => {
@@ -171,7 +186,7 @@ impl AccountPrioStore {
         }
 
         for (account, data) in &accounts_by_prioritization_read_write {
-            match self.account_by_prio_fees_writeonly.get_mut(account) {
+            match self.account_by_prio_fees_all.get_mut(account) {
                 Some(mut prio) => {
                     prio.stats_by_slot.insert(slot, data.clone());
                 }
@@ -180,16 +195,27 @@ impl AccountPrioStore {
                         stats_by_slot: BTreeMap::new(),
                     };
                     prio_fee.stats_by_slot.insert(slot, data.clone());
-                    self.account_by_prio_fees_writeonly
-                        .insert(*account, prio_fee);
+                    self.account_by_prio_fees_all.insert(*account, prio_fee);
                 }
             }
         }
 
         // cleanup old data
-        let min_slot_to_retain = produced_block
-            .slot
-            .saturating_sub(self.number_of_slots_to_save as u64);
+        let min_slot_to_retain = {
+            let data_points = accounts_by_prioritization_write
+                .values()
+                .chain(accounts_by_prioritization_read_write.values())
+                .map(|data| data.transaction_data.len())
+                .sum();
+            let mut data_points_by_slot = self.data_points_by_slot.lock().unwrap();
+            data_points_by_slot.insert(slot, data_points);
+            let evicted = self.window.apply(&mut *data_points_by_slot, slot, |x| *x);
+            ACCOUNT_PRIOFEES_EVICTED_SLOTS.inc_by(evicted as u64);
+            data_points_by_slot
+                .first_key_value()
+                .map(|(first_slot, _)| first_slot.saturating_sub(1))
+                .unwrap_or_else(|| slot.saturating_sub(self.window.slots_to_retain))
+        };
         let cleanup_functor = |iter: &mut AccountPrio| {
             while let Some((k, _)) = iter.stats_by_slot.first_key_value() {
                 if *k > min_slot_to_retain {
@@ -304,4 +330,168 @@ impl AccountPrioStore {
             },
         )
     }
+
+    pub fn get_n_last_slots_stats(
+        &self,
+        account: &Pubkey,
+        nb_slots: u64,
+    ) -> (Slot, AccountPrioFeesStats) {
+        let last_slot = self.last_slot.load(std::sync::atomic::Ordering::Relaxed);
+        let first_slot = last_slot.saturating_sub(nb_slots.saturating_sub(1));
+        let functor = |account_prio: &AccountPrio| {
+            account_prio
+                .stats_by_slot
+                .range(first_slot..)
+                .fold(BlockPrioData::default(), |agg, (_, rhs)| agg.add(rhs))
+        };
+        let all = self
+            .account_by_prio_fees_all
+            .get(account)
+            .map(|x| functor(x.value()))
+            .unwrap_or_default();
+        let write_only = self
+            .account_by_prio_fees_writeonly
+            .get(account)
+            .map(|x| functor(x.value()))
+            .unwrap_or_default();
+        (
+            last_slot,
+            AccountPrioFeesStats {
+                write_stats: write_only.calculate_stats(),
+                all_stats: all.calculate_stats(),
+            },
+        )
+    }
+
+    /// fee percentiles of the transactions write-locking the account in its last `nb` slots with activity
+    pub fn get_write_lock_percentiles(
+        &self,
+        account: &Pubkey,
+        nb: usize,
+    ) -> (Slot, PrioFeesPercentiles) {
+        let percentiles = self
+            .account_by_prio_fees_writeonly
+            .get(account)
+            .map(|account_prio| {
+                let slots = account_prio.stats_by_slot.iter().rev().take(nb);
+                let nb_blocks = slots.len();
+                slots
+                    .fold(BlockPrioData::default(), |agg, (_, rhs)| agg.add(rhs))
+                    .calculate_percentiles(nb_blocks)
+            })
+            .unwrap_or_default();
+        (
+            self.last_slot.load(std::sync::atomic::Ordering::Relaxed),
+            percentiles,
+        )
+    }
+
+    /// accounts write-locked by the message, including the ones loaded from address lookup tables
+    pub async fn writable_accounts(&self, message: &VersionedMessage) -> Vec<Pubkey> {
+        let mut writable_accounts = message
+            .static_account_keys()
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| message.is_maybe_writable(*index))
+            .map(|(_, key)| *key)
+            .collect_vec();
+
+        if let (Some(alt_fetcher), Some(lookups)) = (
+            &self.address_lookup_tables_impl,
+            message.address_table_lookups(),
+        ) {
+            alt_fetcher
+                .reload_if_necessary(&lookups.iter().collect_vec())
+                .await;
+            for lookup in lookups {
+                let (mut alts_w, _alts_r) = alt_fetcher
+                    .resolve_addresses_from_lookup_table(lookup)
+                    .await;
+                writable_accounts.append(&mut alts_w);
+            }
+        }
+        writable_accounts.into_iter().unique().collect()
+    }
+
+    /// fee paid at the given percentile by the transactions write-locking the account within the last `nb_slots` slots,
+    /// None if the account was not write-locked in that window
+    pub fn get_write_lock_fee(
+        &self,
+        account: &Pubkey,
+        nb_slots: u64,
+        percentile: f32,
+    ) -> Option<AccountWriteLockFee> {
+        let last_slot = self.last_slot.load(std::sync::atomic::Ordering::Relaxed);
+        let first_slot = last_slot.saturating_sub(nb_slots.saturating_sub(1));
+        let data = self
+            .account_by_prio_fees_writeonly
+            .get(account)?
+            .stats_by_slot
+            .range(first_slot..)
+            .fold(BlockPrioData::default(), |agg, (_, rhs)| agg.add(rhs));
+        if data.transaction_data.is_empty() {
+            return None;
+        }
+        let (cu_price, _by_cu) = data.calculate_stats().get_percentile(percentile)?;
+        Some(AccountWriteLockFee {
+            account: account.to_string(),
+            tx_count: data.transaction_data.len() as u64,
+            cu_price,
+        })
+    }
+
+    /// accounts write-locked by the most compute units within the last `nb_slots` slots
+    pub fn get_hottest_accounts(
+        &self,
+        nb_slots: u64,
+        limit: usize,
+    ) -> (Slot, Vec<AccountContention>) {
+        let last_slot = self.last_slot.load(std::sync::atomic::Ordering::Relaxed);
+        let first_slot = last_slot.saturating_sub(nb_slots.saturating_sub(1));
+        let hottest = self
+            .account_by_prio_fees_writeonly
+            .iter()
+            .filter_map(|entry| {
+                let (nb_blocks, tx_count, cu_consumed) =
+                    entry.stats_by_slot.range(first_slot..).fold(
+                        (0usize, 0u64, 0u64),
+                        |(nb_blocks, tx_count, cu_consumed), (_, data)| {
+                            (
+                                nb_blocks + 1,
+                                tx_count + data.nb_total_tx,
+                                cu_consumed + data.total_cu_consumed,
+                            )
+                        },
+                    );
+                (nb_blocks > 0).then_some((*entry.key(), nb_blocks, tx_count, cu_consumed))
+            })
+            .sorted_by(|a, b| b.3.cmp(&a.3))
+            .take(limit)
+            .collect_vec();
+
+        let hottest = hottest
+            .into_iter()
+            .map(|(account, nb_blocks, tx_count, cu_consumed)| {
+                let by_tx = self
+                    .account_by_prio_fees_writeonly
+                    .get(&account)
+                    .map(|account_prio| {
+                        let data = account_prio
+                            .stats_by_slot
+                            .range(first_slot..)
+                            .fold(BlockPrioData::default(), |agg, (_, rhs)| agg.add(rhs));
+                        calculate_supp_percentiles(&data.transaction_data).fee_percentiles_by_tx()
+                    })
+                    .unwrap_or_default();
+                AccountContention {
+                    account: account.to_string(),
+                    nb_blocks,
+                    tx_count,
+                    cu_consumed,
+                    by_tx,
+                }
+            })
+            .collect();
+        (last_slot, hottest)
+    }
 }
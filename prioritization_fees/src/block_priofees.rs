@@ -1,6 +1,17 @@
+use crate::priofees_window::PrioFeesWindowConfig;
 use crate::prioritization_fee_data::{BlockPrioData, PrioFeesData};
-use crate::rpc_data::{PrioFeesStats, PrioFeesUpdateMessage};
+use crate::rpc_data::{
+    BlockUtilization, BlockUtilizationStats, PrioFeesPercentiles, PrioFeesStats,
+    PrioFeesUpdateMessage,
+};
+use async_trait::async_trait;
 use log::{error, info, trace, warn};
+use prometheus::{
+    core::GenericGauge, histogram_opts, opts, register_gauge, register_histogram,
+    register_int_counter, register_int_gauge, Gauge, Histogram, IntCounter, IntGauge,
+};
+use solana_lite_rpc_core::memory_budget::{EvictionPriority, MemoryConsumer};
+use solana_lite_rpc_core::structures::produced_block::ProducedBlock;
 use solana_lite_rpc_core::types::BlockStream;
 use solana_sdk::clock::Slot;
 use std::collections::BTreeMap;
@@ -10,11 +21,104 @@ use tokio::sync::broadcast::Sender;
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 
+lazy_static::lazy_static! {
+    static ref BLOCK_PRIOFEES_DATA_POINTS: GenericGauge<prometheus::core::AtomicI64> =
+    register_int_gauge!(opts!("literpc_block_priofees_datapoints", "Number of transactions kept for block priofees stats")).unwrap();
+
+    static ref BLOCK_PRIOFEES_SLOTS: GenericGauge<prometheus::core::AtomicI64> =
+    register_int_gauge!(opts!("literpc_block_priofees_slots", "Number of blocks kept for block priofees stats")).unwrap();
+
+    static ref BLOCK_PRIOFEES_EVICTED_SLOTS: IntCounter =
+    register_int_counter!(opts!("literpc_block_priofees_evicted_slots", "Number of blocks evicted from the block priofees window to stay within the memory bound")).unwrap();
+
+    static ref BLOCK_CU_CONSUMED: IntGauge =
+    register_int_gauge!(opts!("literpc_block_cu_consumed", "Compute units consumed by the transactions of the latest processed block")).unwrap();
+
+    static ref BLOCK_NONVOTE_CU_CONSUMED: IntGauge =
+    register_int_gauge!(opts!("literpc_block_nonvote_cu_consumed", "Compute units consumed by the non-vote transactions of the latest processed block")).unwrap();
+
+    static ref BLOCK_FILL_RATIO: Gauge =
+    register_gauge!(opts!("literpc_block_fill_ratio", "Share of the block compute unit limit consumed by the latest processed block")).unwrap();
+
+    static ref BLOCK_FILL_RATIO_DISTRIBUTION: Histogram = register_histogram!(histogram_opts!(
+        "literpc_block_fill_ratio_distribution",
+        "Share of the block compute unit limit consumed by the processed blocks",
+        vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 0.95, 1.0]
+    ))
+    .unwrap();
+}
+
 /// put everything required to serve sync data calls here
 #[derive(Clone)]
 pub struct PrioFeeStore {
-    // store priofees stats for recently processed blocks up to CLEANUP_SLOTS_AFTER
+    // store priofees stats for recently processed blocks within the configured window
     recent: Arc<RwLock<BTreeMap<Slot, BlockPrioData>>>,
+    window: PrioFeesWindowConfig,
+    // compute units a leader may pack into a block, the utilization is relative to it
+    block_cu_limit: u64,
+}
+
+impl PrioFeeStore {
+    async fn insert(&self, slot: Slot, block_prio_data: BlockPrioData) {
+        let mut lock = self.recent.write().await;
+        lock.insert(slot, block_prio_data);
+        let evicted = self
+            .window
+            .apply(&mut *lock, slot, |data| data.transaction_data.len());
+        BLOCK_PRIOFEES_EVICTED_SLOTS.inc_by(evicted as u64);
+        update_window_metrics(&lock);
+    }
+}
+
+fn update_window_metrics(recent: &BTreeMap<Slot, BlockPrioData>) {
+    BLOCK_PRIOFEES_SLOTS.set(recent.len() as i64);
+    BLOCK_PRIOFEES_DATA_POINTS.set(
+        recent
+            .values()
+            .map(|data| data.transaction_data.len())
+            .sum::<usize>() as i64,
+    );
+}
+
+// estimated heap memory of the stats of a block
+fn block_prio_data_size(data: &BlockPrioData) -> usize {
+    std::mem::size_of::<(Slot, BlockPrioData)>()
+        + data.transaction_data.capacity() * std::mem::size_of::<PrioFeesData>()
+}
+
+#[async_trait]
+impl MemoryConsumer for PrioFeeStore {
+    fn name(&self) -> &'static str {
+        "block_priofees"
+    }
+
+    // a shorter window only makes the aggregates over many blocks less accurate
+    fn eviction_priority(&self) -> EvictionPriority {
+        EvictionPriority::Medium
+    }
+
+    async fn memory_usage(&self) -> usize {
+        self.recent
+            .read()
+            .await
+            .values()
+            .map(block_prio_data_size)
+            .sum()
+    }
+
+    /// evicts the oldest slots, the latest one is always kept
+    async fn evict(&self, bytes: usize) -> usize {
+        let mut lock = self.recent.write().await;
+        let mut freed = 0;
+        while freed < bytes && lock.len() > 1 {
+            if let Some((_, data)) = lock.pop_first() {
+                freed += block_prio_data_size(&data);
+                BLOCK_PRIOFEES_EVICTED_SLOTS.inc();
+            }
+        }
+        update_window_metrics(&lock);
+        freed
+    }
 }
 
 #[derive(Clone)]
@@ -25,6 +129,15 @@ pub struct PrioFeesService {
 }
 
 impl PrioFeesService {
+    /// fills the window with blocks loaded from storage on startup, no update messages are sent
+    pub async fn warm_start(&self, blocks: &[ProducedBlock]) {
+        for block in blocks {
+            self.block_fees_store
+                .insert(block.slot, block_prio_data(block))
+                .await;
+        }
+    }
+
     pub async fn get_latest_priofees(&self) -> Option<(Slot, PrioFeesStats)> {
         let lock = self.block_fees_store.recent.read().await;
         lock.last_key_value()
@@ -49,20 +162,73 @@ impl PrioFeesService {
             .calculate_stats();
         Some((last_slot, prio_fees))
     }
+
+    /// aggregate of the blocks within the last `nb_slots` slots (skipped slots included)
+    pub async fn get_last_n_slots_priofees_aggregate(
+        &self,
+        nb_slots: u64,
+    ) -> Option<(Slot, PrioFeesStats)> {
+        let lock = self.block_fees_store.recent.read().await;
+        let last_slot = *lock.last_key_value()?.0;
+        let first_slot = last_slot.saturating_sub(nb_slots.saturating_sub(1));
+
+        let prio_fees = lock
+            .range(first_slot..)
+            .map(|x| x.1)
+            .fold(BlockPrioData::default(), |acc, x| acc.add(x))
+            .calculate_stats();
+        Some((last_slot, prio_fees))
+    }
+
+    /// compute unit utilization of the last `nb` blocks, newest first
+    pub async fn get_last_n_block_utilization(
+        &self,
+        nb: usize,
+    ) -> Option<(Slot, BlockUtilizationStats)> {
+        let lock = self.block_fees_store.recent.read().await;
+        let last_slot = *lock.last_key_value()?.0;
+
+        let blocks = lock
+            .iter()
+            .rev()
+            .take(nb)
+            .map(|(slot, data)| data.utilization(*slot, self.block_fees_store.block_cu_limit))
+            .collect();
+        Some((last_slot, BlockUtilizationStats::from_blocks(blocks)))
+    }
+
+    pub async fn get_last_n_priofees_percentiles(
+        &self,
+        nb: usize,
+    ) -> Option<(Slot, PrioFeesPercentiles)> {
+        let lock = self.block_fees_store.recent.read().await;
+        let last_slot = *lock.last_key_value()?.0;
+
+        let blocks = lock.iter().rev().take(nb).map(|x| x.1).collect::<Vec<_>>();
+        let percentiles = blocks
+            .iter()
+            .fold(BlockPrioData::default(), |acc, x| acc.add(x))
+            .calculate_percentiles(blocks.len());
+        Some((last_slot, percentiles))
+    }
 }
 
 pub fn start_block_priofees_task(
     mut block_stream: BlockStream,
-    slots_to_retain: u64,
+    window: PrioFeesWindowConfig,
+    block_cu_limit: u64,
 ) -> (JoinHandle<()>, PrioFeesService) {
-    let recent_data = Arc::new(RwLock::new(BTreeMap::new()));
     let store = PrioFeeStore {
-        recent: recent_data.clone(),
+        recent: Arc::new(RwLock::new(BTreeMap::new())),
+        window,
+        block_cu_limit,
     };
     let (priofees_update_sender, _priofees_update_receiver) = tokio::sync::broadcast::channel(64);
     let sender_to_return = priofees_update_sender.clone();
 
+    let store_for_task = store.clone();
     let jh_priofees_task = tokio::spawn(async move {
+        let store = store_for_task;
         let sender = priofees_update_sender.clone();
         'recv_loop: loop {
             let block = block_stream.recv().await;
@@ -72,53 +238,13 @@ pub fn start_block_priofees_task(
                         continue;
                     }
                     let processed_slot = block.slot;
-
-                    let tx_prioritization = block
-                        .transactions
-                        .iter()
-                        .filter(|tx| !tx.is_vote)
-                        .map(|tx| PrioFeesData {
-                            priority: tx.prioritization_fees.unwrap_or_default(),
-                            cu_consumed: tx.cu_consumed.unwrap_or_default(),
-                        })
-                        .collect::<Vec<PrioFeesData>>();
-
-                    let nb_total_tx = block.transactions.len() as u64;
-
-                    let nb_non_vote_tx =
-                        block.transactions.iter().filter(|tx| !tx.is_vote).count() as u64;
-
-                    let total_cu_consumed = block
-                        .transactions
-                        .iter()
-                        .map(|tx| tx.cu_consumed.unwrap_or(0))
-                        .sum::<u64>();
-
-                    let non_vote_cu_consumed = block
-                        .transactions
-                        .iter()
-                        .filter(|tx| !tx.is_vote)
-                        .map(|tx| tx.cu_consumed.unwrap_or(0))
-                        .sum::<u64>();
-
-                    let block_prio_data = BlockPrioData {
-                        transaction_data: tx_prioritization,
-                        nb_non_vote_tx,
-                        nb_total_tx,
-                        non_vote_cu_consumed,
-                        total_cu_consumed,
-                    };
-
+                    let block_prio_data = block_prio_data(&block);
                     trace!("Got prio fees stats for processed block {}", processed_slot);
                     let priofees_stats = block_prio_data.calculate_stats();
-                    {
-                        // first do some cleanup
-                        let mut lock = recent_data.write().await;
-                        lock.insert(processed_slot, block_prio_data);
-                        lock.retain(|slot, _| {
-                            *slot > processed_slot.saturating_sub(slots_to_retain)
-                        });
-                    }
+                    update_utilization_metrics(
+                        &block_prio_data.utilization(processed_slot, store.block_cu_limit),
+                    );
+                    store.insert(processed_slot, block_prio_data).await;
                     let msg = PrioFeesUpdateMessage {
                         slot: processed_slot,
                         priofees_stats,
@@ -160,3 +286,48 @@ pub fn start_block_priofees_task(
         },
     )
 }
+
+fn update_utilization_metrics(utilization: &BlockUtilization) {
+    BLOCK_CU_CONSUMED.set(utilization.cu_consumed.total as i64);
+    BLOCK_NONVOTE_CU_CONSUMED.set(utilization.cu_consumed.nonvote as i64);
+    BLOCK_FILL_RATIO.set(utilization.fill_ratio);
+    BLOCK_FILL_RATIO_DISTRIBUTION.observe(utilization.fill_ratio);
+}
+
+fn block_prio_data(block: &ProducedBlock) -> BlockPrioData {
+    let tx_prioritization = block
+        .transactions
+        .iter()
+        .filter(|tx| !tx.is_vote)
+        .map(|tx| PrioFeesData {
+            priority: tx.prioritization_fees.unwrap_or_default(),
+            cu_consumed: tx.cu_consumed.unwrap_or_default(),
+            failed: tx.err.is_some(),
+        })
+        .collect::<Vec<PrioFeesData>>();
+
+    let nb_total_tx = block.transactions.len() as u64;
+
+    let nb_non_vote_tx = block.transactions.iter().filter(|tx| !tx.is_vote).count() as u64;
+
+    let total_cu_consumed = block
+        .transactions
+        .iter()
+        .map(|tx| tx.cu_consumed.unwrap_or(0))
+        .sum::<u64>();
+
+    let non_vote_cu_consumed = block
+        .transactions
+        .iter()
+        .filter(|tx| !tx.is_vote)
+        .map(|tx| tx.cu_consumed.unwrap_or(0))
+        .sum::<u64>();
+
+    BlockPrioData {
+        transaction_data: tx_prioritization,
+        nb_non_vote_tx,
+        nb_total_tx,
+        non_vote_cu_consumed,
+        total_cu_consumed,
+    }
+}
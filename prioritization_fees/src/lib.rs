@@ -2,8 +2,10 @@ mod block_priofees;
 
 pub mod account_prio_service;
 mod account_priofees;
+pub mod priofees_window;
 pub mod prioritization_fee_calculation_method;
 pub mod prioritization_fee_data;
+pub mod program_priofees;
 pub mod rpc_data;
 mod stats_calculation;
 
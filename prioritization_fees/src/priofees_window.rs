@@ -0,0 +1,74 @@
+use solana_sdk::clock::Slot;
+use std::collections::BTreeMap;
+
+/// how much history the prioritization fee aggregators keep in memory
+#[derive(Clone, Copy, Debug)]
+pub struct PrioFeesWindowConfig {
+    // blocks older than the latest slot minus this number are dropped
+    pub slots_to_retain: u64,
+    // upper bound for the number of transaction data points kept; oldest slots are evicted first
+    pub max_data_points: usize,
+}
+
+impl Default for PrioFeesWindowConfig {
+    fn default() -> Self {
+        Self {
+            slots_to_retain: 100,
+            max_data_points: 1_000_000,
+        }
+    }
+}
+
+impl PrioFeesWindowConfig {
+    /// Drops entries outside of the slot window and then the oldest entries until the data points fit into the bound.
+    /// Returns the number of slots evicted because of the data point bound.
+    pub fn apply<T>(
+        &self,
+        entries: &mut BTreeMap<Slot, T>,
+        latest_slot: Slot,
+        data_points: impl Fn(&T) -> usize,
+    ) -> usize {
+        let min_slot = latest_slot.saturating_sub(self.slots_to_retain);
+        entries.retain(|slot, _| *slot > min_slot);
+
+        let mut total: usize = entries.values().map(&data_points).sum();
+        let mut evicted = 0;
+        // always keep the latest entry
+        while total > self.max_data_points && entries.len() > 1 {
+            if let Some((_, entry)) = entries.pop_first() {
+                total -= data_points(&entry);
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_window() {
+        let config = PrioFeesWindowConfig {
+            slots_to_retain: 2,
+            max_data_points: 100,
+        };
+        let mut entries = BTreeMap::from([(1, 1), (2, 1), (3, 1)]);
+        let evicted = config.apply(&mut entries, 3, |x| *x);
+        assert_eq!(evicted, 0);
+        assert_eq!(entries.keys().copied().collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_data_point_bound() {
+        let config = PrioFeesWindowConfig {
+            slots_to_retain: 100,
+            max_data_points: 10,
+        };
+        let mut entries = BTreeMap::from([(1, 5), (2, 5), (3, 5)]);
+        let evicted = config.apply(&mut entries, 3, |x| *x);
+        assert_eq!(evicted, 1);
+        assert_eq!(entries.keys().copied().collect::<Vec<_>>(), vec![2, 3]);
+    }
+}
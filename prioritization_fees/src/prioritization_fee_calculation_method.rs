@@ -6,6 +6,7 @@ pub enum PrioritizationFeeCalculationMethod {
     #[default]
     Latest,
     LastNBlocks(usize),
+    LastNSlots(u64),
     Unknown,
 }
 
@@ -27,6 +28,15 @@ impl<'de> Deserialize<'de> for PrioritizationFeeCalculationMethod {
                 return Ok(PrioritizationFeeCalculationMethod::Unknown);
             };
             Ok(PrioritizationFeeCalculationMethod::LastNBlocks(nb))
+        } else if s.starts_with("last_n_slots") {
+            let Ok(nb) = s
+                .replace("last_n_slots(", "")
+                .replace(')', "")
+                .parse::<u64>()
+            else {
+                return Ok(PrioritizationFeeCalculationMethod::Unknown);
+            };
+            Ok(PrioritizationFeeCalculationMethod::LastNSlots(nb))
         } else {
             return Ok(PrioritizationFeeCalculationMethod::Unknown);
         }
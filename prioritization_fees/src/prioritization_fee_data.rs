@@ -1,5 +1,10 @@
+use solana_sdk::clock::Slot;
+
 use crate::{
-    rpc_data::{PrioFeesStats, TxAggregateStats},
+    rpc_data::{
+        BlockUtilization, LandedPrioFeesPercentiles, PrioFeesPercentiles, PrioFeesStats,
+        TxAggregateStats,
+    },
     stats_calculation::calculate_supp_percentiles,
 };
 
@@ -7,6 +12,8 @@ use crate::{
 pub struct PrioFeesData {
     pub priority: u64,
     pub cu_consumed: u64,
+    // transaction landed in the block but failed
+    pub failed: bool,
 }
 
 impl From<(u64, u64)> for PrioFeesData {
@@ -14,6 +21,7 @@ impl From<(u64, u64)> for PrioFeesData {
         Self {
             priority: value.0,
             cu_consumed: value.1,
+            failed: false,
         }
     }
 }
@@ -47,6 +55,44 @@ impl BlockPrioData {
         }
     }
 
+    /// compute units consumed by the block relative to the block limit
+    pub fn utilization(&self, slot: Slot, block_cu_limit: u64) -> BlockUtilization {
+        let fill_ratio = |cu_consumed: u64| cu_consumed as f64 / block_cu_limit as f64;
+        BlockUtilization {
+            slot,
+            cu_limit: block_cu_limit,
+            cu_consumed: TxAggregateStats {
+                total: self.total_cu_consumed,
+                nonvote: self.non_vote_cu_consumed,
+            },
+            fill_ratio: fill_ratio(self.total_cu_consumed),
+            nonvote_fill_ratio: fill_ratio(self.non_vote_cu_consumed),
+        }
+    }
+
+    pub fn calculate_percentiles(&self, nb_blocks: usize) -> PrioFeesPercentiles {
+        let (failed, successful): (Vec<PrioFeesData>, Vec<PrioFeesData>) = self
+            .transaction_data
+            .iter()
+            .copied()
+            .partition(|data| data.failed);
+        PrioFeesPercentiles {
+            nb_blocks,
+            successful: Self::landed_percentiles(&successful),
+            failed: Self::landed_percentiles(&failed),
+        }
+    }
+
+    fn landed_percentiles(transaction_data: &[PrioFeesData]) -> LandedPrioFeesPercentiles {
+        let percentiles = calculate_supp_percentiles(transaction_data);
+        LandedPrioFeesPercentiles {
+            tx_count: transaction_data.len() as u64,
+            cu_consumed: transaction_data.iter().map(|x| x.cu_consumed).sum(),
+            by_tx: percentiles.fee_percentiles_by_tx(),
+            by_cu: percentiles.fee_percentiles_by_cu(),
+        }
+    }
+
     pub fn add(&self, rhs: &BlockPrioData) -> BlockPrioData {
         Self {
             transaction_data: [self.transaction_data.clone(), rhs.transaction_data.clone()]
@@ -58,3 +104,40 @@ impl BlockPrioData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc_data::BlockUtilizationStats;
+
+    fn block(total_cu_consumed: u64, non_vote_cu_consumed: u64) -> BlockPrioData {
+        BlockPrioData {
+            total_cu_consumed,
+            non_vote_cu_consumed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_block_utilization() {
+        let utilization = block(36_000_000, 24_000_000).utilization(42, 48_000_000);
+        assert_eq!(utilization.slot, 42);
+        assert_eq!(utilization.cu_consumed.total, 36_000_000);
+        assert_eq!(utilization.fill_ratio, 0.75);
+        assert_eq!(utilization.nonvote_fill_ratio, 0.5);
+
+        let stats = BlockUtilizationStats::from_blocks(vec![
+            utilization,
+            block(12_000_000, 0).utilization(41, 48_000_000),
+        ]);
+        assert_eq!(stats.nb_blocks, 2);
+        assert_eq!(stats.avg_fill_ratio, 0.5);
+        assert_eq!(stats.max_fill_ratio, 0.75);
+        assert_eq!(stats.avg_nonvote_fill_ratio, 0.25);
+
+        assert_eq!(
+            BlockUtilizationStats::from_blocks(vec![]).avg_fill_ratio,
+            0.0
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{atomic::AtomicU64, Arc, Mutex},
+};
+
+use dashmap::DashMap;
+use prometheus::{core::GenericGauge, opts, register_int_counter, register_int_gauge, IntCounter};
+use solana_lite_rpc_core::{structures::produced_block::ProducedBlock, types::BlockStream};
+use solana_sdk::{pubkey::Pubkey, slot_history::Slot};
+use tokio::sync::broadcast::error::RecvError::{Closed, Lagged};
+use tokio::task::JoinHandle;
+
+use crate::{
+    priofees_window::PrioFeesWindowConfig,
+    prioritization_fee_data::{BlockPrioData, PrioFeesData},
+    rpc_data::PrioFeesStats,
+};
+
+lazy_static::lazy_static! {
+    static ref PROGRAMS_IN_PRIO_CACHE: GenericGauge<prometheus::core::AtomicI64> =
+    register_int_gauge!(opts!("literpc_nb_of_programs_in_priofees_cache", "Number of programs in priofees cache")).unwrap();
+
+    static ref PROGRAM_PRIOFEES_EVICTED_SLOTS: IntCounter =
+    register_int_counter!(opts!("literpc_program_priofees_evicted_slots", "Number of slots evicted from the program priofees window to stay within the memory bound")).unwrap();
+}
+
+/// prioritization fees of the non vote transactions grouped by the programs invoked by their top level instructions
+#[derive(Clone)]
+pub struct ProgramPrioStore {
+    program_by_prio_fees: Arc<DashMap<Pubkey, BTreeMap<Slot, BlockPrioData>>>,
+    window: PrioFeesWindowConfig,
+    // number of transaction data points stored per slot, used to enforce the window bounds
+    data_points_by_slot: Arc<Mutex<BTreeMap<Slot, usize>>>,
+    last_slot: Arc<AtomicU64>,
+}
+
+impl ProgramPrioStore {
+    pub fn new(window: PrioFeesWindowConfig) -> Self {
+        Self {
+            program_by_prio_fees: Arc::new(DashMap::new()),
+            window,
+            data_points_by_slot: Arc::new(Mutex::new(BTreeMap::new())),
+            last_slot: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn update(&self, produced_block: &ProducedBlock) {
+        let mut programs_by_prioritization: HashMap<Pubkey, BlockPrioData> = HashMap::new();
+        for transaction in produced_block.transactions.iter().filter(|x| !x.is_vote) {
+            let value = PrioFeesData {
+                priority: transaction.prioritization_fees.unwrap_or_default(),
+                cu_consumed: transaction.cu_consumed.unwrap_or_default(),
+                failed: transaction.err.is_some(),
+            };
+            for program_id in &transaction.program_ids {
+                let data = programs_by_prioritization.entry(*program_id).or_default();
+                data.transaction_data.push(value);
+                data.nb_non_vote_tx += 1;
+                data.nb_total_tx += 1;
+                data.non_vote_cu_consumed += value.cu_consumed;
+                data.total_cu_consumed += value.cu_consumed;
+            }
+        }
+
+        let slot = produced_block.slot;
+        let data_points = programs_by_prioritization
+            .values()
+            .map(|data| data.transaction_data.len())
+            .sum();
+        for (program_id, data) in programs_by_prioritization {
+            self.program_by_prio_fees
+                .entry(program_id)
+                .or_default()
+                .insert(slot, data);
+        }
+
+        // cleanup old data
+        let min_slot_to_retain = {
+            let mut data_points_by_slot = self.data_points_by_slot.lock().unwrap();
+            data_points_by_slot.insert(slot, data_points);
+            let evicted = self.window.apply(&mut *data_points_by_slot, slot, |x| *x);
+            PROGRAM_PRIOFEES_EVICTED_SLOTS.inc_by(evicted as u64);
+            data_points_by_slot
+                .first_key_value()
+                .map(|(first_slot, _)| first_slot.saturating_sub(1))
+                .unwrap_or_else(|| slot.saturating_sub(self.window.slots_to_retain))
+        };
+        self.program_by_prio_fees.retain(|_, stats_by_slot| {
+            stats_by_slot.retain(|k, _| *k > min_slot_to_retain);
+            !stats_by_slot.is_empty()
+        });
+        PROGRAMS_IN_PRIO_CACHE.set(self.program_by_prio_fees.len() as i64);
+
+        self.last_slot
+            .store(slot, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// stats of the last `nb` processed blocks, the blocks in which the program was not invoked count as well
+    pub fn get_n_last_stats(&self, program_id: &Pubkey, nb: usize) -> (Slot, PrioFeesStats) {
+        let (last_slot, first_slot) = {
+            let data_points_by_slot = self.data_points_by_slot.lock().unwrap();
+            let last_slot = data_points_by_slot
+                .last_key_value()
+                .map(|(slot, _)| *slot)
+                .unwrap_or_default();
+            (
+                last_slot,
+                data_points_by_slot.keys().rev().take(nb).last().copied(),
+            )
+        };
+        let stats = match (self.program_by_prio_fees.get(program_id), first_slot) {
+            (Some(stats_by_slot), Some(first_slot)) => stats_by_slot
+                .range(first_slot..)
+                .fold(BlockPrioData::default(), |agg, (_, rhs)| agg.add(rhs)),
+            _ => BlockPrioData::default(),
+        };
+        (last_slot, stats.calculate_stats())
+    }
+
+    pub fn get_n_last_slots_stats(
+        &self,
+        program_id: &Pubkey,
+        nb_slots: u64,
+    ) -> (Slot, PrioFeesStats) {
+        let last_slot = self.last_slot.load(std::sync::atomic::Ordering::Relaxed);
+        let first_slot = last_slot.saturating_sub(nb_slots.saturating_sub(1));
+        let stats = self
+            .program_by_prio_fees
+            .get(program_id)
+            .map(|stats_by_slot| {
+                stats_by_slot
+                    .range(first_slot..)
+                    .fold(BlockPrioData::default(), |agg, (_, rhs)| agg.add(rhs))
+            })
+            .unwrap_or_default();
+        (last_slot, stats.calculate_stats())
+    }
+}
+
+#[derive(Clone)]
+pub struct ProgramPrioService {
+    program_store: ProgramPrioStore,
+}
+
+impl ProgramPrioService {
+    pub fn start_program_priofees_task(
+        mut block_stream: BlockStream,
+        window: PrioFeesWindowConfig,
+    ) -> (JoinHandle<()>, ProgramPrioService) {
+        let program_store = ProgramPrioStore::new(window);
+
+        let jh = {
+            let program_store = program_store.clone();
+            tokio::spawn(async move {
+                'recv_loop: loop {
+                    match block_stream.recv().await {
+                        Ok(block) => {
+                            if !block.commitment_config.is_processed() {
+                                continue;
+                            }
+                            program_store.update(&block);
+                        }
+                        Err(Lagged(_lagged)) => {
+                            log::warn!(
+                                "channel lagged receiving block for program priofees calculation - continue"
+                            );
+                            continue 'recv_loop;
+                        }
+                        Err(Closed) => {
+                            log::error!("failed to receive block, sender closed - aborting");
+                            break 'recv_loop;
+                        }
+                    }
+                }
+                log::info!("program priofees task shutting down");
+            })
+        };
+        (jh, ProgramPrioService { program_store })
+    }
+
+    /// fills the window with blocks loaded from storage on startup
+    pub fn warm_start(&self, blocks: &[ProducedBlock]) {
+        for block in blocks {
+            self.program_store.update(block);
+        }
+    }
+
+    /// stats of the latest processed block, empty if the program was not invoked in it
+    pub fn get_latest_stats(&self, program_id: &Pubkey) -> (Slot, PrioFeesStats) {
+        self.program_store.get_n_last_stats(program_id, 1)
+    }
+
+    pub fn get_n_last_stats(&self, program_id: &Pubkey, nb: usize) -> (Slot, PrioFeesStats) {
+        self.program_store.get_n_last_stats(program_id, nb)
+    }
+
+    pub fn get_n_last_slots_stats(
+        &self,
+        program_id: &Pubkey,
+        nb_slots: u64,
+    ) -> (Slot, PrioFeesStats) {
+        self.program_store
+            .get_n_last_slots_stats(program_id, nb_slots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_lite_rpc_core::structures::produced_block::{TransactionInfo, TransactionMessage};
+    use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash, signature::Signature};
+
+    fn transaction(program_id: Pubkey, priority: u64) -> TransactionInfo {
+        TransactionInfo {
+            signature: Signature::new_unique(),
+            is_vote: false,
+            err: None,
+            cu_requested: None,
+            prioritization_fees: Some(priority),
+            cu_consumed: Some(1000),
+            recent_blockhash: Hash::default(),
+            message: TransactionMessage::default(),
+            writable_accounts: vec![],
+            readable_accounts: vec![],
+            address_lookup_tables: vec![],
+            program_ids: vec![program_id],
+        }
+    }
+
+    fn block(slot: Slot, transactions: Vec<TransactionInfo>) -> ProducedBlock {
+        ProducedBlock {
+            transactions,
+            ..ProducedBlock::new_for_tests(slot, CommitmentConfig::processed())
+        }
+    }
+
+    #[test]
+    fn test_latest_stats_are_from_the_latest_block() {
+        let program_id = Pubkey::new_unique();
+        let store = ProgramPrioStore::new(PrioFeesWindowConfig::default());
+        store.update(&block(10, vec![transaction(program_id, 100)]));
+        store.update(&block(11, vec![transaction(Pubkey::new_unique(), 200)]));
+
+        let (slot, stats) = store.get_n_last_stats(&program_id, 1);
+        assert_eq!(slot, 11);
+        assert_eq!(stats.tx_count.total, 0);
+    }
+
+    #[test]
+    fn test_last_n_blocks_count_all_blocks() {
+        let program_id = Pubkey::new_unique();
+        let store = ProgramPrioStore::new(PrioFeesWindowConfig::default());
+        store.update(&block(10, vec![transaction(program_id, 100)]));
+        store.update(&block(11, vec![]));
+        store.update(&block(12, vec![transaction(program_id, 300)]));
+        store.update(&block(14, vec![]));
+
+        // blocks 12 and 14, slot 13 was skipped
+        let (slot, stats) = store.get_n_last_stats(&program_id, 2);
+        assert_eq!(slot, 14);
+        assert_eq!(stats.tx_count.total, 1);
+
+        let (_, stats) = store.get_n_last_stats(&program_id, 4);
+        assert_eq!(stats.tx_count.total, 2);
+    }
+}
@@ -33,6 +33,145 @@ impl PrioFeesStats {
             None
         }
     }
+
+    pub fn summary(&self, slot: Slot) -> PrioFeesSummary {
+        let fees_at = |percentile: f32| self.get_percentile(percentile).unwrap_or_default();
+        let (p25, p50, p75, p90, p95) = (
+            fees_at(0.25),
+            fees_at(0.50),
+            fees_at(0.75),
+            fees_at(0.90),
+            fees_at(0.95),
+        );
+        PrioFeesSummary {
+            slot,
+            tx_count: self.tx_count.clone(),
+            cu_consumed: self.cu_consumed.clone(),
+            by_tx: FeePercentiles {
+                p25: p25.0,
+                p50: p50.0,
+                p75: p75.0,
+                p90: p90.0,
+                p95: p95.0,
+            },
+            by_cu: FeePercentiles {
+                p25: p25.1,
+                p50: p50.1,
+                p75: p75.1,
+                p90: p90.1,
+                p95: p95.1,
+            },
+        }
+    }
+}
+
+/// compute unit prices (in micro lamports) at fixed percentiles
+#[derive(Clone, Serialize, Debug, Default, Eq, PartialEq)]
+pub struct FeePercentiles {
+    pub p25: u64,
+    pub p50: u64,
+    pub p75: u64,
+    pub p90: u64,
+    pub p95: u64,
+}
+
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct LandedPrioFeesPercentiles {
+    pub tx_count: u64,
+    pub cu_consumed: u64,
+    pub by_tx: FeePercentiles,
+    pub by_cu: FeePercentiles,
+}
+
+/// fee percentiles over the last blocks, split by whether the transactions executed successfully
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct PrioFeesPercentiles {
+    pub nb_blocks: usize,
+    pub successful: LandedPrioFeesPercentiles,
+    pub failed: LandedPrioFeesPercentiles,
+}
+
+/// compact version of PrioFeesStats for a single block, total and nonvote split for counts and compute units
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct PrioFeesSummary {
+    pub slot: Slot,
+    pub tx_count: TxAggregateStats,
+    pub cu_consumed: TxAggregateStats,
+    pub by_tx: FeePercentiles,
+    pub by_cu: FeePercentiles,
+}
+
+/// compute units consumed by a block relative to the compute unit limit of a block
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct BlockUtilization {
+    pub slot: Slot,
+    pub cu_limit: u64,
+    pub cu_consumed: TxAggregateStats,
+    // share of the limit consumed by all transactions, votes included
+    pub fill_ratio: f64,
+    pub nonvote_fill_ratio: f64,
+}
+
+/// utilization of the last blocks, the blocks are ordered newest first
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct BlockUtilizationStats {
+    pub nb_blocks: usize,
+    pub avg_fill_ratio: f64,
+    pub max_fill_ratio: f64,
+    pub avg_nonvote_fill_ratio: f64,
+    pub blocks: Vec<BlockUtilization>,
+}
+
+impl BlockUtilizationStats {
+    pub fn from_blocks(blocks: Vec<BlockUtilization>) -> Self {
+        let nb_blocks = blocks.len();
+        let avg = |ratio: fn(&BlockUtilization) -> f64| {
+            if nb_blocks == 0 {
+                0.0
+            } else {
+                blocks.iter().map(ratio).sum::<f64>() / nb_blocks as f64
+            }
+        };
+        Self {
+            nb_blocks,
+            avg_fill_ratio: avg(|block| block.fill_ratio),
+            max_fill_ratio: blocks
+                .iter()
+                .map(|block| block.fill_ratio)
+                .fold(0.0, f64::max),
+            avg_nonvote_fill_ratio: avg(|block| block.nonvote_fill_ratio),
+            blocks,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct AccountWriteLockFee {
+    pub account: String,
+    // transactions write-locking the account in the lookback window
+    pub tx_count: u64,
+    pub cu_price: u64,
+}
+
+/// write-lock activity on an account over the last slots
+#[derive(Clone, Serialize, Debug)]
+pub struct AccountContention {
+    pub account: String,
+    // blocks in which the account was write-locked
+    pub nb_blocks: usize,
+    pub tx_count: u64,
+    pub cu_consumed: u64,
+    pub by_tx: FeePercentiles,
+}
+
+/// compute unit price recommended for a message based on the recent contention on its writable accounts
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct MessageFeeRecommendation {
+    pub recommended_cu_price: u64,
+    pub percentile: u32,
+    pub lookback_slots: u64,
+    // writable accounts of the message which were write-locked by other transactions in the lookback window
+    pub contended_accounts: Vec<AccountWriteLockFee>,
 }
 
 #[derive(Clone, Serialize, Debug, Eq, PartialEq, Hash)]
@@ -61,6 +200,13 @@ pub struct AccountPrioFeesStats {
     pub all_stats: PrioFeesStats,
 }
 
+/// fees paid by the transactions invoking a program next to the fees of all transactions in the same blocks
+#[derive(Clone, Serialize, Debug)]
+pub struct ProgramPrioFeesStats {
+    pub program_stats: PrioFeesStats,
+    pub market_stats: PrioFeesStats,
+}
+
 #[derive(Clone)]
 pub struct AccountPrioFeesUpdateMessage {
     pub slot: Slot,
@@ -1,4 +1,7 @@
-use crate::{prioritization_fee_data::PrioFeesData, rpc_data::FeePoint};
+use crate::{
+    prioritization_fee_data::PrioFeesData,
+    rpc_data::{FeePercentiles, FeePoint},
+};
 use itertools::Itertools;
 use std::iter::zip;
 
@@ -84,7 +87,6 @@ pub struct Percentiles {
     pub by_cu_percentiles: Vec<f32>,
 }
 
-#[allow(dead_code)]
 impl Percentiles {
     fn get_fees_by_tx(&self, percentile: f32) -> Option<u64> {
         zip(&self.by_tx_percentiles, &self.by_tx)
@@ -97,6 +99,25 @@ impl Percentiles {
             .find(|(&p, _cu)| p == percentile)
             .map(|(_p, &cu)| cu)
     }
+
+    fn fee_percentiles(get_fees: impl Fn(f32) -> Option<u64>) -> FeePercentiles {
+        // note: all requested percentiles are multiples of the 5% step used above
+        FeePercentiles {
+            p25: get_fees(0.25).unwrap_or_default(),
+            p50: get_fees(0.50).unwrap_or_default(),
+            p75: get_fees(0.75).unwrap_or_default(),
+            p90: get_fees(0.90).unwrap_or_default(),
+            p95: get_fees(0.95).unwrap_or_default(),
+        }
+    }
+
+    pub fn fee_percentiles_by_tx(&self) -> FeePercentiles {
+        Self::fee_percentiles(|percentile| self.get_fees_by_tx(percentile))
+    }
+
+    pub fn fee_percentiles_by_cu(&self) -> FeePercentiles {
+        Self::fee_percentiles(|percentile| self.get_fees_cu(percentile))
+    }
 }
 
 #[cfg(test)]
@@ -196,6 +217,17 @@ mod tests {
         assert_eq!(supp_info.by_cu[20], 10);
     }
 
+    #[test]
+    fn test_fee_percentiles() {
+        let prio_fees_in_block = (0..100).map(|x| PrioFeesData::from((x, 1))).collect_vec();
+        let fee_percentiles =
+            calculate_supp_percentiles(&prio_fees_in_block).fee_percentiles_by_tx();
+        assert_eq!(fee_percentiles.p25, 25);
+        assert_eq!(fee_percentiles.p50, 50);
+        assert_eq!(fee_percentiles.p90, 90);
+        assert_eq!(fee_percentiles.p95, 95);
+    }
+
     #[test]
     fn test_large_list() {
         let prio_fees_in_block = (0..1000).map(|x| PrioFeesData::from((x, x))).collect_vec();
@@ -40,6 +40,8 @@ use solana_lite_rpc_quic_forward_proxy::tls_self_signed_pair_generator::SelfSign
 use solana_lite_rpc_quic_forward_proxy::validator_identity::ValidatorIdentity;
 use solana_lite_rpc_services::quic_connection_utils::QuicConnectionParameters;
 use solana_lite_rpc_services::tpu_utils::quic_proxy_connection_manager::QuicProxyConnectionManager;
+use solana_lite_rpc_services::tpu_utils::send_pacer::SendPacingConfig;
+use solana_lite_rpc_services::tpu_utils::send_strategy::DefaultSendStrategy;
 use tracing_subscriber::fmt::format::FmtSpan;
 
 #[derive(Copy, Clone, Debug)]
@@ -60,6 +62,7 @@ const QUIC_CONNECTION_PARAMS: QuicConnectionParameters = QuicConnectionParameter
     unistream_timeout: Duration::from_secs(2),
     write_timeout: Duration::from_secs(2),
     number_of_transactions_per_unistream: 10,
+    send_pacing: SendPacingConfig::disabled(),
 };
 
 #[test]
@@ -519,6 +522,7 @@ async fn start_literpc_client_direct_mode(
             // note: tx_store is useless in this scenario as it is never changed; it's only used to check for duplicates
             DataCache::new_for_tests(),
             QUIC_CONNECTION_PARAMS,
+            &DefaultSendStrategy,
         )
         .await;
 
@@ -672,6 +676,7 @@ async fn start_quic_proxy(proxy_listen_addr: SocketAddr) -> anyhow::Result<()> {
         proxy_listen_addr,
         tls_config,
         random_unstaked_validator_identity,
+        None,
     )
     .await?
     .start_services();
@@ -745,10 +750,11 @@ pub fn build_raw_sample_tx(i: u32) -> SentTransactionInfo {
         bincode::serialize::<VersionedTransaction>(&tx).expect("failed to serialize tx");
 
     SentTransactionInfo {
-        signature: tx.get_signature().to_string(),
+        signature: *tx.get_signature(),
         slot: 1,
         transaction,
         last_valid_block_height: 300,
+        request_id: None,
     }
 }
 
@@ -8,4 +8,10 @@ pub struct Args {
     // e.g. 0.0.0.0:11111 or "localhost:11111"
     #[arg(short = 'l', long, env)]
     pub proxy_listen_addr: String,
+    // comma separated identities of the lite-rpc instances allowed to connect; empty allows any client
+    #[arg(short = 'a', long, env, value_delimiter = ',')]
+    pub allowed_clients: Vec<String>,
+    // rate limit applied to each connected client; unlimited if not set
+    #[arg(long, env)]
+    pub max_txs_per_second_per_client: Option<u64>,
 }
@@ -0,0 +1,177 @@
+use dashmap::{DashMap, DashSet};
+use log::info;
+use prometheus::{opts, register_int_counter_vec, IntCounterVec};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+lazy_static::lazy_static! {
+    static ref PROXY_CLIENT_FORWARDED_TXS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_proxy_client_forwarded_txs", "Transactions forwarded per client identity"), &["client"]).unwrap();
+    static ref PROXY_CLIENT_FORWARDED_BYTES: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_proxy_client_forwarded_bytes", "Transaction bytes forwarded per client identity"), &["client"]).unwrap();
+    static ref PROXY_CLIENT_ERRORS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_proxy_client_errors", "Failed proxy requests per client identity"), &["client"]).unwrap();
+    static ref PROXY_CLIENT_RATE_LIMITED_TXS: IntCounterVec =
+        register_int_counter_vec!(opts!("literpc_proxy_client_rate_limited_txs", "Transactions dropped by the per client rate limit"), &["client"]).unwrap();
+}
+
+// clients beyond this many share the `other` label, so unauthenticated clients cannot grow the metrics without bound
+const MAX_LABELED_CLIENTS: usize = 64;
+const OTHER_CLIENTS_LABEL: &str = "other";
+
+#[derive(Debug)]
+struct ClientStats {
+    forwarded_txs: u64,
+    forwarded_bytes: u64,
+    errors: u64,
+    rate_limited_txs: u64,
+    // token bucket for the rate limit
+    tokens: f64,
+    tokens_updated_at: Instant,
+}
+
+/// Tracks what every connected lite-rpc instance forwards through the proxy and enforces a per client rate limit.
+/// Clients are identified by the identity of their TLS client certificate or by their ip address if they did not present one.
+pub struct ClientAccounting {
+    clients: DashMap<String, ClientStats>,
+    labeled_clients: DashSet<String>,
+    max_txs_per_second: Option<u64>,
+}
+
+impl ClientAccounting {
+    pub fn new(max_txs_per_second_per_client: Option<u64>) -> Arc<Self> {
+        Arc::new(Self {
+            clients: DashMap::new(),
+            labeled_clients: DashSet::new(),
+            max_txs_per_second: max_txs_per_second_per_client.filter(|max| *max > 0),
+        })
+    }
+
+    /// returns how many of the transactions the client may forward right now; the rest is dropped
+    pub fn admit(&self, client: &str, nb_transactions: usize) -> usize {
+        let Some(max_txs_per_second) = self.max_txs_per_second else {
+            return nb_transactions;
+        };
+        let capacity = max_txs_per_second as f64;
+
+        let mut stats = self.client_stats(client, capacity);
+        let now = Instant::now();
+        let elapsed = now.duration_since(stats.tokens_updated_at).as_secs_f64();
+        stats.tokens = (stats.tokens + elapsed * capacity).min(capacity);
+        stats.tokens_updated_at = now;
+
+        let admitted = (stats.tokens.floor() as usize).min(nb_transactions);
+        stats.tokens -= admitted as f64;
+        let rate_limited = nb_transactions - admitted;
+        if rate_limited > 0 {
+            stats.rate_limited_txs += rate_limited as u64;
+            PROXY_CLIENT_RATE_LIMITED_TXS
+                .with_label_values(&[self.metric_label(client)])
+                .inc_by(rate_limited as u64);
+        }
+        admitted
+    }
+
+    pub fn record_forwarded(&self, client: &str, nb_transactions: usize, nb_bytes: usize) {
+        let mut stats = self.client_stats(client, 0.0);
+        stats.forwarded_txs += nb_transactions as u64;
+        stats.forwarded_bytes += nb_bytes as u64;
+        let label = self.metric_label(client);
+        PROXY_CLIENT_FORWARDED_TXS
+            .with_label_values(&[label])
+            .inc_by(nb_transactions as u64);
+        PROXY_CLIENT_FORWARDED_BYTES
+            .with_label_values(&[label])
+            .inc_by(nb_bytes as u64);
+    }
+
+    pub fn record_error(&self, client: &str) {
+        self.client_stats(client, 0.0).errors += 1;
+        PROXY_CLIENT_ERRORS
+            .with_label_values(&[self.metric_label(client)])
+            .inc();
+    }
+
+    /// the client itself for the first clients seen, `other` once the label limit is reached
+    fn metric_label<'a>(&self, client: &'a str) -> &'a str {
+        if self.labeled_clients.contains(client) {
+            return client;
+        }
+        if self.labeled_clients.len() < MAX_LABELED_CLIENTS {
+            self.labeled_clients.insert(client.to_string());
+            return client;
+        }
+        OTHER_CLIENTS_LABEL
+    }
+
+    fn client_stats(
+        &self,
+        client: &str,
+        initial_tokens: f64,
+    ) -> dashmap::mapref::one::RefMut<'_, String, ClientStats> {
+        self.clients
+            .entry(client.to_string())
+            .or_insert_with(|| ClientStats {
+                forwarded_txs: 0,
+                forwarded_bytes: 0,
+                errors: 0,
+                rate_limited_txs: 0,
+                tokens: initial_tokens,
+                tokens_updated_at: Instant::now(),
+            })
+    }
+
+    fn log_summary(&self) {
+        for entry in self.clients.iter() {
+            let stats = entry.value();
+            info!(
+                "client {}: forwarded {} txs ({} bytes), {} errors, {} rate limited",
+                entry.key(),
+                stats.forwarded_txs,
+                stats.forwarded_bytes,
+                stats.errors,
+                stats.rate_limited_txs
+            );
+        }
+    }
+
+    pub fn start_reporting(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let this = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                this.log_summary();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_admits_everything() {
+        let accounting = ClientAccounting::new(None);
+        assert_eq!(accounting.admit("client", 1000), 1000);
+    }
+
+    #[test]
+    fn rate_limit_is_per_client() {
+        let accounting = ClientAccounting::new(Some(10));
+        assert_eq!(accounting.admit("a", 8), 8);
+        assert_eq!(accounting.admit("a", 8), 2);
+        assert_eq!(accounting.admit("b", 8), 8);
+    }
+
+    #[test]
+    fn metric_labels_are_capped() {
+        let accounting = ClientAccounting::new(None);
+        for i in 0..MAX_LABELED_CLIENTS {
+            let client = format!("10.0.0.{i}");
+            assert_eq!(accounting.metric_label(&client), client);
+        }
+        assert_eq!(accounting.metric_label("10.0.1.1"), OTHER_CLIENTS_LABEL);
+        assert_eq!(accounting.metric_label("10.0.0.0"), "10.0.0.0");
+    }
+}
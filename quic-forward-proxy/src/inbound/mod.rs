@@ -1 +1,2 @@
+pub(crate) mod client_accounting;
 pub(crate) mod proxy_listener;
@@ -1,5 +1,6 @@
+use crate::inbound::client_accounting::ClientAccounting;
 use crate::proxy_request_format::TpuForwardingRequest;
-use crate::quic_util::connection_stats;
+use crate::quic_util::{connection_stats, get_client_identity};
 use crate::shared::ForwardPacket;
 use crate::tls_config_provider_server::ProxyTlsConfigProvider;
 use crate::tls_self_signed_pair_generator::SelfSignedTlsConfigProvider;
@@ -21,16 +22,19 @@ const MAX_CONCURRENT_UNI_STREAMS: u32 = 24;
 pub struct ProxyListener {
     tls_config: Arc<SelfSignedTlsConfigProvider>,
     proxy_listener_addr: SocketAddr,
+    client_accounting: Arc<ClientAccounting>,
 }
 
 impl ProxyListener {
     pub fn new(
         proxy_listener_addr: SocketAddr,
         tls_config: Arc<SelfSignedTlsConfigProvider>,
+        client_accounting: Arc<ClientAccounting>,
     ) -> Self {
         Self {
             proxy_listener_addr,
             tls_config,
+            client_accounting,
         }
     }
 
@@ -46,8 +50,15 @@ impl ProxyListener {
 
         while let Some(connecting) = endpoint.accept().await {
             let forwarder_channel_copy = forwarder_channel.clone();
+            let client_accounting = self.client_accounting.clone();
             tokio::spawn(async move {
-                match Self::handle_client_connection(connecting, forwarder_channel_copy).await {
+                match Self::handle_client_connection(
+                    connecting,
+                    forwarder_channel_copy,
+                    client_accounting,
+                )
+                .await
+                {
                     Ok(()) => {
                         debug!("connection handled correctly");
                     }
@@ -91,11 +102,17 @@ impl ProxyListener {
     async fn handle_client_connection(
         client_conn_handshake: Connecting,
         forwarder_channel: Sender<ForwardPacket>,
+        client_accounting: Arc<ClientAccounting>,
     ) -> anyhow::Result<()> {
         let client_connection = client_conn_handshake.await.context("handshake")?;
 
+        let client = match get_client_identity(&client_connection) {
+            Some(identity) => identity.to_string(),
+            None => client_connection.remote_address().ip().to_string(),
+        };
         debug!(
-            "inbound connection established, client {}",
+            "inbound connection established, client {} ({})",
+            client,
             client_connection.remote_address()
         );
 
@@ -104,12 +121,29 @@ impl ProxyListener {
             match maybe_stream {
                 Ok(mut recv_stream) => {
                     let forwarder_channel_copy = forwarder_channel.clone();
+                    let client_accounting = client_accounting.clone();
+                    let client = client.clone();
                     tokio::spawn(async move {
-                        let raw_request = recv_stream.read_to_end(10_000_000).await.unwrap();
+                        let raw_request = match recv_stream.read_to_end(10_000_000).await {
+                            Ok(raw_request) => raw_request,
+                            Err(err) => {
+                                warn!("failed to read request from client {}: {}", client, err);
+                                client_accounting.record_error(&client);
+                                return;
+                            }
+                        };
 
                         let proxy_request =
-                            TpuForwardingRequest::try_deserialize_from_wire_format(&raw_request)
-                                .unwrap();
+                            match TpuForwardingRequest::try_deserialize_from_wire_format(
+                                &raw_request,
+                            ) {
+                                Ok(proxy_request) => proxy_request,
+                                Err(err) => {
+                                    warn!("invalid request from client {}: {}", client, err);
+                                    client_accounting.record_error(&client);
+                                    return;
+                                }
+                            };
 
                         if proxy_request.get_tpu_nodes().is_empty() {
                             warn!("no tpu nodes in request - skip");
@@ -117,7 +151,14 @@ impl ProxyListener {
                         }
 
                         trace!("proxy request details: {}", proxy_request);
-                        let txs = proxy_request.get_transaction_bytes();
+                        let mut txs = proxy_request.get_transaction_bytes();
+                        let admitted = client_accounting.admit(&client, txs.len());
+                        if admitted == 0 {
+                            debug!("client {} exceeded its rate limit - skip", client);
+                            return;
+                        }
+                        txs.truncate(admitted);
+                        let nb_bytes = txs.iter().map(|tx| tx.len()).sum();
 
                         debug!(
                             "enqueue transaction batch of size {} to {} tpu nodes",
@@ -135,7 +176,7 @@ impl ProxyListener {
 
                         for tpu_node in proxy_request.get_tpu_nodes() {
                             let tpu_address = tpu_node.tpu_socket_addr;
-                            forwarder_channel_copy
+                            let result = forwarder_channel_copy
                                 .send_timeout(
                                     ForwardPacket::new(
                                         txs.clone(),
@@ -145,9 +186,14 @@ impl ProxyListener {
                                     FALLBACK_TIMEOUT,
                                 )
                                 .await
-                                .context("sending internal packet from proxy to forwarder")
-                                .unwrap();
+                                .context("sending internal packet from proxy to forwarder");
+                            if let Err(err) = result {
+                                error!("failed to forward request from client {}: {}", client, err);
+                                client_accounting.record_error(&client);
+                                return;
+                            }
                         }
+                        client_accounting.record_forwarded(&client, txs.len(), nb_bytes);
                     });
 
                     debug!(
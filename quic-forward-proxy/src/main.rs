@@ -1,11 +1,14 @@
 use crate::cli::Args;
 use crate::proxy::QuicForwardProxy;
 use crate::tls_self_signed_pair_generator::SelfSignedTlsConfigProvider;
-use anyhow::bail;
+use anyhow::{bail, Context};
 use clap::Parser;
 use dotenv::dotenv;
-use log::info;
+use log::{info, warn};
 use solana_lite_rpc_core::keypair_loader::load_identity_keypair;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::validator_identity::ValidatorIdentity;
@@ -31,6 +34,8 @@ pub async fn main() -> anyhow::Result<()> {
     let Args {
         identity_keypair,
         proxy_listen_addr,
+        allowed_clients,
+        max_txs_per_second_per_client,
     } = Args::parse();
     dotenv().ok();
 
@@ -38,10 +43,35 @@ pub async fn main() -> anyhow::Result<()> {
     let validator_identity =
         ValidatorIdentity::new(load_identity_keypair(Some(identity_keypair)).await?);
 
-    let tls_config = Arc::new(SelfSignedTlsConfigProvider::new_singleton_self_signed_localhost());
-    let main_services = QuicForwardProxy::new(proxy_listener_addr, tls_config, validator_identity)
-        .await?
-        .start_services();
+    let allowed_clients = allowed_clients
+        .iter()
+        .map(|client| client.trim())
+        .filter(|client| !client.is_empty())
+        .map(Pubkey::from_str)
+        .collect::<Result<HashSet<_>, _>>()
+        .context("parse allowed clients")?;
+    if allowed_clients.is_empty() {
+        warn!("No allowed clients configured - proxy accepts connections from any client");
+    } else {
+        info!(
+            "Proxy accepts connections from {} allowed clients",
+            allowed_clients.len()
+        );
+    }
+
+    let tls_config = Arc::new(
+        SelfSignedTlsConfigProvider::new_self_signed_localhost_with_allowed_clients(
+            allowed_clients,
+        ),
+    );
+    let main_services = QuicForwardProxy::new(
+        proxy_listener_addr,
+        tls_config,
+        validator_identity,
+        max_txs_per_second_per_client,
+    )
+    .await?
+    .start_services();
 
     let ctrl_c_signal = tokio::signal::ctrl_c();
 
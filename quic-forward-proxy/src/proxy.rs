@@ -4,18 +4,25 @@ use anyhow::bail;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
+use crate::inbound::client_accounting::ClientAccounting;
 use crate::inbound::proxy_listener;
 use crate::outbound::tx_forward::tx_forwarder;
 use crate::tls_self_signed_pair_generator::SelfSignedTlsConfigProvider;
 use crate::util::AnyhowJoinHandle;
 use crate::validator_identity::ValidatorIdentity;
 use log::info;
+use std::time::Duration;
+
+// interval for logging the per client statistics
+const CLIENT_STATS_REPORT_INTERVAL: Duration = Duration::from_secs(60);
 
 pub struct QuicForwardProxy {
     // endpoint: Endpoint,
     validator_identity: ValidatorIdentity,
     tls_config: Arc<SelfSignedTlsConfigProvider>,
     pub proxy_listener_addr: SocketAddr,
+    // None or 0 disables the rate limit
+    max_txs_per_second_per_client: Option<u64>,
 }
 
 impl QuicForwardProxy {
@@ -23,6 +30,7 @@ impl QuicForwardProxy {
         proxy_listener_addr: SocketAddr,
         tls_config: Arc<SelfSignedTlsConfigProvider>,
         validator_identity: ValidatorIdentity,
+        max_txs_per_second_per_client: Option<u64>,
     ) -> anyhow::Result<Self> {
         info!("Quic proxy uses validator identity {}", validator_identity);
 
@@ -30,6 +38,7 @@ impl QuicForwardProxy {
             proxy_listener_addr,
             validator_identity,
             tls_config,
+            max_txs_per_second_per_client,
         })
     }
 
@@ -38,8 +47,14 @@ impl QuicForwardProxy {
 
         let (forwarder_channel, forward_receiver) = tokio::sync::mpsc::channel(1000);
 
-        let proxy_listener =
-            proxy_listener::ProxyListener::new(self.proxy_listener_addr, self.tls_config);
+        let client_accounting = ClientAccounting::new(self.max_txs_per_second_per_client);
+        let client_stats_reporter = client_accounting.start_reporting(CLIENT_STATS_REPORT_INTERVAL);
+
+        let proxy_listener = proxy_listener::ProxyListener::new(
+            self.proxy_listener_addr,
+            self.tls_config,
+            client_accounting,
+        );
 
         let quic_proxy = tokio::spawn(async move {
             proxy_listener
@@ -63,6 +78,9 @@ impl QuicForwardProxy {
             res = forwarder => {
                 bail!("TPU Quic Tx forwarder exited unexpectedly {res:?}");
             },
+            res = client_stats_reporter => {
+                bail!("Client stats reporter exited unexpectedly {res:?}");
+            },
         }
     }
 }
@@ -1,4 +1,8 @@
 use quinn::Connection;
+use rustls::DistinguishedName;
+use solana_sdk::pubkey::Pubkey;
+use solana_streamer::tls_certificates::get_pubkey_from_tls_certificate;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 pub const ALPN_TPU_FORWARDPROXY_PROTOCOL_ID: &[u8] = b"solana-tpu-forward-proxy";
@@ -40,3 +44,57 @@ pub fn connection_stats(connection: &Connection) -> String {
         connection.stats().path.rtt
     )
 }
+
+/// Accepts only clients presenting a TLS certificate generated from one of the allowed identity keypairs
+/// (see solana new_self_signed_tls_certificate). The certificate is not chained to any CA;
+/// possession of the key is proven by the TLS handshake signature.
+/// With an empty allow list any client is accepted and the certificate is only used to identify the client.
+pub struct AllowedClientsVerifier {
+    allowed_clients: HashSet<Pubkey>,
+}
+
+impl AllowedClientsVerifier {
+    pub fn new(allowed_clients: HashSet<Pubkey>) -> Arc<Self> {
+        Arc::new(Self { allowed_clients })
+    }
+}
+
+impl rustls::server::ClientCertVerifier for AllowedClientsVerifier {
+    fn client_auth_mandatory(&self) -> bool {
+        !self.allowed_clients.is_empty()
+    }
+
+    fn client_auth_root_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _now: std::time::SystemTime,
+    ) -> Result<rustls::server::ClientCertVerified, rustls::Error> {
+        if self.allowed_clients.is_empty() {
+            return Ok(rustls::server::ClientCertVerified::assertion());
+        }
+        let client_identity = get_pubkey_from_tls_certificate(end_entity).ok_or_else(|| {
+            rustls::Error::General("client certificate does not contain an identity".to_string())
+        })?;
+        if !self.allowed_clients.contains(&client_identity) {
+            return Err(rustls::Error::General(format!(
+                "client identity {} is not allowed",
+                client_identity
+            )));
+        }
+        Ok(rustls::server::ClientCertVerified::assertion())
+    }
+}
+
+pub fn get_client_identity(connection: &Connection) -> Option<Pubkey> {
+    connection
+        .peer_identity()?
+        .downcast::<Vec<rustls::Certificate>>()
+        .ok()?
+        .first()
+        .and_then(get_pubkey_from_tls_certificate)
+}
@@ -1,8 +1,12 @@
-use crate::quic_util::{SkipServerVerification, ALPN_TPU_FORWARDPROXY_PROTOCOL_ID};
+use crate::quic_util::{
+    AllowedClientsVerifier, SkipServerVerification, ALPN_TPU_FORWARDPROXY_PROTOCOL_ID,
+};
 use crate::tls_config_provider_client::TpuClientTlsConfigProvider;
 use crate::tls_config_provider_server::ProxyTlsConfigProvider;
 use rcgen::generate_simple_self_signed;
 use rustls::{Certificate, ClientConfig, PrivateKey, ServerConfig};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
 
 impl ProxyTlsConfigProvider for SelfSignedTlsConfigProvider {
     fn get_server_tls_crypto_config(&self) -> ServerConfig {
@@ -23,10 +27,18 @@ pub struct SelfSignedTlsConfigProvider {
 
 impl SelfSignedTlsConfigProvider {
     pub fn new_singleton_self_signed_localhost() -> Self {
+        Self::new_self_signed_localhost_with_allowed_clients(HashSet::new())
+    }
+
+    /// only clients authenticating with a certificate of one of the given identities are accepted;
+    /// an empty set accepts any client
+    pub fn new_self_signed_localhost_with_allowed_clients(
+        allowed_clients: HashSet<Pubkey>,
+    ) -> Self {
         // note: this check could be relaxed when you know what you are doing!
         let hostnames = vec!["localhost".to_string()];
         let (certificate, private_key) = Self::gen_tls_certificate_and_key(hostnames);
-        let server_crypto = Self::build_server_crypto(certificate, private_key);
+        let server_crypto = Self::build_server_crypto(certificate, private_key, allowed_clients);
         Self {
             client_crypto: Self::build_client_crypto_insecure(),
             server_crypto,
@@ -50,13 +62,14 @@ impl SelfSignedTlsConfigProvider {
         client_crypto
     }
 
-    fn build_server_crypto(server_cert: Certificate, server_key: PrivateKey) -> ServerConfig {
-        // let (server_cert, server_key) = gen_tls_certificate_and_key();
-
+    fn build_server_crypto(
+        server_cert: Certificate,
+        server_key: PrivateKey,
+        allowed_clients: HashSet<Pubkey>,
+    ) -> ServerConfig {
         let mut server_crypto = rustls::ServerConfig::builder()
-            // FIXME we want client auth
             .with_safe_defaults()
-            .with_no_client_auth()
+            .with_client_cert_verifier(AllowedClientsVerifier::new(allowed_clients))
             .with_single_cert(vec![server_cert], server_key)
             .unwrap();
         server_crypto.alpn_protocols = vec![ALPN_TPU_FORWARDPROXY_PROTOCOL_ID.to_vec()];
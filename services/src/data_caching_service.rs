@@ -1,16 +1,22 @@
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 use anyhow::{bail, Context};
 use prometheus::core::GenericGauge;
 use prometheus::{opts, register_int_counter, register_int_gauge, IntCounter};
 use solana_lite_rpc_core::stores::{
-    block_information_store::BlockInformation, data_cache::DataCache,
+    block_information_store::BlockInformation, data_cache::DataCache, tx_store::TxStatusUpdate,
 };
+use solana_lite_rpc_core::structures::produced_block::{ProducedBlock, TransactionInfo};
+use solana_lite_rpc_core::structures::slot_notification::SlotNotification;
 use solana_lite_rpc_core::types::{BlockStream, ClusterInfoStream, SlotStream, VoteAccountStream};
 use solana_lite_rpc_core::AnyhowJoinHandle;
-use solana_sdk::clock::MAX_RECENT_BLOCKHASHES;
+use solana_sdk::clock::{Slot, DEFAULT_MS_PER_SLOT, MAX_RECENT_BLOCKHASHES};
 use solana_sdk::commitment_config::CommitmentLevel;
+use solana_sdk::hash::Hash;
 use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{timeout, Instant};
 
 lazy_static::lazy_static! {
     static ref NB_CLUSTER_NODES: GenericGauge<prometheus::core::AtomicI64> =
@@ -30,14 +36,133 @@ lazy_static::lazy_static! {
 
     static ref TXS_PROCESSED: IntCounter =
     register_int_counter!(opts!("literpc_txs_processed", "Number of Transactions Processed")).unwrap();
+
+    static ref EXTRAPOLATED_SLOTS: IntCounter =
+    register_int_counter!(opts!("literpc_extrapolated_slots", "Number of times the estimated slot advanced while the slot stream stalled")).unwrap();
 }
 
+const SLOT_DURATION: Duration = Duration::from_millis(DEFAULT_MS_PER_SLOT);
+// the slot stream is considered stalled once no new slot arrived for that long, slots arrive with jitter
+const SLOT_STALL_DURATION: Duration = Duration::from_millis(2 * DEFAULT_MS_PER_SLOT);
+// the estimated slot is not extrapolated further ahead of the newest received slot,
+// the sources could be right and the cluster stalled
+const MAX_EXTRAPOLATED_SLOTS: u64 = 32;
+// the block loop beats at least this often while waiting for blocks
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct DataCachingService {
     pub data_cache: DataCache,
     pub clean_duration: Duration,
+    /// how long the statuses of finalized transactions are kept
+    pub tx_status_retention: Duration,
+    /// blocks confirmed by the observed votes, applied like the confirmed blocks of the sources
+    pub optimistically_confirmed_blocks: Option<BlockStream>,
+}
+
+fn last_valid_blockheight(data_cache: &DataCache, tx: &TransactionInfo, block_height: u64) -> u64 {
+    match data_cache.blockhash_cache.get(&tx.recent_blockhash) {
+        Some(blockhash_info) => blockhash_info.last_valid_block_height,
+        None => block_height + MAX_RECENT_BLOCKHASHES as u64,
+    }
+}
+
+// the status updates of all transactions of a block, applied to the tx store at once
+fn tx_status_updates(
+    data_cache: &DataCache,
+    block: &ProducedBlock,
+    confirmation_status: TransactionConfirmationStatus,
+) -> Vec<TxStatusUpdate> {
+    let mut updates = Vec::with_capacity(block.transactions.len());
+    for tx in &block.transactions {
+        updates.push(TxStatusUpdate {
+            signature: tx.signature,
+            status: TransactionStatus {
+                slot: block.slot,
+                confirmations: None,
+                status: tx.err.clone().map_or(Ok(()), Err),
+                err: tx.err.clone(),
+                confirmation_status: Some(confirmation_status.clone()),
+            },
+            last_valid_blockheight: last_valid_blockheight(data_cache, tx, block.block_height),
+        });
+    }
+    updates
+}
+
+async fn process_block(data_cache: &DataCache, block: &ProducedBlock) {
+    // the blockhash is known before the block becomes the latest block
+    data_cache.blockhash_cache.add_block(block);
+    data_cache
+        .block_information_store
+        .add_block(BlockInformation::from_block(block))
+        .await;
+    data_cache.block_cache.insert(block.clone());
+
+    let confirmation_status = match block.commitment_config.commitment {
+        CommitmentLevel::Finalized => TransactionConfirmationStatus::Finalized,
+        CommitmentLevel::Confirmed => TransactionConfirmationStatus::Confirmed,
+        _ => TransactionConfirmationStatus::Processed,
+    };
+
+    let updated = data_cache.txs.update_statuses(tx_status_updates(
+        data_cache,
+        block,
+        confirmation_status.clone(),
+    )) as u64;
+    data_cache.tx_lifecycles.record_landed(
+        block.slot,
+        &confirmation_status,
+        block
+            .transactions
+            .iter()
+            .map(|tx| (&tx.signature, tx.err.is_some())),
+    );
+    // transactions sent by lite-rpc updated
+    match confirmation_status {
+        TransactionConfirmationStatus::Finalized => {
+            TXS_FINALIZED.inc_by(updated);
+        }
+        TransactionConfirmationStatus::Confirmed => {
+            TXS_CONFIRMED.inc_by(updated);
+        }
+        TransactionConfirmationStatus::Processed => {
+            TXS_PROCESSED.inc_by(updated);
+        }
+    }
+
+    for tx in &block.transactions {
+        // notify
+        data_cache
+            .tx_subs
+            .notify(block.slot, tx, block.commitment_config)
+            .await;
+    }
 }
 
 impl DataCachingService {
+    /// Restores the blockhashes, transaction statuses and cached blocks of blocks loaded from storage
+    /// before the services start, so recent blockhashes are known right after a restart.
+    pub async fn warm_start(&self, blocks: &[ProducedBlock]) {
+        let data_cache = &self.data_cache;
+        for block in blocks {
+            // the blockhash is known before the block becomes the latest block
+            data_cache.blockhash_cache.add_block(block);
+            data_cache
+                .block_information_store
+                .add_block(BlockInformation::from_block(block))
+                .await;
+            data_cache.block_cache.insert(block.clone());
+        }
+        // blockhashes of all loaded blocks are known now
+        for block in blocks {
+            data_cache.txs.update_statuses(tx_status_updates(
+                data_cache,
+                block,
+                TransactionConfirmationStatus::Finalized,
+            ));
+        }
+    }
+
     pub fn listen(
         self,
         block_notifier: BlockStream,
@@ -47,80 +172,102 @@ impl DataCachingService {
     ) -> Vec<AnyhowJoinHandle> {
         // clone the ledger to move into the processor task
         let data_cache = self.data_cache.clone();
+        let mut optimistically_confirmed_blocks = self.optimistically_confirmed_blocks;
         // process all the data into the ledger
-        let block_cache_jh = tokio::spawn(async move {
+        let block_cache_jh: AnyhowJoinHandle = tokio::spawn(async move {
             let mut block_notifier = block_notifier;
+            // blocks confirmed by the votes, not applied again once a source confirms them
+            let mut optimistically_confirmed: BTreeMap<Slot, Hash> = BTreeMap::new();
             loop {
-                let block = block_notifier.recv().await.expect("Should recv blocks");
-
-                data_cache
-                    .block_information_store
-                    .add_block(BlockInformation::from_block(&block))
-                    .await;
-
-                let confirmation_status = match block.commitment_config.commitment {
-                    CommitmentLevel::Finalized => TransactionConfirmationStatus::Finalized,
-                    CommitmentLevel::Confirmed => TransactionConfirmationStatus::Confirmed,
-                    _ => TransactionConfirmationStatus::Processed,
+                data_cache.heartbeats.blocks.beat();
+                let optimistically_confirmed_block = async {
+                    match optimistically_confirmed_blocks.as_mut() {
+                        Some(blocks) => blocks.recv().await,
+                        None => std::future::pending().await,
+                    }
                 };
-
-                for tx in block.transactions {
-                    let block_info = data_cache
-                        .block_information_store
-                        .get_block_info(&tx.recent_blockhash);
-                    let last_valid_blockheight = if let Some(block_info) = block_info {
-                        block_info.last_valid_blockheight
-                    } else {
-                        block.block_height + MAX_RECENT_BLOCKHASHES as u64
-                    };
-
-                    if data_cache.txs.update_status(
-                        &tx.signature,
-                        TransactionStatus {
-                            slot: block.slot,
-                            confirmations: None,
-                            status: tx.err.clone().map_or(Ok(()), Err),
-                            err: tx.err.clone(),
-                            confirmation_status: Some(confirmation_status.clone()),
-                        },
-                        last_valid_blockheight,
-                    ) {
-                        // transaction updated
-                        match confirmation_status {
-                            TransactionConfirmationStatus::Finalized => {
-                                TXS_FINALIZED.inc();
-                            }
-                            TransactionConfirmationStatus::Confirmed => {
-                                TXS_CONFIRMED.inc();
-                            }
-                            TransactionConfirmationStatus::Processed => {
-                                TXS_PROCESSED.inc();
-                            }
+                let block = tokio::select! {
+                    block = block_notifier.recv() => block.expect("Should recv blocks"),
+                    block = optimistically_confirmed_block => match block {
+                        Ok(block) => {
+                            optimistically_confirmed.insert(block.slot, block.blockhash);
+                            process_block(&data_cache, &block).await;
+                            continue;
+                        }
+                        Err(RecvError::Lagged(lagged)) => {
+                            log::warn!("Optimistically confirmed blocks lagged by {lagged} blocks");
+                            continue;
                         }
+                        Err(RecvError::Closed) => bail!("optimistically confirmed blocks closed"),
+                    },
+                    // no block but the loop is alive
+                    _ = tokio::time::sleep(HEARTBEAT_INTERVAL) => continue,
+                };
+                match block.commitment_config.commitment {
+                    CommitmentLevel::Confirmed
+                        if optimistically_confirmed.get(&block.slot) == Some(&block.blockhash) =>
+                    {
+                        continue;
+                    }
+                    CommitmentLevel::Finalized => {
+                        optimistically_confirmed =
+                            optimistically_confirmed.split_off(&(block.slot + 1));
                     }
-                    // notify
-                    data_cache
-                        .tx_subs
-                        .notify(block.slot, &tx, block.commitment_config)
-                        .await;
+                    _ => {}
                 }
+                process_block(&data_cache, &block).await;
             }
         });
 
         let data_cache = self.data_cache.clone();
         let slot_cache_jh = tokio::spawn(async move {
             let mut slot_notification = slot_notification;
+            let mut latest = SlotNotification {
+                processed_slot: data_cache.slot_cache.get_current_slot(),
+                estimated_processed_slot: data_cache.slot_cache.get_estimated_slot(),
+            };
+            let mut received_at = Instant::now();
+            let mut next_estimation_at = received_at + SLOT_STALL_DURATION;
             loop {
-                match slot_notification.recv().await {
-                    Ok(slot_notification) => {
-                        CURRENT_SLOT.set(slot_notification.processed_slot as i64);
-                        ESTIMATED_SLOT.set(slot_notification.estimated_processed_slot as i64);
-                        data_cache.slot_cache.update(slot_notification);
+                let estimated_slot = tokio::select! {
+                    notification = slot_notification.recv() => {
+                        let notification = match notification {
+                            Ok(notification) => notification,
+                            Err(e) => bail!("Error in slot notification {e:?}"),
+                        };
+                        if notification.processed_slot > latest.processed_slot {
+                            received_at = Instant::now();
+                            next_estimation_at = received_at + SLOT_STALL_DURATION;
+                        }
+                        // an extrapolated slot is kept until the sources catch up with it
+                        let extrapolated_slot = latest
+                            .estimated_processed_slot
+                            .min(notification.processed_slot + MAX_EXTRAPOLATED_SLOTS);
+                        latest.processed_slot = notification.processed_slot;
+                        notification.estimated_processed_slot.max(extrapolated_slot)
                     }
-                    Err(e) => {
-                        bail!("Error in slot notification {e:?}");
+                    // the sources lag, the cluster keeps producing slots at the usual pace
+                    _ = tokio::time::sleep_until(next_estimation_at) => {
+                        next_estimation_at += SLOT_DURATION;
+                        let elapsed_slots =
+                            (received_at.elapsed().as_millis() / SLOT_DURATION.as_millis()) as u64;
+                        let extrapolated_slot =
+                            latest.processed_slot + elapsed_slots.min(MAX_EXTRAPOLATED_SLOTS);
+                        if extrapolated_slot.max(data_cache.slot_cache.get_reference_slot())
+                            <= latest.estimated_processed_slot
+                        {
+                            continue;
+                        }
+                        EXTRAPOLATED_SLOTS.inc();
+                        extrapolated_slot
                     }
-                }
+                };
+                // the reference rpc node is ahead of the sources
+                latest.estimated_processed_slot =
+                    estimated_slot.max(data_cache.slot_cache.get_reference_slot());
+                CURRENT_SLOT.set(latest.processed_slot as i64);
+                ESTIMATED_SLOT.set(latest.estimated_processed_slot as i64);
+                data_cache.slot_cache.update(latest.clone());
             }
         });
 
@@ -153,11 +300,12 @@ impl DataCachingService {
 
         let data_cache: DataCache = self.data_cache;
         let clean_ttl = self.clean_duration;
+        let tx_status_retention = self.tx_status_retention;
         let cleaning_service = tokio::spawn(async move {
             loop {
                 // clean frequency 1min
                 tokio::time::sleep(Duration::from_secs(60)).await;
-                data_cache.clean(clean_ttl).await;
+                data_cache.clean(clean_ttl, tx_status_retention).await;
             }
         });
         vec![
@@ -169,3 +317,81 @@ impl DataCachingService {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::broadcast;
+
+    use super::*;
+
+    fn slot_notification(slot: u64) -> SlotNotification {
+        SlotNotification {
+            processed_slot: slot,
+            estimated_processed_slot: slot,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_estimated_slot_is_extrapolated_while_the_sources_stall() {
+        let data_cache = DataCache::new_for_tests();
+        let (block_sender, block_notifier) = broadcast::channel(8);
+        let (slot_sender, slot_notifier) = broadcast::channel(8);
+        let (cluster_info_sender, cluster_info_notifier) = broadcast::channel(8);
+        let (va_sender, va_notifier) = broadcast::channel(8);
+        let _tasks = DataCachingService {
+            data_cache: data_cache.clone(),
+            clean_duration: Duration::from_secs(120),
+            tx_status_retention: Duration::from_secs(120),
+            optimistically_confirmed_blocks: None,
+        }
+        .listen(
+            block_notifier,
+            slot_notifier,
+            cluster_info_notifier,
+            va_notifier,
+        );
+        let slot_cache = &data_cache.slot_cache;
+
+        slot_sender.send(slot_notification(100)).unwrap();
+        // slots arrive with jitter, the stream is not stalled before the threshold
+        tokio::time::sleep(SLOT_STALL_DURATION - Duration::from_millis(10)).await;
+        assert_eq!(slot_cache.get_current_slot(), 100);
+        assert_eq!(slot_cache.get_estimated_slot(), 100);
+
+        // stalled, the slots elapsed since the last received one are added
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(slot_cache.get_current_slot(), 100);
+        assert_eq!(slot_cache.get_estimated_slot(), 102);
+        tokio::time::sleep(SLOT_DURATION).await;
+        assert_eq!(slot_cache.get_estimated_slot(), 103);
+
+        // not further than the cap ahead of the received slot
+        tokio::time::sleep(SLOT_DURATION * 40).await;
+        assert_eq!(
+            slot_cache.get_estimated_slot(),
+            100 + MAX_EXTRAPOLATED_SLOTS
+        );
+
+        // the extrapolated slot is held until the sources catch up
+        slot_sender.send(slot_notification(110)).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(slot_cache.get_current_slot(), 110);
+        assert_eq!(
+            slot_cache.get_estimated_slot(),
+            100 + MAX_EXTRAPOLATED_SLOTS
+        );
+        // extrapolated from the new slot, which is still behind the held one
+        tokio::time::sleep(SLOT_STALL_DURATION).await;
+        assert_eq!(
+            slot_cache.get_estimated_slot(),
+            100 + MAX_EXTRAPOLATED_SLOTS
+        );
+
+        slot_sender.send(slot_notification(140)).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(slot_cache.get_current_slot(), 140);
+        assert_eq!(slot_cache.get_estimated_slot(), 140);
+
+        drop((block_sender, cluster_info_sender, va_sender));
+    }
+}
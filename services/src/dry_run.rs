@@ -0,0 +1,66 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+};
+
+use solana_lite_rpc_core::request_id::current_api_key_id;
+
+/// Sending mode in which the transactions are validated, their fee estimated and their leaders
+/// resolved but never forwarded, either for all transactions or only for the requests
+/// authenticated with one of the dry-run api keys.
+#[derive(Clone, Default)]
+pub struct DryRun {
+    global: bool,
+    // api key ids
+    keys: Arc<RwLock<HashSet<String>>>,
+}
+
+impl DryRun {
+    pub fn new(global: bool) -> Self {
+        Self {
+            global,
+            keys: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+
+    /// replaces the dry-run api keys, called when the api keys are reloaded
+    pub fn set_keys(&self, keys: HashSet<String>) {
+        *self.keys.write().unwrap() = keys;
+    }
+
+    /// true if the transactions of the current request are not forwarded
+    pub fn is_active(&self) -> bool {
+        self.global
+            || current_api_key_id()
+                .is_some_and(|api_key_id| self.keys.read().unwrap().contains(&api_key_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_lite_rpc_core::request_id::with_api_key_id;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dry_run_of_the_api_keys() {
+        let dry_run = DryRun::new(false);
+        dry_run.set_keys(HashSet::from(["staging".to_string()]));
+
+        assert!(!dry_run.is_active());
+        assert!(with_api_key_id("staging".to_string(), async { dry_run.is_active() }).await);
+        assert!(!with_api_key_id("production".to_string(), async { dry_run.is_active() }).await);
+
+        // reloaded keys replace the previous ones
+        dry_run.set_keys(HashSet::from(["production".to_string()]));
+        assert!(!with_api_key_id("staging".to_string(), async { dry_run.is_active() }).await);
+        assert!(with_api_key_id("production".to_string(), async { dry_run.is_active() }).await);
+    }
+
+    #[tokio::test]
+    async fn test_global_dry_run() {
+        let dry_run = DryRun::new(true);
+        assert!(dry_run.is_active());
+        assert!(with_api_key_id("production".to_string(), async { dry_run.is_active() }).await);
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use prometheus::{opts, register_int_counter_vec, IntCounterVec};
+use serde::{Deserialize, Serialize};
+use solana_lite_rpc_core::request_id::current_api_key_id;
+use solana_sdk::{
+    borsh0_10::try_from_slice_unchecked,
+    compute_budget::{self, ComputeBudgetInstruction},
+    transaction::VersionedTransaction,
+};
+
+lazy_static::lazy_static! {
+    static ref FEE_GUARDRAIL_VIOLATIONS: IntCounterVec =
+    register_int_counter_vec!(opts!("literpc_fee_guardrail_violations", "Sent transactions exceeding a fee or compute unit limit, by limit and action"), &["limit", "action"]).unwrap();
+}
+
+// compute units of every instruction without a compute unit limit instruction, as in the runtime
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailAction {
+    /// the transaction is not sent
+    #[default]
+    Reject,
+    /// the transaction is sent and the violation is logged
+    Warn,
+}
+
+/// upper bounds of the fees and compute units of sent transactions, unset limits are not checked
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeLimits {
+    /// in micro-lamports per compute unit
+    #[serde(default)]
+    pub max_compute_unit_price: Option<u64>,
+    /// compute unit price times the compute unit limit, in lamports
+    #[serde(default)]
+    pub max_priority_fee_lamports: Option<u64>,
+    #[serde(default)]
+    pub max_compute_units: Option<u32>,
+    #[serde(default)]
+    pub action: GuardrailAction,
+}
+
+impl FeeLimits {
+    pub fn is_empty(&self) -> bool {
+        self.max_compute_unit_price.is_none()
+            && self.max_priority_fee_lamports.is_none()
+            && self.max_compute_units.is_none()
+    }
+}
+
+/// limits of an api key, the fields which are set override the ones of the default limits
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeLimitOverrides {
+    #[serde(default)]
+    pub max_compute_unit_price: Option<u64>,
+    #[serde(default)]
+    pub max_priority_fee_lamports: Option<u64>,
+    #[serde(default)]
+    pub max_compute_units: Option<u32>,
+    #[serde(default)]
+    pub action: Option<GuardrailAction>,
+}
+
+impl FeeLimitOverrides {
+    pub fn apply_to(&self, limits: &FeeLimits) -> FeeLimits {
+        FeeLimits {
+            max_compute_unit_price: self
+                .max_compute_unit_price
+                .or(limits.max_compute_unit_price),
+            max_priority_fee_lamports: self
+                .max_priority_fee_lamports
+                .or(limits.max_priority_fee_lamports),
+            max_compute_units: self.max_compute_units.or(limits.max_compute_units),
+            action: self.action.unwrap_or(limits.action),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum FeeLimitExceeded {
+    #[error("compute unit price of {price} micro-lamports exceeds the limit of {max}")]
+    ComputeUnitPrice { price: u64, max: u64 },
+    #[error("priority fee of {fee} lamports exceeds the limit of {max}")]
+    PriorityFee { fee: u64, max: u64 },
+    #[error("{units} requested compute units exceed the limit of {max}")]
+    ComputeUnits { units: u32, max: u32 },
+}
+
+impl FeeLimitExceeded {
+    fn limit(&self) -> &'static str {
+        match self {
+            Self::ComputeUnitPrice { .. } => "compute_unit_price",
+            Self::PriorityFee { .. } => "priority_fee",
+            Self::ComputeUnits { .. } => "compute_units",
+        }
+    }
+}
+
+/// Limits on the compute unit price, the priority fee and the requested compute units of the sent
+/// transactions, protecting clients from fat-fingered fee parameters. The limits of an api key
+/// override single default limits for the requests authenticated with it.
+#[derive(Clone, Default)]
+pub struct FeeGuardrails {
+    default_limits: FeeLimits,
+    // api key id -> limits
+    limits_by_key: Arc<RwLock<HashMap<String, FeeLimitOverrides>>>,
+}
+
+impl FeeGuardrails {
+    pub fn new(default_limits: FeeLimits) -> Self {
+        Self {
+            default_limits,
+            limits_by_key: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// replaces the limits of all api keys, called when the api keys are reloaded
+    pub fn set_key_limits(&self, limits_by_key: HashMap<String, FeeLimitOverrides>) {
+        *self.limits_by_key.write().unwrap() = limits_by_key;
+    }
+
+    pub fn limits(&self, api_key_id: Option<&str>) -> FeeLimits {
+        match api_key_id
+            .and_then(|api_key_id| self.limits_by_key.read().unwrap().get(api_key_id).cloned())
+        {
+            Some(overrides) => overrides.apply_to(&self.default_limits),
+            None => self.default_limits.clone(),
+        }
+    }
+
+    /// checks the transaction against the limits of the api key of the current request
+    pub fn check(&self, tx: &VersionedTransaction) -> Result<(), FeeLimitExceeded> {
+        let api_key_id = current_api_key_id();
+        let limits = self.limits(api_key_id.as_deref());
+        if limits.is_empty() {
+            return Ok(());
+        }
+        let Err(violation) = check_limits(tx, &limits) else {
+            return Ok(());
+        };
+        match limits.action {
+            GuardrailAction::Reject => {
+                FEE_GUARDRAIL_VIOLATIONS
+                    .with_label_values(&[violation.limit(), "reject"])
+                    .inc();
+                Err(violation)
+            }
+            GuardrailAction::Warn => {
+                FEE_GUARDRAIL_VIOLATIONS
+                    .with_label_values(&[violation.limit(), "warn"])
+                    .inc();
+                log::warn!(
+                    "Transaction {} of api key {} sent anyway: {violation}",
+                    tx.signatures[0],
+                    api_key_id.as_deref().unwrap_or("-")
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+fn check_limits(tx: &VersionedTransaction, limits: &FeeLimits) -> Result<(), FeeLimitExceeded> {
+    let (compute_units, compute_unit_price, priority_fee) = compute_budget(tx);
+    if let Some(max) = limits.max_compute_units.filter(|max| compute_units > *max) {
+        return Err(FeeLimitExceeded::ComputeUnits {
+            units: compute_units,
+            max,
+        });
+    }
+    if let Some(max) = limits
+        .max_compute_unit_price
+        .filter(|max| compute_unit_price > *max)
+    {
+        return Err(FeeLimitExceeded::ComputeUnitPrice {
+            price: compute_unit_price,
+            max,
+        });
+    }
+    if let Some(max) = limits
+        .max_priority_fee_lamports
+        .filter(|max| priority_fee > *max)
+    {
+        return Err(FeeLimitExceeded::PriorityFee {
+            fee: priority_fee,
+            max,
+        });
+    }
+    Ok(())
+}
+
+/// signature fees plus the priority fee of the transaction, in lamports
+pub fn estimated_fee(tx: &VersionedTransaction) -> u64 {
+    let (_, _, priority_fee) = compute_budget(tx);
+    let signatures = tx.message.header().num_required_signatures as u64;
+    (signatures * LAMPORTS_PER_SIGNATURE).saturating_add(priority_fee)
+}
+
+// requested compute units, compute unit price and priority fee in lamports
+fn compute_budget(tx: &VersionedTransaction) -> (u32, u64, u64) {
+    let account_keys = tx.message.static_account_keys();
+    let mut compute_unit_limit = None;
+    let mut compute_unit_price = 0u64;
+    let mut legacy_additional_fee = None;
+    let mut other_instructions = 0u32;
+    for instruction in tx.message.instructions() {
+        if *instruction.program_id(account_keys) != compute_budget::id() {
+            other_instructions += 1;
+            continue;
+        }
+        match try_from_slice_unchecked(&instruction.data) {
+            Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => {
+                compute_unit_limit = Some(limit)
+            }
+            Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => compute_unit_price = price,
+            Ok(ComputeBudgetInstruction::RequestUnitsDeprecated {
+                units,
+                additional_fee,
+            }) => {
+                compute_unit_limit = Some(units);
+                legacy_additional_fee = Some(additional_fee as u64);
+            }
+            _ => {}
+        }
+    }
+
+    let compute_units = compute_unit_limit
+        .unwrap_or(other_instructions.saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT))
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    let priority_fee = match legacy_additional_fee {
+        Some(additional_fee) => additional_fee,
+        None => (compute_unit_price as u128 * compute_units as u128)
+            .div_ceil(MICRO_LAMPORTS_PER_LAMPORT)
+            .min(u64::MAX as u128) as u64,
+    };
+    (compute_units, compute_unit_price, priority_fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_lite_rpc_core::request_id::with_api_key_id;
+    use solana_sdk::{
+        instruction::Instruction, pubkey::Pubkey, system_instruction, transaction::Transaction,
+    };
+
+    use super::*;
+
+    fn transaction(instructions: &[Instruction], payer: &Pubkey) -> VersionedTransaction {
+        Transaction::new_with_payer(instructions, Some(payer)).into()
+    }
+
+    #[test]
+    fn test_estimated_fee() {
+        let payer = Pubkey::new_unique();
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+
+        // no priority fee, only the signature
+        assert_eq!(
+            estimated_fee(&transaction(&[transfer.clone()], &payer)),
+            5_000
+        );
+
+        // 10_000 micro-lamports for each of 300_000 compute units
+        let tx = transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+                ComputeBudgetInstruction::set_compute_unit_price(10_000),
+                transfer.clone(),
+            ],
+            &payer,
+        );
+        assert_eq!(estimated_fee(&tx), 5_000 + 3_000);
+
+        // every signer pays for its signature
+        let other_signer = Pubkey::new_unique();
+        let tx = transaction(
+            &[
+                transfer,
+                system_instruction::transfer(&other_signer, &payer, 1),
+            ],
+            &payer,
+        );
+        assert_eq!(estimated_fee(&tx), 2 * 5_000);
+    }
+
+    #[test]
+    fn test_compute_budget() {
+        let payer = Pubkey::new_unique();
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+
+        // the default limit per instruction, compute budget instructions do not count
+        let tx = transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_price(1),
+                transfer.clone(),
+                transfer.clone(),
+            ],
+            &payer,
+        );
+        // 400_000 units at 1 micro-lamport are 0.4 lamports, rounded up
+        assert_eq!(compute_budget(&tx), (400_000, 1, 1));
+
+        // the requested limit is capped by the runtime maximum
+        let tx = transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(2_000_000),
+                ComputeBudgetInstruction::set_compute_unit_price(2_000_000),
+                transfer.clone(),
+            ],
+            &payer,
+        );
+        assert_eq!(compute_budget(&tx), (1_400_000, 2_000_000, 2_800_000));
+
+        // the price does not overflow
+        let tx = transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+                ComputeBudgetInstruction::set_compute_unit_price(u64::MAX),
+                transfer,
+            ],
+            &payer,
+        );
+        assert_eq!(compute_budget(&tx).2, u64::MAX);
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let payer = Pubkey::new_unique();
+        let tx = transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+                ComputeBudgetInstruction::set_compute_unit_price(10_000),
+                system_instruction::transfer(&payer, &Pubkey::new_unique(), 1),
+            ],
+            &payer,
+        );
+        let limits =
+            |max_compute_unit_price, max_priority_fee_lamports, max_compute_units| FeeLimits {
+                max_compute_unit_price,
+                max_priority_fee_lamports,
+                max_compute_units,
+                action: GuardrailAction::Reject,
+            };
+
+        assert_eq!(check_limits(&tx, &limits(None, None, None)), Ok(()));
+        assert_eq!(
+            check_limits(&tx, &limits(Some(10_000), Some(3_000), Some(300_000))),
+            Ok(())
+        );
+        assert_eq!(
+            check_limits(&tx, &limits(Some(9_999), None, None)),
+            Err(FeeLimitExceeded::ComputeUnitPrice {
+                price: 10_000,
+                max: 9_999
+            })
+        );
+        assert_eq!(
+            check_limits(&tx, &limits(None, Some(2_999), None)),
+            Err(FeeLimitExceeded::PriorityFee {
+                fee: 3_000,
+                max: 2_999
+            })
+        );
+        assert_eq!(
+            check_limits(&tx, &limits(None, None, Some(200_000))),
+            Err(FeeLimitExceeded::ComputeUnits {
+                units: 300_000,
+                max: 200_000
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_limits_of_the_api_key_override_single_defaults() {
+        let guardrails = FeeGuardrails::new(FeeLimits {
+            max_compute_unit_price: Some(1_000),
+            max_priority_fee_lamports: Some(10_000),
+            max_compute_units: None,
+            action: GuardrailAction::Reject,
+        });
+        guardrails.set_key_limits(HashMap::from([(
+            "desk".to_string(),
+            FeeLimitOverrides {
+                max_compute_unit_price: Some(100_000),
+                action: Some(GuardrailAction::Warn),
+                ..Default::default()
+            },
+        )]));
+
+        let desk = guardrails.limits(Some("desk"));
+        assert_eq!(desk.max_compute_unit_price, Some(100_000));
+        assert_eq!(desk.max_priority_fee_lamports, Some(10_000));
+        assert_eq!(desk.action, GuardrailAction::Warn);
+        assert_eq!(guardrails.limits(Some("other")), guardrails.limits(None));
+
+        // the api key of the request is taken from the task local of the send
+        let payer = Pubkey::new_unique();
+        let tx = transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(100),
+                ComputeBudgetInstruction::set_compute_unit_price(50_000),
+                system_instruction::transfer(&payer, &Pubkey::new_unique(), 1),
+            ],
+            &payer,
+        );
+        assert!(guardrails.check(&tx).is_err());
+        let checked = with_api_key_id("desk".to_string(), async { guardrails.check(&tx) }).await;
+        assert_eq!(checked, Ok(()));
+    }
+}
@@ -1,9 +1,13 @@
 pub mod data_caching_service;
+pub mod dry_run;
+pub mod fee_guardrails;
 pub mod metrics_capture;
+pub mod optimistic_confirmation;
 pub mod prometheus_sync;
 pub mod quic_connection;
 pub mod quic_connection_utils;
 pub mod tpu_utils;
 pub mod transaction_replayer;
+pub mod transaction_sanitizer;
 pub mod transaction_service;
 pub mod tx_sender;
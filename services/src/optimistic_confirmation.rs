@@ -0,0 +1,352 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use anyhow::bail;
+use prometheus::{opts, register_int_counter, register_int_gauge, IntCounter, IntGauge};
+use solana_lite_rpc_core::structures::produced_block::{ProducedBlock, TransactionInfo};
+use solana_lite_rpc_core::types::{BlockStream, VoteAccountStream};
+use solana_lite_rpc_core::AnyhowJoinHandle;
+use solana_rpc_client_api::response::RpcVoteAccountStatus;
+use solana_sdk::commitment_config::CommitmentLevel;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::slot_history::Slot;
+use solana_sdk::vote::instruction::VoteInstruction;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+lazy_static::lazy_static! {
+    static ref OPTIMISTIC_CONFIRMED_SLOT: IntGauge =
+        register_int_gauge!(opts!("literpc_optimistic_confirmed_slot", "Latest confirmed slot, confirmed by the observed votes or by the source")).unwrap();
+    static ref OPTIMISTIC_CONFIRMED_BLOCKS: IntCounter =
+        register_int_counter!(opts!("literpc_optimistic_confirmed_blocks", "Blocks confirmed by the observed votes before the source confirmed them")).unwrap();
+    static ref OBSERVED_VOTES: IntCounter =
+        register_int_counter!(opts!("literpc_observed_votes", "Vote instructions parsed from the processed blocks")).unwrap();
+}
+
+// processed blocks older than this behind the newest one are dropped unconfirmed
+const MAX_PENDING_SLOTS: u64 = 256;
+
+/// Computes optimistic confirmation from the vote transactions of the processed blocks and the stakes
+/// of the vote accounts. A slot is confirmed once more than 2/3 of the stake voted for it or one of
+/// its descendants, which also confirms blocks of sources that only stream processed commitment.
+#[derive(Default)]
+pub struct OptimisticConfirmationTracker {
+    stakes: HashMap<Pubkey, u64>,
+    total_stake: u64,
+    // latest slot voted by every vote account
+    latest_votes: HashMap<Pubkey, Slot>,
+    // parent of every processed block in the window
+    parents: BTreeMap<Slot, Slot>,
+    // processed blocks which are not confirmed yet
+    pending_blocks: BTreeMap<Slot, ProducedBlock>,
+    confirmed_slot: Slot,
+}
+
+impl OptimisticConfirmationTracker {
+    pub fn update_stakes(&mut self, vote_accounts: &RpcVoteAccountStatus) {
+        self.stakes = vote_accounts
+            .current
+            .iter()
+            .chain(vote_accounts.delinquent.iter())
+            .filter_map(|vote_account| {
+                let vote_pubkey = Pubkey::from_str(&vote_account.vote_pubkey).ok()?;
+                Some((vote_pubkey, vote_account.activated_stake))
+            })
+            .collect();
+        self.total_stake = self.stakes.values().sum();
+    }
+
+    /// adds a block of the block stream, returns the blocks which got confirmed by its votes as
+    /// confirmed blocks ordered by slot
+    pub fn add_block(&mut self, block: &ProducedBlock) -> Vec<ProducedBlock> {
+        if block.commitment_config.commitment != CommitmentLevel::Processed {
+            // confirmed by the source, nothing to do for this slot and its ancestors
+            self.set_confirmed(block.slot);
+            return vec![];
+        }
+        if block.slot <= self.confirmed_slot {
+            return vec![];
+        }
+
+        self.parents.insert(block.slot, block.parent_slot);
+        self.pending_blocks.insert(block.slot, block.clone());
+        for transaction in block.transactions.iter().filter(|tx| tx.is_vote) {
+            for (vote_account, voted_slot) in votes(transaction) {
+                OBSERVED_VOTES.inc();
+                let latest_vote = self.latest_votes.entry(vote_account).or_default();
+                *latest_vote = (*latest_vote).max(voted_slot);
+            }
+        }
+
+        let confirmed_blocks = match self.newly_confirmed_slot() {
+            Some(slot) => self.confirm(slot),
+            None => vec![],
+        };
+        let oldest_slot = block.slot.saturating_sub(MAX_PENDING_SLOTS);
+        self.parents = self.parents.split_off(&oldest_slot);
+        self.pending_blocks = self.pending_blocks.split_off(&oldest_slot);
+        confirmed_blocks
+    }
+
+    // highest slot above the confirmed slot with a supermajority of the stake on its fork
+    fn newly_confirmed_slot(&self) -> Option<Slot> {
+        if self.total_stake == 0 {
+            return None;
+        }
+        let mut stake_by_slot: HashMap<Slot, u64> = HashMap::new();
+        for (vote_account, voted_slot) in &self.latest_votes {
+            let Some(stake) = self.stakes.get(vote_account).filter(|stake| **stake > 0) else {
+                continue;
+            };
+            // a vote for a slot is a vote for all of its ancestors
+            let mut slot = *voted_slot;
+            while slot > self.confirmed_slot {
+                let Some(parent) = self.parents.get(&slot) else {
+                    break;
+                };
+                *stake_by_slot.entry(slot).or_default() += stake;
+                slot = *parent;
+            }
+        }
+        stake_by_slot
+            .into_iter()
+            .filter(|(_, stake)| *stake as u128 * 3 > self.total_stake as u128 * 2)
+            .map(|(slot, _)| slot)
+            .max()
+    }
+
+    // the confirmed slot and its pending ancestors as confirmed blocks
+    fn confirm(&mut self, slot: Slot) -> Vec<ProducedBlock> {
+        let mut confirmed_blocks = vec![];
+        let mut ancestor = Some(slot);
+        while let Some(current) = ancestor.filter(|current| *current > self.confirmed_slot) {
+            if let Some(block) = self.pending_blocks.get(&current) {
+                confirmed_blocks.push(block.to_confirmed_block());
+            }
+            ancestor = self.parents.get(&current).copied();
+        }
+        confirmed_blocks.reverse();
+        OPTIMISTIC_CONFIRMED_BLOCKS.inc_by(confirmed_blocks.len() as u64);
+        self.set_confirmed(slot);
+        confirmed_blocks
+    }
+
+    fn set_confirmed(&mut self, slot: Slot) {
+        if slot <= self.confirmed_slot {
+            return;
+        }
+        self.confirmed_slot = slot;
+        self.pending_blocks = self.pending_blocks.split_off(&(slot + 1));
+        OPTIMISTIC_CONFIRMED_SLOT.set(slot as i64);
+    }
+
+    /// Tracks the votes of the block stream. The blocks confirmed by the votes before the source
+    /// confirmed them are sent on the returned stream.
+    pub fn start(
+        mut self,
+        mut block_notifier: BlockStream,
+        mut vote_account_notifier: VoteAccountStream,
+    ) -> (BlockStream, AnyhowJoinHandle) {
+        let (confirmed_blocks_sender, confirmed_blocks) = broadcast::channel(64);
+        let jh = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    block = block_notifier.recv() => {
+                        let block = match block {
+                            Ok(block) => block,
+                            Err(RecvError::Lagged(lagged)) => {
+                                log::warn!("Optimistic confirmation lagged by {lagged} blocks");
+                                continue;
+                            }
+                            Err(RecvError::Closed) => bail!("block stream closed"),
+                        };
+                        for confirmed_block in self.add_block(&block) {
+                            // fails only once the receiver is dropped on shutdown
+                            let _ = confirmed_blocks_sender.send(confirmed_block);
+                        }
+                    }
+                    vote_accounts = vote_account_notifier.recv() => {
+                        match vote_accounts {
+                            Ok(vote_accounts) => self.update_stakes(&vote_accounts),
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => bail!("vote account stream closed"),
+                        }
+                    }
+                }
+            }
+        });
+        (confirmed_blocks, jh)
+    }
+}
+
+// vote accounts and the last slot they voted for in a vote transaction
+fn votes(transaction: &TransactionInfo) -> Vec<(Pubkey, Slot)> {
+    if transaction.err.is_some() {
+        return vec![];
+    }
+    let Ok(message) = transaction.message.to_versioned_message() else {
+        return vec![];
+    };
+    let account_keys = message.static_account_keys();
+    message
+        .instructions()
+        .iter()
+        .filter(|instruction| {
+            account_keys.get(instruction.program_id_index as usize)
+                == Some(&solana_sdk::vote::program::id())
+        })
+        .filter_map(|instruction| {
+            let vote_instruction: VoteInstruction = bincode::deserialize(&instruction.data).ok()?;
+            if !vote_instruction.is_simple_vote() {
+                return None;
+            }
+            let vote_account = account_keys.get(*instruction.accounts.first()? as usize)?;
+            Some((*vote_account, vote_instruction.last_voted_slot()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_lite_rpc_core::structures::produced_block::TransactionMessage;
+    use solana_rpc_client_api::response::RpcVoteAccountInfo;
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{Message, VersionedMessage};
+    use solana_sdk::signature::Signature;
+    use solana_sdk::transaction::TransactionError;
+    use solana_sdk::vote::instruction as vote_instruction;
+    use solana_sdk::vote::state::Vote;
+
+    use super::*;
+
+    fn tracker(stakes: &[(Pubkey, u64)]) -> OptimisticConfirmationTracker {
+        let mut tracker = OptimisticConfirmationTracker::default();
+        tracker.update_stakes(&RpcVoteAccountStatus {
+            current: stakes
+                .iter()
+                .map(|(vote_account, stake)| RpcVoteAccountInfo {
+                    vote_pubkey: vote_account.to_string(),
+                    node_pubkey: Pubkey::new_unique().to_string(),
+                    activated_stake: *stake,
+                    commission: 0,
+                    epoch_vote_account: true,
+                    epoch_credits: vec![],
+                    last_vote: 0,
+                    root_slot: 0,
+                })
+                .collect(),
+            delinquent: vec![],
+        });
+        tracker
+    }
+
+    fn vote_transaction(vote_account: &Pubkey, slot: Slot) -> TransactionInfo {
+        let authority = Pubkey::new_unique();
+        let instruction = vote_instruction::vote(
+            vote_account,
+            &authority,
+            Vote::new(vec![slot], Hash::default()),
+        );
+        let message = VersionedMessage::Legacy(Message::new(&[instruction], Some(&authority)));
+        TransactionInfo {
+            signature: Signature::new_unique(),
+            is_vote: true,
+            err: None,
+            cu_requested: None,
+            prioritization_fees: None,
+            cu_consumed: None,
+            recent_blockhash: Hash::default(),
+            message: TransactionMessage::new(message.serialize()),
+            writable_accounts: vec![],
+            readable_accounts: vec![],
+            address_lookup_tables: vec![],
+            program_ids: vec![solana_sdk::vote::program::id()],
+        }
+    }
+
+    fn processed_block(slot: Slot, votes: Vec<TransactionInfo>) -> ProducedBlock {
+        ProducedBlock {
+            transactions: votes,
+            ..ProducedBlock::new_for_tests(slot, CommitmentConfig::processed())
+        }
+    }
+
+    fn slots(blocks: &[ProducedBlock]) -> Vec<Slot> {
+        blocks.iter().map(|block| block.slot).collect()
+    }
+
+    #[test]
+    fn test_supermajority_confirms_the_voted_slot_and_its_ancestors() {
+        let [a, b, c] = [(); 3].map(|_| Pubkey::new_unique());
+        let mut tracker = tracker(&[(a, 40), (b, 30), (c, 30)]);
+        for slot in 1..=3 {
+            assert!(tracker.add_block(&processed_block(slot, vec![])).is_empty());
+        }
+
+        // 70% voted for slot 2 or a descendant
+        let confirmed = tracker.add_block(&processed_block(
+            4,
+            vec![vote_transaction(&a, 2), vote_transaction(&b, 3)],
+        ));
+        assert_eq!(slots(&confirmed), vec![1, 2]);
+        assert!(confirmed
+            .iter()
+            .all(|block| block.commitment_config == CommitmentConfig::confirmed()));
+
+        // 60% on slot 3 is not enough, a's vote for a descendant adds its stake
+        assert!(tracker
+            .add_block(&processed_block(5, vec![vote_transaction(&c, 3)]))
+            .is_empty());
+        let confirmed = tracker.add_block(&processed_block(6, vec![vote_transaction(&a, 4)]));
+        assert_eq!(slots(&confirmed), vec![3]);
+    }
+
+    #[test]
+    fn test_exactly_two_thirds_of_the_stake_do_not_confirm() {
+        let [a, b] = [(); 2].map(|_| Pubkey::new_unique());
+        let mut tracker = tracker(&[(a, 2), (b, 1)]);
+        assert!(tracker.add_block(&processed_block(1, vec![])).is_empty());
+        assert!(tracker
+            .add_block(&processed_block(2, vec![vote_transaction(&a, 1)]))
+            .is_empty());
+        let confirmed = tracker.add_block(&processed_block(3, vec![vote_transaction(&b, 1)]));
+        assert_eq!(slots(&confirmed), vec![1]);
+    }
+
+    #[test]
+    fn test_failed_votes_and_unstaked_accounts_are_ignored() {
+        let [a, unstaked] = [(); 2].map(|_| Pubkey::new_unique());
+        let mut tracker = tracker(&[(a, 10)]);
+        assert!(tracker.add_block(&processed_block(1, vec![])).is_empty());
+        let failed_vote = TransactionInfo {
+            err: Some(TransactionError::AccountNotFound),
+            ..vote_transaction(&a, 1)
+        };
+        assert!(tracker
+            .add_block(&processed_block(
+                2,
+                vec![failed_vote, vote_transaction(&unstaked, 1)]
+            ))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_blocks_confirmed_by_the_source_are_not_confirmed_again() {
+        let a = Pubkey::new_unique();
+        let mut tracker = tracker(&[(a, 10)]);
+        assert!(tracker.add_block(&processed_block(1, vec![])).is_empty());
+        assert!(tracker.add_block(&processed_block(2, vec![])).is_empty());
+        assert!(tracker
+            .add_block(&ProducedBlock::new_for_tests(
+                2,
+                CommitmentConfig::confirmed()
+            ))
+            .is_empty());
+
+        assert!(tracker
+            .add_block(&processed_block(3, vec![vote_transaction(&a, 2)]))
+            .is_empty());
+        let confirmed = tracker.add_block(&processed_block(4, vec![vote_transaction(&a, 3)]));
+        assert_eq!(slots(&confirmed), vec![3]);
+    }
+}
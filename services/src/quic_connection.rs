@@ -118,12 +118,15 @@ impl QuicConnection {
         }
     }
 
-    pub async fn send_transaction(&self, tx: Vec<u8>) {
+    // returns false if the transaction could not be written because of a timeout or a connection error
+    #[tracing::instrument(skip_all, level = "debug")]
+    pub async fn send_transaction(&self, tx: Vec<u8>) -> bool {
         let connection_retry_count = self.connection_params.connection_retry_count;
+        let mut sent = false;
         for _ in 0..connection_retry_count {
             if self.exit_signal.load(Ordering::Relaxed) {
                 // return
-                return;
+                return sent;
             }
 
             let mut do_retry = false;
@@ -149,6 +152,7 @@ impl QuicConnection {
                         {
                             Ok(()) => {
                                 SEND_TRANSCTION_SUCESSFUL.inc();
+                                sent = true;
                             }
                             Err(QuicConnectionError::ConnectionError { retry }) => {
                                 do_retry = retry;
@@ -182,6 +186,7 @@ impl QuicConnection {
                 break;
             }
         }
+        sent
     }
 
     pub fn get_timeout_count(&self) -> u64 {
@@ -1,3 +1,4 @@
+use crate::tpu_utils::send_pacer::SendPacingConfig;
 use log::trace;
 use prometheus::{core::GenericGauge, opts, register_int_gauge};
 use quinn::{
@@ -49,6 +50,7 @@ pub struct QuicConnectionParameters {
     pub connection_retry_count: usize,
     pub max_number_of_connections: usize,
     pub number_of_transactions_per_unistream: usize,
+    pub send_pacing: SendPacingConfig,
 }
 
 pub struct QuicConnectionUtils {}
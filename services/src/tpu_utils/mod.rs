@@ -1,6 +1,11 @@
 pub mod tpu_service;
 
+#[cfg(feature = "quic-proxy")]
 pub mod quic_proxy_connection_manager;
+#[cfg(feature = "quic-proxy")]
 pub mod quinn_auto_reconnect;
+pub mod send_pacer;
+pub mod send_runtime;
+pub mod send_strategy;
 pub mod tpu_connection_manager;
 pub mod tpu_connection_path;
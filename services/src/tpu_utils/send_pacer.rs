@@ -0,0 +1,389 @@
+use prometheus::{core::GenericGauge, opts, register_int_counter, register_int_gauge, IntCounter};
+use solana_sdk::signature::Signature;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+lazy_static::lazy_static! {
+    static ref NB_PACING_SLOWDOWNS: IntCounter =
+        register_int_counter!(opts!("literpc_send_pacing_slowdowns", "Number of times the send rate to a leader was reduced because of congestion")).unwrap();
+    static ref NB_PACING_DELAYED_SENDS: IntCounter =
+        register_int_counter!(opts!("literpc_send_pacing_delayed_sends", "Number of transactions delayed by send pacing")).unwrap();
+    static ref NB_LEADERS_PACED: GenericGauge<prometheus::core::AtomicI64> =
+        register_int_gauge!(opts!("literpc_send_pacing_leaders_paced", "Number of leader connections sending below the maximum rate")).unwrap();
+}
+
+// multiplicative decrease factor applied on congestion
+const DECREASE_FACTOR: f64 = 0.5;
+// fraction of the maximum rate recovered on every successful send
+const INCREASE_STEP_RATIO: f64 = 0.01;
+// congestion signals within this interval of the last decrease do not reduce the rate again
+const CONGESTION_INTERVAL: Duration = Duration::from_millis(500);
+// a send which would have to wait longer than this for its turn is dropped instead
+const MAX_PACING_DELAY: Duration = Duration::from_secs(1);
+// sent transactions are checked for confirmation after this delay
+const LANDING_DELAY: Duration = Duration::from_secs(5);
+// the landing rate is only considered once this many sent transactions were checked
+const MIN_LANDING_SAMPLES: usize = 50;
+// a landing rate below this fraction counts as congestion
+const LANDING_RATE_COLLAPSE: f64 = 0.1;
+// upper bound of the sent transactions waiting to be checked per leader connection
+const MAX_TRACKED_SENDS: usize = 10_000;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SendPacingConfig {
+    pub enabled: bool,
+    // lower bound in transactions per second, pacing never goes below this rate
+    pub min_txs_per_second: u64,
+    // upper bound in transactions per second, also the rate used when the leader is healthy
+    pub max_txs_per_second: u64,
+}
+
+impl SendPacingConfig {
+    pub const fn disabled() -> Self {
+        Self {
+            enabled: false,
+            min_txs_per_second: 0,
+            max_txs_per_second: 0,
+        }
+    }
+}
+
+impl Default for SendPacingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_txs_per_second: 100,
+            max_txs_per_second: 10_000,
+        }
+    }
+}
+
+struct PacerState {
+    txs_per_second: f64,
+    next_send_at: Instant,
+    last_congestion_at: Option<Instant>,
+    is_paced: bool,
+}
+
+/// AIMD (additive increase, multiplicative decrease) rate limiter used per leader connection.
+/// Failed or timed out sends and a collapsed landing rate halve the send rate, at most once per `CONGESTION_INTERVAL`,
+/// successful sends slowly bring it back to the maximum.
+#[derive(Clone)]
+pub struct SendPacer {
+    config: SendPacingConfig,
+    state: Arc<Mutex<PacerState>>,
+}
+
+impl SendPacer {
+    pub fn new(config: SendPacingConfig) -> Self {
+        let max = config
+            .max_txs_per_second
+            .max(config.min_txs_per_second)
+            .max(1);
+        Self {
+            config: SendPacingConfig {
+                max_txs_per_second: max,
+                ..config
+            },
+            state: Arc::new(Mutex::new(PacerState {
+                txs_per_second: max as f64,
+                next_send_at: Instant::now(),
+                last_congestion_at: None,
+                is_paced: false,
+            })),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// reserve the next turn to send a transaction to the leader, returns the instant to send it at
+    /// or none if the pacer is too far behind and the transaction should be dropped
+    pub fn reserve_turn(&self) -> Option<Instant> {
+        let now = Instant::now();
+        if !self.config.enabled {
+            return Some(now);
+        }
+        let mut state = self.state.lock().unwrap();
+        let send_at = state.next_send_at.max(now);
+        if send_at > now + MAX_PACING_DELAY {
+            return None;
+        }
+        let interval = Duration::from_secs_f64(1.0 / state.txs_per_second);
+        state.next_send_at = send_at + interval;
+        if send_at > now {
+            NB_PACING_DELAYED_SENDS.inc();
+        }
+        Some(send_at)
+    }
+
+    pub fn on_success(&self) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let max = self.config.max_txs_per_second as f64;
+        let step = (max * INCREASE_STEP_RATIO).max(1.0);
+        state.txs_per_second = (state.txs_per_second + step).min(max);
+        if state.is_paced && state.txs_per_second >= max {
+            state.is_paced = false;
+            NB_LEADERS_PACED.dec();
+        }
+    }
+
+    pub fn on_congestion(&self) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if state
+            .last_congestion_at
+            .is_some_and(|last| now.duration_since(last) < CONGESTION_INTERVAL)
+        {
+            return;
+        }
+        state.last_congestion_at = Some(now);
+        let min = self.config.min_txs_per_second.max(1) as f64;
+        let reduced = (state.txs_per_second * DECREASE_FACTOR).max(min);
+        if reduced < state.txs_per_second {
+            NB_PACING_SLOWDOWNS.inc();
+        }
+        state.txs_per_second = reduced;
+        if !state.is_paced {
+            state.is_paced = true;
+            NB_LEADERS_PACED.inc();
+        }
+    }
+
+    /// `landed` of the `checked` sent transactions were confirmed
+    pub fn on_landing_rate(&self, landed: usize, checked: usize) {
+        if checked < MIN_LANDING_SAMPLES {
+            return;
+        }
+        if (landed as f64) < checked as f64 * LANDING_RATE_COLLAPSE {
+            self.on_congestion();
+        }
+    }
+
+    pub fn current_rate(&self) -> u64 {
+        self.state.lock().unwrap().txs_per_second as u64
+    }
+}
+
+/// Transactions sent to a leader, checked for confirmation once they had time to land
+#[derive(Default)]
+pub struct LandingTracker {
+    sent: VecDeque<(Instant, Signature)>,
+}
+
+impl LandingTracker {
+    pub fn on_sent(&mut self, signature: Signature) {
+        if self.sent.len() >= MAX_TRACKED_SENDS {
+            self.sent.pop_front();
+        }
+        self.sent.push_back((Instant::now(), signature));
+    }
+
+    /// checks the transactions sent at least `LANDING_DELAY` ago, returns how many of them landed and how many were checked
+    pub fn check_landed(&mut self, is_landed: impl Fn(&Signature) -> bool) -> (usize, usize) {
+        self.check_landed_before(Instant::now() - LANDING_DELAY, is_landed)
+    }
+
+    fn check_landed_before(
+        &mut self,
+        sent_before: Instant,
+        is_landed: impl Fn(&Signature) -> bool,
+    ) -> (usize, usize) {
+        let mut landed = 0;
+        let mut checked = 0;
+        while let Some((_, signature)) = self
+            .sent
+            .front()
+            .filter(|(sent_at, _)| *sent_at <= sent_before)
+        {
+            if is_landed(signature) {
+                landed += 1;
+            }
+            checked += 1;
+            self.sent.pop_front();
+        }
+        (landed, checked)
+    }
+}
+
+impl Drop for PacerState {
+    fn drop(&mut self) {
+        if self.is_paced {
+            NB_LEADERS_PACED.dec();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    /// connection to a leader which fails the sends it was told to
+    struct MockConnection {
+        results: VecDeque<bool>,
+    }
+
+    impl MockConnection {
+        fn new(results: impl IntoIterator<Item = bool>) -> Self {
+            Self {
+                results: results.into_iter().collect(),
+            }
+        }
+
+        /// sends like the connection manager does, reporting the result to the pacer
+        async fn send(&mut self, send_pacer: &SendPacer) {
+            let send_at = send_pacer.reserve_turn().unwrap();
+            tokio::time::sleep_until(send_at.into()).await;
+            if self.results.pop_front().unwrap_or(true) {
+                send_pacer.on_success();
+            } else {
+                send_pacer.on_congestion();
+            }
+        }
+    }
+
+    /// lets the next congestion signal reduce the rate right away
+    fn end_congestion_interval(send_pacer: &SendPacer) {
+        send_pacer.state.lock().unwrap().last_congestion_at = None;
+    }
+
+    fn config() -> SendPacingConfig {
+        SendPacingConfig {
+            enabled: true,
+            min_txs_per_second: 1_000,
+            max_txs_per_second: 10_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backs_off_on_failed_sends() {
+        let send_pacer = SendPacer::new(config());
+        assert_eq!(send_pacer.current_rate(), 10_000);
+
+        let mut connection = MockConnection::new([false, false]);
+        connection.send(&send_pacer).await;
+        assert_eq!(send_pacer.current_rate(), 5_000);
+        end_congestion_interval(&send_pacer);
+        connection.send(&send_pacer).await;
+        assert_eq!(send_pacer.current_rate(), 2_500);
+
+        // never below the minimum rate
+        let mut connection = MockConnection::new([false; 10]);
+        for _ in 0..10 {
+            end_congestion_interval(&send_pacer);
+            connection.send(&send_pacer).await;
+        }
+        assert_eq!(send_pacer.current_rate(), 1_000);
+        assert!(send_pacer.state.lock().unwrap().is_paced);
+    }
+
+    #[tokio::test]
+    async fn test_burst_of_failures_reduces_the_rate_once() {
+        let send_pacer = SendPacer::new(config());
+        let mut connection = MockConnection::new([false; 10]);
+        for _ in 0..10 {
+            connection.send(&send_pacer).await;
+        }
+        assert_eq!(send_pacer.current_rate(), 5_000);
+    }
+
+    #[tokio::test]
+    async fn test_drops_sends_too_far_behind() {
+        let send_pacer = SendPacer::new(SendPacingConfig {
+            enabled: true,
+            min_txs_per_second: 10,
+            max_txs_per_second: 10,
+        });
+        // one turn every 100ms, the turns more than one second ahead are refused
+        let reserved = (0..20).map_while(|_| send_pacer.reserve_turn()).count();
+        assert_eq!(reserved, 11);
+    }
+
+    #[tokio::test]
+    async fn test_collapsed_landing_rate_reduces_the_rate() {
+        let send_pacer = SendPacer::new(config());
+        // too few samples to judge
+        send_pacer.on_landing_rate(0, 10);
+        assert_eq!(send_pacer.current_rate(), 10_000);
+        send_pacer.on_landing_rate(50, 100);
+        assert_eq!(send_pacer.current_rate(), 10_000);
+        send_pacer.on_landing_rate(5, 100);
+        assert_eq!(send_pacer.current_rate(), 5_000);
+    }
+
+    #[test]
+    fn test_landing_tracker_checks_old_sends_only() {
+        let mut landing_tracker = LandingTracker::default();
+        let landed = Signature::new_unique();
+        landing_tracker.on_sent(landed);
+        landing_tracker.on_sent(Signature::new_unique());
+        let sent_before = Instant::now();
+        landing_tracker
+            .sent
+            .push_back((sent_before + Duration::from_secs(1), landed));
+
+        assert_eq!(
+            landing_tracker.check_landed_before(sent_before, |signature| *signature == landed),
+            (1, 2)
+        );
+        assert_eq!(landing_tracker.sent.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_recovers_after_successful_sends() {
+        let send_pacer = SendPacer::new(config());
+        let mut connection = MockConnection::new([false, true]);
+        connection.send(&send_pacer).await;
+        connection.send(&send_pacer).await;
+        // every success adds 1% of the maximum rate
+        assert_eq!(send_pacer.current_rate(), 5_100);
+
+        let mut connection = MockConnection::new([]);
+        for _ in 0..49 {
+            connection.send(&send_pacer).await;
+        }
+        assert_eq!(send_pacer.current_rate(), 10_000);
+        assert!(!send_pacer.state.lock().unwrap().is_paced);
+    }
+
+    #[tokio::test]
+    async fn test_spaces_sends_at_the_current_rate() {
+        let send_pacer = SendPacer::new(SendPacingConfig {
+            enabled: true,
+            min_txs_per_second: 100,
+            max_txs_per_second: 100,
+        });
+        let started_at = Instant::now();
+        let mut connection = MockConnection::new([]);
+        for _ in 0..3 {
+            connection.send(&send_pacer).await;
+        }
+        // the first send is immediate, the next ones wait 10ms each
+        assert!(started_at.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_pacer_keeps_the_rate() {
+        let send_pacer = SendPacer::new(SendPacingConfig {
+            enabled: false,
+            ..config()
+        });
+        let mut connection = MockConnection::new([false; 3]);
+        for _ in 0..3 {
+            connection.send(&send_pacer).await;
+        }
+        assert_eq!(send_pacer.current_rate(), 10_000);
+    }
+}
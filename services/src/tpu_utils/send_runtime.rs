@@ -0,0 +1,51 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use anyhow::Context;
+use log::{info, warn};
+use tokio::runtime::Handle;
+
+#[derive(Clone, Debug, Default)]
+pub struct SendRuntimeConfig {
+    pub worker_threads: usize,
+    // the worker threads are pinned to these cores round robin, not pinned if empty
+    pub core_ids: Vec<usize>,
+}
+
+/// Starts a tokio runtime of its own for the QUIC connections to the leaders, so spikes of block
+/// mapping or rpc requests on the main runtime do not add latency to the transaction forwarding.
+/// The runtime lives until the process exits.
+pub fn start_send_runtime(config: &SendRuntimeConfig) -> anyhow::Result<Handle> {
+    let core_ids = config.core_ids.clone();
+    let next_core = Arc::new(AtomicUsize::new(0));
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(config.worker_threads)
+        .thread_name("lite-rpc-send")
+        .enable_all()
+        // also called for the blocking threads, they share the cores of the workers
+        .on_thread_start(move || {
+            if core_ids.is_empty() {
+                return;
+            }
+            let id = core_ids[next_core.fetch_add(1, Ordering::Relaxed) % core_ids.len()];
+            if !core_affinity::set_for_current(core_affinity::CoreId { id }) {
+                warn!("Cannot pin send runtime thread to core {id}");
+            }
+        })
+        .build()
+        .context("Cannot build the send runtime")?;
+    let handle = runtime.handle().clone();
+
+    // a runtime cannot be dropped from async code, it is parked on a thread of its own
+    std::thread::Builder::new()
+        .name("lite-rpc-send-rt".to_string())
+        .spawn(move || runtime.block_on(std::future::pending::<()>()))
+        .context("Cannot start the send runtime")?;
+    info!(
+        "Send runtime started with {} worker threads pinned to cores {:?}",
+        config.worker_threads, config.core_ids
+    );
+    Ok(handle)
+}
@@ -0,0 +1,86 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use dashmap::DashMap;
+use solana_lite_rpc_core::structures::leader_data::LeaderData;
+use solana_rpc_client_api::response::RpcContactInfo;
+use solana_sdk::{pubkey::Pubkey, quic::QUIC_PORT_OFFSET};
+
+use crate::quic_connection_utils::QuicConnectionParameters;
+
+/// Decides which leaders receive transactions, how many connections are kept to each of them and when
+/// unconfirmed transactions are sent again.
+/// Implement this trait to plug custom routing into the tpu service when embedding lite-rpc as a library,
+/// e.g. to only send to leaders running a specific client version.
+pub trait SendStrategy: Send + Sync {
+    /// select the leaders to send to from the leaders of the upcoming slots (fanout),
+    /// returns the identity and the quic tpu address of each selected leader
+    fn select_leaders(
+        &self,
+        upcoming_leaders: &[LeaderData],
+        cluster_nodes: &DashMap<Pubkey, Arc<RpcContactInfo>>,
+    ) -> HashMap<Pubkey, SocketAddr>;
+
+    /// number of quic connections opened to the given leader
+    fn connections_per_leader(
+        &self,
+        leader: &Pubkey,
+        connection_parameters: &QuicConnectionParameters,
+    ) -> usize;
+
+    /// delay before an unconfirmed transaction is sent again after it was replayed `replay_count` times,
+    /// None stops replaying the transaction
+    fn replay_delay(
+        &self,
+        replay_count: usize,
+        max_replay: usize,
+        retry_offset: Duration,
+    ) -> Option<Duration>;
+}
+
+/// Sends to every upcoming leader with a known tpu address and replays with a linearly increasing delay
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultSendStrategy;
+
+impl DefaultSendStrategy {
+    pub fn tpu_quic_address(contact_info: &RpcContactInfo) -> Option<SocketAddr> {
+        contact_info.tpu.map(|mut addr| {
+            // add quic port offset
+            addr.set_port(addr.port() + QUIC_PORT_OFFSET);
+            addr
+        })
+    }
+}
+
+impl SendStrategy for DefaultSendStrategy {
+    fn select_leaders(
+        &self,
+        upcoming_leaders: &[LeaderData],
+        cluster_nodes: &DashMap<Pubkey, Arc<RpcContactInfo>>,
+    ) -> HashMap<Pubkey, SocketAddr> {
+        upcoming_leaders
+            .iter()
+            .filter_map(|leader| {
+                let contact_info = cluster_nodes.get(&leader.pubkey)?;
+                let tpu_address = Self::tpu_quic_address(&contact_info)?;
+                Some((leader.pubkey, tpu_address))
+            })
+            .collect()
+    }
+
+    fn connections_per_leader(
+        &self,
+        _leader: &Pubkey,
+        connection_parameters: &QuicConnectionParameters,
+    ) -> usize {
+        connection_parameters.max_number_of_connections
+    }
+
+    fn replay_delay(
+        &self,
+        replay_count: usize,
+        max_replay: usize,
+        retry_offset: Duration,
+    ) -> Option<Duration> {
+        (replay_count < max_replay).then(|| retry_offset.mul_f32((replay_count + 1) as f32))
+    }
+}
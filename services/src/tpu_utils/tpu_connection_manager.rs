@@ -5,7 +5,9 @@ use quinn::Endpoint;
 use solana_lite_rpc_core::{
     stores::data_cache::DataCache,
     structures::{
-        identity_stakes::IdentityStakesData, rotating_queue::RotatingQueue,
+        dropped_transaction::{record_dropped_transactions, DropReason},
+        identity_stakes::IdentityStakesData,
+        rotating_queue::RotatingQueue,
         transaction_sent_info::SentTransactionInfo,
     },
 };
@@ -15,15 +17,25 @@ use std::{
     collections::HashMap,
     net::SocketAddr,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
+    time::Duration,
+};
+use tokio::sync::{
+    broadcast::error::RecvError,
+    broadcast::Receiver,
+    broadcast::Sender,
+    mpsc::{UnboundedReceiver, UnboundedSender},
 };
-use tokio::sync::{broadcast::Receiver, broadcast::Sender};
 
 use crate::{
     quic_connection::{PooledConnection, QuicConnectionPool},
     quic_connection_utils::{QuicConnectionParameters, QuicConnectionUtils},
+    tpu_utils::{
+        send_pacer::{LandingTracker, SendPacer},
+        send_strategy::SendStrategy,
+    },
 };
 
 lazy_static::lazy_static! {
@@ -37,6 +49,27 @@ lazy_static::lazy_static! {
         register_int_gauge!(opts!("literpc_quic_tasks", "Number of connections to keep asked by tpu service")).unwrap();
 }
 
+const IN_FLIGHT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+// how often the landing rate of the sent transactions is fed to the send pacer
+const LANDING_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Counts a transaction from the moment a connection takes it from the broadcast channel
+/// until its send finished, so that closing the connections can wait for it.
+struct InFlightSend(Arc<AtomicUsize>);
+
+impl InFlightSend {
+    fn new(in_flight_sends: &Arc<AtomicUsize>) -> Self {
+        in_flight_sends.fetch_add(1, Ordering::Relaxed);
+        Self(in_flight_sends.clone())
+    }
+}
+
+impl Drop for InFlightSend {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 #[derive(Clone)]
 struct ActiveConnection {
     endpoints: RotatingQueue<Endpoint>,
@@ -45,6 +78,7 @@ struct ActiveConnection {
     exit_signal: Arc<AtomicBool>,
     data_cache: DataCache,
     connection_parameters: QuicConnectionParameters,
+    in_flight_sends: Arc<AtomicUsize>,
 }
 
 impl ActiveConnection {
@@ -54,6 +88,7 @@ impl ActiveConnection {
         identity: Pubkey,
         data_cache: DataCache,
         connection_parameters: QuicConnectionParameters,
+        in_flight_sends: Arc<AtomicUsize>,
     ) -> Self {
         Self {
             endpoints,
@@ -62,6 +97,7 @@ impl ActiveConnection {
             exit_signal: Arc::new(AtomicBool::new(false)),
             data_cache,
             connection_parameters,
+            in_flight_sends,
         }
     }
 
@@ -69,12 +105,14 @@ impl ActiveConnection {
     async fn listen(
         &self,
         transaction_reciever: Receiver<SentTransactionInfo>,
+        forward_receiver: UnboundedReceiver<SentTransactionInfo>,
         exit_oneshot_channel: tokio::sync::mpsc::Receiver<()>,
         addr: SocketAddr,
         identity_stakes: IdentityStakesData,
     ) {
         NB_QUIC_ACTIVE_CONNECTIONS.inc();
         let mut transaction_reciever = transaction_reciever;
+        let mut forward_receiver = forward_receiver;
         let mut exit_oneshot_channel = exit_oneshot_channel;
         let identity = self.identity;
 
@@ -95,6 +133,9 @@ impl ActiveConnection {
             max_number_of_connections,
             max_uni_stream_connections,
         );
+        let send_pacer = SendPacer::new(self.connection_parameters.send_pacing);
+        let mut landing_tracker = LandingTracker::default();
+        let mut landing_check = tokio::time::interval(LANDING_CHECK_INTERVAL);
 
         loop {
             // exit signal set
@@ -102,53 +143,83 @@ impl ActiveConnection {
                 break;
             }
 
-            tokio::select! {
-                tx = transaction_reciever.recv() => {
-                    // exit signal set
-                    if exit_signal.load(Ordering::Relaxed) {
-                        break;
-                    }
-
-                    let tx: Vec<u8> = match tx {
-                        Ok(transaction_sent_info) => {
-                            if self.data_cache.txs.is_transaction_confirmed(&transaction_sent_info.signature) {
-                                // transaction is already confirmed/ no need to send
-                                continue;
-                            }
-                            transaction_sent_info.transaction
-                        },
-                        Err(e) => {
-                            error!(
-                                "Broadcast channel error on recv for {} error {} - continue",
-                                identity, e
-                            );
-                            continue;
+            let transaction_sent_info = tokio::select! {
+                tx = transaction_reciever.recv() => match tx {
+                    Ok(transaction_sent_info) => transaction_sent_info,
+                    Err(e) => {
+                        if let RecvError::Lagged(lagged) = e {
+                            record_dropped_transactions(DropReason::QueueFull, lagged);
                         }
-                    };
-
-                    let PooledConnection {
-                        connection,
-                        permit
-                    } = match connection_pool.get_pooled_connection().await {
-                        Ok(connection_pool) => connection_pool,
-                        Err(e) => {
-                            error!("error getting pooled connection {e:?}");
-                            break;
-                        },
-                    };
-
-                    tokio::spawn(async move {
-                        // permit will be used to send all the transaction and then destroyed
-                        let _permit = permit;
-                        NB_QUIC_TASKS.inc();
-                        connection.send_transaction(tx).await;
-                        NB_QUIC_TASKS.dec();
-                    });
+                        error!(
+                            "Broadcast channel error on recv for {} error {} - continue",
+                            identity, e
+                        );
+                        continue;
+                    }
                 },
+                // transactions sent to this leader only, see `TpuConnectionManager::forward_to`
+                Some(transaction_sent_info) = forward_receiver.recv() => transaction_sent_info,
+                _ = landing_check.tick(), if send_pacer.is_enabled() => {
+                    let (landed, checked) = landing_tracker.check_landed(|signature| {
+                        self.data_cache.txs.is_transaction_confirmed(signature)
+                    });
+                    send_pacer.on_landing_rate(landed, checked);
+                    continue;
+                }
                 _ = exit_oneshot_channel.recv() => {
                     break;
                 }
+            };
+
+            // exit signal set
+            if exit_signal.load(Ordering::Relaxed) {
+                break;
+            }
+
+            if self
+                .data_cache
+                .txs
+                .is_transaction_confirmed(&transaction_sent_info.signature)
+            {
+                // transaction is already confirmed/ no need to send
+                continue;
             }
+            // the leader is paced and already has a second of sends queued
+            let Some(send_at) = send_pacer.reserve_turn() else {
+                record_dropped_transactions(DropReason::SendPaced, 1);
+                continue;
+            };
+            if send_pacer.is_enabled() {
+                landing_tracker.on_sent(transaction_sent_info.signature);
+            }
+            let tx = transaction_sent_info.transaction;
+            let in_flight_send = InFlightSend::new(&self.in_flight_sends);
+
+            let PooledConnection { connection, permit } =
+                match connection_pool.get_pooled_connection().await {
+                    Ok(connection_pool) => connection_pool,
+                    Err(e) => {
+                        record_dropped_transactions(DropReason::ConnectionFailure, 1);
+                        error!("error getting pooled connection {e:?}");
+                        break;
+                    }
+                };
+
+            let send_pacer = send_pacer.clone();
+            tokio::spawn(async move {
+                // permit will be used to send all the transaction and then destroyed
+                let _permit = permit;
+                let _in_flight_send = in_flight_send;
+                NB_QUIC_TASKS.inc();
+                tokio::time::sleep_until(send_at.into()).await;
+                if connection.send_transaction(tx).await {
+                    send_pacer.on_success();
+                } else {
+                    record_dropped_transactions(DropReason::ConnectionFailure, 1);
+                    send_pacer.on_congestion();
+                }
+                NB_QUIC_TASKS.dec();
+            });
         }
         drop(transaction_reciever);
         NB_QUIC_CONNECTIONS.dec();
@@ -158,6 +229,7 @@ impl ActiveConnection {
     pub fn start_listening(
         &self,
         transaction_reciever: Receiver<SentTransactionInfo>,
+        forward_receiver: UnboundedReceiver<SentTransactionInfo>,
         exit_oneshot_channel: tokio::sync::mpsc::Receiver<()>,
         identity_stakes: IdentityStakesData,
     ) {
@@ -166,6 +238,7 @@ impl ActiveConnection {
         tokio::spawn(async move {
             this.listen(
                 transaction_reciever,
+                forward_receiver,
                 exit_oneshot_channel,
                 addr,
                 identity_stakes,
@@ -178,11 +251,13 @@ impl ActiveConnection {
 struct ActiveConnectionWithExitChannel {
     pub active_connection: ActiveConnection,
     pub exit_stream: tokio::sync::mpsc::Sender<()>,
+    pub forward_sender: UnboundedSender<SentTransactionInfo>,
 }
 
 pub struct TpuConnectionManager {
     endpoints: RotatingQueue<Endpoint>,
     identity_to_active_connection: Arc<DashMap<Pubkey, Arc<ActiveConnectionWithExitChannel>>>,
+    in_flight_sends: Arc<AtomicUsize>,
 }
 
 impl TpuConnectionManager {
@@ -197,9 +272,15 @@ impl TpuConnectionManager {
                 QuicConnectionUtils::create_endpoint(certificate.clone(), key.clone())
             }),
             identity_to_active_connection: Arc::new(DashMap::new()),
+            in_flight_sends: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// Number of transactions taken by a connection whose send did not finish yet
+    pub fn in_flight_sends(&self) -> usize {
+        self.in_flight_sends.load(Ordering::Relaxed)
+    }
+
     pub async fn update_connections(
         &self,
         broadcast_sender: Arc<Sender<SentTransactionInfo>>,
@@ -207,6 +288,7 @@ impl TpuConnectionManager {
         identity_stakes: IdentityStakesData,
         data_cache: DataCache,
         connection_parameters: QuicConnectionParameters,
+        send_strategy: &dyn SendStrategy,
     ) {
         NB_CONNECTIONS_TO_KEEP.set(connections_to_keep.len() as i64);
         for (identity, socket_addr) in &connections_to_keep {
@@ -217,18 +299,31 @@ impl TpuConnectionManager {
                     *socket_addr,
                     *identity,
                     data_cache.clone(),
-                    connection_parameters,
+                    QuicConnectionParameters {
+                        max_number_of_connections: send_strategy
+                            .connections_per_leader(identity, &connection_parameters),
+                        ..connection_parameters
+                    },
+                    self.in_flight_sends.clone(),
                 );
                 // using mpsc as a oneshot channel/ because with one shot channel we cannot reuse the reciever
                 let (sx, rx) = tokio::sync::mpsc::channel(1);
 
+                let (forward_sender, forward_receiver) = tokio::sync::mpsc::unbounded_channel();
+
                 let broadcast_receiver = broadcast_sender.subscribe();
-                active_connection.start_listening(broadcast_receiver, rx, identity_stakes);
+                active_connection.start_listening(
+                    broadcast_receiver,
+                    forward_receiver,
+                    rx,
+                    identity_stakes,
+                );
                 self.identity_to_active_connection.insert(
                     *identity,
                     Arc::new(ActiveConnectionWithExitChannel {
                         active_connection,
                         exit_stream: sx,
+                        forward_sender,
                     }),
                 );
             }
@@ -253,4 +348,100 @@ impl TpuConnectionManager {
             }
         }
     }
+
+    /// Send transactions to a single leader in addition to the broadcast ones,
+    /// returns the number of transactions queued, 0 if there is no connection to the leader
+    pub fn forward_to(&self, identity: &Pubkey, transactions: Vec<SentTransactionInfo>) -> usize {
+        let Some(active_connection) = self.identity_to_active_connection.get(identity) else {
+            return 0;
+        };
+        transactions
+            .into_iter()
+            .take_while(|transaction| {
+                active_connection
+                    .forward_sender
+                    .send(transaction.clone())
+                    .is_ok()
+            })
+            .count()
+    }
+
+    /// Used when the identity is rotated: the whole manager is replaced by a new one.
+    /// The connections stop taking transactions right away, the sends in flight get up to
+    /// `drain_timeout` to finish before the connections are torn down.
+    /// Returns false if some sends were still in flight after the timeout.
+    pub async fn close_all_connections(&self, drain_timeout: Duration) -> bool {
+        let active_connections = self
+            .identity_to_active_connection
+            .iter()
+            .map(|x| (*x.key(), x.value().clone()))
+            .collect::<Vec<_>>();
+        for (identity, value) in &active_connections {
+            trace!("closing connection for {}", identity);
+            let _ = value.exit_stream.send(()).await;
+            self.identity_to_active_connection.remove(identity);
+        }
+
+        let drained = tokio::time::timeout(drain_timeout, async {
+            while self.in_flight_sends() > 0 {
+                tokio::time::sleep(IN_FLIGHT_POLL_INTERVAL).await;
+            }
+        })
+        .await
+        .is_ok();
+
+        for (_, value) in active_connections {
+            value
+                .active_connection
+                .exit_signal
+                .store(true, Ordering::Relaxed);
+        }
+        drained
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+    use solana_streamer::tls_certificates::new_self_signed_tls_certificate;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    async fn connection_manager() -> TpuConnectionManager {
+        let (certificate, key) =
+            new_self_signed_tls_certificate(&Keypair::new(), IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)))
+                .unwrap();
+        TpuConnectionManager::new(certificate, key, 1).await
+    }
+
+    #[tokio::test]
+    async fn test_close_waits_for_in_flight_sends() {
+        let connection_manager = connection_manager().await;
+        let in_flight_send = InFlightSend::new(&connection_manager.in_flight_sends);
+        assert_eq!(connection_manager.in_flight_sends(), 1);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(in_flight_send);
+        });
+        assert!(
+            connection_manager
+                .close_all_connections(Duration::from_secs(5))
+                .await
+        );
+        assert_eq!(connection_manager.in_flight_sends(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_close_gives_up_after_the_timeout() {
+        let connection_manager = connection_manager().await;
+        let _in_flight_send = InFlightSend::new(&connection_manager.in_flight_sends);
+
+        assert!(
+            !connection_manager
+                .close_all_connections(Duration::from_millis(50))
+                .await
+        );
+        assert_eq!(connection_manager.in_flight_sends(), 1);
+    }
 }
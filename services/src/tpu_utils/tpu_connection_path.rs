@@ -1,20 +1,25 @@
 use std::fmt::Display;
 use std::net::SocketAddr;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TpuConnectionPath {
     QuicDirectPath,
-    QuicForwardProxyPath { forward_proxy_address: SocketAddr },
+    /// every transaction is sent through each of the proxies, e.g. in different regions
+    #[cfg(feature = "quic-proxy")]
+    QuicForwardProxyPath {
+        forward_proxy_addresses: Vec<SocketAddr>,
+    },
 }
 
 impl Display for TpuConnectionPath {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TpuConnectionPath::QuicDirectPath => write!(f, "Direct QUIC connection to TPU"),
+            #[cfg(feature = "quic-proxy")]
             TpuConnectionPath::QuicForwardProxyPath {
-                forward_proxy_address,
+                forward_proxy_addresses,
             } => {
-                write!(f, "QUIC Forward Proxy on {}", forward_proxy_address)
+                write!(f, "QUIC Forward Proxy on {:?}", forward_proxy_addresses)
             }
         }
     }
@@ -1,24 +1,36 @@
 use anyhow::Context;
-use prometheus::{core::GenericGauge, opts, register_int_gauge};
+use prometheus::{core::GenericGauge, opts, register_int_counter, register_int_gauge, IntCounter};
 
 use super::tpu_connection_manager::TpuConnectionManager;
 use crate::quic_connection_utils::QuicConnectionParameters;
+#[cfg(feature = "quic-proxy")]
 use crate::tpu_utils::quic_proxy_connection_manager::QuicProxyConnectionManager;
+use crate::tpu_utils::send_pacer::SendPacingConfig;
+use crate::tpu_utils::send_strategy::SendStrategy;
 use crate::tpu_utils::tpu_connection_path::TpuConnectionPath;
-use crate::tpu_utils::tpu_service::ConnectionManager::{DirectTpu, QuicProxy};
+use crate::tpu_utils::tpu_service::ConnectionManager::DirectTpu;
+#[cfg(feature = "quic-proxy")]
+use crate::tpu_utils::tpu_service::ConnectionManager::QuicProxy;
 
+use serde::Serialize;
 use solana_lite_rpc_core::network_utils::log_gso_workaround;
 use solana_lite_rpc_core::stores::data_cache::DataCache;
+use solana_lite_rpc_core::structures::leader_data::LeaderData;
 use solana_lite_rpc_core::structures::transaction_sent_info::SentTransactionInfo;
 use solana_lite_rpc_core::traits::leaders_fetcher_interface::LeaderFetcherInterface;
-use solana_lite_rpc_core::types::SlotStream;
+use solana_lite_rpc_core::types::LatestSlot;
 use solana_lite_rpc_core::AnyhowJoinHandle;
-use solana_sdk::{quic::QUIC_PORT_OFFSET, signature::Keypair, slot_history::Slot};
+use solana_sdk::{
+    clock::DEFAULT_MS_PER_SLOT, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    slot_history::Slot,
+};
 use solana_streamer::tls_certificates::new_self_signed_tls_certificate;
 use std::{
     net::{IpAddr, Ipv4Addr},
     sync::Arc,
+    time::Duration,
 };
+use tokio::{runtime::Handle, sync::RwLock, time::Instant};
 
 lazy_static::lazy_static! {
     static ref NB_CLUSTER_NODES: GenericGauge<prometheus::core::AtomicI64> =
@@ -32,23 +44,56 @@ lazy_static::lazy_static! {
 
     static ref ESTIMATED_SLOT: GenericGauge<prometheus::core::AtomicI64> =
     register_int_gauge!(opts!("literpc_estimated_slot", "Estimated slot seen by last rpc")).unwrap();
+
+    static ref NB_IDENTITY_ROTATIONS: IntCounter =
+    register_int_counter!(opts!("literpc_identity_rotations", "Number of times the identity keypair was rotated")).unwrap();
+
+    static ref NB_SLOT_EDGE_FORWARDS: IntCounter =
+    register_int_counter!(opts!("literpc_slot_edge_forwards", "Transactions sent to the next leader at the end of the last slot of a leader")).unwrap();
 }
 
-#[derive(Clone, Copy)]
+// time given to the connections of the old identity to finish the sends in flight
+const IDENTITY_ROTATION_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
 pub struct TpuServiceConfig {
     pub fanout_slots: u64,
     pub maximum_transaction_in_queue: usize,
     pub quic_connection_params: QuicConnectionParameters,
     pub tpu_connection_path: TpuConnectionPath,
+    // at this time before the end of the last slot of a leader the next leader also gets the
+    // transactions, even if it is not covered by the fanout; None disables it
+    pub slot_edge_forwarding_window: Option<Duration>,
+}
+
+/// a leader of the upcoming slots, as resolved for forwarding the transactions
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingLeader {
+    pub identity: String,
+    pub slots: Vec<Slot>,
+    /// none if the leader is not in the cluster nodes or not selected by the send strategy
+    pub tpu_quic: Option<String>,
+    /// activated stake, none before the vote accounts are known
+    pub stake: Option<u64>,
 }
 
 #[derive(Clone)]
 pub struct TpuService {
     broadcast_sender: Arc<tokio::sync::broadcast::Sender<SentTransactionInfo>>,
-    connection_manager: ConnectionManager,
+    // swapped out when the identity keypair is rotated
+    connection_manager: Arc<RwLock<ConnectionManager>>,
     leader_schedule: Arc<dyn LeaderFetcherInterface>,
-    config: TpuServiceConfig,
+    send_strategy: Arc<dyn SendStrategy>,
+    // fanout and send pacing can be changed at runtime
+    config: Arc<std::sync::RwLock<TpuServiceConfig>>,
+    // leaders the transactions are currently sent to, recorded in the transaction lifecycles
+    current_leaders: Arc<std::sync::RwLock<Vec<Pubkey>>>,
+    // transactions sent during the current slot, sent again to the next leader at the slot edge
+    slot_transactions: Arc<std::sync::Mutex<Vec<SentTransactionInfo>>>,
     data_cache: DataCache,
+    // the QUIC endpoints and connection tasks run on this runtime
+    runtime: Handle,
 }
 
 #[derive(Clone)]
@@ -56,8 +101,10 @@ enum ConnectionManager {
     DirectTpu {
         tpu_connection_manager: Arc<TpuConnectionManager>,
     },
+    #[cfg(feature = "quic-proxy")]
     QuicProxy {
-        quic_proxy_connection_manager: Arc<QuicProxyConnectionManager>,
+        // one per proxy, each sends every transaction
+        quic_proxy_connection_managers: Vec<Arc<QuicProxyConnectionManager>>,
     },
 }
 
@@ -66,18 +113,75 @@ impl TpuService {
         config: TpuServiceConfig,
         identity: Arc<Keypair>,
         leader_schedule: Arc<dyn LeaderFetcherInterface>,
+        send_strategy: Arc<dyn SendStrategy>,
         data_cache: DataCache,
     ) -> anyhow::Result<Self> {
-        let (sender, _) = tokio::sync::broadcast::channel(config.maximum_transaction_in_queue);
-        let (certificate, key) = new_self_signed_tls_certificate(
-            identity.as_ref(),
-            IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+        Self::new_on_runtime(
+            config,
+            identity,
+            leader_schedule,
+            send_strategy,
+            data_cache,
+            Handle::current(),
         )
-        .expect("Failed to initialize QUIC client certificates");
+        .await
+    }
+
+    /// like [`TpuService::new`] with the QUIC send path on another runtime, e.g. one started with
+    /// [`crate::tpu_utils::send_runtime::start_send_runtime`]
+    pub async fn new_on_runtime(
+        config: TpuServiceConfig,
+        identity: Arc<Keypair>,
+        leader_schedule: Arc<dyn LeaderFetcherInterface>,
+        send_strategy: Arc<dyn SendStrategy>,
+        data_cache: DataCache,
+        runtime: Handle,
+    ) -> anyhow::Result<Self> {
+        let (sender, _) = tokio::sync::broadcast::channel(config.maximum_transaction_in_queue);
 
         log_gso_workaround();
 
-        let connection_manager = match config.tpu_connection_path {
+        let connection_manager =
+            Self::create_connection_manager(&config, identity.as_ref(), &runtime).await;
+
+        Ok(Self {
+            leader_schedule,
+            send_strategy,
+            broadcast_sender: Arc::new(sender),
+            connection_manager: Arc::new(RwLock::new(connection_manager)),
+            config: Arc::new(std::sync::RwLock::new(config)),
+            current_leaders: Arc::new(std::sync::RwLock::new(Vec::new())),
+            slot_transactions: Arc::new(std::sync::Mutex::new(Vec::new())),
+            data_cache,
+            runtime,
+        })
+    }
+
+    async fn create_connection_manager(
+        config: &TpuServiceConfig,
+        identity: &Keypair,
+        runtime: &Handle,
+    ) -> ConnectionManager {
+        let (certificate, key) =
+            new_self_signed_tls_certificate(identity, IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)))
+                .expect("Failed to initialize QUIC client certificates");
+        let config = config.clone();
+
+        // the endpoints drive their sockets on the runtime they are created on
+        runtime
+            .spawn(async move {
+                Self::create_connection_manager_on_current_runtime(config, certificate, key).await
+            })
+            .await
+            .expect("Failed to create the QUIC connection manager")
+    }
+
+    async fn create_connection_manager_on_current_runtime(
+        config: TpuServiceConfig,
+        certificate: rustls::Certificate,
+        key: rustls::PrivateKey,
+    ) -> ConnectionManager {
+        match config.tpu_connection_path {
             TpuConnectionPath::QuicDirectPath => {
                 let tpu_connection_manager =
                     TpuConnectionManager::new(certificate, key, config.fanout_slots as usize).await;
@@ -85,68 +189,242 @@ impl TpuService {
                     tpu_connection_manager: Arc::new(tpu_connection_manager),
                 }
             }
+            #[cfg(feature = "quic-proxy")]
             TpuConnectionPath::QuicForwardProxyPath {
-                forward_proxy_address,
+                forward_proxy_addresses,
             } => {
-                let quic_proxy_connection_manager =
-                    QuicProxyConnectionManager::new(certificate, key, forward_proxy_address).await;
+                let mut quic_proxy_connection_managers = vec![];
+                for forward_proxy_address in forward_proxy_addresses {
+                    let quic_proxy_connection_manager = QuicProxyConnectionManager::new(
+                        certificate.clone(),
+                        key.clone(),
+                        forward_proxy_address,
+                    )
+                    .await;
+                    quic_proxy_connection_managers.push(Arc::new(quic_proxy_connection_manager));
+                }
 
                 QuicProxy {
-                    quic_proxy_connection_manager: Arc::new(quic_proxy_connection_manager),
+                    quic_proxy_connection_managers,
                 }
             }
+        }
+    }
+
+    /// Replace the identity used for the QUIC client certificate at runtime.
+    /// The connections of the new identity are opened right away. The connections of the old
+    /// identity stop taking transactions and are closed once the sends they already started
+    /// finished, or after [`IDENTITY_ROTATION_DRAIN_TIMEOUT`].
+    pub async fn update_identity(&self, identity: &Keypair) {
+        log::info!("Rotating TPU identity to {}", identity.pubkey());
+        let new_connection_manager =
+            Self::create_connection_manager(&self.config(), identity, &self.runtime).await;
+        self.data_cache
+            .identity_stakes
+            .set_identity(identity.pubkey())
+            .await;
+
+        let old_connection_manager = {
+            let mut lk = self.connection_manager.write().await;
+            std::mem::replace(&mut *lk, new_connection_manager)
         };
+        // the new connections subscribe to the transactions before the old ones are closed
+        if let Err(e) = self
+            .update_quic_connections(
+                self.data_cache.slot_cache.get_current_slot(),
+                self.data_cache.slot_cache.get_estimated_slot(),
+                None,
+            )
+            .await
+        {
+            log::error!("Could not open the connections of the new identity: {e:?}");
+        }
+        match old_connection_manager {
+            DirectTpu {
+                tpu_connection_manager,
+            } => {
+                if !tpu_connection_manager
+                    .close_all_connections(IDENTITY_ROTATION_DRAIN_TIMEOUT)
+                    .await
+                {
+                    log::warn!(
+                        "{} sends of the old identity were still in flight after {:?}",
+                        tpu_connection_manager.in_flight_sends(),
+                        IDENTITY_ROTATION_DRAIN_TIMEOUT
+                    );
+                }
+            }
+            #[cfg(feature = "quic-proxy")]
+            QuicProxy {
+                quic_proxy_connection_managers,
+            } => {
+                for quic_proxy_connection_manager in quic_proxy_connection_managers {
+                    quic_proxy_connection_manager.signal_shutdown();
+                }
+            }
+        }
+        NB_IDENTITY_ROTATIONS.inc();
+    }
 
-        Ok(Self {
-            leader_schedule,
-            broadcast_sender: Arc::new(sender),
-            connection_manager,
-            config,
-            data_cache,
-        })
+    fn config(&self) -> TpuServiceConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Change the number of upcoming leaders receiving the transactions, applied on the next slot notification.
+    pub fn update_fanout_slots(&self, fanout_slots: u64) {
+        log::info!("Changing TPU fanout to {fanout_slots} slots");
+        self.config.write().unwrap().fanout_slots = fanout_slots;
+    }
+
+    /// Change the send pacing, connections opened before keep their current pacing.
+    pub fn update_send_pacing(&self, send_pacing: SendPacingConfig) {
+        log::info!("Changing send pacing to {send_pacing:?}");
+        self.config
+            .write()
+            .unwrap()
+            .quic_connection_params
+            .send_pacing = send_pacing;
+    }
+
+    pub fn send_strategy(&self) -> Arc<dyn SendStrategy> {
+        self.send_strategy.clone()
+    }
+
+    /// identities of the leaders the sent transactions are forwarded to
+    pub fn current_leaders(&self) -> Vec<Pubkey> {
+        self.current_leaders.read().unwrap().clone()
+    }
+
+    /// the leaders of the next `slots` slots from the estimated slot on, in the order of their first slot
+    pub async fn upcoming_leaders(&self, slots: u64) -> anyhow::Result<Vec<UpcomingLeader>> {
+        let estimated_slot = self.data_cache.slot_cache.get_estimated_slot();
+        let leaders = self
+            .leader_schedule
+            .get_slot_leaders(estimated_slot, estimated_slot + slots.saturating_sub(1))
+            .await?;
+        let tpu_addresses = self
+            .send_strategy
+            .select_leaders(&leaders, &self.data_cache.cluster_info.cluster_nodes);
+
+        let mut upcoming_leaders: Vec<UpcomingLeader> = vec![];
+        for leader in leaders {
+            let identity = leader.pubkey.to_string();
+            if let Some(upcoming_leader) = upcoming_leaders
+                .iter_mut()
+                .find(|upcoming_leader| upcoming_leader.identity == identity)
+            {
+                upcoming_leader.slots.push(leader.leader_slot);
+                continue;
+            }
+            upcoming_leaders.push(UpcomingLeader {
+                identity,
+                slots: vec![leader.leader_slot],
+                tpu_quic: tpu_addresses
+                    .get(&leader.pubkey)
+                    .map(|address| address.to_string()),
+                stake: self
+                    .data_cache
+                    .identity_stakes
+                    .get_node_stake(&leader.pubkey)
+                    .await,
+            });
+        }
+        Ok(upcoming_leaders)
+    }
+
+    /// transactions not yet received by every connection plus the sends in flight
+    pub async fn nb_pending_sends(&self) -> usize {
+        let in_flight_sends = match &*self.connection_manager.read().await {
+            DirectTpu {
+                tpu_connection_manager,
+            } => tpu_connection_manager.in_flight_sends(),
+            #[cfg(feature = "quic-proxy")]
+            QuicProxy { .. } => 0,
+        };
+        self.broadcast_sender.len() + in_flight_sends
     }
 
     pub fn send_transaction(&self, transaction: &SentTransactionInfo) -> anyhow::Result<()> {
         self.broadcast_sender.send(transaction.clone())?;
+        if self.config().slot_edge_forwarding_window.is_some() {
+            self.slot_transactions
+                .lock()
+                .unwrap()
+                .push(transaction.clone());
+        }
         Ok(())
     }
 
+    /// the leader of the slot after `slot` if it is another leader, i.e. `slot` is the last slot of its leader
+    async fn next_leader_after(&self, slot: Slot) -> anyhow::Result<Option<LeaderData>> {
+        let leaders = self
+            .leader_schedule
+            .get_slot_leaders(slot, slot + 1)
+            .await?;
+        let leader = leaders.iter().find(|leader| leader.leader_slot == slot);
+        let next_leader = leaders.iter().find(|leader| leader.leader_slot == slot + 1);
+        Ok(match (leader, next_leader) {
+            (Some(leader), Some(next_leader)) if leader.pubkey != next_leader.pubkey => {
+                Some(next_leader.clone())
+            }
+            _ => None,
+        })
+    }
+
+    /// send the unconfirmed transactions of the current slot to the next leader,
+    /// the transactions sent afterwards reach it through the broadcast
+    async fn forward_slot_transactions(&self, next_leader: &Pubkey) {
+        let transactions = std::mem::take(&mut *self.slot_transactions.lock().unwrap())
+            .into_iter()
+            .filter(|transaction| {
+                !self
+                    .data_cache
+                    .txs
+                    .is_transaction_confirmed(&transaction.signature)
+            })
+            .collect::<Vec<_>>();
+        if transactions.is_empty() {
+            return;
+        }
+        match &*self.connection_manager.read().await {
+            DirectTpu {
+                tpu_connection_manager,
+            } => {
+                let forwarded = tpu_connection_manager.forward_to(next_leader, transactions);
+                NB_SLOT_EDGE_FORWARDS.inc_by(forwarded as u64);
+            }
+            // the proxies get every transaction already
+            #[cfg(feature = "quic-proxy")]
+            QuicProxy { .. } => {}
+        }
+    }
+
     // update/reconfigure connections on slot change
+    // at the slot edge the next leader is included so transactions sent late in the slot do not miss the rotation
     async fn update_quic_connections(
         &self,
         current_slot: Slot,
         estimated_slot: Slot,
+        slot_edge_leader: Option<&LeaderData>,
     ) -> anyhow::Result<()> {
-        let fanout = self.config.fanout_slots;
-        let last_slot = estimated_slot + fanout;
+        let config = self.config();
+        let last_slot = estimated_slot + config.fanout_slots;
 
         let cluster_nodes = self.data_cache.cluster_info.cluster_nodes.clone();
 
-        let next_leaders = self
+        let mut next_leaders = self
             .leader_schedule
             .get_slot_leaders(current_slot, last_slot)
             .await?;
-        // get next leader with its tpu port
-        let connections_to_keep = next_leaders
-            .iter()
-            .map(|x| {
-                let contact_info = cluster_nodes.get(&x.pubkey);
-                let tpu_port = match contact_info {
-                    Some(info) => info.tpu,
-                    _ => None,
-                };
-                (x.pubkey, tpu_port)
-            })
-            .filter(|x| x.1.is_some())
-            .map(|x| {
-                let mut addr = x.1.unwrap();
-                // add quic port offset
-                addr.set_port(addr.port() + QUIC_PORT_OFFSET);
-                (x.0, addr)
-            })
-            .collect();
+        next_leaders.extend(slot_edge_leader.cloned());
+        // get next leaders with their tpu port
+        let connections_to_keep = self
+            .send_strategy
+            .select_leaders(&next_leaders, &cluster_nodes);
+        *self.current_leaders.write().unwrap() = connections_to_keep.keys().copied().collect();
 
-        match &self.connection_manager {
+        let connection_manager = self.connection_manager.read().await.clone();
+        match &connection_manager {
             DirectTpu {
                 tpu_connection_manager,
             } => {
@@ -156,41 +434,274 @@ impl TpuService {
                         connections_to_keep,
                         self.data_cache.identity_stakes.get_stakes().await,
                         self.data_cache.clone(),
-                        self.config.quic_connection_params,
+                        config.quic_connection_params,
+                        self.send_strategy.as_ref(),
                     )
                     .await;
             }
+            #[cfg(feature = "quic-proxy")]
             QuicProxy {
-                quic_proxy_connection_manager,
+                quic_proxy_connection_managers,
             } => {
-                let transaction_receiver = self.broadcast_sender.subscribe();
-                quic_proxy_connection_manager
-                    .update_connection(
-                        transaction_receiver,
-                        connections_to_keep,
-                        self.config.quic_connection_params,
-                    )
-                    .await;
+                for quic_proxy_connection_manager in quic_proxy_connection_managers {
+                    let transaction_receiver = self.broadcast_sender.subscribe();
+                    quic_proxy_connection_manager
+                        .update_connection(
+                            transaction_receiver,
+                            connections_to_keep.clone(),
+                            config.quic_connection_params,
+                        )
+                        .await;
+                }
             }
         }
         Ok(())
     }
 
-    pub fn start(&self, slot_notifications: SlotStream) -> AnyhowJoinHandle {
+    pub fn start(&self, latest_slot: LatestSlot) -> AnyhowJoinHandle {
         let this = self.clone();
-        tokio::spawn(async move {
-            let mut slot_notifications = slot_notifications;
+        // the connection tasks are spawned from here and stay on the same runtime
+        self.runtime.spawn(async move {
+            // only the newest slot matters, intermediate updates are skipped instead of lagging
+            let mut latest_slot = latest_slot;
+            let slot_duration = Duration::from_millis(DEFAULT_MS_PER_SLOT);
+            let mut current_slot = 0;
+            let mut estimated_slot = 0;
+            // set in the last slot of a leader: the next leader and when the edge of the slot is reached
+            let mut slot_edge: Option<(LeaderData, Instant)> = None;
+            // the next leader while the edge of the slot is reached
+            let mut slot_edge_leader: Option<LeaderData> = None;
             loop {
-                let notification = slot_notifications
-                    .recv()
-                    .await
-                    .context("Tpu service cannot get slot notification")?;
-                this.update_quic_connections(
-                    notification.processed_slot,
-                    notification.estimated_processed_slot,
-                )
-                .await?;
+                let mut reached_slot_edge = false;
+                tokio::select! {
+                    changed = latest_slot.changed() => {
+                        changed.context("Tpu service cannot get slot notification")?;
+                        let notification = latest_slot.borrow_and_update().clone();
+                        current_slot = notification.processed_slot;
+                        if notification.estimated_processed_slot != estimated_slot {
+                            estimated_slot = notification.estimated_processed_slot;
+                            this.slot_transactions.lock().unwrap().clear();
+                            slot_edge_leader = None;
+                            slot_edge = match this.config().slot_edge_forwarding_window {
+                                // a leader schedule miss only skips the forwarding of this slot
+                                Some(window) => match this.next_leader_after(estimated_slot).await {
+                                    Ok(next_leader) => next_leader.map(|next_leader| {
+                                        (next_leader, Instant::now() + slot_duration.saturating_sub(window))
+                                    }),
+                                    Err(e) => {
+                                        log::warn!(
+                                            "Could not get the next leader after slot {estimated_slot}, not forwarding: {e:?}"
+                                        );
+                                        None
+                                    }
+                                },
+                                None => None,
+                            };
+                        }
+                    }
+                    _ = tokio::time::sleep_until(slot_edge.as_ref().map_or_else(Instant::now, |(_, at)| *at)),
+                        if slot_edge.is_some() => {
+                        slot_edge_leader = slot_edge.take().map(|(next_leader, _)| next_leader);
+                        reached_slot_edge = true;
+                    }
+                }
+                this.update_quic_connections(current_slot, estimated_slot, slot_edge_leader.as_ref())
+                    .await?;
+                if reached_slot_edge {
+                    if let Some(next_leader) = &slot_edge_leader {
+                        this.forward_slot_transactions(&next_leader.pubkey).await;
+                    }
+                }
             }
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tpu_utils::send_strategy::DefaultSendStrategy;
+    use async_trait::async_trait;
+    use dashmap::DashMap;
+    use solana_lite_rpc_core::structures::slot_notification::SlotNotification;
+    use solana_rpc_client_api::response::RpcContactInfo;
+    use solana_sdk::{clock::NUM_CONSECUTIVE_LEADER_SLOTS, signature::Signature};
+    use std::{
+        collections::{HashMap, HashSet},
+        net::SocketAddr,
+    };
+
+    /// the leaders take turns every 4 slots
+    struct RoundRobinLeaderSchedule(Vec<Pubkey>);
+
+    #[async_trait]
+    impl LeaderFetcherInterface for RoundRobinLeaderSchedule {
+        async fn get_slot_leaders(&self, from: Slot, to: Slot) -> anyhow::Result<Vec<LeaderData>> {
+            Ok((from..=to)
+                .map(|slot| LeaderData {
+                    leader_slot: slot,
+                    pubkey: self.0[(slot / NUM_CONSECUTIVE_LEADER_SLOTS) as usize % self.0.len()],
+                })
+                .collect())
+        }
+    }
+
+    /// every leader gets a local address without a listener, the sends fail
+    struct LocalSendStrategy;
+
+    impl SendStrategy for LocalSendStrategy {
+        fn select_leaders(
+            &self,
+            upcoming_leaders: &[LeaderData],
+            _cluster_nodes: &DashMap<Pubkey, Arc<RpcContactInfo>>,
+        ) -> HashMap<Pubkey, SocketAddr> {
+            upcoming_leaders
+                .iter()
+                .map(|leader| (leader.pubkey, SocketAddr::from(([127, 0, 0, 1], 9))))
+                .collect()
+        }
+
+        fn connections_per_leader(
+            &self,
+            leader: &Pubkey,
+            connection_parameters: &QuicConnectionParameters,
+        ) -> usize {
+            DefaultSendStrategy.connections_per_leader(leader, connection_parameters)
+        }
+
+        fn replay_delay(
+            &self,
+            replay_count: usize,
+            max_replay: usize,
+            retry_offset: Duration,
+        ) -> Option<Duration> {
+            DefaultSendStrategy.replay_delay(replay_count, max_replay, retry_offset)
+        }
+    }
+
+    fn config(fanout_slots: u64) -> TpuServiceConfig {
+        TpuServiceConfig {
+            fanout_slots,
+            maximum_transaction_in_queue: 100,
+            quic_connection_params: QuicConnectionParameters {
+                connection_timeout: Duration::from_millis(100),
+                unistream_timeout: Duration::from_millis(100),
+                write_timeout: Duration::from_millis(100),
+                finalize_timeout: Duration::from_millis(100),
+                connection_retry_count: 1,
+                max_number_of_connections: 1,
+                number_of_transactions_per_unistream: 1,
+                send_pacing: SendPacingConfig::default(),
+            },
+            tpu_connection_path: TpuConnectionPath::QuicDirectPath,
+            slot_edge_forwarding_window: Some(Duration::from_millis(100)),
+        }
+    }
+
+    /// one leader known up to a slot, like the schedule at the end of its epoch
+    struct TruncatedLeaderSchedule {
+        leader: Pubkey,
+        last_slot: Slot,
+    }
+
+    #[async_trait]
+    impl LeaderFetcherInterface for TruncatedLeaderSchedule {
+        async fn get_slot_leaders(&self, from: Slot, to: Slot) -> anyhow::Result<Vec<LeaderData>> {
+            anyhow::ensure!(
+                to <= self.last_slot,
+                "slot {to} is not in the leader schedule"
+            );
+            Ok((from..=to)
+                .map(|slot| LeaderData {
+                    leader_slot: slot,
+                    pubkey: self.leader,
+                })
+                .collect())
+        }
+    }
+
+    fn slot_notification(slot: Slot) -> SlotNotification {
+        SlotNotification {
+            processed_slot: slot,
+            estimated_processed_slot: slot,
+        }
+    }
+
+    fn current_leaders(tpu_service: &TpuService) -> HashSet<Pubkey> {
+        tpu_service.current_leaders().into_iter().collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_next_leader_gets_the_transactions_at_the_edge_of_the_last_slot() {
+        let leader = Pubkey::new_unique();
+        let next_leader = Pubkey::new_unique();
+        let tpu_service = TpuService::new(
+            config(0),
+            Arc::new(Keypair::new()),
+            Arc::new(RoundRobinLeaderSchedule(vec![leader, next_leader])),
+            Arc::new(LocalSendStrategy),
+            DataCache::new_for_tests(),
+        )
+        .await
+        .unwrap();
+        let (slot_sender, latest_slot) = tokio::sync::watch::channel(slot_notification(0));
+        let _tpu_service_task = tpu_service.start(latest_slot);
+
+        // not the last slot of the leader, the whole slot passes without the next leader
+        slot_sender.send(slot_notification(2)).unwrap();
+        tokio::time::sleep(Duration::from_millis(390)).await;
+        assert_eq!(current_leaders(&tpu_service), HashSet::from([leader]));
+
+        slot_sender.send(slot_notification(3)).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(current_leaders(&tpu_service), HashSet::from([leader]));
+        tpu_service
+            .send_transaction(&SentTransactionInfo {
+                signature: Signature::new_unique(),
+                slot: 3,
+                transaction: vec![0; 10],
+                last_valid_block_height: 300,
+                request_id: None,
+            })
+            .unwrap();
+        let slot_edge_forwards = NB_SLOT_EDGE_FORWARDS.get();
+
+        // 100ms before the end of the slot
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(
+            current_leaders(&tpu_service),
+            HashSet::from([leader, next_leader])
+        );
+        assert_eq!(NB_SLOT_EDGE_FORWARDS.get(), slot_edge_forwards + 1);
+
+        // the rotation happened, the previous leader is not sent to any more
+        slot_sender.send(slot_notification(4)).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(current_leaders(&tpu_service), HashSet::from([next_leader]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_a_missing_next_leader_only_skips_the_slot_edge_forwarding() {
+        let leader = Pubkey::new_unique();
+        let tpu_service = TpuService::new(
+            config(0),
+            Arc::new(Keypair::new()),
+            Arc::new(TruncatedLeaderSchedule {
+                leader,
+                last_slot: 3,
+            }),
+            Arc::new(LocalSendStrategy),
+            DataCache::new_for_tests(),
+        )
+        .await
+        .unwrap();
+        let (slot_sender, latest_slot) = tokio::sync::watch::channel(slot_notification(0));
+        let tpu_service_task = tpu_service.start(latest_slot);
+
+        // the leader of slot 4 is not known yet
+        slot_sender.send(slot_notification(3)).unwrap();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(!tpu_service_task.is_finished());
+        assert_eq!(current_leaders(&tpu_service), HashSet::from([leader]));
+    }
+}
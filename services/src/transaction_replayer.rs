@@ -3,7 +3,12 @@ use anyhow::{bail, Context};
 use log::error;
 use prometheus::{core::GenericGauge, opts, register_int_gauge};
 use solana_lite_rpc_core::{
-    stores::tx_store::TxStore, structures::transaction_sent_info::SentTransactionInfo,
+    stores::{
+        blockhash_cache::BlockhashCache,
+        tx_lifecycle_store::{TxLifecycleState, TxLifecycleStore},
+        tx_store::TxStore,
+    },
+    structures::transaction_sent_info::SentTransactionInfo,
     AnyhowJoinHandle,
 };
 use std::time::Duration;
@@ -30,19 +35,30 @@ pub struct TransactionReplay {
 /// They will be replayed max_replay times
 /// The replay time will be linearly increasing by after count * replay after
 /// So the transasctions will be replayed like retry_after, retry_after*2, retry_after*3 ...
+/// (the delays can be customized with the SendStrategy of the tpu service)
 
 #[derive(Clone)]
 pub struct TransactionReplayer {
     pub tpu_service: TpuService,
     pub tx_store: TxStore,
+    pub tx_lifecycles: TxLifecycleStore,
+    pub blockhash_cache: BlockhashCache,
     pub retry_offset: Duration,
 }
 
 impl TransactionReplayer {
-    pub fn new(tpu_service: TpuService, tx_store: TxStore, retry_offset: Duration) -> Self {
+    pub fn new(
+        tpu_service: TpuService,
+        tx_store: TxStore,
+        tx_lifecycles: TxLifecycleStore,
+        blockhash_cache: BlockhashCache,
+        retry_offset: Duration,
+    ) -> Self {
         Self {
             tpu_service,
             tx_store,
+            tx_lifecycles,
+            blockhash_cache,
             retry_offset,
         }
     }
@@ -54,7 +70,10 @@ impl TransactionReplayer {
     ) -> AnyhowJoinHandle {
         let tpu_service = self.tpu_service.clone();
         let tx_store = self.tx_store.clone();
+        let tx_lifecycles = self.tx_lifecycles.clone();
+        let blockhash_cache = self.blockhash_cache.clone();
         let retry_offset = self.retry_offset;
+        let send_strategy = self.tpu_service.send_strategy();
 
         tokio::spawn(async move {
             while let Some(mut tx_replay) = reciever.recv().await {
@@ -78,13 +97,43 @@ impl TransactionReplayer {
                     // transaction timed out
                     continue;
                 }
-                // ignore reset error
-                let _ = tpu_service.send_transaction(&tx_replay.transaction);
+                if blockhash_cache.is_expired(tx_replay.transaction.last_valid_block_height) {
+                    // the blockhash expired, the leaders drop the transaction
+                    tx_lifecycles
+                        .record(&tx_replay.transaction.signature, TxLifecycleState::Expired);
+                    continue;
+                }
+                tracing::trace!(
+                    request_id = tx_replay.transaction.request_id.as_deref(),
+                    "replaying transaction {} ({}/{})",
+                    tx_replay.transaction.signature,
+                    tx_replay.replay_count + 1,
+                    tx_replay.max_replay
+                );
+                // the first attempt was made by the tx sender
+                let attempt = tx_replay.replay_count + 2;
+                match tpu_service.send_transaction(&tx_replay.transaction) {
+                    Ok(()) => tx_lifecycles.record_forwarded(
+                        &tx_replay.transaction.signature,
+                        &tpu_service.current_leaders(),
+                        attempt,
+                    ),
+                    Err(err) => tx_lifecycles.record(
+                        &tx_replay.transaction.signature,
+                        TxLifecycleState::ForwardFailed {
+                            attempt,
+                            error: err.to_string(),
+                        },
+                    ),
+                }
 
-                if tx_replay.replay_count < tx_replay.max_replay {
+                if let Some(replay_delay) = send_strategy.replay_delay(
+                    tx_replay.replay_count,
+                    tx_replay.max_replay,
+                    retry_offset,
+                ) {
                     tx_replay.replay_count += 1;
-                    tx_replay.replay_at =
-                        Instant::now() + retry_offset.mul_f32(tx_replay.replay_count as f32);
+                    tx_replay.replay_at = Instant::now() + replay_delay;
                     sender.send(tx_replay).context("replay channel closed")?;
                     MESSAGES_IN_REPLAY_QUEUE.inc();
                 }
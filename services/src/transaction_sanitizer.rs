@@ -0,0 +1,74 @@
+use bincode::Options;
+use prometheus::{opts, register_int_counter_vec, IntCounterVec};
+use solana_sdk::{
+    packet::PACKET_DATA_SIZE, sanitize::SanitizeError, transaction::VersionedTransaction,
+};
+
+lazy_static::lazy_static! {
+    static ref TXS_REJECTED: IntCounterVec =
+    register_int_counter_vec!(opts!("literpc_txs_rejected", "Number of sent transactions rejected by the sanitization before forwarding, by reason"), &["reason"]).unwrap();
+}
+
+/// why a sent transaction is rejected, the messages are the ones of solana-rpc
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum TransactionSanitizeError {
+    #[error("decoded solana_sdk::transaction::VersionedTransaction too large: {0} bytes (max: {PACKET_DATA_SIZE} bytes)")]
+    TooLarge(usize),
+    #[error("failed to deserialize solana_sdk::transaction::VersionedTransaction: {0}")]
+    Deserialize(String),
+    #[error("invalid transaction: {0}")]
+    Invalid(SanitizeError),
+    #[error("Transaction signature length mismatch")]
+    SignatureLenMismatch,
+    #[error("Transaction signature verification failure")]
+    SignatureVerificationFailure,
+}
+
+impl TransactionSanitizeError {
+    fn reason(&self) -> &'static str {
+        match self {
+            Self::TooLarge(_) => "too_large",
+            Self::Deserialize(_) => "deserialize",
+            Self::Invalid(_) => "invalid",
+            Self::SignatureLenMismatch => "signature_len_mismatch",
+            Self::SignatureVerificationFailure => "signature_verification",
+        }
+    }
+}
+
+/// Checks a wire transaction like solana-rpc does before it spends any bandwidth on it: the size
+/// limit of a packet, the structure of the message and the number of signatures. Verifying the
+/// signatures costs the most cpu, relays which trust their clients can skip it.
+pub fn sanitize_transaction(
+    raw_tx: &[u8],
+    verify_signatures: bool,
+) -> Result<VersionedTransaction, TransactionSanitizeError> {
+    let result = check_transaction(raw_tx, verify_signatures);
+    if let Err(err) = &result {
+        TXS_REJECTED.with_label_values(&[err.reason()]).inc();
+    }
+    result
+}
+
+fn check_transaction(
+    raw_tx: &[u8],
+    verify_signatures: bool,
+) -> Result<VersionedTransaction, TransactionSanitizeError> {
+    if raw_tx.len() > PACKET_DATA_SIZE {
+        return Err(TransactionSanitizeError::TooLarge(raw_tx.len()));
+    }
+    let tx: VersionedTransaction = bincode::options()
+        .with_limit(PACKET_DATA_SIZE as u64)
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .deserialize_from(raw_tx)
+        .map_err(|err| TransactionSanitizeError::Deserialize(err.to_string()))?;
+    tx.sanitize().map_err(TransactionSanitizeError::Invalid)?;
+    if tx.signatures.len() != tx.message.header().num_required_signatures as usize {
+        return Err(TransactionSanitizeError::SignatureLenMismatch);
+    }
+    if verify_signatures && tx.verify_with_results().iter().any(|valid| !valid) {
+        return Err(TransactionSanitizeError::SignatureVerificationFailure);
+    }
+    Ok(tx)
+}
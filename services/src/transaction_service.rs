@@ -1,35 +1,76 @@
 // This class will manage the lifecycle for a transaction
 // It will send, replay if necessary and confirm by listening to blocks
 
-use std::time::Duration;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
 
 use crate::{
+    dry_run::DryRun,
+    fee_guardrails::{estimated_fee, FeeGuardrails},
     tpu_utils::tpu_service::TpuService,
     transaction_replayer::{TransactionReplay, TransactionReplayer, MESSAGES_IN_REPLAY_QUEUE},
+    transaction_sanitizer::sanitize_transaction,
     tx_sender::TxSender,
 };
 use anyhow::bail;
+use prometheus::{
+    core::GenericGauge, histogram_opts, opts, register_histogram, register_int_counter,
+    register_int_gauge, Histogram, IntCounter,
+};
 use solana_lite_rpc_core::{
-    solana_utils::SerializableTransaction, structures::transaction_sent_info::SentTransactionInfo,
-    types::SlotStream,
+    request_id::current_request_id,
+    solana_utils::SerializableTransaction,
+    structures::{
+        dropped_transaction::{record_dropped_transactions, DropReason},
+        transaction_sent_info::SentTransactionInfo,
+    },
+    types::LatestSlot,
 };
 use solana_lite_rpc_core::{
-    stores::block_information_store::{BlockInformation, BlockInformationStore},
+    stores::{
+        blockhash_cache::{BlockhashCache, BlockhashInfo},
+        tx_lifecycle_store::{TxLifecycleState, TxLifecycleStore},
+    },
     structures::notifications::NotificationSender,
     AnyhowJoinHandle,
 };
-use solana_sdk::transaction::VersionedTransaction;
+use solana_sdk::signature::Signature;
 use tokio::{
     sync::mpsc::{self, Sender, UnboundedSender},
     time::Instant,
 };
 
+lazy_static::lazy_static! {
+    static ref DRAINING: GenericGauge<prometheus::core::AtomicI64> =
+        register_int_gauge!(opts!("literpc_draining", "1 while new transactions are rejected to drain the node")).unwrap();
+    static ref TXS_REJECTED_DRAINING: IntCounter =
+        register_int_counter!(opts!("literpc_txs_rejected_draining", "Number of sent transactions rejected while the node drains")).unwrap();
+    static ref TXS_DRY_RUN: IntCounter =
+        register_int_counter!(opts!("literpc_txs_dry_run", "Number of sent transactions which passed all checks but were not forwarded in dry-run mode")).unwrap();
+    static ref DRY_RUN_FEE: Histogram = register_histogram!(histogram_opts!(
+        "literpc_dry_run_fee_lamports",
+        "Estimated fee in lamports of the transactions sent in dry-run mode",
+        vec![5_000.0, 10_000.0, 25_000.0, 50_000.0, 100_000.0, 250_000.0, 500_000.0, 1_000_000.0, 5_000_000.0, 10_000_000.0]
+    ))
+    .unwrap();
+}
+
+const FLUSH_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 #[derive(Clone)]
 pub struct TransactionServiceBuilder {
     tx_sender: TxSender,
     tx_replayer: TransactionReplayer,
     tpu_service: TpuService,
     max_nb_txs_in_queue: usize,
+    verify_signatures: bool,
+    fee_guardrails: FeeGuardrails,
+    dry_run: DryRun,
 }
 
 impl TransactionServiceBuilder {
@@ -44,15 +85,34 @@ impl TransactionServiceBuilder {
             tx_replayer,
             tpu_service,
             max_nb_txs_in_queue,
+            verify_signatures: true,
+            fee_guardrails: FeeGuardrails::default(),
+            dry_run: DryRun::default(),
         }
     }
 
+    /// verifying the signatures of the sent transactions can be skipped by relays short on cpu
+    pub fn with_signature_verification(mut self, verify_signatures: bool) -> Self {
+        self.verify_signatures = verify_signatures;
+        self
+    }
+
+    pub fn with_fee_guardrails(mut self, fee_guardrails: FeeGuardrails) -> Self {
+        self.fee_guardrails = fee_guardrails;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: DryRun) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
     pub fn start(
         self,
         notifier: Option<NotificationSender>,
-        block_information_store: BlockInformationStore,
+        blockhash_cache: BlockhashCache,
         max_retries: usize,
-        slot_notifications: SlotStream,
+        latest_slot: LatestSlot,
     ) -> (TransactionService, AnyhowJoinHandle) {
         let (transaction_channel, tx_recv) = mpsc::channel(self.max_nb_txs_in_queue);
         let (replay_channel, replay_reciever) = tokio::sync::mpsc::unbounded_channel();
@@ -64,7 +124,7 @@ impl TransactionServiceBuilder {
             let replay_channel_task = replay_channel.clone();
 
             tokio::spawn(async move {
-                let tpu_service_fx = tpu_service.start(slot_notifications);
+                let tpu_service_fx = tpu_service.start(latest_slot);
 
                 let tx_sender_jh = tx_sender.clone().execute(tx_recv, notifier.clone());
 
@@ -89,9 +149,16 @@ impl TransactionServiceBuilder {
             TransactionService {
                 transaction_channel,
                 replay_channel,
-                block_information_store,
+                blockhash_cache,
                 max_retries,
                 replay_offset: self.tx_replayer.retry_offset,
+                tx_lifecycles: self.tx_replayer.tx_lifecycles.clone(),
+                verify_signatures: self.verify_signatures,
+                fee_guardrails: self.fee_guardrails,
+                dry_run: self.dry_run,
+                draining: DrainSwitch::default(),
+                tpu_service: self.tpu_service,
+                tx_sender: self.tx_sender,
             },
             jh_services,
         )
@@ -102,48 +169,139 @@ impl TransactionServiceBuilder {
 pub struct TransactionService {
     pub transaction_channel: Sender<SentTransactionInfo>,
     pub replay_channel: UnboundedSender<TransactionReplay>,
-    pub block_information_store: BlockInformationStore,
+    pub blockhash_cache: BlockhashCache,
     pub max_retries: usize,
     pub replay_offset: Duration,
+    pub tx_lifecycles: TxLifecycleStore,
+    pub verify_signatures: bool,
+    pub fee_guardrails: FeeGuardrails,
+    pub dry_run: DryRun,
+    /// resolves the leaders the transactions are forwarded to
+    pub tpu_service: TpuService,
+    tx_sender: TxSender,
+    // shared by all clones, toggled by the admin api
+    draining: DrainSwitch,
 }
 
+/// whether new transactions are rejected, shared by the clones of a [`TransactionService`]
+#[derive(Clone, Debug, Default)]
+pub struct DrainSwitch(Arc<AtomicBool>);
+
+impl DrainSwitch {
+    pub fn start_draining(&self) {
+        if !self.0.swap(true, Ordering::Relaxed) {
+            log::info!("Draining, new transactions are rejected");
+        }
+        DRAINING.set(1);
+    }
+
+    pub fn stop_draining(&self) {
+        if self.0.swap(false, Ordering::Relaxed) {
+            log::info!("Stopped draining, accepting transactions again");
+        }
+        DRAINING.set(0);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// new transactions are rejected while the node drains, the client should retry on another node
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("node is draining, retry on another node")]
+pub struct NodeDraining;
+
 impl TransactionService {
+    /// rejects new transactions with [`NodeDraining`], the transactions sent before are still
+    /// replayed until they landed or expired
+    pub fn start_draining(&self) {
+        self.draining.start_draining();
+    }
+
+    pub fn stop_draining(&self) {
+        self.draining.stop_draining();
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.is_draining()
+    }
+
+    pub fn drain_switch(&self) -> DrainSwitch {
+        self.draining.clone()
+    }
+
+    #[tracing::instrument(skip_all, level = "debug")]
     pub async fn send_transaction(
         &self,
         raw_tx: Vec<u8>,
         max_retries: Option<u16>,
-    ) -> anyhow::Result<String> {
-        let tx = match bincode::deserialize::<VersionedTransaction>(&raw_tx) {
-            Ok(tx) => tx,
-            Err(err) => {
-                bail!(err.to_string());
-            }
-        };
+    ) -> anyhow::Result<Signature> {
+        if self.is_draining() {
+            TXS_REJECTED_DRAINING.inc();
+            return Err(NodeDraining.into());
+        }
+        let received = SystemTime::now();
+        // the typed error is kept so the rpc servers can return the error codes of solana-rpc
+        let tx = sanitize_transaction(&raw_tx, self.verify_signatures)?;
         let signature = tx.signatures[0];
+        self.tx_lifecycles.start(signature, received);
+        if let Err(err) = self.fee_guardrails.check(&tx) {
+            self.tx_lifecycles
+                .record(&signature, dropped("fee_limit_exceeded"));
+            return Err(err.into());
+        }
 
-        let Some(BlockInformation {
+        let Some(BlockhashInfo {
             slot,
-            last_valid_blockheight,
+            last_valid_block_height,
             ..
-        }) = self
-            .block_information_store
-            .get_block_info(&tx.get_recent_blockhash().to_string())
+        }) = self.blockhash_cache.get(tx.get_recent_blockhash())
         else {
-            bail!("Blockhash not found in block store".to_string());
+            record_dropped_transactions(DropReason::BlockhashNotFound, 1);
+            self.tx_lifecycles
+                .record(&signature, dropped(DropReason::BlockhashNotFound.as_str()));
+            bail!("Blockhash not found in blockhash cache".to_string());
         };
 
+        if self.dry_run.is_active() {
+            let fee_lamports = estimated_fee(&tx);
+            let leaders = self.tpu_service.current_leaders();
+            TXS_DRY_RUN.inc();
+            DRY_RUN_FEE.observe(fee_lamports as f64);
+            log::debug!(
+                "Dry run of transaction {signature} with a fee of {fee_lamports} lamports to {} leaders",
+                leaders.len()
+            );
+            self.tx_lifecycles.record(
+                &signature,
+                TxLifecycleState::DryRun {
+                    leaders: leaders.iter().map(|leader| leader.to_string()).collect(),
+                    fee_lamports,
+                },
+            );
+            return Ok(signature);
+        }
+
         let max_replay = max_retries.map_or(self.max_retries, |x| x as usize);
         let transaction_info = SentTransactionInfo {
-            signature: signature.to_string(),
-            last_valid_block_height: last_valid_blockheight,
+            signature,
+            last_valid_block_height,
             slot,
             transaction: raw_tx,
+            request_id: current_request_id(),
         };
+        // recorded before the tx sender can forward it
+        self.tx_lifecycles
+            .record_queued(&signature, last_valid_block_height);
         if let Err(e) = self
             .transaction_channel
             .send(transaction_info.clone())
             .await
         {
+            record_dropped_transactions(DropReason::QueueFull, 1);
+            self.tx_lifecycles
+                .record(&signature, dropped(DropReason::QueueFull.as_str()));
             bail!(
                 "Internal error sending transaction on send channel error {}",
                 e
@@ -163,6 +321,31 @@ impl TransactionService {
         {
             MESSAGES_IN_REPLAY_QUEUE.inc();
         }
-        Ok(signature.to_string())
+        Ok(signature)
+    }
+
+    /// transactions queued in the channel, batched by the tx sender, or not yet sent by a tpu connection
+    pub async fn nb_pending_sends(&self) -> usize {
+        let nb_queued =
+            self.transaction_channel.max_capacity() - self.transaction_channel.capacity();
+        nb_queued + self.tx_sender.nb_batched() + self.tpu_service.nb_pending_sends().await
+    }
+
+    /// waits until the queued transactions were sent to the leaders, false on timeout;
+    /// transactions waiting for a replay are not flushed
+    pub async fn flush(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            while self.nb_pending_sends().await > 0 {
+                tokio::time::sleep(FLUSH_POLL_INTERVAL).await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+}
+
+fn dropped(reason: &str) -> TxLifecycleState {
+    TxLifecycleState::Dropped {
+        reason: reason.to_string(),
     }
 }
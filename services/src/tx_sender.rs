@@ -1,8 +1,14 @@
-use std::time::{Duration, Instant};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use anyhow::bail;
 use chrono::Utc;
-use log::{trace, warn};
+use log::trace;
 
 use prometheus::{
     core::GenericGauge, histogram_opts, opts, register_histogram, register_int_counter,
@@ -12,8 +18,9 @@ use tokio::sync::mpsc::Receiver;
 
 use crate::tpu_utils::tpu_service::TpuService;
 use solana_lite_rpc_core::{
-    stores::{data_cache::DataCache, tx_store::TxProps},
+    stores::{data_cache::DataCache, tx_lifecycle_store::TxLifecycleState, tx_store::TxProps},
     structures::{
+        dropped_transaction::{record_dropped_transactions, DropReason},
         notifications::{NotificationMsg, NotificationSender, TransactionNotification},
         transaction_sent_info::SentTransactionInfo,
     },
@@ -47,6 +54,8 @@ pub struct TxSender {
     /// TpuClient to call the tpu port
     tpu_service: TpuService,
     data_cache: DataCache,
+    // taken from the channel but not yet handed to the tpu service, shared by all clones
+    nb_batched: Arc<AtomicUsize>,
 }
 
 impl TxSender {
@@ -54,10 +63,17 @@ impl TxSender {
         Self {
             tpu_service,
             data_cache,
+            nb_batched: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// transactions taken from the channel which wait in the current batch
+    pub fn nb_batched(&self) -> usize {
+        self.nb_batched.load(Ordering::Relaxed)
+    }
+
     /// retry enqued_tx(s)
+    #[tracing::instrument(skip_all, level = "debug", fields(nb_txs = transaction_infos.len()))]
     async fn forward_txs(
         &self,
         transaction_infos: Vec<SentTransactionInfo>,
@@ -72,29 +88,49 @@ impl TxSender {
 
         let tpu_client = self.tpu_service.clone();
         let txs_sent = self.data_cache.txs.clone();
+        let tx_lifecycles = &self.data_cache.tx_lifecycles;
+        let leaders = tpu_client.current_leaders();
         let forwarded_slot = self.data_cache.slot_cache.get_current_slot();
         let forwarded_local_time = Utc::now();
 
         let mut quic_responses = vec![];
         for transaction_info in transaction_infos.iter() {
-            trace!("sending transaction {}", transaction_info.signature);
+            tracing::trace!(
+                request_id = transaction_info.request_id.as_deref(),
+                "sending transaction {}",
+                transaction_info.signature
+            );
             txs_sent.insert(
-                transaction_info.signature.clone(),
+                transaction_info.signature,
                 TxProps {
                     status: None,
                     last_valid_blockheight: transaction_info.last_valid_block_height,
                     sent_by_lite_rpc: true,
+                    finalized_at: None,
                 },
             );
 
             let quic_response = match tpu_client.send_transaction(transaction_info) {
                 Ok(_) => {
                     TXS_SENT.inc_by(1);
+                    tx_lifecycles.record_forwarded(&transaction_info.signature, &leaders, 1);
                     1
                 }
                 Err(err) => {
                     TXS_SENT_ERRORS.inc_by(1);
-                    warn!("{err}");
+                    record_dropped_transactions(DropReason::ConnectionFailure, 1);
+                    tx_lifecycles.record(
+                        &transaction_info.signature,
+                        TxLifecycleState::ForwardFailed {
+                            attempt: 1,
+                            error: err.to_string(),
+                        },
+                    );
+                    tracing::warn!(
+                        request_id = transaction_info.request_id.as_deref(),
+                        "sending transaction {} failed: {err}",
+                        transaction_info.signature
+                    );
                     0
                 }
             };
@@ -105,7 +141,7 @@ impl TxSender {
                 .iter()
                 .enumerate()
                 .map(|(index, transaction_info)| TransactionNotification {
-                    signature: transaction_info.signature.clone(),
+                    signature: transaction_info.signature.to_string(),
                     recent_slot: transaction_info.slot,
                     forwarded_slot,
                     forwarded_local_time,
@@ -134,6 +170,8 @@ impl TxSender {
     ) -> AnyhowJoinHandle {
         tokio::spawn(async move {
             loop {
+                // beats after every batch window, also without transactions
+                self.data_cache.heartbeats.transactions.beat();
                 let mut transaction_infos = Vec::with_capacity(MAX_BATCH_SIZE_IN_PER_INTERVAL);
                 let mut timeout_interval = INTERVAL_PER_BATCH_IN_MS;
 
@@ -154,9 +192,11 @@ impl TxSender {
                                     .txs
                                     .contains_key(&transaction_info.signature)
                                 {
+                                    record_dropped_transactions(DropReason::Duplicate, 1);
                                     continue;
                                 }
                                 transaction_infos.push(transaction_info);
+                                self.nb_batched.fetch_add(1, Ordering::Relaxed);
                                 // update the timeout inteval
                                 timeout_interval = timeout_interval
                                     .saturating_sub(instance.elapsed().as_millis() as u64)
@@ -179,7 +219,9 @@ impl TxSender {
 
                 TX_BATCH_SIZES.set(transaction_infos.len() as i64);
 
+                let nb_txs = transaction_infos.len();
                 self.forward_txs(transaction_infos, notifier.clone()).await;
+                self.nb_batched.fetch_sub(nb_txs, Ordering::Relaxed);
             }
         })
     }
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use anyhow::Context;
+use futures::{Stream, StreamExt};
+use tonic::Status;
+use yellowstone_grpc_proto::{
+    geyser::{subscribe_update::UpdateOneof, SubscribeUpdate},
+    prost::{bytes::Buf, Message},
+};
+
+/// Fixture files are the recorded updates of a geyser subscription as length delimited protobuf
+/// messages, in the order they were received.
+pub fn read_fixture(path: impl AsRef<Path>) -> anyhow::Result<Vec<SubscribeUpdate>> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).with_context(|| format!("read fixture {path:?}"))?;
+    let mut buf = bytes.as_slice();
+    let mut updates = vec![];
+    while buf.has_remaining() {
+        let update = SubscribeUpdate::decode_length_delimited(&mut buf)
+            .with_context(|| format!("decode update {} of fixture {path:?}", updates.len()))?;
+        updates.push(update);
+    }
+    Ok(updates)
+}
+
+pub fn write_fixture(path: impl AsRef<Path>, updates: &[SubscribeUpdate]) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let mut bytes = vec![];
+    for update in updates {
+        update.encode_length_delimited(&mut bytes)?;
+    }
+    std::fs::write(path, bytes).with_context(|| format!("write fixture {path:?}"))
+}
+
+/// records up to `max_updates` of a subscription, e.g. the stream of `GeyserGrpcClient::subscribe_once`
+/// against a real source; pings are not recorded
+pub async fn record_fixture(
+    path: impl AsRef<Path>,
+    updates: impl Stream<Item = Result<SubscribeUpdate, Status>>,
+    max_updates: usize,
+) -> anyhow::Result<usize> {
+    let mut updates = std::pin::pin!(updates);
+    let mut recorded = vec![];
+    while recorded.len() < max_updates {
+        let Some(update) = updates.next().await else {
+            break;
+        };
+        let update = update.context("subscription failed while recording")?;
+        if matches!(update.update_oneof, None | Some(UpdateOneof::Ping(_))) {
+            continue;
+        }
+        recorded.push(update);
+    }
+    write_fixture(path, &recorded)?;
+    Ok(recorded.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::updates::chain;
+    use yellowstone_grpc_proto::geyser::CommitmentLevel;
+
+    #[test]
+    fn fixture_roundtrip() {
+        let path =
+            std::env::temp_dir().join(format!("lite-rpc-fixture-{}.bin", std::process::id()));
+        let updates = chain(100, 10, CommitmentLevel::Confirmed);
+        write_fixture(&path, &updates).unwrap();
+        let read = read_fixture(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, updates);
+    }
+}
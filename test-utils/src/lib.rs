@@ -0,0 +1,3 @@
+pub mod fixture;
+pub mod mock_geyser;
+pub mod updates;
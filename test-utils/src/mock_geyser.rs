@@ -0,0 +1,389 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    path::Path,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use futures::{Stream, StreamExt};
+use log::debug;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response, Status, Streaming};
+use yellowstone_grpc_proto::geyser::{
+    geyser_server::{Geyser, GeyserServer},
+    subscribe_update::UpdateOneof,
+    CommitmentLevel, GetBlockHeightRequest, GetBlockHeightResponse, GetLatestBlockhashRequest,
+    GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse, GetVersionRequest,
+    GetVersionResponse, IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest,
+    PongResponse, SubscribeRequest, SubscribeUpdate,
+};
+
+use crate::fixture::read_fixture;
+
+/// fault injected into the replay before the update at its index
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// closes the subscription with an `unavailable` status, the next subscription of the same
+    /// kind resumes at the update
+    Disconnect,
+    /// drops this many updates starting at the index, a gap in the stream
+    Skip(usize),
+    /// sends the update this many extra times
+    Duplicate(usize),
+    /// waits before sending the update
+    Delay(Duration),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum UpdateKind {
+    Slot,
+    Account,
+    Transaction,
+    Block,
+    BlockMeta,
+    Other,
+}
+
+fn update_kind(update: &SubscribeUpdate) -> UpdateKind {
+    match update.update_oneof {
+        Some(UpdateOneof::Slot(_)) => UpdateKind::Slot,
+        Some(UpdateOneof::Account(_)) => UpdateKind::Account,
+        Some(UpdateOneof::Transaction(_)) => UpdateKind::Transaction,
+        Some(UpdateOneof::Block(_)) => UpdateKind::Block,
+        Some(UpdateOneof::BlockMeta(_)) => UpdateKind::BlockMeta,
+        _ => UpdateKind::Other,
+    }
+}
+
+fn update_slot(update: &SubscribeUpdate) -> Option<u64> {
+    match &update.update_oneof {
+        Some(UpdateOneof::Slot(slot)) => Some(slot.slot),
+        Some(UpdateOneof::Block(block)) => Some(block.slot),
+        Some(UpdateOneof::BlockMeta(block_meta)) => Some(block_meta.slot),
+        _ => None,
+    }
+}
+
+// the update kinds a subscribe request asks for, filters within a kind are not applied
+fn requested_kinds(request: &SubscribeRequest) -> Vec<UpdateKind> {
+    let mut kinds = vec![];
+    if !request.slots.is_empty() {
+        kinds.push(UpdateKind::Slot);
+    }
+    if !request.accounts.is_empty() {
+        kinds.push(UpdateKind::Account);
+    }
+    if !request.transactions.is_empty() {
+        kinds.push(UpdateKind::Transaction);
+    }
+    if !request.blocks.is_empty() {
+        kinds.push(UpdateKind::Block);
+    }
+    if !request.blocks_meta.is_empty() {
+        kinds.push(UpdateKind::BlockMeta);
+    }
+    kinds
+}
+
+#[derive(Clone, Default)]
+struct Script {
+    updates: Vec<SubscribeUpdate>,
+    faults: HashMap<usize, Vec<Fault>>,
+}
+
+// commitment level and requested update kinds
+type SubscriptionKey = (i32, Vec<UpdateKind>);
+
+#[derive(Default)]
+struct Replay {
+    position: usize,
+    // disconnects already injected, every one happens once
+    disconnected: HashSet<usize>,
+}
+
+/// In-process yellowstone gRPC server replaying scripted updates, so the multiplexer, the block
+/// mapping and the services behind them can be tested deterministically. Every commitment level
+/// has its own script; a subscription gets the updates of the kinds it asks for (slots, blocks,
+/// block metas, ...) from the script of its commitment level. Subscriptions with the same
+/// commitment level and update kinds share their replay position, so a client reconnecting after
+/// a [`Fault::Disconnect`] resumes where it was dropped. Once its script is replayed a subscription
+/// stays open without updates.
+#[derive(Clone, Default)]
+pub struct MockGeyser {
+    scripts: HashMap<i32, Script>,
+    interval: Duration,
+}
+
+impl MockGeyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// appends updates to the script of the commitment level
+    pub fn with_updates(
+        mut self,
+        commitment: CommitmentLevel,
+        updates: Vec<SubscribeUpdate>,
+    ) -> Self {
+        self.scripts
+            .entry(commitment as i32)
+            .or_default()
+            .updates
+            .extend(updates);
+        self
+    }
+
+    /// appends the updates recorded in a fixture file, see [`crate::fixture`]
+    pub fn with_fixture(
+        self,
+        commitment: CommitmentLevel,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<Self> {
+        Ok(self.with_updates(commitment, read_fixture(path)?))
+    }
+
+    /// injects the fault before the update at the index of the script of the commitment level
+    pub fn with_fault(mut self, commitment: CommitmentLevel, index: usize, fault: Fault) -> Self {
+        self.scripts
+            .entry(commitment as i32)
+            .or_default()
+            .faults
+            .entry(index)
+            .or_default()
+            .push(fault);
+        self
+    }
+
+    /// pause after every sent update, updates are sent as fast as the client reads them by default
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// serves the scripts on a random local port until the handle is dropped
+    pub async fn start(self) -> anyhow::Result<MockGeyserHandle> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let service = MockGeyserService {
+            scripts: Arc::new(self.scripts),
+            interval: self.interval,
+            replays: Arc::new(Mutex::new(HashMap::new())),
+            subscriptions: Arc::new(AtomicUsize::new(0)),
+            last_slot: Arc::new(AtomicU64::new(0)),
+        };
+        let subscriptions = service.subscriptions.clone();
+        let shutdown = CancellationToken::new();
+        let stopped = shutdown.clone();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(GeyserServer::new(service))
+                .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async move {
+                    stopped.cancelled().await
+                }),
+        );
+        debug!("mock geyser listening on {addr}");
+        Ok(MockGeyserHandle {
+            addr,
+            subscriptions,
+            shutdown,
+        })
+    }
+}
+
+/// stops the server when dropped
+pub struct MockGeyserHandle {
+    addr: SocketAddr,
+    subscriptions: Arc<AtomicUsize>,
+    shutdown: CancellationToken,
+}
+
+impl MockGeyserHandle {
+    /// the address to use as grpc source
+    pub fn grpc_addr(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// subscribe calls served so far, reconnects included
+    pub fn subscriptions(&self) -> usize {
+        self.subscriptions.load(Ordering::Relaxed)
+    }
+
+    /// stops accepting connections
+    pub fn stop(&self) {
+        self.shutdown.cancel();
+    }
+}
+
+impl Drop for MockGeyserHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[derive(Clone)]
+struct MockGeyserService {
+    scripts: Arc<HashMap<i32, Script>>,
+    interval: Duration,
+    replays: Arc<Mutex<HashMap<SubscriptionKey, Replay>>>,
+    subscriptions: Arc<AtomicUsize>,
+    // highest slot sent, answered by get_slot
+    last_slot: Arc<AtomicU64>,
+}
+
+impl MockGeyserService {
+    async fn replay(
+        &self,
+        key: SubscriptionKey,
+        sender: mpsc::Sender<Result<SubscribeUpdate, Status>>,
+    ) {
+        let Some(script) = self.scripts.get(&key.0) else {
+            sender.closed().await;
+            return;
+        };
+        loop {
+            let position = self
+                .replays
+                .lock()
+                .unwrap()
+                .entry(key.clone())
+                .or_default()
+                .position;
+            let Some(update) = script.updates.get(position) else {
+                break;
+            };
+
+            let mut copies = 1;
+            let mut skip = 0;
+            for fault in script.faults.get(&position).into_iter().flatten() {
+                match fault {
+                    Fault::Disconnect => {
+                        let first_time = self
+                            .replays
+                            .lock()
+                            .unwrap()
+                            .entry(key.clone())
+                            .or_default()
+                            .disconnected
+                            .insert(position);
+                        if first_time {
+                            debug!("mock geyser disconnects before update {position}");
+                            let _ = sender
+                                .send(Err(Status::unavailable("mock geyser disconnect")))
+                                .await;
+                            return;
+                        }
+                    }
+                    Fault::Skip(count) => skip = *count,
+                    Fault::Duplicate(count) => copies += count,
+                    Fault::Delay(delay) => tokio::time::sleep(*delay).await,
+                }
+            }
+
+            if skip == 0 && key.1.contains(&update_kind(update)) {
+                for _ in 0..copies {
+                    if sender.send(Ok(update.clone())).await.is_err() {
+                        return;
+                    }
+                }
+                if let Some(slot) = update_slot(update) {
+                    self.last_slot.fetch_max(slot, Ordering::Relaxed);
+                }
+                if !self.interval.is_zero() {
+                    tokio::time::sleep(self.interval).await;
+                }
+            }
+            self.replays
+                .lock()
+                .unwrap()
+                .entry(key.clone())
+                .or_default()
+                .position = position + skip.max(1);
+        }
+        // like a source without new slots
+        sender.closed().await;
+    }
+}
+
+type SubscribeStream =
+    Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send + 'static>>;
+
+#[tonic::async_trait]
+impl Geyser for MockGeyserService {
+    type SubscribeStream = SubscribeStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<Streaming<SubscribeRequest>>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        self.subscriptions.fetch_add(1, Ordering::Relaxed);
+        let mut requests = request.into_inner();
+        let (sender, receiver) = mpsc::channel(16);
+        let service = self.clone();
+        tokio::spawn(async move {
+            // only the first request is used, the replay does not follow filter changes
+            let request = match requests.next().await {
+                Some(Ok(request)) => request,
+                _ => return,
+            };
+            let commitment = request
+                .commitment
+                .unwrap_or(CommitmentLevel::Processed as i32);
+            let mut kinds = requested_kinds(&request);
+            kinds.sort();
+            service.replay((commitment, kinds), sender).await;
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+
+    async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
+        Ok(Response::new(PongResponse {
+            count: request.into_inner().count,
+        }))
+    }
+
+    async fn get_latest_blockhash(
+        &self,
+        _request: Request<GetLatestBlockhashRequest>,
+    ) -> Result<Response<GetLatestBlockhashResponse>, Status> {
+        Err(Status::unimplemented("not replayed by the mock geyser"))
+    }
+
+    async fn get_block_height(
+        &self,
+        _request: Request<GetBlockHeightRequest>,
+    ) -> Result<Response<GetBlockHeightResponse>, Status> {
+        Err(Status::unimplemented("not replayed by the mock geyser"))
+    }
+
+    async fn get_slot(
+        &self,
+        _request: Request<GetSlotRequest>,
+    ) -> Result<Response<GetSlotResponse>, Status> {
+        Ok(Response::new(GetSlotResponse {
+            slot: self.last_slot.load(Ordering::Relaxed),
+        }))
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        _request: Request<IsBlockhashValidRequest>,
+    ) -> Result<Response<IsBlockhashValidResponse>, Status> {
+        Err(Status::unimplemented("not replayed by the mock geyser"))
+    }
+
+    async fn get_version(
+        &self,
+        _request: Request<GetVersionRequest>,
+    ) -> Result<Response<GetVersionResponse>, Status> {
+        Ok(Response::new(GetVersionResponse {
+            version: "mock-geyser".to_string(),
+        }))
+    }
+}
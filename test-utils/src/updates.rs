@@ -0,0 +1,78 @@
+use solana_sdk::{
+    clock::Slot,
+    hash::{hashv, Hash},
+};
+use yellowstone_grpc_proto::{
+    geyser::{
+        subscribe_update::UpdateOneof, CommitmentLevel, SubscribeUpdate, SubscribeUpdateBlock,
+        SubscribeUpdateBlockMeta, SubscribeUpdateSlot,
+    },
+    solana::storage::confirmed_block::{BlockHeight, UnixTimestamp},
+};
+
+/// deterministic blockhash of a synthetic block, the same for every commitment level so the block
+/// metas of confirmed and finalized subscriptions match the processed blocks
+pub fn blockhash(slot: Slot) -> Hash {
+    hashv(&[b"lite-rpc-mock-block", &slot.to_le_bytes()])
+}
+
+pub fn slot_update(slot: Slot, parent: Option<Slot>, status: CommitmentLevel) -> SubscribeUpdate {
+    SubscribeUpdate {
+        filters: vec!["slots".to_string()],
+        update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+            slot,
+            parent,
+            status: status as i32,
+        })),
+    }
+}
+
+/// a block without transactions, the block height and time are the slot
+pub fn block_update(slot: Slot, parent_slot: Slot) -> SubscribeUpdate {
+    SubscribeUpdate {
+        filters: vec!["blocks".to_string()],
+        update_oneof: Some(UpdateOneof::Block(SubscribeUpdateBlock {
+            slot,
+            blockhash: blockhash(slot).to_string(),
+            block_time: Some(UnixTimestamp {
+                timestamp: slot as i64,
+            }),
+            block_height: Some(BlockHeight { block_height: slot }),
+            parent_slot,
+            parent_blockhash: blockhash(parent_slot).to_string(),
+            ..Default::default()
+        })),
+    }
+}
+
+pub fn block_meta_update(slot: Slot, parent_slot: Slot) -> SubscribeUpdate {
+    SubscribeUpdate {
+        filters: vec!["blocks_meta".to_string()],
+        update_oneof: Some(UpdateOneof::BlockMeta(SubscribeUpdateBlockMeta {
+            slot,
+            blockhash: blockhash(slot).to_string(),
+            block_time: Some(UnixTimestamp {
+                timestamp: slot as i64,
+            }),
+            block_height: Some(BlockHeight { block_height: slot }),
+            parent_slot,
+            parent_blockhash: blockhash(parent_slot).to_string(),
+            ..Default::default()
+        })),
+    }
+}
+
+/// updates of `count` consecutive slots starting at `first_slot` as streamed to a subscription of
+/// the commitment level: a slot and a block per slot for processed, a slot and a block meta otherwise
+pub fn chain(first_slot: Slot, count: u64, commitment: CommitmentLevel) -> Vec<SubscribeUpdate> {
+    (first_slot..first_slot + count)
+        .flat_map(|slot| {
+            let parent_slot = slot.saturating_sub(1);
+            let block = match commitment {
+                CommitmentLevel::Processed => block_update(slot, parent_slot),
+                _ => block_meta_update(slot, parent_slot),
+            };
+            [slot_update(slot, Some(parent_slot), commitment), block]
+        })
+        .collect()
+}